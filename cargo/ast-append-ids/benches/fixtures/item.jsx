@@ -0,0 +1,16 @@
+      <article className="card">
+        <header className="card-header">
+          <h2>{item.title}</h2>
+          <span className="badge">{item.status}</span>
+        </header>
+        <p className="summary">Short description of the item &amp; its owner.</p>
+        <ul className="tags">
+          <li>frontend</li>
+          <li>design</li>
+          <li>review</li>
+        </ul>
+        <footer>
+          <button type="button" onClick={() => select(item)}>Open</button>
+          <a href="/items/edit">Edit</a>
+        </footer>
+      </article>
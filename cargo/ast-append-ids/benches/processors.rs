@@ -0,0 +1,79 @@
+//! Processor throughput over small, medium and large documents, and over a
+//! list of 100k elements with the same slug. Compare a run with the recorded
+//! baseline using `cargo xtask bench-compare`.
+
+use ast_append_ids::html::HtmlProcessor;
+use ast_append_ids::jsx::JsxProcessor;
+use ast_append_ids::xml::XmlProcessor;
+use ast_append_ids::{AstProcessor, IdOptions, IdStrategy};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const JSX_ITEM: &str = include_str!("fixtures/item.jsx");
+const HTML_ITEM: &str = include_str!("fixtures/item.html");
+const XML_ITEM: &str = include_str!("fixtures/item.xml");
+const DUPLICATE_ITEM: &str = include_str!("fixtures/duplicate.html");
+
+/// Number of elements sharing one slug in the duplicates benchmark
+const DUPLICATES: usize = 100_000;
+
+/// Number of repeated items in each document size
+const SIZES: [(&str, usize); 3] = [("small", 5), ("medium", 50), ("large", 500)];
+
+fn document(kind: &str, items: usize) -> String {
+    let (head, item, tail) = match kind {
+        "jsx" => (
+            "export function Page({ items, select }) {\n  return (\n    <main className=\"page\">\n",
+            JSX_ITEM,
+            "    </main>\n  );\n}\n",
+        ),
+        "html" => (
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<body>\n  <main class=\"page\">\n",
+            HTML_ITEM,
+            "  </main>\n</body>\n</html>\n",
+        ),
+        _ => ("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<catalog>\n", XML_ITEM, "</catalog>\n"),
+    };
+    let mut doc = String::from(head);
+    for _ in 0..items {
+        doc.push_str(item);
+    }
+    doc.push_str(tail);
+    doc
+}
+
+fn bench_processor<P: AstProcessor>(c: &mut Criterion, kind: &str, new: fn() -> P) {
+    let options = IdOptions::default();
+    let mut group = c.benchmark_group(kind);
+
+    for (size, items) in SIZES {
+        let input = document(kind, items);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &input, |b, input| {
+            b.iter(|| new().process(input, &options).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+/// Slug IDs for many elements with the same text, each of which needs a suffix
+fn duplicates(c: &mut Criterion) {
+    let options = IdOptions { strategy: IdStrategy::Slug, ..IdOptions::default() };
+    let input = format!("<ul>\n{}</ul>\n", DUPLICATE_ITEM.repeat(DUPLICATES));
+    let mut group = c.benchmark_group("duplicates");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(DUPLICATES as u64));
+    group.bench_with_input(BenchmarkId::from_parameter("slug"), &input, |b, input| {
+        b.iter(|| HtmlProcessor::new().process(input, &options).unwrap())
+    });
+    group.finish();
+}
+
+fn processors(c: &mut Criterion) {
+    bench_processor(c, "jsx", JsxProcessor::new);
+    bench_processor(c, "html", HtmlProcessor::new);
+    bench_processor(c, "xml", XmlProcessor::new);
+}
+
+criterion_group!(benches, processors, duplicates);
+criterion_main!(benches);
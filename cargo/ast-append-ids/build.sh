@@ -5,9 +5,11 @@
 set -e
 
 echo "Building WASM target..."
-wasm-pack build --target web --out-dir pkg-web --release
-wasm-pack build --target nodejs --out-dir pkg-node --release
-wasm-pack build --target bundler --out-dir pkg-bundler --release
+# One package with every format plus one per format (pkg-web-html, ...), built
+# with the size-optimized `wasm` profile and run through wasm-opt
+cargo xtask wasm --target web
+cargo xtask wasm --target nodejs
+cargo xtask wasm --target bundler
 
 echo "Building native CLI..."
 cargo build --release --bin ast-append-ids
@@ -22,17 +24,13 @@ echo "Copying WASM packages to npm directory..."
 NPM_PACKAGE_DIR="../../npm/packages/ast-append-ids"
 
 if [ -d "$NPM_PACKAGE_DIR" ]; then
-  echo "  - Copying pkg-web to npm package..."
-  rm -rf "$NPM_PACKAGE_DIR/pkg-web"
-  cp -r ./pkg-web "$NPM_PACKAGE_DIR/"
-  
-  echo "  - Copying pkg-node to npm package..."
-  rm -rf "$NPM_PACKAGE_DIR/pkg-node"
-  cp -r ./pkg-node "$NPM_PACKAGE_DIR/"
-  
-  echo "  - Copying pkg-bundler to npm package..."
-  rm -rf "$NPM_PACKAGE_DIR/pkg-bundler"
-  cp -r ./pkg-bundler "$NPM_PACKAGE_DIR/"
+  for pkg in pkg-web pkg-node pkg-bundler; do
+    for dir in "$pkg" "$pkg-jsx" "$pkg-html" "$pkg-xml"; do
+      echo "  - Copying $dir to npm package..."
+      rm -rf "$NPM_PACKAGE_DIR/$dir"
+      cp -r "./$dir" "$NPM_PACKAGE_DIR/"
+    done
+  done
   
   echo "WASM packages copied to npm directory successfully!"
 else
@@ -45,6 +43,7 @@ echo "Outputs:"
 echo "  - WASM for web: ./pkg-web/"
 echo "  - WASM for Node.js: ./pkg-node/"
 echo "  - WASM for bundlers: ./pkg-bundler/"
+echo "  - Single-format WASM: ./pkg-{web,node,bundler}-{jsx,html,xml}/"
 echo "  - Native CLI: ./target/release/ast-append-ids"
 if [ -d "$NPM_PACKAGE_DIR" ]; then
   echo "  - npm package WASM: $NPM_PACKAGE_DIR/pkg-*/"
@@ -0,0 +1,27 @@
+#![no_main]
+
+use ast_append_ids::html::HtmlProcessor;
+use ast_append_ids::{AstProcessor, IdOptions, IdStrategy};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // The first byte picks the options, the rest is the document
+    let Some((&flags, rest)) = data.split_first() else {
+        return;
+    };
+    let Ok(input) = std::str::from_utf8(rest) else {
+        return;
+    };
+
+    let mut options = IdOptions::default();
+    options.strategy = match flags % 3 {
+        0 => IdStrategy::Hash,
+        1 => IdStrategy::Slug,
+        _ => IdStrategy::Path,
+    };
+    options.overwrite = flags & 0x04 != 0;
+    options.templates = flags & 0x08 == 0;
+
+    // Errors are fine, panics are not
+    let _ = HtmlProcessor::new().process(input, &options);
+});
@@ -0,0 +1,32 @@
+#![no_main]
+
+use ast_append_ids::jsx::JsxProcessor;
+use ast_append_ids::{AstProcessor, IdOptions, IdStrategy};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // The first byte picks the options, the rest is the document
+    let Some((&flags, rest)) = data.split_first() else {
+        return;
+    };
+    let Ok(input) = std::str::from_utf8(rest) else {
+        return;
+    };
+
+    let mut options = IdOptions::default();
+    options.strategy = match flags % 3 {
+        0 => IdStrategy::Hash,
+        1 => IdStrategy::Slug,
+        _ => IdStrategy::Path,
+    };
+    options.overwrite = flags & 0x04 != 0;
+    options.vue_h = flags & 0x08 != 0;
+    options.dynamic_keys = flags & 0x10 != 0;
+    if flags & 0x20 != 0 {
+        options.id_helper = Some("testId".to_string());
+        options.id_helper_import = Some("./test-id".to_string());
+    }
+
+    // Errors are fine, panics are not
+    let _ = JsxProcessor::new().process(input, &options);
+});
@@ -4,17 +4,128 @@ export interface IdOptions {
   /** Attribute name for ID (default: 'data-ast-id') */
   attr?: string;
   /** ID generation strategy (default: 'hash') */
-  strategy?: 'hash' | 'slug' | 'path';
+  strategy?: 'hash' | 'slug' | 'path' | 'taxonomy';
   /** ID prefix (default: 'el-') */
   prefix?: string;
   /** Overwrite existing IDs (default: false) */
   overwrite?: boolean;
-  /** CSS selector for target elements (HTML/XML only) */
+  /** CSS selector for target elements; XML and JSX support a subset */
   selector?: string | null;
-  /** Tags to include (JSX only) */
+  /** Tags to include */
   include?: string[];
-  /** Tags to exclude (JSX only) */
+  /** Tags to exclude */
   exclude?: string[];
+  /** Only tag elements with one of these classes in className or class, including static words of template literals */
+  include_class?: string[];
+  /** Assign IDs inside <template> content and shadow roots (HTML only, default: true) */
+  templates?: boolean;
+  /** Leave <code> elements and their content untouched, like <pre> content (HTML only, default: false) */
+  skip_code?: boolean;
+  /** Template literal tags whose markup is processed as HTML, e.g. ['html'] (JSX only) */
+  template_tags?: string[];
+  /** Tag Vue render function h('div', props) calls (JSX only, default: false) */
+  vue_h?: boolean;
+  /** Append the `key` expression to IDs of elements rendered in .map() callbacks (JSX only, default: false) */
+  dynamic_keys?: boolean;
+  /** Write IDs as calls to this helper, e.g. data-ast-id={aid("el-abc")} (JSX only) */
+  id_helper?: string | null;
+  /** Module the helper is imported from when a file does not import it yet (JSX only) */
+  id_helper_import?: string | null;
+  /** Levels of the taxonomy strategy as 'name=selectors', outermost first (default: area, section, component) */
+  taxonomy_levels?: string[];
+  /** Only tag elements whose own text, not that of child elements, matches this regular expression */
+  text_matches?: string | null;
+  /** Anchor comments matching this regular expression with an <?ast-anchor?> processing instruction carrying an ID (XML only) */
+  comment_anchors?: string | null;
+  /** Only tag elements whose start tag begins in this byte range, leaving the rest of the input byte-identical */
+  range?: { start: number; end: number } | null;
+  /** Also write the provenance of each generated ID to this attribute; IDs without it count as hand-written */
+  origin_attr?: string | null;
+  /** Attributes written next to each ID as 'name=template', e.g. 'data-ast-depth={depth}' */
+  extra_attrs?: string[];
+  /** Scheme of hash IDs: 'v1' is 8 hex characters, 'v2' 11 salted base62 characters (default: 'v1') */
+  scheme?: 'v1' | 'v2';
+  /** Salt of 'v2' hash IDs */
+  salt?: string;
+  /** Cut hash IDs to this many characters, at least 4; IDs whose short hash is taken get longer ones */
+  hash_length?: number | null;
+  /** Whether tag names in include, exclude and selectors ignore case; 'auto' ignores it in HTML only (default: 'auto') */
+  name_case?: 'auto' | 'sensitive' | 'insensitive';
+  /** Elements that advance the counter behind path and hash IDs: every element, or only those that get an ID (default: 'all') */
+  counter?: 'all' | 'processed';
+  /** Attribute naming the component an element is the root of; counter and path restart there and IDs get the component's name after the prefix */
+  component_scope?: string | null;
+  /** Record the tool version and a hash of the options before the root element or in a data-ast-run attribute on it (XML only; default: 'off') */
+  metadata?: 'off' | 'pi' | 'attribute';
+  /** Seconds since the Unix epoch recorded with metadata; left out unless set */
+  metadata_timestamp?: number | null;
+  /** Where the manifest is served, written with a hash of the page's IDs to a <meta name="ast-append-ids"> in the <head> (HTML only) */
+  manifest_meta?: string | null;
+}
+
+/**
+ * Options as react-append-ids and rehype-append-ids spell them, accepted
+ * wherever IdOptions are: camelCase keys of IdOptions (e.g. `templateTags`)
+ * plus these. Both shapes can be mixed, but not for the same option.
+ */
+export interface PluginOptions {
+  /** Tags to include, like `include` */
+  tags?: string | string[];
+  /** Tags to exclude, like `exclude` */
+  skip?: string | string[];
+  /** Attribute name for ID, like `attr` */
+  idPropName?: string;
+  [camelCaseKey: string]: unknown;
+}
+
+/**
+ * A file event as chokidar reports it, with the file's content for 'add'
+ * and 'change'
+ */
+export interface FileChange {
+  event: 'add' | 'change' | 'unlink' | 'addDir' | 'unlinkDir';
+  path: string;
+  content?: string | null;
+}
+
+/**
+ * A node of a DOM snapshot: an element, with its tag as localName gives it
+ * and its attributes in order, or a text node. Comments are left out.
+ */
+export type SnapshotNode =
+  | { tag: string; attributes?: [string, string][]; children?: SnapshotNode[] }
+  | { text: string };
+
+/** The ID an element of a snapshot gets */
+export interface SnapshotId {
+  /** Element child positions from the snapshot's root: [1, 0] is root.children[1].children[0] */
+  path: number[];
+  tag: string;
+  /** 'skipped': the element keeps the ID it has */
+  action: 'added' | 'overwritten' | 'skipped';
+  id: string;
+}
+
+/** What became of a file of a processChanges batch */
+export interface FileResult {
+  path: string;
+  /**
+   * 'processed': write `content` back; 'unchanged': nothing to add, or the
+   * file is this processor's own last output; 'skipped': no processor for the
+   * file or it opts out; 'removed': unlinked and forgotten
+   */
+  status: 'processed' | 'unchanged' | 'skipped' | 'removed' | 'error';
+  content?: string;
+  error?: string;
+  warnings?: Warning[];
+}
+
+/** Something suspicious noticed processing a document */
+export interface Warning {
+  kind: 'duplicate-id' | 'empty-id' | 'included-and-excluded' | 'selector-matched-nothing';
+  message: string;
+  /** Start of the element in the input, for warnings about one */
+  offset?: number;
 }
 
 export declare class AstAppendIds {
@@ -23,22 +134,42 @@ export declare class AstAppendIds {
   /**
    * Process JSX/React content
    */
-  processJsx(content: string, options?: IdOptions): Promise<string>;
+  processJsx(content: string, options?: IdOptions | PluginOptions): Promise<string>;
   
   /**
    * Process XML content
    */
-  processXml(content: string, options?: IdOptions): Promise<string>;
+  processXml(content: string, options?: IdOptions | PluginOptions): Promise<string>;
   
   /**
    * Process HTML content
    */
-  processHtml(content: string, options?: IdOptions): Promise<string>;
+  processHtml(content: string, options?: IdOptions | PluginOptions): Promise<string>;
   
   /**
-   * Auto-detect content type and process
+   * Auto-detect content type and process; scripts without JSX and Vue
+   * single-file components are returned unchanged
+   */
+  processAuto(content: string, options?: IdOptions | PluginOptions): Promise<string>;
+
+  /**
+   * Process a batch of watcher events; only the last event per path counts.
+   * Each file is processed on its own, so its IDs do not depend on the others.
+   */
+  processChanges(changes: FileChange[], options?: IdOptions | PluginOptions): Promise<FileResult[]>;
+
+  /**
+   * Warnings about the document the last process call handled, e.g. a
+   * selector that matched nothing; cleared by the next call
+   */
+  takeWarnings(): Promise<Warning[]>;
+
+  /**
+   * The IDs the build would give the elements of a DOM snapshot, e.g. one a
+   * devtools extension captured from the live page, without rewriting markup.
+   * Warnings go to takeWarnings.
    */
-  processAuto(content: string, options?: IdOptions): Promise<string>;
+  idsForSnapshot(snapshot: SnapshotNode, options?: IdOptions | PluginOptions): Promise<SnapshotId[]>;
 }
 
 /**
@@ -49,19 +180,19 @@ export declare function babelPluginJsxAppendIds(babel: any): any;
 /**
  * Rehype plugin for HTML ID appending
  */
-export declare function rehypeAppendIds(options?: IdOptions): any;
+export declare function rehypeAppendIds(options?: IdOptions | PluginOptions): any;
 
 /**
  * XAST plugin for XML ID appending
  */
-export declare function xastAppendIds(options?: IdOptions): any;
+export declare function xastAppendIds(options?: IdOptions | PluginOptions): any;
 
 /**
  * Direct WASM function exports
  */
-export declare function processJsx(content: string, options?: IdOptions): Promise<string>;
-export declare function processXml(content: string, options?: IdOptions): Promise<string>;
-export declare function processHtml(content: string, options?: IdOptions): Promise<string>;
+export declare function processJsx(content: string, options?: IdOptions | PluginOptions): Promise<string>;
+export declare function processXml(content: string, options?: IdOptions | PluginOptions): Promise<string>;
+export declare function processHtml(content: string, options?: IdOptions | PluginOptions): Promise<string>;
 
 /**
  * Get library version
@@ -73,4 +204,25 @@ export declare function version(): Promise<string>;
  */
 export declare function createDefaultOptions(): Promise<IdOptions>;
 
+/**
+ * JSON Schema of IdOptions, to validate options before processing
+ */
+export declare function optionsSchema(): Promise<object>;
+
+/** What detectFormat found out about a file */
+export interface Detection {
+  /** 'script' has no JSX to tag and 'vue' no processor; processAuto returns both unchanged */
+  format: 'jsx' | 'xml' | 'html' | 'script' | 'vue';
+  /** Share of the evidence found that points to `format`, from 0 to 1; 0 when nothing was found */
+  confidence: number;
+  /** Signals that pointed to `format`, e.g. 'extension', 'doctype' */
+  signals: string[];
+}
+
+/**
+ * Work out what `content` is, as processAuto does; the extension of `path`
+ * counts as evidence too
+ */
+export declare function detectFormat(content: string, path?: string): Promise<Detection>;
+
 export default AstAppendIds;
\ No newline at end of file
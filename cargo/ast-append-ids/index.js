@@ -23,16 +23,22 @@ class AstAppendIds {
     return this.processor.processAuto(content, this._normalizeOptions(options));
   }
 
+  processChanges(changes, options = {}) {
+    return this.processor.processChanges(changes, this._normalizeOptions(options));
+  }
+
+  takeWarnings() {
+    return this.processor.takeWarnings();
+  }
+
+  idsForSnapshot(snapshot, options = {}) {
+    return this.processor.idsForSnapshot(snapshot, this._normalizeOptions(options));
+  }
+
+  // The WASM module fills in defaults, accepts the option shapes of the
+  // react-append-ids and rehype-append-ids plugins and rejects unknown keys
   _normalizeOptions(options) {
-    return {
-      attr: options.attr || 'data-ast-id',
-      strategy: options.strategy || 'hash',
-      prefix: options.prefix || 'el-',
-      overwrite: options.overwrite || false,
-      selector: options.selector || null,
-      include: options.include || [],
-      exclude: options.exclude || []
-    };
+    return { ...options };
   }
 }
 
@@ -105,7 +111,9 @@ module.exports = {
   processXml: wasm.xastAppendIds,
   processHtml: wasm.rehypeAppendIds,
   version: wasm.version,
-  createDefaultOptions: wasm.create_default_options
+  createDefaultOptions: wasm.create_default_options,
+  optionsSchema: wasm.optionsSchema,
+  detectFormat: wasm.detectFormat
 };
 
 // Also export as default
@@ -45,16 +45,25 @@ export class AstAppendIds {
     return this.processor.processAuto(content, this._normalizeOptions(options));
   }
 
+  async processChanges(changes, options = {}) {
+    await this.initPromise;
+    return this.processor.processChanges(changes, this._normalizeOptions(options));
+  }
+
+  async takeWarnings() {
+    await this.initPromise;
+    return this.processor.takeWarnings();
+  }
+
+  async idsForSnapshot(snapshot, options = {}) {
+    await this.initPromise;
+    return this.processor.idsForSnapshot(snapshot, this._normalizeOptions(options));
+  }
+
+  // The WASM module fills in defaults, accepts the option shapes of the
+  // react-append-ids and rehype-append-ids plugins and rejects unknown keys
   _normalizeOptions(options) {
-    return {
-      attr: options.attr || 'data-ast-id',
-      strategy: options.strategy || 'hash',
-      prefix: options.prefix || 'el-',
-      overwrite: options.overwrite || false,
-      selector: options.selector || null,
-      include: options.include || [],
-      exclude: options.exclude || []
-    };
+    return { ...options };
   }
 }
 
@@ -159,5 +168,10 @@ export async function createDefaultOptions() {
   return wasm.create_default_options();
 }
 
+export async function detectFormat(content, path) {
+  await ensureWasmInit();
+  return wasm.detectFormat(content, path);
+}
+
 // Default export
 export default AstAppendIds;
\ No newline at end of file
@@ -0,0 +1,168 @@
+use crate::id_generator::Assignment;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+pub const ANCHORS_VERSION: u32 = 1;
+
+/// Names picked by hand for critical elements, in a file checked in with the
+/// sources. An element whose fingerprint (`ast_common::node_fingerprint`: its
+/// tag and attributes other than the ID) is listed gets the name as its ID
+/// instead of a generated one, so it is the same on every branch; every other
+/// element keeps a generated ID.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Anchors {
+    pub version: u32,
+    /// Fingerprint per name
+    pub anchors: BTreeMap<String, String>,
+}
+
+impl Anchors {
+    pub fn new() -> Self {
+        Self {
+            version: ANCHORS_VERSION,
+            anchors: BTreeMap::new(),
+        }
+    }
+
+    /// Loads an anchors file, returning an empty one when it does not exist yet.
+    pub fn load_or_default(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        Self::load(path)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read anchors {}: {}", path.display(), e))?;
+        let anchors: Anchors = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid anchors {}: {}", path.display(), e))?;
+
+        if anchors.version != ANCHORS_VERSION {
+            return Err(format!(
+                "Unsupported anchors version {} (expected {})",
+                anchors.version, ANCHORS_VERSION
+            ));
+        }
+        anchors.check().map_err(|e| format!("Invalid anchors {}: {}", path.display(), e))?;
+
+        Ok(anchors)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize anchors: {}", e))?;
+        fs::write(path, json + "\n")
+            .map_err(|e| format!("Failed to write anchors {}: {}", path.display(), e))
+    }
+
+    /// Names the element with `fingerprint` `name`, replacing what either was
+    /// anchored to before.
+    pub fn insert(&mut self, name: &str, fingerprint: &str) -> Result<(), String> {
+        check_name(name)?;
+        self.anchors.retain(|_, anchored| anchored != fingerprint);
+        self.anchors.insert(name.to_string(), fingerprint.to_string());
+        Ok(())
+    }
+
+    /// The anchors as assignments for `IdGenerator::add_reusable`, which hands
+    /// each name to the first element with its fingerprint
+    pub fn assignments(&self) -> Vec<Assignment> {
+        self.anchors
+            .iter()
+            .map(|(name, fingerprint)| Assignment {
+                fingerprint: fingerprint.clone(),
+                id: name.clone(),
+                tag: String::new(),
+                path: Vec::new(),
+                alias: None,
+            })
+            .collect()
+    }
+
+    /// Names must be usable as IDs, and an element can have one name only
+    fn check(&self) -> Result<(), String> {
+        let mut seen = BTreeMap::new();
+        for (name, fingerprint) in &self.anchors {
+            check_name(name)?;
+            if let Some(other) = seen.insert(fingerprint, name) {
+                return Err(format!("'{}' and '{}' anchor the same fingerprint {}", other, name, fingerprint));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(format!("'{}' is not a valid anchor name", name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "html")]
+    #[test]
+    fn test_anchored_elements_get_their_names() {
+        use crate::ast_common::node_fingerprint;
+        use crate::html::HtmlProcessor;
+        use crate::id_generator::IdGenerator;
+        use crate::{ast_common::AstNode, AstProcessor, IdOptions, IdStrategy};
+
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+        let submit = AstNode {
+            node_type: "button".to_string(),
+            text_content: None,
+            attributes: vec![("type".to_string(), "submit".to_string())],
+            path: Vec::new(),
+            context: Vec::new(),
+        };
+        let mut anchors = Anchors::new();
+        anchors.insert("checkout-submit", &node_fingerprint(&submit, &options.attr)).unwrap();
+
+        let mut generator = IdGenerator::new();
+        generator.add_reusable(anchors.assignments());
+        let mut processor = HtmlProcessor::with_generator(generator);
+        let input = r#"<form><button type="button">Back</button><button type="submit">Pay</button></form>"#;
+        let output = processor.process(input, &options).unwrap();
+        assert_eq!(
+            output,
+            concat!(
+                r#"<form data-ast-id="el-form-0"><button type="button" data-ast-id="el-button-0-1">Back</button>"#,
+                r#"<button type="submit" data-ast-id="checkout-submit">Pay</button></form>"#,
+            )
+        );
+
+        // A name moves to the element anchored last
+        anchors.insert("pay", &node_fingerprint(&submit, &options.attr)).unwrap();
+        assert_eq!(anchors.anchors.keys().collect::<Vec<_>>(), ["pay"]);
+        assert!(anchors.insert("two words", "0123").is_err());
+    }
+
+    #[test]
+    fn test_anchors_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ast-append-ids-anchors-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("anchors.json");
+
+        assert!(Anchors::load(&path).is_err());
+        assert!(Anchors::load_or_default(&path).unwrap().anchors.is_empty());
+
+        let mut anchors = Anchors::new();
+        anchors.insert("main-nav", "0123456789abcdef").unwrap();
+        anchors.save(&path).unwrap();
+        assert_eq!(Anchors::load(&path).unwrap(), anchors);
+
+        // Hand edits that give an element two names are caught
+        anchors.anchors.insert("nav".to_string(), "0123456789abcdef".to_string());
+        anchors.save(&path).unwrap();
+        assert!(Anchors::load(&path).unwrap_err().contains("same fingerprint"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,11 +1,22 @@
-use crate::id_generator::TextExtractable;
-use crate::{IdOptions, IdStrategy};
+use crate::id_generator::{Assignment, Change, ChangeAction, FilterDecision, SkipReason, TextExtractable};
+use crate::report;
+use crate::resolved::ResolvedOptions;
+use crate::strategy::StrategyContext;
+use crate::warnings::Warning;
+use crate::{AstProcessor, IdOptions, IdStrategy, ProcessOutput};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::HashSet;
 
 pub struct AstNode {
     pub node_type: String,
     pub text_content: Option<String>,
     pub attributes: Vec<(String, String)>,
     pub path: Vec<usize>,
+    /// Labels of the enclosing taxonomy levels, outermost first (taxonomy
+    /// strategy only)
+    pub context: Vec<String>,
 }
 
 impl TextExtractable for AstNode {
@@ -14,8 +25,281 @@ impl TextExtractable for AstNode {
     }
 }
 
+/// Attribute that opts an element and its subtree out of ID generation
+pub const IGNORE_ATTR: &str = "data-ast-ignore";
+/// HTML/XML comment (`<!-- ast-append-ids: ignore -->`) opting out the next element
+pub const IGNORE_COMMENT: &str = "ast-append-ids: ignore";
+/// JSX comment (`// ast-append-ids-ignore-next`) opting out the next element
+pub const JSX_IGNORE_COMMENT: &str = "ast-append-ids-ignore-next";
+
+pub fn is_ignore_comment(text: &str) -> bool {
+    text.trim() == IGNORE_COMMENT
+}
+
+/// Tracks opt-out markers while streaming through a document. An ignored
+/// element skips its whole subtree.
+#[derive(Debug, Default)]
+pub struct IgnoreTracker {
+    depth: usize,
+    pending: bool,
+}
+
+impl IgnoreTracker {
+    /// An ignore comment was seen; the next element is ignored.
+    pub fn mark_next(&mut self) {
+        self.pending = true;
+    }
+
+    /// Called when an element starts. Returns whether it is ignored; ignored
+    /// elements with content must be matched by a call to `leave`.
+    pub fn enter(&mut self, has_ignore_attr: bool, has_content: bool) -> bool {
+        let ignored = self.depth > 0 || std::mem::take(&mut self.pending) || has_ignore_attr;
+        if ignored && has_content {
+            self.depth += 1;
+        }
+        ignored
+    }
+
+    /// Called when an ignored element's end tag is reached.
+    pub fn leave(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
+
+/// Option overrides from a directive comment such as
+/// `<!-- ast-append-ids: prefix=modal- strategy=path -->`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OptionOverrides {
+    pub prefix: Option<String>,
+    pub strategy: Option<IdStrategy>,
+}
+
+impl OptionOverrides {
+    /// Parses a comment's text. Returns `Ok(None)` for comments that are not
+    /// option directives (including the `ignore`/`on`/`off` markers).
+    pub fn parse(comment: &str) -> Result<Option<Self>, String> {
+        let Some(rest) = comment.trim().strip_prefix("ast-append-ids:") else {
+            return Ok(None);
+        };
+        if !rest.contains('=') {
+            return Ok(None);
+        }
+
+        let mut overrides = Self::default();
+        for pair in rest.split_whitespace() {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid directive '{}': expected key=value", pair))?;
+            match key {
+                "prefix" => overrides.prefix = Some(value.to_string()),
+                "strategy" => {
+                    overrides.strategy = Some(match value {
+                        "hash" => IdStrategy::Hash,
+                        "slug" => IdStrategy::Slug,
+                        "path" => IdStrategy::Path,
+                        "taxonomy" => IdStrategy::Taxonomy,
+                        _ => return Err(format!("Invalid directive strategy '{}'", value)),
+                    })
+                }
+                _ => return Err(format!("Unknown directive option '{}'", key)),
+            }
+        }
+        Ok(Some(overrides))
+    }
+
+    /// Whether a directive comment in `content` switches to `strategy`, for
+    /// setup that processing with that strategy needs up front. Directives
+    /// that do not parse are left to the pass reporting them.
+    pub fn any_selects(content: &str, strategy: &IdStrategy) -> bool {
+        content.match_indices("ast-append-ids:").any(|(start, _)| {
+            let rest = &content[start..];
+            let end = ["\n", "-->", "*/"].iter().filter_map(|close| rest.find(close)).min().unwrap_or(rest.len());
+            matches!(Self::parse(&rest[..end]), Ok(Some(Self { strategy: Some(found), .. })) if found == *strategy)
+        })
+    }
+
+    pub fn apply(&self, options: &IdOptions) -> IdOptions {
+        let mut options = options.clone();
+        if let Some(prefix) = &self.prefix {
+            options.prefix = prefix.clone();
+        }
+        if let Some(strategy) = &self.strategy {
+            options.strategy = strategy.clone();
+        }
+        options
+    }
+}
+
+/// Tracks directive comments while streaming through a document. A directive
+/// applies to the next element and its subtree.
+#[derive(Debug, Default)]
+pub struct OverrideScopes {
+    stack: Vec<IdOptions>,
+    pending: Option<OptionOverrides>,
+    /// Options for an element without content that had a directive
+    transient: Option<IdOptions>,
+}
+
+impl OverrideScopes {
+    pub fn set_pending(&mut self, overrides: OptionOverrides) {
+        self.pending = Some(overrides);
+    }
+
+    /// Adds the overrides of a component scope to those of the next element.
+    /// A directive in front of the element still has the last word.
+    pub fn add_component(&mut self, overrides: OptionOverrides) {
+        self.pending = Some(match self.pending.take() {
+            Some(directive) => OptionOverrides {
+                prefix: directive.prefix.or(overrides.prefix),
+                strategy: directive.strategy.or(overrides.strategy),
+            },
+            None => overrides,
+        });
+    }
+
+    /// Called when an element starts. Returns whether a scope was opened, in
+    /// which case `leave` must be called at the element's end tag.
+    pub fn enter(&mut self, base: &IdOptions, has_content: bool) -> bool {
+        self.transient = None;
+        let Some(overrides) = self.pending.take() else {
+            return false;
+        };
+
+        let scoped = overrides.apply(self.current(base));
+        if has_content {
+            self.stack.push(scoped);
+            true
+        } else {
+            self.transient = Some(scoped);
+            false
+        }
+    }
+
+    pub fn leave(&mut self) {
+        self.transient = None;
+        self.stack.pop();
+    }
+
+    /// Options in effect for the element most recently entered.
+    pub fn current<'a>(&'a self, base: &'a IdOptions) -> &'a IdOptions {
+        self.transient
+            .as_ref()
+            .or(self.stack.last())
+            .unwrap_or(base)
+    }
+}
+
+/// Open scopes of `IdOptions::component_scope`, innermost last: the depth of
+/// each root element and the counter outside it
+#[derive(Debug, Clone, Default)]
+pub struct ComponentScopes(Vec<(usize, usize)>);
+
+impl ComponentScopes {
+    /// Opens a scope at a root element `depth` elements deep, keeping the
+    /// `counter` outside it. The counter restarts at 0 in the scope.
+    pub fn enter(&mut self, depth: usize, counter: usize) {
+        self.0.push((depth, counter));
+    }
+
+    /// Closes the scopes of the element `depth` elements deep and the ones in
+    /// it, returning the counter to go on with when there were any
+    pub fn leave(&mut self, depth: usize) -> Option<usize> {
+        let mut counter = None;
+        while self.0.last().is_some_and(|&(root, _)| root >= depth) {
+            counter = self.0.pop().map(|(_, outside)| outside);
+        }
+        counter
+    }
+
+    /// Where paths start in the innermost scope: the depth of its root
+    pub fn start(&self) -> usize {
+        self.0.last().map_or(0, |&(root, _)| root)
+    }
+}
+
+/// Compiles `IdOptions::text_matches`.
+pub fn text_pattern(options: &IdOptions) -> Result<Option<Regex>, String> {
+    options
+        .text_matches
+        .as_deref()
+        .map(|pattern| Regex::new(pattern).map_err(|e| format!("Invalid text pattern '{}': {}", pattern, e)))
+        .transpose()
+}
+
+/// Compiles `IdOptions::comment_anchors`.
+pub fn comment_anchor_pattern(options: &IdOptions) -> Result<Option<Regex>, String> {
+    options
+        .comment_anchors
+        .as_deref()
+        .map(|pattern| Regex::new(pattern).map_err(|e| format!("Invalid comment anchor pattern '{}': {}", pattern, e)))
+        .transpose()
+}
+
+/// `include` and `exclude` compiled once per document, so each element
+/// costs a hash lookup however long the lists are
+#[derive(Debug, Clone, Default)]
+pub struct NameFilter {
+    include: HashSet<String>,
+    exclude: HashSet<String>,
+    /// Names are kept lowercase and looked up lowercase
+    ignore_case: bool,
+}
+
+impl NameFilter {
+    /// With `ignore_case`, `name_case` settled for the format
+    pub fn new(options: &IdOptions, ignore_case: bool) -> Self {
+        let compile = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| if ignore_case { name.to_ascii_lowercase() } else { name.clone() })
+                .collect()
+        };
+        Self {
+            include: compile(&options.include),
+            exclude: compile(&options.exclude),
+            ignore_case,
+        }
+    }
+
+    /// Whether elements named `name` pass the include and exclude lists
+    pub fn allows(&self, name: &str) -> bool {
+        self.skip_reason(name).is_none()
+    }
+
+    /// Whether `include` names `name`
+    pub fn includes(&self, name: &str) -> bool {
+        !self.include.is_empty() && self.include.contains(self.key(name).as_ref())
+    }
+
+    /// Which list keeps elements named `name` out, if one does
+    pub fn skip_reason(&self, name: &str) -> Option<SkipReason> {
+        if self.include.is_empty() && self.exclude.is_empty() {
+            return None;
+        }
+        let name = self.key(name);
+        if !self.include.is_empty() && !self.include.contains(name.as_ref()) {
+            Some(SkipReason::NotIncluded)
+        } else if self.exclude.contains(name.as_ref()) {
+            Some(SkipReason::Excluded)
+        } else {
+            None
+        }
+    }
+
+    /// `name` as the lists hold it. Only names with capitals need a
+    /// lowercase copy.
+    fn key<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        if self.ignore_case && name.bytes().any(|b| b.is_ascii_uppercase()) {
+            Cow::Owned(name.to_ascii_lowercase())
+        } else {
+            Cow::Borrowed(name)
+        }
+    }
+}
+
 pub fn should_process_node(
     node_name: &str,
+    filter: &NameFilter,
     options: &IdOptions,
     existing_id: Option<&str>,
 ) -> bool {
@@ -24,35 +308,372 @@ pub fn should_process_node(
         return false;
     }
 
-    // Check include list
-    if !options.include.is_empty() && !options.include.contains(&node_name.to_string()) {
-        return false;
+    filter.allows(node_name)
+}
+
+/// What the filters see of an element that the selector and `text_matches`
+/// picked, see `check_element`
+pub struct Candidate<'a> {
+    pub name: &'a str,
+    pub classes: &'a [&'a str],
+    /// Start of the element in the input, for `range`
+    pub offset: usize,
+    pub existing_id: Option<&'a str>,
+    /// Whether the existing ID is an expression, whose value is not known
+    pub dynamic_id: bool,
+    /// Whether the element carries `origin_attr`
+    pub has_origin: bool,
+}
+
+/// Whether the selector and `text_matches` pick an element, the first of
+/// the checks `SkipReason` lists
+pub fn pick_element(
+    generator: &mut crate::id_generator::IdGenerator,
+    selected: bool,
+    text_matched: bool,
+) -> Result<(), SkipReason> {
+    if !selected {
+        return Err(SkipReason::NotSelected);
+    }
+    generator.note_selected();
+    if !text_matched {
+        return Err(SkipReason::TextMismatch);
     }
+    Ok(())
+}
 
-    // Check exclude list
-    if options.exclude.contains(&node_name.to_string()) {
-        return false;
+/// The rest of the checks on a picked element, in the order `SkipReason`
+/// lists them. Every processor goes through this, so the options combine
+/// the same way in all formats. Warns about an existing ID that is empty or
+/// on another element too, and about a tag both included and excluded.
+pub fn check_element(
+    generator: &mut crate::id_generator::IdGenerator,
+    filter: &NameFilter,
+    options: &IdOptions,
+    element: &Candidate,
+) -> Result<(), SkipReason> {
+    match element.existing_id {
+        _ if element.dynamic_id => {}
+        Some("") => generator.add_warning(Warning::empty_id(element.name, &options.attr, element.offset)),
+        Some(id) if generator.note_existing_id(id) => generator.add_warning(Warning::duplicate_id(id, element.offset)),
+        _ => {}
+    }
+    if filter.includes(element.name) {
+        generator.note_included();
+    }
+    if let Some(reason) = filter.skip_reason(element.name) {
+        if reason == SkipReason::Excluded && filter.includes(element.name) && generator.note_conflict(element.name) {
+            generator.add_warning(Warning::included_and_excluded(element.name));
+        }
+        return Err(reason);
+    }
+    if !class_included(options, element.classes) {
+        return Err(SkipReason::ClassMismatch);
+    }
+    if !in_range(options, element.offset) {
+        return Err(SkipReason::OutOfRange);
+    }
+    if element.existing_id.is_some() && !options.overwrite {
+        return Err(SkipReason::HasId);
+    }
+    if hand_written(options, element.existing_id, element.has_origin) {
+        return Err(SkipReason::HandWritten);
+    }
+    Ok(())
+}
+
+/// Records whether the element at `offset` got an ID, if the generator keeps
+/// a filter log. `skipped` is the check it failed.
+pub fn record_decision(
+    generator: &mut crate::id_generator::IdGenerator,
+    offset: usize,
+    tag: &str,
+    skipped: Option<SkipReason>,
+) {
+    if generator.logs_filters() {
+        generator.record_decision(FilterDecision { offset, tag: tag.to_string(), skipped });
     }
+}
 
-    true
+/// Value of `options.origin_attr`: the tool, its version and the strategy
+/// that made the ID
+pub fn origin(options: &IdOptions) -> String {
+    format!("ast-append-ids@{}:{}", env!("CARGO_PKG_VERSION"), options.strategy.name())
+}
+
+/// `value` written as a double-quoted attribute value. Character references
+/// stand for `&`, `"` and `<`, which HTML, XML and JSX all decode.
+pub fn escape_attribute(value: &str) -> Cow<'_, str> {
+    if !value.contains(['&', '"', '<']) {
+        return Cow::Borrowed(value);
+    }
+    Cow::Owned(value.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;"))
+}
+
+/// Whether an element's existing ID was written by hand: with
+/// `origin_attr` set, an ID the tool wrote carries that attribute. Such IDs
+/// are kept even with `overwrite`.
+pub fn hand_written(options: &IdOptions, existing_id: Option<&str>, has_origin: bool) -> bool {
+    options.origin_attr.is_some() && existing_id.is_some() && !has_origin
+}
+
+/// Whether an element whose start tag begins at `offset` is inside
+/// `options.range`; without a range every element is.
+pub fn in_range(options: &IdOptions, offset: usize) -> bool {
+    options.range.as_ref().is_none_or(|range| range.contains(&offset))
+}
+
+/// Byte range of the 1-based lines `first` to `last` of `content`, both
+/// included, as `IdOptions::range` takes it. Lines past the end are empty.
+pub fn line_range(content: &str, first: usize, last: usize) -> std::ops::Range<usize> {
+    let line_start = |line: usize| {
+        if line <= 1 {
+            return 0;
+        }
+        content
+            .match_indices('\n')
+            .nth(line - 2)
+            .map_or(content.len(), |(index, _)| index + 1)
+    };
+    line_start(first)..line_start(last + 1).max(line_start(first))
+}
+
+/// Whether an element with `classes` passes the `include_class` filter.
+pub fn class_included(options: &IdOptions, classes: &[&str]) -> bool {
+    options.include_class.is_empty()
+        || classes.iter().any(|class| options.include_class.iter().any(|included| included == class))
+}
+
+/// Fingerprint of an element's own content: its tag and attributes, excluding
+/// the ID attribute itself. Independent of the element's position, so it
+/// survives siblings being inserted or removed. The element's text is left
+/// out: the streaming processors write the ID at the start tag, before the
+/// text has been read, so only the slug strategy has it, and a fingerprint
+/// with it would no longer match once the strategy changes.
+pub fn node_fingerprint(node: &AstNode, id_attr: &str) -> String {
+    let mut attributes: Vec<_> = node
+        .attributes
+        .iter()
+        .filter(|(name, _)| name != id_attr)
+        .collect();
+    attributes.sort();
+
+    let mut hasher = Sha256::new();
+    hasher.update(node.node_type.as_bytes());
+    for (name, value) in attributes {
+        hasher.update(b"\0");
+        hasher.update(name.as_bytes());
+        hasher.update(b"=");
+        hasher.update(value.as_bytes());
+    }
+    let hash = format!("{:x}", hasher.finalize());
+    hash[..16].to_string()
 }
 
 pub fn generate_id_for_node(
     generator: &mut crate::id_generator::IdGenerator,
     node: &AstNode,
     options: &IdOptions,
+    resolved: &ResolvedOptions,
 ) -> String {
-    match options.strategy {
-        IdStrategy::Hash => {
-            generator.generate_hash_id(&node.node_type, &node.path, &options.prefix)
+    let fingerprint = node_fingerprint(node, &options.attr);
+
+    let (id, reused) = match generator.take_reusable(&fingerprint) {
+        Some(id) => (id, true),
+        None => (generate_fresh_id(generator, node, options, resolved), false),
+    };
+    tracing::debug!(tag = %node.node_type, path = ?node.path, id = %id, reused, "assigned ID");
+
+    generator.record_assignment(Assignment {
+        fingerprint,
+        id: id.clone(),
+        tag: node.node_type.clone(),
+        path: node.path.clone(),
+        alias: None,
+    });
+    options.with_env_prefix(&id)
+}
+
+/// Records what happened to the element at `offset` if the generator keeps a
+/// change log. `id` is the ID that was written; `None` means the element was
+/// left alone, which is only logged when it already had an ID.
+pub fn record_change(
+    generator: &mut crate::id_generator::IdGenerator,
+    offset: usize,
+    tag: &str,
+    existing_id: Option<&str>,
+    id: Option<&str>,
+) {
+    if !generator.logs_changes() {
+        return;
+    }
+    let (action, id) = match (id, existing_id) {
+        (Some(id), None) => (ChangeAction::Added, id),
+        (Some(id), Some(_)) => (ChangeAction::Overwritten, id),
+        (None, Some(existing)) => (ChangeAction::Skipped, existing),
+        (None, None) => return,
+    };
+    generator.record_change(Change {
+        offset,
+        tag: tag.to_string(),
+        action,
+        id: id.to_string(),
+    });
+}
+
+/// An element carrying an ID attribute, located by byte offset into its source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdLocation {
+    pub offset: usize,
+    pub tag: String,
+    pub id: String,
+    /// `node_fingerprint` of the element
+    pub fingerprint: String,
+}
+
+impl IdLocation {
+    pub fn new(offset: usize, tag: String, id: String, attributes: Vec<(String, String)>, id_attr: &str) -> Self {
+        let node = AstNode {
+            node_type: tag,
+            text_content: None,
+            attributes,
+            path: Vec::new(),
+            context: Vec::new(),
+        };
+        let fingerprint = node_fingerprint(&node, id_attr);
+        Self {
+            offset,
+            tag: node.node_type,
+            id,
+            fingerprint,
         }
-        IdStrategy::Slug => {
-            let text = node.text_content.as_deref().unwrap_or("");
-            generator.generate_slug_id(text, &options.prefix)
+    }
+}
+
+/// 1-based line and column of each byte offset in `content`. Offsets must be
+/// in ascending order; columns count characters.
+pub fn line_columns(content: &str, offsets: impl IntoIterator<Item = usize>) -> Vec<(usize, usize)> {
+    let (mut line, mut col, mut pos) = (1, 1, 0);
+    offsets
+        .into_iter()
+        .map(|offset| {
+            let mut offset = offset.min(content.len());
+            while !content.is_char_boundary(offset) {
+                offset -= 1;
+            }
+            for c in content[pos..offset].chars() {
+                if c == '\n' {
+                    line += 1;
+                    col = 1;
+                } else {
+                    col += 1;
+                }
+            }
+            pos = offset;
+            (line, col)
+        })
+        .collect()
+}
+
+/// Makes `output` end like `input`, with the same run of line breaks or none,
+/// and drops trailing whitespace a changed line gained over the input line it
+/// replaces, so formatters checking either see no difference.
+pub fn match_layout(input: &str, output: String) -> String {
+    let mut output = output;
+    let ending = trailing_newlines(input);
+    if trailing_newlines(&output) != ending {
+        output.truncate(output.trim_end_matches(['\n', '\r']).len());
+        output.push_str(ending);
+    }
+    strip_added_trailing_whitespace(input, output)
+}
+
+fn trailing_newlines(text: &str) -> &str {
+    &text[text.trim_end_matches(['\n', '\r']).len()..]
+}
+
+fn trailing_whitespace(line: &str) -> usize {
+    line.len() - line.trim_end_matches([' ', '\t']).len()
+}
+
+fn strip_added_trailing_whitespace(input: &str, output: String) -> String {
+    // Most outputs only touch lines without trailing whitespace; skip the diff
+    let input_lines: HashSet<&str> = input.lines().collect();
+    if !output.lines().any(|line| trailing_whitespace(line) > 0 && !input_lines.contains(line)) {
+        return output;
+    }
+
+    let input_lines: Vec<&str> = input.lines().collect();
+    let mut allowed = vec![0; output.lines().count()];
+    for row in report::diff_rows(input, &output) {
+        if let (Some(new), Some(old)) = (row.new, row.old) {
+            allowed[new] = trailing_whitespace(input_lines[old]);
+        }
+    }
+
+    let mut result = String::with_capacity(output.len());
+    for (index, line) in output.split_inclusive('\n').enumerate() {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let extra = trailing_whitespace(content).saturating_sub(allowed[index]);
+        result.push_str(&content[..content.len() - extra]);
+        result.push_str(&line[content.len()..]);
+    }
+    result
+}
+
+/// Longest snippet `source_snippet` returns, in characters
+pub const SNIPPET_LEN: usize = 120;
+
+/// Source from `offset` to the end of its line, shortened to `SNIPPET_LEN`
+/// characters.
+pub fn source_snippet(content: &str, offset: usize) -> String {
+    let rest = content.get(offset..).unwrap_or_default();
+    let line = rest.lines().next().unwrap_or_default().trim_end();
+    if line.chars().count() <= SNIPPET_LEN {
+        return line.to_string();
+    }
+    let mut snippet: String = line.chars().take(SNIPPET_LEN).collect();
+    snippet.push('…');
+    snippet
+}
+
+/// IDs to write for a manual `value` on `matched.len()` elements: the first
+/// gets `value`, the others a `-2`, `-3`, ... suffix. `all` holds every ID in
+/// the document and `matched` the IDs the matched elements carry now; only the
+/// ones that stay are reserved, so running the same edit twice changes nothing.
+pub fn manual_ids(
+    generator: &mut crate::id_generator::IdGenerator,
+    value: &str,
+    mut all: Vec<String>,
+    matched: &[Option<String>],
+) -> Vec<String> {
+    for id in matched.iter().flatten() {
+        if let Some(index) = all.iter().position(|existing| existing == id) {
+            all.swap_remove(index);
         }
-        IdStrategy::Path => {
-            generator.generate_path_id(&node.node_type, &node.path, &options.prefix)
+    }
+    for id in all {
+        generator.reserve(id);
+    }
+    matched
+        .iter()
+        .map(|_| generator.ensure_unique(value.to_string()))
+        .collect()
+}
+
+fn generate_fresh_id(
+    generator: &mut crate::id_generator::IdGenerator,
+    node: &AstNode,
+    options: &IdOptions,
+    resolved: &ResolvedOptions,
+) -> String {
+    match generator.take_strategy() {
+        Some(mut custom) => {
+            let id = custom.id_for(node, &mut StrategyContext { generator, options });
+            generator.restore_strategy(custom);
+            id
         }
+        None => resolved.fresh_id(node, &mut StrategyContext { generator, options }),
     }
 }
 
@@ -78,34 +699,254 @@ pub fn set_attribute(
     }
 }
 
+/// Runs `process` with the generator of `processor` and collects what it did
+/// into a `ProcessOutput`.
+pub fn with_meta<P: AstProcessor + ?Sized>(
+    processor: &mut P,
+    options: &IdOptions,
+    process: impl FnOnce(&mut P) -> Result<String, String>,
+) -> Result<ProcessOutput, String> {
+    let generator = processor.generator_mut();
+    let (assigned, visited) = (generator.assignment_count(), generator.visited_count());
+    generator.start_document();
+    let content = process(processor)?;
+    let generator = processor.generator_mut();
+    if let Some(selector) = &options.selector {
+        if generator.selected_count() == 0 {
+            generator.add_warning(Warning::selector_matched_nothing(selector));
+        }
+    }
+    Ok(ProcessOutput {
+        content,
+        ids_added: generator.assignment_count() - assigned,
+        elements_visited: generator.visited_count() - visited,
+        warnings: generator.take_warnings(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_line_range() {
+        let content = "<a>\n<b/>\n</a>";
+        assert_eq!(line_range(content, 2, 2), 4..9);
+        assert_eq!(&content[line_range(content, 2, 3)], "<b/>\n</a>");
+        assert_eq!(line_range(content, 5, 9), 13..13);
+
+        let mut options = IdOptions::default();
+        assert!(in_range(&options, 100));
+        options.range = Some(line_range(content, 2, 2));
+        assert!(in_range(&options, 4) && !in_range(&options, 0) && !in_range(&options, 9));
+    }
+
+    #[test]
+    fn test_match_layout() {
+        // Final line breaks follow the input
+        assert_eq!(match_layout("<a/>", "<a id=\"x\"/>\n".to_string()), "<a id=\"x\"/>");
+        assert_eq!(match_layout("<a/>\n\n", "<a id=\"x\"/>\n".to_string()), "<a id=\"x\"/>\n\n");
+        assert_eq!(match_layout("<a/>\r\n", "<a id=\"x\"/>".to_string()), "<a id=\"x\"/>\r\n");
+
+        // Trailing whitespace gained by a changed line is dropped, while
+        // whitespace the input already had stays
+        let input = "<div>\n  <pre>code  </pre>\n<p>text</p>   \n</div>\n";
+        let output = "<div id=\"a\">  \n  <pre id=\"b\">code  </pre>\n<p>text</p>   \n\t\n</div>\n";
+        assert_eq!(
+            match_layout(input, output.to_string()),
+            "<div id=\"a\">\n  <pre id=\"b\">code  </pre>\n<p>text</p>   \n\n</div>\n"
+        );
+    }
+
     #[test]
     fn test_should_process_node() {
         let mut options = IdOptions::default();
+        let should_process = |name: &str, options: &IdOptions, existing_id: Option<&str>| {
+            should_process_node(name, &NameFilter::new(options, options.name_case.ignores_case()), options, existing_id)
+        };
         
         // Test with no restrictions
-        assert!(should_process_node("div", &options, None));
+        assert!(should_process("div", &options, None));
         
         // Test with existing ID and no overwrite
-        assert!(!should_process_node("div", &options, Some("existing-id")));
+        assert!(!should_process("div", &options, Some("existing-id")));
         
         // Test with overwrite enabled
         options.overwrite = true;
-        assert!(should_process_node("div", &options, Some("existing-id")));
+        assert!(should_process("div", &options, Some("existing-id")));
         
         // Test include list
         options.include = vec!["div".to_string(), "span".to_string()];
-        assert!(should_process_node("div", &options, None));
-        assert!(!should_process_node("p", &options, None));
+        assert!(should_process("div", &options, None));
+        assert!(!should_process("p", &options, None));
         
         // Test exclude list
         options.include.clear();
         options.exclude = vec!["script".to_string(), "style".to_string()];
-        assert!(should_process_node("div", &options, None));
-        assert!(!should_process_node("script", &options, None));
+        assert!(should_process("div", &options, None));
+        assert!(!should_process("script", &options, None));
+
+        // Case is ignored on both sides when asked to
+        options.exclude = vec!["Script".to_string()];
+        assert!(should_process("SCRIPT", &options, None));
+        options.name_case = crate::NameCase::Insensitive;
+        assert!(!should_process("SCRIPT", &options, None));
+        assert!(!should_process("script", &options, None));
+    }
+
+    #[test]
+    fn test_node_fingerprint_ignores_path_and_id_attr() {
+        let a = AstNode {
+            node_type: "button".to_string(),
+            text_content: None,
+            attributes: vec![
+                ("class".to_string(), "primary".to_string()),
+                ("data-ast-id".to_string(), "el-1".to_string()),
+            ],
+            path: vec![0, 1],
+            context: Vec::new(),
+        };
+        let b = AstNode {
+            node_type: "button".to_string(),
+            text_content: None,
+            attributes: vec![("class".to_string(), "primary".to_string())],
+            path: vec![0, 5],
+            context: Vec::new(),
+        };
+        assert_eq!(node_fingerprint(&a, "data-ast-id"), node_fingerprint(&b, "data-ast-id"));
+
+        let c = AstNode { node_type: "a".to_string(), ..b };
+        assert_ne!(node_fingerprint(&a, "data-ast-id"), node_fingerprint(&c, "data-ast-id"));
+    }
+
+    #[test]
+    fn test_generate_id_reuses_cached_id() {
+        let mut generator = crate::id_generator::IdGenerator::new();
+        let options = IdOptions::default();
+        let node = AstNode {
+            node_type: "div".to_string(),
+            text_content: None,
+            attributes: Vec::new(),
+            path: vec![3],
+            context: Vec::new(),
+        };
+        let fingerprint = node_fingerprint(&node, &options.attr);
+        generator.enable_assignment_log();
+        generator.add_reusable(vec![Assignment {
+            fingerprint,
+            id: "el-old".to_string(),
+            tag: "div".to_string(),
+            path: vec![2],
+            alias: None,
+        }]);
+        let resolved = ResolvedOptions::new(&options, crate::detect::Format::Html).unwrap();
+
+        assert_eq!(generate_id_for_node(&mut generator, &node, &options, &resolved), "el-old");
+        assert_ne!(generate_id_for_node(&mut generator, &node, &options, &resolved), "el-old");
+        assert_eq!(generator.take_assignments().len(), 2);
+    }
+
+    #[test]
+    fn test_generate_id_with_env_prefix() {
+        let mut generator = crate::id_generator::IdGenerator::new();
+        let mut options = IdOptions::default();
+        options.strategy = crate::IdStrategy::Path;
+        options.env_prefix = "staging:".to_string();
+        let node = AstNode {
+            node_type: "div".to_string(),
+            text_content: None,
+            attributes: Vec::new(),
+            path: vec![0],
+            context: Vec::new(),
+        };
+
+        let resolved = ResolvedOptions::new(&options, crate::detect::Format::Html).unwrap();
+
+        generator.enable_assignment_log();
+        generator.reserve(options.without_env_prefix("staging:el-div-0"));
+        assert_eq!(generate_id_for_node(&mut generator, &node, &options, &resolved), "staging:el-div-0-2");
+        assert_eq!(generator.take_assignments()[0].id, "el-div-0-2");
+        assert_eq!(options.without_env_prefix("prod:el-div-0"), "prod:el-div-0");
+    }
+
+    #[test]
+    fn test_manual_ids() {
+        let mut generator = crate::id_generator::IdGenerator::new();
+        let all = vec!["a".to_string(), "x".to_string(), "b".to_string()];
+        let matched = [Some("a".to_string()), None];
+        assert_eq!(manual_ids(&mut generator, "a", all.clone(), &matched), vec!["a", "a-2"]);
+
+        // IDs kept by elements that are not matched are never reused
+        let mut generator = crate::id_generator::IdGenerator::new();
+        assert_eq!(manual_ids(&mut generator, "x", all, &matched), vec!["x-2", "x-3"]);
+    }
+
+    #[test]
+    fn test_ignore_tracker() {
+        let mut tracker = IgnoreTracker::default();
+        assert!(!tracker.enter(false, true));
+
+        // Ignored parent skips its children until it is left
+        assert!(tracker.enter(true, true));
+        assert!(tracker.enter(false, false));
+        tracker.leave();
+        assert!(!tracker.enter(false, false));
+
+        // Comment marker applies to the next element only
+        tracker.mark_next();
+        assert!(tracker.enter(false, false));
+        assert!(!tracker.enter(false, false));
+
+        assert!(is_ignore_comment(" ast-append-ids: ignore "));
+        assert!(!is_ignore_comment("ast-append-ids: off"));
+    }
+
+    #[test]
+    fn test_option_overrides() {
+        let overrides = OptionOverrides::parse(" ast-append-ids: prefix=modal- strategy=path ")
+            .unwrap()
+            .unwrap();
+        assert_eq!(overrides.prefix.as_deref(), Some("modal-"));
+        assert_eq!(overrides.strategy, Some(IdStrategy::Path));
+
+        assert_eq!(OptionOverrides::parse("ast-append-ids: ignore").unwrap(), None);
+        assert_eq!(OptionOverrides::parse("just a comment").unwrap(), None);
+        assert!(OptionOverrides::parse("ast-append-ids: perfix=x").is_err());
+        assert!(OptionOverrides::parse("ast-append-ids: strategy=uuid").is_err());
+    }
+
+    #[test]
+    fn test_option_overrides_any_selects() {
+        let slug = IdStrategy::Slug;
+        assert!(OptionOverrides::any_selects("<p></p><!-- ast-append-ids: prefix=a- strategy=slug -->", &slug));
+        assert!(OptionOverrides::any_selects("{/* ast-append-ids: strategy=slug */}\n<p />", &slug));
+        assert!(!OptionOverrides::any_selects("<!-- ast-append-ids: strategy=path -->", &slug));
+        // Text that only mentions the option is no directive
+        assert!(!OptionOverrides::any_selects("<code>strategy=slug</code>", &slug));
+        assert!(!OptionOverrides::any_selects("<!-- ast-append-ids: on --> strategy=slug", &slug));
+    }
+
+    #[test]
+    fn test_override_scopes() {
+        let base = IdOptions::default();
+        let mut scopes = OverrideScopes::default();
+        let modal = OptionOverrides { prefix: Some("modal-".to_string()), strategy: None };
+
+        assert!(!scopes.enter(&base, true));
+        assert_eq!(scopes.current(&base).prefix, "el-");
+
+        scopes.set_pending(modal.clone());
+        assert!(scopes.enter(&base, true));
+        assert!(!scopes.enter(&base, true));
+        assert_eq!(scopes.current(&base).prefix, "modal-");
+        scopes.leave();
+        assert_eq!(scopes.current(&base).prefix, "el-");
+
+        scopes.set_pending(modal);
+        assert!(!scopes.enter(&base, false));
+        assert_eq!(scopes.current(&base).prefix, "modal-");
+        scopes.enter(&base, false);
+        assert_eq!(scopes.current(&base).prefix, "el-");
     }
 
     #[test]
@@ -0,0 +1,95 @@
+use crate::ast_common;
+use crate::id_generator::{Change, ChangeAction};
+use serde::Serialize;
+use std::io::Write;
+
+/// One line of the NDJSON change log. `line` and `col` are 1-based and point
+/// at the element's start in the input file; `col` counts characters.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangeRecord {
+    pub file: String,
+    pub line: usize,
+    pub col: usize,
+    pub tag: String,
+    pub action: ChangeAction,
+    pub id: String,
+}
+
+/// Resolves the changes made to `content` to line and column numbers, in
+/// source order.
+pub fn change_records(file: &str, content: &str, mut changes: Vec<Change>) -> Vec<ChangeRecord> {
+    changes.sort_by_key(|change| change.offset);
+    let positions = ast_common::line_columns(content, changes.iter().map(|change| change.offset));
+
+    changes
+        .into_iter()
+        .zip(positions)
+        .map(|(change, (line, col))| ChangeRecord {
+            file: file.to_string(),
+            line,
+            col,
+            tag: change.tag,
+            action: change.action,
+            id: change.id,
+        })
+        .collect()
+}
+
+/// Writes `records` as newline-delimited JSON.
+pub fn write_ndjson(out: &mut impl Write, records: &[ChangeRecord]) -> Result<(), String> {
+    for record in records {
+        serde_json::to_writer(&mut *out, record)
+            .map_err(|e| format!("Failed to write change log: {}", e))?;
+        out.write_all(b"\n")
+            .map_err(|e| format!("Failed to write change log: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(offset: usize, action: ChangeAction) -> Change {
+        Change {
+            offset,
+            tag: "div".to_string(),
+            action,
+            id: "el-1".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_change_records_positions() {
+        let content = "<a>\n  <é><div>\n<div>";
+        let changes = vec![
+            change(content.rfind("<div").unwrap(), ChangeAction::Added),
+            change(0, ChangeAction::Skipped),
+            change(content.find("<div").unwrap(), ChangeAction::Overwritten),
+        ];
+
+        let positions: Vec<_> = change_records("a.html", content, changes)
+            .iter()
+            .map(|record| (record.line, record.col, record.action))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (1, 1, ChangeAction::Skipped),
+                (2, 6, ChangeAction::Overwritten),
+                (3, 1, ChangeAction::Added),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_ndjson() {
+        let records = change_records("a.xml", "<a/>", vec![change(0, ChangeAction::Added)]);
+        let mut out = Vec::new();
+        write_ndjson(&mut out, &records).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"file\":\"a.xml\",\"line\":1,\"col\":1,\"tag\":\"div\",\"action\":\"added\",\"id\":\"el-1\"}\n"
+        );
+    }
+}
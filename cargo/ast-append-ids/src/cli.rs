@@ -1,13 +1,60 @@
+mod anchor;
+mod codemod_tests;
+mod coverage;
+mod diff_ids;
+mod explain;
+mod export_text;
+mod find;
+mod gen_runtime;
+mod gen_selectors;
+mod import_text;
+mod migrate_scheme;
+mod options_schema;
+mod select;
+mod set_id;
+mod stories;
+mod sync;
+mod undo;
+
 use anyhow::{Context, Result};
-use ast_append_ids::{AstProcessor, IdOptions, IdStrategy};
-use ast_append_ids::jsx::JsxProcessor;
-use ast_append_ids::xml::XmlProcessor;
-use ast_append_ids::html::HtmlProcessor;
-use clap::{Parser, Subcommand, ValueEnum};
-use colored::*;
+use ast_append_ids::{IdOptions, IdScheme, IdStrategy, NameCase, CounterMode, ProcessOutput, Processor, RunMetadata};
+use ast_append_ids::ast_common::{self, IdLocation};
+use ast_append_ids::change_log;
+use ast_append_ids::config::{self, Config, Rule};
+use ast_append_ids::coverage::PolicyElement;
+use ast_append_ids::detect::{self, Format};
+use ast_append_ids::edits::{self, StructuralEdit};
+use ast_append_ids::gen_selectors::{Naming, SelectorOptions};
+use ast_append_ids::limits::{self, Limits};
+use ast_append_ids::file_io::{FileContent, FileLock, Preserve};
+use ast_append_ids::anchors::Anchors;
+use ast_append_ids::id_cache::IdCache;
+use ast_append_ids::id_generator::{Assignment, FilterDecision, IdGenerator};
+use ast_append_ids::manifest::Manifest;
+use ast_append_ids::patch;
+use ast_append_ids::project::{self, Package};
+use ast_append_ids::pragma::{self, FilePragma};
+use ast_append_ids::report::{self, FileReport};
+use ast_append_ids::resolved::ResolvedOptions;
+use ast_append_ids::select::SelectedElement;
+use ast_append_ids::selector::Selector;
+use ast_append_ids::suggest;
+use ast_append_ids::warnings::Warning;
+use ast_append_ids::sink::{FileSink, Replace, Sink, StdoutSink};
+use ast_append_ids::jsx;
+use ast_append_ids::xml;
+use ast_append_ids::html;
+use ast_append_ids::namespace::{self, NamespaceStyle};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use glob::glob;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::io::{BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use tracing::level_filters::LevelFilter;
+use tracing::{debug, error, info, info_span, warn};
 
 #[derive(Parser)]
 #[command(name = "ast-append-ids")]
@@ -15,15 +62,23 @@ use std::path::{Path, PathBuf};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Most detailed log level to show (default: info, debug with --verbose)
+    #[arg(long, value_enum, global = true, value_name = "LEVEL")]
+    log_level: Option<LogLevel>,
+
+    /// Log output format, written to stderr
+    #[arg(long, value_enum, global = true, default_value = "text")]
+    log_format: LogFormat,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Process JSX/React files
     Jsx {
-        /// Input file or glob pattern
-        #[arg(value_name = "PATH")]
-        path: String,
+        /// Input file or glob pattern (default: the paths of --profile)
+        #[arg(value_name = "PATH", required_unless_present = "profile")]
+        path: Option<String>,
         
         /// Attribute name for ID
         #[arg(long, default_value = "data-ast-id")]
@@ -48,21 +103,50 @@ enum Commands {
         /// Tags to exclude (comma-separated)
         #[arg(long, value_delimiter = ',')]
         exclude: Vec<String>,
+
+        /// Only tag elements with one of these classes in `className` (comma-separated)
+        #[arg(long, value_delimiter = ',', value_name = "CLASSES")]
+        include_class: Vec<String>,
+
+        /// Process HTML inside template literals with these tags, e.g. `html,render`
+        #[arg(long, value_delimiter = ',', value_name = "TAGS")]
+        template_tags: Vec<String>,
+
+        /// Also tag Vue render function `h('div', {...})` calls
+        #[arg(long)]
+        vue_h: bool,
+
+        /// Give elements rendered in `.map()` callbacks IDs that include their `key`
+        #[arg(long)]
+        dynamic_keys: bool,
+
+        /// Write IDs as calls to this helper: data-ast-id={aid("el-...")}
+        #[arg(long, value_name = "NAME")]
+        id_helper: Option<String>,
+
+        /// Import the helper from this module in files that use it
+        #[arg(long, value_name = "MODULE", requires = "id_helper")]
+        id_helper_import: Option<String>,
+
+        /// Skip JSX rendered only when this expression holds (`cond && ...`,
+        /// `cond ? ... :`, `if (cond)`); can be repeated
+        #[arg(long = "skip-condition", value_name = "EXPR")]
+        skip_conditions: Vec<String>,
+
+        /// Also mark the root element each component returns with the
+        /// component's name, in this attribute
+        #[arg(long, value_name = "ATTR", num_args = 0..=1, default_missing_value = "data-ast-component")]
+        component_attr: Option<String>,
         
-        /// Output directory (default: in-place)
-        #[arg(short, long)]
-        output: Option<PathBuf>,
-        
-        /// Verbose output
-        #[arg(short, long)]
-        verbose: bool,
+        #[command(flatten)]
+        run: RunArgs,
     },
     
     /// Process XML files
     Xml {
-        /// Input file or glob pattern
-        #[arg(value_name = "PATH")]
-        path: String,
+        /// Input file or glob pattern (default: the paths of --profile)
+        #[arg(value_name = "PATH", required_unless_present = "profile")]
+        path: Option<String>,
         
         /// Attribute name for ID
         #[arg(long, default_value = "data-ast-id")]
@@ -83,21 +167,27 @@ enum Commands {
         /// CSS selector for target elements
         #[arg(long)]
         selector: Option<String>,
+
+        /// Record the tool version and a hash of the options in each file,
+        /// in a processing instruction before the root element or in a
+        /// data-ast-run attribute on it
+        #[arg(long, value_enum)]
+        metadata: Option<Metadata>,
+
+        /// Also record the time of the run with --metadata, taken from
+        /// SOURCE_DATE_EPOCH when set
+        #[arg(long, requires = "metadata")]
+        metadata_timestamp: bool,
         
-        /// Output directory (default: in-place)
-        #[arg(short, long)]
-        output: Option<PathBuf>,
-        
-        /// Verbose output
-        #[arg(short, long)]
-        verbose: bool,
+        #[command(flatten)]
+        run: RunArgs,
     },
     
     /// Process HTML files
     Html {
-        /// Input file or glob pattern
-        #[arg(value_name = "PATH")]
-        path: String,
+        /// Input file or glob pattern (default: the paths of --profile)
+        #[arg(value_name = "PATH", required_unless_present = "profile")]
+        path: Option<String>,
         
         /// Attribute name for ID
         #[arg(long, default_value = "data-ast-id")]
@@ -118,21 +208,24 @@ enum Commands {
         /// CSS selector for target elements
         #[arg(long)]
         selector: Option<String>,
+
+        /// Leave elements inside <template> content untouched
+        #[arg(long)]
+        skip_templates: bool,
+
+        /// Leave <code> elements and their content untouched, like <pre> content
+        #[arg(long)]
+        skip_code: bool,
         
-        /// Output directory (default: in-place)
-        #[arg(short, long)]
-        output: Option<PathBuf>,
-        
-        /// Verbose output
-        #[arg(short, long)]
-        verbose: bool,
+        #[command(flatten)]
+        run: RunArgs,
     },
     
     /// Auto-detect file type and process
     Auto {
-        /// Input file or glob pattern
-        #[arg(value_name = "PATH")]
-        path: String,
+        /// Input file or glob pattern (default: the paths of --profile)
+        #[arg(value_name = "PATH", required_unless_present = "profile")]
+        path: Option<String>,
         
         /// Attribute name for ID
         #[arg(long, default_value = "data-ast-id")]
@@ -149,261 +242,2278 @@ enum Commands {
         /// Overwrite existing IDs
         #[arg(long)]
         overwrite: bool,
+
+        /// CSS selector for target elements
+        #[arg(long)]
+        selector: Option<String>,
+
+        /// Tags to include (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        include: Vec<String>,
+
+        /// Tags to exclude (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        exclude: Vec<String>,
+
+        /// Only tag elements with one of these classes (comma-separated)
+        #[arg(long, value_delimiter = ',', value_name = "CLASSES")]
+        include_class: Vec<String>,
+
+        /// Leave elements inside <template> content untouched (HTML files)
+        #[arg(long)]
+        skip_templates: bool,
+
+        /// Leave <code> elements and their content untouched, like <pre> content (HTML files)
+        #[arg(long)]
+        skip_code: bool,
         
-        /// Output directory (default: in-place)
+        #[command(flatten)]
+        run: RunArgs,
+    },
+
+    /// Set or replace the ID on the elements matching a selector (HTML and XML)
+    SetId {
+        /// File to edit in place
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// CSS selector or XPath (starting with `/`) for the elements to change;
+        /// HTML takes any CSS selector, XPath and XML the subset `select` supports
+        #[arg(value_name = "SELECTOR")]
+        selector: String,
+
+        /// ID to write; further matches get `-2`, `-3`, ... suffixes
+        #[arg(value_name = "ID")]
+        id: String,
+
+        /// Attribute name for ID
+        #[arg(long, default_value = "data-ast-id")]
+        attr: String,
+
+        /// Verbose output
         #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Copy IDs from a tagged reference file to the matching elements of a
+    /// structurally similar file, such as an A/B variant or a translation
+    Sync {
+        /// Tagged file to copy IDs from
+        #[arg(value_name = "REFERENCE")]
+        reference: PathBuf,
+
+        /// File to copy IDs to (edited in place unless --output is given)
+        #[arg(value_name = "TARGET")]
+        target: PathBuf,
+
+        /// Write the result here instead of editing TARGET
+        #[arg(short, long, value_name = "FILE")]
         output: Option<PathBuf>,
-        
+
+        /// Attribute name for ID
+        #[arg(long, default_value = "data-ast-id")]
+        attr: String,
+
+        /// Replace IDs the target's matching elements already have
+        #[arg(long)]
+        overwrite: bool,
+
         /// Verbose output
         #[arg(short, long)]
         verbose: bool,
     },
-}
 
-#[derive(Copy, Clone, Debug, ValueEnum)]
-enum Strategy {
-    Hash,
-    Slug,
-    Path,
-}
+    /// Print the location of the elements carrying an ID, as file:line:col
+    Find {
+        /// ID to look for
+        #[arg(value_name = "ID")]
+        id: String,
 
-impl From<Strategy> for IdStrategy {
-    fn from(s: Strategy) -> Self {
-        match s {
-            Strategy::Hash => IdStrategy::Hash,
-            Strategy::Slug => IdStrategy::Slug,
-            Strategy::Path => IdStrategy::Path,
-        }
-    }
-}
+        /// File, directory or glob pattern to search (default: current directory)
+        #[arg(value_name = "PATH")]
+        path: Option<String>,
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    
-    match cli.command {
-        Commands::Jsx { path, attr, strategy, prefix, overwrite, include, exclude, output, verbose } => {
-            let options = IdOptions {
-                attr,
-                strategy: strategy.into(),
-                prefix,
-                overwrite,
-                selector: None,
-                include,
-                exclude,
-            };
-            process_files(&path, FileType::Jsx, &options, output.as_deref(), verbose)
-        }
-        Commands::Xml { path, attr, strategy, prefix, overwrite, selector, output, verbose } => {
-            let options = IdOptions {
-                attr,
-                strategy: strategy.into(),
-                prefix,
-                overwrite,
-                selector,
-                include: Vec::new(),
-                exclude: Vec::new(),
-            };
-            process_files(&path, FileType::Xml, &options, output.as_deref(), verbose)
-        }
-        Commands::Html { path, attr, strategy, prefix, overwrite, selector, output, verbose } => {
-            let options = IdOptions {
-                attr,
-                strategy: strategy.into(),
-                prefix,
-                overwrite,
-                selector,
-                include: Vec::new(),
-                exclude: Vec::new(),
-            };
-            process_files(&path, FileType::Html, &options, output.as_deref(), verbose)
-        }
-        Commands::Auto { path, attr, strategy, prefix, overwrite, output, verbose } => {
-            let options = IdOptions {
-                attr,
-                strategy: strategy.into(),
-                prefix,
-                overwrite,
-                selector: None,
-                include: Vec::new(),
-                exclude: Vec::new(),
-            };
-            process_files(&path, FileType::Auto, &options, output.as_deref(), verbose)
-        }
-    }
-}
+        /// Match every ID starting with ID
+        #[arg(long)]
+        prefix: bool,
 
-#[derive(Debug, Clone, Copy)]
-enum FileType {
-    Jsx,
-    Xml,
-    Html,
-    Auto,
-}
+        /// Search the files of this manifest that list a matching ID instead
+        #[arg(long, value_name = "FILE", conflicts_with = "path")]
+        manifest: Option<PathBuf>,
 
-fn process_files(
-    path_pattern: &str,
-    file_type: FileType,
-    options: &IdOptions,
-    output_dir: Option<&Path>,
-    verbose: bool,
-) -> Result<()> {
-    let files = find_files(path_pattern)?;
-    
-    if files.is_empty() {
-        eprintln!("{} No files found matching: {}", "✗".red(), path_pattern);
-        return Ok(());
-    }
-    
-    if verbose {
-        println!("{} Found {} file(s) to process", "→".blue(), files.len());
-    }
-    
-    let mut success_count = 0;
-    let mut error_count = 0;
-    
-    for file_path in &files {
-        match process_single_file(file_path, file_type, options, output_dir, verbose) {
-            Ok(_) => {
-                success_count += 1;
-                if verbose {
-                    println!("{} Processed: {}", "✓".green(), file_path.display());
-                }
-            }
-            Err(e) => {
-                error_count += 1;
-                eprintln!("{} Error processing {}: {}", "✗".red(), file_path.display(), e);
-            }
-        }
-    }
-    
-    println!(
-        "\n{} Processed {} file(s) successfully, {} error(s)",
-        if error_count == 0 { "✓".green() } else { "⚠".yellow() },
-        success_count,
-        error_count
-    );
-    
-    if error_count > 0 {
-        std::process::exit(1);
-    }
-    
-    Ok(())
-}
+        /// Attribute name for ID (taken from the manifest with --manifest)
+        #[arg(long, default_value = "data-ast-id")]
+        attr: String,
 
-fn process_single_file(
-    file_path: &Path,
-    file_type: FileType,
-    options: &IdOptions,
-    output_dir: Option<&Path>,
-    verbose: bool,
-) -> Result<()> {
-    let content = fs::read_to_string(file_path)
-        .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
-    
-    let detected_type = if matches!(file_type, FileType::Auto) {
-        detect_file_type(file_path, &content)
-    } else {
-        file_type
-    };
-    
-    if verbose {
-        println!("  Processing as: {:?}", detected_type);
-    }
-    
-    let processed = match detected_type {
-        FileType::Jsx => {
-            let mut processor = JsxProcessor::new();
-            processor.process(&content, options).map_err(anyhow::Error::msg)?
-        }
-        FileType::Xml => {
-            let mut processor = XmlProcessor::new();
-            processor.process(&content, options).map_err(anyhow::Error::msg)?
-        }
-        FileType::Html => {
-            let mut processor = HtmlProcessor::new();
-            processor.process(&content, options).map_err(anyhow::Error::msg)?
-        }
-        FileType::Auto => unreachable!(),
-    };
-    
-    let output_path = if let Some(dir) = output_dir {
-        fs::create_dir_all(dir)
-            .with_context(|| format!("Failed to create output directory: {}", dir.display()))?;
-        dir.join(file_path.file_name().unwrap())
-    } else {
-        file_path.to_path_buf()
-    };
-    
-    fs::write(&output_path, processed)
-        .with_context(|| format!("Failed to write file: {}", output_path.display()))?;
-    
-    Ok(())
-}
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
 
-fn find_files(pattern: &str) -> Result<Vec<PathBuf>> {
-    let path = Path::new(pattern);
-    
-    if path.is_file() {
-        return Ok(vec![path.to_path_buf()]);
-    }
-    
-    if path.is_dir() {
-        let patterns = vec![
-            format!("{}/**/*.jsx", pattern),
-            format!("{}/**/*.tsx", pattern),
-            format!("{}/**/*.js", pattern),
-            format!("{}/**/*.ts", pattern),
-            format!("{}/**/*.xml", pattern),
-            format!("{}/**/*.svg", pattern),
-            format!("{}/**/*.html", pattern),
-            format!("{}/**/*.htm", pattern),
-        ];
-        
-        let mut files = Vec::new();
-        for pattern in patterns {
-            for entry in glob(&pattern)? {
-                if let Ok(path) = entry {
-                    files.push(path);
-                }
-            }
-        }
-        return Ok(files);
-    }
-    
-    // Treat as glob pattern
-    let mut files = Vec::new();
-    for entry in glob(pattern)? {
-        if let Ok(path) = entry {
-            files.push(path);
-        }
-    }
-    
-    Ok(files)
-}
+    /// Name the element carrying ID in an anchors file, so runs with
+    /// --anchors give every element with its tag and attributes the name
+    Anchor {
+        /// Name to give the element
+        #[arg(value_name = "NAME")]
+        name: String,
 
-fn detect_file_type(path: &Path, content: &str) -> FileType {
-    // Check by file extension first
-    if let Some(ext) = path.extension() {
-        let ext_str = ext.to_str().unwrap_or("").to_lowercase();
-        match ext_str.as_str() {
-            "jsx" | "tsx" => return FileType::Jsx,
-            "xml" | "svg" => return FileType::Xml,
-            "html" | "htm" => return FileType::Html,
-            _ => {}
-        }
-    }
-    
-    // Check by content
-    let trimmed = content.trim();
-    if trimmed.starts_with("<?xml") || trimmed.starts_with("<svg") {
-        FileType::Xml
-    } else if trimmed.starts_with("<!DOCTYPE") || trimmed.starts_with("<html") {
-        FileType::Html
-    } else if trimmed.contains("jsx") || trimmed.contains("React") || trimmed.contains("=>") {
-        FileType::Jsx
-    } else if trimmed.starts_with("<") {
-        FileType::Html
-    } else {
-        FileType::Jsx
-    }
-}
\ No newline at end of file
+        /// File the element is in
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// ID the element carries now
+        #[arg(value_name = "ID")]
+        id: String,
+
+        /// Anchors file to add the name to, created if missing
+        #[arg(long, value_name = "FILE", default_value = "anchors.json")]
+        anchors: PathBuf,
+
+        /// Attribute name for ID
+        #[arg(long, default_value = "data-ast-id")]
+        attr: String,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Decode how an ID was generated (strategy, prefix, path, duplicate
+    /// counter) and, with a manifest or ID cache, where it was assigned
+    Explain {
+        /// ID to explain
+        #[arg(value_name = "ID")]
+        id: String,
+
+        /// Prefix the IDs were generated with
+        #[arg(long, default_value = "el-")]
+        prefix: String,
+
+        /// Environment token the IDs were written with (default: a leading
+        /// "token:", such as "staging:")
+        #[arg(long, value_name = "TOKEN", default_value = "")]
+        env_prefix: String,
+
+        /// Manifest written by a run with --manifest
+        #[arg(long, value_name = "FILE")]
+        manifest: Option<PathBuf>,
+
+        /// ID cache written by a run with --id-cache
+        #[arg(long, value_name = "FILE")]
+        id_cache: Option<PathBuf>,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Print the ID and text of every element matching a CSS selector or XPath
+    Select {
+        /// CSS selector, or XPath starting with / (e.g. "//form//button[@type='submit']")
+        #[arg(value_name = "QUERY")]
+        query: String,
+
+        /// File, directory or glob pattern to search (default: current directory)
+        #[arg(value_name = "PATH")]
+        path: Option<String>,
+
+        /// Attribute name for ID
+        #[arg(long, default_value = "data-ast-id")]
+        attr: String,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Report how many elements required by a selector policy carry an ID
+    Coverage {
+        /// Input file, directory or glob pattern
+        #[arg(value_name = "PATH")]
+        path: String,
+
+        /// Selector list of the elements that must have the attribute, e.g.
+        /// "button, a, input, [role]"
+        #[arg(long)]
+        policy: String,
+
+        /// Attribute name for ID
+        #[arg(long, default_value = "data-ast-id")]
+        attr: String,
+
+        /// Exit with an error when aggregate coverage is below this percentage
+        #[arg(long, value_name = "PERCENT")]
+        min_coverage: Option<f64>,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// List the IDs added, removed or moved to a changed element between two
+    /// versions of a file, or between two manifests (both `.json`)
+    DiffIds {
+        /// Old version
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+
+        /// New version
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
+
+        /// Attribute name for ID (files only)
+        #[arg(long, default_value = "data-ast-id")]
+        attr: String,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Export the text of HTML elements with an ID, for translation
+    ExportText {
+        /// Input file, directory or glob pattern
+        #[arg(value_name = "PATH")]
+        path: String,
+
+        /// Write to FILE instead of stdout; CSV when it ends in `.csv`, JSON
+        /// otherwise
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Attribute name for ID
+        #[arg(long, default_value = "data-ast-id")]
+        attr: String,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Replace the text of HTML elements with translated text keyed by ID
+    ImportText {
+        /// Input file, directory or glob pattern (rewritten in place)
+        #[arg(value_name = "PATH")]
+        path: String,
+
+        /// CSV or JSON file written by export-text
+        #[arg(long, value_name = "FILE")]
+        texts: PathBuf,
+
+        /// Attribute name for ID
+        #[arg(long, default_value = "data-ast-id")]
+        attr: String,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Add IDs to Storybook story files and write an index of the IDs each
+    /// story renders
+    Stories {
+        /// Input file, directory or glob pattern; only `*.stories.{tsx,jsx,ts,js}`
+        /// files are processed
+        #[arg(value_name = "PATH")]
+        path: String,
+
+        /// Write the index to FILE instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Attribute name for ID
+        #[arg(long, default_value = "data-ast-id")]
+        attr: String,
+
+        /// ID generation strategy
+        #[arg(long, value_enum, default_value = "hash")]
+        strategy: Strategy,
+
+        /// ID prefix
+        #[arg(long, default_value = "el-")]
+        prefix: String,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Generate a TypeScript module of selector constants from a manifest, for
+    /// Playwright or Cypress tests
+    GenSelectors {
+        /// Manifest written by a run with --manifest
+        #[arg(value_name = "MANIFEST")]
+        manifest: PathBuf,
+
+        /// Write the module to FILE instead of stdout, e.g. selectors.ts
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// How constants are named
+        #[arg(long, value_enum, default_value = "id")]
+        naming: SelectorNaming,
+
+        /// Remove this prefix from IDs before naming them
+        #[arg(long, value_name = "PREFIX", default_value = "")]
+        strip_prefix: String,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Generate a small JavaScript module for the browser: byAstId(id),
+    /// allAstIds() and warnUntagged(), which reports interactive elements
+    /// added without an ID
+    GenRuntime {
+        /// Write the module to FILE instead of stdout, e.g. ast-ids.js
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Attribute name for ID
+        #[arg(long, default_value = "data-ast-id")]
+        attr: String,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Rewrite Testing Library text queries in test files, e.g.
+    /// getByText("Submit"), to getByTestId queries for the matching ID
+    CodemodTests {
+        /// Test files: file, directory or glob pattern (rewritten in place)
+        #[arg(value_name = "PATH")]
+        path: String,
+
+        /// Manifest whose files are read to map element text to IDs
+        #[arg(long, value_name = "FILE")]
+        manifest: PathBuf,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Print the JSON Schema of the processing options, for validating config
+    /// files and options passed to the JavaScript API
+    OptionsSchema {
+        /// Write the schema to FILE instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Move the hash IDs recorded in a manifest to another scheme, rewriting
+    /// the files and the manifest and writing a JSON map of old to new IDs
+    /// per file for downstream systems
+    MigrateScheme {
+        /// Manifest written by a previous run with --manifest
+        #[arg(value_name = "MANIFEST")]
+        manifest: PathBuf,
+
+        /// Scheme to move to
+        #[arg(long, value_enum, default_value = "v2")]
+        to: Scheme,
+
+        /// Salt of the new scheme
+        #[arg(long, default_value = "")]
+        salt: String,
+
+        /// Salt the IDs were generated with
+        #[arg(long, value_name = "SALT", default_value = "")]
+        from_salt: String,
+
+        /// Write the map of old to new IDs to FILE instead of stdout
+        #[arg(long, value_name = "FILE")]
+        mapping: Option<PathBuf>,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Remove the IDs recorded in a manifest, leaving hand-written IDs untouched
+    Undo {
+        /// Manifest written by a previous run with --manifest
+        #[arg(value_name = "MANIFEST")]
+        manifest: PathBuf,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+}
+
+/// Options controlling how a batch of files is processed and written
+#[derive(Args, Debug, Clone)]
+struct RunArgs {
+    /// Output directory (default: in-place), or `-` for stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Also write an untagged copy of each processed file to this directory,
+    /// checked to be byte for byte the input, so a build can publish both
+    /// variants from one run
+    #[arg(long, value_name = "DIR", requires = "output")]
+    clean_output: Option<PathBuf>,
+
+    /// Verbose output
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Environment token prepended to every new ID when it is written, e.g.
+    /// "staging:"; IDs are generated and kept unique without it, so each
+    /// environment gets the same IDs behind its own token
+    #[arg(long, value_name = "TOKEN", default_value = "")]
+    env_prefix: String,
+
+    /// Level of the taxonomy strategy as NAME=SELECTORS, e.g.
+    /// "area=header, nav, main"; repeat for each level, outermost first
+    /// (default: area, section and component levels)
+    #[arg(long = "taxonomy-level", value_name = "NAME=SELECTORS")]
+    taxonomy_levels: Vec<String>,
+
+    /// Only tag elements whose own text (not that of child elements) matches
+    /// this regular expression, e.g. "[$€£]\s?\d"
+    #[arg(long, value_name = "REGEX")]
+    text_matches: Option<String>,
+
+    /// In XML, anchor comments matching this regular expression, e.g.
+    /// "^\s*section:", with a <?ast-anchor?> processing instruction after each
+    #[arg(long, value_name = "REGEX")]
+    comment_anchors: Option<String>,
+
+    /// Also write where each new ID comes from to this attribute, e.g.
+    /// "data-ast-id-origin"; IDs without it count as written by hand, which
+    /// --overwrite and `undo` leave alone
+    #[arg(long, value_name = "ATTR")]
+    origin_attr: Option<String>,
+
+    /// Also write this attribute next to each new ID, its value made from
+    /// the element: {id}, {tag}, {depth}, {index}, {path}, {context} and
+    /// {attr:NAME}, e.g. "data-ast-depth={depth}"; repeatable
+    #[arg(long = "extra-attr", value_name = "NAME=TEMPLATE")]
+    extra_attrs: Vec<String>,
+
+    /// Scheme of hash IDs; v2 is salted base62 (see migrate-scheme)
+    #[arg(long, value_enum, default_value = "v1")]
+    scheme: Scheme,
+
+    /// Salt of v2 hash IDs
+    #[arg(long, default_value = "")]
+    salt: String,
+
+    /// Cut hash IDs to this many characters (4 at least), lengthening only
+    /// those whose short hash is taken already
+    #[arg(long, value_name = "N")]
+    hash_length: Option<usize>,
+
+    /// Whether tag names in --include, --exclude and --selector match
+    /// regardless of case; auto ignores case in HTML only
+    #[arg(long, value_enum, default_value = "auto")]
+    name_case: Case,
+
+    /// Elements that advance the counter behind path and hash IDs: all
+    /// elements, the same in every format, or only those that get an ID
+    #[arg(long, value_enum, default_value = "all")]
+    counter: Counting,
+
+    /// Restart counter and path at each element carrying this attribute and
+    /// name its IDs after the component it holds, so a component's IDs in
+    /// server-rendered HTML match those in its JSX (which marks its roots)
+    #[arg(long, value_name = "ATTR", num_args = 0..=1, default_missing_value = "data-ast-component")]
+    component_scope: Option<String>,
+
+    /// Prepend a namespace derived from each file's relative path to every ID
+    #[arg(long, value_enum, value_name = "STYLE", num_args = 0..=1, default_missing_value = "slug")]
+    namespace_by_path: Option<Namespace>,
+
+    /// Two-pass mode: reserve every existing ID across all files first, then
+    /// assign new IDs that are unique across the whole run
+    #[arg(long, conflicts_with = "overwrite")]
+    reserve: bool,
+
+    /// JSON file recording assigned IDs by element fingerprint; elements that
+    /// only moved keep their previous ID on later runs
+    #[arg(long, value_name = "FILE")]
+    id_cache: Option<PathBuf>,
+
+    /// JSON file naming elements by fingerprint (see the `anchor` command);
+    /// the first element of each file with a listed fingerprint gets the
+    /// name as its ID, which --reserve would allow once per run only
+    #[arg(long, value_name = "FILE", conflicts_with = "reserve")]
+    anchors: Option<PathBuf>,
+
+    /// JSON manifest listing every ID written per output file (used by `undo`)
+    #[arg(long, value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// Point the <head> of HTML pages to the manifest at this URL or path,
+    /// with a <meta name="ast-append-ids"> runtime tooling can look up
+    #[arg(long, value_name = "URL", requires = "manifest")]
+    manifest_meta: Option<String>,
+
+    /// Write an NDJSON record per element that got, kept or replaced an ID
+    /// (`-` for stdout)
+    #[arg(long, value_name = "FILE")]
+    change_log: Option<PathBuf>,
+
+    /// Log, for every element the filters see, whether it was tagged or
+    /// which of --selector, --text-matches, --include, --exclude,
+    /// --include-class, the range or an existing ID left it alone
+    #[arg(long)]
+    explain_filters: bool,
+
+    /// Fail the run when --selector, or --include, matched no element in any
+    /// of the files, rather than quietly tagging nothing
+    #[arg(long)]
+    require_matches: bool,
+
+    /// Dry run: write a report of the changes instead of the files, e.g.
+    /// `--report html report.html`; the manifest and ID cache are left as they are
+    #[arg(long, num_args = 2, value_names = ["FORMAT", "FILE"])]
+    report: Option<Vec<String>>,
+
+    /// What a run writes: the processed files, or instead of them a single
+    /// unified diff of all changes or a JSON list of structural edits, to
+    /// --emit-file; the manifest and ID cache are then left as they are
+    #[arg(long, value_enum, value_name = "KIND", default_value = "files")]
+    emit: Emit,
+
+    /// Where `--emit patch` and `--emit edits` write (`-` for stdout)
+    #[arg(long, value_name = "FILE", default_value = "-")]
+    emit_file: PathBuf,
+
+    /// Debug check: process each output a second time and fail the file if
+    /// that would change it
+    #[arg(long)]
+    assert_idempotent: bool,
+
+    /// Use the options and paths of this profile of the config file; options
+    /// given on the command line take precedence
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Config file defining the profiles and per-path rules
+    #[arg(long, value_name = "FILE", default_value = config::DEFAULT_CONFIG_FILE)]
+    config: PathBuf,
+
+    /// Treat each PATH as a workspace: find its packages (directories with a
+    /// package.json or Cargo.toml), apply the rules of the config file in each
+    /// package to its files, and report the outcome per package. IDs are
+    /// namespaced by package, e.g. `webapp--el-...`
+    #[arg(long)]
+    project: bool,
+
+    /// With --project, leave the package name out of the IDs
+    #[arg(long, requires = "project")]
+    no_package_namespace: bool,
+
+    /// Number of leading lines searched for `ast-append-ids: on|off` file pragmas
+    #[arg(long, value_name = "N", default_value_t = pragma::DEFAULT_PRAGMA_LINES)]
+    pragma_lines: usize,
+
+    /// Memory-map input files instead of reading them into memory; files that
+    /// cannot be mapped are read normally
+    #[arg(long)]
+    mmap: bool,
+
+    /// With `auto`, process the files matching GLOB as TYPE (jsx, xml or html)
+    /// instead of detecting their type, e.g. "src/legacy/*.js=jsx"; can be
+    /// repeated, the last matching one wins
+    #[arg(long = "force-type", value_name = "GLOB=TYPE", value_parser = parse_forced_type)]
+    force_types: Vec<ForcedType>,
+
+    /// Process minified files and generated bundles instead of skipping them
+    #[arg(long)]
+    allow_minified: bool,
+
+    /// Process files in node_modules directories, which are left out even
+    /// when a path or glob names them, e.g. to tag vendored email templates
+    #[arg(long)]
+    include_node_modules: bool,
+
+    /// Leave out files larger than this, e.g. 10M (suffixes K, M and G)
+    #[arg(long, value_name = "SIZE", value_parser = limits::parse_size)]
+    max_file_size: Option<u64>,
+
+    /// Leave out files with more elements than this
+    #[arg(long, value_name = "N")]
+    max_elements: Option<usize>,
+
+    /// What happens to files over --max-file-size or --max-elements
+    #[arg(long, value_enum, value_name = "ACTION", default_value = "skip")]
+    on_limit: LimitAction,
+
+    /// Order files are processed in, which decides the counters of IDs shared
+    /// through --reserve and the order of logs; ties are broken by path
+    #[arg(long, value_enum, default_value = "path")]
+    order: FileOrder,
+
+    /// How symbolic links are treated: followed and written through to their
+    /// target, skipped along with files in linked directories, or replaced by
+    /// a regular file when written in place
+    #[arg(long, value_enum, value_name = "POLICY", default_value = "follow")]
+    symlinks: SymlinkPolicy,
+
+    /// How files with several hard links are written in place: shared keeps
+    /// one file so every link sees the change, break gives the processed path
+    /// its own copy
+    #[arg(long, value_enum, value_name = "POLICY", default_value = "shared")]
+    hardlinks: HardlinkPolicy,
+
+    /// Take advisory locks so concurrent runs over the same files do not
+    /// interleave their writes: `files` locks each file while it is
+    /// processed, `run` holds --lock-file for the whole run
+    #[arg(long, value_enum, value_name = "MODE", default_value = "none")]
+    lock: LockMode,
+
+    /// Lock file held by `--lock run`
+    #[arg(long, value_name = "FILE", default_value = ".ast-append-ids.lock")]
+    lock_file: PathBuf,
+
+    /// What happens when another run holds the lock
+    #[arg(long, value_enum, value_name = "ACTION", default_value = "wait")]
+    on_locked: LockedAction,
+
+    /// Only tag elements starting on lines FIRST to LAST (1-based, both
+    /// included), leaving the rest of each file byte-identical
+    #[arg(long, value_name = "FIRST-LAST", value_parser = parse_line_span, conflicts_with = "byte_range")]
+    lines: Option<(usize, usize)>,
+
+    /// Only tag elements starting in bytes START to END (END excluded),
+    /// leaving the rest of each file byte-identical
+    #[arg(long, value_name = "START-END", value_parser = parse_span)]
+    byte_range: Option<(usize, usize)>,
+
+    /// Keep the modification time of each input on its output (permissions
+    /// are always kept)
+    #[arg(long)]
+    preserve_mtime: bool,
+
+    /// Keep the owner and group of each input on its output (Unix only,
+    /// usually requires root)
+    #[arg(long)]
+    preserve_owner: bool,
+}
+
+impl RunArgs {
+    /// The id options shared by all formats; each format command fills in
+    /// its own arguments on top
+    fn to_id_options(&self) -> IdOptions {
+        IdOptions {
+            component_scope: self.component_scope.clone(),
+            env_prefix: self.env_prefix.clone(),
+            taxonomy_levels: self.taxonomy_levels.clone(),
+            text_matches: self.text_matches.clone(),
+            comment_anchors: self.comment_anchors.clone(),
+            origin_attr: self.origin_attr.clone(),
+            extra_attrs: self.extra_attrs.clone(),
+            scheme: self.scheme.into(),
+            salt: self.salt.clone(),
+            hash_length: self.hash_length,
+            name_case: self.name_case.into(),
+            counter: self.counter.into(),
+            manifest_meta: self.manifest_meta.clone(),
+            ..IdOptions::default()
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum LockMode {
+    /// No locking
+    None,
+    /// Lock each file while it is processed
+    Files,
+    /// Lock --lock-file for the whole run
+    Run,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum LockedAction {
+    /// Wait for the lock to be released
+    Wait,
+    /// Skip the file, or the whole run, with a warning
+    Skip,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// Write the processed files
+    Files,
+    /// Write a unified diff instead, for `git apply`
+    Patch,
+    /// Write the edits as JSON instead, each an element locator and the
+    /// attribute to set
+    Edits,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum SymlinkPolicy {
+    /// Process linked files and directories, writing to the link target
+    Follow,
+    /// Leave out links and everything reached through them
+    Skip,
+    /// Process links, replacing a linked file by a regular one on write
+    Replace,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum HardlinkPolicy {
+    /// Rewrite the file itself, so all its links change
+    Shared,
+    /// Write a new file, so other links keep the old content
+    Break,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum FileOrder {
+    /// By path
+    Path,
+    /// Least recently modified first
+    Mtime,
+    /// Smallest first
+    Size,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum LimitAction {
+    /// Skip the file with a warning
+    Skip,
+    /// Fail the file, so the run exits with an error
+    Error,
+}
+
+/// A `--force-type` argument
+#[derive(Debug, Clone)]
+struct ForcedType {
+    glob: glob::Pattern,
+    format: Format,
+}
+
+/// Parses a `START-END` span, e.g. `120-240`
+fn parse_span(value: &str) -> Result<(usize, usize), String> {
+    let (start, end) = value.split_once('-').ok_or("expected START-END, e.g. 120-240")?;
+    let number = |part: &str| part.trim().parse::<usize>().map_err(|_| format!("'{}' is not a number", part));
+    let (start, end) = (number(start)?, number(end)?);
+    if start > end {
+        return Err(format!("start {} is after end {}", start, end));
+    }
+    Ok((start, end))
+}
+
+fn parse_line_span(value: &str) -> Result<(usize, usize), String> {
+    match parse_span(value)? {
+        (0, _) => Err("lines are numbered from 1".to_string()),
+        span => Ok(span),
+    }
+}
+
+fn parse_forced_type(value: &str) -> Result<ForcedType, String> {
+    let (glob, format) = value.split_once('=').ok_or("expected GLOB=TYPE")?;
+    let format = match format {
+        "jsx" => Format::Jsx,
+        "xml" => Format::Xml,
+        "html" => Format::Html,
+        _ => return Err(format!("unknown type '{}' (expected jsx, xml or html)", format)),
+    };
+    let glob = glob::Pattern::new(glob).map_err(|e| format!("invalid glob '{}': {}", glob, e))?;
+    Ok(ForcedType { glob, format })
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum LogFormat {
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+fn init_logging(level: LevelFilter, format: LogFormat) {
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_target(false);
+    match format {
+        LogFormat::Text => builder.without_time().init(),
+        LogFormat::Json => builder.json().with_current_span(true).with_span_list(false).init(),
+    }
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Namespace {
+    Slug,
+    Hash,
+}
+
+impl From<Namespace> for NamespaceStyle {
+    fn from(n: Namespace) -> Self {
+        match n {
+            Namespace::Slug => NamespaceStyle::Slug,
+            Namespace::Hash => NamespaceStyle::Hash,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Strategy {
+    Hash,
+    Slug,
+    Path,
+    Taxonomy,
+}
+
+impl From<Strategy> for IdStrategy {
+    fn from(s: Strategy) -> Self {
+        match s {
+            Strategy::Hash => IdStrategy::Hash,
+            Strategy::Slug => IdStrategy::Slug,
+            Strategy::Path => IdStrategy::Path,
+            Strategy::Taxonomy => IdStrategy::Taxonomy,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Scheme {
+    V1,
+    V2,
+}
+
+impl From<Scheme> for IdScheme {
+    fn from(s: Scheme) -> Self {
+        match s {
+            Scheme::V1 => IdScheme::V1,
+            Scheme::V2 => IdScheme::V2,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Case {
+    Auto,
+    Sensitive,
+    Insensitive,
+}
+
+impl From<Case> for NameCase {
+    fn from(n: Case) -> Self {
+        match n {
+            Case::Auto => NameCase::Auto,
+            Case::Sensitive => NameCase::Sensitive,
+            Case::Insensitive => NameCase::Insensitive,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Counting {
+    All,
+    Processed,
+}
+
+impl From<Counting> for CounterMode {
+    fn from(c: Counting) -> Self {
+        match c {
+            Counting::All => CounterMode::All,
+            Counting::Processed => CounterMode::Processed,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Metadata {
+    Pi,
+    Attribute,
+}
+
+impl From<Metadata> for RunMetadata {
+    fn from(m: Metadata) -> Self {
+        match m {
+            Metadata::Pi => RunMetadata::Pi,
+            Metadata::Attribute => RunMetadata::Attribute,
+        }
+    }
+}
+
+/// Seconds since the Unix epoch for --metadata-timestamp; SOURCE_DATE_EPOCH
+/// keeps reproducible builds reproducible
+fn run_timestamp() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH").ok().and_then(|value| value.parse().ok()).unwrap_or_else(|| {
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+    })
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum SelectorNaming {
+    /// From the ID: login-submit -> LOGIN_SUBMIT
+    Id,
+    /// From the file name and the ID: LOGIN_FORM_LOGIN_SUBMIT
+    FileId,
+    /// From the file name and the tag: LOGIN_FORM_BUTTON, LOGIN_FORM_BUTTON_2
+    FileTag,
+}
+
+impl From<SelectorNaming> for Naming {
+    fn from(naming: SelectorNaming) -> Self {
+        match naming {
+            SelectorNaming::Id => Naming::Id,
+            SelectorNaming::FileId => Naming::FileId,
+            SelectorNaming::FileTag => Naming::FileTag,
+        }
+    }
+}
+
+pub fn main() -> Result<()> {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let explicit = command_line_args(&matches);
+
+    let verbose = match &cli.command {
+        Commands::Jsx { run, .. }
+        | Commands::Xml { run, .. }
+        | Commands::Html { run, .. }
+        | Commands::Auto { run, .. } => run.verbose,
+        Commands::SetId { verbose, .. }
+        | Commands::Sync { verbose, .. }
+        | Commands::Find { verbose, .. }
+        | Commands::Anchor { verbose, .. }
+        | Commands::Explain { verbose, .. }
+        | Commands::Select { verbose, .. }
+        | Commands::Coverage { verbose, .. }
+        | Commands::DiffIds { verbose, .. }
+        | Commands::ExportText { verbose, .. }
+        | Commands::ImportText { verbose, .. }
+        | Commands::Stories { verbose, .. }
+        | Commands::GenSelectors { verbose, .. }
+        | Commands::GenRuntime { verbose, .. }
+        | Commands::CodemodTests { verbose, .. }
+        | Commands::OptionsSchema { verbose, .. }
+        | Commands::MigrateScheme { verbose, .. }
+        | Commands::Undo { verbose, .. } => *verbose,
+    };
+    let default_level = if verbose { LogLevel::Debug } else { LogLevel::Info };
+    init_logging(cli.log_level.unwrap_or(default_level).into(), cli.log_format);
+    
+    match cli.command {
+        Commands::Jsx { path, attr, strategy, prefix, overwrite, include, exclude, include_class, template_tags, vue_h, dynamic_keys, id_helper, id_helper_import, skip_conditions, component_attr, run } => {
+            let options = IdOptions {
+                attr,
+                strategy: strategy.into(),
+                prefix,
+                overwrite,
+                include,
+                exclude,
+                include_class,
+                template_tags,
+                vue_h,
+                dynamic_keys,
+                id_helper,
+                id_helper_import,
+                skip_conditions,
+                component_attr,
+                ..run.to_id_options()
+            };
+            let setup = apply_config(options, path, &run, &explicit)?;
+            process_files(&setup.paths, FileType::Jsx, &setup.options, &setup.rules, &setup.packages, &run)
+        }
+        Commands::Xml { path, attr, strategy, prefix, overwrite, selector, metadata, metadata_timestamp, run } => {
+            let options = IdOptions {
+                attr,
+                strategy: strategy.into(),
+                prefix,
+                overwrite,
+                selector,
+                metadata: metadata.map_or(RunMetadata::Off, Into::into),
+                metadata_timestamp: metadata_timestamp.then(run_timestamp),
+                ..run.to_id_options()
+            };
+            let setup = apply_config(options, path, &run, &explicit)?;
+            process_files(&setup.paths, FileType::Xml, &setup.options, &setup.rules, &setup.packages, &run)
+        }
+        Commands::Html { path, attr, strategy, prefix, overwrite, selector, skip_templates, skip_code, run } => {
+            let options = IdOptions {
+                attr,
+                strategy: strategy.into(),
+                prefix,
+                overwrite,
+                selector,
+                templates: !skip_templates,
+                skip_code,
+                ..run.to_id_options()
+            };
+            let setup = apply_config(options, path, &run, &explicit)?;
+            process_files(&setup.paths, FileType::Html, &setup.options, &setup.rules, &setup.packages, &run)
+        }
+        Commands::Auto { path, attr, strategy, prefix, overwrite, selector, include, exclude, include_class, skip_templates, skip_code, run } => {
+            let options = IdOptions {
+                attr,
+                strategy: strategy.into(),
+                prefix,
+                overwrite,
+                selector,
+                include,
+                exclude,
+                include_class,
+                templates: !skip_templates,
+                skip_code,
+                ..run.to_id_options()
+            };
+            let setup = apply_config(options, path, &run, &explicit)?;
+            process_files(&setup.paths, FileType::Auto, &setup.options, &setup.rules, &setup.packages, &run)
+        }
+        Commands::SetId { file, selector, id, attr, .. } => set_id::run(&file, &selector, &attr, &id),
+        Commands::Sync { reference, target, output, attr, overwrite, .. } => {
+            sync::run(&reference, &target, output.as_deref(), &attr, overwrite)
+        }
+        Commands::Find { id, path, prefix, manifest, attr, .. } => {
+            find::run(&id, path.as_deref(), manifest.as_deref(), &attr, prefix)
+        }
+        Commands::Anchor { name, file, id, anchors, attr, .. } => anchor::run(&name, &file, &id, &anchors, &attr),
+        Commands::Explain { id, prefix, env_prefix, manifest, id_cache, .. } => {
+            explain::run(&id, &prefix, &env_prefix, manifest.as_deref(), id_cache.as_deref())
+        }
+        Commands::Select { query, path, attr, .. } => select::run(&query, path.as_deref().unwrap_or("."), &attr),
+        Commands::Coverage { path, policy, attr, min_coverage, .. } => {
+            coverage::run(&path, &policy, &attr, min_coverage)
+        }
+        Commands::DiffIds { old, new, attr, .. } => diff_ids::run(&old, &new, &attr),
+        Commands::ExportText { path, output, attr, .. } => export_text::run(&path, output.as_deref(), &attr),
+        Commands::ImportText { path, texts, attr, .. } => import_text::run(&path, &texts, &attr),
+        Commands::Stories { path, output, attr, strategy, prefix, .. } => {
+            let options = IdOptions {
+                attr,
+                strategy: strategy.into(),
+                prefix,
+                ..IdOptions::default()
+            };
+            stories::run(&path, output.as_deref(), &options)
+        }
+        Commands::GenSelectors { manifest, output, naming, strip_prefix, .. } => {
+            let options = SelectorOptions { naming: naming.into(), strip_prefix };
+            gen_selectors::run(&manifest, output.as_deref(), &options)
+        }
+        Commands::GenRuntime { output, attr, .. } => gen_runtime::run(&attr, output.as_deref()),
+        Commands::CodemodTests { path, manifest, .. } => codemod_tests::run(&path, &manifest),
+        Commands::OptionsSchema { output, .. } => options_schema::run(output.as_deref()),
+        Commands::MigrateScheme { manifest, to, salt, from_salt, mapping, .. } => {
+            migrate_scheme::run(&manifest, to.into(), &from_salt, &salt, mapping.as_deref())
+        }
+        Commands::Undo { manifest, .. } => undo::run(&manifest),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FileType {
+    Jsx,
+    Xml,
+    Html,
+    Auto,
+}
+
+enum FileOutcome {
+    Processed,
+    Skipped(&'static str),
+    /// Skipped because it is unsafe to process, which the user should hear about
+    Guarded(String),
+}
+
+/// Outcomes of the files of one package, for `--project`
+#[derive(Debug, Clone, Default)]
+struct PackageStats {
+    processed: usize,
+    skipped: usize,
+    errors: usize,
+    ids_added: usize,
+}
+
+impl PackageStats {
+    fn record(&mut self, outcome: &Result<FileOutcome>, ids_added: usize) {
+        match outcome {
+            Ok(FileOutcome::Processed) => self.processed += 1,
+            Ok(_) => self.skipped += 1,
+            Err(_) => self.errors += 1,
+        }
+        self.ids_added += ids_added;
+    }
+
+    fn files(&self) -> usize {
+        self.processed + self.skipped + self.errors
+    }
+}
+
+/// State carried across all files of a single run
+struct RunState {
+    generator: IdGenerator,
+    id_cache: Option<IdCache>,
+    anchors: Option<Anchors>,
+    manifest: Option<Manifest>,
+    change_log: Option<Box<dyn Write>>,
+    /// Files collected for `--report`, which are then not written
+    report: Option<Vec<FileReport>>,
+    /// Diff collected for `--emit patch`, which likewise writes no files
+    patch: Option<String>,
+    /// Edits collected for `--emit edits`, which writes no files either
+    edits: Option<Vec<StructuralEdit>>,
+    /// Where processed files go
+    sink: Box<dyn Sink>,
+    /// Where the untagged copies of `--clean-output` go
+    clean_sink: Option<Box<dyn Sink>>,
+    /// IDs added to all files so far
+    ids_added: usize,
+    /// Elements of all files so far that the selector matched, and of
+    /// those, elements with an included tag, for `--require-matches`
+    selector_matches: usize,
+    include_matches: usize,
+    /// The options of the run, compiled for the processors
+    resolved: ResolvedFormats,
+}
+
+/// The options of a run compiled once for each format its files may have
+struct ResolvedFormats(Vec<(Format, Rc<ResolvedOptions>)>);
+
+impl ResolvedFormats {
+    fn new(options: &IdOptions, file_type: FileType) -> Result<Self> {
+        let formats = match file_type {
+            FileType::Auto => vec![Format::Jsx, Format::Xml, Format::Html],
+            file_type => vec![format_of(file_type)],
+        };
+        let resolved = formats
+            .into_iter()
+            .map(|format| {
+                let resolved = ResolvedOptions::new(options, format).map_err(anyhow::Error::msg)?;
+                Ok((format, Rc::new(resolved)))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self(resolved))
+    }
+
+    fn get(&self, format: Format) -> Rc<ResolvedOptions> {
+        let (_, resolved) = self
+            .0
+            .iter()
+            .find(|(compiled, _)| *compiled == format)
+            .expect("the formats of the run's files are all compiled");
+        resolved.clone()
+    }
+}
+
+/// Names of the arguments of the subcommand that were given on the command
+/// line rather than defaulted
+fn command_line_args(matches: &ArgMatches) -> HashSet<String> {
+    let Some((_, matches)) = matches.subcommand() else {
+        return HashSet::new();
+    };
+    matches
+        .ids()
+        .filter(|id| matches.value_source(id.as_str()) == Some(ValueSource::CommandLine))
+        .map(|id| id.to_string())
+        .collect()
+}
+
+/// Whether the option `name` was set by an `explicit` command line argument,
+/// which are named like the options they set
+fn set_on_command_line(name: &str, explicit: &HashSet<String>) -> bool {
+    explicit.contains(name) || (name == "templates" && explicit.contains("skip_templates"))
+}
+
+/// What a processing run works from once the config file is applied
+struct RunSetup {
+    options: IdOptions,
+    paths: Vec<String>,
+    rules: Vec<Rule>,
+    /// Packages found with `--project`
+    packages: Vec<Package>,
+}
+
+/// Applies the config file: the options of `--profile`, overridden by the
+/// `explicit` command line arguments, its paths unless `path` is given, and
+/// the per-path rules minus the options set on the command line. With
+/// `--project`, also finds the packages under the paths and adds the rules of
+/// their own config files.
+fn apply_config(
+    options: IdOptions,
+    path: Option<String>,
+    run: &RunArgs,
+    explicit: &HashSet<String>,
+) -> Result<RunSetup> {
+    // The default file is optional; one named with --config or needed for
+    // --profile is not
+    let config = if run.config.exists() || run.profile.is_some() || explicit.contains("config") {
+        Config::load(&run.config).map_err(anyhow::Error::msg)?
+    } else {
+        Config::default()
+    };
+    let mut rules = config.rules.clone();
+
+    let (options, paths) = match &run.profile {
+        Some(name) => {
+            let profile = profile_options(&config, name, options, path, explicit)?;
+            debug!("Using profile '{}' from {}", name, run.config.display());
+            profile
+        }
+        None => (options, path.into_iter().collect()),
+    };
+    let packages = if run.project {
+        let (packages, package_rules) = project_rules(&paths, &run.config)?;
+        rules.extend(package_rules);
+        packages
+    } else {
+        Vec::new()
+    };
+    for rule in &mut rules {
+        rule.overrides.retain(|name, _| !set_on_command_line(name, explicit));
+    }
+    Ok(RunSetup { options, paths, rules, packages })
+}
+
+/// The options of profile `name`, overridden by the `explicit` command line
+/// arguments, and its paths unless `path` is given
+fn profile_options(
+    config: &Config,
+    name: &str,
+    options: IdOptions,
+    path: Option<String>,
+    explicit: &HashSet<String>,
+) -> Result<(IdOptions, Vec<String>)> {
+    let profile = config.profile(name).map_err(anyhow::Error::msg)?;
+
+    let serde_json::Value::Object(command_line) = serde_json::to_value(&options)? else {
+        unreachable!("options serialize to an object");
+    };
+    let overrides = command_line
+        .into_iter()
+        .filter(|(name, _)| set_on_command_line(name, explicit));
+    let options = config::override_options(&profile.options, overrides)
+        .map_err(|e| anyhow::anyhow!("Profile '{}': {}", name, e))?;
+
+    let paths = match path {
+        Some(path) => vec![path],
+        None if profile.paths.is_empty() => anyhow::bail!("Profile '{}' lists no paths; pass PATH", name),
+        None => profile.paths.clone(),
+    };
+    Ok((options, paths))
+}
+
+/// Packages under `paths` and the rules of their config files, with globs
+/// made relative to the current directory so they only match the package's
+/// files; later rules win, so a package's rules override the top-level ones.
+/// `root_config` is left out, its rules already apply.
+fn project_rules(paths: &[String], root_config: &Path) -> Result<(Vec<Package>, Vec<Rule>)> {
+    let mut packages = Vec::new();
+    for path in paths {
+        let root = walk_root(path);
+        let root = if root.as_os_str().is_empty() { PathBuf::from(".") } else { root };
+        if root.is_dir() {
+            packages.extend(project::detect_packages(&root).map_err(anyhow::Error::msg)?);
+        }
+    }
+    // Found files have no `./` in front, and neither should the roots
+    for package in &mut packages {
+        package.root = without_cur_dir(&package.root);
+    }
+    packages.sort_by(|a, b| a.root.cmp(&b.root));
+    packages.dedup();
+
+    let root_config = fs::canonicalize(root_config).ok();
+    let mut rules = Vec::new();
+    for package in &packages {
+        let config_path = package.root.join(config::DEFAULT_CONFIG_FILE);
+        if !config_path.is_file() || fs::canonicalize(&config_path).ok() == root_config {
+            continue;
+        }
+        let package_config = Config::load(&config_path).map_err(anyhow::Error::msg)?;
+        let prefix = match file_key(&package.root) {
+            key if key.is_empty() => String::new(),
+            key => format!("{}/", glob::Pattern::escape(&key)),
+        };
+        for mut rule in package_config.rules {
+            let glob = format!("{}{}", prefix, rule.glob.as_str());
+            rule.glob = glob::Pattern::new(&glob).with_context(|| format!("Invalid glob in {}", config_path.display()))?;
+            rules.push(rule);
+        }
+        debug!("Using rules of package '{}' from {}", package.name, config_path.display());
+    }
+    Ok((packages, rules))
+}
+
+fn process_files(
+    path_patterns: &[String],
+    file_type: FileType,
+    options: &IdOptions,
+    rules: &[Rule],
+    packages: &[Package],
+    run: &RunArgs,
+) -> Result<()> {
+    options.validate().map_err(anyhow::Error::msg)?;
+    let report_path = report_path(run)?;
+    if run.clean_output.is_some() && run.clean_output == run.output {
+        anyhow::bail!("--clean-output must be a different directory from --output");
+    }
+    let mut files = Vec::new();
+    for pattern in path_patterns {
+        let filter = FileFilter { file_type, forced: &run.force_types, pragma_lines: run.pragma_lines };
+        let found = find_files(pattern, &filter, run.include_node_modules)?;
+        if run.symlinks != SymlinkPolicy::Skip {
+            files.extend(found);
+            continue;
+        }
+        let root = walk_root(pattern);
+        for file in found {
+            if through_symlink(&root, &file) {
+                debug!("Skipped {}: symbolic link", file.display());
+            } else {
+                files.push(file);
+            }
+        }
+    }
+    sort_files(&mut files, run.order);
+    files.dedup();
+    if run.symlinks == SymlinkPolicy::Follow {
+        // A link and its target are the same file; process it once
+        let mut seen = HashSet::new();
+        files.retain(|file| seen.insert(fs::canonicalize(file).unwrap_or_else(|_| file.clone())));
+    }
+    
+    if files.is_empty() {
+        warn!("No files found matching: {}", path_patterns.join(", "));
+        return Ok(());
+    }
+    
+    debug!("Found {} file(s) to process", files.len());
+
+    let _run_lock = if run.lock == LockMode::Run {
+        let wait = run.on_locked == LockedAction::Wait;
+        match FileLock::acquire(&run.lock_file, wait).map_err(anyhow::Error::msg)? {
+            Some(lock) => Some(lock),
+            None => {
+                warn!("Skipped run: {} is locked by another run", run.lock_file.display());
+                return Ok(());
+            }
+        }
+    } else {
+        None
+    };
+    
+    let mut success_count = 0;
+    let mut skipped_count = 0;
+    let mut error_count = 0;
+
+    let mut state = RunState {
+        generator: IdGenerator::new(),
+        id_cache: match &run.id_cache {
+            Some(path) => Some(IdCache::load(path).map_err(anyhow::Error::msg)?),
+            None => None,
+        },
+        anchors: match &run.anchors {
+            Some(path) => Some(Anchors::load(path).map_err(anyhow::Error::msg)?),
+            None => None,
+        },
+        manifest: match &run.manifest {
+            Some(path) => Some(Manifest::load_or_default(path).map_err(anyhow::Error::msg)?),
+            None => None,
+        },
+        change_log: match &run.change_log {
+            Some(path) => Some(open_change_log(path)?),
+            None => None,
+        },
+        report: report_path.is_some().then(Vec::new),
+        patch: (run.emit == Emit::Patch).then(String::new),
+        edits: (run.emit == Emit::Edits).then(Vec::new),
+        sink: output_sink(run.output.as_deref(), run),
+        clean_sink: run.clean_output.as_deref().map(|dir| output_sink(Some(dir), run)),
+        ids_added: 0,
+        selector_matches: 0,
+        include_matches: 0,
+        resolved: ResolvedFormats::new(options, file_type)?,
+    };
+    // Outcomes per package with --project, by index into `packages`
+    let mut package_stats = vec![PackageStats::default(); packages.len()];
+
+    // In reserve mode a single generator, seeded with every existing ID, is
+    // shared by all files so new IDs never clash with hand-written ones.
+    if run.reserve {
+        let reserved = reserve_existing_ids(&files, file_type, options, rules, run.mmap, &mut state.generator);
+        debug!("Reserved {} existing ID(s)", reserved);
+    }
+    
+    for file_path in &files {
+        if run.reserve {
+            state.generator.reset_counter();
+        } else {
+            state.generator = IdGenerator::new();
+        }
+
+        let _span = info_span!("file", path = %file_path.display()).entered();
+        let ids_before = state.ids_added;
+        let package = project::package_for(packages, &without_cur_dir(file_path));
+        let outcome = process_single_file(file_path, file_type, options, rules, package, run, &mut state);
+        if let Some(index) = package.and_then(|package| packages.iter().position(|p| p == package)) {
+            package_stats[index].record(&outcome, state.ids_added - ids_before);
+        }
+        match outcome {
+            Ok(FileOutcome::Processed) => {
+                success_count += 1;
+                debug!("Processed");
+            }
+            Ok(FileOutcome::Skipped(reason)) => {
+                skipped_count += 1;
+                debug!("Skipped: {}", reason);
+            }
+            Ok(FileOutcome::Guarded(reason)) => {
+                skipped_count += 1;
+                warn!("Skipped: {}", reason);
+            }
+            Err(e) => {
+                error_count += 1;
+                error!("{:#}", e);
+            }
+        }
+    }
+
+    for (package, stats) in packages.iter().zip(&package_stats) {
+        if stats.files() > 0 {
+            let root = if package.root.as_os_str().is_empty() { Path::new(".") } else { &package.root };
+            info!(
+                "Package {} ({}): {} processed, {} skipped, {} error(s), {} ID(s) added",
+                package.name,
+                root.display(),
+                stats.processed,
+                stats.skipped,
+                stats.errors,
+                stats.ids_added
+            );
+        }
+    }
+    
+    if error_count == 0 {
+        info!("Processed {} file(s) successfully, {} error(s)", success_count, error_count);
+    } else {
+        warn!("Processed {} file(s) successfully, {} error(s)", success_count, error_count);
+    }
+    if skipped_count > 0 {
+        info!("Skipped {} file(s)", skipped_count);
+    }
+    
+    if let (Some(files), Some(path)) = (&state.report, &report_path) {
+        fs::write(path, report::render_html(files))
+            .with_context(|| format!("Failed to write report: {}", path.display()))?;
+        info!("Wrote report: {}", path.display());
+    }
+    if let Some(diff) = &state.patch {
+        write_emitted(&run.emit_file, diff, "patch")?;
+    }
+    if let Some(edits) = &state.edits {
+        let json = serde_json::to_string_pretty(edits).context("Failed to serialize edits")?;
+        write_emitted(&run.emit_file, &(json + "\n"), "edits")?;
+    }
+    if state.report.is_none() && state.patch.is_none() && state.edits.is_none() {
+        if let (Some(cache), Some(path)) = (&state.id_cache, &run.id_cache) {
+            cache.save(path).map_err(anyhow::Error::msg)?;
+            debug!("Updated ID cache: {}", path.display());
+        }
+
+        if let (Some(manifest), Some(path)) = (&state.manifest, &run.manifest) {
+            manifest.save(path).map_err(anyhow::Error::msg)?;
+            debug!("Wrote manifest: {}", path.display());
+        }
+    }
+
+    if let Some(out) = &mut state.change_log {
+        out.flush().context("Failed to write change log")?;
+    }
+
+    if run.require_matches {
+        require_matches(options, &state, success_count)?;
+    }
+
+    if error_count > 0 {
+        std::process::exit(1);
+    }
+    
+    Ok(())
+}
+
+/// Fails when the selector or the include list matched no element of the
+/// `processed` files, which a typo in either makes a quiet no-op
+fn require_matches(options: &IdOptions, state: &RunState, processed: usize) -> Result<()> {
+    if let Some(selector) = &options.selector {
+        if state.selector_matches == 0 {
+            anyhow::bail!("Selector '{}' matched no elements in {} file(s)", selector, processed);
+        }
+    }
+    if !options.include.is_empty() && state.include_matches == 0 {
+        anyhow::bail!("No element in {} file(s) has an included tag ({})", processed, options.include.join(", "));
+    }
+    Ok(())
+}
+
+fn process_single_file(
+    file_path: &Path,
+    file_type: FileType,
+    options: &IdOptions,
+    rules: &[Rule],
+    package: Option<&Package>,
+    run: &RunArgs,
+    state: &mut RunState,
+) -> Result<FileOutcome> {
+    let _file_lock = if run.lock == LockMode::Files {
+        let target = match &run.output {
+            Some(dir) => dir.join(file_path.file_name().unwrap_or_default()),
+            None => file_path.to_path_buf(),
+        };
+        let wait = run.on_locked == LockedAction::Wait;
+        match FileLock::acquire_for(&target, wait).map_err(anyhow::Error::msg)? {
+            Some(lock) => Some(lock),
+            None => return Ok(FileOutcome::Guarded("locked by another run".to_string())),
+        }
+    } else {
+        None
+    };
+    if looks_binary(file_path) {
+        return Ok(FileOutcome::Guarded("binary file".to_string()));
+    }
+    let limits = Limits { max_file_size: run.max_file_size, max_elements: run.max_elements };
+    // For the size limit only; the sink reads the permissions and times to
+    // keep itself, right before it writes
+    let size = fs::metadata(file_path).map_or(0, |metadata| metadata.len());
+    if let Err(reason) = limits.check_size(size) {
+        return over_limit(reason, run);
+    }
+    let (content, format) = read_with_format(file_path, file_type, run.mmap, &run.force_types)?;
+
+    if pragma::detect_file_pragma(&content, run.pragma_lines) == Some(FilePragma::Off) {
+        return Ok(FileOutcome::Skipped("ast-append-ids: off pragma"));
+    }
+    let detected_type = match format {
+        Format::Script => return Ok(FileOutcome::Skipped("script without JSX")),
+        Format::Vue => return Ok(FileOutcome::Skipped("Vue single-file component (see --force-type)")),
+        format => file_type_of(format),
+    };
+    if !run.allow_minified {
+        if let Some(reason) = detect::generated(Some(file_path), &content) {
+            return Ok(FileOutcome::Guarded(format!("{} (use --allow-minified to process it)", reason)));
+        }
+    }
+    if let Err(reason) = limits.check_elements(&content) {
+        return over_limit(reason, run);
+    }
+    let cache_key = file_key(file_path);
+    let rule_options = config::apply_rules(rules, options, &cache_key).map_err(anyhow::Error::msg)?;
+    // Options a rule changed are compiled for the file; the rest were compiled for the run
+    let resolved = match &rule_options {
+        Some(rule_options) => {
+            Rc::new(ResolvedOptions::new(rule_options, format_of(detected_type)).map_err(anyhow::Error::msg)?)
+        }
+        None => state.resolved.get(format_of(detected_type)),
+    };
+    let options = rule_options.as_ref().unwrap_or(options);
+    let generator = &mut state.generator;
+
+    if state.id_cache.is_some() || state.anchors.is_some() {
+        // IDs kept in place must not be handed to another element
+        if !options.overwrite {
+            for id in existing_ids(&content, detected_type, &options.attr).unwrap_or_default() {
+                generator.reserve(options.without_env_prefix(&id));
+            }
+        }
+        generator.clear_reusable();
+        // Names picked by hand come before IDs of earlier runs
+        if let Some(anchors) = &state.anchors {
+            generator.add_reusable(anchors.assignments());
+        }
+        if let Some(cache) = &state.id_cache {
+            generator.add_reusable(cache.entries_for(&cache_key));
+        }
+    }
+    generator.enable_assignment_log();
+    generator.take_assignments();
+    if state.change_log.is_some() || state.report.is_some() || state.edits.is_some() {
+        generator.enable_change_log();
+    }
+    if run.explain_filters {
+        generator.enable_filter_log();
+    }
+    
+    debug!("Processing as: {:?}", detected_type);
+
+    let range = match (run.lines, run.byte_range) {
+        (Some((first, last)), _) => Some(ast_common::line_range(&content, first, last)),
+        (None, Some((start, end))) => Some(start..end),
+        (None, None) => None,
+    };
+    let package = package.filter(|_| !run.no_package_namespace);
+    let mut file_options;
+    let options = if run.namespace_by_path.is_some() || package.is_some() || range.is_some() {
+        file_options = options.clone();
+        if let Some(style) = run.namespace_by_path {
+            let ns = namespace::namespace_for_path(&relative_path(file_path), style.into());
+            file_options.prefix = namespace::namespaced_prefix(&ns, &file_options.prefix);
+        }
+        // The package goes first: `webapp--pages-login--el-...`
+        if let Some(package) = package {
+            let ns = namespace::namespace_for_package(&package.name);
+            file_options.prefix = namespace::namespaced_prefix(&ns, &file_options.prefix);
+        }
+        file_options.range = range;
+        &file_options
+    } else {
+        options
+    };
+    
+    let before_len = content.len();
+    let output = run_processor(detected_type, &content, options, &resolved, generator)
+        .map_err(anyhow::Error::msg)?;
+    let processed = output.content;
+    state.selector_matches += generator.selected_count();
+    state.include_matches += generator.included_count();
+    log_warnings(&relative_path(file_path).display().to_string(), &content, &output.warnings);
+    // Positions refer to the input, so resolve them while it is still around
+    let raw_changes = generator.take_changes();
+    if run.explain_filters {
+        explain_filters(&relative_path(file_path).display().to_string(), &content, generator.take_decisions());
+    }
+    let file_edits = match &state.edits {
+        Some(_) => {
+            let all = Selector::parse("*").map_err(anyhow::Error::msg)?;
+            let elements = select_elements(&content, detected_type, &all, &options.attr)
+                .map_err(anyhow::Error::msg)?;
+            Some(edits::structural_edits(&file_key(file_path), &content, &options.attr, raw_changes.clone(), &elements))
+        }
+        None => None,
+    };
+    let changes = change_log::change_records(
+        &relative_path(file_path).display().to_string(),
+        &content,
+        raw_changes,
+    );
+    let dry_run = state.report.is_some() || state.patch.is_some() || state.edits.is_some();
+    let mut before = (dry_run || run.clean_output.is_some()).then(|| content.to_string());
+    // A mapped input must be released before the file is rewritten in place
+    drop(content);
+
+    if run.assert_idempotent {
+        // IDs were only inserted in the range, which grew by their length
+        let mut again_options = options.clone();
+        if let Some(range) = &mut again_options.range {
+            range.end += processed.len().saturating_sub(before_len);
+        }
+        let again = run_processor(detected_type, &processed, &again_options, &resolved, &mut IdGenerator::new())
+            .map_err(anyhow::Error::msg)?
+            .content;
+        if let Some(line) = first_difference(&processed, &again) {
+            anyhow::bail!("Output is not idempotent: a second run changes line {}", line);
+        }
+    }
+
+    let assignments = generator.take_assignments();
+    state.ids_added += assignments.len();
+    if let Some(cache) = &mut state.id_cache {
+        if options.overwrite {
+            cache.update(cache_key, assignments.clone());
+        } else {
+            cache.merge(cache_key, assignments.clone());
+        }
+    }
+    
+    if let Some(before) = before.take_if(|_| dry_run) {
+        if let Some(diff) = &mut state.patch {
+            diff.push_str(&patch::unified_diff(&file_key(file_path), &before, &processed));
+        }
+        if let (Some(all), Some(file_edits)) = (&mut state.edits, file_edits) {
+            all.extend(file_edits);
+        }
+        if let Some(files) = &mut state.report {
+            files.push(FileReport {
+                file: file_key(file_path),
+                before,
+                after: processed,
+                changes,
+            });
+        }
+        return Ok(FileOutcome::Processed);
+    }
+
+    let output_path = state
+        .sink
+        .write(file_path, &processed)
+        .map_err(anyhow::Error::msg)?
+        .unwrap_or_else(|| file_path.to_path_buf());
+    if let (Some(sink), Some(input)) = (&mut state.clean_sink, before) {
+        if let Some(clean_path) = sink.write(file_path, &input).map_err(anyhow::Error::msg)? {
+            let written = fs::read(&clean_path)
+                .with_context(|| format!("Failed to read back {}", clean_path.display()))?;
+            if written != input.as_bytes() {
+                anyhow::bail!("Untagged copy {} differs from its input", clean_path.display());
+            }
+        }
+    }
+
+    if let Some(manifest) = &mut state.manifest {
+        // Names for reviewers come from the labels and headings around each element
+        let all = Selector::parse("*").map_err(anyhow::Error::msg)?;
+        let elements = select_elements(&processed, detected_type, &all, &options.attr).map_err(anyhow::Error::msg)?;
+        let aliases: HashMap<String, String> = elements
+            .iter()
+            .zip(suggest::suggest_aliases(&elements))
+            .filter_map(|(element, alias)| Some((element.id.clone()?, alias?)))
+            .collect();
+        // The manifest lists the values in the output, environment token included
+        let written = assignments
+            .into_iter()
+            .map(|assignment| {
+                let id = options.with_env_prefix(&assignment.id);
+                Assignment { alias: aliases.get(&id).cloned(), id, ..assignment }
+            })
+            .collect();
+        let key = file_key(&output_path);
+        manifest.record(key.clone(), &options.attr, written, options.overwrite);
+        if let Some(manifest_file) = manifest.files.get_mut(&key) {
+            manifest_file.origin_attr = options.origin_attr.clone();
+            manifest_file.scheme = options.scheme;
+        }
+        manifest.record_warnings(key, output.warnings);
+    }
+
+    if let Some(out) = &mut state.change_log {
+        change_log::write_ndjson(out, &changes).map_err(anyhow::Error::msg)?;
+    }
+    
+    Ok(FileOutcome::Processed)
+}
+
+/// Logs where each element the filters saw is and what they made of it
+fn explain_filters(file: &str, content: &str, mut decisions: Vec<FilterDecision>) {
+    decisions.sort_by_key(|decision| decision.offset);
+    let positions = ast_common::line_columns(content, decisions.iter().map(|decision| decision.offset));
+    for (decision, (line, col)) in decisions.iter().zip(positions) {
+        match decision.skipped {
+            None => info!("{}:{}:{} <{}> tagged", file, line, col, decision.tag),
+            Some(reason) => info!("{}:{}:{} <{}> skipped: {} ({})", file, line, col, decision.tag, reason, reason.option()),
+        }
+    }
+}
+
+/// Sink writing to `dir`, in place without one, or to stdout for `-`
+fn output_sink(dir: Option<&Path>, run: &RunArgs) -> Box<dyn Sink> {
+    if dir == Some(Path::new("-")) {
+        return Box::new(StdoutSink);
+    }
+    let replace = if run.hardlinks == HardlinkPolicy::Break {
+        Replace::Always
+    } else if run.symlinks == SymlinkPolicy::Replace {
+        Replace::Symlinks
+    } else {
+        Replace::Never
+    };
+    Box::new(FileSink {
+        dir: dir.map(Path::to_path_buf),
+        replace,
+        preserve: Preserve { mtime: run.preserve_mtime, owner: run.preserve_owner },
+    })
+}
+
+/// Outcome of a file over a safety limit, as chosen by `--on-limit`
+fn over_limit(reason: String, run: &RunArgs) -> Result<FileOutcome> {
+    match run.on_limit {
+        LimitAction::Skip => Ok(FileOutcome::Guarded(reason)),
+        LimitAction::Error => anyhow::bail!("File over limit: {}", reason),
+    }
+}
+
+/// The file `--report` writes to, checking the report format
+fn report_path(run: &RunArgs) -> Result<Option<PathBuf>> {
+    match run.report.as_deref() {
+        Some([format, path]) if format == "html" => Ok(Some(PathBuf::from(path))),
+        Some([format, _]) => anyhow::bail!("Unsupported report format '{}' (expected: html)", format),
+        _ => Ok(None),
+    }
+}
+
+/// Writes what `--emit` collected, with `-` meaning stdout
+fn write_emitted(path: &Path, content: &str, what: &str) -> Result<()> {
+    if path == Path::new("-") {
+        let mut out = std::io::stdout().lock();
+        return out
+            .write_all(content.as_bytes())
+            .and_then(|_| out.flush())
+            .with_context(|| format!("Failed to write {}", what));
+    }
+    fs::write(path, content).with_context(|| format!("Failed to write {}: {}", what, path.display()))?;
+    info!("Wrote {}: {}", what, path.display());
+    Ok(())
+}
+
+/// Opens the change log destination, with `-` meaning stdout
+fn open_change_log(path: &Path) -> Result<Box<dyn Write>> {
+    if path == Path::new("-") {
+        return Ok(Box::new(BufWriter::new(std::io::stdout())));
+    }
+    let file = fs::File::create(path)
+        .with_context(|| format!("Failed to create change log: {}", path.display()))?;
+    Ok(Box::new(BufWriter::new(file)))
+}
+
+/// Processes `content` with the processor for `file_type`, drawing IDs from `generator`.
+fn run_processor(
+    file_type: FileType,
+    content: &str,
+    options: &IdOptions,
+    resolved: &Rc<ResolvedOptions>,
+    generator: &mut IdGenerator,
+) -> Result<ProcessOutput, String> {
+    let mut processor = Processor::with_generator(std::mem::take(generator));
+    let result = processor.process_resolved(format_of(file_type), content, options, resolved);
+    *generator = processor.into_generator();
+    let output = result?;
+    debug!("Visited {} element(s), added {} ID(s)", output.elements_visited, output.ids_added);
+    Ok(output)
+}
+
+/// Logs the warnings about `content` in source order, located where they
+/// have an offset
+fn log_warnings(file: &str, content: &str, warnings: &[Warning]) {
+    let mut warnings: Vec<&Warning> = warnings.iter().collect();
+    warnings.sort_by_key(|warning| warning.offset);
+    let offsets = warnings.iter().filter_map(|warning| warning.offset);
+    let mut positions = ast_common::line_columns(content, offsets).into_iter();
+    for warning in warnings {
+        match warning.offset.and(positions.next()) {
+            Some((line, col)) => warn!("{}:{}:{}: {}", file, line, col, warning),
+            None => warn!("{}: {}", file, warning),
+        }
+    }
+}
+
+/// 1-based number of the first line that differs between `a` and `b`
+fn first_difference(a: &str, b: &str) -> Option<usize> {
+    if a == b {
+        return None;
+    }
+    let line = a
+        .lines()
+        .zip(b.lines())
+        .position(|(x, y)| x != y)
+        .unwrap_or_else(|| a.lines().count().min(b.lines().count()));
+    Some(line + 1)
+}
+
+/// Reads `file_path` and writes back what `rewrite` makes of it, if anything,
+/// holding the file's lock throughout. The write replaces the file, links
+/// included, and keeps its permissions, as runs do with `--symlinks replace`.
+fn rewrite_in_place(file_path: &Path, rewrite: impl FnOnce(&str, FileType) -> Result<Option<String>>) -> Result<()> {
+    let _lock = FileLock::acquire_for(file_path, true).map_err(anyhow::Error::msg)?;
+    let (content, detected_type) = read_with_type(file_path, FileType::Auto, false)?;
+    let Some(output) = rewrite(&content, detected_type)? else {
+        return Ok(());
+    };
+    drop(content);
+    let mut sink = FileSink { dir: None, replace: Replace::Always, preserve: Preserve::default() };
+    sink.write(file_path, &output).map_err(anyhow::Error::msg)?;
+    Ok(())
+}
+
+/// Reads a file and works out its type when `file_type` is `Auto`. Scripts
+/// without JSX read as JSX and Vue components as HTML, the closest parsers.
+fn read_with_type(file_path: &Path, file_type: FileType, mmap: bool) -> Result<(FileContent, FileType)> {
+    let (content, format) = read_with_format(file_path, file_type, mmap, &[])?;
+    Ok((content, file_type_of(format)))
+}
+
+/// Reads a file and works out its format: `file_type` unless it is `Auto`,
+/// otherwise the last of `forced` matching the path, or the detected format.
+fn read_with_format(
+    file_path: &Path,
+    file_type: FileType,
+    mmap: bool,
+    forced: &[ForcedType],
+) -> Result<(FileContent, Format)> {
+    let content = if mmap {
+        FileContent::map(file_path)
+    } else {
+        FileContent::read(file_path)
+    }
+    .map_err(anyhow::Error::msg)?;
+
+    let format = match file_type {
+        FileType::Jsx => Format::Jsx,
+        FileType::Xml => Format::Xml,
+        FileType::Html => Format::Html,
+        FileType::Auto => {
+            let key = file_key(file_path);
+            match forced.iter().rev().find(|forced| forced.glob.matches(&key)) {
+                Some(forced) => forced.format,
+                None => {
+                    let detection = detect::detect(Some(file_path), &content);
+                    debug!(
+                        "Detected {} with confidence {:.2} ({})",
+                        detection.format.name(),
+                        detection.confidence,
+                        detection.signals.join(", ")
+                    );
+                    detection.format
+                }
+            }
+        }
+    };
+
+    Ok((content, format))
+}
+
+fn file_type_of(format: Format) -> FileType {
+    match format {
+        Format::Jsx | Format::Script => FileType::Jsx,
+        Format::Xml => FileType::Xml,
+        Format::Html | Format::Vue => FileType::Html,
+    }
+}
+
+/// Format of a `FileType` other than `Auto`
+fn format_of(file_type: FileType) -> Format {
+    match file_type {
+        FileType::Jsx => Format::Jsx,
+        FileType::Xml => Format::Xml,
+        FileType::Html => Format::Html,
+        FileType::Auto => unreachable!(),
+    }
+}
+
+fn existing_ids(content: &str, file_type: FileType, attr: &str) -> Result<Vec<String>, String> {
+    match file_type {
+        FileType::Jsx => jsx::existing_ids(content, attr),
+        FileType::Xml => xml::existing_ids(content, attr),
+        FileType::Html => html::existing_ids(content, attr),
+        FileType::Auto => unreachable!(),
+    }
+}
+
+fn locate_ids(content: &str, file_type: FileType, attr: &str) -> Result<Vec<IdLocation>, String> {
+    match file_type {
+        FileType::Jsx => jsx::locate_ids(content, attr),
+        FileType::Xml => xml::locate_ids(content, attr),
+        FileType::Html => html::locate_ids(content, attr),
+        FileType::Auto => unreachable!(),
+    }
+}
+
+fn policy_elements(content: &str, file_type: FileType, policy: &str, attr: &str) -> Result<Vec<PolicyElement>, String> {
+    match file_type {
+        FileType::Jsx => jsx::policy_elements(content, policy, attr),
+        FileType::Xml => xml::policy_elements(content, policy, attr),
+        FileType::Html => html::policy_elements(content, policy, attr),
+        FileType::Auto => unreachable!(),
+    }
+}
+
+fn select_elements(
+    content: &str,
+    file_type: FileType,
+    selector: &Selector,
+    attr: &str,
+) -> Result<Vec<SelectedElement>, String> {
+    match file_type {
+        FileType::Jsx => jsx::select_elements(content, selector, attr),
+        FileType::Xml => xml::select_elements(content, selector, attr),
+        FileType::Html => html::select_elements(content, selector, attr),
+        FileType::Auto => unreachable!(),
+    }
+}
+
+/// First pass of reserve mode: collects the IDs already present in every file.
+/// Unreadable or unparsable files are skipped here and reported by the second pass.
+fn reserve_existing_ids(
+    files: &[PathBuf],
+    file_type: FileType,
+    options: &IdOptions,
+    rules: &[Rule],
+    mmap: bool,
+    generator: &mut IdGenerator,
+) -> usize {
+    let mut reserved = 0;
+
+    for file_path in files {
+        let Ok((content, detected_type)) = read_with_type(file_path, file_type, mmap) else {
+            continue;
+        };
+        let Ok(rule_options) = config::apply_rules(rules, options, &file_key(file_path)) else {
+            continue;
+        };
+        let options = rule_options.as_ref().unwrap_or(options);
+
+        for id in existing_ids(&content, detected_type, &options.attr).unwrap_or_default() {
+            generator.reserve(options.without_env_prefix(&id));
+            reserved += 1;
+        }
+    }
+
+    reserved
+}
+
+/// Key identifying a file in the ID cache and manifest: its relative path with
+/// `/` separators
+fn file_key(file_path: &Path) -> String {
+    relative_path(file_path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Path of `file_path` relative to the current directory when possible
+fn relative_path(file_path: &Path) -> PathBuf {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| file_path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| file_path.to_path_buf())
+}
+
+/// Extensions of the files a walk picks up without a pragma
+const SOURCE_EXTENSIONS: [&str; 9] = ["jsx", "tsx", "js", "ts", "xml", "svg", "mml", "html", "htm"];
+
+/// Which of the files a path argument names are processed: those with a
+/// source extension or a `--force-type` glob, and others that opt in with an
+/// `ast-append-ids: on` pragma. A type given on the command line takes every
+/// file for that type.
+struct FileFilter<'a> {
+    file_type: FileType,
+    forced: &'a [ForcedType],
+    pragma_lines: usize,
+}
+
+impl Default for FileFilter<'_> {
+    fn default() -> Self {
+        Self { file_type: FileType::Auto, forced: &[], pragma_lines: pragma::DEFAULT_PRAGMA_LINES }
+    }
+}
+
+impl FileFilter<'_> {
+    fn keeps(&self, path: &Path) -> bool {
+        let known = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| SOURCE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()));
+        if known || !matches!(self.file_type, FileType::Auto) {
+            return true;
+        }
+        let key = file_key(path);
+        if self.forced.iter().any(|forced| forced.glob.matches(&key)) {
+            return true;
+        }
+        // Read last, and only the head of text files
+        !looks_binary(path) && has_on_pragma(path, self.pragma_lines)
+    }
+}
+
+/// Files `pattern` names that `filter` keeps, leaving out those in
+/// node_modules directories unless `node_modules` is set
+fn find_files(pattern: &str, filter: &FileFilter, node_modules: bool) -> Result<Vec<PathBuf>> {
+    let mut files = files_matching(pattern)?;
+    if !node_modules {
+        let found = files.len();
+        files.retain(|path| !in_node_modules(path));
+        let skipped = found - files.len();
+        // A pattern naming node_modules itself expects those files
+        if skipped > 0 && in_node_modules(&walk_root(pattern)) {
+            warn!("Skipped {} file(s) in node_modules matching {}", skipped, pattern);
+        } else if skipped > 0 {
+            debug!("Skipped {} file(s) in node_modules under {}", skipped, pattern);
+        }
+    }
+    files.retain(|path| {
+        let keep = filter.keeps(path);
+        if !keep {
+            debug!("Skipped {}: not a source file and no ast-append-ids: on pragma", path.display());
+        }
+        keep
+    });
+    Ok(files)
+}
+
+/// Whether `path` goes through a node_modules directory
+fn in_node_modules(path: &Path) -> bool {
+    path.components().any(|component| component.as_os_str() == "node_modules")
+}
+
+/// The file `pattern` names, the files under the directory it names, or the
+/// files matching it as a glob
+fn files_matching(pattern: &str) -> Result<Vec<PathBuf>> {
+    let path = Path::new(pattern);
+    
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    
+    let pattern = if path.is_dir() { format!("{}/**/*", pattern) } else { pattern.to_string() };
+    let mut files = Vec::new();
+    for path in glob(&pattern)?.flatten() {
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Sorts `files` by path, then stably by `order`, so runs are reproducible
+/// whatever order the filesystem lists them in. Files whose metadata cannot
+/// be read sort first.
+fn sort_files(files: &mut [PathBuf], order: FileOrder) {
+    files.sort();
+    match order {
+        FileOrder::Path => {}
+        FileOrder::Mtime => files.sort_by_cached_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok()),
+        FileOrder::Size => files.sort_by_cached_key(|path| fs::metadata(path).map(|m| m.len()).ok()),
+    }
+}
+
+/// The directory a path argument is walked from: the argument itself, or the
+/// part of a glob before its first wildcard
+fn walk_root(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|component| !component.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+        .collect()
+}
+
+/// Whether `file`, or a directory between `root` and `file`, is a symbolic link
+fn through_symlink(root: &Path, file: &Path) -> bool {
+    file.ancestors()
+        .take_while(|path| *path != root && !path.as_os_str().is_empty())
+        .any(is_symlink)
+}
+
+/// `path` without `.` components, empty for the current directory
+fn without_cur_dir(path: &Path) -> PathBuf {
+    path.components().filter(|component| *component != Component::CurDir).collect()
+}
+
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+/// Whether the head of the file looks binary; unreadable files are left for
+/// the read to report
+fn looks_binary(path: &Path) -> bool {
+    let Ok(file) = fs::File::open(path) else {
+        return false;
+    };
+    let mut head = Vec::with_capacity(detect::BINARY_CHECK_BYTES);
+    match file.take(detect::BINARY_CHECK_BYTES as u64).read_to_end(&mut head) {
+        Ok(_) => detect::is_binary(&head),
+        Err(_) => false,
+    }
+}
+
+/// Reads only the head of the file, so large or binary files are cheap to check
+fn has_on_pragma(path: &Path, pragma_lines: usize) -> bool {
+    let Ok(file) = fs::File::open(path) else {
+        return false;
+    };
+    let head: String = BufReader::new(file)
+        .lines()
+        .take(pragma_lines)
+        .map_while(|line| line.ok())
+        .collect::<Vec<_>>()
+        .join("\n");
+    pragma::detect_file_pragma(&head, pragma_lines) == Some(FilePragma::On)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_files_in_stable_order() {
+        let dir = std::env::temp_dir().join(format!("ast-append-ids-order-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        let now = SystemTime::now();
+        // Written out of order, each bigger and older than the one before
+        for (index, name) in ["nested/c.jsx", "b.html", "a.xml"].iter().enumerate() {
+            let path = dir.join(name);
+            fs::write(&path, "<p/>".repeat(index + 1)).unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(now - Duration::from_secs(60 * index as u64)).unwrap();
+        }
+        let names = |files: &[PathBuf]| -> Vec<String> {
+            files.iter().map(|file| file.strip_prefix(&dir).unwrap().to_string_lossy().replace('\\', "/")).collect()
+        };
+
+        let mut files = find_files(&dir.to_string_lossy(), &FileFilter::default(), false).unwrap();
+        assert_eq!(names(&files), ["a.xml", "b.html", "nested/c.jsx"]);
+        sort_files(&mut files, FileOrder::Size);
+        assert_eq!(names(&files), ["nested/c.jsx", "b.html", "a.xml"]);
+        sort_files(&mut files, FileOrder::Mtime);
+        assert_eq!(names(&files), ["a.xml", "b.html", "nested/c.jsx"]);
+        sort_files(&mut files, FileOrder::Path);
+        assert_eq!(names(&files), ["a.xml", "b.html", "nested/c.jsx"]);
+
+        // Equal keys keep the path order
+        for file in &files {
+            fs::write(file, "<p/>").unwrap();
+        }
+        sort_files(&mut files, FileOrder::Size);
+        assert_eq!(names(&files), ["a.xml", "b.html", "nested/c.jsx"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rewrite_in_place_keeps_mode_and_links() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = std::env::temp_dir().join(format!("ast-append-ids-rewrite-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target.html");
+        let link = dir.join("link.html");
+        fs::write(&target, "<p>a</p>").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o640)).unwrap();
+        symlink(&target, &link).unwrap();
+
+        rewrite_in_place(&target, |content, file_type| {
+            assert!(matches!(file_type, FileType::Html));
+            Ok(Some(content.replace('a', "b")))
+        })
+        .unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "<p>b</p>");
+        assert_eq!(fs::metadata(&target).unwrap().permissions().mode() & 0o777, 0o640);
+
+        // A link becomes a file of its own, leaving its target alone
+        rewrite_in_place(&link, |_, _| Ok(Some("<p>c</p>".to_string()))).unwrap();
+        assert!(!fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "<p>b</p>");
+        assert_eq!(fs::read_to_string(&link).unwrap(), "<p>c</p>");
+
+        rewrite_in_place(&target, |_, _| Ok(None)).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "<p>b</p>");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,30 @@
+use super::{locate_ids, read_with_type, FileType};
+use anyhow::Result;
+use ast_append_ids::anchors::Anchors;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// Names the element of `file_path` carrying `id` in the anchors file.
+pub fn run(name: &str, file_path: &Path, id: &str, anchors_path: &Path, attr: &str) -> Result<()> {
+    let (content, detected_type) = read_with_type(file_path, FileType::Auto, false)?;
+    let locations = locate_ids(&content, detected_type, attr).map_err(anyhow::Error::msg)?;
+    let element = match locations.iter().filter(|location| location.id == id).collect::<Vec<_>>()[..] {
+        [element] => element,
+        [] => anyhow::bail!("No element in {} has {}=\"{}\"", file_path.display(), attr, id),
+        _ => anyhow::bail!("More than one element in {} has {}=\"{}\"", file_path.display(), attr, id),
+    };
+    let alike = locations.iter().filter(|location| location.fingerprint == element.fingerprint).count() - 1;
+    if alike > 0 {
+        warn!(
+            "{} other element(s) in {} have the same tag and attributes; only the first of them gets the name",
+            alike,
+            file_path.display()
+        );
+    }
+
+    let mut anchors = Anchors::load_or_default(anchors_path).map_err(anyhow::Error::msg)?;
+    anchors.insert(name, &element.fingerprint).map_err(anyhow::Error::msg)?;
+    anchors.save(anchors_path).map_err(anyhow::Error::msg)?;
+    info!("Anchored '{}' to <{}> ({}) in {}", name, element.tag, element.fingerprint, anchors_path.display());
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use super::{find_files, read_with_type, FileFilter, FileType};
+use anyhow::Result;
+use ast_append_ids::codemod;
+use ast_append_ids::file_io;
+use ast_append_ids::html;
+use ast_append_ids::jsx;
+use ast_append_ids::manifest::Manifest;
+use std::path::Path;
+use tracing::{debug, error, info, info_span, warn};
+
+/// Rewrites the text queries of the tests under `path_pattern` to query the
+/// test IDs the manifest records for the same text.
+pub fn run(path_pattern: &str, manifest_path: &Path) -> Result<()> {
+    let manifest = Manifest::load(manifest_path).map_err(anyhow::Error::msg)?;
+
+    // Text of the elements the manifest lists, read from the instrumented files
+    let mut texts = Vec::new();
+    for (file, manifest_file) in &manifest.files {
+        let _span = info_span!("file", path = %file).entered();
+        let file_texts = read_with_type(Path::new(file), FileType::Auto, false).and_then(|(content, detected_type)| {
+            match detected_type {
+                FileType::Jsx => jsx::element_texts(&content, &manifest_file.attr),
+                FileType::Html => html::element_texts(&content, &manifest_file.attr),
+                FileType::Xml | FileType::Auto => Ok(Vec::new()),
+            }
+            .map_err(anyhow::Error::msg)
+        });
+        match file_texts {
+            Ok(file_texts) => {
+                let ids = manifest_file.ids();
+                texts.extend(file_texts.into_iter().filter(|(id, _)| ids.contains(id)));
+            }
+            Err(e) => warn!("{:#}", e),
+        }
+    }
+    let ids = codemod::text_index(texts);
+    debug!("Mapped {} text(s) to IDs", ids.len());
+
+    let mut query_count = 0;
+    let mut rewritten_count = 0;
+    let mut error_count = 0;
+    for file_path in find_files(path_pattern, &FileFilter::default(), false)? {
+        let _span = info_span!("file", path = %file_path.display()).entered();
+        let result = read_with_type(&file_path, FileType::Auto, false).and_then(|(content, detected_type)| {
+            if !matches!(detected_type, FileType::Jsx) {
+                return Ok((0, 0));
+            }
+            let queries = jsx::text_queries(&content).map_err(anyhow::Error::msg)?;
+            let found = queries.len();
+            let (output, rewritten) = codemod::rewrite_text_queries(&content, queries, &ids);
+            drop(content);
+            if rewritten > 0 {
+                file_io::write_output(&file_path, &output).map_err(anyhow::Error::msg)?;
+            }
+            Ok((found, rewritten))
+        });
+
+        match result {
+            Ok((found, rewritten)) => {
+                if found > 0 {
+                    debug!("Rewrote {} of {} text queries", rewritten, found);
+                }
+                query_count += found;
+                rewritten_count += rewritten;
+            }
+            Err(e) => {
+                error_count += 1;
+                error!("{:#}", e);
+            }
+        }
+    }
+
+    info!("Rewrote {} of {} text queries", rewritten_count, query_count);
+    if error_count > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use super::{find_files, policy_elements, read_with_type, FileFilter, FileType};
+use anyhow::Result;
+use ast_append_ids::coverage::Coverage;
+use std::io::Write;
+use tracing::{debug, error, info_span, warn};
+
+/// Prints how many of the elements matching `policy` have an ID, per file
+/// and in total, failing below `min_coverage` percent.
+pub fn run(path_pattern: &str, policy: &str, attr: &str, min_coverage: Option<f64>) -> Result<()> {
+    let files = find_files(path_pattern, &FileFilter::default(), false)?;
+    if files.is_empty() {
+        warn!("No files found matching: {}", path_pattern);
+        return Ok(());
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    let mut total = Coverage::default();
+    let mut error_count = 0;
+
+    for file_path in &files {
+        let _span = info_span!("file", path = %file_path.display()).entered();
+        let result = read_with_type(file_path, FileType::Auto, false).and_then(|(content, detected_type)| {
+            let elements = policy_elements(&content, detected_type, policy, attr).map_err(anyhow::Error::msg)?;
+            Ok(Coverage::new(&content, elements))
+        });
+
+        let file_coverage = match result {
+            Ok(file_coverage) => file_coverage,
+            Err(e) => {
+                error_count += 1;
+                error!("{:#}", e);
+                continue;
+            }
+        };
+        if file_coverage.total == 0 {
+            debug!("No elements match the policy");
+            continue;
+        }
+
+        writeln!(
+            stdout,
+            "{}: {}/{} ({:.1}%)",
+            file_path.display(),
+            file_coverage.covered,
+            file_coverage.total,
+            file_coverage.percent()
+        )?;
+        for element in &file_coverage.uncovered {
+            writeln!(stdout, "  {}:{}:{}: {}", file_path.display(), element.line, element.col, element.snippet)?;
+        }
+        total.add(&file_coverage);
+    }
+
+    writeln!(stdout, "Total: {}/{} ({:.1}%)", total.covered, total.total, total.percent())?;
+
+    if let Some(min) = min_coverage {
+        if total.percent() < min {
+            error!("Coverage {:.1}% is below the minimum of {}%", total.percent(), min);
+            std::process::exit(1);
+        }
+    }
+    if error_count > 0 {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use super::{locate_ids, read_with_type, FileType};
+use anyhow::Result;
+use ast_append_ids::diff::{self, DiffKind, IdEntry};
+use ast_append_ids::manifest::Manifest;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use tracing::info;
+
+/// Prints the IDs added, removed or changed between two versions of a file,
+/// or between two manifests.
+pub fn run(old: &Path, new: &Path, attr: &str) -> Result<()> {
+    let is_json = |path: &Path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+    let manifests = is_json(old) && is_json(new);
+    let files = if manifests {
+        let old = Manifest::load(old).map_err(anyhow::Error::msg)?;
+        let new = Manifest::load(new).map_err(anyhow::Error::msg)?;
+        diff::diff_manifests(&old, &new)
+    } else {
+        let entries = |path: &Path| -> Result<Vec<IdEntry>> {
+            let (content, detected_type) = read_with_type(path, FileType::Auto, false)?;
+            let locations = locate_ids(&content, detected_type, attr).map_err(anyhow::Error::msg)?;
+            Ok(locations.into_iter().map(IdEntry::from).collect())
+        };
+        let diffs = diff::diff_ids(entries(old)?, entries(new)?);
+        BTreeMap::from([(new.display().to_string(), diffs)])
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+    for (file, diffs) in &files {
+        if manifests {
+            writeln!(stdout, "{}:", file)?;
+        }
+        for diff in diffs {
+            let marker = match diff.kind {
+                DiffKind::Added => {
+                    added += 1;
+                    '+'
+                }
+                DiffKind::Removed => {
+                    removed += 1;
+                    '-'
+                }
+                DiffKind::Changed => {
+                    changed += 1;
+                    '~'
+                }
+            };
+            writeln!(stdout, "{} {} <{}>", marker, diff.id, diff.tag)?;
+        }
+    }
+
+    info!("{} added, {} removed, {} changed", added, removed, changed);
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use super::{locate_ids, read_with_type, FileType};
+use anyhow::Result;
+use ast_append_ids::explain::{self, Explanation};
+use ast_append_ids::find;
+use ast_append_ids::id_cache::IdCache;
+use ast_append_ids::id_generator::Assignment;
+use ast_append_ids::manifest::Manifest;
+use std::io::Write;
+use std::path::Path;
+use tracing::warn;
+
+/// Where a manifest or ID cache says an ID was assigned
+struct IdRecord {
+    source: &'static str,
+    file: String,
+    element: Assignment,
+    /// Attribute the ID was written to, known from manifests only
+    attr: Option<String>,
+}
+
+fn id_records(id: &str, manifest: Option<&Manifest>, id_cache: Option<&IdCache>) -> Vec<IdRecord> {
+    let mut records = Vec::new();
+    for (file, manifest_file) in manifest.iter().flat_map(|manifest| &manifest.files) {
+        records.extend(manifest_file.entries.iter().filter(|entry| entry.id == id).map(|entry| IdRecord {
+            source: "manifest",
+            file: file.clone(),
+            element: entry.clone(),
+            attr: Some(manifest_file.attr.clone()),
+        }));
+    }
+    for (file, entries) in id_cache.iter().flat_map(|cache| &cache.files) {
+        records.extend(entries.iter().filter(|entry| entry.id == id).map(|entry| IdRecord {
+            source: "ID cache",
+            file: file.clone(),
+            element: entry.clone(),
+            attr: None,
+        }));
+    }
+    records
+}
+
+/// Prints the records of `id` and, for manifest records, where the ID is now.
+fn print_records(out: &mut impl Write, id: &str, records: &[IdRecord]) -> Result<()> {
+    for record in records {
+        let path: Vec<String> = record.element.path.iter().map(usize::to_string).collect();
+        writeln!(
+            out,
+            "  {}: <{}> at path {} ({})",
+            record.file,
+            record.element.tag,
+            path.join("/"),
+            record.source
+        )?;
+        let Some(attr) = &record.attr else {
+            continue;
+        };
+        let found = read_with_type(Path::new(&record.file), FileType::Auto, false).and_then(|(content, detected_type)| {
+            let locations = locate_ids(&content, detected_type, attr).map_err(anyhow::Error::msg)?;
+            Ok(find::find_ids(&content, locations, id, false))
+        });
+        match found {
+            Ok(found) if found.is_empty() => writeln!(out, "    no longer in the file")?,
+            Ok(found) => {
+                for element in found {
+                    writeln!(out, "    {}:{}:{}: {}", record.file, element.line, element.col, element.snippet)?;
+                }
+            }
+            Err(e) => warn!("{:#}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Prints what `id` says about how it was made and, with a manifest or ID
+/// cache, where it and the ID it repeats were assigned.
+pub fn run(id: &str, prefix: &str, env_prefix: &str, manifest: Option<&Path>, id_cache: Option<&Path>) -> Result<()> {
+    let manifest = manifest.map(Manifest::load).transpose().map_err(anyhow::Error::msg)?;
+    let id_cache = id_cache.map(IdCache::load).transpose().map_err(anyhow::Error::msg)?;
+    let records = id_records(id, manifest.as_ref(), id_cache.as_ref());
+
+    let mut explanation = explain::explain(id, prefix, env_prefix);
+    let mut moved = false;
+    if let Some(record) = records.first() {
+        moved = !explanation.narrow(&record.element);
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    print_explanation(&mut stdout, &explanation, prefix, moved)?;
+
+    if manifest.is_some() || id_cache.is_some() {
+        if records.is_empty() {
+            writeln!(stdout, "Recorded: not found")?;
+        } else {
+            writeln!(stdout, "Recorded:")?;
+            print_records(&mut stdout, id, &records)?;
+        }
+    }
+
+    let every_reading = explanation.readings.len();
+    for duplicate in explanation.duplicates() {
+        if duplicate.strategies.len() == every_reading {
+            writeln!(stdout, "Duplicate of: {}", duplicate.original)?;
+        } else {
+            let strategies: Vec<&str> = duplicate.strategies.iter().map(|strategy| strategy.name()).collect();
+            writeln!(stdout, "Duplicate of: {} (if {})", duplicate.original, strategies.join(" or "))?;
+        }
+        let records = id_records(&duplicate.original, manifest.as_ref(), id_cache.as_ref());
+        print_records(&mut stdout, &duplicate.original, &records)?;
+    }
+    Ok(())
+}
+
+fn print_explanation(out: &mut impl Write, explanation: &Explanation, prefix: &str, moved: bool) -> Result<()> {
+    writeln!(out, "ID: {}", explanation.id)?;
+    if let Some(env) = &explanation.env {
+        writeln!(out, "Environment: {} (from --env-prefix)", env)?;
+    }
+    if let Some(namespace) = &explanation.namespace {
+        writeln!(out, "Namespace: {} (from --namespace-by-path)", namespace)?;
+    }
+    match &explanation.prefix {
+        Some(prefix) => writeln!(out, "Prefix: {}", prefix)?,
+        None => writeln!(out, "Prefix: none, the ID does not start with \"{}\"", prefix)?,
+    }
+    for (i, reading) in explanation.readings.iter().enumerate() {
+        let label = if i == 0 { "Strategy:" } else { "      or:" };
+        writeln!(out, "{} {}", label, reading)?;
+    }
+    if explanation.readings.is_empty() {
+        writeln!(out, "Strategy: unknown, probably written by hand")?;
+    }
+    if moved {
+        writeln!(out, "The recorded element does not fit the ID; it may have been reused from the ID cache after the element moved")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_print_explanation() {
+        let explanation = explain::explain("staging:el-div-0-4", "el-", "");
+        let mut out = Vec::new();
+        print_explanation(&mut out, &explanation, "el-", false).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "ID: staging:el-div-0-4\n\
+             Environment: staging: (from --env-prefix)\n\
+             Prefix: el-\n\
+             Strategy: path: <div> at 0/4\n      \
+             or: taxonomy: <div> #0 outside any landmark, made unique with -4\n      \
+             or: slug of the element's text \"div-0\", made unique with -4\n      \
+             or: slug of the element's text \"div-0-4\"\n"
+        );
+    }
+}
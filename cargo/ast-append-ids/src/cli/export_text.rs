@@ -0,0 +1,36 @@
+use super::{find_files, read_with_type, relative_path, FileFilter, FileType};
+use anyhow::Result;
+use ast_append_ids::file_io;
+use ast_append_ids::html;
+use ast_append_ids::localize::{TextEntry, TextFormat};
+use std::io::Write;
+use std::path::Path;
+use tracing::{debug, info, info_span};
+
+/// Writes the text of the HTML elements with an ID, keyed by ID, in the
+/// format `output`'s extension names (JSON on stdout).
+pub fn run(path_pattern: &str, output: Option<&Path>, attr: &str) -> Result<()> {
+    let mut entries = Vec::new();
+    for file_path in find_files(path_pattern, &FileFilter::default(), false)? {
+        let _span = info_span!("file", path = %file_path.display()).entered();
+        let (content, detected_type) = read_with_type(&file_path, FileType::Auto, false)?;
+        if !matches!(detected_type, FileType::Html) {
+            debug!("Skipped: not HTML");
+            continue;
+        }
+        let file = relative_path(&file_path).display().to_string();
+        for (id, text) in html::element_texts(&content, attr).map_err(anyhow::Error::msg)? {
+            entries.push(TextEntry { file: file.clone(), id, text });
+        }
+    }
+
+    let format = output.map_or(TextFormat::Json, |path| TextFormat::from_path(&path.to_string_lossy()));
+    let exported = format.write(&entries).map_err(anyhow::Error::msg)?;
+    match output {
+        Some(path) => file_io::write_output(path, &exported).map_err(anyhow::Error::msg)?,
+        None => std::io::stdout().lock().write_all(exported.as_bytes())?,
+    }
+
+    info!("Exported the text of {} element(s)", entries.len());
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use super::{find_files, locate_ids, read_with_type, FileFilter, FileType};
+use anyhow::Result;
+use ast_append_ids::find;
+use ast_append_ids::manifest::Manifest;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{debug, error, info_span};
+
+/// Prints the elements whose ID is `query` (or starts with it with `prefix`),
+/// searching the files under `path` or the ones a manifest lists.
+pub fn run(query: &str, path: Option<&str>, manifest: Option<&Path>, attr: &str, prefix: bool) -> Result<()> {
+    let matches_query = |id: &str| if prefix { id.starts_with(query) } else { id == query };
+
+    // Files to search, with the attribute the IDs are written to
+    let targets: Vec<(PathBuf, String)> = match manifest {
+        Some(manifest_path) => Manifest::load(manifest_path)
+            .map_err(anyhow::Error::msg)?
+            .files
+            .into_iter()
+            .filter(|(_, file)| file.entries.iter().any(|entry| matches_query(&entry.id)))
+            .map(|(file, manifest_file)| (PathBuf::from(file), manifest_file.attr))
+            .collect(),
+        None => find_files(path.unwrap_or("."), &FileFilter::default(), false)?
+            .into_iter()
+            .map(|file| (file, attr.to_string()))
+            .collect(),
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    let mut found_count = 0;
+    let mut error_count = 0;
+
+    for (file_path, attr) in targets {
+        let _span = info_span!("file", path = %file_path.display()).entered();
+        let result = read_with_type(&file_path, FileType::Auto, false).and_then(|(content, detected_type)| {
+            let locations = locate_ids(&content, detected_type, &attr).map_err(anyhow::Error::msg)?;
+            Ok(find::find_ids(&content, locations, query, prefix))
+        });
+
+        match result {
+            Ok(found) => {
+                for element in found {
+                    writeln!(stdout, "{}:{}:{}: {}", file_path.display(), element.line, element.col, element.snippet)?;
+                    found_count += 1;
+                }
+            }
+            Err(e) => {
+                error_count += 1;
+                error!("{:#}", e);
+            }
+        }
+    }
+
+    debug!("Found {} element(s), {} error(s)", found_count, error_count);
+
+    // Like grep, exit with 1 when nothing was found
+    if found_count == 0 || error_count > 0 {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,15 @@
+use anyhow::Result;
+use ast_append_ids::file_io;
+use ast_append_ids::runtime;
+use std::io::Write;
+use std::path::Path;
+
+/// Writes the browser module looking elements up by their `attr`.
+pub fn run(attr: &str, output: Option<&Path>) -> Result<()> {
+    let module = runtime::runtime_module(attr).map_err(anyhow::Error::msg)?;
+    match output {
+        Some(path) => file_io::write_output(path, &module).map_err(anyhow::Error::msg)?,
+        None => std::io::stdout().lock().write_all(module.as_bytes())?,
+    }
+    Ok(())
+}
@@ -0,0 +1,23 @@
+use super::file_key;
+use anyhow::Result;
+use ast_append_ids::file_io;
+use ast_append_ids::gen_selectors::{self, SelectorOptions};
+use ast_append_ids::manifest::Manifest;
+use std::io::Write;
+use std::path::Path;
+use tracing::info;
+
+/// Writes a TypeScript module of selector constants for the IDs of a
+/// manifest.
+pub fn run(manifest_path: &Path, output: Option<&Path>, options: &SelectorOptions) -> Result<()> {
+    let manifest = Manifest::load(manifest_path).map_err(anyhow::Error::msg)?;
+    let constants = gen_selectors::selector_constants(&manifest, options);
+    let module = gen_selectors::to_typescript(&constants, &file_key(manifest_path));
+
+    match output {
+        Some(path) => file_io::write_output(path, &module).map_err(anyhow::Error::msg)?,
+        None => std::io::stdout().lock().write_all(module.as_bytes())?,
+    }
+    info!("Generated {} selector(s)", constants.len());
+    Ok(())
+}
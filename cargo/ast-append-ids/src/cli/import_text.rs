@@ -0,0 +1,58 @@
+use super::{find_files, rewrite_in_place, FileFilter, FileType};
+use anyhow::{Context, Result};
+use ast_append_ids::html;
+use ast_append_ids::localize::TextFormat;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use tracing::{debug, error, info, info_span};
+
+/// Replaces the text of the HTML elements with an ID by the texts in
+/// `texts_path`, keyed by ID.
+pub fn run(path_pattern: &str, texts_path: &Path, attr: &str) -> Result<()> {
+    let content = fs::read_to_string(texts_path)
+        .with_context(|| format!("Failed to read texts {}", texts_path.display()))?;
+    let entries = TextFormat::from_path(&texts_path.to_string_lossy())
+        .read(&content)
+        .map_err(anyhow::Error::msg)?;
+    let texts: HashMap<String, String> = entries.into_iter().map(|entry| (entry.id, entry.text)).collect();
+
+    let mut replaced_ids = HashSet::new();
+    let mut error_count = 0;
+    for file_path in find_files(path_pattern, &FileFilter::default(), false)? {
+        let _span = info_span!("file", path = %file_path.display()).entered();
+        let mut replaced = Vec::new();
+        let result = rewrite_in_place(&file_path, |content, detected_type| {
+            if !matches!(detected_type, FileType::Html) {
+                debug!("Skipped: not HTML");
+                return Ok(None);
+            }
+            let (output, found) = html::replace_texts(content, attr, &texts).map_err(anyhow::Error::msg)?;
+            replaced = found;
+            Ok((!replaced.is_empty()).then_some(output))
+        })
+        .map(|()| replaced);
+
+        match result {
+            Ok(replaced) => {
+                debug!("Replaced the text of {} element(s)", replaced.len());
+                replaced_ids.extend(replaced);
+            }
+            Err(e) => {
+                error_count += 1;
+                error!("{:#}", e);
+            }
+        }
+    }
+
+    let unused = texts.keys().filter(|id| !replaced_ids.contains(*id)).count();
+    if unused > 0 {
+        debug!("{} text(s) unchanged or without a matching element", unused);
+    }
+    info!("Replaced the text of {} ID(s)", replaced_ids.len());
+
+    if error_count > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
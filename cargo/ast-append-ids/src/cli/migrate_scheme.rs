@@ -0,0 +1,82 @@
+use super::{rewrite_in_place, select_elements, FileType};
+use anyhow::{Context, Result};
+use ast_append_ids::file_io;
+use ast_append_ids::manifest::Manifest;
+use ast_append_ids::scheme;
+use ast_append_ids::selector::Selector;
+use ast_append_ids::{html, jsx, xml, IdScheme};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use tracing::{debug, error, info, info_span};
+
+/// Moves the hash IDs of a manifest to the scheme `to`, rewriting the files
+/// and the manifest, and writes the old and new IDs of each file as JSON.
+pub fn run(
+    manifest_path: &Path,
+    to: IdScheme,
+    from_salt: &str,
+    to_salt: &str,
+    mapping_path: Option<&Path>,
+) -> Result<()> {
+    let mut manifest = Manifest::load(manifest_path).map_err(anyhow::Error::msg)?;
+    let all = Selector::parse("*").map_err(anyhow::Error::msg)?;
+    let mut mappings = BTreeMap::new();
+    let mut error_count = 0;
+
+    for (file, manifest_file) in manifest.files.iter_mut() {
+        let _span = info_span!("file", path = %file).entered();
+        // Files are only touched through a copy, so a failure leaves the entry as it was
+        let mut migrated = manifest_file.clone();
+        let mapping = scheme::migrate(&mut migrated, to, from_salt, to_salt);
+
+        let result = if mapping.is_empty() {
+            Ok(())
+        } else {
+            rewrite_in_place(Path::new(file), |content, detected_type| {
+                let attr = &manifest_file.attr;
+                let ids: Vec<Option<String>> = select_elements(content, detected_type, &all, attr)
+                    .map_err(anyhow::Error::msg)?
+                    .into_iter()
+                    .map(|element| element.id.and_then(|id| mapping.get(&id).cloned()))
+                    .collect();
+                let output = match detected_type {
+                    FileType::Jsx => jsx::apply_ids(content, attr, &ids),
+                    FileType::Xml => xml::apply_ids(content, attr, &ids),
+                    FileType::Html => html::apply_ids(content, attr, &ids),
+                    FileType::Auto => unreachable!(),
+                }
+                .map_err(anyhow::Error::msg)?;
+                Ok(Some(output))
+            })
+        };
+
+        match result {
+            Ok(()) => {
+                debug!("Migrated {} ID(s)", mapping.len());
+                *manifest_file = migrated;
+                if !mapping.is_empty() {
+                    mappings.insert(file.clone(), mapping);
+                }
+            }
+            Err(e) => {
+                error_count += 1;
+                error!("{:#}", e);
+            }
+        }
+    }
+
+    manifest.save(manifest_path).map_err(anyhow::Error::msg)?;
+    let json = serde_json::to_string_pretty(&mappings).context("Failed to serialize ID mapping")? + "\n";
+    match mapping_path {
+        Some(path) => file_io::write_output(path, &json).map_err(anyhow::Error::msg)?,
+        None => std::io::stdout().lock().write_all(json.as_bytes())?,
+    }
+
+    let id_count: usize = mappings.values().map(BTreeMap::len).sum();
+    info!("Migrated {} ID(s) in {} file(s), {} error(s)", id_count, mappings.len(), error_count);
+    if error_count > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,15 @@
+use anyhow::{Context, Result};
+use ast_append_ids::file_io;
+use ast_append_ids::IdOptions;
+use std::io::Write;
+use std::path::Path;
+
+/// Writes the JSON Schema of `IdOptions`.
+pub fn run(output: Option<&Path>) -> Result<()> {
+    let schema = serde_json::to_string_pretty(&IdOptions::json_schema()).context("Failed to serialize schema")? + "\n";
+    match output {
+        Some(path) => file_io::write_output(path, &schema).map_err(anyhow::Error::msg)?,
+        None => std::io::stdout().lock().write_all(schema.as_bytes())?,
+    }
+    Ok(())
+}
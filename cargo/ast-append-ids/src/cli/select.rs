@@ -0,0 +1,56 @@
+use super::{find_files, read_with_type, select_elements, FileFilter, FileType};
+use anyhow::Result;
+use ast_append_ids::select;
+use std::io::Write;
+use tracing::{debug, error, info_span};
+
+/// Prints the position, tag, ID and text of the elements matching `query`, a
+/// CSS selector or XPath, in the files under `path_pattern`.
+pub fn run(query: &str, path_pattern: &str, attr: &str) -> Result<()> {
+    let selector = select::parse_query(query).map_err(anyhow::Error::msg)?;
+    let files = find_files(path_pattern, &FileFilter::default(), false)?;
+
+    let mut stdout = std::io::stdout().lock();
+    let mut selected_count = 0;
+    let mut error_count = 0;
+
+    for file_path in files {
+        let _span = info_span!("file", path = %file_path.display()).entered();
+        let result = read_with_type(&file_path, FileType::Auto, false).and_then(|(content, detected_type)| {
+            let elements = select_elements(&content, detected_type, &selector, attr).map_err(anyhow::Error::msg)?;
+            Ok(select::locate(&content, elements))
+        });
+
+        match result {
+            Ok(selected) => {
+                for element in selected {
+                    let id = element.id.as_deref().unwrap_or("-");
+                    writeln!(
+                        stdout,
+                        "{}:{}:{}: {} {} {:?}",
+                        file_path.display(),
+                        element.line,
+                        element.col,
+                        element.tag,
+                        id,
+                        element.text
+                    )?;
+                    selected_count += 1;
+                }
+            }
+            Err(e) => {
+                error_count += 1;
+                error!("{:#}", e);
+            }
+        }
+    }
+
+    debug!("Selected {} element(s), {} error(s)", selected_count, error_count);
+
+    // Like grep, exit with 1 when nothing matched
+    if selected_count == 0 || error_count > 0 {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
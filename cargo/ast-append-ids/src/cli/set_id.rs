@@ -0,0 +1,79 @@
+use super::{read_with_type, FileType};
+use anyhow::Result;
+use ast_append_ids::file_io;
+use ast_append_ids::html;
+use ast_append_ids::id_generator::IdGenerator;
+use ast_append_ids::select;
+use ast_append_ids::xml;
+use std::path::Path;
+use tracing::{debug, info, info_span, warn};
+
+/// Sets `attr` to `value` on the elements of an HTML or XML file matching
+/// `query`, a CSS selector or XPath (see `select::parse_query`). HTML files
+/// take any CSS selector, the other queries the subset `Selector` evaluates.
+pub fn run(file_path: &Path, query: &str, attr: &str, value: &str) -> Result<()> {
+    let _span = info_span!("file", path = %file_path.display()).entered();
+    let (content, detected_type) = read_with_type(file_path, FileType::Auto, false)?;
+
+    let mut generator = IdGenerator::new();
+    let (output, ids) = match detected_type {
+        FileType::Html if !select::is_xpath(query) => html::set_ids(&content, query, attr, value, &mut generator),
+        FileType::Html => select::parse_query(query)
+            .and_then(|selector| html::set_ids_matching(&content, &selector, attr, value, &mut generator)),
+        FileType::Xml => select::parse_query(query)
+            .and_then(|selector| xml::set_ids(&content, &selector, attr, value, &mut generator)),
+        FileType::Jsx => Err("set-id supports HTML and XML files only".to_string()),
+        FileType::Auto => unreachable!(),
+    }
+    .map_err(anyhow::Error::msg)?;
+    drop(content);
+
+    if ids.is_empty() {
+        anyhow::bail!("No elements match selector '{}'", query);
+    }
+    if ids[0] != value {
+        warn!("ID '{}' is already used by another element, wrote '{}'", value, ids[0]);
+    }
+    file_io::write_output(file_path, &output).map_err(anyhow::Error::msg)?;
+
+    for id in &ids {
+        debug!(id = %id, "set ID");
+    }
+    info!("Set {} on {} element(s)", attr, ids.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_set_id_by_xpath() {
+        let dir = std::env::temp_dir().join(format!("ast-append-ids-set-id-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let html_path = dir.join("page.html");
+        let xml_path = dir.join("icons.svg");
+        fs::write(&html_path, r#"<form><button type="submit">Go</button><button>Back</button></form>"#).unwrap();
+        fs::write(&xml_path, r#"<svg><g class="toolbar"><path d="M0"/></g><path d="M1"/></svg>"#).unwrap();
+
+        run(&html_path, "//form/button[@type='submit']", "data-ast-id", "go").unwrap();
+        assert_eq!(
+            fs::read_to_string(&html_path).unwrap(),
+            r#"<form><button type="submit" data-ast-id="go">Go</button><button>Back</button></form>"#
+        );
+        run(&xml_path, "/svg/g/path", "data-ast-id", "icon").unwrap();
+        assert_eq!(
+            fs::read_to_string(&xml_path).unwrap(),
+            r#"<svg><g class="toolbar"><path d="M0" data-ast-id="icon"/></g><path d="M1"/></svg>"#
+        );
+
+        // CSS keeps working, and a query matching nothing is an error
+        run(&html_path, "button:not([type])", "data-ast-id", "back").unwrap();
+        assert!(fs::read_to_string(&html_path).unwrap().contains(r#"<button data-ast-id="back">Back</button>"#));
+        assert!(run(&xml_path, "//circle", "data-ast-id", "x").is_err());
+        assert!(run(&xml_path, "//path[", "data-ast-id", "x").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
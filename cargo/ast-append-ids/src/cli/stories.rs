@@ -0,0 +1,55 @@
+use super::{file_key, find_files, read_with_type, FileFilter, FileType};
+use anyhow::Result;
+use ast_append_ids::file_io;
+use ast_append_ids::jsx::{self, JsxProcessor};
+use ast_append_ids::storybook::{self, StoryIndex};
+use ast_append_ids::{AstProcessor, IdOptions};
+use std::io::Write;
+use std::path::Path;
+use tracing::{debug, error, info, info_span};
+
+/// Tags the story files under `path_pattern` and writes the index of the IDs
+/// each story export renders.
+pub fn run(path_pattern: &str, output: Option<&Path>, options: &IdOptions) -> Result<()> {
+    let mut index = StoryIndex::new();
+    let mut error_count = 0;
+
+    for file_path in find_files(path_pattern, &FileFilter::default(), false)? {
+        if !storybook::is_story_file(&file_path) {
+            continue;
+        }
+        let _span = info_span!("file", path = %file_path.display()).entered();
+        let result = read_with_type(&file_path, FileType::Jsx, false).and_then(|(content, _)| {
+            let processed = JsxProcessor::new().process(&content, options).map_err(anyhow::Error::msg)?;
+            let story_ids = jsx::story_ids(&processed, &options.attr).map_err(anyhow::Error::msg)?;
+            if processed != *content {
+                drop(content);
+                file_io::write_output(&file_path, &processed).map_err(anyhow::Error::msg)?;
+            }
+            Ok(story_ids)
+        });
+
+        match result {
+            Ok(story_ids) => {
+                debug!("Indexed {} story export(s)", story_ids.len());
+                index.add(file_key(&file_path), story_ids);
+            }
+            Err(e) => {
+                error_count += 1;
+                error!("{:#}", e);
+            }
+        }
+    }
+
+    let json = index.to_json().map_err(anyhow::Error::msg)?;
+    match output {
+        Some(path) => file_io::write_output(path, &json).map_err(anyhow::Error::msg)?,
+        None => std::io::stdout().lock().write_all(json.as_bytes())?,
+    }
+    info!("Indexed {} story export(s) in {} file(s)", index.story_count(), index.files.len());
+
+    if error_count > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
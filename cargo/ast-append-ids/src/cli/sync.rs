@@ -0,0 +1,75 @@
+use super::{read_with_type, select_elements, FileType};
+use anyhow::{Context, Result};
+use ast_append_ids::file_io::{self, FileContent};
+use ast_append_ids::html;
+use ast_append_ids::jsx;
+use ast_append_ids::select::SelectedElement;
+use ast_append_ids::selector::Selector;
+use ast_append_ids::sync;
+use ast_append_ids::xml;
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+/// Copies the IDs of `reference_path` to the matching elements of
+/// `target_path`, writing the result to `output` or back to the target.
+pub fn run(reference_path: &Path, target_path: &Path, output: Option<&Path>, attr: &str, overwrite: bool) -> Result<()> {
+    let all = Selector::parse("*").map_err(anyhow::Error::msg)?;
+    let elements = |path: &Path| -> Result<(FileContent, FileType, Vec<SelectedElement>)> {
+        let (content, detected_type) = read_with_type(path, FileType::Auto, false)?;
+        let elements = select_elements(&content, detected_type, &all, attr)
+            .map_err(anyhow::Error::msg)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok((content, detected_type, elements))
+    };
+    let (_, _, reference) = elements(reference_path)?;
+    let (content, target_type, target) = elements(target_path)?;
+
+    let plan = sync::sync_ids(&reference, &target, overwrite);
+    for id in &plan.skipped {
+        debug!(id = %id, "not copied");
+    }
+    if !plan.skipped.is_empty() {
+        warn!("{} reference ID(s) have no free matching element in {}", plan.skipped.len(), target_path.display());
+    }
+
+    let output_path = output.unwrap_or(target_path);
+    if plan.copied > 0 || output.is_some() {
+        let synced = match target_type {
+            FileType::Jsx => jsx::apply_ids(&content, attr, &plan.ids),
+            FileType::Xml => xml::apply_ids(&content, attr, &plan.ids),
+            FileType::Html => html::apply_ids(&content, attr, &plan.ids),
+            FileType::Auto => unreachable!(),
+        }
+        .map_err(anyhow::Error::msg)?;
+        file_io::write_output(output_path, &synced).map_err(anyhow::Error::msg)?;
+    }
+
+    info!("Copied {} ID(s) to {}", plan.copied, output_path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_sync_to_output() {
+        let dir = std::env::temp_dir().join(format!("ast-append-ids-sync-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let reference = dir.join("a.html");
+        let target = dir.join("b.html");
+        let output = dir.join("b.synced.html");
+        fs::write(&reference, r#"<ul data-ast-id="list"><li data-ast-id="first">One</li></ul>"#).unwrap();
+        fs::write(&target, r#"<ul><li>Eins</li></ul>"#).unwrap();
+
+        run(&reference, &target, Some(&output), "data-ast-id", false).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), r#"<ul><li>Eins</li></ul>"#);
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            r#"<ul data-ast-id="list"><li data-ast-id="first">Eins</li></ul>"#
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
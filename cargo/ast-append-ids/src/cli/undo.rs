@@ -0,0 +1,68 @@
+use super::{rewrite_in_place, FileType};
+use anyhow::{Context, Result};
+use ast_append_ids::manifest::Manifest;
+use ast_append_ids::{html, jsx, xml};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, error, info, info_span, warn};
+
+/// Removes the IDs recorded in a manifest from their files, and the files
+/// restored from the manifest.
+pub fn run(manifest_path: &Path) -> Result<()> {
+    let mut manifest = Manifest::load(manifest_path).map_err(anyhow::Error::msg)?;
+    let files: Vec<String> = manifest.files.keys().cloned().collect();
+
+    let mut success_count = 0;
+    let mut error_count = 0;
+
+    for file in files {
+        let _span = info_span!("file", path = %file).entered();
+        let file_path = PathBuf::from(&file);
+        let manifest_file = &manifest.files[&file];
+
+        let result = rewrite_in_place(&file_path, |content, detected_type| {
+            let ids = manifest_file.ids();
+            let origin_attr = manifest_file.origin_attr.as_deref();
+            let restored = match detected_type {
+                FileType::Jsx => jsx::remove_ids(content, &manifest_file.attr, &ids, origin_attr),
+                FileType::Xml => xml::remove_ids(content, &manifest_file.attr, &ids, origin_attr),
+                FileType::Html => html::remove_ids(content, &manifest_file.attr, &ids, origin_attr),
+                FileType::Auto => unreachable!(),
+            }
+            .map_err(anyhow::Error::msg)?;
+            Ok(Some(restored))
+        });
+
+        match result {
+            Ok(()) => {
+                success_count += 1;
+                debug!("Restored");
+                manifest.remove_file(&file);
+            }
+            Err(e) => {
+                error_count += 1;
+                error!("{:#}", e);
+            }
+        }
+    }
+
+    // Keep entries for files that could not be restored so undo can be retried
+    if manifest.files.is_empty() {
+        fs::remove_file(manifest_path)
+            .with_context(|| format!("Failed to remove manifest: {}", manifest_path.display()))?;
+    } else {
+        manifest.save(manifest_path).map_err(anyhow::Error::msg)?;
+    }
+
+    if error_count == 0 {
+        info!("Restored {} file(s) successfully, {} error(s)", success_count, error_count);
+    } else {
+        warn!("Restored {} file(s) successfully, {} error(s)", success_count, error_count);
+    }
+
+    if error_count > 0 {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// A Testing Library text query with a single string argument, e.g.
+/// `screen.getByText("Submit")`, located by byte ranges into its source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextQuery {
+    /// The method name, e.g. `getByText`
+    pub method: String,
+    pub method_range: Range<usize>,
+    pub text: String,
+    /// The string literal, quotes included
+    pub arg_range: Range<usize>,
+    pub quote: char,
+}
+
+/// Query methods that have a `ByTestId` counterpart
+pub const TEXT_QUERY_METHODS: [&str; 6] = [
+    "getByText",
+    "getAllByText",
+    "queryByText",
+    "queryAllByText",
+    "findByText",
+    "findAllByText",
+];
+
+/// Collapses whitespace the way Testing Library's default text matcher does.
+pub fn normalize_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Maps element text to the ID of the only element showing it. Texts shared by
+/// elements with different IDs are left out, since a query for them cannot be
+/// rewritten to a single ID.
+pub fn text_index(texts: impl IntoIterator<Item = (String, String)>) -> HashMap<String, String> {
+    let mut ids: HashMap<String, Option<String>> = HashMap::new();
+    for (id, text) in texts {
+        let text = normalize_text(&text);
+        if text.is_empty() {
+            continue;
+        }
+        ids.entry(text)
+            .and_modify(|existing| {
+                if existing.as_deref() != Some(id.as_str()) {
+                    *existing = None;
+                }
+            })
+            .or_insert(Some(id));
+    }
+    ids.into_iter().filter_map(|(text, id)| Some((text, id?))).collect()
+}
+
+/// Rewrites each query whose text is in `ids` to the matching `ByTestId` query,
+/// leaving the rest of `content` untouched. Returns the output and the number
+/// of queries rewritten.
+pub fn rewrite_text_queries(content: &str, mut queries: Vec<TextQuery>, ids: &HashMap<String, String>) -> (String, usize) {
+    queries.sort_by_key(|query| query.method_range.start);
+
+    let mut output = String::with_capacity(content.len());
+    let mut copied = 0;
+    let mut rewritten = 0;
+    for query in queries {
+        let Some(id) = ids.get(&normalize_text(&query.text)) else {
+            continue;
+        };
+        let escaped = id.replace('\\', "\\\\").replace(query.quote, &format!("\\{}", query.quote));
+
+        output.push_str(&content[copied..query.method_range.start]);
+        output.push_str(&query.method.replace("ByText", "ByTestId"));
+        output.push_str(&content[query.method_range.end..query.arg_range.start]);
+        output.push_str(&format!("{}{}{}", query.quote, escaped, query.quote));
+        copied = query.arg_range.end;
+        rewritten += 1;
+    }
+    output.push_str(&content[copied..]);
+
+    (output, rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_text_queries() {
+        let ids = text_index(vec![
+            ("el-submit".to_string(), "  Submit\n  order ".to_string()),
+            ("el-a".to_string(), "Cancel".to_string()),
+            ("el-b".to_string(), "Cancel".to_string()),
+            ("el-c".to_string(), "Help".to_string()),
+            ("el-c".to_string(), "Help".to_string()),
+        ]);
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids["Submit order"], "el-submit");
+
+        let content = "click(getByText('Submit order'));\nexpect(queryByText('Cancel')).toBeNull();";
+        let query = |method: &str, text: &str, quote| {
+            let start = content.find(&format!("{}(", method)).unwrap();
+            let arg = start + method.len() + 1;
+            TextQuery {
+                method: method.to_string(),
+                method_range: start..start + method.len(),
+                text: text.to_string(),
+                arg_range: arg..arg + text.len() + 2,
+                quote,
+            }
+        };
+        let queries = vec![query("queryByText", "Cancel", '\''), query("getByText", "Submit order", '\'')];
+
+        let (output, rewritten) = rewrite_text_queries(content, queries, &ids);
+        assert_eq!(rewritten, 1);
+        assert_eq!(output, "click(getByTestId('el-submit'));\nexpect(queryByText('Cancel')).toBeNull();");
+    }
+}
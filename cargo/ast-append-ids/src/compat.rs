@@ -0,0 +1,94 @@
+use crate::IdOptions;
+use serde_json::{Map, Value};
+
+/// Keys of the react-append-ids and rehype-append-ids option objects that
+/// are not just camelCase, and the options they stand for
+const PLUGIN_KEYS: [(&str, &str); 3] = [("tags", "include"), ("skip", "exclude"), ("idPropName", "attr")];
+
+/// `options` as the JavaScript plugins this crate replaces spell them, with
+/// the keys renamed to those of `IdOptions`: camelCase becomes snake_case,
+/// `tags` is `include`, `skip` is `exclude` and `idPropName` is `attr`. A
+/// single tag name stands for a list of one. Keys already named as in
+/// `IdOptions` are kept, so both shapes can be mixed, but not for the same
+/// option.
+pub fn from_plugin_options(options: Map<String, Value>) -> Result<Map<String, Value>, String> {
+    let mut translated = Map::new();
+    // Key each option was given as, for reporting duplicates
+    let mut given_as = Map::new();
+    for (key, mut value) in options {
+        let name = match PLUGIN_KEYS.iter().find(|(plugin_key, _)| *plugin_key == key) {
+            Some((_, name)) => name.to_string(),
+            None => snake_case(&key),
+        };
+        if matches!(key.as_str(), "tags" | "skip") {
+            if let Value::String(tag) = value {
+                value = Value::Array(vec![Value::String(tag)]);
+            }
+        }
+        if let Some(Value::String(other)) = given_as.insert(name.clone(), Value::String(key.clone())) {
+            return Err(format!("Option '{}' is given twice, as '{}' and '{}'", name, other, key));
+        }
+        translated.insert(name, value);
+    }
+    Ok(translated)
+}
+
+/// Options from a JavaScript object in either shape `from_plugin_options`
+/// accepts, over the defaults. Unknown keys and invalid values are errors.
+pub fn options_from_value(value: Value) -> Result<IdOptions, String> {
+    let Value::Object(options) = value else {
+        return Err("Invalid options: expected an object".to_string());
+    };
+    let mut merged = match serde_json::to_value(IdOptions::default()).map_err(|e| e.to_string())? {
+        Value::Object(defaults) => defaults,
+        _ => unreachable!("options serialize to an object"),
+    };
+    merged.extend(from_plugin_options(options)?);
+    let options: IdOptions =
+        serde_json::from_value(Value::Object(merged)).map_err(|e| format!("Invalid options: {}", e))?;
+    options.validate()?;
+    Ok(options)
+}
+
+fn snake_case(key: &str) -> String {
+    let mut name = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            name.push('_');
+            name.push(c.to_ascii_lowercase());
+        } else {
+            name.push(c);
+        }
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_plugin_options() {
+        let options = options_from_value(json!({
+            "idPropName": "data-testid",
+            "tags": ["button", "a"],
+            "skip": "svg",
+            "templateTags": ["html"],
+            "prefix": "qa-",
+        }))
+        .unwrap();
+        assert_eq!(options.attr, "data-testid");
+        assert_eq!(options.include, vec!["button", "a"]);
+        assert_eq!(options.exclude, vec!["svg"]);
+        assert_eq!(options.template_tags, vec!["html"]);
+        assert_eq!(options.prefix, "qa-");
+        assert!(options.templates);
+
+        let err = options_from_value(json!({"attr": "data-id", "idPropName": "data-testid"})).unwrap_err();
+        assert_eq!(err, "Option 'attr' is given twice, as 'attr' and 'idPropName'");
+        let err = options_from_value(json!({"idPerfix": "x-"})).unwrap_err();
+        assert!(err.starts_with("Invalid options: unknown field `id_perfix`"), "{}", err);
+        assert!(options_from_value(json!({"tags": [""]})).is_err());
+    }
+}
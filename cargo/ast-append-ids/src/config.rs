@@ -0,0 +1,238 @@
+use crate::compat;
+use crate::IdOptions;
+use glob::Pattern;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Config file read when `--config` does not name another
+pub const DEFAULT_CONFIG_FILE: &str = "ast-append-ids.toml";
+
+/// Named option sets and per-path overrides from a TOML config file:
+///
+/// ```toml
+/// [profile.e2e]
+/// attr = "data-testid"
+/// strategy = "slug"
+/// paths = ["src/**/*.tsx"]
+///
+/// [[rules]]
+/// glob = "src/emails/**"
+/// attr = "data-email-id"
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub profiles: BTreeMap<String, Profile>,
+    /// In file order; later rules win
+    pub rules: Vec<Rule>,
+}
+
+/// Options of a profile, over the defaults, and the files it applies to
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub options: IdOptions,
+    /// Files, directories or glob patterns
+    pub paths: Vec<String>,
+}
+
+/// Options replaced for the files whose relative path matches `glob`
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub glob: Pattern,
+    pub overrides: Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    profile: BTreeMap<String, toml::Table>,
+    #[serde(default)]
+    rules: Vec<toml::Table>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+        Self::parse(&content).map_err(|e| format!("Invalid config {}: {}", path.display(), e))
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let file: ConfigFile = toml::from_str(content).map_err(|e| e.to_string())?;
+        let profiles = file
+            .profile
+            .into_iter()
+            .map(|(name, table)| {
+                let profile = Profile::from_table(table).map_err(|e| format!("profile '{}': {}", name, e))?;
+                Ok((name, profile))
+            })
+            .collect::<Result<_, String>>()?;
+        let rules = file
+            .rules
+            .into_iter()
+            .enumerate()
+            .map(|(i, table)| Rule::from_table(table).map_err(|e| format!("rule {}: {}", i + 1, e)))
+            .collect::<Result<_, String>>()?;
+        Ok(Self { profiles, rules })
+    }
+
+    pub fn profile(&self, name: &str) -> Result<&Profile, String> {
+        self.profiles.get(name).ok_or_else(|| {
+            let names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            format!("Unknown profile '{}' (defined: {})", name, names.join(", "))
+        })
+    }
+}
+
+impl Profile {
+    fn from_table(mut table: toml::Table) -> Result<Self, String> {
+        let paths = match table.remove("paths") {
+            Some(paths) => paths.try_into().map_err(|e| format!("paths: {}", e))?,
+            None => Vec::new(),
+        };
+        Ok(Self {
+            options: override_options(&IdOptions::default(), table_to_map(table)?)?,
+            paths,
+        })
+    }
+}
+
+impl Rule {
+    fn from_table(mut table: toml::Table) -> Result<Self, String> {
+        let glob = match table.remove("glob") {
+            Some(toml::Value::String(glob)) => glob,
+            Some(_) => return Err("glob: expected a string".to_string()),
+            None => return Err("missing field `glob`".to_string()),
+        };
+        let glob = Pattern::new(&glob).map_err(|e| format!("glob '{}': {}", glob, e))?;
+        let overrides = table_to_map(table)?;
+        // Checked over the defaults so typos surface before any file matches
+        override_options(&IdOptions::default(), overrides.clone())?;
+        Ok(Self { glob, overrides })
+    }
+
+    /// Whether the rule covers `path`, relative with `/` separators
+    pub fn matches(&self, path: &str) -> bool {
+        self.glob.matches(path)
+    }
+}
+
+/// `options` with the overrides of every rule matching `path` applied in
+/// order, or `None` when no rule matches
+pub fn apply_rules(rules: &[Rule], options: &IdOptions, path: &str) -> Result<Option<IdOptions>, String> {
+    let mut matching = rules.iter().filter(|rule| rule.matches(path)).peekable();
+    if matching.peek().is_none() {
+        return Ok(None);
+    }
+    let overrides = matching.flat_map(|rule| rule.overrides.clone());
+    override_options(options, overrides).map(Some)
+}
+
+/// Options of a table, which may also be spelled as the JavaScript plugins
+/// do, see `compat::from_plugin_options`
+fn table_to_map(table: toml::Table) -> Result<Map<String, Value>, String> {
+    match serde_json::to_value(table).map_err(|e| e.to_string())? {
+        Value::Object(map) => compat::from_plugin_options(map),
+        _ => unreachable!("a table serializes to an object"),
+    }
+}
+
+/// `base` with the options named in `overrides` replaced by their values.
+/// Unknown names and invalid values are errors.
+pub fn override_options(
+    base: &IdOptions,
+    overrides: impl IntoIterator<Item = (String, Value)>,
+) -> Result<IdOptions, String> {
+    let mut options = serde_json::to_value(base).map_err(|e| e.to_string())?;
+    for (name, value) in overrides {
+        options[name] = value;
+    }
+    let options: IdOptions = serde_json::from_value(options).map_err(|e| e.to_string())?;
+    options.validate()?;
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IdStrategy;
+
+    #[test]
+    fn test_config_profiles() {
+        let config = Config::parse(
+            r#"
+            [profile.e2e]
+            attr = "data-testid"
+            strategy = "slug"
+            include = ["button", "a"]
+            paths = ["src/**/*.tsx"]
+
+            [profile.analytics]
+            attr = "data-track-id"
+            "#,
+        )
+        .unwrap();
+
+        let e2e = config.profile("e2e").unwrap();
+        assert_eq!(e2e.options.attr, "data-testid");
+        assert_eq!(e2e.options.strategy, IdStrategy::Slug);
+        assert_eq!(e2e.options.include, vec!["button", "a"]);
+        assert_eq!(e2e.options.prefix, "el-");
+        assert_eq!(e2e.paths, vec!["src/**/*.tsx"]);
+        assert!(config.profile("analytics").unwrap().paths.is_empty());
+        assert_eq!(
+            config.profile("qa").unwrap_err(),
+            "Unknown profile 'qa' (defined: analytics, e2e)"
+        );
+
+        let err = Config::parse("[profile.e2e]\nperfix = \"x-\"").unwrap_err();
+        assert!(err.starts_with("profile 'e2e': unknown field `perfix`"), "{}", err);
+        let err = Config::parse("[profile.e2e]\nattr = \"\"").unwrap_err();
+        assert_eq!(err, "profile 'e2e': Invalid option 'attr': must not be empty");
+
+        // As the JavaScript plugins spell options
+        let config = Config::parse("[profile.e2e]\nidPropName = \"data-testid\"\ntags = [\"button\"]\nincludeClass = [\"cta\"]").unwrap();
+        let e2e = config.profile("e2e").unwrap();
+        assert_eq!(e2e.options.attr, "data-testid");
+        assert_eq!(e2e.options.include, vec!["button"]);
+        assert_eq!(e2e.options.include_class, vec!["cta"]);
+        let err = Config::parse("[profile.e2e]\nattr = \"\"").unwrap_err();
+        assert_eq!(err, "profile 'e2e': Invalid option 'attr': must not be empty");
+        assert!(Config::parse("[profiles.e2e]").is_err());
+    }
+
+    #[test]
+    fn test_config_rules() {
+        let config = Config::parse(
+            r#"
+            [[rules]]
+            glob = "src/emails/**"
+            attr = "data-email-id"
+            strategy = "path"
+
+            [[rules]]
+            glob = "src/emails/legacy/*.html"
+            prefix = "legacy-"
+            "#,
+        )
+        .unwrap();
+        let base = IdOptions::default();
+
+        assert!(apply_rules(&config.rules, &base, "src/App.tsx").unwrap().is_none());
+        let email = apply_rules(&config.rules, &base, "src/emails/Welcome.tsx").unwrap().unwrap();
+        assert_eq!(email.attr, "data-email-id");
+        assert_eq!(email.strategy, IdStrategy::Path);
+        assert_eq!(email.prefix, "el-");
+        let legacy = apply_rules(&config.rules, &base, "src/emails/legacy/reset.html").unwrap().unwrap();
+        assert_eq!(legacy.attr, "data-email-id");
+        assert_eq!(legacy.prefix, "legacy-");
+
+        let err = Config::parse("[[rules]]\nattr = \"x\"").unwrap_err();
+        assert_eq!(err, "rule 1: missing field `glob`");
+        let err = Config::parse("[[rules]]\nglob = \"src/**\"\nstrategy = \"random\"").unwrap_err();
+        assert!(err.starts_with("rule 1: unknown variant `random`"), "{}", err);
+    }
+}
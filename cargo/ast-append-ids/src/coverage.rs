@@ -0,0 +1,109 @@
+use crate::ast_common;
+
+/// An element matched by a coverage policy, located by byte offset into its
+/// source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyElement {
+    pub offset: usize,
+    pub tag: String,
+    /// Whether the element carries the ID attribute with a value; an empty
+    /// one does not name the element
+    pub covered: bool,
+}
+
+/// A policy element without the ID attribute. `line` and `col` are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Uncovered {
+    pub line: usize,
+    pub col: usize,
+    pub tag: String,
+    pub snippet: String,
+}
+
+/// How many of a document's policy elements carry the ID attribute
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    pub total: usize,
+    pub covered: usize,
+    /// In source order
+    pub uncovered: Vec<Uncovered>,
+}
+
+impl Coverage {
+    pub fn new(content: &str, mut elements: Vec<PolicyElement>) -> Self {
+        let total = elements.len();
+        elements.retain(|element| !element.covered);
+        elements.sort_by_key(|element| element.offset);
+        let positions = ast_common::line_columns(content, elements.iter().map(|element| element.offset));
+
+        let uncovered = elements
+            .into_iter()
+            .zip(positions)
+            .map(|(element, (line, col))| Uncovered {
+                line,
+                col,
+                snippet: ast_common::source_snippet(content, element.offset),
+                tag: element.tag,
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            total,
+            covered: total - uncovered.len(),
+            uncovered,
+        }
+    }
+
+    /// Adds another document's counts, for an aggregate over several files.
+    /// Uncovered elements are not carried over.
+    pub fn add(&mut self, other: &Coverage) {
+        self.total += other.total;
+        self.covered += other.covered;
+    }
+
+    /// Covered share in percent; 100 when the policy matched nothing.
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.covered as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage() {
+        let content = "<a data-ast-id=\"x\"></a>\n  <button>Save</button>";
+        let elements = vec![
+            PolicyElement {
+                offset: content.find("<button").unwrap(),
+                tag: "button".to_string(),
+                covered: false,
+            },
+            PolicyElement { offset: 0, tag: "a".to_string(), covered: true },
+        ];
+
+        let coverage = Coverage::new(content, elements);
+        assert_eq!((coverage.total, coverage.covered), (2, 1));
+        assert_eq!(coverage.percent(), 50.0);
+        assert_eq!(
+            coverage.uncovered,
+            vec![Uncovered {
+                line: 2,
+                col: 3,
+                tag: "button".to_string(),
+                snippet: "<button>Save</button>".to_string(),
+            }]
+        );
+
+        let mut total = Coverage::default();
+        assert_eq!(total.percent(), 100.0);
+        total.add(&coverage);
+        total.add(&Coverage::new("", Vec::new()));
+        assert_eq!((total.total, total.covered, total.uncovered.len()), (2, 1, 0));
+    }
+}
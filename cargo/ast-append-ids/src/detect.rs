@@ -0,0 +1,313 @@
+use regex::Regex;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Kinds of file `detect` tells apart, serialized as their `name`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Jsx,
+    Xml,
+    Html,
+    /// JavaScript or TypeScript without JSX, which has nothing to tag
+    Script,
+    /// Vue single-file component, which no processor handles
+    Vue,
+}
+
+impl Format {
+    const ALL: [Format; 5] = [Format::Jsx, Format::Xml, Format::Html, Format::Script, Format::Vue];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Jsx => "jsx",
+            Format::Xml => "xml",
+            Format::Html => "html",
+            Format::Script => "script",
+            Format::Vue => "vue",
+        }
+    }
+}
+
+/// Outcome of `detect`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Detection {
+    pub format: Format,
+    /// Share of the evidence found that points to `format`, from 0 to 1; 0
+    /// when nothing was found and `format` is a fallback
+    pub confidence: f64,
+    /// Signals that pointed to `format`
+    pub signals: Vec<&'static str>,
+}
+
+/// Evidence for a format: a signal name and its weight
+struct Score {
+    format: Format,
+    signal: &'static str,
+    weight: u32,
+}
+
+fn jsx_pragma_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"@jsx(?:ImportSource|Runtime|Frag)?\s").unwrap())
+}
+
+fn jsx_import_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r#"(?:from\s*|require\s*\(\s*)['"](?:react|react-dom|preact|solid-js)(?:/[^'"]*)?['"]"#).unwrap()
+    })
+}
+
+fn import_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r#"(?m)^\s*(?:import\s[^;]*?\bfrom\s*['"]|import\s*['"]|export\s|(?:const|let|var)\s+\w+\s*=\s*require\s*\()"#)
+            .unwrap()
+    })
+}
+
+/// A tag where an expression is expected: after `return`, `=>`, an operator,
+/// an opening bracket or a separator. Plain comparisons such as `a < b` do not
+/// match, as the tag name must be followed by `>`, `/` or whitespace and an
+/// attribute or the end of the tag.
+fn jsx_tag_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"(?:\breturn|=>|[=(,:?\[{]|&&|\|\|)\s*<(?:>|[A-Za-z][\w.:-]*(?:\s*/?>|\s+[A-Za-z_{][\w:-]*))")
+            .unwrap()
+    })
+}
+
+fn vue_block_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?m)^<(?:script|style)[\s>]").unwrap())
+}
+
+/// Works out what `content` is from its extension and the signals in it: a
+/// shebang, doctype or XML declaration, JSX pragmas, imports and JSX tags. Each
+/// signal adds weight to a format; the heaviest format wins.
+pub fn detect(path: Option<&Path>, content: &str) -> Detection {
+    let mut scores = Vec::new();
+    let mut add = |format, signal, weight| scores.push(Score { format, signal, weight });
+
+    let extension = path
+        .and_then(Path::extension)
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("jsx" | "tsx") => add(Format::Jsx, "extension", 4),
+        Some("xml" | "svg" | "mml") => add(Format::Xml, "extension", 4),
+        Some("html" | "htm") => add(Format::Html, "extension", 4),
+        Some("vue") => add(Format::Vue, "extension", 4),
+        // TypeScript only allows JSX in .tsx files
+        Some("ts" | "mts" | "cts") => add(Format::Script, "extension", 4),
+        // JSX found in the content outweighs this
+        Some("js" | "mjs" | "cjs") => add(Format::Script, "extension", 2),
+        _ => {}
+    }
+
+    let start = content.trim_start_matches('\u{feff}').trim_start();
+    let head: String = start.chars().take(15).collect::<String>().to_ascii_lowercase();
+    if start.starts_with("#!") {
+        add(Format::Script, "shebang", 2);
+    }
+    if head.starts_with("<?xml") {
+        add(Format::Xml, "xml declaration", 4);
+    } else if head.starts_with("<!doctype html") {
+        add(Format::Html, "doctype", 4);
+    } else if head.starts_with("<!doctype") {
+        add(Format::Xml, "doctype", 2);
+    } else if head.starts_with("<html") {
+        add(Format::Html, "html element", 3);
+    } else if head.starts_with("<svg") {
+        add(Format::Xml, "svg element", 3);
+    } else if head.starts_with("<math") {
+        add(Format::Xml, "math element", 3);
+    } else if head.starts_with("<template") && vue_block_regex().is_match(content) {
+        add(Format::Vue, "template and script blocks", 4);
+    } else if head.starts_with('<') {
+        add(Format::Html, "markup", 1);
+    }
+
+    if jsx_pragma_regex().is_match(content) {
+        add(Format::Jsx, "jsx pragma", 3);
+    }
+    if jsx_import_regex().is_match(content) {
+        add(Format::Jsx, "jsx library import", 2);
+    } else if import_regex().is_match(content) {
+        add(Format::Script, "imports", 1);
+    }
+    // Markup starts with a tag; only scripts can hold tags in expressions
+    if !head.starts_with('<') && jsx_tag_regex().is_match(content) {
+        add(Format::Jsx, "jsx tags", 3);
+    }
+
+    let total: u32 = scores.iter().map(|score| score.weight).sum();
+    let weight = |format| -> u32 { scores.iter().filter(|score| score.format == format).map(|score| score.weight).sum() };
+    // Ties go to the format listed first
+    let format = Format::ALL
+        .into_iter()
+        .fold(None, |best: Option<(Format, u32)>, format| match best {
+            Some((_, best_weight)) if best_weight >= weight(format) => best,
+            _ => Some((format, weight(format))),
+        })
+        .filter(|&(_, weight)| weight > 0)
+        .map(|(format, _)| format);
+
+    match format {
+        Some(format) => Detection {
+            format,
+            confidence: f64::from(weight(format)) / f64::from(total),
+            signals: scores.iter().filter(|score| score.format == format).map(|score| score.signal).collect(),
+        },
+        None => Detection { format: Format::Script, confidence: 0.0, signals: Vec::new() },
+    }
+}
+
+/// Number of leading bytes `is_binary` needs
+pub const BINARY_CHECK_BYTES: usize = 8192;
+
+/// Average line length above which a file counts as minified
+const MINIFIED_LINE_LENGTH: usize = 500;
+
+/// Files smaller than this are never reported as minified
+const MINIFIED_MIN_SIZE: usize = 2048;
+
+/// Whether the first bytes of a file (`BINARY_CHECK_BYTES` of them, or all if
+/// it is shorter) look binary: a NUL byte or invalid UTF-8. A multi-byte
+/// character cut off at the end does not count.
+pub fn is_binary(head: &[u8]) -> bool {
+    head.contains(&0) || std::str::from_utf8(head).is_err_and(|e| e.error_len().is_some())
+}
+
+/// Runtime markers bundlers put at the start of their output
+fn bundle_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"__webpack_require__|webpackBootstrap|webpackChunk|parcelRequire|/\*! For license information please see")
+            .unwrap()
+    })
+}
+
+/// `@generated` banner of a code generator
+fn generated_banner_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^\s*(?://|/?\*|<!--)\s*@generated\b").unwrap())
+}
+
+/// Why `content` looks machine-generated, if it does: a `.min.` file name,
+/// lines long enough to be minified, or the markers bundlers and code
+/// generators leave behind. Such files are rebuilt from sources, which is
+/// where IDs belong.
+pub fn generated(path: Option<&Path>, content: &str) -> Option<&'static str> {
+    let name = path.and_then(Path::file_name).and_then(|name| name.to_str()).unwrap_or_default();
+    if name.contains(".min.") {
+        return Some("minified file name");
+    }
+    if content.len() >= MINIFIED_MIN_SIZE && content.len() / content.lines().count() > MINIFIED_LINE_LENGTH {
+        return Some("minified content");
+    }
+    let mut head_end = content.len().min(BINARY_CHECK_BYTES);
+    while !content.is_char_boundary(head_end) {
+        head_end -= 1;
+    }
+    if bundle_regex().is_match(&content[..head_end]) {
+        return Some("bundle");
+    }
+    if content.lines().take(5).any(|line| generated_banner_regex().is_match(line)) {
+        return Some("@generated banner");
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(path: &str, content: &str) -> Format {
+        detect(Some(Path::new(path)), content).format
+    }
+
+    #[test]
+    fn test_detect_by_extension_and_content() {
+        assert_eq!(format("App.tsx", "export const App = () => null;"), Format::Jsx);
+        assert_eq!(format("page.html", "<div></div>"), Format::Html);
+        assert_eq!(format("data", "<?xml version=\"1.0\"?><root/>"), Format::Xml);
+        assert_eq!(format("icon", "  <svg viewBox=\"0 0 1 1\"/>"), Format::Xml);
+        assert_eq!(format("formula.mml", "<math><mi>x</mi></math>"), Format::Xml);
+        assert_eq!(format("formula", "<math display=\"block\"><mn>1</mn></math>"), Format::Xml);
+        assert_eq!(format("page", "<!DOCTYPE html>\n<html></html>"), Format::Html);
+        assert_eq!(format("fragment", "<ul><li>a</li></ul>"), Format::Html);
+        assert_eq!(format("empty", ""), Format::Script);
+    }
+
+    #[test]
+    fn test_detect_plain_scripts() {
+        let js = "import { add } from './math';\nexport const sum = (xs) => xs.reduce((a, b) => add(a, b), 0);\nif (a < b && c > d) {}";
+        let detection = detect(Some(Path::new("sum.js")), js);
+        assert_eq!(detection.format, Format::Script);
+        assert_eq!(detection.signals, vec!["extension", "imports"]);
+        assert_eq!(detection.confidence, 1.0);
+
+        assert_eq!(format("cli", "#!/usr/bin/env node\nconst run = () => main();"), Format::Script);
+        assert_eq!(format("tool.ts", "const pick = <T,>(xs: T[]) => xs[0];"), Format::Script);
+    }
+
+    #[test]
+    fn test_detect_jsx_in_scripts() {
+        assert_eq!(format("Button.js", "const Button = () => <button>Go</button>;"), Format::Jsx);
+        assert_eq!(format("List.js", "function List() {\n  return (\n    <ul className=\"list\"></ul>\n  );\n}"), Format::Jsx);
+        assert_eq!(format("Frag.js", "const a = <><b /></>;"), Format::Jsx);
+
+        let detection = detect(Some(Path::new("view.js")), "/** @jsx h */\nimport { h } from 'preact';\nexport default h;");
+        assert_eq!(detection.format, Format::Jsx);
+        assert_eq!(detection.signals, vec!["jsx pragma", "jsx library import"]);
+        assert!((detection.confidence - 5.0 / 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_detection_serializes() {
+        let detection = detect(Some(Path::new("page.html")), "<!DOCTYPE html>\n<html></html>");
+        let json = serde_json::to_value(&detection).unwrap();
+        assert_eq!(json["format"], "html");
+        assert_eq!(json["confidence"], 1.0);
+        assert_eq!(json["signals"], serde_json::json!(["extension", "doctype"]));
+        assert!(Format::ALL.iter().all(|format| serde_json::to_value(format).unwrap() == format.name()));
+    }
+
+    #[test]
+    fn test_detect_vue() {
+        let sfc = "<template>\n  <div>{{ msg }}</div>\n</template>\n\n<script setup>\nconst msg = 'hi';\n</script>\n";
+        assert_eq!(format("Hello.vue", sfc), Format::Vue);
+        assert_eq!(format("Hello", sfc), Format::Vue);
+        assert_eq!(format("snippet", "<template><p>a</p></template>"), Format::Html);
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(is_binary(b"\x89PNG\r\n\x1a\n\0\0"));
+        assert!(is_binary(b"abc\xff\xfedef"));
+        assert!(!is_binary("<p>caf\u{e9}</p>".as_bytes()));
+        // A character cut off by the end of the head
+        assert!(!is_binary(&"<p>\u{e9}".as_bytes()[..4]));
+    }
+
+    #[test]
+    fn test_generated() {
+        let page = "<div>\n  <p>text</p>\n</div>\n".repeat(200);
+        assert_eq!(generated(Some(Path::new("page.html")), &page), None);
+        assert_eq!(generated(Some(Path::new("app.min.js")), "a"), Some("minified file name"));
+
+        let minified = format!("<div>{}</div>", "<p>x</p>".repeat(400));
+        assert_eq!(generated(Some(Path::new("index.html")), &minified), Some("minified content"));
+        let short = "<div><p>x</p></div>";
+        assert_eq!(generated(Some(Path::new("index.html")), short), None);
+
+        let bundle = "(() => {\n  var __webpack_require__ = {};\n})();\n";
+        assert_eq!(generated(None, bundle), Some("bundle"));
+        let banner = "/**\n * @generated by relay-compiler\n */\nexport default {};\n";
+        assert_eq!(generated(None, banner), Some("@generated banner"));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::ast_common::IdLocation;
+use crate::id_generator::Assignment;
+use crate::manifest::Manifest;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// An ID together with a fingerprint of the element carrying it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdEntry {
+    pub id: String,
+    pub tag: String,
+    pub fingerprint: String,
+}
+
+impl From<IdLocation> for IdEntry {
+    fn from(location: IdLocation) -> Self {
+        Self {
+            id: location.id,
+            tag: location.tag,
+            fingerprint: location.fingerprint,
+        }
+    }
+}
+
+impl From<Assignment> for IdEntry {
+    fn from(assignment: Assignment) -> Self {
+        Self {
+            id: assignment.id,
+            tag: assignment.tag,
+            fingerprint: assignment.fingerprint,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    Added,
+    Removed,
+    /// The ID is on an element whose tag or attributes changed
+    Changed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdDiff {
+    pub kind: DiffKind,
+    pub id: String,
+    /// Tag in the new version, or the old one for removed IDs
+    pub tag: String,
+}
+
+/// Compares the IDs of two versions of a document. Added and changed IDs come
+/// in the new version's order, followed by removed IDs in the old version's
+/// order. When an ID appears twice, its first element is compared.
+pub fn diff_ids(old: Vec<IdEntry>, new: Vec<IdEntry>) -> Vec<IdDiff> {
+    let mut old_by_id = HashMap::new();
+    for entry in &old {
+        old_by_id.entry(entry.id.as_str()).or_insert(entry);
+    }
+
+    let mut diffs = Vec::new();
+    let mut seen = HashSet::new();
+    for entry in &new {
+        if !seen.insert(entry.id.as_str()) {
+            continue;
+        }
+        let kind = match old_by_id.get(entry.id.as_str()) {
+            None => DiffKind::Added,
+            Some(old) if old.fingerprint != entry.fingerprint => DiffKind::Changed,
+            Some(_) => continue,
+        };
+        diffs.push(IdDiff {
+            kind,
+            id: entry.id.clone(),
+            tag: entry.tag.clone(),
+        });
+    }
+
+    for entry in &old {
+        if seen.insert(entry.id.as_str()) {
+            diffs.push(IdDiff {
+                kind: DiffKind::Removed,
+                id: entry.id.clone(),
+                tag: entry.tag.clone(),
+            });
+        }
+    }
+
+    diffs
+}
+
+/// Compares two manifests file by file. Files without differences are left out.
+pub fn diff_manifests(old: &Manifest, new: &Manifest) -> BTreeMap<String, Vec<IdDiff>> {
+    let entries = |manifest: &Manifest, file: &str| -> Vec<IdEntry> {
+        manifest
+            .files
+            .get(file)
+            .map(|file| file.entries.iter().cloned().map(IdEntry::from).collect())
+            .unwrap_or_default()
+    };
+
+    old.files
+        .keys()
+        .chain(new.files.keys())
+        .filter_map(|file| {
+            let diffs = diff_ids(entries(old, file), entries(new, file));
+            (!diffs.is_empty()).then(|| (file.clone(), diffs))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, fingerprint: &str) -> IdEntry {
+        IdEntry {
+            id: id.to_string(),
+            tag: "div".to_string(),
+            fingerprint: fingerprint.to_string(),
+        }
+    }
+
+    fn summary(diffs: &[IdDiff]) -> Vec<(DiffKind, &str)> {
+        diffs.iter().map(|diff| (diff.kind, diff.id.as_str())).collect()
+    }
+
+    #[test]
+    fn test_diff_ids() {
+        let old = vec![entry("a", "1"), entry("b", "2"), entry("c", "3"), entry("c", "9")];
+        let new = vec![entry("d", "4"), entry("c", "3"), entry("b", "5"), entry("d", "6")];
+        assert_eq!(
+            summary(&diff_ids(old, new)),
+            vec![(DiffKind::Added, "d"), (DiffKind::Changed, "b"), (DiffKind::Removed, "a")]
+        );
+    }
+
+    #[test]
+    fn test_diff_manifests() {
+        let assignment = |id: &str, fingerprint: &str| Assignment {
+            fingerprint: fingerprint.to_string(),
+            id: id.to_string(),
+            tag: "p".to_string(),
+            path: vec![0],
+            alias: None,
+        };
+        let mut old = Manifest::new();
+        old.record("a.html".to_string(), "data-ast-id", vec![assignment("x", "1")], true);
+        old.record("gone.html".to_string(), "data-ast-id", vec![assignment("y", "2")], true);
+        let mut new = Manifest::new();
+        new.record("a.html".to_string(), "data-ast-id", vec![assignment("x", "1")], true);
+        new.record("b.html".to_string(), "data-ast-id", vec![assignment("z", "3")], true);
+
+        let diffs = diff_manifests(&old, &new);
+        assert_eq!(diffs.keys().collect::<Vec<_>>(), vec!["b.html", "gone.html"]);
+        assert_eq!(summary(&diffs["b.html"]), vec![(DiffKind::Added, "z")]);
+        assert_eq!(summary(&diffs["gone.html"]), vec![(DiffKind::Removed, "y")]);
+    }
+}
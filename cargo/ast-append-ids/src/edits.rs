@@ -0,0 +1,149 @@
+use crate::ast_common;
+use crate::id_generator::{Change, ChangeAction};
+use crate::select::SelectedElement;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A change as an edit for tools that hold their own AST or DOM: set `attr`
+/// to `value` on the element found at `xpath` (or at `line`/`col`/`offset`
+/// in the input file).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StructuralEdit {
+    pub file: String,
+    pub op: EditOp,
+    /// Absolute XPath of the element with 1-based positions among siblings of
+    /// the same tag, e.g. `/main[1]/ul[1]/li[2]`. `None` for elements not in
+    /// the document tree, such as markup in JSX template literals.
+    pub xpath: Option<String>,
+    pub tag: String,
+    pub line: usize,
+    pub col: usize,
+    /// Byte offset of the element's start tag
+    pub offset: usize,
+    pub attr: String,
+    /// The ID as written; with `id_helper` the attribute holds a call
+    /// wrapping it
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditOp {
+    /// The element gets the attribute
+    Add,
+    /// The element's attribute gets a new value
+    Replace,
+}
+
+/// XPaths of `elements`, which must be every element of a document as
+/// `select_elements` reports them for `*`, by offset.
+pub fn xpaths(elements: &[SelectedElement]) -> HashMap<usize, String> {
+    let mut elements: Vec<&SelectedElement> = elements.iter().collect();
+    elements.sort_by_key(|element| element.offset);
+
+    // XPath of each open element and how often each tag occurred among its
+    // children so far; the first entry stands for the document
+    let mut open: Vec<(String, HashMap<&str, usize>)> = vec![(String::new(), HashMap::new())];
+    let mut paths = HashMap::new();
+    for element in elements {
+        let depth = element.ancestors.len();
+        if depth >= open.len() {
+            continue;
+        }
+        open.truncate(depth + 1);
+        let (parent, counts) = &mut open[depth];
+        let position = counts.entry(&element.tag).or_default();
+        *position += 1;
+        let xpath = format!("{}/{}[{}]", parent, element.tag, position);
+        paths.insert(element.offset, xpath.clone());
+        open.push((xpath, HashMap::new()));
+    }
+    paths
+}
+
+/// The edits behind `changes` to `content`, in source order. Elements that
+/// kept their ID need no edit and are left out.
+pub fn structural_edits(
+    file: &str,
+    content: &str,
+    attr: &str,
+    mut changes: Vec<Change>,
+    elements: &[SelectedElement],
+) -> Vec<StructuralEdit> {
+    changes.retain(|change| change.action != ChangeAction::Skipped);
+    changes.sort_by_key(|change| change.offset);
+    let paths = xpaths(elements);
+    let positions = ast_common::line_columns(content, changes.iter().map(|change| change.offset));
+
+    changes
+        .into_iter()
+        .zip(positions)
+        .map(|(change, (line, col))| StructuralEdit {
+            file: file.to_string(),
+            op: match change.action {
+                ChangeAction::Overwritten => EditOp::Replace,
+                _ => EditOp::Add,
+            },
+            xpath: paths.get(&change.offset).cloned(),
+            tag: change.tag,
+            line,
+            col,
+            offset: change.offset,
+            attr: attr.to_string(),
+            value: change.id,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(content: &str, tag: &str, nth: usize, ancestors: &[&str]) -> SelectedElement {
+        SelectedElement {
+            offset: content.match_indices(&format!("<{}", tag)).nth(nth).unwrap().0,
+            tag: tag.to_string(),
+            ancestors: ancestors.iter().map(|name| name.to_string()).collect(),
+            id: None,
+            text: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_structural_edits() {
+        let content = "<ul>\n  <li>a</li>\n  <li>b</li>\n</ul>";
+        let elements = vec![
+            element(content, "ul", 0, &[]),
+            element(content, "li", 0, &["ul"]),
+            element(content, "li", 1, &["ul"]),
+        ];
+        let change = |element: &SelectedElement, action| Change {
+            offset: element.offset,
+            tag: element.tag.clone(),
+            action,
+            id: format!("el-{}", element.offset),
+        };
+        let changes = vec![
+            change(&elements[2], ChangeAction::Overwritten),
+            change(&elements[0], ChangeAction::Skipped),
+            change(&elements[1], ChangeAction::Added),
+        ];
+
+        let edits = structural_edits("list.html", content, "data-ast-id", changes, &elements);
+        let summary: Vec<_> = edits
+            .iter()
+            .map(|edit| (edit.op, edit.xpath.as_deref(), edit.line, edit.col, edit.value.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (EditOp::Add, Some("/ul[1]/li[1]"), 2, 3, "el-7"),
+                (EditOp::Replace, Some("/ul[1]/li[2]"), 3, 3, "el-20"),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&edits[0]).unwrap()["op"],
+            serde_json::Value::String("add".to_string())
+        );
+    }
+}
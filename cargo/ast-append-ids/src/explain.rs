@@ -0,0 +1,392 @@
+use crate::id_generator::{scheme_hash, slugify, Assignment};
+use crate::namespace::NAMESPACE_SEPARATOR;
+use crate::{IdScheme, IdStrategy};
+use std::fmt;
+
+/// What can be read from an ID without knowing the options it was made with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub id: String,
+    /// Environment token from `--env-prefix`, e.g. `staging:`
+    pub env: Option<String>,
+    /// Namespace from `--namespace-by-path`, before `--`
+    pub namespace: Option<String>,
+    /// The expected prefix, if the ID has it
+    pub prefix: Option<String>,
+    /// The ID without environment token, namespace and prefix
+    pub body: String,
+    /// Strategies that produce IDs of this shape, most likely first
+    pub readings: Vec<Reading>,
+}
+
+/// One way the body of an ID can have been generated. `duplicate` is the
+/// counter `-N` appended when the ID was taken already.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reading {
+    Hash { hash: String, scheme: IdScheme, duplicate: Option<usize> },
+    Taxonomy { context: Vec<String>, tag: String, index: usize, duplicate: Option<usize> },
+    Path { tag: String, path: Vec<usize> },
+    Slug { slug: String, duplicate: Option<usize> },
+}
+
+impl Reading {
+    pub fn strategy(&self) -> IdStrategy {
+        match self {
+            Reading::Hash { .. } => IdStrategy::Hash,
+            Reading::Taxonomy { .. } => IdStrategy::Taxonomy,
+            Reading::Path { .. } => IdStrategy::Path,
+            Reading::Slug { .. } => IdStrategy::Slug,
+        }
+    }
+
+    pub fn duplicate(&self) -> Option<usize> {
+        match self {
+            Reading::Hash { duplicate, .. } | Reading::Taxonomy { duplicate, .. } | Reading::Slug { duplicate, .. } => {
+                *duplicate
+            }
+            Reading::Path { .. } => None,
+        }
+    }
+
+    /// Whether the recorded `element` can have been given the ID this way.
+    /// `None` for slugs, which depend on the element's text, not recorded,
+    /// and for v2 hashes that do not match unsalted, as the salt is unknown.
+    pub fn matches(&self, element: &Assignment) -> Option<bool> {
+        match self {
+            Reading::Hash { hash, scheme, .. } => {
+                let matches = scheme_hash(*scheme, "", &element.tag, &element.path).starts_with(hash.as_str());
+                (matches || scheme.is_v1()).then_some(matches)
+            }
+            Reading::Taxonomy { tag, .. } => Some(slugify(&element.tag) == *tag),
+            Reading::Path { tag, path } => Some(element.tag == *tag && element.path == *path),
+            Reading::Slug { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Reading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reading::Hash { hash, scheme: IdScheme::V1, .. } => write!(f, "hash {} of the element's tag and path", hash)?,
+            Reading::Hash { hash, scheme: IdScheme::V2, .. } => {
+                write!(f, "hash {} of the element's tag and path (scheme v2)", hash)?
+            }
+            Reading::Taxonomy { context, tag, index, .. } if context.is_empty() => {
+                write!(f, "taxonomy: <{}> #{} outside any landmark", tag, index)?
+            }
+            Reading::Taxonomy { context, tag, index, .. } => {
+                write!(f, "taxonomy: <{}> #{} in {}", tag, index, context.join(" > "))?
+            }
+            Reading::Path { tag, path } => {
+                let path: Vec<String> = path.iter().map(usize::to_string).collect();
+                write!(f, "path: <{}> at {}", tag, path.join("/"))?
+            }
+            Reading::Slug { slug, .. } => write!(f, "slug of the element's text \"{}\"", slug)?,
+        }
+        if let Some(n) = self.duplicate() {
+            write!(f, ", made unique with -{}", n)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes `id`, assuming IDs are generated with `prefix`. The environment
+/// token is `env_prefix` when it is not empty, otherwise a leading `token:`
+/// such as `staging:` in front of an ID that does not start with `prefix`.
+pub fn explain(id: &str, prefix: &str, env_prefix: &str) -> Explanation {
+    let (env, rest) = split_env(id, prefix, env_prefix);
+    let (namespace, rest) = match rest.strip_prefix(prefix) {
+        Some(_) => (None, rest),
+        None => match rest.split_once(NAMESPACE_SEPARATOR) {
+            Some((namespace, rest)) if !namespace.is_empty() => (Some(namespace.to_string()), rest),
+            _ => (None, rest),
+        },
+    };
+    let (prefix, body) = match rest.strip_prefix(prefix) {
+        Some(body) => (Some(prefix.to_string()), body),
+        None => (None, rest),
+    };
+
+    Explanation {
+        id: id.to_string(),
+        env,
+        namespace,
+        prefix,
+        body: body.to_string(),
+        readings: readings(body),
+    }
+}
+
+/// An ID that `Explanation::id` repeats with a `-N` counter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    pub original: String,
+    /// Strategies whose reading has the counter
+    pub strategies: Vec<IdStrategy>,
+}
+
+fn split_env<'a>(id: &'a str, prefix: &str, env_prefix: &str) -> (Option<String>, &'a str) {
+    if !env_prefix.is_empty() {
+        return match id.strip_prefix(env_prefix) {
+            Some(rest) => (Some(env_prefix.to_string()), rest),
+            None => (None, id),
+        };
+    }
+    match id.split_once(':') {
+        Some((token, rest)) if !token.is_empty() && !id.starts_with(prefix) => (Some(format!("{}:", token)), rest),
+        _ => (None, id),
+    }
+}
+
+impl Explanation {
+    /// The IDs this one would have been without a `-N` counter, one for each
+    /// counter the readings see, most likely first.
+    pub fn duplicates(&self) -> Vec<Duplicate> {
+        let mut duplicates: Vec<Duplicate> = Vec::new();
+        for reading in &self.readings {
+            let Some(original) = reading.duplicate().and_then(|n| self.id.strip_suffix(&format!("-{}", n))) else {
+                continue;
+            };
+            match duplicates.iter_mut().find(|duplicate| duplicate.original == original) {
+                Some(duplicate) if duplicate.strategies.contains(&reading.strategy()) => {}
+                Some(duplicate) => duplicate.strategies.push(reading.strategy()),
+                None => duplicates.push(Duplicate { original: original.to_string(), strategies: vec![reading.strategy()] }),
+            }
+        }
+        duplicates
+    }
+
+    /// Drops the readings the recorded `element` rules out. Returns false and
+    /// keeps them all when it rules out every reading it can be checked
+    /// against, e.g. for an ID reused from the ID cache after its element
+    /// moved.
+    pub fn narrow(&mut self, element: &Assignment) -> bool {
+        let checked: Vec<bool> = self.readings.iter().filter_map(|reading| reading.matches(element)).collect();
+        if !checked.is_empty() && !checked.contains(&true) {
+            return false;
+        }
+        self.readings.retain(|reading| reading.matches(element) != Some(false));
+        true
+    }
+}
+
+fn readings(body: &str) -> Vec<Reading> {
+    let mut readings = Vec::new();
+    // Hash and slug IDs that were taken get a `-N` counter
+    let counted = split_counter(body).filter(|&(_, n)| n >= 2).map(|(base, n)| (base, Some(n)));
+
+    for (hash, duplicate) in [(body, None)].into_iter().chain(counted) {
+        if let Some(scheme) = hash_scheme(hash) {
+            readings.push(Reading::Hash { hash: hash.to_string(), scheme, duplicate });
+        }
+    }
+
+    // `context.tag-index`, with a counter when taken
+    let (context, element) = match body.rsplit_once('.') {
+        Some((context, element)) => (context.split('.').map(str::to_string).collect(), element),
+        None => (Vec::new(), body),
+    };
+    let (tag, numbers) = split_numbers(element);
+    let taxonomy = match numbers[..] {
+        [index] => Some((index, None)),
+        [index, n] if n >= 2 => Some((index, Some(n))),
+        _ => None,
+    };
+    let taxonomy = taxonomy
+        .filter(|_| is_tag(tag) && is_slug(tag) && context.iter().all(|label: &String| !label.is_empty()))
+        .map(|(index, duplicate)| Reading::Taxonomy { context, tag: tag.to_string(), index, duplicate });
+
+    // `tag-i-j-k`; a counter cannot be told apart from the path
+    let (tag, path) = split_numbers(body);
+    let path = (!path.is_empty() && is_tag(tag)).then(|| Reading::Path { tag: tag.to_string(), path });
+
+    // Without landmarks a taxonomy ID looks like a path ID one level deep
+    let mut structural = Vec::new();
+    if matches!(&taxonomy, Some(Reading::Taxonomy { context, .. }) if !context.is_empty()) {
+        structural.extend(taxonomy);
+        structural.extend(path);
+    } else {
+        structural.extend(path);
+        structural.extend(taxonomy);
+    }
+    // Custom elements, with a hyphen in their name, are rarer than slugs of
+    // several words
+    let (custom, structural): (Vec<Reading>, Vec<Reading>) = structural
+        .into_iter()
+        .partition(|reading| matches!(reading, Reading::Path { tag, .. } | Reading::Taxonomy { tag, .. } if tag.contains('-')));
+    readings.extend(structural);
+
+    for (slug, duplicate) in counted.into_iter().chain([(body, None)]) {
+        if is_slug(slug) {
+            readings.push(Reading::Slug { slug: slug.to_string(), duplicate });
+        }
+    }
+    readings.extend(custom);
+    readings
+}
+
+/// The scheme of a hash of this shape, full or cut short with `hash_length`.
+/// A v2 hash needs an uppercase letter, and a digit unless it is full length,
+/// to be told apart from a word; the few without are not recognized.
+fn hash_scheme(text: &str) -> Option<IdScheme> {
+    let length = |scheme: IdScheme| (4..=scheme.hash_len()).contains(&text.len());
+    if length(IdScheme::V1) && text.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
+        Some(IdScheme::V1)
+    } else if length(IdScheme::V2)
+        && text.chars().all(|c| c.is_ascii_alphanumeric())
+        && text.chars().any(|c| c.is_ascii_uppercase())
+        && (text.len() == IdScheme::V2.hash_len() || text.chars().any(|c| c.is_ascii_digit()))
+    {
+        Some(IdScheme::V2)
+    } else {
+        None
+    }
+}
+
+/// Splits `name-3` into `name` and 3.
+fn split_counter(text: &str) -> Option<(&str, usize)> {
+    let (rest, counter) = text.rsplit_once('-')?;
+    Some((rest, parse_counter(counter)?))
+}
+
+/// Splits `name-0-3` into `name` and [0, 3].
+fn split_numbers(text: &str) -> (&str, Vec<usize>) {
+    let mut rest = text;
+    let mut numbers = Vec::new();
+    while let Some((head, n)) = split_counter(rest) {
+        numbers.insert(0, n);
+        rest = head;
+    }
+    (rest, numbers)
+}
+
+fn parse_counter(text: &str) -> Option<usize> {
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// Element names start with a letter and have no number between hyphens.
+fn is_tag(name: &str) -> bool {
+    name.chars().next().is_some_and(char::is_alphabetic) && name.split('-').all(|part| parse_counter(part).is_none())
+}
+
+/// Whether `text` can be the output of `slugify`.
+fn is_slug(text: &str) -> bool {
+    let length = text.chars().count();
+    (1..=50).contains(&length)
+        && text.chars().all(|c| c == '-' || (c.is_alphanumeric() && !c.is_uppercase()))
+        && !text.starts_with('-')
+        && !text.contains("--")
+        // Cut at 50 characters, a slug can end in the hyphen before a word
+        && (!text.ends_with('-') || length == 50)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::id_generator::IdGenerator;
+
+    fn strategies(explanation: &Explanation) -> Vec<IdStrategy> {
+        explanation.readings.iter().map(Reading::strategy).collect()
+    }
+
+    #[test]
+    fn test_explain_ids() {
+        let hash = IdGenerator::new().generate_hash_id("button", &[0, 3], "");
+        let explanation = explain(&format!("login-form--el-{}-2", hash), "el-", "");
+        assert_eq!(explanation.namespace.as_deref(), Some("login-form"));
+        assert_eq!(explanation.prefix.as_deref(), Some("el-"));
+        assert_eq!(explanation.readings[0], Reading::Hash { hash: hash.clone(), scheme: IdScheme::V1, duplicate: Some(2) });
+        assert_eq!(explanation.duplicates()[0].original, format!("login-form--el-{}", hash));
+
+        let mut explanation = explain(&format!("el-{}", hash), "el-", "");
+        let element = |tag: &str, path: Vec<usize>| Assignment {
+            fingerprint: String::new(),
+            id: String::new(),
+            tag: tag.to_string(),
+            path,
+            alias: None,
+        };
+        assert!(explanation.narrow(&element("button", vec![0, 3])));
+        assert_eq!(strategies(&explanation), vec![IdStrategy::Hash, IdStrategy::Slug]);
+        assert!(!explanation.narrow(&element("a", vec![0, 3])));
+
+        // Cut short with `hash_length`
+        let mut explanation = explain(&format!("el-{}", &hash[..5]), "el-", "");
+        assert_eq!(strategies(&explanation)[0], IdStrategy::Hash);
+        assert!(explanation.narrow(&element("button", vec![0, 3])));
+        assert!(!explanation.narrow(&element("a", vec![0, 3])));
+
+        let explanation = explain("el-main.cart.button-0-3", "el-", "");
+        assert_eq!(
+            explanation.readings[0],
+            Reading::Taxonomy {
+                context: vec!["main".to_string(), "cart".to_string()],
+                tag: "button".to_string(),
+                index: 0,
+                duplicate: Some(3),
+            }
+        );
+        assert_eq!(explanation.duplicates()[0].original, "el-main.cart.button-0");
+
+        // Every reading with a counter names the ID it repeats
+        let explanation = explain("el-div-0-4", "el-", "");
+        assert_eq!(strategies(&explanation), vec![IdStrategy::Path, IdStrategy::Taxonomy, IdStrategy::Slug, IdStrategy::Slug]);
+        assert_eq!(explanation.readings[0].to_string(), "path: <div> at 0/4");
+        assert_eq!(
+            explanation.duplicates(),
+            vec![Duplicate { original: "el-div-0".to_string(), strategies: vec![IdStrategy::Taxonomy, IdStrategy::Slug] }]
+        );
+
+        let explanation = explain("Submit", "el-", "");
+        assert_eq!(explanation.prefix, None);
+        assert!(explanation.readings.is_empty());
+    }
+
+    #[test]
+    fn test_explain_each_strategy() {
+        let options = crate::IdOptions { prefix: "el-".to_string(), ..Default::default() };
+        let explain_first = |id: &str| {
+            let explanation = explain(id, "el-", "");
+            assert_eq!(explanation.prefix.as_deref(), Some("el-"), "{}", id);
+            explanation.readings[0].to_string()
+        };
+
+        let mut generator = IdGenerator::new();
+        let hash = generator.generate_hash_id("button", &[0, 3], "el-");
+        assert_eq!(explain_first(&hash), format!("hash {} of the element's tag and path", &hash[3..]));
+        let v2 = generator.generate_scheme_hash_id("button", &[0, 3], "el-", IdScheme::V2, "");
+        assert_eq!(explain_first(&v2), format!("hash {} of the element's tag and path (scheme v2)", &v2[3..]));
+        let mut explanation = explain(&v2, "el-", "");
+        let element = Assignment { fingerprint: String::new(), id: v2.clone(), tag: "button".to_string(), path: vec![0, 3], alias: None };
+        assert!(explanation.narrow(&element));
+
+        let path = generator.generate_path_id("div", &[0, 1, 2], &options.prefix);
+        assert_eq!(path, "el-div-0-1-2");
+        let explanation = explain(&path, "el-", "");
+        assert_eq!(strategies(&explanation), vec![IdStrategy::Path, IdStrategy::Slug, IdStrategy::Slug]);
+        assert_eq!(explain_first(&path), "path: <div> at 0/1/2");
+        assert_eq!(explanation.duplicates()[0].strategies, vec![IdStrategy::Slug]);
+
+        let slug = generator.generate_slug_id("Save changes", "el-");
+        let counted = generator.generate_slug_id("Save changes", "el-");
+        assert_eq!(explain_first(&slug), "slug of the element's text \"save-changes\"");
+        assert_eq!(explain_first(&counted), "slug of the element's text \"save-changes\", made unique with -2");
+        assert_eq!(explain(&counted, "el-", "").duplicates()[0].original, slug);
+
+        let taxonomy = generator.generate_taxonomy_id("a", &["header".to_string(), "nav".to_string()], "el-");
+        assert_eq!(explain_first(&taxonomy), "taxonomy: <a> #0 in header > nav");
+
+        // The environment token comes off before anything else
+        let explanation = explain(&format!("staging:{}", path), "el-", "");
+        assert_eq!(explanation.env.as_deref(), Some("staging:"));
+        assert_eq!(explanation.readings[0].to_string(), "path: <div> at 0/1/2");
+        let explanation = explain("prod/login--el-div-0", "el-", "prod/");
+        assert_eq!(explanation.env.as_deref(), Some("prod/"));
+        assert_eq!(explanation.namespace.as_deref(), Some("login"));
+        assert_eq!(explanation.readings[0].to_string(), "path: <div> at 0");
+        assert_eq!(explain("el:div-0", "el:", "").env, None);
+    }
+}
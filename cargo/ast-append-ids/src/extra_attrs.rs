@@ -0,0 +1,158 @@
+use crate::ast_common::AstNode;
+
+/// An attribute written next to each generated ID, its value made from the
+/// element: `IdOptions::extra_attrs` entries such as `data-ast-depth={depth}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttrTemplate {
+    pub name: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    /// The ID as written, environment token included
+    Id,
+    Tag,
+    /// Number of enclosing elements, 0 for a root
+    Depth,
+    /// Position among the elements counted, the last number of `path`
+    Index,
+    /// The element's path, `0-3-1`
+    Path,
+    /// Labels of the enclosing taxonomy levels, `checkout.form`
+    Context,
+    /// Value of another attribute of the element, empty without it
+    Attr(String),
+}
+
+/// Placeholders templates can use, for error messages
+const PLACEHOLDERS: &str = "{id}, {tag}, {depth}, {index}, {path}, {context} and {attr:NAME}";
+
+impl AttrTemplate {
+    /// Parses `name=template`. `{{` and `}}` stand for braces.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (name, template) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("'{}' is not NAME=TEMPLATE", spec))?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!("'{}' has no attribute name", spec));
+        }
+
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        placeholder.push(c);
+                    }
+                    if !closed {
+                        return Err(format!("Unclosed '{{' in '{}'", spec));
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(match placeholder.as_str() {
+                        "id" => Part::Id,
+                        "tag" => Part::Tag,
+                        "depth" => Part::Depth,
+                        "index" => Part::Index,
+                        "path" => Part::Path,
+                        "context" => Part::Context,
+                        other => match other.strip_prefix("attr:") {
+                            Some(attr) if !attr.is_empty() => Part::Attr(attr.to_string()),
+                            _ => return Err(format!("Unknown placeholder {{{}}} in '{}'; use {}", other, spec, PLACEHOLDERS)),
+                        },
+                    });
+                }
+                '}' => return Err(format!("Unmatched '}}' in '{}'; write '}}}}' for a brace", spec)),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self { name: name.to_string(), parts })
+    }
+
+    /// The value for `node`, which gets ID `id`
+    pub fn render(&self, node: &AstNode, id: &str) -> String {
+        let mut value = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => value.push_str(text),
+                Part::Id => value.push_str(id),
+                Part::Tag => value.push_str(&node.node_type),
+                Part::Depth => value.push_str(&node.path.len().saturating_sub(1).to_string()),
+                Part::Index => value.push_str(&node.path.last().copied().unwrap_or_default().to_string()),
+                Part::Path => {
+                    let path: Vec<String> = node.path.iter().map(usize::to_string).collect();
+                    value.push_str(&path.join("-"));
+                }
+                Part::Context => value.push_str(&node.context.join(".")),
+                Part::Attr(name) => {
+                    if let Some((_, attr)) = node.attributes.iter().find(|(key, _)| key == name) {
+                        value.push_str(attr);
+                    }
+                }
+            }
+        }
+        value
+    }
+}
+
+/// Parses `IdOptions::extra_attrs`
+pub fn parse_all(specs: &[String]) -> Result<Vec<AttrTemplate>, String> {
+    specs.iter().map(|spec| AttrTemplate::parse(spec)).collect()
+}
+
+/// Names and values of the `templates` for `node`
+pub fn render_all(templates: &[AttrTemplate], node: &AstNode, id: &str) -> Vec<(String, String)> {
+    templates.iter().map(|template| (template.name.clone(), template.render(node, id))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attr_templates() {
+        let node = AstNode {
+            node_type: "button".to_string(),
+            text_content: None,
+            attributes: vec![("type".to_string(), "submit".to_string())],
+            path: vec![0, 3, 1],
+            context: vec!["checkout".to_string(), "form".to_string()],
+        };
+        let render = |spec: &str| AttrTemplate::parse(spec).unwrap().render(&node, "el-a1");
+
+        assert_eq!(render("data-ast-depth={depth}"), "2");
+        assert_eq!(render("data-qa={context}.{tag}[{index}]"), "checkout.form.button[1]");
+        assert_eq!(render("data-test={tag}-{attr:type}-{attr:name}"), "button-submit-");
+        assert_eq!(render("data-at={path} of {id}"), "0-3-1 of el-a1");
+        assert_eq!(render("data-json={{\"id\": \"{id}\"}}"), "{\"id\": \"el-a1\"}");
+        assert_eq!(AttrTemplate::parse(" data-x =").unwrap().name, "data-x");
+
+        assert!(AttrTemplate::parse("data-x").unwrap_err().contains("NAME=TEMPLATE"));
+        assert!(AttrTemplate::parse("data-x={size}").unwrap_err().contains("Unknown placeholder {size}"));
+        assert!(AttrTemplate::parse("data-x=a}b").unwrap_err().contains("Unmatched"));
+        assert!(AttrTemplate::parse("data-x={id").unwrap_err().contains("Unclosed"));
+    }
+}
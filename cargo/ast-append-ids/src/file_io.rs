@@ -0,0 +1,307 @@
+use memmap2::Mmap;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{BufWriter, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// Contents of an input file, either read into memory or memory-mapped.
+pub enum FileContent {
+    Read(String),
+    Mapped(Mmap),
+}
+
+impl FileContent {
+    pub fn read(path: &Path) -> Result<Self, String> {
+        fs::read_to_string(path)
+            .map(FileContent::Read)
+            .map_err(|e| format!("Failed to read file {}: {}", path.display(), e))
+    }
+
+    /// Maps `path` into memory so large documents are not copied into a
+    /// `String`. Falls back to a regular read when the file cannot be mapped
+    /// (empty files, some network and virtual filesystems).
+    ///
+    /// The file must not be modified while the mapping is alive; drop the
+    /// content before writing the output back to the same path.
+    pub fn map(path: &Path) -> Result<Self, String> {
+        let file = File::open(path)
+            .map_err(|e| format!("Failed to read file {}: {}", path.display(), e))?;
+
+        // SAFETY: the mapping is only read, and callers drop it before the
+        // file is rewritten
+        match unsafe { Mmap::map(&file) } {
+            Ok(mmap) if !mmap.is_empty() => {
+                std::str::from_utf8(&mmap)
+                    .map_err(|e| format!("Failed to read file {}: {}", path.display(), e))?;
+                Ok(FileContent::Mapped(mmap))
+            }
+            _ => Self::read(path),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            FileContent::Read(content) => content,
+            // Validated as UTF-8 when the file was mapped
+            FileContent::Mapped(mmap) => unsafe { std::str::from_utf8_unchecked(mmap) },
+        }
+    }
+
+    pub fn is_mapped(&self) -> bool {
+        matches!(self, FileContent::Mapped(_))
+    }
+}
+
+impl Deref for FileContent {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// Writes `content` to `path` through a buffered writer.
+pub fn write_output(path: &Path, content: &str) -> Result<(), String> {
+    let file = File::create(path)
+        .map_err(|e| format!("Failed to write file {}: {}", path.display(), e))?;
+    let mut writer = BufWriter::new(file);
+    writer
+        .write_all(content.as_bytes())
+        .and_then(|_| writer.flush())
+        .map_err(|e| format!("Failed to write file {}: {}", path.display(), e))
+}
+
+/// Writes `content` to a new file that then takes the place of `path`, so a
+/// symbolic link at `path` is replaced instead of written through, and other
+/// hard links to the old file keep the old content. The permissions of the
+/// old file carry over.
+pub fn replace_output(path: &Path, content: &str) -> Result<(), String> {
+    let error = |e: std::io::Error| format!("Failed to write file {}: {}", path.display(), e);
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("Failed to write file {}: not a file path", path.display()))?;
+    let temp = path.with_file_name(format!(".{}.ast-append-ids.tmp", file_name.to_string_lossy()));
+    let permissions = fs::metadata(path).map(|metadata| metadata.permissions()).ok();
+
+    write_output(&temp, content)?;
+    let replaced = match permissions {
+        Some(permissions) => fs::set_permissions(&temp, permissions),
+        None => Ok(()),
+    }
+    .and_then(|_| fs::rename(&temp, path));
+    if let Err(e) = replaced {
+        let _ = fs::remove_file(&temp);
+        return Err(error(e));
+    }
+    Ok(())
+}
+
+/// Metadata of an input that carries over to its output besides the
+/// permissions, which always do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Preserve {
+    pub mtime: bool,
+    /// Owner and group, on Unix only; changing them usually requires root
+    pub owner: bool,
+}
+
+/// Gives `path` the permissions of `source`, the metadata of the input taken
+/// before it was rewritten, and the modification time and owner if asked.
+pub fn preserve_metadata(path: &Path, source: &fs::Metadata, preserve: Preserve) -> Result<(), String> {
+    let error = |e: std::io::Error| format!("Failed to preserve metadata of {}: {}", path.display(), e);
+    fs::set_permissions(path, source.permissions()).map_err(error)?;
+    if preserve.mtime {
+        let modified = source.modified().map_err(error)?;
+        OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(modified))
+            .map_err(error)?;
+    }
+    #[cfg(unix)]
+    if preserve.owner {
+        use std::os::unix::fs::MetadataExt;
+        let current = fs::metadata(path).map_err(error)?;
+        if (current.uid(), current.gid()) != (source.uid(), source.gid()) {
+            std::os::unix::fs::chown(path, Some(source.uid()), Some(source.gid())).map_err(error)?;
+        }
+    }
+    Ok(())
+}
+
+/// An exclusive advisory lock, released when dropped. Other processes only
+/// honour it if they take the same lock.
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Locks `path`, creating it if needed. Unless `wait` is set, returns
+    /// `Ok(None)` instead of blocking when another process holds the lock.
+    pub fn acquire(path: &Path, wait: bool) -> Result<Option<Self>, String> {
+        let error = |e: std::io::Error| format!("Failed to lock {}: {}", path.display(), e);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .map_err(error)?;
+        if wait {
+            file.lock().map_err(error)?;
+        } else {
+            match file.try_lock() {
+                Ok(()) => {}
+                Err(TryLockError::WouldBlock) => return Ok(None),
+                Err(TryLockError::Error(e)) => return Err(error(e)),
+            }
+        }
+        Ok(Some(FileLock { _file: file }))
+    }
+
+    /// Locks `target` through a lock file in the system temp directory, so
+    /// the target itself can still be rewritten on platforms where locks are
+    /// mandatory.
+    pub fn acquire_for(target: &Path, wait: bool) -> Result<Option<Self>, String> {
+        let dir = std::env::temp_dir().join("ast-append-ids-locks");
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create lock directory {}: {}", dir.display(), e))?;
+        Self::acquire(&dir.join(lock_file_name(target)), wait)
+    }
+}
+
+/// Name of the lock file for `target`, the same for every spelling of its path
+fn lock_file_name(target: &Path) -> String {
+    let resolved: PathBuf = fs::canonicalize(target)
+        .or_else(|_| std::path::absolute(target))
+        .unwrap_or_else(|_| target.to_path_buf());
+    let digest = Sha256::digest(resolved.to_string_lossy().as_bytes());
+    let hex: String = digest[..8].iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}.lock", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_and_read_agree() {
+        let dir = std::env::temp_dir().join(format!("ast-append-ids-file-io-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("page.html");
+        write_output(&path, "<p>Hello</p>\n").unwrap();
+        let mapped = FileContent::map(&path).unwrap();
+        assert!(mapped.is_mapped());
+        assert_eq!(&*mapped, &*FileContent::read(&path).unwrap());
+
+        // Empty files cannot be mapped everywhere and are read instead
+        let empty = dir.join("empty.html");
+        write_output(&empty, "").unwrap();
+        assert!(!FileContent::map(&empty).unwrap().is_mapped());
+
+        let binary = dir.join("binary.html");
+        fs::write(&binary, [0xff, 0xfe]).unwrap();
+        assert!(FileContent::map(&binary).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_output_detaches_links() {
+        let dir = std::env::temp_dir().join(format!("ast-append-ids-replace-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let target = dir.join("shared.html");
+        write_output(&target, "<p>Shared</p>").unwrap();
+        let symlink = dir.join("symlink.html");
+        std::os::unix::fs::symlink(&target, &symlink).unwrap();
+        let hardlink = dir.join("hardlink.html");
+        fs::hard_link(&target, &hardlink).unwrap();
+
+        replace_output(&symlink, "<p id=\"a\">Shared</p>").unwrap();
+        replace_output(&hardlink, "<p id=\"b\">Shared</p>").unwrap();
+        assert!(!fs::symlink_metadata(&symlink).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&symlink).unwrap(), "<p id=\"a\">Shared</p>");
+        assert_eq!(fs::read_to_string(&hardlink).unwrap(), "<p id=\"b\">Shared</p>");
+        assert_eq!(fs::read_to_string(&target).unwrap(), "<p>Shared</p>");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_lock() {
+        let dir = std::env::temp_dir().join(format!("ast-append-ids-lock-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("page.html");
+        write_output(&target, "<p>Hello</p>").unwrap();
+
+        let held = FileLock::acquire_for(&target, false).unwrap();
+        assert!(held.is_some());
+        // Locks are per open file, so a second one conflicts even in-process
+        assert!(FileLock::acquire_for(&dir.join(".").join("page.html"), false).unwrap().is_none());
+        drop(held);
+        assert!(FileLock::acquire_for(&target, false).unwrap().is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_lock_holds_off_a_second_writer() {
+        let dir = std::env::temp_dir().join(format!("ast-append-ids-writers-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("page.html");
+        write_output(&target, "").unwrap();
+
+        // The second writer waits for the lock, so it appends after the first
+        // has written all of its output
+        let held = FileLock::acquire_for(&target, true).unwrap();
+        let second = {
+            let target = target.clone();
+            std::thread::spawn(move || {
+                let _lock = FileLock::acquire_for(&target, true).unwrap();
+                let content = fs::read_to_string(&target).unwrap();
+                write_output(&target, &format!("{}second\n", content)).unwrap();
+            })
+        };
+        for line in ["first\n", "again\n"] {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            let content = fs::read_to_string(&target).unwrap();
+            write_output(&target, &format!("{}{}", content, line)).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&target).unwrap(), "first\nagain\n");
+        drop(held);
+        second.join().unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "first\nagain\nsecond\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_preserve_metadata() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::time::{Duration, SystemTime};
+
+        let dir = std::env::temp_dir().join(format!("ast-append-ids-metadata-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("build.html");
+        write_output(&input, "<p>Hello</p>").unwrap();
+        fs::set_permissions(&input, fs::Permissions::from_mode(0o755)).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        File::options().write(true).open(&input).unwrap().set_modified(modified).unwrap();
+        let source = fs::metadata(&input).unwrap();
+
+        let output = dir.join("out.html");
+        write_output(&output, "<p id=\"a\">Hello</p>").unwrap();
+        preserve_metadata(&output, &source, Preserve::default()).unwrap();
+        assert_eq!(fs::metadata(&output).unwrap().permissions().mode() & 0o777, 0o755);
+        assert_ne!(fs::metadata(&output).unwrap().modified().unwrap(), modified);
+
+        preserve_metadata(&output, &source, Preserve { mtime: true, owner: true }).unwrap();
+        assert_eq!(fs::metadata(&output).unwrap().modified().unwrap(), modified);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,69 @@
+use crate::ast_common::{self, IdLocation};
+
+/// An element whose ID matched a `find` query. `line` and `col` are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundId {
+    pub line: usize,
+    pub col: usize,
+    pub tag: String,
+    pub id: String,
+    /// Source from the element's start to the end of its line
+    pub snippet: String,
+}
+
+/// Keeps the `locations` in `content` whose ID equals `query`, or starts with
+/// it when `prefix` is set, in source order.
+pub fn find_ids(content: &str, mut locations: Vec<IdLocation>, query: &str, prefix: bool) -> Vec<FoundId> {
+    locations.retain(|location| {
+        if prefix {
+            location.id.starts_with(query)
+        } else {
+            location.id == query
+        }
+    });
+    locations.sort_by_key(|location| location.offset);
+    let positions = ast_common::line_columns(content, locations.iter().map(|location| location.offset));
+
+    locations
+        .into_iter()
+        .zip(positions)
+        .map(|(location, (line, col))| FoundId {
+            line,
+            col,
+            snippet: ast_common::source_snippet(content, location.offset),
+            tag: location.tag,
+            id: location.id,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(content: &str, needle: &str, id: &str) -> IdLocation {
+        IdLocation::new(content.find(needle).unwrap(), "div".to_string(), id.to_string(), Vec::new(), "data-ast-id")
+    }
+
+    #[test]
+    fn test_find_ids() {
+        let long = format!("<div data-ast-id=\"el-b\">{}</div>", "x".repeat(200));
+        let content = format!("<main>\n  <div data-ast-id=\"el-a\">a</div>\n{}\n</main>", long);
+        let locations = vec![
+            location(&content, "<div data-ast-id=\"el-b\"", "el-b"),
+            location(&content, "<div data-ast-id=\"el-a\"", "el-a"),
+            location(&content, "<main>", "other"),
+        ];
+
+        let found = find_ids(&content, locations.clone(), "el-a", false);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].line, found[0].col), (2, 3));
+        assert_eq!(found[0].snippet, "<div data-ast-id=\"el-a\">a</div>");
+
+        let found = find_ids(&content, locations, "el-", true);
+        let ids: Vec<_> = found.iter().map(|f| (f.line, f.id.as_str())).collect();
+        assert_eq!(ids, vec![(2, "el-a"), (3, "el-b")]);
+        assert_eq!(found[1].snippet.chars().count(), ast_common::SNIPPET_LEN + 1);
+        assert!(found[1].snippet.ends_with('…'));
+    }
+}
@@ -0,0 +1,138 @@
+use crate::manifest::Manifest;
+use std::collections::HashSet;
+
+/// How `gen-selectors` names the constant for an ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Naming {
+    /// From the ID, e.g. `login-submit` -> `LOGIN_SUBMIT`
+    Id,
+    /// From the file name and the ID, e.g. `LOGIN_FORM_LOGIN_SUBMIT`
+    FileId,
+    /// From the file name and the tag, e.g. `LOGIN_FORM_BUTTON`
+    FileTag,
+}
+
+#[derive(Debug, Clone)]
+pub struct SelectorOptions {
+    pub naming: Naming,
+    /// Removed from the start of IDs before they are turned into names
+    pub strip_prefix: String,
+}
+
+/// A named CSS selector matching one manifest entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorConstant {
+    pub name: String,
+    pub selector: String,
+}
+
+/// Builds one constant per manifest entry, in manifest order. Names that would
+/// repeat get `_2`, `_3`, ... suffixes.
+pub fn selector_constants(manifest: &Manifest, options: &SelectorOptions) -> Vec<SelectorConstant> {
+    let mut used = HashSet::new();
+    let mut constants = Vec::new();
+
+    for (file, manifest_file) in &manifest.files {
+        let file_name = file.rsplit('/').next().unwrap_or(file);
+        let stem = file_name.split_once('.').map_or(file_name, |(stem, _)| stem);
+
+        for entry in &manifest_file.entries {
+            let id = entry.id.strip_prefix(options.strip_prefix.as_str()).unwrap_or(&entry.id);
+            let base = match options.naming {
+                Naming::Id => constant_case(id),
+                Naming::FileId => format!("{}_{}", constant_case(stem), constant_case(id)),
+                Naming::FileTag => format!("{}_{}", constant_case(stem), constant_case(&entry.tag)),
+            };
+            let base = match base.trim_matches('_') {
+                "" => "SELECTOR".to_string(),
+                name if name.starts_with(|c: char| c.is_ascii_digit()) => format!("_{}", name),
+                name => name.to_string(),
+            };
+
+            let mut name = base.clone();
+            let mut counter = 2;
+            while !used.insert(name.clone()) {
+                name = format!("{}_{}", base, counter);
+                counter += 1;
+            }
+
+            constants.push(SelectorConstant {
+                name,
+                selector: format!("[{}=\"{}\"]", manifest_file.attr, entry.id.replace('\\', "\\\\").replace('"', "\\\"")),
+            });
+        }
+    }
+
+    constants
+}
+
+/// `LoginForm`, `login-form` and `loginForm` all become `LOGIN_FORM`.
+fn constant_case(text: &str) -> String {
+    let mut name = String::new();
+    let mut previous: Option<char> = None;
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            let word_start = previous.is_some_and(|p| p.is_ascii_lowercase() && c.is_ascii_uppercase());
+            if word_start {
+                name.push('_');
+            }
+            name.push(c.to_ascii_uppercase());
+        } else if !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+        previous = Some(c);
+    }
+    name
+}
+
+/// Renders the constants as a TypeScript module. `source` names the manifest
+/// in the header comment.
+pub fn to_typescript(constants: &[SelectorConstant], source: &str) -> String {
+    let mut module = format!("// Generated by ast-append-ids gen-selectors from {}. Do not edit.\n\n", source);
+    for constant in constants {
+        let selector = constant.selector.replace('\\', "\\\\").replace('\'', "\\'");
+        module.push_str(&format!("export const {} = '{}' as const;\n", constant.name, selector));
+    }
+    module
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::id_generator::Assignment;
+
+    #[test]
+    fn test_selector_constants() {
+        let assignment = |id: &str, tag: &str| Assignment {
+            fingerprint: String::new(),
+            id: id.to_string(),
+            tag: tag.to_string(),
+            path: Vec::new(),
+            alias: None,
+        };
+        let mut manifest = Manifest::new();
+        manifest.record(
+            "src/LoginForm.jsx".to_string(),
+            "data-ast-id",
+            vec![assignment("el-login-submit", "button"), assignment("el-3f9a", "button"), assignment("el-it's", "a")],
+            true,
+        );
+        manifest.record("pages/login.html".to_string(), "data-test", vec![assignment("el-login-submit", "button")], true);
+
+        let names = |naming| {
+            let options = SelectorOptions { naming, strip_prefix: "el-".to_string() };
+            selector_constants(&manifest, &options).into_iter().map(|c| c.name).collect::<Vec<_>>()
+        };
+        assert_eq!(names(Naming::Id), vec!["LOGIN_SUBMIT", "LOGIN_SUBMIT_2", "_3F9A", "IT_S"]);
+        assert_eq!(
+            names(Naming::FileId),
+            vec!["LOGIN_LOGIN_SUBMIT", "LOGIN_FORM_LOGIN_SUBMIT", "LOGIN_FORM_3F9A", "LOGIN_FORM_IT_S"]
+        );
+        assert_eq!(names(Naming::FileTag), vec!["LOGIN_BUTTON", "LOGIN_FORM_BUTTON", "LOGIN_FORM_BUTTON_2", "LOGIN_FORM_A"]);
+
+        let options = SelectorOptions { naming: Naming::Id, strip_prefix: String::new() };
+        let module = to_typescript(&selector_constants(&manifest, &options), "ids-manifest.json");
+        assert!(module.contains("export const EL_LOGIN_SUBMIT = '[data-test=\"el-login-submit\"]' as const;\n"));
+        assert!(module.contains("export const EL_IT_S = '[data-ast-id=\"el-it\\'s\"]' as const;\n"));
+    }
+}
@@ -1,13 +1,23 @@
-use crate::ast_common::{self, AstNode};
-use crate::id_generator::IdGenerator;
-use crate::{AstProcessor, IdOptions, IdStrategy};
-use lol_html::{element, rewrite_str, RewriteStrSettings};
-use std::cell::RefCell;
+use crate::ast_common::{self, AstNode, Candidate, ComponentScopes, IdLocation, IgnoreTracker, OptionOverrides, OverrideScopes, IGNORE_ATTR};
+use crate::coverage::PolicyElement;
+use crate::extra_attrs;
+use crate::id_generator::{IdGenerator, SkipReason};
+use crate::resolved::ResolvedOptions;
+use crate::select::SelectedElement;
+use crate::selector::{Selector, SelectorElement};
+use crate::taxonomy::TaxonomyTracker;
+use crate::detect::Format;
+use crate::{AstProcessor, IdOptions, IdStrategy, ProcessOutput};
+use lol_html::html_content::{ContentType, Element, TextChunk, TextType};
+use lol_html::{
+    doc_comments, doc_text, element, rewrite_str, DocumentContentHandlers, ElementContentHandlers, RewriteStrSettings,
+};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 pub struct HtmlProcessor {
-    #[allow(dead_code)]
     generator: IdGenerator,
 }
 
@@ -17,95 +27,385 @@ impl HtmlProcessor {
             generator: IdGenerator::new(),
         }
     }
-    
-    fn extract_text_content(&self, html: &str) -> HashMap<usize, String> {
-        let mut text_map = HashMap::new();
-        let doc = scraper::Html::parse_document(html);
-        let mut counter = 0;
-        
-        // Use a simple approach - iterate through all elements
-        for element_ref in doc.select(&scraper::Selector::parse("*").unwrap()) {
-            let mut text_content = String::new();
-            
-            // Collect direct text nodes only (not nested)
-            for text in element_ref.text() {
-                let trimmed = text.trim();
-                if !trimmed.is_empty() {
-                    if !text_content.is_empty() {
-                        text_content.push(' ');
-                    }
-                    text_content.push_str(trimmed);
-                }
-            }
-            
-            if !text_content.is_empty() {
-                text_map.insert(counter, text_content);
-            }
-            counter += 1;
-        }
-        
-        text_map
+
+    /// Creates a processor that draws IDs from an existing generator, e.g. one
+    /// pre-seeded with reserved IDs or shared across several files.
+    pub fn with_generator(generator: IdGenerator) -> Self {
+        Self { generator }
+    }
+
+    pub fn into_generator(self) -> IdGenerator {
+        self.generator
+    }
+}
+
+impl Default for HtmlProcessor {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl AstProcessor for HtmlProcessor {
-    fn process(&mut self, content: &str, options: &IdOptions) -> Result<String, String> {
-        // Pre-extract text content if using slug strategy
-        let text_map = if matches!(options.strategy, IdStrategy::Slug) {
-            Rc::new(self.extract_text_content(content))
+    fn generator_mut(&mut self) -> &mut IdGenerator {
+        &mut self.generator
+    }
+
+    fn process_with_meta(&mut self, content: &str, options: &IdOptions) -> Result<ProcessOutput, String> {
+        let resolved = Rc::new(ResolvedOptions::new(options, Format::Html)?);
+        self.process_resolved(content, options, &resolved)
+    }
+
+    fn process_resolved(
+        &mut self,
+        content: &str,
+        options: &IdOptions,
+        resolved: &Rc<ResolvedOptions>,
+    ) -> Result<ProcessOutput, String> {
+        ast_common::with_meta(self, options, |processor| {
+            let output = processor.process_document(content, options, resolved)?;
+            match &options.manifest_meta {
+                Some(reference) => write_manifest_meta(&output, reference, &options.attr),
+                None => Ok(output),
+            }
+        })
+    }
+}
+
+impl HtmlProcessor {
+    pub(crate) fn process_document(
+        &mut self,
+        content: &str,
+        options: &IdOptions,
+        resolved: &Rc<ResolvedOptions>,
+    ) -> Result<String, String> {
+        let _span = tracing::debug_span!("process", format = "html", bytes = content.len()).entered();
+        let generator = std::mem::take(&mut self.generator);
+        let mut visitor = HtmlVisitor::with_resolved(content, options, resolved.clone(), generator)?;
+        let rewrite_settings = RewriteStrSettings {
+            element_content_handlers: visitor.element_content_handlers(),
+            document_content_handlers: visitor.document_content_handlers(),
+            ..RewriteStrSettings::default()
+        };
+        let result = rewrite_str(content, rewrite_settings)
+            .map(|output| visitor.finish(output))
+            .map_err(|e| format!("HTML processing error: {}", e));
+        self.generator = visitor.into_generator();
+        result
+    }
+}
+
+/// Adds IDs to HTML as lol_html rewrites it, for pipelines that run lol_html
+/// themselves with handlers of their own next to these. The handlers are
+/// taken once and go into the rewriter settings ahead of any others, and the
+/// rewritten document then goes through `finish`. `HtmlProcessor` is this
+/// visitor around `rewrite_str`.
+pub struct HtmlVisitor<'a> {
+    content: &'a str,
+    generator: Rc<RefCell<IdGenerator>>,
+    resolved: Rc<ResolvedOptions>,
+    /// Marker of the placeholders standing in for slug IDs
+    deferred: Option<char>,
+    pending: Rc<RefCell<Vec<PendingId>>>,
+    element_content_handlers: Vec<(Cow<'static, lol_html::Selector>, ElementContentHandlers<'a>)>,
+    document_content_handlers: Vec<DocumentContentHandlers<'a>>,
+}
+
+impl<'a> HtmlVisitor<'a> {
+    /// A visitor drawing IDs from `generator` for `content`, which must also
+    /// be the document the rewriter is given
+    pub fn new(content: &'a str, options: &IdOptions, generator: IdGenerator) -> Result<Self, String> {
+        let resolved = Rc::new(ResolvedOptions::new(options, Format::Html)?);
+        Self::with_resolved(content, options, resolved, generator)
+    }
+
+    /// `new` with `options` compiled up front, e.g. once for many documents
+    pub fn with_resolved(
+        content: &'a str,
+        options: &IdOptions,
+        resolved: Rc<ResolvedOptions>,
+        generator: IdGenerator,
+    ) -> Result<Self, String> {
+        // Slug IDs need the element's text, which is only streamed after its
+        // start tag. Those elements get a placeholder that is filled in once the
+        // whole document has been seen.
+        let deferred = if matches!(options.strategy, IdStrategy::Slug) || OptionOverrides::any_selects(content, &IdStrategy::Slug) {
+            let marker = ('\u{E000}'..='\u{F8FF}')
+                .find(|c| !content.contains(*c))
+                .ok_or_else(|| "HTML processing error: no placeholder character available".to_string())?;
+            Some(marker)
         } else {
-            Rc::new(HashMap::new())
+            None
         };
+        let pending = Rc::new(RefCell::new(Vec::<PendingId>::new()));
+        let open_pending = Rc::new(RefCell::new(Vec::<usize>::new()));
         
-        let generator = Rc::new(RefCell::new(IdGenerator::new()));
+        // Positions are only looked up for the change log, the filter log,
+        // ranges and the warnings about existing IDs
+        let locator = Rc::new(RefCell::new(
+            (generator.logs_changes() || generator.logs_filters() || options.range.is_some() || content.contains(options.attr.as_str()))
+                .then(|| TagLocator::new(content)),
+        ));
+        let element_offset = Rc::new(Cell::new(0usize));
+        let locator_all = locator.clone();
+        // What is kept for an element's subtree ends with it, also where its
+        // end tag is implied
+        let open_elements = OpenElements::default();
+        let open_elements_selector = open_elements.clone();
+        let element_offset_all = element_offset.clone();
+
+        let generator = Rc::new(RefCell::new(generator));
         let options = Rc::new(options.clone());
-        let element_counter = Rc::new(RefCell::new(0usize));
+        let resolved_all = resolved.clone();
+        let resolved_selector = resolved.clone();
         
         let selector = if let Some(ref selector_str) = options.selector {
+            // `element!` panics on selectors it cannot parse
+            selector_str
+                .parse::<lol_html::Selector>()
+                .map_err(|e| format!("Invalid selector '{}': {}", selector_str, e))?;
             selector_str.clone()
         } else {
             "*".to_string() // Select all elements
         };
         
+        // Elements the selector leaves out are only seen by the handler for all
+        let log_unselected = options.selector.is_some() && generator.borrow().logs_filters();
         let generator_clone = generator.clone();
+        let generator_all = generator.clone();
         let options_clone = options.clone();
-        let counter_clone = element_counter.clone();
-        let text_map_clone = text_map.clone();
+        let pending_clone = pending.clone();
+        let open_pending_clone = open_pending.clone();
+
+        // Opt-out markers are tracked on every element, whatever the selector
+        let tracker = Rc::new(RefCell::new(IgnoreTracker::default()));
+        let ignored = Rc::new(Cell::new(false));
+        let tracker_clone = tracker.clone();
+        let ignored_clone = ignored.clone();
+        let tracker_comments = tracker.clone();
+        let template_depth = Rc::new(Cell::new(0usize));
+        // Content of <pre>, and of <code> with `skip_code`, is shown as is and
+        // often holds markup samples, so elements inside it are left alone.
+        // Script and style content is raw text and never reaches the handlers.
+        let verbatim_depth = Rc::new(Cell::new(0usize));
+
+        // Inline option directives apply to the element that follows them
+        let scopes = Rc::new(RefCell::new(OverrideScopes::default()));
+        let scopes_clone = scopes.clone();
+        let scopes_selector = scopes.clone();
+        let scopes_comments = scopes.clone();
+        let options_base = options.clone();
+
+        // Context of the current element for the taxonomy strategy
+        let taxonomy = Rc::new(RefCell::new(TaxonomyTracker::for_document(content, &options, &resolved)));
+        let taxonomy_clone = taxonomy.clone();
+        let context = Rc::new(RefCell::new(Vec::<String>::new()));
+        let context_clone = context.clone();
+
+        let namespaces = Namespaces::default();
+        // Path of the current element for path and hash IDs
+        let paths = Paths::default();
+        let paths_selector = paths.clone();
+        let counts_all = options.counter.counts_all();
+        let namespace = Rc::new(Cell::new(HTML_NAMESPACE));
+        let namespace_all = namespace.clone();
+
+        // An element's text is only streamed after its start tag, so it is
+        // looked up in a first pass over the document
+        let text_matches = match &resolved.text_pattern {
+            Some(pattern) => Some(
+                direct_texts(content)?
+                    .iter()
+                    .map(|text| pattern.is_match(text))
+                    .collect::<Vec<_>>(),
+            ),
+            None => None,
+        };
+        let mut element_index = 0usize;
+        let text_matched = Rc::new(Cell::new(true));
+        let text_matched_clone = text_matched.clone();
         
         let element_content_handlers = vec![
+            element!("*", move |el| {
+                if let Some(locator) = &mut *locator_all.borrow_mut() {
+                    element_offset_all.set(locator.next(&el.tag_name()));
+                }
+                if let Some(text_matches) = &text_matches {
+                    text_matched_clone.set(text_matches.get(element_index).copied().unwrap_or(false));
+                }
+                element_index += 1;
+                open_elements.enter(el);
+                generator_all.borrow_mut().count_visit();
+                namespace_all.set(namespaces.enter(el));
+                let component = options_base
+                    .component_scope
+                    .as_deref()
+                    .and_then(|attr| attribute(el, attr))
+                    .filter(|name| !name.is_empty());
+                paths.enter(el, &open_elements, counts_all, component.is_some());
+                if let Some(component) = &component {
+                    scopes_clone
+                        .borrow_mut()
+                        .add_component(resolved_all.component_overrides(&options_base, component));
+                }
+                let has_content = el.can_have_content();
+                let is_ignored = tracker_clone
+                    .borrow_mut()
+                    .enter(el.has_attribute(IGNORE_ATTR), has_content);
+                let in_template = template_depth.get() > 0;
+                let in_verbatim = verbatim_depth.get() > 0;
+                let is_code = options_base.skip_code && el.tag_name() == "code";
+                ignored_clone.set(is_ignored || (in_template && !options_base.templates) || in_verbatim || is_code);
+                if log_unselected && !ignored_clone.get() {
+                    // Replaced once the selector's handler sees the element
+                    ast_common::record_decision(
+                        &mut generator_all.borrow_mut(),
+                        element_offset_all.get(),
+                        &element_name(el, namespace_all.get()),
+                        Some(SkipReason::NotSelected),
+                    );
+                }
+
+                // Template content (and declarative shadow roots) is inert in the
+                // DOM but still streamed through here like any other markup
+                if el.tag_name() == "template" && has_content {
+                    template_depth.set(template_depth.get() + 1);
+                    let depth = template_depth.clone();
+                    open_elements.on_close(move || depth.set(depth.get() - 1));
+                }
+
+                if (is_code || el.tag_name() == "pre") && has_content {
+                    verbatim_depth.set(verbatim_depth.get() + 1);
+                    let depth = verbatim_depth.clone();
+                    open_elements.on_close(move || depth.set(depth.get() - 1));
+                }
+
+                if is_ignored && has_content {
+                    let tracker = tracker_clone.clone();
+                    open_elements.on_close(move || tracker.borrow_mut().leave());
+                }
+
+                if scopes_clone.borrow_mut().enter(&options_base, has_content) {
+                    let scopes = scopes_clone.clone();
+                    open_elements.on_close(move || scopes.borrow_mut().leave());
+                }
+
+                if let Some(tracker) = &mut *taxonomy_clone.borrow_mut() {
+                    let depth = tracker.depth();
+                    let element = SelectorElement {
+                        name: element_name(el, namespace_all.get()),
+                        attributes: attributes(el),
+                    };
+                    *context_clone.borrow_mut() = tracker.enter(element, has_content);
+                    if has_content {
+                        let taxonomy = taxonomy_clone.clone();
+                        open_elements.on_close(move || {
+                            if let Some(tracker) = &mut *taxonomy.borrow_mut() {
+                                tracker.truncate(depth);
+                            }
+                        });
+                    }
+                }
+                Ok(())
+            }),
             element!(selector.as_str(), move |el| {
-                let element_name = el.tag_name();
-                let existing_id = el.get_attribute(&options_clone.attr);
+                if ignored.get() {
+                    return Ok(());
+                }
+
+                let scopes = scopes_selector.borrow();
+                let options = scopes.current(&options_clone);
+                let foreign = foreign_options(namespace.get(), options);
+                let options = foreign.as_ref().unwrap_or(options);
+
+                let element_name = element_name(el, namespace.get());
+                let existing_id = attribute(el, &options.attr);
+                let offset = element_offset.get();
                 
-                if ast_common::should_process_node(&element_name, &options_clone, existing_id.as_deref()) {
-                    let counter = *counter_clone.borrow();
-                    
-                    let text_content = if matches!(options_clone.strategy, IdStrategy::Slug) {
-                        text_map_clone.get(&counter).cloned()
-                    } else {
-                        None
-                    };
-                    
-                    let path = vec![counter];
-                    *counter_clone.borrow_mut() += 1;
-                    
+                let classes = attribute(el, "class").unwrap_or_default();
+                let has_origin = options.origin_attr.as_ref().is_some_and(|attr| el.has_attribute(attr));
+                // Elements the selector leaves out never get here
+                let checked = {
+                    let mut generator = generator_clone.borrow_mut();
+                    ast_common::pick_element(&mut generator, true, text_matched.get()).and_then(|()| {
+                        let candidate = Candidate {
+                            name: &element_name,
+                            classes: &classes.split_whitespace().collect::<Vec<_>>(),
+                            offset,
+                            existing_id: existing_id.as_deref(),
+                            dynamic_id: false,
+                            has_origin,
+                        };
+                        ast_common::check_element(&mut generator, &resolved_selector.filter, options, &candidate)
+                    })
+                };
+                if let Err(reason) = checked {
+                    let mut generator = generator_clone.borrow_mut();
+                    ast_common::record_change(&mut generator, offset, &element_name, existing_id.as_deref(), None);
+                    ast_common::record_decision(&mut generator, offset, &element_name, Some(reason));
+                } else {
+                    if !counts_all {
+                        paths_selector.advance();
+                    }
+
                     let ast_node = AstNode {
                         node_type: element_name.clone(),
-                        text_content,
-                        attributes: Vec::new(),
-                        path,
+                        text_content: None,
+                        attributes: attributes(el),
+                        path: paths_selector.current(),
+                        context: context.borrow().clone(),
                     };
                     
-                    let id = ast_common::generate_id_for_node(
-                        &mut *generator_clone.borrow_mut(),
-                        &ast_node,
-                        &options_clone
-                    );
+                    // A deferred ID is a placeholder until the text is in, filled
+                    // in wherever a template used it
+                    let (id, extra) = if let Some(marker) = deferred {
+                        let mut pending = pending_clone.borrow_mut();
+                        let index = pending.len();
+                        let id = format!("{}{}{}", marker, index, marker);
+                        let extra = extra_attrs::render_all(&resolved_selector.extra_attrs, &ast_node, &id);
+                        ast_common::record_decision(&mut generator_clone.borrow_mut(), offset, &element_name, None);
+                        pending.push(PendingId {
+                            node: ast_node,
+                            options: options.clone(),
+                            text: String::new(),
+                            offset,
+                            existing_id: existing_id.clone(),
+                        });
+
+                        if el.can_have_content() {
+                            let mut open = open_pending_clone.borrow_mut();
+                            let depth = open.len();
+                            open.push(index);
+                            let open_pending = open_pending_clone.clone();
+                            open_elements_selector.on_close(move || open_pending.borrow_mut().truncate(depth));
+                        }
+                        (id, extra)
+                    } else {
+                        let mut generator = generator_clone.borrow_mut();
+                        let id = ast_common::generate_id_for_node(&mut generator, &ast_node, options, &resolved_selector);
+                        ast_common::record_change(
+                            &mut generator,
+                            offset,
+                            &element_name,
+                            existing_id.as_deref(),
+                            Some(&id),
+                        );
+                        ast_common::record_decision(&mut generator, offset, &element_name, None);
+                        let extra = extra_attrs::render_all(&resolved_selector.extra_attrs, &ast_node, &id);
+                        (id, extra)
+                    };
                     
                     // Set or replace the attribute
-                    if existing_id.is_none() || options_clone.overwrite {
-                        el.set_attribute(&options_clone.attr, &id)
+                    if existing_id.is_none() || options.overwrite {
+                        el.set_attribute(&options.attr, &ast_common::escape_attribute(&id))
                             .map_err(|e| format!("Failed to set attribute: {}", e))?;
+                        if let Some(origin_attr) = &options.origin_attr {
+                            el.set_attribute(origin_attr, &ast_common::origin(options))
+                                .map_err(|e| format!("Failed to set attribute: {}", e))?;
+                        }
+                        for (name, value) in extra {
+                            el.set_attribute(&name, &ast_common::escape_attribute(&value))
+                                .map_err(|e| format!("Failed to set attribute: {}", e))?;
+                        }
                     }
                 }
                 
@@ -113,14 +413,1046 @@ impl AstProcessor for HtmlProcessor {
             })
         ];
         
-        let rewrite_settings = RewriteStrSettings {
+        // Text nodes can arrive in several chunks; each whole node is added
+        // to every element with a pending ID that is still open
+        let pending_text = pending.clone();
+        let mut node_text = String::new();
+
+        Ok(Self {
+            content,
+            generator,
+            resolved,
+            deferred,
+            pending,
             element_content_handlers,
-            ..RewriteStrSettings::default()
+            document_content_handlers: vec![
+                doc_comments!(move |c| {
+                    let text = c.text();
+                    if ast_common::is_ignore_comment(&text) {
+                        tracker_comments.borrow_mut().mark_next();
+                    } else if let Some(overrides) = OptionOverrides::parse(&text)? {
+                        scopes_comments.borrow_mut().set_pending(overrides);
+                    }
+                    Ok(())
+                }),
+                doc_text!(move |t| {
+                    if deferred.is_none() || !is_document_text(t) {
+                        return Ok(());
+                    }
+                    node_text.push_str(t.as_str());
+                    if t.last_in_text_node() {
+                        let text = decode_entities(node_text.trim());
+                        if !text.is_empty() {
+                            let mut pending = pending_text.borrow_mut();
+                            for &index in open_pending.borrow().iter() {
+                                let element_text = &mut pending[index].text;
+                                if !element_text.is_empty() {
+                                    element_text.push(' ');
+                                }
+                                element_text.push_str(&text);
+                            }
+                        }
+                        node_text.clear();
+                    }
+                    Ok(())
+                }),
+            ],
+        })
+    }
+
+    /// Handlers for the elements of the document; empty once taken
+    pub fn element_content_handlers(&mut self) -> Vec<(Cow<'static, lol_html::Selector>, ElementContentHandlers<'a>)> {
+        std::mem::take(&mut self.element_content_handlers)
+    }
+
+    /// Handlers for the comments and text of the document; empty once taken
+    pub fn document_content_handlers(&mut self) -> Vec<DocumentContentHandlers<'a>> {
+        std::mem::take(&mut self.document_content_handlers)
+    }
+
+    /// The rewritten document with its slug IDs filled in, in the layout of
+    /// the original
+    pub fn finish(&mut self, output: String) -> String {
+        let output = match self.deferred {
+            Some(marker) => {
+                let ids: Vec<String> = std::mem::take(&mut *self.pending.borrow_mut())
+                    .into_iter()
+                    .map(|entry| self.generate_pending_id(entry))
+                    .collect();
+                fill_placeholders(&output, marker, &ids)
+            }
+            None => output,
         };
-        
-        rewrite_str(content, rewrite_settings)
-            .map_err(|e| format!("HTML processing error: {}", e))
+        ast_common::match_layout(self.content, output)
+    }
+
+    /// The generator, with the IDs handed out for the document
+    pub fn into_generator(self) -> IdGenerator {
+        std::mem::take(&mut *self.generator.borrow_mut())
+    }
+
+    fn generate_pending_id(&self, entry: PendingId) -> String {
+        let PendingId { mut node, options, text, offset, existing_id } = entry;
+        if matches!(options.strategy, IdStrategy::Slug) && !text.is_empty() {
+            node.text_content = Some(text);
+        }
+        let mut generator = self.generator.borrow_mut();
+        let id = ast_common::generate_id_for_node(&mut generator, &node, &options, &self.resolved);
+        ast_common::record_change(&mut generator, offset, &node.node_type, existing_id.as_deref(), Some(&id));
+        ast_common::escape_attribute(&id).into_owned()
+    }
+}
+
+/// An element whose ID is generated after its text content has been seen
+struct PendingId {
+    node: AstNode,
+    options: IdOptions,
+    text: String,
+    offset: usize,
+    existing_id: Option<String>,
+}
+
+/// Elements whose content is raw text, so tags inside them are not elements
+const RAW_TEXT_ELEMENTS: [&str; 9] = [
+    "script", "style", "textarea", "title", "xmp", "iframe", "noembed", "noframes", "noscript",
+];
+
+const HTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+/// SVG element names with capitals. HTML parsers lowercase tag names and then
+/// give inline SVG elements these names back.
+const SVG_MIXED_CASE_NAMES: [&str; 37] = [
+    "altGlyph", "altGlyphDef", "altGlyphItem", "animateColor", "animateMotion", "animateTransform",
+    "clipPath", "feBlend", "feColorMatrix", "feComponentTransfer", "feComposite", "feConvolveMatrix",
+    "feDiffuseLighting", "feDisplacementMap", "feDistantLight", "feDropShadow", "feFlood", "feFuncA",
+    "feFuncB", "feFuncG", "feFuncR", "feGaussianBlur", "feImage", "feMerge", "feMergeNode",
+    "feMorphology", "feOffset", "fePointLight", "feSpecularLighting", "feSpotLight", "feTile",
+    "feTurbulence", "foreignObject", "glyphRef", "linearGradient", "radialGradient", "textPath",
+];
+
+/// SVG and MathML elements whose content is HTML again
+const INTEGRATION_POINTS: [&str; 9] =
+    ["foreignobject", "desc", "title", "mi", "mo", "mn", "ms", "mtext", "annotation-xml"];
+
+/// Namespaces of the open elements. lol_html reports integration points such
+/// as `<foreignObject>` in the HTML namespace already, as their content is
+/// HTML; here they stay in the namespace of their parent.
+#[derive(Debug, Clone, Default)]
+struct Namespaces(Rc<RefCell<Vec<&'static str>>>);
+
+impl Namespaces {
+    /// Namespace of `el`, which is entered until its end tag
+    fn enter(&self, el: &mut Element) -> &'static str {
+        let mut open = self.0.borrow_mut();
+        let parent = open.last().copied().unwrap_or(HTML_NAMESPACE);
+        let reported = el.namespace_uri();
+        let namespace = if parent != HTML_NAMESPACE
+            && reported == HTML_NAMESPACE
+            && INTEGRATION_POINTS.contains(&el.tag_name().as_str())
+        {
+            parent
+        } else {
+            reported
+        };
+
+        if el.can_have_content() {
+            let depth = open.len();
+            open.push(namespace);
+            if let Some(handlers) = el.end_tag_handlers() {
+                let open = self.0.clone();
+                // Also closes children whose end tag was omitted
+                handlers.push(Box::new(move |_end| {
+                    open.borrow_mut().truncate(depth);
+                    Ok(())
+                }));
+            }
+        }
+        namespace
+    }
+}
+
+/// Elements that close an open `p`, as the HTML parser has it
+const CLOSES_P: [&str; 36] = [
+    "address", "article", "aside", "blockquote", "center", "details", "dialog", "dir", "div", "dl", "fieldset",
+    "figcaption", "figure", "footer", "header", "hgroup", "main", "menu", "nav", "ol", "p", "search", "section",
+    "summary", "ul", "h1", "h2", "h3", "h4", "h5", "h6", "pre", "listing", "form", "table", "hr",
+];
+
+/// Elements a `p` is not closed across
+const P_SCOPE: [&str; 10] = ["applet", "caption", "html", "table", "td", "th", "marquee", "object", "template", "button"];
+
+/// Elements an `li`, `dd` or `dt` is not closed across; the parser's special
+/// elements other than `address`, `div` and `p`
+const LIST_ITEM_SCOPE: [&str; 42] = [
+    "applet", "article", "aside", "blockquote", "body", "button", "caption", "center", "col", "colgroup", "details",
+    "dialog", "dir", "dl", "fieldset", "figcaption", "figure", "footer", "form", "header", "hgroup", "html",
+    "iframe", "main", "marquee", "menu", "nav", "object", "ol", "search", "section", "select", "summary", "table",
+    "tbody", "td", "template", "tfoot", "th", "thead", "tr", "ul",
+];
+
+/// How many of the `open` elements, innermost last, a start tag `name`
+/// closes without their end tag: `<li>One<li>Two`, `<p>Alpha<div>`.
+/// `counts` has how many elements of each name are open.
+fn implied_ends(open: &[OpenElement], counts: &HashMap<String, usize>, name: &str) -> usize {
+    let is_heading = |tag: &str| matches!(tag, "h1" | "h2" | "h3" | "h4" | "h5" | "h6");
+    let top = |tag: &str| open.last().is_some_and(|element| element.name == tag);
+    // The innermost open element named one of `closes`, unless one of `scope`
+    // comes first. None being open is the common case, and saves the walk.
+    let find = |closes: &[&str], scope: &[&str]| {
+        if !closes.iter().any(|tag| counts.get(*tag).is_some_and(|count| *count > 0)) {
+            return 0;
+        }
+        for (index, element) in open.iter().enumerate().rev() {
+            if closes.contains(&element.name.as_str()) {
+                return open.len() - index;
+            }
+            if scope.contains(&element.name.as_str()) {
+                break;
+            }
+        }
+        0
+    };
+    let p_closed = if CLOSES_P.contains(&name) || matches!(name, "li" | "dd" | "dt" | "plaintext" | "xmp") {
+        find(&["p"], &P_SCOPE)
+    } else {
+        0
+    };
+    let closed = match name {
+        "li" => find(&["li"], &LIST_ITEM_SCOPE),
+        "dd" | "dt" => find(&["dd", "dt"], &LIST_ITEM_SCOPE),
+        "option" => usize::from(top("option")),
+        "optgroup" => {
+            let option = usize::from(top("option"));
+            let optgroup = open.len().checked_sub(option + 1).is_some_and(|index| open[index].name == "optgroup");
+            option + usize::from(optgroup)
+        }
+        "td" | "th" => find(&["td", "th"], &["tr", "table", "template"]),
+        "tr" => find(&["tr"], &["tbody", "thead", "tfoot", "table", "template"]),
+        "tbody" | "thead" | "tfoot" => find(&["tbody", "thead", "tfoot"], &["table", "template"]),
+        name if is_heading(name) && open.last().is_some_and(|element| is_heading(&element.name)) => 1,
+        _ => 0,
+    };
+    closed.max(p_closed)
+}
+
+struct OpenElement {
+    serial: usize,
+    /// Empty for foreign elements, which no start tag implies the end of
+    name: String,
+    closers: Vec<Box<dyn FnOnce()>>,
+}
+
+#[derive(Default)]
+struct OpenStack {
+    elements: Vec<OpenElement>,
+    counts: HashMap<String, usize>,
+    entered: usize,
+}
+
+/// The open elements, as far as their end matters: lol_html only reports
+/// end tags that are written, so elements whose end tag the HTML parser
+/// implies (`<li>One<li>Two`) would stay open until their parent's end.
+/// Closers attached to an element run when it ends either way, innermost
+/// element first, as do those of elements closed along with it.
+#[derive(Clone, Default)]
+struct OpenElements(Rc<RefCell<OpenStack>>);
+
+impl OpenElements {
+    /// Enters `el`, first closing the elements its start tag implies the end
+    /// of. Elements with content stay open until their end.
+    fn enter(&self, el: &mut Element) {
+        let name = if el.namespace_uri() == HTML_NAMESPACE { el.tag_name() } else { String::new() };
+        let depth = {
+            let open = self.0.borrow();
+            let closed = if name.is_empty() { 0 } else { implied_ends(&open.elements, &open.counts, &name) };
+            open.elements.len() - closed
+        };
+        self.close_to(depth);
+
+        if el.can_have_content() {
+            let serial = {
+                let mut open = self.0.borrow_mut();
+                open.entered += 1;
+                let serial = open.entered;
+                *open.counts.entry(name.clone()).or_default() += 1;
+                open.elements.push(OpenElement { serial, name, closers: Vec::new() });
+                serial
+            };
+            let open = self.clone();
+            if let Some(handlers) = el.end_tag_handlers() {
+                handlers.push(Box::new(move |_end| {
+                    // Unless its end was implied already
+                    let position = open.0.borrow().elements.iter().rposition(|element| element.serial == serial);
+                    if let Some(position) = position {
+                        open.close_to(position);
+                    }
+                    Ok(())
+                }));
+            }
+        }
+    }
+
+    /// Runs `closer` when the element last entered, which must have content,
+    /// ends
+    fn on_close(&self, closer: impl FnOnce() + 'static) {
+        if let Some(element) = self.0.borrow_mut().elements.last_mut() {
+            element.closers.push(Box::new(closer));
+        }
+    }
+
+    /// Closes the open elements from `depth` on
+    fn close_to(&self, depth: usize) {
+        loop {
+            let element = {
+                let mut open = self.0.borrow_mut();
+                if open.elements.len() <= depth {
+                    return;
+                }
+                let element = open.elements.pop().expect("open element");
+                if let Some(count) = open.counts.get_mut(&element.name) {
+                    *count -= 1;
+                }
+                element
+            };
+            // Without the stack borrowed, as closers may look at it
+            for closer in element.closers.into_iter().rev() {
+                closer();
+            }
+        }
+    }
+}
+
+/// Counter values at the open elements, which make up the path of the next
+/// element, and the counter itself
+#[derive(Debug, Clone, Default)]
+struct Paths {
+    open: Rc<RefCell<Vec<usize>>>,
+    counter: Rc<Cell<usize>>,
+    components: Rc<RefCell<ComponentScopes>>,
+    /// Start of the path of the element last entered in `open`, its depth
+    /// and its position
+    current: Rc<Cell<(usize, usize, usize)>>,
+}
+
+impl Paths {
+    /// Enters `el`, just entered in `open_elements`, until its end, as well
+    /// as the scope it is the root of with `component`. With `counts_all`,
+    /// every element advances the counter; otherwise see `advance`.
+    fn enter(&self, el: &Element, open_elements: &OpenElements, counts_all: bool, component: bool) {
+        let mut open = self.open.borrow_mut();
+        let depth = open.len();
+        if component {
+            self.components.borrow_mut().enter(depth, self.counter.take());
+        }
+        let position = self.counter.get();
+        self.current.set((self.components.borrow().start(), depth, position));
+        if counts_all {
+            self.advance();
+        }
+
+        if el.can_have_content() {
+            open.push(position);
+            let paths = self.clone();
+            open_elements.on_close(move || {
+                paths.open.borrow_mut().truncate(depth);
+                paths.leave(depth);
+            });
+        } else if component {
+            self.leave(depth);
+        }
+    }
+
+    /// Path of the element last entered. Only built for elements that get
+    /// an ID, as it is as long as the element is deep.
+    fn current(&self) -> Vec<usize> {
+        let (start, depth, position) = self.current.get();
+        let mut path = self.open.borrow()[start..depth].to_vec();
+        path.push(position);
+        path
+    }
+
+    /// Goes on with the counter outside the component scopes closed at `depth`
+    fn leave(&self, depth: usize) {
+        if let Some(counter) = self.components.borrow_mut().leave(depth) {
+            self.counter.set(counter);
+        }
+    }
+
+    /// Advances the counter past an element
+    fn advance(&self) {
+        self.counter.set(self.counter.get() + 1);
+    }
+}
+
+/// Name of an element in `namespace` as the DOM has it: lowercase in HTML,
+/// and as SVG spells it for inline SVG, e.g. `linearGradient`, like the XML
+/// processor reports it. IDs, filters and selectors all see this name.
+fn element_name(el: &Element, namespace: &str) -> String {
+    let name = el.tag_name();
+    if namespace != SVG_NAMESPACE {
+        return name;
+    }
+    SVG_MIXED_CASE_NAMES
+        .iter()
+        .find(|svg_name| svg_name.eq_ignore_ascii_case(&name))
+        .map_or(name, |svg_name| svg_name.to_string())
+}
+
+/// Options for an element in the SVG or MathML `namespace`, when they differ from
+/// `options`. The `id` of such elements is an XML name, which must start
+/// with a letter or `_`, so IDs written to `id` get a `_` in front when the
+/// prefix does not make sure of that.
+fn foreign_options(namespace: &str, options: &IdOptions) -> Option<IdOptions> {
+    let starts_name = options.prefix.starts_with(|c: char| c.is_alphabetic() || c == '_');
+    if options.attr != "id" || starts_name || namespace == HTML_NAMESPACE {
+        return None;
+    }
+    Some(IdOptions { prefix: format!("_{}", options.prefix), ..options.clone() })
+}
+
+/// Whether `text` is text of the document rather than script, style sheet or
+/// other raw text content (`<noscript>`, `<iframe>` fallback, ...) that a
+/// browser running scripts never shows, and which is no element's text
+fn is_document_text(text: &TextChunk) -> bool {
+    !matches!(text.text_type(), TextType::ScriptData | TextType::RawText | TextType::PlainText)
+}
+
+/// Finds where each element starts in the source. lol_html does not report
+/// positions, but it streams elements in source order, so the next start tag
+/// with the element's name is the one being handled.
+struct TagLocator<'a> {
+    content: &'a str,
+    pos: usize,
+}
+
+impl<'a> TagLocator<'a> {
+    fn new(content: &'a str) -> Self {
+        Self { content, pos: 0 }
+    }
+
+    /// Returns the byte offset of the next `<tag` start tag.
+    fn next(&mut self, tag: &str) -> usize {
+        let bytes = self.content.as_bytes();
+        while let Some(found) = self.content[self.pos..].find('<') {
+            let start = self.pos + found;
+            let rest = &bytes[start + 1..];
+            if rest.starts_with(b"!--") {
+                self.pos = self.content[start..]
+                    .find("-->")
+                    .map_or(bytes.len(), |end| start + end + 3);
+                continue;
+            }
+
+            self.pos = start + 1;
+            let name_matches = rest.len() >= tag.len() && rest[..tag.len()].eq_ignore_ascii_case(tag.as_bytes());
+            let name_ends = rest
+                .get(tag.len())
+                .is_none_or(|&b| b.is_ascii_whitespace() || b == b'/' || b == b'>');
+            if name_matches && name_ends {
+                if RAW_TEXT_ELEMENTS.contains(&tag) {
+                    self.skip_to_end_tag(tag);
+                }
+                return start;
+            }
+        }
+        bytes.len()
+    }
+
+    fn skip_to_end_tag(&mut self, tag: &str) {
+        let bytes = self.content.as_bytes();
+        self.pos = bytes[self.pos..]
+            .windows(tag.len() + 2)
+            .position(|w| w.starts_with(b"</") && w[2..].eq_ignore_ascii_case(tag.as_bytes()))
+            .map_or(bytes.len(), |end| self.pos + end);
+    }
+}
+
+/// Replaces each `<marker><index><marker>` in `output` with `ids[index]`.
+fn fill_placeholders(output: &str, marker: char, ids: &[String]) -> String {
+    let mut result = String::with_capacity(output.len());
+    let mut parts = output.split(marker);
+    result.push_str(parts.next().unwrap_or_default());
+    while let (Some(index), Some(rest)) = (parts.next(), parts.next()) {
+        if let Some(id) = index.parse::<usize>().ok().and_then(|i| ids.get(i)) {
+            result.push_str(id);
+        }
+        result.push_str(rest);
+    }
+    result
+}
+
+/// Value of attribute `name` of `el`, character references decoded
+fn attribute(el: &Element, name: &str) -> Option<String> {
+    el.get_attribute(name).map(|value| decode_entities(&value))
+}
+
+/// Names and values of the attributes of `el`, character references decoded
+fn attributes(el: &Element) -> Vec<(String, String)> {
+    el.attributes().iter().map(|attr| (attr.name(), decode_entities(&attr.value()))).collect()
+}
+
+/// Decodes the character references that commonly appear in text, for slugs
+/// and element text reported to the user.
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let ch = reference.and_then(|name| match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => {
+                let code = name.strip_prefix('#')?;
+                let value = match code.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => code.parse().ok()?,
+                };
+                char::from_u32(value)
+            }
+        });
+        match (ch, reference) {
+            (Some(ch), Some(name)) => {
+                decoded.push(ch);
+                rest = &rest[name.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Returns the values of `attr` already present in an HTML document.
+pub fn existing_ids(content: &str, attr: &str) -> Result<Vec<String>, String> {
+    Ok(locate_ids(content, attr)?.into_iter().map(|location| location.id).collect())
+}
+
+/// Finds every element carrying `attr`, in document order.
+pub fn locate_ids(content: &str, attr: &str) -> Result<Vec<IdLocation>, String> {
+    let locations = Rc::new(RefCell::new(Vec::new()));
+    let locations_clone = locations.clone();
+    let attr_name = attr.to_string();
+    let mut locator = TagLocator::new(content);
+    let namespaces = Namespaces::default();
+
+    let rewrite_settings = RewriteStrSettings {
+        element_content_handlers: vec![element!("*", move |el| {
+            let namespace = namespaces.enter(el);
+            let tag = element_name(el, namespace);
+            let offset = locator.next(&tag);
+            if let Some(id) = attribute(el, &attr_name) {
+                let attributes = attributes(el);
+                locations_clone
+                    .borrow_mut()
+                    .push(IdLocation::new(offset, tag, id, attributes, &attr_name));
+            }
+            Ok(())
+        })],
+        ..RewriteStrSettings::default()
+    };
+
+    rewrite_str(content, rewrite_settings)
+        .map_err(|e| format!("HTML processing error: {}", e))?;
+
+    Ok(locations.take())
+}
+
+/// Finds every element matching the CSS `policy` and whether it carries
+/// `attr`, in document order.
+pub fn policy_elements(content: &str, policy: &str, attr: &str) -> Result<Vec<PolicyElement>, String> {
+    // `element!` panics on selectors it cannot parse
+    policy
+        .parse::<lol_html::Selector>()
+        .map_err(|e| format!("Invalid selector '{}': {}", policy, e))?;
+
+    let elements = Rc::new(RefCell::new(Vec::new()));
+    let elements_clone = elements.clone();
+    let offset = Rc::new(Cell::new(0usize));
+    let offset_clone = offset.clone();
+    let mut locator = TagLocator::new(content);
+    let attr_name = attr.to_string();
+    let namespaces = Namespaces::default();
+    let tag = Rc::new(RefCell::new(String::new()));
+    let tag_clone = tag.clone();
+
+    let rewrite_settings = RewriteStrSettings {
+        element_content_handlers: vec![
+            element!("*", move |el| {
+                offset.set(locator.next(&el.tag_name()));
+                let namespace = namespaces.enter(el);
+                *tag.borrow_mut() = element_name(el, namespace);
+                Ok(())
+            }),
+            element!(policy, move |el| {
+                elements_clone.borrow_mut().push(PolicyElement {
+                    offset: offset_clone.get(),
+                    tag: tag_clone.borrow().clone(),
+                    covered: el.get_attribute(&attr_name).is_some_and(|id| !id.trim().is_empty()),
+                });
+                Ok(())
+            }),
+        ],
+        ..RewriteStrSettings::default()
+    };
+
+    rewrite_str(content, rewrite_settings)
+        .map_err(|e| format!("HTML processing error: {}", e))?;
+
+    Ok(elements.take())
+}
+
+/// Removes `attr` from every element whose value is one of `ids`, leaving
+/// hand-written values alone. With `origin_attr`, only elements carrying it
+/// count as generated, and they lose it too.
+pub fn remove_ids(
+    content: &str,
+    attr: &str,
+    ids: &HashSet<String>,
+    origin_attr: Option<&str>,
+) -> Result<String, String> {
+    let attr_name = attr.to_string();
+    let origin_attr = origin_attr.map(str::to_string);
+    let ids = ids.clone();
+
+    let rewrite_settings = RewriteStrSettings {
+        element_content_handlers: vec![element!("*", move |el| {
+            let generated = origin_attr.as_ref().is_none_or(|origin| el.has_attribute(origin));
+            if let Some(value) = attribute(el, &attr_name) {
+                if generated && ids.contains(&value) {
+                    el.remove_attribute(&attr_name);
+                    if let Some(origin) = &origin_attr {
+                        el.remove_attribute(origin);
+                    }
+                }
+            }
+            Ok(())
+        })],
+        ..RewriteStrSettings::default()
+    };
+
+    rewrite_str(content, rewrite_settings)
+        .map_err(|e| format!("HTML processing error: {}", e))
+}
+
+/// Sets `attr` on every element matching the CSS `selector`, replacing any
+/// value it has. The first match gets `value`; `generator` makes the others
+/// unique. Returns the output and the IDs written, in document order.
+pub fn set_ids(
+    content: &str,
+    selector: &str,
+    attr: &str,
+    value: &str,
+    generator: &mut IdGenerator,
+) -> Result<(String, Vec<String>), String> {
+    // `element!` panics on selectors it cannot parse
+    selector
+        .parse::<lol_html::Selector>()
+        .map_err(|e| format!("Invalid selector '{}': {}", selector, e))?;
+
+    let matched = Rc::new(RefCell::new(Vec::new()));
+    let matched_clone = matched.clone();
+    let attr_name = attr.to_string();
+    let rewrite_settings = RewriteStrSettings {
+        element_content_handlers: vec![element!(selector, move |el| {
+            matched_clone.borrow_mut().push(attribute(el, &attr_name));
+            Ok(())
+        })],
+        ..RewriteStrSettings::default()
+    };
+    rewrite_str(content, rewrite_settings)
+        .map_err(|e| format!("HTML processing error: {}", e))?;
+
+    let matched = matched.take();
+    let ids = ast_common::manual_ids(generator, value, existing_ids(content, attr)?, &matched);
+
+    let mut queue = ids.clone().into_iter();
+    let attr_name = attr.to_string();
+    let rewrite_settings = RewriteStrSettings {
+        element_content_handlers: vec![element!(selector, move |el| {
+            if let Some(id) = queue.next() {
+                el.set_attribute(&attr_name, &ast_common::escape_attribute(&id))
+                    .map_err(|e| format!("Failed to set attribute: {}", e))?;
+            }
+            Ok(())
+        })],
+        ..RewriteStrSettings::default()
+    };
+    let output = rewrite_str(content, rewrite_settings)
+        .map_err(|e| format!("HTML processing error: {}", e))?;
+
+    Ok((output, ids))
+}
+
+/// Like `set_ids`, for the elements matching a `Selector`, such as one parsed
+/// from XPath.
+pub fn set_ids_matching(
+    content: &str,
+    selector: &Selector,
+    attr: &str,
+    value: &str,
+    generator: &mut IdGenerator,
+) -> Result<(String, Vec<String>), String> {
+    let all = select_elements(content, &Selector::parse("*")?, attr)?;
+    let matched = select_elements(content, selector, attr)?;
+    let matched_ids: Vec<Option<String>> = matched.iter().map(|element| element.id.clone()).collect();
+    let ids = ast_common::manual_ids(generator, value, existing_ids(content, attr)?, &matched_ids);
+
+    // One entry per element in document order, as `apply_ids` expects
+    let mut matched_offsets = matched.iter().map(|element| element.offset).peekable();
+    let mut queue = ids.iter();
+    let element_ids: Vec<Option<String>> = all
+        .iter()
+        .map(|element| matched_offsets.next_if_eq(&element.offset).and_then(|_| queue.next().cloned()))
+        .collect();
+    let output = apply_ids(content, attr, &element_ids)?;
+
+    Ok((output, ids))
+}
+
+/// Sets `attr` on the elements whose entry in `ids` is `Some`, replacing any
+/// value they have. `ids` has an entry per element, in document order.
+pub fn apply_ids(content: &str, attr: &str, ids: &[Option<String>]) -> Result<String, String> {
+    let mut ids = ids.iter();
+    let rewrite_settings = RewriteStrSettings {
+        element_content_handlers: vec![element!("*", move |el| {
+            if let Some(Some(id)) = ids.next() {
+                el.set_attribute(attr, &ast_common::escape_attribute(id))
+                    .map_err(|e| format!("Failed to set attribute: {}", e))?;
+            }
+            Ok(())
+        })],
+        ..RewriteStrSettings::default()
+    };
+
+    rewrite_str(content, rewrite_settings)
+        .map_err(|e| format!("HTML processing error: {}", e))
+}
+
+/// `name` of the `<meta>` pointing a page to its manifest
+pub const MANIFEST_META_NAME: &str = "ast-append-ids";
+
+/// Points the `<head>` of `content` to the manifest at `reference`, as
+/// `IdOptions::manifest_meta` does: `<meta name="ast-append-ids"
+/// content="manifest=ids.json; ids=...">`, where `ids` is `ids_hash` of the
+/// values of `attr` in the document. A meta left by an earlier run is
+/// updated rather than added to. Documents without a `<head>` tag, such as
+/// fragments, come back as they are. Pipelines running `HtmlVisitor` call it
+/// on the result of `finish`.
+pub fn write_manifest_meta(content: &str, reference: &str, attr: &str) -> Result<String, String> {
+    let ids = Rc::new(RefCell::new(Vec::<String>::new()));
+    let has_head = Rc::new(Cell::new(false));
+    let has_meta = Rc::new(Cell::new(false));
+    let (ids_clone, has_head_clone, has_meta_clone) = (ids.clone(), has_head.clone(), has_meta.clone());
+    let scan_settings = RewriteStrSettings {
+        element_content_handlers: vec![element!("*", move |el| {
+            match el.tag_name().as_str() {
+                "head" => has_head_clone.set(true),
+                "meta" if is_manifest_meta(el) => {
+                    has_meta_clone.set(true);
+                    return Ok(());
+                }
+                _ => {}
+            }
+            if let Some(id) = el.get_attribute(attr) {
+                ids_clone.borrow_mut().push(decode_entities(&id));
+            }
+            Ok(())
+        })],
+        ..RewriteStrSettings::default()
+    };
+    rewrite_str(content, scan_settings).map_err(|e| format!("HTML processing error: {}", e))?;
+    if !has_head.get() {
+        return Ok(content.to_string());
     }
+
+    let value = format!("manifest={}; ids={}", reference, ids_hash(&ids.borrow()));
+    let value = ast_common::escape_attribute(&value).into_owned();
+    let mut written = false;
+    let rewrite_settings = RewriteStrSettings {
+        element_content_handlers: vec![element!("head, meta", move |el| {
+            if written {
+                return Ok(());
+            }
+            if has_meta.get() && el.tag_name() == "meta" && is_manifest_meta(el) {
+                el.set_attribute("content", &value)?;
+                written = true;
+            } else if !has_meta.get() && el.tag_name() == "head" {
+                // Ignored, so later runs do not give it an ID
+                let meta = format!(r#"<meta name="{}" content="{}" {}>"#, MANIFEST_META_NAME, value, IGNORE_ATTR);
+                el.prepend(&meta, ContentType::Html);
+                written = true;
+            }
+            Ok(())
+        })],
+        ..RewriteStrSettings::default()
+    };
+    rewrite_str(content, rewrite_settings).map_err(|e| format!("HTML processing error: {}", e))
+}
+
+/// Hash of the IDs of a page, in document order, as `write_manifest_meta`
+/// records it: the first 12 hex characters of the SHA-256 of the IDs joined
+/// by newlines
+pub fn ids_hash(ids: &[String]) -> String {
+    use sha2::{Digest, Sha256};
+    let hash = format!("{:x}", Sha256::digest(ids.join("\n").as_bytes()));
+    hash[..12].to_string()
+}
+
+fn is_manifest_meta(el: &Element) -> bool {
+    el.get_attribute("name").is_some_and(|name| name == MANIFEST_META_NAME)
+}
+
+/// Text directly inside each element, leaving out that of its child elements,
+/// in document order with entities decoded.
+fn direct_texts(content: &str) -> Result<Vec<String>, String> {
+    let texts = Rc::new(RefCell::new(Vec::<String>::new()));
+    let open = Rc::new(RefCell::new(Vec::<usize>::new()));
+    let texts_clone = texts.clone();
+    let open_clone = open.clone();
+    let open_elements = OpenElements::default();
+
+    let rewrite_settings = RewriteStrSettings {
+        element_content_handlers: vec![element!("*", move |el| {
+            open_elements.enter(el);
+            let mut texts = texts_clone.borrow_mut();
+            let index = texts.len();
+            texts.push(String::new());
+            if el.can_have_content() {
+                let mut open = open_clone.borrow_mut();
+                let depth = open.len();
+                open.push(index);
+                let open = open_clone.clone();
+                open_elements.on_close(move || open.borrow_mut().truncate(depth));
+            }
+            Ok(())
+        })],
+        document_content_handlers: vec![doc_text!(|t| {
+            if !is_document_text(t) {
+                return Ok(());
+            }
+            if let Some(&index) = open.borrow().last() {
+                texts.borrow_mut()[index].push_str(t.as_str());
+            }
+            Ok(())
+        })],
+        ..RewriteStrSettings::default()
+    };
+
+    rewrite_str(content, rewrite_settings)
+        .map_err(|e| format!("HTML processing error: {}", e))?;
+
+    Ok(texts.take().iter().map(|text| decode_entities(text)).collect())
+}
+
+/// Text of an element carrying an ID, collected by `element_texts`
+struct ElementText {
+    id: String,
+    text: String,
+    /// No child elements, so the text is the whole content
+    leaf: bool,
+    closed: bool,
+}
+
+/// Returns the text of every element that carries `attr` and contains only
+/// text, in document order, with entities decoded and surrounding whitespace
+/// trimmed. Elements with child elements, raw text elements (`script`,
+/// `style`, ...) and empty ones are left out.
+pub fn element_texts(content: &str, attr: &str) -> Result<Vec<(String, String)>, String> {
+    let elements = Rc::new(RefCell::new(Vec::<ElementText>::new()));
+    let open = Rc::new(RefCell::new(Vec::<usize>::new()));
+    let elements_clone = elements.clone();
+    let open_clone = open.clone();
+    let elements_text = elements.clone();
+    let attr_name = attr.to_string();
+
+    let rewrite_settings = RewriteStrSettings {
+        element_content_handlers: vec![element!("*", move |el| {
+            let mut elements = elements_clone.borrow_mut();
+            for &index in open_clone.borrow().iter() {
+                elements[index].leaf = false;
+            }
+
+            let tag = el.tag_name();
+            let Some(id) = attribute(el, &attr_name) else {
+                return Ok(());
+            };
+            if !el.can_have_content() || RAW_TEXT_ELEMENTS.contains(&tag.as_str()) {
+                return Ok(());
+            }
+
+            let index = elements.len();
+            elements.push(ElementText {
+                id,
+                text: String::new(),
+                leaf: true,
+                closed: false,
+            });
+            let mut open = open_clone.borrow_mut();
+            let depth = open.len();
+            open.push(index);
+
+            let elements = elements_clone.clone();
+            let open = open_clone.clone();
+            if let Some(handlers) = el.end_tag_handlers() {
+                // Also closes children whose end tag was omitted
+                handlers.push(Box::new(move |_end| {
+                    open.borrow_mut().truncate(depth);
+                    elements.borrow_mut()[index].closed = true;
+                    Ok(())
+                }));
+            }
+            Ok(())
+        })],
+        document_content_handlers: vec![doc_text!(move |t| {
+            if let Some(&index) = open.borrow().last() {
+                elements_text.borrow_mut()[index].text.push_str(t.as_str());
+            }
+            Ok(())
+        })],
+        ..RewriteStrSettings::default()
+    };
+
+    rewrite_str(content, rewrite_settings)
+        .map_err(|e| format!("HTML processing error: {}", e))?;
+
+    Ok(elements
+        .take()
+        .into_iter()
+        .filter(|element| element.leaf && element.closed)
+        .map(|element| (element.id, decode_entities(element.text.trim())))
+        .filter(|(_, text)| !text.is_empty())
+        .collect())
+}
+
+/// Finds every element matching `selector`, with its ID and the text of its
+/// descendants (entities decoded), in document order. Tag names match
+/// regardless of case, as in CSS.
+pub fn select_elements(content: &str, selector: &Selector, attr: &str) -> Result<Vec<SelectedElement>, String> {
+    let selector = selector.clone().ignoring_case(true);
+    let elements = Rc::new(RefCell::new(Vec::<SelectedElement>::new()));
+    let path = Rc::new(RefCell::new(Vec::<SelectorElement>::new()));
+    // Selected elements collecting text, with their depth in `path`
+    let open = Rc::new(RefCell::new(Vec::<(usize, usize)>::new()));
+    let elements_clone = elements.clone();
+    let open_clone = open.clone();
+    let mut locator = TagLocator::new(content);
+    let namespaces = Namespaces::default();
+
+    let rewrite_settings = RewriteStrSettings {
+        element_content_handlers: vec![element!("*", move |el| {
+            let offset = locator.next(&el.tag_name());
+            let namespace = namespaces.enter(el);
+            let name = element_name(el, namespace);
+            let mut path_elements = path.borrow_mut();
+            let depth = path_elements.len();
+            path_elements.push(SelectorElement {
+                name: name.clone(),
+                attributes: attributes(el),
+            });
+
+            if selector.matches(&path_elements) {
+                let mut elements = elements_clone.borrow_mut();
+                if el.can_have_content() {
+                    open_clone.borrow_mut().push((depth, elements.len()));
+                }
+                elements.push(SelectedElement {
+                    offset,
+                    tag: name,
+                    ancestors: path_elements[..depth].iter().map(|element| element.name.clone()).collect(),
+                    id: attribute(el, attr),
+                    text: String::new(),
+                });
+            }
+
+            if !el.can_have_content() {
+                path_elements.pop();
+            } else if let Some(handlers) = el.end_tag_handlers() {
+                let path = path.clone();
+                let open = open_clone.clone();
+                // Also closes children whose end tag was omitted
+                handlers.push(Box::new(move |_end| {
+                    path.borrow_mut().truncate(depth);
+                    open.borrow_mut().retain(|&(open_depth, _)| open_depth < depth);
+                    Ok(())
+                }));
+            }
+            Ok(())
+        })],
+        document_content_handlers: vec![doc_text!(|t| {
+            if !is_document_text(t) {
+                return Ok(());
+            }
+            let mut elements = elements.borrow_mut();
+            for &(_, index) in open.borrow().iter() {
+                elements[index].text.push_str(t.as_str());
+            }
+            Ok(())
+        })],
+        ..RewriteStrSettings::default()
+    };
+
+    rewrite_str(content, rewrite_settings)
+        .map_err(|e| format!("HTML processing error: {}", e))?;
+
+    Ok(elements
+        .take()
+        .into_iter()
+        .map(|element| SelectedElement {
+            text: decode_entities(&element.text),
+            ..element
+        })
+        .collect())
+}
+
+/// Replaces the content of the elements `element_texts` reports with the text
+/// keyed by their ID in `texts`, escaping it. Elements whose text is already
+/// the same are left untouched. Returns the output and the IDs replaced, in
+/// document order.
+pub fn replace_texts(
+    content: &str,
+    attr: &str,
+    texts: &HashMap<String, String>,
+) -> Result<(String, Vec<String>), String> {
+    let current: HashMap<String, String> = element_texts(content, attr)?.into_iter().collect();
+    let replaced = Rc::new(RefCell::new(Vec::new()));
+    let replaced_clone = replaced.clone();
+    let attr_name = attr.to_string();
+    let texts = texts.clone();
+
+    let rewrite_settings = RewriteStrSettings {
+        element_content_handlers: vec![element!("*", move |el| {
+            let Some(id) = attribute(el, &attr_name) else {
+                return Ok(());
+            };
+            if let (Some(old), Some(new)) = (current.get(&id), texts.get(&id)) {
+                if old != new {
+                    el.set_inner_content(new, ContentType::Text);
+                    replaced_clone.borrow_mut().push(id);
+                }
+            }
+            Ok(())
+        })],
+        ..RewriteStrSettings::default()
+    };
+
+    let output = rewrite_str(content, rewrite_settings)
+        .map_err(|e| format!("HTML processing error: {}", e))?;
+
+    Ok((output, replaced.take()))
 }
 
 #[cfg(test)]
@@ -187,4 +1519,481 @@ mod tests {
         assert!(!result2.contains("data-ast-id=\"existing\""));
         assert!(result2.contains("data-ast-id=\""));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_html_existing_ids_and_reserved_generator() {
+        let input = r#"<div data-ast-id="el-div-0"><span>Hi</span></div>"#;
+        let ids = existing_ids(input, "data-ast-id").unwrap();
+        assert_eq!(ids, vec!["el-div-0".to_string()]);
+
+        let mut generator = IdGenerator::new();
+        generator.reserve("el-span-0-1");
+        let mut processor = HtmlProcessor::with_generator(generator);
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+
+        let result = processor.process(input, &options).unwrap();
+        assert!(result.contains("data-ast-id=\"el-div-0\""));
+        assert!(result.contains("data-ast-id=\"el-span-0-1-2\""));
+        assert!(processor.into_generator().is_used("el-span-0-1-2"));
+    }
+
+    #[test]
+    fn test_html_ignore_markers() {
+        let mut processor = HtmlProcessor::new();
+        let options = IdOptions::default();
+
+        let input = r#"<div data-ast-ignore><span>skip</span></div><!-- ast-append-ids: ignore --><p><b>skip</b></p><i>tag</i>"#;
+        let result = processor.process(input, &options).unwrap();
+
+        assert_eq!(result.matches("data-ast-id=").count(), 1);
+        assert!(result.contains("<i data-ast-id="));
+
+        // An ignored element ends where its end tag is implied
+        let result = processor.process("<ul><li data-ast-ignore>a<li>b</ul>", &options).unwrap();
+        assert_eq!(result.matches("<li data-ast-id=").count(), 1, "{}", result);
+        assert!(result.contains("<li data-ast-ignore>a<li data-ast-id="), "{}", result);
+        let result = processor.process("<div><p data-ast-ignore>a<p>b</div>", &options).unwrap();
+        assert!(result.contains("<p data-ast-ignore>a<p data-ast-id="), "{}", result);
+    }
+
+    #[test]
+    fn test_html_option_directives() {
+        let mut processor = HtmlProcessor::new();
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+
+        let input = r#"<main><!-- ast-append-ids: prefix=modal- --><section><p>A</p></section><p>B</p><!-- ast-append-ids: strategy=hash --><br><p>C</p></main>"#;
+        let result = processor.process(input, &options).unwrap();
+
+        assert!(result.contains(r#"<main data-ast-id="el-main-0">"#));
+        assert!(result.contains(r#"<section data-ast-id="modal-section-0-1">"#));
+        assert!(result.contains(r#"<p data-ast-id="modal-p-0-1-2">A</p>"#));
+        assert!(result.contains(r#"<p data-ast-id="el-p-0-3">B</p>"#));
+        assert!(result.contains(r#"<br data-ast-id="el-"#) && !result.contains("el-br-0-4"));
+        assert!(result.contains(r#"<p data-ast-id="el-p-0-5">C</p>"#));
+
+        let bad = "<!-- ast-append-ids: strategy=random --><div></div>";
+        assert!(HtmlProcessor::new().process(bad, &options).is_err());
+    }
+
+    #[test]
+    fn test_html_template_content() {
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+
+        let input = r#"<div><template><p>a</p></template><my-card><template shadowrootmode="open"><slot></slot></template></my-card><i>x</i></div>"#;
+        let result = HtmlProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains(r#"<template data-ast-id="el-template-0-1"><p data-ast-id="el-p-0-1-2">a</p></template>"#));
+        assert!(result.contains(r#"<template shadowrootmode="open" data-ast-id="el-template-0-3-4"><slot data-ast-id="el-slot-0-3-4-5"></slot>"#));
+        assert!(result.contains(r#"<i data-ast-id="el-i-0-6">"#));
+
+        options.templates = false;
+        let result = HtmlProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains(r#"<template data-ast-id="el-template-0-1"><p>a</p></template>"#));
+        assert!(result.contains(r#"<template shadowrootmode="open" data-ast-id="el-template-0-3-4"><slot></slot>"#));
+        assert!(result.contains(r#"<i data-ast-id="el-i-0-6">"#));
+    }
+
+    #[test]
+    fn test_html_verbatim_content() {
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+        options.selector = Some("*".to_string());
+
+        let input = r#"<div><script>const t = "<p>x</p>"; if (a<b) {}</script><style>p > a { color: red }</style><!-- <p>c</p> --><pre><b>1</b><code><i>2</i></code></pre><code><span>3</span></code><textarea><p>4</p></textarea></div>"#;
+        let result = HtmlProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains(r#"<script data-ast-id="el-script-0-1">const t = "<p>x</p>"; if (a<b) {}</script>"#));
+        assert!(result.contains(r#"<style data-ast-id="el-style-0-2">p > a { color: red }</style><!-- <p>c</p> -->"#));
+        assert!(result.contains(r#"<pre data-ast-id="el-pre-0-3"><b>1</b><code><i>2</i></code></pre>"#));
+        assert!(result.contains(r#"<code data-ast-id="el-code-0-7"><span data-ast-id="el-span-0-7-8">3</span></code>"#));
+        assert!(result.contains(r#"<textarea data-ast-id="el-textarea-0-9"><p>4</p></textarea>"#));
+
+        options.skip_code = true;
+        let result = HtmlProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains(r#"<pre data-ast-id="el-pre-0-3"><b>1</b><code><i>2</i></code></pre><code><span>3</span></code>"#));
+        assert!(result.contains(r#"<textarea data-ast-id="el-textarea-0-9">"#));
+    }
+
+    #[test]
+    fn test_html_taxonomy_ids() {
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Taxonomy;
+
+        let input = r#"<main><section aria-label="Checkout"><form name="payment"><input><li>One<li>Two</form><button>Pay</button></section></main><p>x</p>"#;
+        let result = HtmlProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains(r#"<main data-ast-id="el-main-0">"#));
+        assert!(result.contains(r#"<section aria-label="Checkout" data-ast-id="el-main.section-0">"#));
+        assert!(result.contains(r#"<input data-ast-id="el-main.payment.input-0">"#));
+        assert!(result.contains(r#"<li data-ast-id="el-main.payment.li-1">Two"#));
+        assert!(result.contains(r#"<button data-ast-id="el-main.checkout.button-0">"#));
+        assert!(result.contains(r#"<p data-ast-id="el-p-0">"#));
+
+        options.taxonomy_levels = vec!["page=form".to_string()];
+        let result = HtmlProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains(r#"<input data-ast-id="el-payment.input-0">"#));
+        assert!(result.contains(r#"<button data-ast-id="el-button-0">"#));
+    }
+
+    #[test]
+    fn test_html_text_matches() {
+        let mut options = IdOptions::default();
+        options.text_matches = Some(r"[$€]\s?\d".to_string());
+
+        let input = r#"<ul><li>Tea <span>$3.50</span></li><li>Cake: &#8364;4<br></li><li>Free refills</li></ul>"#;
+        let result = HtmlProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains(r#"<li>Tea <span data-ast-id="#));
+        assert!(result.contains(r#"<li data-ast-id="el-"#));
+        assert!(result.contains("<li>Free refills</li>"));
+        assert!(result.contains("<br>"));
+
+        options.text_matches = Some("[".to_string());
+        assert!(HtmlProcessor::new().process(input, &options).is_err());
+    }
+
+    #[test]
+    fn test_html_slug_uses_element_text() {
+        let mut processor = HtmlProcessor::new();
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Slug;
+
+        // A fragment: the HTML parser would add html/head/body around it
+        let input = "<ul><li>Tom &amp; Jerry</li><li>Second <b>item</b></li><li>Third</ul><p>After</p>";
+        let result = processor.process(input, &options).unwrap();
+        assert_eq!(
+            result,
+            concat!(
+                r#"<ul data-ast-id="el-tom-jerry-second-item-third">"#,
+                r#"<li data-ast-id="el-tom-jerry">Tom &amp; Jerry</li>"#,
+                r#"<li data-ast-id="el-second-item">Second <b data-ast-id="el-item">item</b></li>"#,
+                r#"<li data-ast-id="el-third">Third</ul>"#,
+                r#"<p data-ast-id="el-after">After</p>"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_html_implied_end_tags() {
+        let options = IdOptions { strategy: IdStrategy::Slug, ..IdOptions::default() };
+
+        // The text of an element whose end tag is left out is not the next one's
+        let result = HtmlProcessor::new().process("<ul><li>One<li>Two</ul>", &options).unwrap();
+        assert_eq!(
+            result,
+            r#"<ul data-ast-id="el-one-two"><li data-ast-id="el-one">One<li data-ast-id="el-two">Two</ul>"#
+        );
+        let result = HtmlProcessor::new().process("<div><p>Alpha<p>Beta</div>", &options).unwrap();
+        assert_eq!(
+            result,
+            r#"<div data-ast-id="el-alpha-beta"><p data-ast-id="el-alpha">Alpha<p data-ast-id="el-beta">Beta</div>"#
+        );
+        let result = HtmlProcessor::new()
+            .process("<dl><dt>Term<dd>Meaning</dl><p>Text<ul><li>Item</ul>", &options)
+            .unwrap();
+        for id in ["el-term", "el-meaning", "el-text", "el-item"] {
+            assert!(result.contains(&format!(r#"data-ast-id="{}">"#, id)), "{}", result);
+        }
+
+        // Nor is the element the next one's parent
+        let options = IdOptions { strategy: IdStrategy::Path, ..IdOptions::default() };
+        let result = HtmlProcessor::new().process("<ul><li>One<li>Two</ul>", &options).unwrap();
+        assert_eq!(result, r#"<ul data-ast-id="el-ul-0"><li data-ast-id="el-li-0-1">One<li data-ast-id="el-li-0-2">Two</ul>"#);
+
+        // Only a directive turns on slug IDs, not text that mentions them
+        let input = "<p>strategy=slug</p><!-- ast-append-ids: strategy=slug --><p>Slugged</p>";
+        let result = HtmlProcessor::new().process(input, &IdOptions::default()).unwrap();
+        assert!(result.contains(r#"<p data-ast-id="el-slugged">"#), "{}", result);
+    }
+
+    #[test]
+    fn test_html_void_and_foreign_elements() {
+        let mut processor = HtmlProcessor::new();
+        let options = IdOptions { strategy: IdStrategy::Path, ..IdOptions::default() };
+
+        // Void elements keep their syntax and never get an end tag; foreign
+        // elements are tagged like any other, self-closing ones staying so
+        let input = concat!(
+            r#"<p>a<br>b<img src=x/><BR/></p>"#,
+            r#"<svg viewBox="0 0 8 8"><path d="M0 0"/><foreignObject><i>x</i></foreignObject></svg>"#,
+            r#"<math><mi>x</mi><mspace width="1em"/></math>"#,
+        );
+        let result = processor.process(input, &options).unwrap();
+        assert_eq!(
+            result,
+            concat!(
+                r#"<p data-ast-id="el-p-0">a<br data-ast-id="el-br-0-1">b<img src=x/ data-ast-id="el-img-0-2">"#,
+                r#"<BR data-ast-id="el-br-0-3" /></p>"#,
+                r#"<svg viewBox="0 0 8 8" data-ast-id="el-svg-4"><path d="M0 0" data-ast-id="el-path-4-5" />"#,
+                r#"<foreignObject data-ast-id="el-foreignObject-4-6"><i data-ast-id="el-i-4-6-7">x</i></foreignObject></svg>"#,
+                r#"<math data-ast-id="el-math-8"><mi data-ast-id="el-mi-8-9">x</mi>"#,
+                r#"<mspace width="1em" data-ast-id="el-mspace-8-10" /></math>"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_html_inline_svg_follows_xml_rules() {
+        let mut processor = HtmlProcessor::new();
+
+        // SVG names keep their case, in IDs and filters alike
+        let options = IdOptions {
+            strategy: IdStrategy::Path,
+            include: vec!["linearGradient".to_string(), "clipPath".to_string()],
+            ..IdOptions::default()
+        };
+        let input = r#"<svg><defs><LINEARGRADIENT></LINEARGRADIENT><clippath></clippath></defs></svg>"#;
+        let result = processor.process(input, &options).unwrap();
+        assert_eq!(
+            result,
+            r#"<svg><defs><LINEARGRADIENT data-ast-id="el-linearGradient-0-1-2"></LINEARGRADIENT><clippath data-ast-id="el-clipPath-0-1-3"></clippath></defs></svg>"#
+        );
+
+        // An SVG `id` must be an XML name; HTML elements keep the prefix as is
+        let options = IdOptions {
+            strategy: IdStrategy::Path,
+            attr: "id".to_string(),
+            prefix: "1-".to_string(),
+            ..IdOptions::default()
+        };
+        let input = r#"<p><svg><title>t</title><foreignObject><b>x</b></foreignObject></svg></p>"#;
+        let result = processor.process(input, &options).unwrap();
+        assert_eq!(
+            result,
+            concat!(
+                r#"<p id="1-p-0"><svg id="_1-svg-0-1"><title id="_1-title-0-1-2">t</title>"#,
+                r#"<foreignObject id="_1-foreignObject-0-1-3"><b id="1-b-0-1-3-4">x</b></foreignObject></svg></p>"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_html_inline_mathml() {
+        let mut processor = HtmlProcessor::new();
+        let options = IdOptions {
+            strategy: IdStrategy::Path,
+            attr: "id".to_string(),
+            prefix: "1-".to_string(),
+            ..IdOptions::default()
+        };
+
+        // Token elements and annotations hold HTML but are MathML themselves
+        let input = concat!(
+            r#"<p><math><mi>x</mi><mo>&InvisibleTimes;</mo><mn>2</mn>"#,
+            r#"<annotation-xml encoding="text/html"><b>x2</b></annotation-xml></math></p>"#,
+        );
+        let result = processor.process(input, &options).unwrap();
+        assert_eq!(
+            result,
+            concat!(
+                r#"<p id="1-p-0"><math id="_1-math-0-1"><mi id="_1-mi-0-1-2">x</mi><mo id="_1-mo-0-1-3">&InvisibleTimes;</mo><mn id="_1-mn-0-1-4">2</mn>"#,
+                r#"<annotation-xml encoding="text/html" id="_1-annotation-xml-0-1-5"><b id="1-b-0-1-5-6">x2</b></annotation-xml></math></p>"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_html_raw_text_is_not_element_text() {
+        let mut processor = HtmlProcessor::new();
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Slug;
+
+        // Markup in raw text is not elements, and script or style content
+        // is no element's text
+        let input = r#"<p>Total<script>document.write("<b>")</script></p><style>p > b {}</style><noscript><img></noscript><p>=</p>"#;
+        let result = processor.process(input, &options).unwrap();
+        let hash = IdGenerator::new().generate_hash_id("unknown", &[], "el-");
+        assert_eq!(
+            result,
+            format!(
+                r#"<p data-ast-id="el-total">Total<script data-ast-id="{0}">document.write("<b>")</script></p><style data-ast-id="{0}-2">p > b {{}}</style><noscript data-ast-id="{0}-3"><img></noscript><p data-ast-id="{0}-4">=</p>"#,
+                hash
+            )
+        );
+    }
+
+    #[test]
+    fn test_html_change_log_with_slug_ids() {
+        use crate::id_generator::ChangeAction;
+
+        let mut generator = IdGenerator::new();
+        generator.enable_change_log();
+        let mut processor = HtmlProcessor::with_generator(generator);
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Slug;
+
+        let input = r#"<h1>Title</h1><p data-ast-id="x">Body</p>"#;
+        processor.process(input, &options).unwrap();
+        let changes: Vec<_> = processor
+            .into_generator()
+            .take_changes()
+            .into_iter()
+            .map(|change| (change.offset, change.action, change.id))
+            .collect();
+        // Slug IDs are generated once the document has been read
+        assert_eq!(
+            changes,
+            vec![
+                (14, ChangeAction::Skipped, "x".to_string()),
+                (0, ChangeAction::Added, "el-title".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(decode_entities("a &amp; b &lt;&#x41;&#66;&gt; &bogus; &"), "a & b <AB> &bogus; &");
+    }
+
+    #[test]
+    fn test_html_invalid_selector() {
+        let mut options = IdOptions::default();
+        options.selector = Some("div[".to_string());
+
+        let err = HtmlProcessor::new().process("<div></div>", &options).unwrap_err();
+        assert!(err.starts_with("Invalid selector 'div['"));
+    }
+
+    #[test]
+    fn test_html_set_ids() {
+        let input = r#"<ul><li class="a" data-ast-id="old">x</li><li>y</li><li data-ast-id="save">z</li></ul>"#;
+        let (output, ids) = set_ids(input, "li:not([data-ast-id=save])", "data-ast-id", "save", &mut IdGenerator::new()).unwrap();
+        assert_eq!(ids, vec!["save-2", "save-3"]);
+        assert_eq!(
+            output,
+            r#"<ul><li class="a" data-ast-id="save-2">x</li><li data-ast-id="save-3">y</li><li data-ast-id="save">z</li></ul>"#
+        );
+
+        let (again, _) = set_ids(&output, "li.a", "data-ast-id", "save-2", &mut IdGenerator::new()).unwrap();
+        assert_eq!(again, output);
+        assert!(set_ids(input, "li[", "data-ast-id", "x", &mut IdGenerator::new()).is_err());
+    }
+
+    #[test]
+    fn test_html_set_ids_matching() {
+        let input = r#"<ul><li class="a" data-ast-id="old">x</li><li>y</li><li data-ast-id="save">z</li></ul><p><li>w</li></p>"#;
+        let xpath = Selector::parse_xpath("//ul/li[@class='a']|//ul/LI").unwrap();
+        let (output, ids) = set_ids_matching(input, &xpath, "data-ast-id", "save", &mut IdGenerator::new()).unwrap();
+        assert_eq!(ids, vec!["save", "save-2", "save-3"]);
+        assert_eq!(
+            output,
+            r#"<ul><li class="a" data-ast-id="save">x</li><li data-ast-id="save-2">y</li><li data-ast-id="save-3">z</li></ul><p><li>w</li></p>"#
+        );
+
+        let (again, _) = set_ids_matching(&output, &xpath, "data-ast-id", "save", &mut IdGenerator::new()).unwrap();
+        assert_eq!(again, output);
+    }
+
+    #[test]
+    fn test_html_manifest_meta() {
+        let mut options = IdOptions::default();
+        options.manifest_meta = Some("/ids.json".to_string());
+        let input = "<!DOCTYPE html>\n<html><head><title>Shop</title></head><body><p>Hi</p></body></html>";
+        let output = HtmlProcessor::new().process(input, &options).unwrap();
+        let tagged = Selector::parse("[data-ast-id]").unwrap();
+        let ids: Vec<String> =
+            select_elements(&output, &tagged, "data-ast-id").unwrap().into_iter().filter_map(|element| element.id).collect();
+        let meta = format!(
+            r#"><meta name="ast-append-ids" content="manifest=/ids.json; ids={}" data-ast-ignore><title"#,
+            ids_hash(&ids)
+        );
+        assert!(output.contains(&meta), "{}", output);
+
+        // A second run leaves the page as it is; a changed page gets a new hash
+        assert_eq!(HtmlProcessor::new().process(&output, &options).unwrap(), output);
+        let changed = output.replace("</body>", "<p>New</p></body>");
+        let again = HtmlProcessor::new().process(&changed, &options).unwrap();
+        assert_eq!(again.matches("<meta").count(), 1);
+        assert!(!again.contains(&meta));
+
+        // Fragments have no head to put it in
+        assert!(!HtmlProcessor::new().process("<p>Hi</p>", &options).unwrap().contains("<meta"));
+    }
+
+    #[test]
+    fn test_html_remove_ids() {
+        let input = r#"<div data-ast-id="el-1"><span data-ast-id="hand">Hi</span></div>"#;
+        let ids = HashSet::from(["el-1".to_string()]);
+        let result = remove_ids(input, "data-ast-id", &ids, None).unwrap();
+        assert_eq!(result, r#"<div><span data-ast-id="hand">Hi</span></div>"#);
+    }
+
+    #[test]
+    fn test_html_select_elements() {
+        let input = r#"<ul data-ast-id="list"><li data-ast-id="a">Tom &amp; <b>Jerry</b></li><li>Spike<img src="x.png"></ul><p>Out</p>"#;
+        let summary = |query: &str| {
+            let selector = crate::select::parse_query(query).unwrap();
+            select_elements(input, &selector, "data-ast-id")
+                .unwrap()
+                .into_iter()
+                .map(|element| (element.tag, element.id, element.text))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            summary("ul > li"),
+            vec![
+                ("li".to_string(), Some("a".to_string()), "Tom & Jerry".to_string()),
+                ("li".to_string(), None, "Spike".to_string()),
+            ]
+        );
+        assert_eq!(summary("/ul").len(), 1);
+        assert_eq!(summary("//li//img").len(), 1);
+        assert_eq!(summary("ul p"), vec![]);
+    }
+
+    #[test]
+    fn test_html_element_texts() {
+        let input = r#"<ul data-ast-id="list">
+  <li data-ast-id="a">  Fish &amp; chips </li>
+  <li data-ast-id="b">Hello <b>world</b></li>
+  <li data-ast-id="c"></li>
+  <li><span data-ast-id="d">Tea</span></li>
+  <script data-ast-id="s">run()</script>
+</ul>"#;
+
+        let texts = element_texts(input, "data-ast-id").unwrap();
+        assert_eq!(
+            texts,
+            vec![("a".to_string(), "Fish & chips".to_string()), ("d".to_string(), "Tea".to_string())]
+        );
+
+        let translations = HashMap::from([
+            ("a".to_string(), "Poisson & frites".to_string()),
+            ("b".to_string(), "Bonjour".to_string()),
+            ("d".to_string(), "Tea".to_string()),
+            ("missing".to_string(), "x".to_string()),
+        ]);
+        let (output, replaced) = replace_texts(input, "data-ast-id", &translations).unwrap();
+        assert_eq!(replaced, vec!["a".to_string()]);
+        assert!(output.contains(r#"<li data-ast-id="a">Poisson &amp; frites</li>"#));
+        assert!(output.contains("Hello <b>world</b>"));
+    }
+
+    #[test]
+    fn test_html_visitor_with_own_handlers() {
+        let input = r#"<main><img src="a.png"><p>Hi</p></main>"#;
+        let options = IdOptions { strategy: IdStrategy::Slug, ..IdOptions::default() };
+        let mut visitor = HtmlVisitor::new(input, &options, IdGenerator::new()).unwrap();
+        let mut element_content_handlers = visitor.element_content_handlers();
+        element_content_handlers.push(element!("img", |el| {
+            el.set_attribute("loading", "lazy")?;
+            Ok(())
+        }));
+        let output = rewrite_str(
+            input,
+            RewriteStrSettings {
+                element_content_handlers,
+                document_content_handlers: visitor.document_content_handlers(),
+                ..RewriteStrSettings::default()
+            },
+        )
+        .unwrap();
+        let output = visitor.finish(output);
+
+        assert_eq!(
+            output,
+            r#"<main data-ast-id="el-hi"><img src="a.png" data-ast-id="el-46638cd1" loading="lazy"><p data-ast-id="el-hi-2">Hi</p></main>"#
+        );
+        assert!(visitor.into_generator().is_used("el-hi-2"));
+    }
+}
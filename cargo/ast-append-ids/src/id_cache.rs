@@ -0,0 +1,131 @@
+use crate::id_generator::Assignment;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+pub const ID_CACHE_VERSION: u32 = 1;
+
+/// IDs assigned in previous runs, keyed by file. Feeding these back into the
+/// generator lets an element keep its ID when only its position changed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IdCache {
+    pub version: u32,
+    pub files: BTreeMap<String, Vec<Assignment>>,
+}
+
+impl IdCache {
+    pub fn new() -> Self {
+        Self {
+            version: ID_CACHE_VERSION,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Loads a cache file, returning an empty cache when it does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read ID cache {}: {}", path.display(), e))?;
+        let cache: IdCache = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid ID cache {}: {}", path.display(), e))?;
+
+        if cache.version != ID_CACHE_VERSION {
+            return Err(format!(
+                "Unsupported ID cache version {} (expected {})",
+                cache.version, ID_CACHE_VERSION
+            ));
+        }
+
+        Ok(cache)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize ID cache: {}", e))?;
+        fs::write(path, json + "\n")
+            .map_err(|e| format!("Failed to write ID cache {}: {}", path.display(), e))
+    }
+
+    pub fn entries_for(&self, file: &str) -> Vec<Assignment> {
+        self.files.get(file).cloned().unwrap_or_default()
+    }
+
+    /// Adds assignments for `file`, keeping earlier entries for other IDs. Used
+    /// when existing IDs were left in place and only new ones were assigned.
+    pub fn merge(&mut self, file: String, assignments: Vec<Assignment>) {
+        let entries = self.files.entry(file).or_default();
+        entries.retain(|old| !assignments.iter().any(|new| new.id == old.id));
+        entries.extend(assignments);
+    }
+
+    /// Replaces the recorded assignments for `file`.
+    pub fn update(&mut self, file: String, assignments: Vec<Assignment>) {
+        if assignments.is_empty() {
+            self.files.remove(&file);
+        } else {
+            self.files.insert(file, assignments);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "html")]
+    #[test]
+    fn test_ids_survive_inserted_siblings() {
+        use crate::html::HtmlProcessor;
+        use crate::id_generator::IdGenerator;
+        use crate::{AstProcessor, IdOptions, IdStrategy};
+
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+        options.overwrite = true;
+
+        let before = r#"<ul><li class="a">A</li><li class="b">B</li></ul>"#;
+        let mut generator = IdGenerator::new();
+        generator.enable_assignment_log();
+        let mut processor = HtmlProcessor::with_generator(generator);
+        let first = processor.process(before, &options).unwrap();
+        let mut cache = IdCache::new();
+        cache.update("list.html".to_string(), processor.into_generator().take_assignments());
+
+        // A new sibling shifts the path of every following element
+        let after = first.replacen("<li class=\"a\"", "<li class=\"new\">N</li><li class=\"a\"", 1);
+        let mut generator = IdGenerator::new();
+        generator.add_reusable(cache.entries_for("list.html"));
+        let mut processor = HtmlProcessor::with_generator(generator);
+        let second = processor.process(&after, &options).unwrap();
+
+        for li in [r#"<li class="a" data-ast-id="el-li-0-1">"#, r#"<li class="b" data-ast-id="el-li-0-2">"#] {
+            assert!(first.contains(li));
+            assert!(second.contains(li));
+        }
+        assert!(second.contains(r#"<li class="new" data-ast-id="el-li-0-1-2">"#));
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ast-append-ids-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cache.json");
+
+        assert!(IdCache::load(&path).unwrap().files.is_empty());
+
+        let mut cache = IdCache::new();
+        cache.update(
+            "a.html".to_string(),
+            vec![Assignment { fingerprint: "fp".to_string(), id: "el-x".to_string(), tag: "div".to_string(), path: vec![0], alias: None }],
+        );
+        cache.save(&path).unwrap();
+
+        let loaded = IdCache::load(&path).unwrap();
+        assert_eq!(loaded.entries_for("a.html"), cache.entries_for("a.html"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,65 +1,232 @@
+use crate::strategy::IdStrategyImpl;
+use crate::warnings::Warning;
+use crate::IdScheme;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 pub struct IdGenerator {
-    used_ids: HashSet<String>,
+    used_ids: UsedIds,
+    /// Next suffix to try per ID already taken, so that many elements
+    /// sharing one do not try every lower suffix again
+    next_suffix: HashMap<String, usize>,
     node_counter: usize,
+    /// Next index per taxonomy context and tag
+    taxonomy_counters: HashMap<String, usize>,
+    reusable: HashMap<String, VecDeque<String>>,
+    assignments: Option<Vec<Assignment>>,
+    /// IDs assigned, whether they are kept in `assignments` or not
+    assigned: usize,
+    changes: Option<Vec<Change>>,
+    decisions: Option<Vec<FilterDecision>>,
+    /// Elements the processors walked, whether they were tagged or not
+    visited: usize,
+    warnings: Vec<Warning>,
+    /// What the checks behind the warnings saw of the document being processed
+    document: DocumentChecks,
+    /// Used instead of the strategy the options name
+    strategy: Option<Box<dyn IdStrategyImpl>>,
+}
+
+/// Per document state of the checks behind the warnings
+#[derive(Debug, Default)]
+struct DocumentChecks {
+    existing_ids: HashSet<String>,
+    /// Elements the selector matched
+    selected: usize,
+    /// Elements of those whose tag is in `include`
+    included: usize,
+    /// Tags warned about being both included and excluded
+    conflicts: HashSet<String>,
+}
+
+/// An ID handed out for an element, keyed by the element's content fingerprint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub fingerprint: String,
+    pub id: String,
+    #[serde(default)]
+    pub tag: String,
+    pub path: Vec<usize>,
+    /// Readable name suggested for the element, see `suggest::suggest_aliases`;
+    /// manifests have it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+/// What a processor did with an element's ID attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeAction {
+    Added,
+    Overwritten,
+    /// The element already had an ID and kept it
+    Skipped,
+}
+
+/// An element a processor looked at, located by byte offset into its input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub offset: usize,
+    pub tag: String,
+    pub action: ChangeAction,
+    pub id: String,
+}
+
+/// Why an element got no ID. The checks run in this order in every
+/// processor: `selector` and `text_matches` pick the elements to look at,
+/// then `include` and `exclude` narrow them by tag name, `include_class` by
+/// class, `range` by position, and last an ID already there is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    NotSelected,
+    TextMismatch,
+    NotIncluded,
+    Excluded,
+    ClassMismatch,
+    OutOfRange,
+    /// The element has an ID and `overwrite` is off
+    HasId,
+    /// The element has an ID without `origin_attr`
+    HandWritten,
+}
+
+impl SkipReason {
+    /// The option that left the element alone
+    pub fn option(&self) -> &'static str {
+        match self {
+            SkipReason::NotSelected => "selector",
+            SkipReason::TextMismatch => "text_matches",
+            SkipReason::NotIncluded => "include",
+            SkipReason::Excluded => "exclude",
+            SkipReason::ClassMismatch => "include_class",
+            SkipReason::OutOfRange => "range",
+            SkipReason::HasId => "overwrite",
+            SkipReason::HandWritten => "origin_attr",
+        }
+    }
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SkipReason::NotSelected => "not matched by the selector",
+            SkipReason::TextMismatch => "text does not match",
+            SkipReason::NotIncluded => "tag not included",
+            SkipReason::Excluded => "tag excluded",
+            SkipReason::ClassMismatch => "no included class",
+            SkipReason::OutOfRange => "outside the range",
+            SkipReason::HasId => "has an ID already",
+            SkipReason::HandWritten => "ID written by hand",
+        })
+    }
+}
+
+/// Whether the filters let an element through, located by byte offset into
+/// the processor's input. `skipped` is `None` for an element that got an ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterDecision {
+    pub offset: usize,
+    pub tag: String,
+    pub skipped: Option<SkipReason>,
 }
 
 impl IdGenerator {
     pub fn new() -> Self {
         Self {
-            used_ids: HashSet::new(),
+            used_ids: UsedIds::default(),
+            next_suffix: HashMap::new(),
             node_counter: 0,
+            taxonomy_counters: HashMap::new(),
+            reusable: HashMap::new(),
+            assignments: None,
+            assigned: 0,
+            changes: None,
+            decisions: None,
+            visited: 0,
+            warnings: Vec::new(),
+            document: DocumentChecks::default(),
+            strategy: None,
         }
     }
 
+    /// Makes IDs with `strategy` rather than the one the options name, in
+    /// every processor and for every element, inline directives included.
+    pub fn with_strategy(mut self, strategy: impl IdStrategyImpl + 'static) -> Self {
+        self.strategy = Some(Box::new(strategy));
+        self
+    }
+
+    /// Remembers only the `capacity` IDs used most recently, for streaming
+    /// documents or runs too big to keep every ID of. Generated IDs are then
+    /// unique among those; an ID that has not been used for `capacity` others
+    /// is forgotten and can be handed out again, so a document whose
+    /// duplicates lie further apart than that gets colliding IDs. Without a
+    /// window every ID is kept and IDs never collide.
+    pub fn with_id_window(mut self, capacity: usize) -> Self {
+        self.used_ids.capacity = Some(capacity.max(1));
+        self.forget_old_ids();
+        self
+    }
+
+    /// The strategy set with `with_strategy`, taken out for a call that needs
+    /// the generator; `restore_strategy` puts it back.
+    pub(crate) fn take_strategy(&mut self) -> Option<Box<dyn IdStrategyImpl>> {
+        self.strategy.take()
+    }
+
+    pub(crate) fn restore_strategy(&mut self, strategy: Box<dyn IdStrategyImpl>) {
+        self.strategy = Some(strategy);
+    }
+
     pub fn generate_hash_id(&mut self, node_type: &str, path: &[usize], prefix: &str) -> String {
-        let path_string = path
-            .iter()
-            .map(|i| i.to_string())
-            .collect::<Vec<_>>()
-            .join(":");
-
-        let content = format!(
-            "{{\"type\":\"{}\",\"path\":\"{}\"}}",
-            node_type, path_string
-        );
-
-        let mut hasher = Sha256::new();
-        hasher.update(content.as_bytes());
-        let result = hasher.finalize();
-        let hash = format!("{:x}", result);
-        let short_hash = &hash[..8];
-
-        let id = format!("{}{}", prefix, short_hash);
+        self.generate_scheme_hash_id(node_type, path, prefix, IdScheme::V1, "")
+    }
+
+    /// Like `generate_hash_id`, computing the hash with `scheme`
+    pub fn generate_scheme_hash_id(
+        &mut self,
+        node_type: &str,
+        path: &[usize],
+        prefix: &str,
+        scheme: IdScheme,
+        salt: &str,
+    ) -> String {
+        let id = format!("{}{}", prefix, scheme_hash(scheme, salt, node_type, path));
         self.ensure_unique(id)
     }
 
+    /// Like `generate_scheme_hash_id` with the hash cut to `length`
+    /// characters. A hash that is taken already, usually by another element
+    /// with the same start, is lengthened a character at a time up to the
+    /// full hash, which `ensure_unique` then counts up from.
+    pub fn generate_short_hash_id(
+        &mut self,
+        node_type: &str,
+        path: &[usize],
+        prefix: &str,
+        scheme: IdScheme,
+        salt: &str,
+        length: usize,
+    ) -> String {
+        let hash = scheme_hash(scheme, salt, node_type, path);
+        for end in length.min(hash.len())..hash.len() {
+            let id = format!("{}{}", prefix, &hash[..end]);
+            if self.take_id(id.clone()) {
+                return id;
+            }
+        }
+        self.ensure_unique(format!("{}{}", prefix, hash))
+    }
+
     pub fn generate_slug_id(&mut self, text: &str, prefix: &str) -> String {
-        if text.is_empty() {
+        // Text such as `=` or `…` has no slug either
+        let slug = slugify(text);
+        if slug.is_empty() {
             return self.generate_hash_id("unknown", &[], prefix);
         }
 
-        let slug = text
-            .to_lowercase()
-            .trim()
-            .chars()
-            .map(|c| {
-                if c.is_alphanumeric() || c == ' ' || c == '-' {
-                    c
-                } else {
-                    ' '
-                }
-            })
-            .collect::<String>()
-            .split_whitespace()
-            .collect::<Vec<_>>()
-            .join("-")
-            .chars()
-            .take(50)
-            .collect::<String>();
-
         let id = format!("{}{}", prefix, slug);
         self.ensure_unique(id)
     }
@@ -81,23 +248,231 @@ impl IdGenerator {
         self.ensure_unique(id)
     }
 
+    /// Builds `area.section.component.tag-index` from the labels of the
+    /// element's enclosing landmarks, numbering elements per context and tag.
+    pub fn generate_taxonomy_id(&mut self, node_type: &str, context: &[String], prefix: &str) -> String {
+        let scope = context.join(".");
+        let counter = self
+            .taxonomy_counters
+            .entry(format!("{}\0{}", scope, node_type))
+            .or_insert(0);
+        let element = format!("{}-{}", slugify(node_type), counter);
+        *counter += 1;
+
+        let id = if scope.is_empty() {
+            format!("{}{}", prefix, element)
+        } else {
+            format!("{}{}.{}", prefix, scope, element)
+        };
+        self.ensure_unique(id)
+    }
+
+    /// Marks `id` as taken so generated IDs never collide with it.
+    pub fn reserve(&mut self, id: impl Into<String>) {
+        self.take_id(id.into());
+    }
+
+    pub fn is_used(&self, id: &str) -> bool {
+        self.used_ids.contains(id)
+    }
+
+    /// `id`, or the first of `id-2`, `id-3`, ... that is not used yet. A
+    /// suffix found taken stays taken and the next call for `id` goes on from
+    /// the one after it, until `with_id_window` forgets `id` along with its
+    /// counter; the count then starts over at `id-2`.
     pub fn ensure_unique(&mut self, id: String) -> String {
-        if !self.used_ids.contains(&id) {
-            self.used_ids.insert(id.clone());
+        if self.take_id(id.clone()) {
             return id;
         }
 
-        let mut counter = 2;
+        let counter = self.next_suffix.entry(id.clone()).or_insert(2);
         loop {
             let unique_id = format!("{}-{}", id, counter);
-            if !self.used_ids.contains(&unique_id) {
-                self.used_ids.insert(unique_id.clone());
+            *counter += 1;
+            if self.used_ids.insert(unique_id.clone()) {
+                self.forget_old_ids();
                 return unique_id;
             }
-            counter += 1;
         }
     }
 
+    /// Marks `id` as used, returning whether it was free
+    fn take_id(&mut self, id: String) -> bool {
+        let free = self.used_ids.insert(id);
+        self.forget_old_ids();
+        free
+    }
+
+    /// Drops the IDs that fell out of the window, with their suffix counters
+    fn forget_old_ids(&mut self) {
+        while let Some(id) = self.used_ids.evict() {
+            self.next_suffix.remove(&id);
+        }
+    }
+
+    /// Offers previously assigned IDs for reuse. When an element with a matching
+    /// fingerprint is seen, it gets the recorded ID instead of a fresh one, even if
+    /// its path changed. Elements sharing a fingerprint consume IDs in order.
+    ///
+    /// Reusable IDs are reserved immediately so fresh IDs never take them first;
+    /// IDs that are already in use are skipped.
+    pub fn add_reusable(&mut self, assignments: impl IntoIterator<Item = Assignment>) {
+        for assignment in assignments {
+            if !self.take_id(assignment.id.clone()) {
+                continue;
+            }
+            self.reusable
+                .entry(assignment.fingerprint)
+                .or_default()
+                .push_back(assignment.id);
+        }
+    }
+
+    /// Returns the next reusable ID for `fingerprint`. The ID is already
+    /// reserved, so it must not be passed through `ensure_unique` again.
+    pub fn take_reusable(&mut self, fingerprint: &str) -> Option<String> {
+        let queue = self.reusable.get_mut(fingerprint)?;
+        let id = queue.pop_front();
+        if queue.is_empty() {
+            self.reusable.remove(fingerprint);
+        }
+        id
+    }
+
+    /// Drops reusable IDs that were not claimed. They stay reserved.
+    pub fn clear_reusable(&mut self) {
+        self.reusable.clear();
+    }
+
+    /// Starts keeping an `Assignment` for every ID generated, for the ID cache
+    /// and manifests. Off by default so that long-lived generators, as in
+    /// watch mode or the wasm bindings, do not grow with every document.
+    pub fn enable_assignment_log(&mut self) {
+        self.assignments.get_or_insert_with(Vec::new);
+    }
+
+    pub fn record_assignment(&mut self, assignment: Assignment) {
+        self.assigned += 1;
+        if let Some(assignments) = &mut self.assignments {
+            assignments.push(assignment);
+        }
+    }
+
+    /// Number of IDs assigned so far
+    pub fn assignment_count(&self) -> usize {
+        self.assigned
+    }
+
+    /// Drains the assignments kept since the last call; empty unless
+    /// `enable_assignment_log` was called.
+    pub fn take_assignments(&mut self) -> Vec<Assignment> {
+        self.assignments.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Starts keeping a `Change` for every element the processors add, replace
+    /// or keep an ID on. Off by default since locating elements has a cost.
+    pub fn enable_change_log(&mut self) {
+        self.changes.get_or_insert_with(Vec::new);
+    }
+
+    pub fn logs_changes(&self) -> bool {
+        self.changes.is_some()
+    }
+
+    pub fn record_change(&mut self, change: Change) {
+        if let Some(changes) = &mut self.changes {
+            changes.push(change);
+        }
+    }
+
+    /// Drains the changes recorded since the last call.
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        self.changes.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Starts keeping a `FilterDecision` for every element the filters see,
+    /// for explaining why elements were or were not tagged. Off by default,
+    /// like the change log.
+    pub fn enable_filter_log(&mut self) {
+        self.decisions.get_or_insert_with(Vec::new);
+    }
+
+    pub fn logs_filters(&self) -> bool {
+        self.decisions.is_some()
+    }
+
+    /// Keeps `decision`, replacing one made earlier for the same element
+    pub fn record_decision(&mut self, decision: FilterDecision) {
+        if let Some(decisions) = &mut self.decisions {
+            if let Some(last) = decisions.last_mut().filter(|last| last.offset == decision.offset) {
+                *last = decision;
+            } else {
+                decisions.push(decision);
+            }
+        }
+    }
+
+    /// Drains the decisions recorded since the last call.
+    pub fn take_decisions(&mut self) -> Vec<FilterDecision> {
+        self.decisions.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    pub fn count_visit(&mut self) {
+        self.visited += 1;
+    }
+
+    /// Number of elements walked so far, across all documents
+    pub fn visited_count(&self) -> usize {
+        self.visited
+    }
+
+    /// Keeps a warning about the document being processed, for the caller
+    /// to show
+    pub fn add_warning(&mut self, warning: Warning) {
+        self.warnings.push(warning);
+    }
+
+    /// Drains the warnings added since the last call.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Forgets what the checks saw of the previous document
+    pub fn start_document(&mut self) {
+        self.document = DocumentChecks::default();
+    }
+
+    /// Notes an ID an element of the document has already, returning whether
+    /// another element had it too
+    pub fn note_existing_id(&mut self, id: &str) -> bool {
+        !self.document.existing_ids.insert(id.to_string())
+    }
+
+    pub fn note_selected(&mut self) {
+        self.document.selected += 1;
+    }
+
+    /// Elements of the document the selector matched so far
+    pub fn selected_count(&self) -> usize {
+        self.document.selected
+    }
+
+    pub fn note_included(&mut self) {
+        self.document.included += 1;
+    }
+
+    /// Elements of the document the selector matched whose tag is in
+    /// `include`, so far
+    pub fn included_count(&self) -> usize {
+        self.document.included
+    }
+
+    /// Notes a tag found in both `include` and `exclude`, returning whether
+    /// it is the first time in the document
+    pub fn note_conflict(&mut self, tag: &str) -> bool {
+        self.document.conflicts.insert(tag.to_string())
+    }
+
     pub fn extract_text_content(node: &impl TextExtractable) -> String {
         node.extract_text()
     }
@@ -106,9 +481,143 @@ impl IdGenerator {
         self.node_counter += 1;
     }
 
+    /// Moves the counter past `count` elements at once
+    pub fn advance_counter(&mut self, count: usize) {
+        self.node_counter += count;
+    }
+
+    pub fn set_counter(&mut self, value: usize) {
+        self.node_counter = value;
+    }
+
     pub fn get_counter(&self) -> usize {
         self.node_counter
     }
+
+    /// Resets the node counter while keeping used IDs, so a generator shared
+    /// across files stays globally unique but path positions restart per file.
+    pub fn reset_counter(&mut self) {
+        self.node_counter = 0;
+        self.taxonomy_counters.clear();
+    }
+}
+
+impl Default for IdGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The IDs taken, all of them or with a capacity the most recently used
+#[derive(Default)]
+struct UsedIds {
+    /// ID to the tick it was last used at
+    ids: HashMap<String, u64>,
+    /// With a capacity, IDs in the order they were used, oldest first. An
+    /// entry whose tick is not the ID's latest one is stale.
+    order: VecDeque<(u64, String)>,
+    tick: u64,
+    capacity: Option<usize>,
+}
+
+impl UsedIds {
+    fn contains(&self, id: &str) -> bool {
+        self.ids.contains_key(id)
+    }
+
+    /// Marks `id` as used now, returning whether it was new
+    fn insert(&mut self, id: String) -> bool {
+        let Some(capacity) = self.capacity else {
+            return self.ids.insert(id, 0).is_none();
+        };
+        self.tick += 1;
+        let new = self.ids.insert(id.clone(), self.tick).is_none();
+        self.order.push_back((self.tick, id));
+        // IDs used over and over leave stale entries behind
+        if self.order.len() > capacity * 2 {
+            let ids = &self.ids;
+            self.order.retain(|(tick, id)| ids.get(id) == Some(tick));
+        }
+        new
+    }
+
+    /// Removes and returns the least recently used ID once there are more
+    /// than the capacity
+    fn evict(&mut self) -> Option<String> {
+        let capacity = self.capacity?;
+        if self.ids.len() <= capacity {
+            return None;
+        }
+        while let Some((tick, id)) = self.order.pop_front() {
+            if self.ids.get(&id) == Some(&tick) {
+                self.ids.remove(&id);
+                return Some(id);
+            }
+        }
+        None
+    }
+}
+
+/// The hash part of a hash ID: the one an element with this tag and path gets
+/// under `scheme`, before prefix and `-N` counter.
+pub fn scheme_hash(scheme: IdScheme, salt: &str, node_type: &str, path: &[usize]) -> String {
+    let path_string = path
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(":");
+
+    let content = format!(
+        "{{\"type\":\"{}\",\"path\":\"{}\"}}",
+        node_type, path_string
+    );
+
+    let mut hasher = Sha256::new();
+    match scheme {
+        IdScheme::V1 => {
+            hasher.update(content.as_bytes());
+            let hash = format!("{:x}", hasher.finalize());
+            hash[..8].to_string()
+        }
+        IdScheme::V2 => {
+            hasher.update(salt.as_bytes());
+            hasher.update(b"\0");
+            hasher.update(content.as_bytes());
+            let digest = hasher.finalize();
+            let mut value = u64::from_be_bytes(digest[..8].try_into().expect("SHA-256 has 32 bytes"));
+            // 62^11 > 2^64, so 11 digits hold any value
+            let mut digits = [b'0'; 11];
+            for digit in digits.iter_mut().rev() {
+                *digit = BASE62[(value % 62) as usize];
+                value /= 62;
+            }
+            String::from_utf8(digits.to_vec()).expect("base62 digits are ASCII")
+        }
+    }
+}
+
+const BASE62: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Lowercases `text`, replaces anything but alphanumerics and dashes with `-`
+/// and truncates the result to 50 characters.
+pub fn slugify(text: &str) -> String {
+    text.to_lowercase()
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' {
+                c
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .chars()
+        .take(50)
+        .collect()
 }
 
 pub trait TextExtractable {
@@ -118,6 +627,7 @@ pub trait TextExtractable {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     #[test]
     fn test_hash_id_generation() {
@@ -129,6 +639,43 @@ mod tests {
         assert!(id2.ends_with("-2"));
     }
 
+    #[test]
+    fn test_scheme_hash() {
+        let v1 = scheme_hash(IdScheme::V1, "", "div", &[0, 1, 2]);
+        assert_eq!(IdGenerator::new().generate_hash_id("div", &[0, 1, 2], "el-"), format!("el-{}", v1));
+        assert!(v1.len() == 8 && v1.chars().all(|c| c.is_ascii_hexdigit()));
+
+        let v2 = scheme_hash(IdScheme::V2, "pepper", "div", &[0, 1, 2]);
+        assert!(v2.len() == 11 && v2.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(v2, scheme_hash(IdScheme::V2, "salt", "div", &[0, 1, 2]));
+        assert_eq!(v2, scheme_hash(IdScheme::V2, "pepper", "div", &[0, 1, 2]));
+    }
+
+    #[test]
+    fn test_short_hash_id() {
+        let full = |path: &[usize]| scheme_hash(IdScheme::V2, "pepper", "li", path);
+        let short = |gen: &mut IdGenerator, path: &[usize]| {
+            gen.generate_short_hash_id("li", path, "el-", IdScheme::V2, "pepper", 4)
+        };
+
+        // Two elements whose hashes start alike
+        let mut starts = HashMap::new();
+        let (first, second) = (0..)
+            .find_map(|i| starts.insert(full(&[i])[..4].to_string(), i).map(|j| (j, i)))
+            .unwrap();
+        let mut gen = IdGenerator::new();
+        assert_eq!(short(&mut gen, &[first]), format!("el-{}", &full(&[first])[..4]));
+        let common = full(&[first]).chars().zip(full(&[second]).chars()).take_while(|(a, b)| a == b).count();
+        assert_eq!(short(&mut gen, &[second]), format!("el-{}", &full(&[second])[..common.max(4) + 1]));
+
+        // The same element again, e.g. in another file: longer up to the
+        // full hash, then counted
+        let ids: Vec<_> = (0..9).map(|_| short(&mut gen, &[0])).collect();
+        let lengths: Vec<_> = ids.iter().map(|id| id.len() - "el-".len()).collect();
+        assert_eq!(lengths, [4, 5, 6, 7, 8, 9, 10, 11, 13]);
+        assert_eq!(ids[8], format!("el-{}-2", full(&[0])));
+    }
+
     #[test]
     fn test_slug_id_generation() {
         let mut gen = IdGenerator::new();
@@ -137,6 +684,10 @@ mod tests {
 
         let id2 = gen.generate_slug_id("Hello World! 123", "el-");
         assert_eq!(id2, "el-hello-world-123-2");
+
+        // Nothing to slug: a hash instead of a bare prefix
+        let id3 = gen.generate_slug_id("=", "el-");
+        assert_eq!(id3, IdGenerator::new().generate_hash_id("unknown", &[], "el-"));
     }
 
     #[test]
@@ -149,6 +700,19 @@ mod tests {
         assert_eq!(id2, "el-span");
     }
 
+    #[test]
+    fn test_taxonomy_id_generation() {
+        let mut generator = IdGenerator::new();
+        let context = vec!["main".to_string(), "checkout".to_string()];
+        assert_eq!(generator.generate_taxonomy_id("button", &context, "el-"), "el-main.checkout.button-0");
+        assert_eq!(generator.generate_taxonomy_id("button", &context, "el-"), "el-main.checkout.button-1");
+        assert_eq!(generator.generate_taxonomy_id("input", &context, "el-"), "el-main.checkout.input-0");
+        assert_eq!(generator.generate_taxonomy_id("p", &[], "el-"), "el-p-0");
+
+        generator.reset_counter();
+        assert_eq!(generator.generate_taxonomy_id("button", &context, "el-"), "el-main.checkout.button-0-2");
+    }
+
     #[test]
     fn test_uniqueness() {
         let mut gen = IdGenerator::new();
@@ -159,5 +723,99 @@ mod tests {
         assert_eq!(id1, "test-id");
         assert_eq!(id2, "test-id-2");
         assert_eq!(id3, "test-id-3");
+
+        // Suffixes taken some other way are skipped
+        gen.reserve("test-id-5");
+        let ids: Vec<_> = (0..3).map(|_| gen.ensure_unique("test-id".to_string())).collect();
+        assert_eq!(ids, ["test-id-4", "test-id-6", "test-id-7"]);
+        assert_eq!(gen.ensure_unique("test-id-2".to_string()), "test-id-2-2");
+    }
+
+    #[test]
+    fn test_id_window() {
+        let mut gen = IdGenerator::new().with_id_window(3);
+        let ids: Vec<_> = ["a", "a", "b", "c", "d"].map(|id| gen.ensure_unique(id.to_string())).to_vec();
+        assert_eq!(ids, ["a", "a-2", "b", "c", "d"]);
+        // Only the last three are kept, `a` and `a-2` are free again
+        assert_eq!(gen.used_ids.ids.len(), 3);
+        assert!(!gen.is_used("a") && !gen.is_used("a-2") && gen.is_used("b"));
+        assert_eq!(gen.ensure_unique("a".to_string()), "a");
+
+        // Using an ID keeps it, and its suffix counter, from being forgotten
+        let mut gen = IdGenerator::new().with_id_window(2);
+        let ids: Vec<_> = (0..1000).map(|_| gen.ensure_unique("x".to_string())).collect();
+        assert_eq!(ids[999], "x-1000");
+        assert!(gen.is_used("x") && gen.used_ids.ids.len() == 2);
+        assert!(gen.used_ids.order.len() <= 4 && gen.next_suffix.len() == 1);
+
+        // Forgotten IDs take their counters with them
+        gen.reserve("y");
+        gen.reserve("z");
+        assert!(gen.next_suffix.is_empty());
+        assert_eq!(gen.ensure_unique("x".to_string()), "x");
+    }
+
+    #[test]
+    fn test_uniqueness_many_duplicates() {
+        // Each call goes on from the last suffix rather than from 2
+        let mut gen = IdGenerator::new();
+        let ids: Vec<_> = (0..100_000).map(|_| gen.generate_slug_id("Add to cart", "el-")).collect();
+        assert_eq!(ids[0], "el-add-to-cart");
+        assert_eq!(ids[99_999], "el-add-to-cart-100000");
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+    }
+
+    #[test]
+    fn test_reusable_ids() {
+        let mut gen = IdGenerator::new();
+        gen.reserve("el-taken");
+        gen.add_reusable(["el-taken", "el-a", "el-b"].iter().enumerate().map(|(i, id)| {
+            Assignment {
+                fingerprint: "fp".to_string(),
+                id: id.to_string(),
+                tag: "li".to_string(),
+                path: vec![i],
+                alias: None,
+            }
+        }));
+
+        assert_eq!(gen.take_reusable("fp").as_deref(), Some("el-a"));
+        assert_eq!(gen.take_reusable("fp").as_deref(), Some("el-b"));
+        assert_eq!(gen.take_reusable("fp"), None);
+        assert_eq!(gen.take_reusable("other"), None);
+        assert_eq!(gen.generate_path_id("x", &[], "el-"), "el-x");
+        assert!(gen.is_used("el-a"));
+    }
+
+    #[test]
+    fn test_assignment_log() {
+        let assignment = |id: &str| Assignment {
+            fingerprint: "fp".to_string(),
+            id: id.to_string(),
+            tag: "li".to_string(),
+            path: Vec::new(),
+            alias: None,
+        };
+        // Counted, but only kept once asked for
+        let mut gen = IdGenerator::new();
+        gen.record_assignment(assignment("el-a"));
+        assert_eq!(gen.assignment_count(), 1);
+        assert!(gen.take_assignments().is_empty());
+
+        gen.enable_assignment_log();
+        gen.record_assignment(assignment("el-b"));
+        assert_eq!(gen.assignment_count(), 2);
+        assert_eq!(gen.take_assignments(), [assignment("el-b")]);
+        assert!(gen.take_assignments().is_empty());
+    }
+
+    #[test]
+    fn test_reserved_ids() {
+        let mut gen = IdGenerator::new();
+        gen.reserve("el-div-0");
+        assert!(gen.is_used("el-div-0"));
+
+        let id = gen.generate_path_id("div", &[0], "el-");
+        assert_eq!(id, "el-div-0-2");
     }
 }
\ No newline at end of file
@@ -1,11 +1,28 @@
-use crate::ast_common::{self, AstNode};
+use crate::ast_common::{self, AstNode, Candidate, ComponentScopes, IdLocation, OptionOverrides, IGNORE_ATTR, JSX_IGNORE_COMMENT};
+#[cfg(feature = "html")]
+use crate::html::HtmlProcessor;
+use crate::codemod::{self, TextQuery};
+use crate::coverage::PolicyElement;
+use crate::extra_attrs;
 use crate::id_generator::IdGenerator;
-use crate::{AstProcessor, IdOptions};
-use swc_core::common::{FileName, SourceMap, DUMMY_SP};
+use crate::limits;
+use crate::resolved::ResolvedOptions;
+use crate::select::SelectedElement;
+use crate::selector::{Selector, SelectorElement};
+use crate::taxonomy::TaxonomyTracker;
+use crate::detect::Format;
+use crate::{AstProcessor, IdOptions, ProcessOutput};
+use swc_core::common::sync::Lrc;
+use swc_core::common::comments::{CommentKind, Comments, SingleThreadedComments};
+use swc_core::common::{BytePos, EqIgnoreSpan, FileName, SourceFile, SourceMap, SourceMapper, Span, Spanned, DUMMY_SP};
 use swc_core::ecma::ast::*;
-use swc_core::ecma::parser::{lexer::Lexer, Parser, StringInput, Syntax, TsConfig};
-use swc_core::ecma::visit::{VisitMut, VisitMutWith};
-use std::sync::Arc;
+use swc_core::ecma::parser::{lexer::Lexer, EsConfig, Parser, StringInput, Syntax, TsConfig};
+use swc_core::ecma::visit::{Visit, VisitMut, VisitMutWith, VisitWith};
+use regex::Regex;
+use std::collections::HashSet;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::OnceLock;
 
 pub struct JsxProcessor {
     generator: IdGenerator,
@@ -18,8 +35,18 @@ impl JsxProcessor {
         }
     }
 
+    /// Creates a processor that draws IDs from an existing generator, e.g. one
+    /// pre-seeded with reserved IDs or shared across several files.
+    pub fn with_generator(generator: IdGenerator) -> Self {
+        Self { generator }
+    }
+
+    pub fn into_generator(self) -> IdGenerator {
+        self.generator
+    }
+
     fn is_host_element(name: &str) -> bool {
-        name.chars().next().map_or(false, |c| c.is_lowercase())
+        name.chars().next().is_some_and(|c| c.is_lowercase())
     }
 
     fn extract_jsx_element_name(name: &JSXElementName) -> String {
@@ -36,6 +63,30 @@ impl JsxProcessor {
         member.sym.to_string()
     }
 
+    /// Attribute names with their string-literal values. Expression values are
+    /// recorded by name only since their runtime value is unknown.
+    fn static_attributes(attrs: &[JSXAttrOrSpread]) -> Vec<(String, String)> {
+        attrs
+            .iter()
+            .filter_map(|attr| match attr {
+                JSXAttrOrSpread::JSXAttr(attr) => {
+                    let name = match &attr.name {
+                        JSXAttrName::Ident(ident) => ident.sym.to_string(),
+                        JSXAttrName::JSXNamespacedName(ns) => {
+                            format!("{}:{}", ns.ns.sym, ns.name.sym)
+                        }
+                    };
+                    let value = match &attr.value {
+                        Some(JSXAttrValue::Lit(Lit::Str(s))) => s.value.to_string(),
+                        _ => String::new(),
+                    };
+                    Some((name, value))
+                }
+                JSXAttrOrSpread::SpreadElement(_) => None,
+            })
+            .collect()
+    }
+
     #[allow(dead_code)]
     fn extract_text_from_jsx_children(children: &[JSXElementChild]) -> String {
         let mut text_parts = Vec::new();
@@ -69,18 +120,416 @@ impl JsxProcessor {
     }
 }
 
-struct JsxVisitor<'a> {
+/// Adds IDs to the JSX of a module, for pipelines that parse and print with
+/// swc themselves and run passes of their own around this one. Set it up
+/// with `for_module`, let it mark components, visit the module with it and
+/// `finish`; `JsxProcessor` is this visitor between parsing and printing.
+pub struct JsxVisitor<'a> {
     options: &'a IdOptions,
     generator: &'a mut IdGenerator,
     path_stack: Vec<usize>,
+    /// Open scopes of `IdOptions::component_scope`
+    components: ComponentScopes,
+    /// End positions of `// ast-append-ids-ignore-next` comments, in source order
+    ignore_markers: Vec<BytePos>,
+    next_marker: usize,
+    /// Option directive comments by end position, in source order
+    directives: Vec<(BytePos, OptionOverrides)>,
+    next_directive: usize,
+    /// Options in effect inside directive scopes, innermost last
+    scoped_options: Vec<IdOptions>,
+    /// First error raised while processing embedded markup
+    error: Option<String>,
+    /// Number of `.map()` callbacks currently being visited
+    map_depth: usize,
+    /// `key` expressions of the enclosing mapped elements, innermost last
+    dynamic_keys: Vec<Expr>,
+    /// Whether an ID was written through `IdOptions::id_helper`
+    helper_used: bool,
+    /// Position of the first byte of the source, for change log offsets
+    source_start: BytePos,
+    /// Parsed `IdOptions::skip_conditions`
+    skip_conditions: Vec<Expr>,
+    /// Enclosing taxonomy levels, when the taxonomy strategy is in use
+    taxonomy: Option<TaxonomyTracker>,
+    /// Taxonomy context of the element being processed
+    context: Vec<String>,
+    /// Filters and selector compiled from `options`
+    resolved: Rc<ResolvedOptions>,
+    /// Enclosing elements, when a selector is in use
+    selector_path: Vec<SelectorElement>,
 }
 
 impl<'a> JsxVisitor<'a> {
-    fn new(options: &'a IdOptions, generator: &'a mut IdGenerator) -> Self {
+    fn new(options: &'a IdOptions, generator: &'a mut IdGenerator, resolved: Rc<ResolvedOptions>) -> Self {
         Self {
             options,
             generator,
             path_stack: Vec::new(),
+            components: ComponentScopes::default(),
+            ignore_markers: Vec::new(),
+            next_marker: 0,
+            directives: Vec::new(),
+            next_directive: 0,
+            scoped_options: Vec::new(),
+            error: None,
+            map_depth: 0,
+            dynamic_keys: Vec::new(),
+            helper_used: false,
+            source_start: BytePos(0),
+            skip_conditions: Vec::new(),
+            taxonomy: None,
+            context: Vec::new(),
+            resolved,
+            selector_path: Vec::new(),
+        }
+    }
+
+    /// A visitor drawing IDs from `generator` for a module parsed from
+    /// `content`, its comments collected in `comments`. `source_start` is
+    /// where the module's file starts in its `SourceMap`, for the offsets of
+    /// the change log and `range`.
+    pub fn for_module(
+        options: &'a IdOptions,
+        generator: &'a mut IdGenerator,
+        content: &str,
+        comments: &SingleThreadedComments,
+        source_start: BytePos,
+    ) -> Result<Self, String> {
+        let resolved = Rc::new(ResolvedOptions::new(options, Format::Jsx)?);
+        Self::for_module_with_resolved(options, resolved, generator, content, comments, source_start)
+    }
+
+    /// `for_module` with `options` compiled up front, e.g. once for many
+    /// modules
+    pub fn for_module_with_resolved(
+        options: &'a IdOptions,
+        resolved: Rc<ResolvedOptions>,
+        generator: &'a mut IdGenerator,
+        content: &str,
+        comments: &SingleThreadedComments,
+        source_start: BytePos,
+    ) -> Result<Self, String> {
+        Ok(Self::new(options, generator, resolved)
+            .with_source_start(source_start)
+            .with_ignore_markers(comments)
+            .with_directives(comments)?
+            .with_skip_conditions(&options.skip_conditions)?
+            .with_taxonomy(content))
+    }
+
+    /// Writes the name of each component to `component_attr` and
+    /// `component_scope` on its root element. Done before the visit, so the
+    /// names are part of the element fingerprints, as they will be on later
+    /// runs.
+    pub fn mark_components(&self, module: &mut Module) {
+        let options = self.options;
+        if let Some(attr) = &options.component_attr {
+            module.visit_mut_with(&mut ComponentMarker::new(attr));
+        }
+        if let Some(attr) = options.component_scope.as_ref().filter(|&attr| options.component_attr.as_ref() != Some(attr)) {
+            module.visit_mut_with(&mut ComponentMarker::new(attr));
+        }
+    }
+
+    /// Ends the visit of `module`: returns the first error met in it, and
+    /// imports `id_helper` when IDs were written through it
+    pub fn finish(self, module: &mut Module) -> Result<(), String> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        if self.helper_used {
+            if let (Some(helper), Some(source)) = (&self.options.id_helper, &self.options.id_helper_import) {
+                ensure_helper_import(module, helper, source);
+            }
+        }
+        Ok(())
+    }
+
+    fn with_source_start(mut self, pos: BytePos) -> Self {
+        self.source_start = pos;
+        self
+    }
+
+    fn offset(&self, pos: BytePos) -> usize {
+        (pos - self.source_start).0 as usize
+    }
+
+    fn with_ignore_markers(mut self, comments: &SingleThreadedComments) -> Self {
+        let (leading, trailing) = comments.borrow_all();
+        let mut markers: Vec<BytePos> = leading
+            .values()
+            .chain(trailing.values())
+            .flatten()
+            .filter(|comment| comment.text.trim() == JSX_IGNORE_COMMENT)
+            .map(|comment| comment.span.hi)
+            .collect();
+        markers.sort();
+        markers.dedup();
+        self.ignore_markers = markers;
+        self
+    }
+
+    fn with_directives(mut self, comments: &SingleThreadedComments) -> Result<Self, String> {
+        let (leading, trailing) = comments.borrow_all();
+        let mut directives = Vec::new();
+        for comment in leading.values().chain(trailing.values()).flatten() {
+            if let Some(overrides) = OptionOverrides::parse(&comment.text)? {
+                directives.push((comment.span.hi, overrides));
+            }
+        }
+        directives.sort_by_key(|(pos, _)| *pos);
+        directives.dedup_by_key(|(pos, _)| *pos);
+        self.directives = directives;
+        Ok(self)
+    }
+
+    fn with_skip_conditions(mut self, conditions: &[String]) -> Result<Self, String> {
+        self.skip_conditions = conditions
+            .iter()
+            .map(|condition| parse_condition(condition))
+            .collect::<Result<_, _>>()?;
+        Ok(self)
+    }
+
+    fn with_taxonomy(mut self, content: &str) -> Self {
+        self.taxonomy = TaxonomyTracker::for_document(content, self.options, &self.resolved);
+        self
+    }
+
+    /// Enters an element in the taxonomy tracker, setting the context its ID
+    /// is generated with. Returns the depth to truncate the tracker to once
+    /// its children have been visited.
+    fn enter_taxonomy(&mut self, element: impl FnOnce() -> SelectorElement) -> usize {
+        let Some(tracker) = &mut self.taxonomy else {
+            return 0;
+        };
+        let depth = tracker.depth();
+        self.context = tracker.enter(element(), true);
+        depth
+    }
+
+    fn leave_taxonomy(&mut self, depth: usize) {
+        if let Some(tracker) = &mut self.taxonomy {
+            tracker.truncate(depth);
+        }
+    }
+
+    /// Enters an element in the selector path, returning whether the selector
+    /// matches it. Without a selector every element matches.
+    fn enter_selector(&mut self, element: impl FnOnce() -> SelectorElement) -> bool {
+        let Some(selector) = &self.resolved.selector else {
+            return true;
+        };
+        self.selector_path.push(element());
+        selector.matches(&self.selector_path)
+    }
+
+    fn leave_selector(&mut self) {
+        if self.resolved.selector.is_some() {
+            self.selector_path.pop();
+        }
+    }
+
+    /// Which branch of a test on `test` is skipped: `Some(true)` when `test`
+    /// is a skip condition, `Some(false)` when it is one negated with `!`.
+    fn guarded_branch(&self, test: &Expr) -> Option<bool> {
+        if self.skip_conditions.is_empty() {
+            return None;
+        }
+        let is_condition = |expr: &Expr| {
+            let expr = unparen(expr);
+            self.skip_conditions.iter().any(|condition| condition.eq_ignore_span(expr))
+        };
+        if is_condition(test) {
+            return Some(true);
+        }
+        match unparen(test) {
+            Expr::Unary(UnaryExpr { op: op!("!"), arg, .. }) if is_condition(arg) => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Passes over a branch guarded by a skip condition without assigning IDs.
+    /// Its elements still take their counter positions with `CounterMode::All`.
+    fn skip_branch<N: Spanned + for<'c> VisitWith<ElementCounter<'c>>>(&mut self, branch: &N) {
+        let span = branch.span();
+        self.consume_markers_before(span.hi);
+        self.take_directives_before(span.hi);
+        self.pass_over(branch);
+    }
+
+    /// Advances the counter past the elements of a subtree left alone, when
+    /// every element counts
+    fn pass_over<N: for<'c> VisitWith<ElementCounter<'c>>>(&mut self, node: &N) {
+        if self.options.counter.counts_all() {
+            let options = self.scoped_options.last().unwrap_or(self.options);
+            let mut counter = ElementCounter {
+                vue_h: options.vue_h,
+                component_scope: self.options.component_scope.as_deref(),
+                count: 0,
+            };
+            node.visit_with(&mut counter);
+            self.generator.advance_counter(counter.count);
+        }
+    }
+
+    /// Component a host element is the root of, with `component_scope`
+    fn component_root(&self, opening: &JSXOpeningElement) -> Option<String> {
+        component_root(opening, self.options.component_scope.as_deref()?)
+    }
+
+    /// Opens the scope of a component root `depth` elements deep: the
+    /// counter restarts and IDs get the component's name after the prefix
+    fn enter_component(&mut self, component: &str, depth: usize) {
+        self.components.enter(depth, self.generator.get_counter());
+        self.generator.set_counter(0);
+        let options = self.scoped_options.last().unwrap_or(self.options);
+        let scoped = self.resolved.component_overrides(self.options, component).apply(options);
+        self.scoped_options.push(scoped);
+    }
+
+    fn leave_component(&mut self, depth: usize) {
+        self.scoped_options.pop();
+        if let Some(counter) = self.components.leave(depth) {
+            self.generator.set_counter(counter);
+        }
+    }
+
+    /// Enters an element on the path: host elements always, components only
+    /// when just processed elements count. Returns the depth to truncate the
+    /// path to on leaving it.
+    fn enter_path(&mut self, is_host: bool) -> usize {
+        let index = self.path_stack.len();
+        let counts_all = self.options.counter.counts_all();
+        if is_host || !counts_all {
+            self.path_stack.push(self.generator.get_counter());
+        }
+        if is_host && counts_all {
+            self.generator.increment_counter();
+        }
+        index
+    }
+
+    /// Applies the directives placed before `pos` on top of the current options.
+    /// Returns the resulting options if there were any.
+    fn take_directives_before(&mut self, pos: BytePos) -> Option<IdOptions> {
+        let start = self.next_directive;
+        while self.next_directive < self.directives.len() && self.directives[self.next_directive].0 < pos {
+            self.next_directive += 1;
+        }
+
+        let base = self.scoped_options.last().unwrap_or(self.options);
+        self.directives[start..self.next_directive]
+            .iter()
+            .fold(None, |scoped: Option<IdOptions>, (_, overrides)| {
+                Some(overrides.apply(scoped.as_ref().unwrap_or(base)))
+            })
+    }
+
+    /// Consumes the ignore markers placed before `pos`, returning whether any were found.
+    fn consume_markers_before(&mut self, pos: BytePos) -> bool {
+        let start = self.next_marker;
+        while self.next_marker < self.ignore_markers.len() && self.ignore_markers[self.next_marker] < pos {
+            self.next_marker += 1;
+        }
+        self.next_marker > start
+    }
+
+    fn has_ignore_attr(opening: &JSXOpeningElement) -> bool {
+        has_attr(opening, IGNORE_ATTR)
+    }
+
+    /// Adds the ID to the props object of a Vue `h()` call for a host element,
+    /// creating the object when the call has none.
+    fn process_vue_h(&mut self, call: &mut CallExpr, element_name: String) {
+        let options = self.scoped_options.last().unwrap_or(self.options);
+        let props = vue_h_props(call);
+        if props.is_none() && call.args.get(1).is_some_and(|arg| !vue_h_children_arg(arg)) {
+            // Props come from an expression we cannot extend safely
+            return;
+        }
+
+        let written = props.and_then(|props| {
+            prop_index(props, &options.attr)?;
+            Some(object_written_id(props, &options.attr))
+        });
+        let dynamic_id = matches!(written, Some(None));
+        let existing_id = written.map(Option::unwrap_or_default);
+        let offset = self.offset(call.span.lo);
+        let classes = props.and_then(|props| object_prop_value(props, "class")).map(class_words);
+        let has_origin = options
+            .origin_attr
+            .as_ref()
+            .is_some_and(|origin| props.is_some_and(|props| prop_index(props, origin).is_some()));
+        let candidate = Candidate {
+            name: &element_name,
+            classes: &classes.unwrap_or_default(),
+            offset,
+            existing_id: existing_id.as_deref(),
+            dynamic_id,
+            has_origin,
+        };
+        if let Err(reason) = ast_common::check_element(self.generator, &self.resolved.filter, options, &candidate) {
+            ast_common::record_change(self.generator, offset, &element_name, existing_id.as_deref(), None);
+            ast_common::record_decision(self.generator, offset, &element_name, Some(reason));
+            return;
+        }
+
+        let node = AstNode {
+            node_type: element_name,
+            text_content: None,
+            attributes: props.map(object_static_props).unwrap_or_default(),
+            path: self.path_stack[self.components.start()..].to_vec(),
+            context: std::mem::take(&mut self.context),
+        };
+        let id = ast_common::generate_id_for_node(self.generator, &node, options, &self.resolved);
+        ast_common::record_change(self.generator, offset, &node.node_type, existing_id.as_deref(), Some(&id));
+        ast_common::record_decision(self.generator, offset, &node.node_type, None);
+
+        let props = match call.args.get_mut(1) {
+            Some(arg) if matches!(&*arg.expr, Expr::Object(_)) => arg,
+            Some(arg) if matches!(&*arg.expr, Expr::Lit(Lit::Null(_))) => {
+                arg.expr = Box::new(empty_object());
+                arg
+            }
+            _ => {
+                let index = call.args.len().min(1);
+                call.args.insert(index, ExprOrSpread { spread: None, expr: Box::new(empty_object()) });
+                &mut call.args[index]
+            }
+        };
+        let Expr::Object(props) = &mut *props.expr else {
+            return;
+        };
+
+        let extra = extra_attrs::render_all(&self.resolved.extra_attrs, &node, &id);
+        let written: Vec<&str> = [Some(options.attr.as_str()), options.origin_attr.as_deref()]
+            .into_iter()
+            .flatten()
+            .chain(extra.iter().map(|(name, _)| name.as_str()))
+            .collect();
+        props.props.retain(|prop| !prop_key(prop).is_some_and(|key| written.contains(&key.as_str())));
+        props.props.push(PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+            key: prop_name(&options.attr),
+            value: Box::new(id_expr(&id, None, options.id_helper.as_deref())),
+        }))));
+        if let Some(origin_attr) = &options.origin_attr {
+            props.props.push(PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                key: prop_name(origin_attr),
+                value: Box::new(Expr::Lit(Lit::Str(Str::from(ast_common::origin(options))))),
+            }))));
+        }
+        for (name, value) in extra {
+            props.props.push(PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                key: prop_name(&name),
+                value: Box::new(Expr::Lit(Lit::Str(Str::from(value)))),
+            }))));
+        }
+        self.helper_used |= options.id_helper.is_some();
+
+        if !self.options.counter.counts_all() {
+            self.generator.increment_counter();
         }
     }
 
@@ -91,38 +540,61 @@ impl<'a> JsxVisitor<'a> {
             return;
         }
 
+        let options = self.scoped_options.last().unwrap_or(self.options);
+
+        // Expression values count as existing IDs too, so a dynamic ID from an
+        // earlier run is not duplicated
         let existing_attr = opening.attrs.iter().find_map(|attr| {
             if let JSXAttrOrSpread::JSXAttr(attr) = attr {
                 if let JSXAttrName::Ident(ident) = &attr.name {
-                    if ident.sym == self.options.attr.as_str() {
-                        if let Some(JSXAttrValue::Lit(Lit::Str(s))) = &attr.value {
-                            return Some(s.value.to_string());
-                        }
+                    if ident.sym == options.attr.as_str() {
+                        return Some(match &attr.value {
+                            Some(JSXAttrValue::Lit(Lit::Str(s))) => (s.value.to_string(), false),
+                            None => (String::new(), false),
+                            Some(_) => (String::new(), true),
+                        });
                     }
                 }
             }
             None
         });
+        let dynamic_id = existing_attr.as_ref().is_some_and(|(_, dynamic)| *dynamic);
+        let existing_attr = existing_attr.map(|(id, _)| id);
 
-        if !ast_common::should_process_node(&element_name, self.options, existing_attr.as_deref()) {
+        let offset = self.offset(opening.span.lo);
+        let has_origin = options.origin_attr.as_ref().is_some_and(|origin| has_attr(opening, origin));
+        let candidate = Candidate {
+            name: &element_name,
+            classes: &jsx_class_names(&opening.attrs),
+            offset,
+            existing_id: existing_attr.as_deref(),
+            dynamic_id,
+            has_origin,
+        };
+        if let Err(reason) = ast_common::check_element(self.generator, &self.resolved.filter, options, &candidate) {
+            ast_common::record_change(self.generator, offset, &element_name, existing_attr.as_deref(), None);
+            ast_common::record_decision(self.generator, offset, &element_name, Some(reason));
             return;
         }
 
         let node = AstNode {
             node_type: element_name.clone(),
             text_content: None, // Will be extracted from children if needed
-            attributes: Vec::new(),
-            path: self.path_stack.clone(),
+            attributes: JsxProcessor::static_attributes(&opening.attrs),
+            path: self.path_stack[self.components.start()..].to_vec(),
+            context: std::mem::take(&mut self.context),
         };
 
-        let id = ast_common::generate_id_for_node(self.generator, &node, self.options);
+        let id = ast_common::generate_id_for_node(self.generator, &node, options, &self.resolved);
+        ast_common::record_change(self.generator, offset, &element_name, existing_attr.as_deref(), Some(&id));
+        ast_common::record_decision(self.generator, offset, &element_name, None);
 
         // Remove existing attribute if overwriting
-        if self.options.overwrite {
+        if options.overwrite {
             opening.attrs.retain(|attr| {
                 if let JSXAttrOrSpread::JSXAttr(attr) = attr {
                     if let JSXAttrName::Ident(ident) = &attr.name {
-                        return ident.sym != self.options.attr.as_str();
+                        return ident.sym != options.attr.as_str();
                     }
                 }
                 true
@@ -130,41 +602,217 @@ impl<'a> JsxVisitor<'a> {
         }
 
         // Add new attribute
-        if existing_attr.is_none() || self.options.overwrite {
+        if existing_attr.is_none() || options.overwrite {
             let new_attr = JSXAttr {
                 span: DUMMY_SP,
                 name: JSXAttrName::Ident(swc_core::ecma::ast::Ident {
                     span: DUMMY_SP,
-                    sym: self.options.attr.clone().into(),
+                    sym: options.attr.clone().into(),
                     optional: false,
                 }),
-                value: Some(JSXAttrValue::Lit(Lit::Str(Str {
-                    span: DUMMY_SP,
-                    value: id.into(),
-                    raw: None,
-                }))),
+                value: Some(id_attr_value(&id, self.dynamic_keys.last(), options.id_helper.as_deref())),
             };
+            self.helper_used |= options.id_helper.is_some();
             opening.attrs.push(JSXAttrOrSpread::JSXAttr(new_attr));
+            if let Some(origin_attr) = &options.origin_attr {
+                set_string_attr(opening, origin_attr, &ast_common::origin(options));
+            }
+            for (name, value) in extra_attrs::render_all(&self.resolved.extra_attrs, &node, &id) {
+                set_string_attr(opening, &name, &value);
+            }
         }
 
-        self.generator.increment_counter();
+        if !self.options.counter.counts_all() {
+            self.generator.increment_counter();
+        }
     }
 }
 
 impl<'a> VisitMut for JsxVisitor<'a> {
     fn visit_mut_jsx_element(&mut self, node: &mut JSXElement) {
-        let index = self.path_stack.len();
-        self.path_stack.push(self.generator.get_counter());
+        self.generator.count_visit();
+        let marked = self.consume_markers_before(node.span.lo);
+        let component = self.component_root(&node.opening);
+        if marked || Self::has_ignore_attr(&node.opening) {
+            // Skip the whole subtree, including any markers inside it
+            self.consume_markers_before(node.span.hi);
+            self.take_directives_before(node.span.hi);
+            if component.is_none() {
+                self.pass_over(&*node);
+            }
+            return;
+        }
+
+        if let Some(component) = &component {
+            self.enter_component(component, self.path_stack.len());
+        }
+        let scoped = self.take_directives_before(node.span.lo);
+        let has_scope = scoped.is_some();
+        self.scoped_options.extend(scoped);
+
+        let options = self.scoped_options.last().unwrap_or(self.options);
+        let key = (options.dynamic_keys && self.map_depth > 0)
+            .then(|| key_expression(&node.opening))
+            .flatten();
+        let has_key = key.is_some();
+        self.dynamic_keys.extend(key);
+
+        let is_host = JsxProcessor::is_host_element(&JsxProcessor::extract_jsx_element_name(&node.opening.name));
+        let index = self.enter_path(is_host);
+        let element = || SelectorElement {
+            name: JsxProcessor::extract_jsx_element_name(&node.opening.name),
+            attributes: JsxProcessor::static_attributes(&node.opening.attrs),
+        };
+        let depth = self.enter_taxonomy(element);
+        let selected = self.enter_selector(element);
         
-        self.process_jsx_opening(&mut node.opening);
+        let text_matched = self
+            .resolved
+            .text_pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(&direct_text(&node.children)));
+        match ast_common::pick_element(self.generator, selected, text_matched) {
+            Ok(()) => self.process_jsx_opening(&mut node.opening),
+            Err(reason) if is_host && self.generator.logs_filters() => {
+                let offset = self.offset(node.opening.span.lo);
+                let tag = JsxProcessor::extract_jsx_element_name(&node.opening.name);
+                ast_common::record_decision(self.generator, offset, &tag, Some(reason));
+            }
+            Err(_) => {}
+        }
         node.children.visit_mut_children_with(self);
         
+        self.leave_selector();
+        self.leave_taxonomy(depth);
+        self.path_stack.truncate(index);
+        if has_key {
+            self.dynamic_keys.pop();
+        }
+        if has_scope {
+            self.scoped_options.pop();
+        }
+        if component.is_some() {
+            self.leave_component(index);
+        }
+    }
+
+    fn visit_mut_call_expr(&mut self, node: &mut CallExpr) {
+        let options = self.scoped_options.last().unwrap_or(self.options);
+        let Some(element_name) = options.vue_h.then(|| vue_h_tag(node)).flatten() else {
+            let is_map = is_map_call(node);
+            self.map_depth += usize::from(is_map);
+            node.visit_mut_children_with(self);
+            self.map_depth -= usize::from(is_map);
+            return;
+        };
+
+        self.generator.count_visit();
+        let marked = self.consume_markers_before(node.span.lo);
+        let has_ignore_attr = vue_h_props(node).is_some_and(|props| prop_index(props, IGNORE_ATTR).is_some());
+        if marked || has_ignore_attr {
+            self.consume_markers_before(node.span.hi);
+            self.take_directives_before(node.span.hi);
+            self.pass_over(&*node);
+            return;
+        }
+
+        let scoped = self.take_directives_before(node.span.lo);
+        let has_scope = scoped.is_some();
+        self.scoped_options.extend(scoped);
+
+        let index = self.enter_path(true);
+        let element = || SelectorElement {
+            name: element_name.clone(),
+            attributes: vue_h_props(node).map(object_static_props).unwrap_or_default(),
+        };
+        let depth = self.enter_taxonomy(element);
+        let selected = self.enter_selector(element);
+
+        let text_matched = self
+            .resolved
+            .text_pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(&vue_h_text(node)));
+        match ast_common::pick_element(self.generator, selected, text_matched) {
+            Ok(()) => self.process_vue_h(node, element_name),
+            Err(reason) => {
+                let offset = self.offset(node.span.lo);
+                ast_common::record_decision(self.generator, offset, &element_name, Some(reason));
+            }
+        }
+        node.args.visit_mut_children_with(self);
+
+        self.leave_selector();
+        self.leave_taxonomy(depth);
         self.path_stack.truncate(index);
+        if has_scope {
+            self.scoped_options.pop();
+        }
+    }
+
+    fn visit_mut_tagged_tpl(&mut self, node: &mut TaggedTpl) {
+        node.visit_mut_children_with(self);
+
+        let options = self.scoped_options.last().unwrap_or(self.options);
+        let is_markup = template_tag_name(&node.tag)
+            .is_some_and(|name| options.template_tags.contains(&name));
+        if is_markup {
+            let result = process_template_markup(&mut node.tpl, options, &self.resolved, self.generator, self.source_start);
+            if let Err(e) = result {
+                self.error.get_or_insert(e);
+            }
+        }
+    }
+
+    fn visit_mut_bin_expr(&mut self, node: &mut BinExpr) {
+        if node.op == op!("&&") && self.guarded_branch(&node.left) == Some(true) {
+            node.left.visit_mut_with(self);
+            self.skip_branch(&node.right);
+            return;
+        }
+        node.visit_mut_children_with(self);
+    }
+
+    fn visit_mut_cond_expr(&mut self, node: &mut CondExpr) {
+        node.test.visit_mut_with(self);
+        match self.guarded_branch(&node.test) {
+            Some(true) => {
+                self.skip_branch(&node.cons);
+                node.alt.visit_mut_with(self);
+            }
+            Some(false) => {
+                node.cons.visit_mut_with(self);
+                self.skip_branch(&node.alt);
+            }
+            None => {
+                node.cons.visit_mut_with(self);
+                node.alt.visit_mut_with(self);
+            }
+        }
+    }
+
+    fn visit_mut_if_stmt(&mut self, node: &mut IfStmt) {
+        node.test.visit_mut_with(self);
+        match self.guarded_branch(&node.test) {
+            Some(true) => {
+                self.skip_branch(&node.cons);
+                node.alt.visit_mut_with(self);
+            }
+            Some(false) => {
+                node.cons.visit_mut_with(self);
+                if let Some(alt) = &node.alt {
+                    self.skip_branch(alt);
+                }
+            }
+            None => {
+                node.cons.visit_mut_with(self);
+                node.alt.visit_mut_with(self);
+            }
+        }
     }
 
     fn visit_mut_jsx_fragment(&mut self, node: &mut JSXFragment) {
-        let index = self.path_stack.len();
-        self.path_stack.push(self.generator.get_counter());
+        let index = self.enter_path(false);
         
         node.children.visit_mut_children_with(self);
         
@@ -172,81 +820,1948 @@ impl<'a> VisitMut for JsxVisitor<'a> {
     }
 }
 
-impl AstProcessor for JsxProcessor {
-    fn process(&mut self, content: &str, options: &IdOptions) -> Result<String, String> {
-        let cm = Arc::new(SourceMap::default());
-        let fm = cm.new_source_file(FileName::Anon, content.to_string());
-        
-        let lexer = Lexer::new(
-            Syntax::Typescript(TsConfig {
-                tsx: true,
-                decorators: true,
-                ..Default::default()
-            }),
-            Default::default(),
-            StringInput::from(&*fm),
-            None,
-        );
+/// Counts the host elements of a subtree, `h()` calls included with `vue_h`.
+/// Component scopes in it count on their own.
+struct ElementCounter<'a> {
+    vue_h: bool,
+    component_scope: Option<&'a str>,
+    count: usize,
+}
 
-        let mut parser = Parser::new_from(lexer);
-        
-        let mut module = parser
-            .parse_module()
-            .map_err(|e| format!("Parse error: {:?}", e))?;
+impl<'a> Visit for ElementCounter<'a> {
+    fn visit_jsx_element(&mut self, node: &JSXElement) {
+        if self.component_scope.is_some_and(|attr| component_root(&node.opening, attr).is_some()) {
+            return;
+        }
+        if JsxProcessor::is_host_element(&JsxProcessor::extract_jsx_element_name(&node.opening.name)) {
+            self.count += 1;
+        }
+        node.visit_children_with(self);
+    }
 
-        let mut visitor = JsxVisitor::new(options, &mut self.generator);
-        module.visit_mut_with(&mut visitor);
+    fn visit_call_expr(&mut self, node: &CallExpr) {
+        if self.vue_h && vue_h_tag(node).is_some() {
+            self.count += 1;
+        }
+        node.visit_children_with(self);
+    }
+}
 
-        // Convert back to string
-        let output = to_code(&module);
-        
-        Ok(output)
+/// Component a host element is the root of, from `attr`
+fn component_root(opening: &JSXOpeningElement, attr: &str) -> Option<String> {
+    if !JsxProcessor::is_host_element(&JsxProcessor::extract_jsx_element_name(&opening.name)) {
+        return None;
     }
+    JsxProcessor::static_attributes(&opening.attrs)
+        .into_iter()
+        .find_map(|(name, value)| (name == attr && !value.is_empty()).then_some(value))
 }
 
-fn to_code(module: &Module) -> String {
-    use swc_core::common::sync::Lrc;
-    use swc_core::ecma::codegen::{text_writer::JsWriter, Emitter};
-    
-    let cm = Lrc::new(SourceMap::default());
-    let mut buf = Vec::new();
-    let writer = Box::new(JsWriter::new(cm.clone(), "\n", &mut buf, None));
-    
-    let mut emitter = Emitter {
-        cfg: swc_core::ecma::codegen::Config::default(),
-        cm: cm.clone(),
-        comments: None,
-        wr: writer,
-    };
-    
-    emitter.emit_module(module).unwrap();
-    String::from_utf8(buf).unwrap()
+/// Writes each component's name to `attr` on the host element its return
+/// expressions render at the root. Function declarations, functions and arrows
+/// assigned to capitalized names (also through wrappers like `memo(...)`) and
+/// the `render` method of capitalized classes count as components.
+struct ComponentMarker<'a> {
+    attr: &'a str,
+    /// Component name of each enclosing function, innermost last; `None` for
+    /// functions that are not components, such as callbacks
+    components: Vec<Option<String>>,
+    /// Name for the next function visited
+    pending: Option<String>,
+    class_name: Option<String>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl<'a> ComponentMarker<'a> {
+    fn new(attr: &'a str) -> Self {
+        Self {
+            attr,
+            components: Vec::new(),
+            pending: None,
+            class_name: None,
+        }
+    }
 
-    #[test]
-    fn test_jsx_processing() {
-        let mut processor = JsxProcessor::new();
-        let options = IdOptions::default();
-        
-        let input = r#"
-            function App() {
-                return <div><span>Hello</span></div>;
+    fn component_name(name: &str) -> Option<String> {
+        name.chars()
+            .next()
+            .is_some_and(|c| c.is_uppercase())
+            .then(|| name.to_string())
+    }
+
+    fn is_function(expr: &Expr) -> bool {
+        match unparen(expr) {
+            Expr::Arrow(_) | Expr::Fn(_) => true,
+            Expr::Call(call) => call.args.first().is_some_and(|arg| Self::is_function(&arg.expr)),
+            _ => false,
+        }
+    }
+
+    fn current(&self) -> Option<&str> {
+        self.components.last().and_then(|name| name.as_deref())
+    }
+
+    fn mark_root(&self, expr: &mut Expr) {
+        let Some(name) = self.current() else {
+            return;
+        };
+        match expr {
+            Expr::Paren(paren) => self.mark_root(&mut paren.expr),
+            Expr::Cond(cond) => {
+                self.mark_root(&mut cond.cons);
+                self.mark_root(&mut cond.alt);
             }
-        "#;
-        
-        let result = processor.process(input, &options).unwrap();
-        assert!(result.contains("data-ast-id"));
+            Expr::JSXElement(element) => {
+                let opening = &mut element.opening;
+                let element_name = JsxProcessor::extract_jsx_element_name(&opening.name);
+                if !JsxProcessor::is_host_element(&element_name) || JsxVisitor::has_ignore_attr(opening) {
+                    return;
+                }
+
+                set_string_attr(opening, self.attr, name);
+            }
+            _ => {}
+        }
     }
+}
 
-    #[test]
-    fn test_host_element_detection() {
-        assert!(JsxProcessor::is_host_element("div"));
-        assert!(JsxProcessor::is_host_element("span"));
-        assert!(!JsxProcessor::is_host_element("Component"));
-        assert!(!JsxProcessor::is_host_element("MyComponent"));
+impl<'a> VisitMut for ComponentMarker<'a> {
+    fn visit_mut_fn_decl(&mut self, node: &mut FnDecl) {
+        self.pending = Self::component_name(&node.ident.sym);
+        node.function.visit_mut_with(self);
+    }
+
+    /// Named function expressions, e.g. `export default function Name() {}`
+    fn visit_mut_fn_expr(&mut self, node: &mut FnExpr) {
+        if let Some(ident) = &node.ident {
+            self.pending = self.pending.take().or_else(|| Self::component_name(&ident.sym));
+        }
+        node.function.visit_mut_with(self);
+    }
+
+    fn visit_mut_var_declarator(&mut self, node: &mut VarDeclarator) {
+        if let (Pat::Ident(name), Some(init)) = (&node.name, &node.init) {
+            if Self::is_function(init) {
+                self.pending = Self::component_name(&name.id.sym);
+            }
+        }
+        node.visit_mut_children_with(self);
+        self.pending = None;
+    }
+
+    fn visit_mut_class_decl(&mut self, node: &mut ClassDecl) {
+        let outer = self.class_name.replace(node.ident.sym.to_string());
+        node.class.visit_mut_with(self);
+        self.class_name = outer;
+    }
+
+    fn visit_mut_class_expr(&mut self, node: &mut ClassExpr) {
+        let name = node.ident.as_ref().map(|ident| ident.sym.to_string());
+        let outer = std::mem::replace(&mut self.class_name, name);
+        node.class.visit_mut_with(self);
+        self.class_name = outer;
+    }
+
+    fn visit_mut_class_method(&mut self, node: &mut ClassMethod) {
+        let is_render = matches!(&node.key, PropName::Ident(ident) if ident.sym == "render");
+        if is_render {
+            self.pending = self.class_name.as_deref().and_then(Self::component_name);
+        }
+        node.function.visit_mut_with(self);
+    }
+
+    fn visit_mut_function(&mut self, node: &mut Function) {
+        self.components.push(self.pending.take());
+        node.visit_mut_children_with(self);
+        self.components.pop();
+    }
+
+    fn visit_mut_arrow_expr(&mut self, node: &mut ArrowExpr) {
+        self.components.push(self.pending.take());
+        node.visit_mut_children_with(self);
+        if let BlockStmtOrExpr::Expr(body) = &mut *node.body {
+            self.mark_root(body);
+        }
+        self.components.pop();
+    }
+
+    fn visit_mut_return_stmt(&mut self, node: &mut ReturnStmt) {
+        node.visit_mut_children_with(self);
+        if let Some(arg) = &mut node.arg {
+            self.mark_root(arg);
+        }
+    }
+}
+
+impl Default for JsxProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AstProcessor for JsxProcessor {
+    fn generator_mut(&mut self) -> &mut IdGenerator {
+        &mut self.generator
     }
-}
\ No newline at end of file
+
+    fn process_with_meta(&mut self, content: &str, options: &IdOptions) -> Result<ProcessOutput, String> {
+        let resolved = Rc::new(ResolvedOptions::new(options, Format::Jsx)?);
+        self.process_resolved(content, options, &resolved)
+    }
+
+    fn process_resolved(
+        &mut self,
+        content: &str,
+        options: &IdOptions,
+        resolved: &Rc<ResolvedOptions>,
+    ) -> Result<ProcessOutput, String> {
+        ast_common::with_meta(self, options, |processor| processor.process_document(content, options, resolved))
+    }
+}
+
+impl JsxProcessor {
+    fn process_document(&mut self, content: &str, options: &IdOptions, resolved: &Rc<ResolvedOptions>) -> Result<String, String> {
+        let _span = tracing::debug_span!("process", format = "jsx", bytes = content.len()).entered();
+        with_stack(content, || {
+            let comments = SingleThreadedComments::default();
+            let (cm, mut module) = parse_module(content, Some(&comments))?;
+            // Kept to find the tags that change, when only those are written back
+            let original = options.range.is_some().then(|| module.clone());
+
+            let source_start = cm.files().first().map_or(BytePos(0), |file| file.start_pos);
+            let generator = &mut self.generator;
+            let mut visitor =
+                JsxVisitor::for_module_with_resolved(options, resolved.clone(), generator, content, &comments, source_start)?;
+            visitor.mark_components(&mut module);
+            module.visit_mut_with(&mut visitor);
+            visitor.finish(&mut module)?;
+
+            // Convert back to string
+            let output = match (&original, &options.range) {
+                (Some(original), Some(range)) => splice_tags(content, &cm, original, &module, source_start, range),
+                _ => to_code(cm, &module, Some(&comments)),
+            };
+            output.map(|output| ast_common::match_layout(content, output))
+        })
+    }
+}
+
+/// Whether `call` is `something.map(callback)` with an inline callback.
+fn is_map_call(call: &CallExpr) -> bool {
+    let Callee::Expr(callee) = &call.callee else {
+        return false;
+    };
+    let is_map = matches!(&**callee, Expr::Member(member)
+        if matches!(&member.prop, MemberProp::Ident(ident) if ident.sym == *"map"));
+    is_map
+        && call.args.first().is_some_and(|arg| {
+            matches!(&*arg.expr, Expr::Arrow(_) | Expr::Fn(_))
+        })
+}
+
+/// Text directly inside an element: its text children and string literal
+/// expressions, leaving out child elements.
+fn direct_text(children: &[JSXElementChild]) -> String {
+    children
+        .iter()
+        .filter_map(|child| match child {
+            JSXElementChild::JSXText(text) => Some(&*text.value),
+            JSXElementChild::JSXExprContainer(JSXExprContainer { expr: JSXExpr::Expr(expr), .. }) => match &**expr {
+                Expr::Lit(Lit::Str(s)) => Some(&*s.value),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Classes of a JSX element's `className` (or `class`) attribute that are
+/// known statically, see `class_words`.
+fn jsx_class_names(attrs: &[JSXAttrOrSpread]) -> Vec<&str> {
+    let value = attrs.iter().find_map(|attr| match attr {
+        JSXAttrOrSpread::JSXAttr(JSXAttr {
+            name: JSXAttrName::Ident(ident),
+            value: Some(value),
+            ..
+        }) if ident.sym == "className" || ident.sym == "class" => Some(value),
+        _ => None,
+    });
+    match value {
+        Some(JSXAttrValue::Lit(Lit::Str(s))) => s.value.split_whitespace().collect(),
+        Some(JSXAttrValue::JSXExprContainer(JSXExprContainer { expr: JSXExpr::Expr(expr), .. })) => class_words(expr),
+        _ => Vec::new(),
+    }
+}
+
+/// Class names in a string literal, or the words of a template literal that
+/// no expression is glued to: `btn ${active}` gives `btn`, `btn-${size}`
+/// gives nothing.
+fn class_words(expr: &Expr) -> Vec<&str> {
+    match expr {
+        Expr::Lit(Lit::Str(s)) => s.value.split_whitespace().collect(),
+        Expr::Tpl(tpl) => {
+            let last = tpl.quasis.len().saturating_sub(1);
+            tpl.quasis
+                .iter()
+                .enumerate()
+                .flat_map(|(i, quasi)| {
+                    let raw: &str = &quasi.raw;
+                    let words: Vec<&str> = raw.split_whitespace().collect();
+                    let skip_first = i > 0 && !raw.starts_with(char::is_whitespace);
+                    let skip_last = i < last && !raw.ends_with(char::is_whitespace);
+                    let end = words.len().saturating_sub(usize::from(skip_last));
+                    words
+                        .into_iter()
+                        .take(end)
+                        .skip(usize::from(skip_first))
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Whether the element has an attribute `name`, whatever its value
+fn has_attr(opening: &JSXOpeningElement, name: &str) -> bool {
+    opening.attrs.iter().any(|attr| {
+        matches!(attr, JSXAttrOrSpread::JSXAttr(JSXAttr { name: JSXAttrName::Ident(ident), .. })
+            if ident.sym == name)
+    })
+}
+
+/// Sets `attr` to the string `value`, replacing any value it has.
+fn set_string_attr(opening: &mut JSXOpeningElement, attr: &str, value: &str) {
+    let value = JSXAttrValue::Lit(Lit::Str(jsx_str(value)));
+    let existing = opening.attrs.iter_mut().find_map(|existing| match existing {
+        JSXAttrOrSpread::JSXAttr(existing)
+            if matches!(&existing.name, JSXAttrName::Ident(ident) if ident.sym == attr) =>
+        {
+            Some(existing)
+        }
+        _ => None,
+    });
+    match existing {
+        Some(existing) => existing.value = Some(value),
+        None => opening.attrs.push(JSXAttrOrSpread::JSXAttr(JSXAttr {
+            span: DUMMY_SP,
+            name: JSXAttrName::Ident(Ident::new(attr.into(), DUMMY_SP)),
+            value: Some(value),
+        })),
+    }
+}
+
+/// A string literal for a JSX attribute. JSX strings have no escapes other
+/// than character references, so the raw text is written with those.
+fn jsx_str(value: &str) -> Str {
+    Str {
+        span: DUMMY_SP,
+        value: value.into(),
+        raw: Some(format!("\"{}\"", ast_common::escape_attribute(value)).into()),
+    }
+}
+
+/// The expression of a `key={...}` attribute.
+fn key_expression(opening: &JSXOpeningElement) -> Option<Expr> {
+    opening.attrs.iter().find_map(|attr| match attr {
+        JSXAttrOrSpread::JSXAttr(JSXAttr {
+            name: JSXAttrName::Ident(ident),
+            value: Some(JSXAttrValue::JSXExprContainer(JSXExprContainer { expr: JSXExpr::Expr(expr), .. })),
+            ..
+        }) if ident.sym == *"key" => Some((**expr).clone()),
+        _ => None,
+    })
+}
+
+/// The ID as an expression: a string literal, or `${id}-${key}` inside mapped
+/// callbacks so each row gets its own ID at runtime, wrapped in a call to
+/// `helper` when one is configured.
+fn id_expr(id: &str, key: Option<&Expr>, helper: Option<&str>) -> Expr {
+    let value = match key {
+        Some(key) => {
+            let quasi = |cooked: String, tail: bool| TplElement {
+                span: DUMMY_SP,
+                tail,
+                raw: cooked.replace('\\', "\\\\").replace('`', "\\`").replace("${", "\\${").into(),
+                cooked: Some(cooked.into()),
+            };
+            Expr::Tpl(Tpl {
+                span: DUMMY_SP,
+                exprs: vec![Box::new(key.clone())],
+                quasis: vec![quasi(format!("{}-", id), false), quasi(String::new(), true)],
+            })
+        }
+        None => Expr::Lit(Lit::Str(Str {
+            span: DUMMY_SP,
+            value: id.into(),
+            raw: None,
+        })),
+    };
+
+    match helper {
+        Some(helper) => Expr::Call(CallExpr {
+            span: DUMMY_SP,
+            callee: Callee::Expr(Box::new(Expr::Ident(swc_core::ecma::ast::Ident {
+                span: DUMMY_SP,
+                sym: helper.into(),
+                optional: false,
+            }))),
+            args: vec![ExprOrSpread { spread: None, expr: Box::new(value) }],
+            type_args: None,
+        }),
+        None => value,
+    }
+}
+
+fn id_attr_value(id: &str, key: Option<&Expr>, helper: Option<&str>) -> JSXAttrValue {
+    match id_expr(id, key, helper) {
+        Expr::Lit(Lit::Str(s)) => JSXAttrValue::Lit(Lit::Str(jsx_str(&s.value))),
+        expr => JSXAttrValue::JSXExprContainer(JSXExprContainer {
+            span: DUMMY_SP,
+            expr: JSXExpr::Expr(Box::new(expr)),
+        }),
+    }
+}
+
+/// Static part of an ID written by `id_expr`: the literal itself, the text
+/// before a mapped key, or either of those passed to a helper call.
+fn written_id(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Lit(Lit::Str(s)) => Some(&s.value),
+        Expr::Tpl(tpl) if tpl.exprs.len() == 1 => tpl.quasis.first()?.cooked.as_deref()?.strip_suffix('-'),
+        Expr::Call(call) if call.args.len() == 1 => written_id(&call.args[0].expr),
+        _ => None,
+    }
+}
+
+fn written_attr_id(value: &JSXAttrValue) -> Option<&str> {
+    match value {
+        JSXAttrValue::Lit(Lit::Str(s)) => Some(&s.value),
+        JSXAttrValue::JSXExprContainer(JSXExprContainer { expr: JSXExpr::Expr(expr), .. }) => written_id(expr),
+        _ => None,
+    }
+}
+
+/// Inserts `import { helper } from "source"` unless the module already imports
+/// or declares something named `helper`. Leading directives such as
+/// `"use client"` stay first.
+fn ensure_helper_import(module: &mut Module, helper: &str, source: &str) {
+    let bound = module.body.iter().any(|item| match item {
+        ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => import.specifiers.iter().any(|spec| match spec {
+            ImportSpecifier::Named(s) => s.local.sym == *helper,
+            ImportSpecifier::Default(s) => s.local.sym == *helper,
+            ImportSpecifier::Namespace(s) => s.local.sym == *helper,
+        }),
+        ModuleItem::Stmt(Stmt::Decl(Decl::Fn(f))) => f.ident.sym == *helper,
+        _ => false,
+    });
+    if bound {
+        return;
+    }
+
+    let index = module
+        .body
+        .iter()
+        .position(|item| {
+            !matches!(item, ModuleItem::Stmt(Stmt::Expr(ExprStmt { expr, .. })) if matches!(&**expr, Expr::Lit(Lit::Str(_))))
+        })
+        .unwrap_or(module.body.len());
+
+    let import = ImportDecl {
+        span: DUMMY_SP,
+        specifiers: vec![ImportSpecifier::Named(ImportNamedSpecifier {
+            span: DUMMY_SP,
+            local: swc_core::ecma::ast::Ident {
+                span: DUMMY_SP,
+                sym: helper.into(),
+                optional: false,
+            },
+            imported: None,
+            is_type_only: false,
+        })],
+        src: Box::new(Str {
+            span: DUMMY_SP,
+            value: source.into(),
+            raw: None,
+        }),
+        type_only: false,
+        with: None,
+    };
+    module.body.insert(index, ModuleItem::ModuleDecl(ModuleDecl::Import(import)));
+}
+
+/// Element name of a Vue `h('div', ...)` call for a host element. Components
+/// (`h(MyComponent)`) are left alone like their JSX counterparts.
+fn vue_h_tag(call: &CallExpr) -> Option<String> {
+    let Callee::Expr(callee) = &call.callee else {
+        return None;
+    };
+    if !matches!(&**callee, Expr::Ident(ident) if ident.sym == *"h") {
+        return None;
+    }
+    match call.args.first() {
+        Some(ExprOrSpread { spread: None, expr }) => match &**expr {
+            Expr::Lit(Lit::Str(s)) if JsxProcessor::is_host_element(&s.value) => Some(s.value.to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn vue_h_props(call: &CallExpr) -> Option<&ObjectLit> {
+    match call.args.get(1) {
+        Some(ExprOrSpread { spread: None, expr }) => match &**expr {
+            Expr::Object(props) => Some(props),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Text children of an `h()` call: a string literal passed as children, or
+/// the string literals in a children array.
+fn vue_h_text(call: &CallExpr) -> String {
+    let children = call.args.iter().skip(1).find(|arg| vue_h_children_arg(arg));
+    let literal = |expr: &Expr| match expr {
+        Expr::Lit(Lit::Str(s)) => Some(s.value.to_string()),
+        _ => None,
+    };
+    match children.map(|arg| &*arg.expr) {
+        Some(Expr::Array(array)) => array
+            .elems
+            .iter()
+            .flatten()
+            .filter_map(|elem| literal(&elem.expr))
+            .collect(),
+        Some(expr) => literal(expr).unwrap_or_default(),
+        None => String::new(),
+    }
+}
+
+/// Whether the second `h()` argument is children (or `null`) rather than props.
+fn vue_h_children_arg(arg: &ExprOrSpread) -> bool {
+    arg.spread.is_none()
+        && matches!(&*arg.expr, Expr::Array(_) | Expr::Tpl(_) | Expr::Lit(Lit::Str(_) | Lit::Null(_)))
+}
+
+fn prop_key(prop: &PropOrSpread) -> Option<String> {
+    match prop {
+        PropOrSpread::Prop(prop) => match &**prop {
+            Prop::KeyValue(kv) => match &kv.key {
+                PropName::Ident(ident) => Some(ident.sym.to_string()),
+                PropName::Str(s) => Some(s.value.to_string()),
+                _ => None,
+            },
+            _ => None,
+        },
+        PropOrSpread::Spread(_) => None,
+    }
+}
+
+fn prop_index(props: &ObjectLit, key: &str) -> Option<usize> {
+    props.props.iter().position(|prop| prop_key(prop).as_deref() == Some(key))
+}
+
+fn object_prop_value<'p>(props: &'p ObjectLit, key: &str) -> Option<&'p Expr> {
+    let index = prop_index(props, key)?;
+    match &props.props[index] {
+        PropOrSpread::Prop(prop) => match &**prop {
+            Prop::KeyValue(KeyValueProp { value, .. }) => Some(value),
+            _ => None,
+        },
+        PropOrSpread::Spread(_) => None,
+    }
+}
+
+fn object_str_prop(props: &ObjectLit, key: &str) -> Option<String> {
+    match object_prop_value(props, key)? {
+        Expr::Lit(Lit::Str(s)) => Some(s.value.to_string()),
+        _ => None,
+    }
+}
+
+fn object_written_id(props: &ObjectLit, key: &str) -> Option<String> {
+    object_prop_value(props, key).and_then(written_id).map(str::to_string)
+}
+
+/// Prop names with their string-literal values, mirroring `static_attributes`.
+fn object_static_props(props: &ObjectLit) -> Vec<(String, String)> {
+    props
+        .props
+        .iter()
+        .filter_map(|prop| {
+            let key = prop_key(prop)?;
+            let value = object_str_prop(props, &key).unwrap_or_default();
+            Some((key, value))
+        })
+        .collect()
+}
+
+fn prop_name(key: &str) -> PropName {
+    let is_ident = key.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+    if is_ident {
+        PropName::Ident(swc_core::ecma::ast::Ident {
+            span: DUMMY_SP,
+            sym: key.into(),
+            optional: false,
+        })
+    } else {
+        PropName::Str(Str {
+            span: DUMMY_SP,
+            value: key.into(),
+            raw: None,
+        })
+    }
+}
+
+fn empty_object() -> Expr {
+    Expr::Object(ObjectLit {
+        span: DUMMY_SP,
+        props: Vec::new(),
+    })
+}
+
+/// Stands in for a `${...}` interpolation while a template's markup is processed
+#[cfg(feature = "html")]
+fn template_placeholder(index: usize) -> String {
+    format!("__ast_append_ids_expr_{}__", index)
+}
+
+/// Name of a template literal tag: `html` for both html`...` and lit.html`...`.
+fn template_tag_name(tag: &Expr) -> Option<String> {
+    match tag {
+        Expr::Ident(ident) => Some(ident.sym.to_string()),
+        Expr::Member(member) => match &member.prop {
+            MemberProp::Ident(ident) => Some(ident.sym.to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Runs the markup of a template literal through the HTML processor and splices
+/// the result back between the original interpolations.
+#[cfg(not(feature = "html"))]
+fn process_template_markup(
+    _tpl: &mut Tpl,
+    _options: &IdOptions,
+    _resolved: &ResolvedOptions,
+    _generator: &mut IdGenerator,
+    _source_start: BytePos,
+) -> Result<(), String> {
+    Err("Template literal markup needs the `html` feature".to_string())
+}
+
+#[cfg(feature = "html")]
+fn process_template_markup(
+    tpl: &mut Tpl,
+    options: &IdOptions,
+    resolved: &ResolvedOptions,
+    generator: &mut IdGenerator,
+    source_start: BytePos,
+) -> Result<(), String> {
+    let mut markup = String::new();
+    let mut quasi_starts = Vec::with_capacity(tpl.quasis.len());
+    for (i, quasi) in tpl.quasis.iter().enumerate() {
+        if i > 0 {
+            markup.push_str(&template_placeholder(i - 1));
+        }
+        quasi_starts.push(markup.len());
+        markup.push_str(&quasi.raw);
+    }
+
+    let earlier_changes = generator.take_changes();
+    let earlier_decisions = generator.take_decisions();
+    let earlier_warnings = generator.take_warnings();
+    let mut processor = HtmlProcessor::with_generator(std::mem::take(generator));
+    // Not through `process`, which would hand this file's warnings to the markup
+    let result = resolved
+        .embedded_html(options)
+        .and_then(|resolved| processor.process_document(&markup, options, &resolved));
+    *generator = processor.into_generator();
+    let markup_changes = generator.take_changes();
+    let markup_decisions = generator.take_decisions();
+    let markup_warnings = generator.take_warnings();
+    for change in earlier_changes {
+        generator.record_change(change);
+    }
+    for decision in earlier_decisions {
+        generator.record_decision(decision);
+    }
+    for warning in earlier_warnings {
+        generator.add_warning(warning);
+    }
+    let mut rest = result?;
+
+    let mut parts = Vec::with_capacity(tpl.quasis.len());
+    for i in 0..tpl.quasis.len().saturating_sub(1) {
+        let Some((part, tail)) = rest.split_once(&template_placeholder(i)) else {
+            // An interpolation was dropped or moved; keep the original source
+            return Ok(());
+        };
+        parts.push(part.to_string());
+        rest = tail.to_string();
+    }
+    parts.push(rest);
+
+    // Elements start inside a quasi, whose raw text is copied verbatim from the source
+    let source_offset = |offset: usize| {
+        let i = quasi_starts.partition_point(|&start| start <= offset) - 1;
+        (tpl.quasis[i].span.lo - source_start).0 as usize + offset - quasi_starts[i]
+    };
+    for mut change in markup_changes {
+        change.offset = source_offset(change.offset);
+        generator.record_change(change);
+    }
+    for mut decision in markup_decisions {
+        decision.offset = source_offset(decision.offset);
+        generator.record_decision(decision);
+    }
+    for mut warning in markup_warnings {
+        warning.offset = warning.offset.map(source_offset);
+        generator.add_warning(warning);
+    }
+
+    for (quasi, part) in tpl.quasis.iter_mut().zip(parts) {
+        quasi.raw = part.clone().into();
+        quasi.cooked = Some(part.into());
+    }
+    Ok(())
+}
+
+/// Parses an `IdOptions::skip_conditions` entry into the expression it
+/// matches.
+fn parse_condition(condition: &str) -> Result<Expr, String> {
+    let invalid = || format!("Invalid skip condition '{}'", condition);
+    let (_, module) = parse_module(&format!("({});", condition), None).map_err(|_| invalid())?;
+    match module.body.as_slice() {
+        [ModuleItem::Stmt(Stmt::Expr(stmt))] => Ok(unparen(&stmt.expr).clone()),
+        _ => Err(invalid()),
+    }
+}
+
+fn unparen(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Paren(paren) => unparen(&paren.expr),
+        expr => expr,
+    }
+}
+
+/// Stack swc takes per level of JSX nesting to parse, visit, print and drop a
+/// module, with room to spare in unoptimized builds
+const STACK_PER_LEVEL: usize = 16 * 1024;
+/// Stack kept for everything around that
+const STACK_BASE: usize = 1024 * 1024;
+
+/// Runs `f`, which parses `content`, on a stack deep enough for its nesting:
+/// swc recurses at every level of JSX, so documents nested thousands of
+/// levels deep would overflow the thread's own stack.
+#[cfg(not(target_arch = "wasm32"))]
+fn with_stack<R>(content: &str, f: impl FnOnce() -> R) -> R {
+    let needed = limits::nesting_depth(content).saturating_mul(STACK_PER_LEVEL).saturating_add(STACK_BASE);
+    stacker::maybe_grow(needed, needed, f)
+}
+
+/// The stack of a wasm module cannot grow, so how deep JSX can go is up to
+/// the stack the host gives it
+#[cfg(target_arch = "wasm32")]
+fn with_stack<R>(_content: &str, f: impl FnOnce() -> R) -> R {
+    f()
+}
+
+/// Parses `content`, returning the source map alongside the module since the
+/// emitter needs it to print nodes that keep their original spans.
+fn parse_module(
+    content: &str,
+    comments: Option<&SingleThreadedComments>,
+) -> Result<(Lrc<SourceMap>, Module), String> {
+    check_char_references(content)?;
+
+    let cm = Lrc::new(SourceMap::default());
+    let fm = cm.new_source_file(FileName::Anon, content.to_string());
+    let mut module = parse_file(content, &fm, comments)?;
+    module.visit_mut_with(&mut JsxTextRestorer { cm: &cm });
+    Ok((cm, module))
+}
+
+/// Parses `fm`, whose source is `content`
+fn parse_file(content: &str, fm: &SourceFile, comments: Option<&SingleThreadedComments>) -> Result<Module, String> {
+    // swc parses JSX in time quadratic in its nesting as TypeScript, and in
+    // linear time as JavaScript, so deep documents are tried as that first
+    let mut javascript = None;
+    if limits::nesting_depth(content) > PLAIN_JSX_DEPTH {
+        let javascript_comments = SingleThreadedComments::default();
+        let syntax = Syntax::Es(EsConfig { jsx: true, decorators: true, ..Default::default() });
+        if let Ok(module) = parse_source(syntax, fm, Some(&javascript_comments)) {
+            if let Some(comments) = comments {
+                let (leading, trailing) = javascript_comments.take_all();
+                for (pos, list) in leading.take() {
+                    comments.add_leading_comments(pos, list);
+                }
+                for (pos, list) in trailing.take() {
+                    comments.add_trailing_comments(pos, list);
+                }
+            }
+            javascript = Some(module);
+        }
+    }
+    match javascript {
+        Some(module) => Ok(module),
+        None => {
+            let syntax = Syntax::Typescript(TsConfig { tsx: true, decorators: true, ..Default::default() });
+            parse_source(syntax, fm, comments)
+        }
+    }
+}
+
+/// Nesting of JSX from which a document is parsed as JavaScript first
+const PLAIN_JSX_DEPTH: usize = 256;
+
+fn parse_source(syntax: Syntax, fm: &SourceFile, comments: Option<&SingleThreadedComments>) -> Result<Module, String> {
+    let lexer = Lexer::new(syntax, Default::default(), StringInput::from(fm), comments.map(|c| c as &dyn Comments));
+    let mut parser = Parser::new_from(lexer);
+    // A last guard in native builds only: wasm builds abort on a panic, so
+    // input known to panic the lexer is turned away before it gets here
+    panic::catch_unwind(AssertUnwindSafe(|| parser.parse_module()))
+        .map_err(|_| "Parse error: parser failed on malformed input".to_string())?
+        .map_err(|e| format!("Parse error: {:?}", e))
+}
+
+fn char_reference_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"&#(?:x([0-9a-fA-F]{0,8})|([0-9]{0,9}));").unwrap())
+}
+
+/// Rejects numeric character references in JSX text and attribute strings
+/// that do not name a character. The swc JSX lexer panics on them (`&#;`,
+/// `&#xD800;`, ...); in string literals, comments and the like they are just
+/// text. To find out where they are, the module is parsed with the `#` of
+/// each such reference made an `a`, which the lexer takes for an unknown
+/// named reference.
+fn check_char_references(content: &str) -> Result<(), String> {
+    let invalid: Vec<(usize, &str)> = char_reference_regex()
+        .captures_iter(content)
+        .filter_map(|caps| {
+            let code = match (caps.get(1), caps.get(2)) {
+                (Some(hex), _) => u32::from_str_radix(hex.as_str(), 16),
+                (_, Some(dec)) => dec.as_str().parse(),
+                _ => unreachable!(),
+            };
+            let reference = caps.get(0).unwrap();
+            code.ok().and_then(char::from_u32).is_none().then(|| (reference.start(), reference.as_str()))
+        })
+        .collect();
+    if invalid.is_empty() {
+        return Ok(());
+    }
+
+    let mut masked = content.to_string();
+    for &(start, _) in &invalid {
+        masked.replace_range(start + 1..start + 2, "a");
+    }
+    let cm = Lrc::new(SourceMap::default());
+    let fm = cm.new_source_file(FileName::Anon, masked.clone());
+    let module = parse_file(&masked, &fm, None)?;
+    let mut strings = JsxStrings { source_start: fm.start_pos, spans: Vec::new() };
+    module.visit_with(&mut strings);
+
+    let in_jsx = |start: &usize| strings.spans.iter().any(|span| span.contains(start));
+    match invalid.into_iter().find(|(start, _)| in_jsx(start)) {
+        Some((start, reference)) => Err(format!(
+            "Parse error: invalid character reference '{}' on line {}",
+            reference,
+            content[..start].matches('\n').count() + 1
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Where the JSX text and attribute strings of a module are, the places the
+/// lexer reads character references in
+struct JsxStrings {
+    source_start: BytePos,
+    spans: Vec<std::ops::Range<usize>>,
+}
+
+impl JsxStrings {
+    fn push(&mut self, span: Span) {
+        let start = (span.lo - self.source_start).0 as usize;
+        let end = (span.hi - self.source_start).0 as usize;
+        self.spans.push(start..end);
+    }
+}
+
+impl Visit for JsxStrings {
+    fn visit_jsx_text(&mut self, node: &JSXText) {
+        self.push(node.span);
+    }
+
+    fn visit_jsx_attr_value(&mut self, node: &JSXAttrValue) {
+        if let JSXAttrValue::Lit(Lit::Str(s)) = node {
+            self.push(s.span);
+        }
+        node.visit_children_with(self);
+    }
+}
+
+/// Resets JSX text to its exact source. The lexer decodes entities and doubles
+/// line breaks in JSX text, which the emitter would write back, so every run
+/// would change the file again.
+struct JsxTextRestorer<'a> {
+    cm: &'a SourceMap,
+}
+
+impl<'a> VisitMut for JsxTextRestorer<'a> {
+    fn visit_mut_jsx_text(&mut self, node: &mut JSXText) {
+        if let Ok(source) = self.cm.span_to_snippet(node.span) {
+            node.raw = source.clone().into();
+            node.value = source.into();
+        }
+    }
+}
+
+struct ExistingIdCollector<'a> {
+    attr: &'a str,
+    source_start: BytePos,
+    locations: Vec<IdLocation>,
+}
+
+impl<'a> ExistingIdCollector<'a> {
+    fn push(&mut self, pos: BytePos, tag: String, id: String, attributes: Vec<(String, String)>) {
+        let offset = (pos - self.source_start).0 as usize;
+        self.locations.push(IdLocation::new(offset, tag, id, attributes, self.attr));
+    }
+}
+
+impl<'a> Visit for ExistingIdCollector<'a> {
+    fn visit_jsx_opening_element(&mut self, node: &JSXOpeningElement) {
+        for attr in &node.attrs {
+            if let JSXAttrOrSpread::JSXAttr(attr) = attr {
+                if let JSXAttrName::Ident(ident) = &attr.name {
+                    if ident.sym == self.attr {
+                        if let Some(id) = attr.value.as_ref().and_then(written_attr_id) {
+                            let tag = JsxProcessor::extract_jsx_element_name(&node.name);
+                            let attributes = JsxProcessor::static_attributes(&node.attrs);
+                            self.push(node.span.lo, tag, id.to_string(), attributes);
+                        }
+                    }
+                }
+            }
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, node: &CallExpr) {
+        if let Some(tag) = vue_h_tag(node) {
+            if let Some(props) = vue_h_props(node) {
+                if let Some(id) = object_written_id(props, self.attr) {
+                    self.push(node.span.lo, tag, id, object_static_props(props));
+                }
+            }
+        }
+        node.visit_children_with(self);
+    }
+}
+
+/// Returns the string-literal values of `attr` already present on JSX elements
+/// and Vue `h()` calls.
+pub fn existing_ids(content: &str, attr: &str) -> Result<Vec<String>, String> {
+    Ok(locate_ids(content, attr)?.into_iter().map(|location| location.id).collect())
+}
+
+/// Finds every JSX element and Vue `h()` call carrying `attr`, in source order.
+pub fn locate_ids(content: &str, attr: &str) -> Result<Vec<IdLocation>, String> {
+    with_stack(content, || {
+        let (cm, module) = parse_module(content, None)?;
+        let source_start = cm.files().first().map_or(BytePos(0), |file| file.start_pos);
+        let mut collector = ExistingIdCollector { attr, source_start, locations: Vec::new() };
+        module.visit_with(&mut collector);
+        Ok(collector.locations)
+    })
+}
+
+/// IDs on the JSX of each named export of a Storybook story file, by export
+/// name in source order. The default export, which holds the story metadata,
+/// is left out.
+pub fn story_ids(content: &str, attr: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+    with_stack(content, || {
+        let (cm, module) = parse_module(content, None)?;
+        let source_start = cm.files().first().map_or(BytePos(0), |file| file.start_pos);
+        let ids_in = |node: &dyn Fn(&mut ExistingIdCollector)| {
+            let mut collector = ExistingIdCollector { attr, source_start, locations: Vec::new() };
+            node(&mut collector);
+            collector.locations.into_iter().map(|location| location.id).collect::<Vec<_>>()
+        };
+
+        let mut stories = Vec::new();
+        for item in &module.body {
+            let ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(export)) = item else {
+                continue;
+            };
+            match &export.decl {
+                Decl::Var(var) => {
+                    for decl in &var.decls {
+                        if let Pat::Ident(name) = &decl.name {
+                            stories.push((name.id.sym.to_string(), ids_in(&|c| decl.visit_with(c))));
+                        }
+                    }
+                }
+                Decl::Fn(function) => {
+                    stories.push((function.ident.sym.to_string(), ids_in(&|c| function.visit_with(c))));
+                }
+                _ => {}
+            }
+        }
+        // Storybook's own export listing the stories in order
+        stories.retain(|(name, _)| name != "__namedExportsOrder");
+
+        Ok(stories)
+    })
+}
+
+/// Collects host elements with a literal ID whose children are only text.
+struct ElementTextCollector<'a> {
+    attr: &'a str,
+    texts: Vec<(String, String)>,
+}
+
+impl<'a> Visit for ElementTextCollector<'a> {
+    fn visit_jsx_element(&mut self, node: &JSXElement) {
+        let name = JsxProcessor::extract_jsx_element_name(&node.opening.name);
+        let id = node.opening.attrs.iter().find_map(|attr| match attr {
+            JSXAttrOrSpread::JSXAttr(JSXAttr { name: JSXAttrName::Ident(ident), value: Some(value), .. })
+                if ident.sym == self.attr =>
+            {
+                // Template literal IDs differ per list row
+                match value {
+                    JSXAttrValue::JSXExprContainer(JSXExprContainer { expr: JSXExpr::Expr(expr), .. })
+                        if matches!(&**expr, Expr::Tpl(_)) =>
+                    {
+                        None
+                    }
+                    value => written_attr_id(value),
+                }
+            }
+            _ => None,
+        });
+
+        if let Some(id) = id.filter(|_| JsxProcessor::is_host_element(&name)) {
+            let text = node.children.iter().try_fold(String::new(), |mut text, child| {
+                match child {
+                    JSXElementChild::JSXText(t) => text.push_str(&t.value),
+                    JSXElementChild::JSXExprContainer(JSXExprContainer { expr: JSXExpr::Expr(expr), .. }) => {
+                        match &**expr {
+                            Expr::Lit(Lit::Str(s)) => text.push_str(&s.value),
+                            _ => return None,
+                        }
+                    }
+                    _ => return None,
+                }
+                Some(text)
+            });
+            if let Some(text) = text.map(|text| codemod::normalize_text(&text)).filter(|text| !text.is_empty()) {
+                self.texts.push((id.to_string(), text));
+            }
+        }
+        node.visit_children_with(self);
+    }
+}
+
+/// Returns the text of every host element that carries `attr` and contains
+/// only text, with whitespace collapsed, in source order.
+pub fn element_texts(content: &str, attr: &str) -> Result<Vec<(String, String)>, String> {
+    with_stack(content, || {
+        let (_, module) = parse_module(content, None)?;
+        let mut collector = ElementTextCollector { attr, texts: Vec::new() };
+        module.visit_with(&mut collector);
+        Ok(collector.texts)
+    })
+}
+
+struct TextQueryCollector {
+    source_start: BytePos,
+    queries: Vec<TextQuery>,
+}
+
+impl TextQueryCollector {
+    fn range(&self, span: Span) -> std::ops::Range<usize> {
+        (span.lo - self.source_start).0 as usize..(span.hi - self.source_start).0 as usize
+    }
+}
+
+impl Visit for TextQueryCollector {
+    fn visit_call_expr(&mut self, node: &CallExpr) {
+        let method = match &node.callee {
+            Callee::Expr(callee) => match &**callee {
+                Expr::Ident(ident) => Some(ident),
+                Expr::Member(MemberExpr { prop: MemberProp::Ident(ident), .. }) => Some(ident),
+                _ => None,
+            },
+            _ => None,
+        };
+        let method = method.filter(|method| codemod::TEXT_QUERY_METHODS.contains(&&*method.sym));
+        // Options such as `{ exact: false }` have no `ByTestId` equivalent
+        if let (Some(method), [ExprOrSpread { spread: None, expr }]) = (method, node.args.as_slice()) {
+            if let Expr::Lit(Lit::Str(text)) = &**expr {
+                let quote = text.raw.as_ref().and_then(|raw| raw.chars().next()).unwrap_or('"');
+                self.queries.push(TextQuery {
+                    method: method.sym.to_string(),
+                    method_range: self.range(method.span),
+                    text: text.value.to_string(),
+                    arg_range: self.range(text.span),
+                    quote,
+                });
+            }
+        }
+        node.visit_children_with(self);
+    }
+}
+
+/// Finds Testing Library text queries (`getByText("...")` and friends) in a
+/// test file, in source order.
+pub fn text_queries(content: &str) -> Result<Vec<TextQuery>, String> {
+    with_stack(content, || {
+        let (cm, module) = parse_module(content, None)?;
+        let source_start = cm.files().first().map_or(BytePos(0), |file| file.start_pos);
+        let mut collector = TextQueryCollector { source_start, queries: Vec::new() };
+        module.visit_with(&mut collector);
+        Ok(collector.queries)
+    })
+}
+
+struct IdRemover<'a> {
+    attr: &'a str,
+    ids: &'a HashSet<String>,
+    origin_attr: Option<&'a str>,
+}
+
+impl<'a> VisitMut for IdRemover<'a> {
+    fn visit_mut_jsx_opening_element(&mut self, node: &mut JSXOpeningElement) {
+        let generated = self.origin_attr.is_none_or(|origin| has_attr(node, origin))
+            && node.attrs.iter().any(|attr| match attr {
+                JSXAttrOrSpread::JSXAttr(JSXAttr { name: JSXAttrName::Ident(ident), value: Some(value), .. }) => {
+                    ident.sym == self.attr && written_attr_id(value).is_some_and(|id| self.ids.contains(id))
+                }
+                _ => false,
+            });
+        if generated {
+            node.attrs.retain(|attr| {
+                !matches!(attr, JSXAttrOrSpread::JSXAttr(JSXAttr { name: JSXAttrName::Ident(ident), .. })
+                    if ident.sym == self.attr || self.origin_attr.is_some_and(|origin| ident.sym == origin))
+            });
+        }
+        node.visit_mut_children_with(self);
+    }
+
+    fn visit_mut_call_expr(&mut self, node: &mut CallExpr) {
+        if vue_h_tag(node).is_some() {
+            let generated = vue_h_props(node).is_some_and(|props| {
+                self.origin_attr.is_none_or(|origin| prop_index(props, origin).is_some())
+                    && object_written_id(props, self.attr).is_some_and(|id| self.ids.contains(&id))
+            });
+            if generated {
+                if let Some(Expr::Object(props)) = node.args.get_mut(1).map(|arg| &mut *arg.expr) {
+                    props.props.retain(|prop| {
+                        let key = prop_key(prop);
+                        key.as_deref() != Some(self.attr) && key.as_deref() != self.origin_attr
+                    });
+                }
+            }
+        }
+        node.visit_mut_children_with(self);
+    }
+}
+
+struct PolicyCollector<'a> {
+    selector: &'a Selector,
+    attr: &'a str,
+    source_start: BytePos,
+    path: Vec<SelectorElement>,
+    elements: Vec<PolicyElement>,
+}
+
+impl<'a> Visit for PolicyCollector<'a> {
+    fn visit_jsx_element(&mut self, node: &JSXElement) {
+        let opening = &node.opening;
+        let element = SelectorElement {
+            name: JsxProcessor::extract_jsx_element_name(&opening.name),
+            attributes: JsxProcessor::static_attributes(&opening.attrs),
+        };
+        // An expression is taken to give an ID; only a literal can be seen to be empty
+        let covered = opening.attrs.iter().any(|attr| match attr {
+            JSXAttrOrSpread::JSXAttr(JSXAttr { name: JSXAttrName::Ident(name), value, .. }) if &*name.sym == self.attr => {
+                match value {
+                    Some(JSXAttrValue::Lit(Lit::Str(id))) => !id.value.trim().is_empty(),
+                    Some(JSXAttrValue::JSXExprContainer(JSXExprContainer { expr: JSXExpr::Expr(expr), .. })) => {
+                        !matches!(&**expr, Expr::Lit(Lit::Str(id)) if id.value.trim().is_empty())
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        });
+        let tag = element.name.clone();
+
+        self.path.push(element);
+        if self.selector.matches(&self.path) {
+            let offset = (opening.span.lo - self.source_start).0 as usize;
+            self.elements.push(PolicyElement { offset, tag, covered });
+        }
+        node.visit_children_with(self);
+        self.path.pop();
+    }
+}
+
+struct SelectCollector<'a> {
+    selector: &'a Selector,
+    attr: &'a str,
+    source_start: BytePos,
+    path: Vec<SelectorElement>,
+    elements: Vec<SelectedElement>,
+    /// Indices of the selected elements being visited
+    open: Vec<usize>,
+}
+
+impl<'a> Visit for SelectCollector<'a> {
+    fn visit_jsx_element(&mut self, node: &JSXElement) {
+        let opening = &node.opening;
+        let element = SelectorElement {
+            name: JsxProcessor::extract_jsx_element_name(&opening.name),
+            attributes: JsxProcessor::static_attributes(&opening.attrs),
+        };
+        let tag = element.name.clone();
+
+        self.path.push(element);
+        let selected = self.selector.matches(&self.path);
+        if selected {
+            let id = opening.attrs.iter().find_map(|attr| match attr {
+                JSXAttrOrSpread::JSXAttr(JSXAttr { name: JSXAttrName::Ident(ident), value: Some(value), .. })
+                    if ident.sym == self.attr =>
+                {
+                    written_attr_id(value).map(String::from)
+                }
+                _ => None,
+            });
+            self.open.push(self.elements.len());
+            self.elements.push(SelectedElement {
+                offset: (opening.span.lo - self.source_start).0 as usize,
+                tag,
+                ancestors: self.path[..self.path.len() - 1].iter().map(|element| element.name.clone()).collect(),
+                id,
+                text: String::new(),
+            });
+        }
+        node.visit_children_with(self);
+        if selected {
+            self.open.pop();
+        }
+        self.path.pop();
+    }
+
+    fn visit_jsx_text(&mut self, node: &JSXText) {
+        for &index in &self.open {
+            self.elements[index].text.push_str(&node.value);
+        }
+    }
+}
+
+/// Finds every JSX element matching `selector`, with its ID and the literal
+/// text of its descendants, in source order. Attributes with expression
+/// values are matched by name only.
+pub fn select_elements(content: &str, selector: &Selector, attr: &str) -> Result<Vec<SelectedElement>, String> {
+    with_stack(content, || {
+        let (cm, module) = parse_module(content, None)?;
+        let source_start = cm.files().first().map_or(BytePos(0), |file| file.start_pos);
+        let mut collector = SelectCollector {
+            selector,
+            attr,
+            source_start,
+            path: Vec::new(),
+            elements: Vec::new(),
+            open: Vec::new(),
+        };
+        module.visit_with(&mut collector);
+        Ok(collector.elements)
+    })
+}
+
+struct IdApplier<'a> {
+    attr: &'a str,
+    ids: std::slice::Iter<'a, Option<String>>,
+}
+
+impl<'a> VisitMut for IdApplier<'a> {
+    fn visit_mut_jsx_element(&mut self, node: &mut JSXElement) {
+        if let Some(Some(id)) = self.ids.next() {
+            set_string_attr(&mut node.opening, self.attr, id);
+        }
+        node.visit_mut_children_with(self);
+    }
+}
+
+/// Sets `attr` on the JSX elements whose entry in `ids` is `Some`, replacing
+/// any value they have. `ids` has an entry per element, in the order
+/// `select_elements` reports every element.
+pub fn apply_ids(content: &str, attr: &str, ids: &[Option<String>]) -> Result<String, String> {
+    with_stack(content, || {
+        let comments = SingleThreadedComments::default();
+        let (cm, mut module) = parse_module(content, Some(&comments))?;
+        module.visit_mut_with(&mut IdApplier { attr, ids: ids.iter() });
+        to_code(cm, &module, Some(&comments))
+    })
+}
+
+/// Finds every JSX element matching `policy` (see `Selector`) and whether it
+/// carries `attr`, in source order. Attributes with expression values are
+/// matched by name only.
+pub fn policy_elements(content: &str, policy: &str, attr: &str) -> Result<Vec<PolicyElement>, String> {
+    with_stack(content, || {
+        let selector = Selector::parse(policy)?;
+        let (cm, module) = parse_module(content, None)?;
+        let source_start = cm.files().first().map_or(BytePos(0), |file| file.start_pos);
+        let mut collector = PolicyCollector {
+            selector: &selector,
+            attr,
+            source_start,
+            path: Vec::new(),
+            elements: Vec::new(),
+        };
+        module.visit_with(&mut collector);
+        Ok(collector.elements)
+    })
+}
+
+/// Removes `attr` from every JSX element whose value is one of `ids`, leaving
+/// hand-written values alone. With `origin_attr`, only elements carrying it
+/// count as generated, and they lose it too.
+pub fn remove_ids(
+    content: &str,
+    attr: &str,
+    ids: &HashSet<String>,
+    origin_attr: Option<&str>,
+) -> Result<String, String> {
+    with_stack(content, || {
+        let comments = SingleThreadedComments::default();
+        let (cm, mut module) = parse_module(content, Some(&comments))?;
+        module.visit_mut_with(&mut IdRemover { attr, ids, origin_attr });
+        to_code(cm, &module, Some(&comments))
+    })
+}
+
+/// Prints `module`, with the `comments` parsed along with it so that ignore
+/// markers, directives and pragmas survive for the next run.
+fn to_code(cm: Lrc<SourceMap>, module: &Module, comments: Option<&SingleThreadedComments>) -> Result<String, String> {
+    let Some(comments) = comments else {
+        return emit_node(&cm, module, None);
+    };
+    let mut module = module.clone();
+    module.visit_mut_with(&mut EmptyExprComments { comments });
+    emit_node(&cm, &module, Some(comments))
+}
+
+/// The emitter prints an empty JSX expression such as
+/// `{/* ast-append-ids-ignore-next */}` as `{}`, without its comments. Each
+/// one holding comments becomes an identifier spelled as the comments.
+struct EmptyExprComments<'a> {
+    comments: &'a SingleThreadedComments,
+}
+
+impl VisitMut for EmptyExprComments<'_> {
+    fn visit_mut_jsx_expr_container(&mut self, node: &mut JSXExprContainer) {
+        if let JSXExpr::JSXEmptyExpr(empty) = &node.expr {
+            // The comments trail the `{`
+            let comments = self.comments.take_trailing(node.span.lo + BytePos(1)).unwrap_or_default();
+            if !comments.is_empty() {
+                let text: String = comments
+                    .iter()
+                    .map(|comment| match comment.kind {
+                        CommentKind::Block => format!("/*{}*/", comment.text),
+                        CommentKind::Line => format!("//{}\n", comment.text),
+                    })
+                    .collect();
+                node.expr = JSXExpr::Expr(Box::new(Expr::Ident(Ident::new(text.into(), empty.span))));
+            }
+        }
+        node.visit_mut_children_with(self);
+    }
+}
+
+fn emit_node(
+    cm: &Lrc<SourceMap>,
+    node: &impl swc_core::ecma::codegen::Node,
+    comments: Option<&SingleThreadedComments>,
+) -> Result<String, String> {
+    use swc_core::ecma::codegen::{text_writer::JsWriter, Emitter};
+    
+    let mut buf = Vec::new();
+    let writer = Box::new(JsWriter::new(cm.clone(), "\n", &mut buf, None));
+    
+    let mut emitter = Emitter {
+        cfg: swc_core::ecma::codegen::Config::default(),
+        cm: cm.clone(),
+        comments: comments.map(|c| c as &dyn Comments),
+        wr: writer,
+    };
+    
+    node.emit_with(&mut emitter)
+        .map_err(|e| format!("Code generation error: {}", e))?;
+    String::from_utf8(buf).map_err(|e| format!("UTF-8 conversion error: {}", e))
+}
+
+/// A node processing may change, collected in source order
+enum Tag {
+    Opening(JSXOpeningElement),
+    /// A call that may be a Vue `h()` element
+    Call(CallExpr),
+    /// A template literal whose markup may be tagged as HTML
+    Template(Tpl),
+}
+
+#[derive(Default)]
+struct TagCollector {
+    tags: Vec<Tag>,
+}
+
+impl Visit for TagCollector {
+    fn visit_jsx_opening_element(&mut self, node: &JSXOpeningElement) {
+        self.tags.push(Tag::Opening(node.clone()));
+        node.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, node: &CallExpr) {
+        if vue_h_tag(node).is_some() {
+            self.tags.push(Tag::Call(node.clone()));
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_tpl(&mut self, node: &Tpl) {
+        self.tags.push(Tag::Template(node.clone()));
+        node.visit_children_with(self);
+    }
+}
+
+fn collect_tags(module: &Module) -> Vec<Tag> {
+    let mut collector = TagCollector::default();
+    module.visit_with(&mut collector);
+    collector.tags
+}
+
+/// `content` with the tags that start in `range` and differ between
+/// `original` and `module` (the module before and after processing) replaced
+/// by their emitted form, plus any helper import processing added. The rest
+/// of the source keeps its bytes, unlike a full `to_code`; its comments stay
+/// in place, so tags are emitted without them.
+fn splice_tags(
+    content: &str,
+    cm: &Lrc<SourceMap>,
+    original: &Module,
+    module: &Module,
+    source_start: BytePos,
+    range: &std::ops::Range<usize>,
+) -> Result<String, String> {
+    let offset = |pos: BytePos| (pos - source_start).0 as usize;
+    let span_range = |span: Span| offset(span.lo)..offset(span.hi);
+
+    let mut edits: Vec<(std::ops::Range<usize>, String)> = Vec::new();
+    for (old, new) in collect_tags(original).iter().zip(&collect_tags(module)) {
+        match (old, new) {
+            (Tag::Opening(old), Tag::Opening(new)) if old != new => {
+                edits.push((span_range(old.span), emit_node(cm, new, None)?));
+            }
+            (Tag::Template(old), Tag::Template(new)) if old != new => {
+                edits.push((span_range(old.span), emit_node(cm, new, None)?));
+            }
+            (Tag::Call(old), Tag::Call(new)) if old.args.len() == new.args.len() => {
+                if let (Some(old_props), Some(new_props)) = (old.args.get(1), new.args.get(1)) {
+                    if old_props != new_props {
+                        edits.push((span_range(old_props.expr.span()), emit_node(cm, &new_props.expr, None)?));
+                    }
+                }
+            }
+            // Props created for an `h()` call that had none
+            (Tag::Call(old), Tag::Call(new)) if old.args.len() + 1 == new.args.len() => {
+                let props = emit_node(cm, &new.args[1].expr, None)?;
+                edits.push(match old.args.get(1) {
+                    Some(next) => {
+                        let at = offset(next.span().lo);
+                        (at..at, format!("{}, ", props))
+                    }
+                    None => {
+                        let at = offset(old.args[0].span().hi);
+                        (at..at, format!(", {}", props))
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+    edits.retain(|(span, _)| range.contains(&span.start));
+
+    if let Some(index) = module.body.iter().position(|item| item.span().is_dummy()) {
+        let import = emit_node(cm, &module.body[index], None)?;
+        edits.push(match original.body.get(index) {
+            Some(next) => {
+                let at = offset(next.span().lo);
+                (at..at, format!("{}\n", import.trim_end()))
+            }
+            None => (content.len()..content.len(), format!("\n{}", import.trim_end())),
+        });
+    }
+
+    // A changed tag nested in another, e.g. JSX in an attribute, is already
+    // part of the outer tag's emitted form
+    edits.sort_by_key(|(span, _)| (span.start, span.end));
+    let mut output = String::with_capacity(content.len());
+    let mut copied = 0;
+    for (span, text) in edits {
+        if span.start < copied {
+            continue;
+        }
+        output.push_str(&content[copied..span.start]);
+        output.push_str(&text);
+        copied = span.end;
+    }
+    output.push_str(&content[copied..]);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IdStrategy;
+    use crate::pragma::{self, FilePragma};
+
+    #[test]
+    fn test_jsx_processing() {
+        let mut processor = JsxProcessor::new();
+        let options = IdOptions::default();
+        
+        let input = r#"
+            function App() {
+                return <div><span>Hello</span></div>;
+            }
+        "#;
+        
+        let result = processor.process(input, &options).unwrap();
+        assert!(result.contains("data-ast-id"));
+    }
+
+    #[test]
+    fn test_host_element_detection() {
+        assert!(JsxProcessor::is_host_element("div"));
+        assert!(JsxProcessor::is_host_element("span"));
+        assert!(!JsxProcessor::is_host_element("Component"));
+        assert!(!JsxProcessor::is_host_element("MyComponent"));
+    }
+
+    #[test]
+    fn test_jsx_ignore_markers() {
+        let mut processor = JsxProcessor::new();
+        let options = IdOptions::default();
+
+        let input = r#"
+            const a = (
+                <main>
+                    <div data-ast-ignore><span /></div>
+                    {/* ast-append-ids-ignore-next */}
+                    <section><p /></section>
+                    <footer />
+                </main>
+            );
+            // ast-append-ids-ignore-next
+            const b = <aside />;
+        "#;
+
+        let result = processor.process(input, &options).unwrap();
+        assert_eq!(result.matches("data-ast-id").count(), 2);
+        assert!(result.contains("<main data-ast-id"));
+        assert!(result.contains("<footer data-ast-id"));
+
+        // The markers are kept, so a second run skips the same elements
+        let overwrite = IdOptions { overwrite: true, ..IdOptions::default() };
+        let again = JsxProcessor::new().process(&result, &overwrite).unwrap();
+        assert_eq!(again.matches("ast-append-ids-ignore-next").count(), 2);
+        assert_eq!(again.matches("data-ast-id").count(), 2);
+        assert!(!again.contains("<section data-ast-id"));
+        assert!(!again.contains("<aside data-ast-id"));
+    }
+
+    #[test]
+    fn test_jsx_keeps_file_pragma() {
+        let input = "// ast-append-ids: on\nconst a = <div><span /></div>;\n";
+        let result = JsxProcessor::new().process(input, &IdOptions::default()).unwrap();
+        assert_eq!(pragma::detect_file_pragma(&result, pragma::DEFAULT_PRAGMA_LINES), Some(FilePragma::On));
+        assert_eq!(JsxProcessor::new().process(&result, &IdOptions::default()).unwrap(), result);
+
+        let input = "/* ast-append-ids: off */\nconst a = <div />;\n";
+        let result = JsxProcessor::new().process(input, &IdOptions::default()).unwrap();
+        assert_eq!(pragma::detect_file_pragma(&result, pragma::DEFAULT_PRAGMA_LINES), Some(FilePragma::Off));
+    }
+
+    #[test]
+    fn test_jsx_skip_conditions() {
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+        let input = r#"
+            const App = () => (
+                <main>
+                    {process.env.NODE_ENV === "development" && <DebugPanel><pre /></DebugPanel>}
+                    {(process.env.NODE_ENV === 'development') ? <div /> : <header />}
+                    {!(process.env.NODE_ENV === 'development') ? <nav /> : <aside />}
+                    <footer />
+                </main>
+            );
+            function debug() {
+                if (process.env.NODE_ENV === 'development') {
+                    return <section />;
+                }
+                return null;
+            }
+        "#;
+
+        let plain = JsxProcessor::new().process(input, &options).unwrap();
+        assert!(plain.contains(r#"<pre data-ast-id="el-pre-0-1""#));
+
+        options.skip_conditions = vec!["process.env.NODE_ENV === 'development'".to_string()];
+        let result = JsxProcessor::new().process(input, &options).unwrap();
+        for tag in ["pre", "div", "aside", "section"] {
+            assert!(result.contains(&format!("<{}/>", tag)), "{}", tag);
+        }
+        assert!(result.contains(r#"<header data-ast-id="el-header-0-3""#));
+        assert!(result.contains(r#"<nav data-ast-id="el-nav-0-4""#));
+        assert!(result.contains(r#"<footer data-ast-id="el-footer-0-6""#));
+
+        options.skip_conditions = vec!["a &&".to_string()];
+        assert!(JsxProcessor::new().process(input, &options).is_err());
+    }
+
+    #[test]
+    fn test_jsx_component_attr() {
+        let mut options = IdOptions::default();
+        options.component_attr = Some("data-ast-component".to_string());
+        let input = r#"
+            export default function LoginForm({ error }) {
+                const rows = items.map(item => <li>{item}</li>);
+                if (error) {
+                    return <p>{error}</p>;
+                }
+                return (<form><ul>{rows}</ul></form>);
+            }
+            const Card = memo(({ title }) => <section>{title}</section>);
+            const helper = () => <div />;
+            const Wrapper = () => <Layout><main /></Layout>;
+            class Modal extends React.Component {
+                render() {
+                    return open ? <dialog data-ast-component="Old" /> : <aside />;
+                }
+            }
+        "#;
+
+        let result = JsxProcessor::new().process(input, &options).unwrap();
+        assert_eq!(result.matches(r#"data-ast-component="LoginForm""#).count(), 2);
+        assert!(result.contains(r#"<form data-ast-component="LoginForm""#));
+        assert!(result.contains(r#"<section data-ast-component="Card""#));
+        assert_eq!(result.matches(r#"data-ast-component="Modal""#).count(), 2);
+        assert_eq!(result.matches("data-ast-component").count(), 5);
+
+        let again = JsxProcessor::new().process(&result, &options).unwrap();
+        assert_eq!(again, result);
+    }
+
+    #[test]
+    fn test_jsx_option_directives() {
+        let mut processor = JsxProcessor::new();
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+
+        let input = r#"
+            const App = () => (
+                <main>
+                    {/* ast-append-ids: prefix=modal- */}
+                    <dialog><p>Hi</p></dialog>
+                    <p>Out</p>
+                </main>
+            );
+        "#;
+        let result = processor.process(input, &options).unwrap();
+        assert!(result.contains(r#"<main data-ast-id="el-main-0">"#));
+        assert!(result.contains(r#"<dialog data-ast-id="modal-dialog-0-1">"#));
+        assert!(result.contains(r#"<p data-ast-id="modal-p-0-1-2">"#));
+        assert!(result.contains(r#"<p data-ast-id="el-p-0-3">"#));
+
+        // The directive is kept, so later runs still use its prefix
+        options.overwrite = true;
+        let again = JsxProcessor::new().process(&result, &options).unwrap();
+        assert!(again.contains("ast-append-ids: prefix=modal-"));
+        assert!(again.contains(r#"<dialog data-ast-id="modal-dialog-0-1">"#));
+        assert!(again.contains(r#"<p data-ast-id="modal-p-0-1-2">"#));
+        assert_eq!(again, result);
+
+        let bad = "const a = <div>{/* ast-append-ids: prefx=a- */}<span/></div>;";
+        assert!(JsxProcessor::new().process(bad, &options).is_err());
+    }
+
+    #[cfg(feature = "html")]
+    #[test]
+    fn test_jsx_template_literal_markup() {
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+
+        let input = "const view = html`<div class=${cls}><span>${label}</span></div>`;\nconst css = styled.div`color: red;`;\n";
+        let untouched = JsxProcessor::new().process(input, &options).unwrap();
+        assert!(!untouched.contains("data-ast-id"));
+
+        options.template_tags = vec!["html".to_string()];
+        let result = JsxProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains(r#"html`<div class=${cls} data-ast-id="el-div-0"><span data-ast-id="el-span-0-1">${label}</span></div>`"#));
+        assert!(result.contains("styled.div`color: red;`"));
+    }
+
+    #[test]
+    fn test_vue_h_calls() {
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+        options.vue_h = true;
+
+        let input = "const render = () => h('div', { class: 'card' }, [h('span', 'Hi'), h(Child), h('p', null, 'x'), h('i')]);";
+        let result = JsxProcessor::new().process(input, &options).unwrap();
+        let compact: String = result.split_whitespace().collect();
+        assert!(compact.contains(r#"h('div',{class:'card',"data-ast-id":"el-div-0"},["#));
+        assert!(compact.contains(r#"h('span',{"data-ast-id":"el-span-0-1"},'Hi')"#));
+        assert!(compact.contains(r#"h('p',{"data-ast-id":"el-p-0-2"},'x')"#));
+        assert!(compact.contains(r#"h('i',{"data-ast-id":"el-i-0-3"})"#));
+        assert!(result.contains("h(Child)"));
+
+        let ids = existing_ids(&result, "data-ast-id").unwrap();
+        assert_eq!(ids.len(), 4);
+        let removed = remove_ids(&result, "data-ast-id", &ids.into_iter().collect(), None).unwrap();
+        assert!(!removed.contains("data-ast-id"));
+
+        options.vue_h = false;
+        let result = JsxProcessor::new().process(input, &options).unwrap();
+        assert!(!result.contains("data-ast-id"));
+    }
+
+    #[test]
+    fn test_jsx_dynamic_keys() {
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+        options.dynamic_keys = true;
+
+        let input = "const List = () => <ul>{items.map(item => <li key={item.id}><b>{item.name}</b></li>)}</ul>;";
+        let result = JsxProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains(r#"<ul data-ast-id="el-ul-0">"#));
+        assert!(result.contains("<li key={item.id} data-ast-id={`el-li-0-1-${item.id}`}>"));
+        assert!(result.contains("<b data-ast-id={`el-b-0-1-2-${item.id}`}>"));
+
+        // A second run leaves the dynamic IDs alone
+        let again = JsxProcessor::new().process(&result, &options).unwrap();
+        assert_eq!(again, result);
+
+        let ids = existing_ids(&result, "data-ast-id").unwrap();
+        assert_eq!(ids, vec!["el-ul-0", "el-li-0-1", "el-b-0-1-2"]);
+        let removed = remove_ids(&result, "data-ast-id", &ids.into_iter().collect(), None).unwrap();
+        assert!(!removed.contains("data-ast-id"));
+
+        // Template literal syntax in the ID stays text
+        options.prefix = "`${x}\\-".to_string();
+        let result = JsxProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains(r"data-ast-id={`\`\${x}\\-li-0-1-${item.id}`}"), "{}", result);
+        assert_eq!(existing_ids(&result, "data-ast-id").unwrap()[1], "`${x}\\-li-0-1");
+
+        options.prefix = "el-".to_string();
+        options.dynamic_keys = false;
+        let result = JsxProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains(r#"<li key={item.id} data-ast-id="el-li-0-1">"#));
+    }
+
+    #[test]
+    fn test_jsx_id_helper() {
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+        options.id_helper = Some("aid".to_string());
+
+        let input = "'use client';\nimport React from 'react';\nconst A = () => <div><span>Hi</span></div>;\n";
+        let result = JsxProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains(r#"<div data-ast-id={aid("el-div-0")}>"#));
+        assert!(result.contains(r#"<span data-ast-id={aid("el-span-0-1")}>"#));
+        assert!(!result.contains("import { aid }"));
+
+        options.id_helper_import = Some("@/lib/aid".to_string());
+        let result = JsxProcessor::new().process(input, &options).unwrap();
+        assert!(result.starts_with("'use client';\nimport { aid } from \"@/lib/aid\";\nimport React"));
+
+        // Already imported: no second import, and existing IDs are left alone
+        let again = JsxProcessor::new().process(&result, &options).unwrap();
+        assert_eq!(again, result);
+
+        let ids = existing_ids(&result, "data-ast-id").unwrap();
+        assert_eq!(ids, vec!["el-div-0", "el-span-0-1"]);
+        let removed = remove_ids(&result, "data-ast-id", &ids.into_iter().collect(), None).unwrap();
+        assert!(!removed.contains("data-ast-id"));
+    }
+
+    #[test]
+    fn test_jsx_text_is_preserved() {
+        let mut options = IdOptions::default();
+        options.include = vec!["none".to_string()];
+
+        let input = "const a = <p>\n  1 &lt; 2 &amp;&amp; x\r\n</p>;\n";
+        let result = JsxProcessor::new().process(input, &options).unwrap();
+        assert_eq!(result, input);
+    }
+
+    #[test]
+    fn test_jsx_invalid_char_reference() {
+        let mut processor = JsxProcessor::new();
+        let options = IdOptions::default();
+
+        let err = processor.process("const a = 1;\nconst b = <p>&#xD800;</p>;", &options).unwrap_err();
+        assert_eq!(err, "Parse error: invalid character reference '&#xD800;' on line 2");
+        assert!(processor.process("<p>&#;</p>", &options).is_err());
+        assert!(processor.process("<p>&#x1F600; &#169;</p>", &options).is_ok());
+        let err = processor.process(r#"const b = <p title="&#x110000;" />;"#, &options).unwrap_err();
+        assert!(err.contains("'&#x110000;' on line 1"), "{}", err);
+
+        // Outside JSX they are only text
+        let input = "// &#;\nconst s = '&#xD800;';\nconst b = <p>{`&#x;`}</p>;";
+        let output = processor.process(input, &options).unwrap();
+        assert!(output.contains("'&#xD800;'") && output.contains("<p data-ast-id="), "{}", output);
+    }
+
+    #[test]
+    fn test_jsx_text_matches() {
+        let mut options = IdOptions::default();
+        options.text_matches = Some(r"^\$\d".to_string());
+        options.vue_h = true;
+
+        let input = r#"const a = <p>Total: <b>$5</b><i>{"$7"}</i><u>{price}</u></p>;
+const b = h('span', { class: 'x' }, '$9');
+const c = h('span', '9$');"#;
+        let result = JsxProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains("<p>Total:"));
+        assert!(result.contains("<b data-ast-id="));
+        assert!(result.contains("<i data-ast-id="));
+        assert!(result.contains("<u>"));
+        assert_eq!(result.matches("\"data-ast-id\": ").count(), 1);
+    }
+
+    #[test]
+    fn test_jsx_include_class() {
+        let mut options = IdOptions::default();
+        options.include_class = vec!["btn".to_string(), "card".to_string()];
+        options.vue_h = true;
+
+        let input = r#"const a = <div className="page">
+  <section className="card wide"><a className={"btn"}>Go</a><a className={`btn ${kind}`}>Go</a></section>
+  <a className={`btn-${size}`}>No</a><a className={`x${y}btn`}>No</a><a className={classes}>No</a>
+</div>;
+const b = h('span', { class: `card ${tone}` });"#;
+        let result = JsxProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains(r#"<div className="page">"#));
+        assert!(result.contains(r#"<section className="card wide" data-ast-id="#));
+        assert_eq!(result.matches("<a className={").count(), 5);
+        assert_eq!(result.matches("}>Go</a>").count(), 0);
+        assert_eq!(result.matches("}>No</a>").count(), 3);
+        assert!(result.contains(r#""data-ast-id": "#));
+    }
+
+    #[test]
+    fn test_jsx_range_splices_changed_tags() {
+        let mut options = IdOptions::default();
+        options.vue_h = true;
+        options.id_helper = Some("aid".to_string());
+        options.id_helper_import = Some("@/aid".to_string());
+
+        let input = "import { h } from 'vue';\nexport default () => h('ul',   [\n  h('li', 'One'),\n  h('li', { class: 'b' },   'Two'),\n  <Item   icon={<i />} />,\n]);\n";
+        options.range = Some(ast_common::line_range(input, 3, 5));
+        let output = JsxProcessor::new().process(input, &options).unwrap();
+
+        // The helper import is added, the `ul` outside the range and the
+        // untouched component keep their formatting
+        assert!(output.starts_with("import { aid } from \"@/aid\";\nimport { h } from 'vue';\nexport default () => h('ul',   [\n  h('li', {\n"));
+        assert!(output.contains("\"data-ast-id\": aid(\"el-"));
+        assert!(output.contains("},   'Two'),\n  <Item   icon={<i />} />,\n]);\n"));
+        assert_eq!(output.matches("data-ast-id").count(), 2);
+    }
+
+    #[test]
+    fn test_jsx_selector() {
+        let mut options = IdOptions::default();
+        options.selector = Some(r#"form button, div[className="card"] > a"#.to_string());
+        options.vue_h = true;
+
+        let input = r#"const a = <form><div><button>Save</button></div><a>No</a></form>;
+const b = <div className="card"><a>Go</a><p><a>No</a></p><button>No</button></div>;
+const c = h('form', null, [h('button', 'Go')]);"#;
+        let result = JsxProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains("<form><div><button data-ast-id="));
+        assert!(result.contains(r#"<div className="card"><a data-ast-id="#));
+        assert_eq!(result.matches(">No</").count(), 3);
+        assert!(result.contains("h('form', null, ["));
+        assert_eq!(result.matches(r#""data-ast-id": "#).count(), 1);
+    }
+
+    #[test]
+    fn test_jsx_existing_ids() {
+        let input = r#"const a = <div data-ast-id="keep"><span /></div>;"#;
+        let ids = existing_ids(input, "data-ast-id").unwrap();
+        assert_eq!(ids, vec!["keep".to_string()]);
+    }
+
+    #[test]
+    fn test_jsx_remove_ids() {
+        let input = r#"const a = <div data-ast-id="el-1"><span data-ast-id="hand" /></div>;"#;
+        let ids = HashSet::from(["el-1".to_string()]);
+        let result = remove_ids(input, "data-ast-id", &ids, None).unwrap();
+        assert!(!result.contains("el-1"));
+        assert!(result.contains("data-ast-id=\"hand\""));
+    }
+
+    #[test]
+    fn test_jsx_visitor_in_custom_pass() {
+        // A pass of the embedder's own, run after the IDs are added
+        struct DropClassNames;
+        impl VisitMut for DropClassNames {
+            fn visit_mut_jsx_opening_element(&mut self, node: &mut JSXOpeningElement) {
+                node.attrs.retain(|attr| {
+                    !matches!(attr, JSXAttrOrSpread::JSXAttr(JSXAttr { name: JSXAttrName::Ident(name), .. }) if name.sym == *"className")
+                });
+            }
+        }
+
+        let input = r#"const a = <main className="page"><Card /><p className="lead">Hi</p></main>;"#;
+        let options = IdOptions { strategy: crate::IdStrategy::Path, ..IdOptions::default() };
+        let comments = SingleThreadedComments::default();
+        let (cm, mut module) = parse_module(input, Some(&comments)).unwrap();
+        let source_start = cm.files()[0].start_pos;
+        let mut generator = IdGenerator::new();
+        let mut visitor = JsxVisitor::for_module(&options, &mut generator, input, &comments, source_start).unwrap();
+        visitor.mark_components(&mut module);
+        module.visit_mut_with(&mut visitor);
+        visitor.finish(&mut module).unwrap();
+        module.visit_mut_with(&mut DropClassNames);
+
+        let output = to_code(cm, &module, Some(&comments)).unwrap();
+        assert_eq!(output.trim_end(), JsxProcessor::new().process(input, &options).unwrap().replace(r#" className="page""#, "").replace(r#" className="lead""#, ""));
+        assert!(output.contains(r#"<p data-ast-id="el-p-0-1">Hi</p>"#), "{}", output);
+        assert!(generator.is_used("el-main-0"));
+    }
+
+    #[test]
+    fn test_jsx_deep_typescript() {
+        // Deep documents are parsed as JavaScript first, and as TypeScript
+        // when they are not JavaScript. Comments are read either way.
+        let depth = PLAIN_JSX_DEPTH + 1;
+        let markup = format!(
+            "{}{{/* ast-append-ids-ignore-next */}}<b>x</b><b>y</b>{}",
+            "<i>".repeat(depth),
+            "</i>".repeat(depth)
+        );
+        let options = IdOptions { include: vec!["b".to_string()], ..IdOptions::default() };
+        for input in [format!("const a = {};", markup), format!("const a: JSX.Element = {};", markup)] {
+            let output = JsxProcessor::new().process(&input, &options).unwrap();
+            assert!(output.contains("<b>x</b><b data-ast-id="), "{}", input);
+        }
+    }
+}
@@ -1,31 +1,320 @@
+#![cfg_attr(test, allow(clippy::field_reassign_with_default))]
+
 pub mod id_generator;
+#[cfg(feature = "jsx")]
 pub mod jsx;
+#[cfg(feature = "xml")]
 pub mod xml;
+#[cfg(feature = "html")]
 pub mod html;
 pub mod ast_common;
+pub mod resolved;
+pub mod namespace;
+pub mod id_cache;
+pub mod anchors;
+pub mod manifest;
+pub mod change_log;
+pub mod codemod;
+pub mod compat;
+pub mod find;
+pub mod coverage;
+pub mod detect;
+pub mod diff;
+pub mod edits;
+pub mod explain;
+pub mod extra_attrs;
+pub mod gen_selectors;
+pub mod limits;
+pub mod localize;
+pub mod patch;
+pub mod report;
+pub mod runtime;
+pub mod select;
+pub mod selector;
+pub mod snapshot;
+pub mod storybook;
+pub mod scheme;
+pub mod strategy;
+pub mod suggest;
+pub mod sync;
+pub mod taxonomy;
+pub mod pragma;
+pub mod warnings;
+pub mod watch;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod file_io;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sink;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod project;
 
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::rc::Rc;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct IdOptions {
+    /// Attribute the ID is written to
     pub attr: String,
     pub strategy: IdStrategy,
+    /// Prepended to every generated ID
     pub prefix: String,
+    /// Replace IDs elements already have
     pub overwrite: bool,
+    /// Only tag elements matching this CSS selector; XML and JSX support the
+    /// subset of `selector::Selector`
     pub selector: Option<String>,
+    /// Only tag these element names
     pub include: Vec<String>,
+    /// Never tag these element names
     pub exclude: Vec<String>,
+    /// Only tag elements with at least one of these classes in `className`
+    /// (or `class`): string literals and the static words of template
+    /// literals are matched
+    #[serde(default)]
+    pub include_class: Vec<String>,
+    /// Assign IDs inside `<template>` content, including declarative shadow
+    /// roots (HTML only)
+    #[serde(default = "default_true")]
+    pub templates: bool,
+    /// Leave `<code>` elements and their content alone, like that of `<pre>`,
+    /// which is never tagged; documentation embeds markup samples in both
+    /// (HTML only)
+    #[serde(default)]
+    pub skip_code: bool,
+    /// Tags of template literals whose markup is processed as HTML, e.g.
+    /// `html` for lit-html (JSX only, empty to disable)
+    #[serde(default)]
+    pub template_tags: Vec<String>,
+    /// Treat Vue `h('div', props, children)` calls like JSX elements (JSX only)
+    #[serde(default)]
+    pub vue_h: bool,
+    /// Inside `.map()` callbacks, append the element's `key` expression to the ID
+    /// with a template literal so list rows get distinct IDs (JSX only)
+    #[serde(default)]
+    pub dynamic_keys: bool,
+    /// Write IDs as `attr={helper("el-...")}` calls to this function (JSX only)
+    #[serde(default)]
+    pub id_helper: Option<String>,
+    /// Module to import `id_helper` from when a file uses it but does not
+    /// import it yet (JSX only)
+    #[serde(default)]
+    pub id_helper_import: Option<String>,
+    /// Leave out JSX rendered only when one of these expressions holds, e.g.
+    /// `process.env.NODE_ENV === 'development'`, so dev-only markup does not
+    /// shift the IDs of other elements (JSX only)
+    #[serde(default)]
+    pub skip_conditions: Vec<String>,
+    /// Also write each component's name to this attribute, e.g.
+    /// `data-ast-component`, on the root element it returns (JSX only)
+    #[serde(default)]
+    pub component_attr: Option<String>,
+    /// Attribute naming the component an element is the root of, e.g.
+    /// `data-ast-component`. Counter and path restart at each such element,
+    /// leaving the elements outside as if the component were not there, and
+    /// its IDs get the component's name after the prefix. JSX marks component
+    /// roots with it like with `component_attr`, so their rendered HTML gets
+    /// the same IDs; see `for_ssr`.
+    #[serde(default)]
+    pub component_scope: Option<String>,
+    /// Environment token, e.g. `staging:`, prepended to generated IDs when they
+    /// are written. It is left out of uniqueness checks and recorded
+    /// assignments, so every environment gets the same IDs behind its token.
+    #[serde(default)]
+    pub env_prefix: String,
+    /// Levels of the taxonomy strategy as `name=selector list`, outermost
+    /// first, e.g. `area=header, nav, main`. Empty for the default
+    /// area/section/component levels.
+    #[serde(default)]
+    pub taxonomy_levels: Vec<String>,
+    /// Only tag elements whose direct text (not that of child elements)
+    /// matches this regular expression, e.g. a currency amount
+    #[serde(default)]
+    pub text_matches: Option<String>,
+    /// Anchor the comments this regular expression matches, e.g.
+    /// `^\s*section:`, with an ID in a processing instruction right after
+    /// each, `<?ast-anchor data-ast-id="..."?>`. The comment's text after the
+    /// match is what slug IDs are made from. (XML only)
+    #[serde(default)]
+    pub comment_anchors: Option<String>,
+    /// Only tag elements whose start tag begins in this byte range of the
+    /// input, leaving the rest of the document byte-identical; for editors
+    /// tagging a selection
+    #[serde(default)]
+    pub range: Option<std::ops::Range<usize>>,
+    /// Also write where each generated ID comes from to this attribute, e.g.
+    /// `data-ast-id-origin="ast-append-ids@0.1.0:hash"`. IDs without it are
+    /// then taken as written by hand: `overwrite` and `undo` leave them alone.
+    #[serde(default)]
+    pub origin_attr: Option<String>,
+    /// More attributes written next to each generated ID, as
+    /// `name=template`, e.g. `data-ast-depth={depth}`; see
+    /// `extra_attrs::AttrTemplate` for the placeholders
+    #[serde(default)]
+    pub extra_attrs: Vec<String>,
+    /// Scheme hash IDs are computed with; `migrate-scheme` moves documents
+    /// tagged with one scheme to another
+    #[serde(default)]
+    pub scheme: IdScheme,
+    /// Mixed into `v2` hashes, so IDs cannot be worked out from the markup
+    /// alone
+    #[serde(default)]
+    pub salt: String,
+    /// Cut hash IDs to this many characters, 4 at least. An element whose
+    /// short hash is taken already gets a character more, up to the scheme's
+    /// full hash, so only colliding elements get longer IDs; which of them
+    /// does depends on the order they come in.
+    #[serde(default)]
+    pub hash_length: Option<usize>,
+    /// Whether tag names in `include`, `exclude` and selectors match
+    /// regardless of case
+    #[serde(default)]
+    pub name_case: NameCase,
+    /// Which elements advance the counter that path and hash IDs take an
+    /// element's position from
+    #[serde(default)]
+    pub counter: CounterMode,
+    /// Record the tool's version and `options_hash` in the document, so
+    /// systems downstream can check which configuration tagged it (XML only)
+    #[serde(default)]
+    pub metadata: RunMetadata,
+    /// Seconds since the Unix epoch recorded with `metadata`. Left out unless
+    /// set, so that tagging the same input twice gives the same output.
+    #[serde(default)]
+    pub metadata_timestamp: Option<u64>,
+    /// Where the manifest of the run is served, written to a `<meta
+    /// name="ast-append-ids">` in the `<head>` with a hash of the page's IDs,
+    /// so runtime tooling can find the IDs' manifest (HTML only; see
+    /// `html::write_manifest_meta`)
+    #[serde(default)]
+    pub manifest_meta: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// How IDs are generated
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum IdStrategy {
+    /// Hash of the element's tag and path, computed as `IdScheme` says
     Hash,
+    /// Slug of the element's text
     Slug,
+    /// Tag followed by the element's path, e.g. `div-0-3`
     Path,
+    /// `area.section.component.tag-index`, from the labels of the enclosing
+    /// landmarks, sections and components
+    Taxonomy,
+}
+
+impl IdStrategy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            IdStrategy::Hash => "hash",
+            IdStrategy::Slug => "slug",
+            IdStrategy::Path => "path",
+            IdStrategy::Taxonomy => "taxonomy",
+        }
+    }
+}
+
+/// Version of the scheme hash IDs are computed with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum IdScheme {
+    /// 8 hex characters of the SHA-256 of the element's tag and path
+    #[default]
+    V1,
+    /// 11 base62 characters of the SHA-256 of the salt, tag and path
+    V2,
+}
+
+impl IdScheme {
+    pub fn is_v1(&self) -> bool {
+        *self == IdScheme::V1
+    }
+
+    /// Characters in a full hash of the scheme
+    pub fn hash_len(&self) -> usize {
+        match self {
+            IdScheme::V1 => 8,
+            IdScheme::V2 => 11,
+        }
+    }
+}
+
+/// How tag names are compared when filtering and selecting elements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum NameCase {
+    /// As the format does: HTML ignores case, XML and JSX do not
+    #[default]
+    Auto,
+    /// `DIV` only matches `DIV`
+    Sensitive,
+    /// `DIV` matches `div` and `Div`
+    Insensitive,
+}
+
+impl NameCase {
+    /// `Auto` settled for a format whose names do or do not ignore case
+    pub fn resolve(self, format_ignores_case: bool) -> Self {
+        match self {
+            NameCase::Auto if format_ignores_case => NameCase::Insensitive,
+            NameCase::Auto => NameCase::Sensitive,
+            name_case => name_case,
+        }
+    }
+
+    pub fn ignores_case(&self) -> bool {
+        *self == NameCase::Insensitive
+    }
+}
+
+/// Which elements count towards the position of later elements. An element's
+/// path is the counter value at each enclosing element and at itself.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CounterMode {
+    /// Every element in the source, whether it gets an ID or not, so the same
+    /// tree gets the same paths in JSX, XML and HTML and filters, selectors
+    /// and ranges do not shift the IDs of other elements
+    #[default]
+    All,
+    /// Only elements that get an ID
+    Processed,
+}
+
+impl CounterMode {
+    pub fn counts_all(&self) -> bool {
+        *self == CounterMode::All
+    }
+}
+
+/// Where a document records the run that tagged it. A record left by an
+/// earlier run is replaced, not added to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RunMetadata {
+    #[default]
+    Off,
+    /// `<?ast-append-ids version="0.1.0" options="..."?>` before the root
+    /// element
+    Pi,
+    /// `data-ast-run="ast-append-ids@0.1.0; options=..."` on the root element
+    Attribute,
 }
 
 impl Default for IdOptions {
@@ -38,20 +327,381 @@ impl Default for IdOptions {
             selector: None,
             include: Vec::new(),
             exclude: Vec::new(),
+            include_class: Vec::new(),
+            templates: true,
+            skip_code: false,
+            template_tags: Vec::new(),
+            vue_h: false,
+            dynamic_keys: false,
+            id_helper: None,
+            id_helper_import: None,
+            skip_conditions: Vec::new(),
+            component_attr: None,
+            component_scope: None,
+            env_prefix: String::new(),
+            taxonomy_levels: Vec::new(),
+            text_matches: None,
+            comment_anchors: None,
+            range: None,
+            origin_attr: None,
+            extra_attrs: Vec::new(),
+            scheme: IdScheme::V1,
+            salt: String::new(),
+            hash_length: None,
+            name_case: NameCase::Auto,
+            counter: CounterMode::All,
+            metadata: RunMetadata::Off,
+            metadata_timestamp: None,
+            manifest_meta: None,
         }
     }
 }
 
+impl IdOptions {
+    /// JSON Schema of the options, for validating config files and options
+    /// passed from JavaScript before processing.
+    pub fn json_schema() -> schemars::schema::RootSchema {
+        schemars::schema_for!(IdOptions)
+    }
+
+    /// Checks the options up front, so a mistake is reported once, naming the
+    /// option, rather than for every document or not at all.
+    pub fn validate(&self) -> Result<(), String> {
+        let invalid = |name: &str, reason: &dyn std::fmt::Display| format!("Invalid option '{}': {}", name, reason);
+
+        check_attribute_name(&self.attr).map_err(|e| invalid("attr", &e))?;
+        if let Some(attr) = &self.component_attr {
+            check_attribute_name(attr).map_err(|e| invalid("component_attr", &e))?;
+        }
+        if let Some(attr) = &self.component_scope {
+            check_attribute_name(attr).map_err(|e| invalid("component_scope", &e))?;
+        }
+        if let Some(attr) = &self.origin_attr {
+            check_attribute_name(attr).map_err(|e| invalid("origin_attr", &e))?;
+            if *attr == self.attr {
+                return Err(invalid("origin_attr", &"must differ from attr"));
+            }
+        }
+        for (name, prefix) in [("prefix", &self.prefix), ("env_prefix", &self.env_prefix)] {
+            if prefix.chars().any(char::is_whitespace) {
+                return Err(invalid(name, &"must not contain whitespace"));
+            }
+        }
+        if !self.salt.is_empty() && self.scheme.is_v1() {
+            return Err(invalid("salt", &"requires scheme v2"));
+        }
+        if let Some(length) = self.hash_length {
+            let full = self.scheme.hash_len();
+            if !(4..=full).contains(&length) {
+                return Err(invalid("hash_length", &format!("must be from 4 to {} with this scheme", full)));
+            }
+        }
+        if let Some(selector) = &self.selector {
+            check_selector(selector).map_err(|e| invalid("selector", &e))?;
+        }
+        if self.manifest_meta.as_ref().is_some_and(|reference| reference.trim().is_empty()) {
+            return Err(invalid("manifest_meta", &"must not be empty"));
+        }
+        if let Some(range) = &self.range {
+            if range.start > range.end {
+                return Err(invalid("range", &format!("start {} is after end {}", range.start, range.end)));
+            }
+        }
+
+        let lists = [
+            ("include", &self.include),
+            ("exclude", &self.exclude),
+            ("include_class", &self.include_class),
+            ("template_tags", &self.template_tags),
+            ("skip_conditions", &self.skip_conditions),
+        ];
+        for (name, list) in lists {
+            if list.iter().any(|entry| entry.trim().is_empty()) {
+                return Err(invalid(name, &"must not contain empty entries"));
+            }
+        }
+        // Exclude wins, so a tag in both lists would quietly get nothing
+        if let Some(tag) = self.include.iter().find(|tag| self.exclude.contains(tag)) {
+            return Err(invalid("exclude", &format!("'{}' is also in include", tag)));
+        }
+
+        if let Some(helper) = &self.id_helper {
+            let mut chars = helper.chars();
+            let is_identifier = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+                && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+            if !is_identifier {
+                return Err(invalid("id_helper", &format!("'{}' is not a JavaScript identifier", helper)));
+            }
+        } else if self.id_helper_import.is_some() {
+            return Err(invalid("id_helper_import", &"requires id_helper"));
+        }
+
+        if let Some(pattern) = &self.text_matches {
+            regex::Regex::new(pattern).map_err(|e| invalid("text_matches", &e))?;
+        }
+        if let Some(pattern) = &self.comment_anchors {
+            regex::Regex::new(pattern).map_err(|e| invalid("comment_anchors", &e))?;
+        }
+        let mut written: Vec<String> = std::iter::once(&self.attr).chain(&self.origin_attr).cloned().collect();
+        for template in extra_attrs::parse_all(&self.extra_attrs).map_err(|e| invalid("extra_attrs", &e))? {
+            check_attribute_name(&template.name).map_err(|e| invalid("extra_attrs", &e))?;
+            if written.contains(&template.name) {
+                return Err(invalid("extra_attrs", &format!("'{}' is written already", template.name)));
+            }
+            written.push(template.name);
+        }
+        if !self.taxonomy_levels.is_empty() {
+            taxonomy::TaxonomyTracker::new(&self.taxonomy_levels).map_err(|e| invalid("taxonomy_levels", &e))?;
+        }
+        Ok(())
+    }
+
+    /// These options set up so that the IDs a component's JSX gets at build
+    /// time are the IDs its server-rendered HTML gets: every element counts
+    /// and each component, marked with `attr`, is a scope of its own. Process
+    /// both formats with the same options, `salt` and `scheme` included.
+    pub fn for_ssr(self, attr: &str) -> Self {
+        Self { counter: CounterMode::All, component_scope: Some(attr.to_string()), ..self }
+    }
+
+    /// Hash of the options that decide which IDs a document gets, 12 hex
+    /// characters; `metadata` records it. `range` and the metadata options
+    /// themselves are left out.
+    pub fn options_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+        let options = Self { range: None, metadata: RunMetadata::Off, metadata_timestamp: None, ..self.clone() };
+        let json = serde_json::to_string(&options).expect("options serialize to JSON");
+        let hash = format!("{:x}", Sha256::digest(json.as_bytes()));
+        hash[..12].to_string()
+    }
+
+    /// The attribute value written for a generated `id`.
+    pub fn with_env_prefix(&self, id: &str) -> String {
+        format!("{}{}", self.env_prefix, id)
+    }
+
+    /// The generated ID behind an attribute value, for reserving values that
+    /// are already in a document.
+    pub fn without_env_prefix<'a>(&self, value: &'a str) -> &'a str {
+        value.strip_prefix(self.env_prefix.as_str()).unwrap_or(value)
+    }
+}
+
+fn check_attribute_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("must not be empty".to_string());
+    }
+    match name.chars().find(|&c| c.is_whitespace() || c.is_control() || "\"'<>/=".contains(c)) {
+        Some(c) => Err(format!("'{}' is not a valid attribute name (contains {:?})", name, c)),
+        None => Ok(()),
+    }
+}
+
+/// HTML takes any selector lol_html supports; without HTML support only the
+/// subset of `selector::Selector` can be used.
+fn check_selector(selector: &str) -> Result<(), String> {
+    #[cfg(feature = "html")]
+    return selector
+        .parse::<lol_html::Selector>()
+        .map(drop)
+        .map_err(|e| format!("'{}' is not a valid selector: {}", selector, e));
+    #[cfg(not(feature = "html"))]
+    return selector::Selector::parse(selector).map(drop);
+}
+
+/// A processor for one format. Object safe, so `processor_for` can pick one
+/// at run time.
 pub trait AstProcessor {
-    fn process(&mut self, content: &str, options: &IdOptions) -> Result<String, String>;
+    /// Adds IDs to `content`, also telling what it did
+    fn process_with_meta(&mut self, content: &str, options: &IdOptions) -> Result<ProcessOutput, String>;
+
+    /// `process_with_meta` with `options` compiled up front for the format,
+    /// e.g. once for all documents processed with them
+    fn process_resolved(
+        &mut self,
+        content: &str,
+        options: &IdOptions,
+        resolved: &Rc<resolved::ResolvedOptions>,
+    ) -> Result<ProcessOutput, String>;
+
+    /// The generator IDs are drawn from, e.g. to seed it with reserved IDs or
+    /// to move it to the processor of the next file
+    fn generator_mut(&mut self) -> &mut id_generator::IdGenerator;
+
+    /// Adds IDs to `content`, returning only the result
+    fn process(&mut self, content: &str, options: &IdOptions) -> Result<String, String> {
+        self.process_with_meta(content, options).map(|output| output.content)
+    }
 }
 
+/// Result of `process_with_meta`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessOutput {
+    pub content: String,
+    /// Elements that got a new ID, including overwritten ones; 0 when the
+    /// document was left as it was
+    pub ids_added: usize,
+    /// Elements walked, whether they got an ID or not
+    pub elements_visited: usize,
+    /// Suspicious things noticed on the way, for the caller to show
+    pub warnings: Vec<warnings::Warning>,
+}
 
-#[cfg(target_arch = "wasm32")]
+/// A new processor for `format`. Fails for formats without a processor
+/// (`Script`, `Vue`) and those left out of this build.
+pub fn processor_for(format: detect::Format) -> Result<Box<dyn AstProcessor>, String> {
+    match format {
+        #[cfg(feature = "jsx")]
+        detect::Format::Jsx => Ok(Box::new(jsx::JsxProcessor::new())),
+        #[cfg(feature = "xml")]
+        detect::Format::Xml => Ok(Box::new(xml::XmlProcessor::new())),
+        #[cfg(feature = "html")]
+        detect::Format::Html => Ok(Box::new(html::HtmlProcessor::new())),
+        #[allow(unreachable_patterns)]
+        detect::Format::Jsx | detect::Format::Xml | detect::Format::Html => Err(format!(
+            "{} support is not included in this build",
+            format.name().to_uppercase()
+        )),
+        detect::Format::Script | detect::Format::Vue => Err(format!("No processor for {} files", format.name())),
+    }
+}
+
+/// Every format behind one entry point, for front-ends that take any file:
+/// picks the processor for the format, or detects the format first. The
+/// processors are made on first use and kept, and all of them draw IDs from
+/// one generator, so IDs stay unique across the documents processed whatever
+/// their format.
+#[derive(Default)]
+pub struct Processor {
+    generator: id_generator::IdGenerator,
+    processors: Vec<(detect::Format, Box<dyn AstProcessor>)>,
+}
+
+impl Processor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws IDs from `generator`, e.g. one seeded with reserved IDs
+    pub fn with_generator(generator: id_generator::IdGenerator) -> Self {
+        Self { generator, processors: Vec::new() }
+    }
+
+    pub fn generator_mut(&mut self) -> &mut id_generator::IdGenerator {
+        &mut self.generator
+    }
+
+    pub fn into_generator(self) -> id_generator::IdGenerator {
+        self.generator
+    }
+
+    /// Adds IDs to `content` as `format`, also telling what it did. Scripts
+    /// without JSX and Vue files, which no processor handles, come back as
+    /// they are; formats left out of this build are an error.
+    pub fn process_with_meta(
+        &mut self,
+        format: detect::Format,
+        content: &str,
+        options: &IdOptions,
+    ) -> Result<ProcessOutput, String> {
+        self.with_processor(format, content, |processor| processor.process_with_meta(content, options))
+    }
+
+    /// `process_with_meta` with `options` compiled up front for `format`
+    /// with `ResolvedOptions::new`, e.g. once for all files of a run
+    pub fn process_resolved(
+        &mut self,
+        format: detect::Format,
+        content: &str,
+        options: &IdOptions,
+        resolved: &Rc<resolved::ResolvedOptions>,
+    ) -> Result<ProcessOutput, String> {
+        self.with_processor(format, content, |processor| processor.process_resolved(content, options, resolved))
+    }
+
+    /// Runs `process` with the processor for `format`, drawing IDs from the
+    /// shared generator
+    fn with_processor(
+        &mut self,
+        format: detect::Format,
+        content: &str,
+        process: impl FnOnce(&mut dyn AstProcessor) -> Result<ProcessOutput, String>,
+    ) -> Result<ProcessOutput, String> {
+        if matches!(format, detect::Format::Script | detect::Format::Vue) {
+            return Ok(ProcessOutput { content: content.to_string(), ..ProcessOutput::default() });
+        }
+        let index = match self.processors.iter().position(|(kind, _)| *kind == format) {
+            Some(index) => index,
+            None => {
+                self.processors.push((format, processor_for(format)?));
+                self.processors.len() - 1
+            }
+        };
+        let processor = &mut self.processors[index].1;
+        std::mem::swap(processor.generator_mut(), &mut self.generator);
+        let output = process(processor.as_mut());
+        std::mem::swap(processor.generator_mut(), &mut self.generator);
+        output
+    }
+
+    /// Adds IDs to `content` as `format`, returning only the result
+    pub fn process(&mut self, format: detect::Format, content: &str, options: &IdOptions) -> Result<String, String> {
+        self.process_with_meta(format, content, options).map(|output| output.content)
+    }
+
+    /// Works out the format of `content` with `detect::detect`, from the
+    /// extension of `path` too when given, and processes it as that
+    pub fn process_auto(
+        &mut self,
+        path: Option<&std::path::Path>,
+        content: &str,
+        options: &IdOptions,
+    ) -> Result<(detect::Format, ProcessOutput), String> {
+        let format = detect::detect(path, content).format;
+        self.process_with_meta(format, content, options).map(|output| (format, output))
+    }
+}
+
+// SAFETY: wasm32 builds run single-threaded
+#[cfg(all(target_arch = "wasm32", feature = "talc"))]
 #[global_allocator]
-static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
+static ALLOC: talc::TalckWasm = unsafe { talc::TalckWasm::new_global() };
 
 #[cfg(target_arch = "wasm32")]
 pub fn set_panic_hook() {
     console_error_panic_hook::set_once();
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use detect::Format;
+
+    #[test]
+    fn test_processors_follow_features() {
+        let cases = [
+            (Format::Jsx, cfg!(feature = "jsx"), "const A = () => <p>Hi</p>;"),
+            (Format::Html, cfg!(feature = "html"), "<p>Hi</p>"),
+            (Format::Xml, cfg!(feature = "xml"), "<p>Hi</p>"),
+        ];
+        for (format, built, input) in cases {
+            match processor_for(format) {
+                Ok(mut processor) => {
+                    assert!(built, "{:?}", format);
+                    assert!(processor.process(input, &IdOptions::default()).unwrap().contains("data-ast-id"));
+                }
+                Err(e) => {
+                    assert!(!built, "{:?}", format);
+                    assert_eq!(e, format!("{} support is not included in this build", format.name().to_uppercase()));
+                }
+            }
+            // The facade fails the same way for formats left out
+            let result = Processor::new().process(format, input, &IdOptions::default());
+            assert_eq!(result.is_ok(), built, "{:?}", format);
+        }
+
+        // Selectors only HTML's engine takes are checked by the subset otherwise
+        let options = IdOptions { selector: Some("p:nth-of-type(2)".to_string()), ..IdOptions::default() };
+        assert_eq!(options.validate().is_ok(), cfg!(feature = "html"));
+    }
+}
@@ -0,0 +1,129 @@
+/// Safety limits on a single input, checked before it is parsed so a
+/// pathological document cannot exhaust memory or time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Largest input accepted, in bytes
+    pub max_file_size: Option<u64>,
+    /// Most elements accepted, as counted by `count_elements`
+    pub max_elements: Option<usize>,
+}
+
+impl Limits {
+    /// Checks an input of `size` bytes, before it is read.
+    pub fn check_size(&self, size: u64) -> Result<(), String> {
+        match self.max_file_size {
+            Some(max) if size > max => Err(format!("{} bytes exceeds the limit of {} bytes", size, max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the number of elements in `content`, before it is parsed.
+    pub fn check_elements(&self, content: &str) -> Result<(), String> {
+        let Some(max) = self.max_elements else {
+            return Ok(());
+        };
+        // Counting stops at the first element over the limit
+        let count = count_elements_up_to(content, max + 1);
+        if count > max {
+            return Err(format!("more than {} elements", max));
+        }
+        Ok(())
+    }
+}
+
+/// Number of start tags in `content`, found by a quick scan for `<` followed
+/// by a letter rather than by parsing, so it may overcount in scripts with
+/// comparisons like `a<b`.
+pub fn count_elements(content: &str) -> usize {
+    count_elements_up_to(content, usize::MAX)
+}
+
+/// Deepest nesting of tags in `content`, by the same kind of scan as
+/// `count_elements`: `<` followed by a letter, or `<>`, opens a level, and
+/// `</` or `/>` closes one. Comparisons and type arguments in scripts count
+/// as levels, so this is an upper bound for well-formed JSX and XML.
+pub fn nesting_depth(content: &str) -> usize {
+    let bytes = content.as_bytes();
+    let mut depth = 0usize;
+    let mut deepest = 0;
+    let mut i = 0;
+    while i + 1 < bytes.len() {
+        match &bytes[i..i + 2] {
+            // Skipping the `/` keeps `</>` from closing twice
+            [b'<', b'/'] | [b'/', b'>'] => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+            }
+            [b'<', next] if next.is_ascii_alphabetic() || *next == b'>' => {
+                depth += 1;
+                deepest = deepest.max(depth);
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    deepest
+}
+
+fn count_elements_up_to(content: &str, limit: usize) -> usize {
+    content
+        .as_bytes()
+        .windows(2)
+        .filter(|pair| pair[0] == b'<' && pair[1].is_ascii_alphabetic())
+        .take(limit)
+        .count()
+}
+
+/// Parses a size such as `1048576`, `512K`, `10M` or `1G` (binary multiples;
+/// a trailing `B` or `iB` is accepted).
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let invalid = || format!("invalid size '{}' (expected e.g. 500K, 10M or 1G)", value);
+    let trimmed = value.trim();
+    let unit_start = trimmed.find(|c: char| !c.is_ascii_digit()).unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(unit_start);
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let unit = unit.trim_start().to_ascii_uppercase();
+    let multiplier: u64 = match unit.trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return Err(invalid()),
+    };
+    number.checked_mul(multiplier).ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits() {
+        let limits = Limits { max_file_size: Some(10), max_elements: Some(2) };
+        assert!(limits.check_size(10).is_ok());
+        assert_eq!(limits.check_size(11).unwrap_err(), "11 bytes exceeds the limit of 10 bytes");
+
+        assert_eq!(count_elements("<div><p>a < b</p><br/></div>"), 3);
+        assert!(limits.check_elements("<div><p>a</p></div>").is_ok());
+        assert_eq!(limits.check_elements("<ul><li/><li/></ul>").unwrap_err(), "more than 2 elements");
+        assert!(Limits::default().check_elements(&"<p>".repeat(100)).is_ok());
+    }
+
+    #[test]
+    fn test_nesting_depth() {
+        assert_eq!(nesting_depth("<div><p>a</p><p><br/><>b</></p></div>"), 3);
+        assert_eq!(nesting_depth("<a/><b/><c></c>"), 1);
+        assert_eq!(nesting_depth("</a></b><c>"), 1);
+        assert_eq!(nesting_depth("a < b"), 0);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1048576"), Ok(1 << 20));
+        assert_eq!(parse_size("512K"), Ok(512 << 10));
+        assert_eq!(parse_size("10mb"), Ok(10 << 20));
+        assert_eq!(parse_size("1GiB"), Ok(1 << 30));
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("M").is_err());
+    }
+}
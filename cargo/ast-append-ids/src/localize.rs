@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+
+/// Text of an element keyed by its ID, as exported for translation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextEntry {
+    /// File the text was exported from. Only there for context; imports are
+    /// keyed by ID alone.
+    #[serde(default)]
+    pub file: String,
+    pub id: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextFormat {
+    Csv,
+    Json,
+}
+
+impl TextFormat {
+    /// CSV for a `.csv` path, JSON otherwise.
+    pub fn from_path(path: &str) -> Self {
+        if path.to_ascii_lowercase().ends_with(".csv") {
+            Self::Csv
+        } else {
+            Self::Json
+        }
+    }
+
+    pub fn write(self, entries: &[TextEntry]) -> Result<String, String> {
+        match self {
+            Self::Csv => Ok(to_csv(entries)),
+            Self::Json => serde_json::to_string_pretty(entries)
+                .map(|json| json + "\n")
+                .map_err(|e| format!("Failed to serialize texts: {}", e)),
+        }
+    }
+
+    pub fn read(self, content: &str) -> Result<Vec<TextEntry>, String> {
+        match self {
+            Self::Csv => from_csv(content),
+            Self::Json => serde_json::from_str(content).map_err(|e| format!("Invalid texts JSON: {}", e)),
+        }
+    }
+}
+
+const CSV_HEADER: [&str; 3] = ["file", "id", "text"];
+
+fn to_csv(entries: &[TextEntry]) -> String {
+    let mut csv = CSV_HEADER.join(",") + "\n";
+    for entry in entries {
+        let fields = [&entry.file, &entry.id, &entry.text].map(|field| csv_field(field));
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Parses RFC 4180 CSV with a header row naming at least the `id` and `text`
+/// columns, in any order.
+fn from_csv(content: &str) -> Result<Vec<TextEntry>, String> {
+    let mut records = parse_csv(content)?.into_iter();
+    let header = records.next().ok_or_else(|| "Texts CSV is empty".to_string())?;
+    let column = |name: &str| header.iter().position(|field| field.trim() == name);
+    let (Some(id), Some(text)) = (column("id"), column("text")) else {
+        return Err("Texts CSV needs 'id' and 'text' columns".to_string());
+    };
+    let file = column("file");
+
+    records
+        .enumerate()
+        .filter(|(_, record)| record.iter().any(|field| !field.is_empty()))
+        .map(|(index, record)| {
+            let field = |column: usize| {
+                record
+                    .get(column)
+                    .cloned()
+                    .ok_or_else(|| format!("Texts CSV row {} has too few fields", index + 2))
+            };
+            Ok(TextEntry {
+                file: file.map(field).transpose()?.unwrap_or_default(),
+                id: field(id)?,
+                text: field(text)?,
+            })
+        })
+        .collect()
+}
+
+fn parse_csv(content: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if field.is_empty() => loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err("Texts CSV has an unterminated quoted field".to_string()),
+                }
+            },
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_texts_round_trip() {
+        let entries = vec![
+            TextEntry {
+                file: "index.html".to_string(),
+                id: "el-1".to_string(),
+                text: "Fish, \"chips\"\nand peas".to_string(),
+            },
+            TextEntry {
+                file: "index.html".to_string(),
+                id: "el-2".to_string(),
+                text: "Tea".to_string(),
+            },
+        ];
+
+        for format in [TextFormat::Csv, TextFormat::Json] {
+            let written = format.write(&entries).unwrap();
+            assert_eq!(format.read(&written).unwrap(), entries);
+        }
+        assert_eq!(TextFormat::from_path("fr.CSV"), TextFormat::Csv);
+
+        let csv = "text,id\r\nBonjour,el-1\r\n\r\n\"\",el-2\r\n";
+        let read = TextFormat::Csv.read(csv).unwrap();
+        assert_eq!(
+            read.iter().map(|e| (e.id.as_str(), e.text.as_str())).collect::<Vec<_>>(),
+            vec![("el-1", "Bonjour"), ("el-2", "")]
+        );
+        assert!(TextFormat::Csv.read("id\nel-1\n").is_err());
+        assert!(TextFormat::Csv.read("id,text\n\"open\n").is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::id_generator::Assignment;
+use crate::warnings::Warning;
+use crate::IdScheme;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Record of the attributes the tool wrote, keyed by output file. It is the
+/// source of truth for commands that need to find or remove generated IDs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub files: BTreeMap<String, ManifestFile>,
+    /// Warnings of the last run over each file, kept apart from `files` so
+    /// files that got no ID have theirs too; offsets are into the input
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub warnings: BTreeMap<String, Vec<Warning>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// Attribute the IDs were written to
+    pub attr: String,
+    /// Attribute marking the elements whose ID the tool wrote, when
+    /// `origin_attr` was set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin_attr: Option<String>,
+    /// Scheme the hash IDs were computed with
+    #[serde(default, skip_serializing_if = "IdScheme::is_v1")]
+    pub scheme: IdScheme,
+    pub entries: Vec<Assignment>,
+}
+
+impl ManifestFile {
+    pub fn ids(&self) -> HashSet<String> {
+        self.entries.iter().map(|entry| entry.id.clone()).collect()
+    }
+}
+
+impl Manifest {
+    pub fn new() -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            files: BTreeMap::new(),
+            warnings: BTreeMap::new(),
+        }
+    }
+
+    /// Loads a manifest, returning an empty one when the file does not exist yet.
+    pub fn load_or_default(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        Self::load(path)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read manifest {}: {}", path.display(), e))?;
+        let manifest: Manifest = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid manifest {}: {}", path.display(), e))?;
+
+        if manifest.version != MANIFEST_VERSION {
+            return Err(format!(
+                "Unsupported manifest version {} (expected {})",
+                manifest.version, MANIFEST_VERSION
+            ));
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+        fs::write(path, json + "\n")
+            .map_err(|e| format!("Failed to write manifest {}: {}", path.display(), e))
+    }
+
+    /// Records the IDs written to `file`. With `replace` the previous entries for
+    /// the file are dropped (all IDs were regenerated); otherwise new entries are
+    /// added alongside the ones from earlier runs.
+    pub fn record(&mut self, file: String, attr: &str, assignments: Vec<Assignment>, replace: bool) {
+        let manifest_file = self.files.entry(file.clone()).or_default();
+
+        if replace || manifest_file.attr != attr {
+            manifest_file.entries.clear();
+        }
+        manifest_file.attr = attr.to_string();
+        manifest_file
+            .entries
+            .retain(|old| !assignments.iter().any(|new| new.id == old.id));
+        manifest_file.entries.extend(assignments);
+
+        if manifest_file.entries.is_empty() {
+            self.files.remove(&file);
+        }
+    }
+
+    /// Replaces the warnings recorded for `file`
+    pub fn record_warnings(&mut self, file: String, warnings: Vec<Warning>) {
+        if warnings.is_empty() {
+            self.warnings.remove(&file);
+        } else {
+            self.warnings.insert(file, warnings);
+        }
+    }
+
+    pub fn remove_file(&mut self, file: &str) -> Option<ManifestFile> {
+        self.warnings.remove(file);
+        self.files.remove(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assignment(id: &str) -> Assignment {
+        Assignment {
+            fingerprint: String::new(),
+            id: id.to_string(),
+            tag: "div".to_string(),
+            path: Vec::new(),
+            alias: None,
+        }
+    }
+
+    #[test]
+    fn test_record_merges_and_replaces() {
+        let mut manifest = Manifest::new();
+        manifest.record("a.html".to_string(), "data-ast-id", vec![assignment("el-1")], false);
+        manifest.record("a.html".to_string(), "data-ast-id", vec![assignment("el-2")], false);
+        assert_eq!(manifest.files["a.html"].entries.len(), 2);
+
+        manifest.record("a.html".to_string(), "data-ast-id", vec![assignment("el-3")], true);
+        assert_eq!(manifest.files["a.html"].ids(), HashSet::from(["el-3".to_string()]));
+
+        manifest.record("a.html".to_string(), "data-ast-id", Vec::new(), true);
+        assert!(manifest.files.is_empty());
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ast-append-ids-manifest-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("manifest.json");
+
+        assert!(Manifest::load(&path).is_err());
+        assert!(Manifest::load_or_default(&path).unwrap().files.is_empty());
+
+        let mut manifest = Manifest::new();
+        manifest.record("a.xml".to_string(), "id", vec![assignment("el-1")], true);
+        // Files without IDs keep their warnings
+        let warnings = vec![Warning::selector_matched_nothing("form")];
+        manifest.record_warnings("b.xml".to_string(), warnings.clone());
+        manifest.save(&path).unwrap();
+
+        let loaded = Manifest::load(&path).unwrap();
+        assert_eq!(loaded.files, manifest.files);
+        assert_eq!(loaded.warnings["b.xml"], warnings);
+
+        manifest.record_warnings("b.xml".to_string(), Vec::new());
+        assert!(manifest.warnings.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
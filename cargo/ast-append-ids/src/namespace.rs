@@ -0,0 +1,95 @@
+use crate::id_generator::slugify;
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path};
+
+/// Separator placed between a namespace and the generated ID.
+pub const NAMESPACE_SEPARATOR: &str = "--";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamespaceStyle {
+    /// Readable slug of the relative path (`pages/login-form.html` -> `pages-login-form`)
+    Slug,
+    /// First 8 hex characters of the SHA-256 of the relative path
+    Hash,
+}
+
+/// Builds a namespace for a file from its relative path.
+///
+/// The extension is dropped and path separators are normalized to `/`, so the
+/// same file produces the same namespace on every platform.
+pub fn namespace_for_path(path: &Path, style: NamespaceStyle) -> String {
+    let normalized = normalize_path(&path.with_extension(""));
+
+    match style {
+        NamespaceStyle::Slug => slugify(&normalized.replace('/', " ")),
+        NamespaceStyle::Hash => {
+            let mut hasher = Sha256::new();
+            hasher.update(normalized.as_bytes());
+            let hash = format!("{:x}", hasher.finalize());
+            hash[..8].to_string()
+        }
+    }
+}
+
+/// Builds a short namespace from a package name, leaving out the npm scope
+/// (`@acme/Web_App` -> `web-app`).
+pub fn namespace_for_package(name: &str) -> String {
+    let name = name.rsplit('/').next().unwrap_or(name);
+    slugify(name)
+}
+
+/// Prepends a namespace to an ID prefix (`login-form` + `el-` -> `login-form--el-`).
+pub fn namespaced_prefix(namespace: &str, prefix: &str) -> String {
+    if namespace.is_empty() {
+        return prefix.to_string();
+    }
+    format!("{}{}{}", namespace, NAMESPACE_SEPARATOR, prefix)
+}
+
+fn normalize_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slug_namespace() {
+        let ns = namespace_for_path(Path::new("login-form.html"), NamespaceStyle::Slug);
+        assert_eq!(ns, "login-form");
+
+        let ns = namespace_for_path(Path::new("./src/pages/Login_Form.tsx"), NamespaceStyle::Slug);
+        assert_eq!(ns, "src-pages-login-form");
+    }
+
+    #[test]
+    fn test_hash_namespace() {
+        let a = namespace_for_path(Path::new("src/a.html"), NamespaceStyle::Hash);
+        let b = namespace_for_path(Path::new("./src/a.xml"), NamespaceStyle::Hash);
+        let c = namespace_for_path(Path::new("src/b.html"), NamespaceStyle::Hash);
+
+        assert_eq!(a.len(), 8);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_package_namespace() {
+        assert_eq!(namespace_for_package("webapp"), "webapp");
+        assert_eq!(namespace_for_package("@acme/Web_App"), "web-app");
+        assert_eq!(namespace_for_package("admin.ui"), "admin-ui");
+    }
+
+    #[test]
+    fn test_namespaced_prefix() {
+        assert_eq!(namespaced_prefix("login-form", "el-"), "login-form--el-");
+        assert_eq!(namespaced_prefix("", "el-"), "el-");
+    }
+}
@@ -0,0 +1,118 @@
+use crate::report::{diff_rows, DiffRow, CONTEXT_LINES};
+use std::fmt::Write;
+
+/// Unified diff turning `before` into `after`, with `path` (relative, `/`
+/// separated) on both sides, as `git apply` and `patch -p1` take it. Empty
+/// when the texts are equal.
+pub fn unified_diff(path: &str, before: &str, after: &str) -> String {
+    if before == after {
+        return String::new();
+    }
+
+    // Lines keep their line breaks, so CRLF files and a missing final newline
+    // survive the round trip
+    let old: Vec<&str> = before.split_inclusive('\n').collect();
+    let new: Vec<&str> = after.split_inclusive('\n').collect();
+    let rows = diff_rows(before, after);
+    let changed = |row: &DiffRow| match (row.old, row.new) {
+        (Some(i), Some(j)) => old[i] != new[j],
+        _ => true,
+    };
+
+    // Rows of each hunk: the changes with their context, merged when close
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (k, _) in rows.iter().enumerate().filter(|(_, row)| changed(row)) {
+        let from = k.saturating_sub(CONTEXT_LINES);
+        let to = (k + CONTEXT_LINES + 1).min(rows.len());
+        match hunks.last_mut() {
+            Some(last) if from <= last.1 => last.1 = to,
+            _ => hunks.push((from, to)),
+        }
+    }
+
+    let mut patch = format!("diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}\n", path);
+    for (from, to) in hunks {
+        let rows = &rows[from..to];
+        let _ = writeln!(
+            patch,
+            "@@ -{} +{} @@",
+            hunk_range(rows.iter().filter_map(|row| row.old)),
+            hunk_range(rows.iter().filter_map(|row| row.new))
+        );
+
+        // Removed lines of a change come before the added ones
+        let mut added = Vec::new();
+        for row in rows {
+            if !changed(row) {
+                push_lines(&mut patch, '+', &added);
+                added.clear();
+                push_line(&mut patch, ' ', old[row.old.unwrap_or_default()]);
+                continue;
+            }
+            if let Some(i) = row.old {
+                push_line(&mut patch, '-', old[i]);
+            }
+            if let Some(j) = row.new {
+                added.push(new[j]);
+            }
+        }
+        push_lines(&mut patch, '+', &added);
+    }
+    patch
+}
+
+/// `start,count` of a hunk side with these 0-based line indexes; an empty
+/// side starts at the line before it, as in `diff -u`.
+fn hunk_range(mut lines: impl Iterator<Item = usize>) -> String {
+    match lines.next() {
+        Some(first) => format!("{},{}", first + 1, 1 + lines.count()),
+        None => "0,0".to_string(),
+    }
+}
+
+fn push_lines(patch: &mut String, marker: char, lines: &[&str]) {
+    for line in lines {
+        push_line(patch, marker, line);
+    }
+}
+
+fn push_line(patch: &mut String, marker: char, line: &str) {
+    patch.push(marker);
+    patch.push_str(line);
+    if !line.ends_with('\n') {
+        patch.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        assert_eq!(unified_diff("a.html", "<p/>\n", "<p/>\n"), "");
+
+        let before = "<ul>\n<li>1</li>\n<li>2</li>\n<li>3</li>\n<li>4</li>\n<li>5</li>\n<li>6</li>\n<li>7</li>\n<li>8</li>\n</ul>";
+        let after = before.replacen("<ul>", "<ul id=\"a\">", 1).replacen("</ul>", "</ul >", 1);
+        assert_eq!(
+            unified_diff("src/list.html", before, &after),
+            "diff --git a/src/list.html b/src/list.html\n\
+             --- a/src/list.html\n\
+             +++ b/src/list.html\n\
+             @@ -1,4 +1,4 @@\n\
+             -<ul>\n\
+             +<ul id=\"a\">\n \
+             <li>1</li>\n \
+             <li>2</li>\n \
+             <li>3</li>\n\
+             @@ -7,4 +7,4 @@\n \
+             <li>6</li>\n \
+             <li>7</li>\n \
+             <li>8</li>\n\
+             -</ul>\n\
+             \\ No newline at end of file\n\
+             +</ul >\n\
+             \\ No newline at end of file\n"
+        );
+    }
+}
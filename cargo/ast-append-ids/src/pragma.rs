@@ -0,0 +1,52 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Number of leading lines searched for a file-level pragma by default
+pub const DEFAULT_PRAGMA_LINES: usize = 5;
+
+/// File-level switch written as `/* ast-append-ids: off */`, `// ast-append-ids: on`
+/// or `<!-- ast-append-ids: off -->`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilePragma {
+    /// Process the file even when it is not matched by the configured globs
+    On,
+    /// Never process the file
+    Off,
+}
+
+fn pragma_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?:/\*|//|<!--)\s*ast-append-ids:\s*(on|off)\b").unwrap())
+}
+
+/// Looks for a file-level pragma in the first `max_lines` lines of `content`.
+/// The first pragma found wins.
+pub fn detect_file_pragma(content: &str, max_lines: usize) -> Option<FilePragma> {
+    content.lines().take(max_lines).find_map(|line| {
+        pragma_regex().captures(line).map(|caps| match &caps[1] {
+            "on" => FilePragma::On,
+            _ => FilePragma::Off,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_file_pragma() {
+        assert_eq!(detect_file_pragma("/* ast-append-ids: off */\nconst a = 1;", 5), Some(FilePragma::Off));
+        assert_eq!(detect_file_pragma("<!doctype html>\n<!-- ast-append-ids: on -->", 5), Some(FilePragma::On));
+        assert_eq!(detect_file_pragma("// ast-append-ids:off", 5), Some(FilePragma::Off));
+        assert_eq!(detect_file_pragma("<div>ast-append-ids: off</div>", 5), None);
+        assert_eq!(detect_file_pragma("<!-- ast-append-ids: ignore -->", 5), None);
+    }
+
+    #[test]
+    fn test_pragma_outside_scanned_lines() {
+        let content = "a\nb\nc\n<!-- ast-append-ids: off -->";
+        assert_eq!(detect_file_pragma(content, 3), None);
+        assert_eq!(detect_file_pragma(content, 4), Some(FilePragma::Off));
+    }
+}
@@ -0,0 +1,114 @@
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files that make a directory the root of a package
+pub const PACKAGE_MANIFESTS: [&str; 2] = ["package.json", "Cargo.toml"];
+
+/// Directories never searched for packages: dependencies and build output
+const SKIPPED_DIRS: [&str; 4] = ["node_modules", "target", "dist", "build"];
+
+/// A package of a workspace or monorepo
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    /// From the manifest, or the directory name when it has none
+    pub name: String,
+    /// Directory holding the manifest
+    pub root: PathBuf,
+}
+
+/// Packages under `root`, `root` itself included when it has a manifest,
+/// sorted by path so outer packages come before the ones nested in them.
+/// Hidden, dependency and build directories are not searched.
+pub fn detect_packages(root: &Path) -> Result<Vec<Package>, String> {
+    let mut packages = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if let Some(name) = package_name(&dir) {
+            packages.push(Package { name, root: dir.clone() });
+        }
+        let entries = fs::read_dir(&dir).map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            if is_dir && !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()) {
+                pending.push(entry.path());
+            }
+        }
+    }
+    packages.sort_by(|a, b| a.root.cmp(&b.root));
+    Ok(packages)
+}
+
+/// Name of the package rooted at `dir`, or `None` when `dir` has no manifest
+fn package_name(dir: &Path) -> Option<String> {
+    let manifest = PACKAGE_MANIFESTS.iter().map(|name| dir.join(name)).find(|path| path.is_file())?;
+    let content = fs::read_to_string(&manifest).unwrap_or_default();
+    let name = if manifest.ends_with("package.json") {
+        serde_json::from_str::<Value>(&content)
+            .ok()
+            .and_then(|json| json["name"].as_str().map(str::to_string))
+    } else {
+        toml::from_str::<toml::Table>(&content)
+            .ok()
+            .and_then(|toml| toml.get("package")?.get("name")?.as_str().map(str::to_string))
+    };
+    let dir_name = || {
+        fs::canonicalize(dir)
+            .unwrap_or_else(|_| dir.to_path_buf())
+            .file_name()
+            .map_or_else(|| "root".to_string(), |name| name.to_string_lossy().into_owned())
+    };
+    Some(name.filter(|name| !name.is_empty()).unwrap_or_else(dir_name))
+}
+
+/// The innermost of `packages` that contains `path`
+pub fn package_for<'a>(packages: &'a [Package], path: &Path) -> Option<&'a Package> {
+    packages
+        .iter()
+        .filter(|package| path.starts_with(&package.root))
+        .max_by_key(|package| package.root.components().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_packages() {
+        let dir = std::env::temp_dir().join(format!("ast-append-ids-project-{}", std::process::id()));
+        let write = |path: &str, content: &str| {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write("package.json", r#"{"private": true}"#);
+        write("packages/webapp/package.json", r#"{"name": "@acme/webapp"}"#);
+        write("packages/webapp/src/App.tsx", "");
+        write("packages/webapp/node_modules/react/package.json", r#"{"name": "react"}"#);
+        write("crates/viewer/Cargo.toml", "[package]\nname = \"viewer\"\n");
+        write("docs/index.html", "");
+
+        let packages = detect_packages(&dir).unwrap();
+        let found: Vec<(&str, PathBuf)> = packages
+            .iter()
+            .map(|package| (package.name.as_str(), package.root.strip_prefix(&dir).unwrap().to_path_buf()))
+            .collect();
+        let root_name = dir.file_name().unwrap().to_string_lossy().into_owned();
+        assert_eq!(
+            found,
+            vec![
+                (root_name.as_str(), PathBuf::new()),
+                ("viewer", PathBuf::from("crates/viewer")),
+                ("@acme/webapp", PathBuf::from("packages/webapp")),
+            ]
+        );
+
+        let package = |path: &str| package_for(&packages, &dir.join(path)).map(|package| package.name.as_str());
+        assert_eq!(package("packages/webapp/src/App.tsx"), Some("@acme/webapp"));
+        assert_eq!(package("docs/index.html"), Some(root_name.as_str()));
+        assert_eq!(package_for(&packages, Path::new("/elsewhere/page.html")), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
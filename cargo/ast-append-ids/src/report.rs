@@ -0,0 +1,348 @@
+use crate::change_log::ChangeRecord;
+use crate::id_generator::ChangeAction;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Unchanged lines shown around each change in the side-by-side diffs
+pub const CONTEXT_LINES: usize = 3;
+
+/// Above this many line pairs the differing middle of a file is not aligned
+/// line by line, to bound the time and memory of the diff
+const MAX_ALIGN_CELLS: usize = 4_000_000;
+
+/// A file as a run would write it, for the dry-run report
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    pub file: String,
+    pub before: String,
+    pub after: String,
+    /// Changes located in `before`
+    pub changes: Vec<ChangeRecord>,
+}
+
+/// A row of a side-by-side diff: 0-based line indexes into the old and new
+/// text. Rows with only one side are removed or added lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffRow {
+    pub old: Option<usize>,
+    pub new: Option<usize>,
+}
+
+/// Aligns the lines of `old` and `new`, keeping unchanged lines side by side
+/// and pairing up removed and added lines in between.
+pub fn diff_rows(old: &str, new: &str) -> Vec<DiffRow> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_mid, new_mid) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut rows: Vec<DiffRow> = (0..prefix).map(|i| DiffRow { old: Some(i), new: Some(i) }).collect();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    for (old_line, new_line) in align_lines(old_mid, new_mid) {
+        match (old_line, new_line) {
+            (Some(i), Some(j)) => {
+                pair_up(&mut rows, &mut removed, &mut added);
+                rows.push(DiffRow { old: Some(prefix + i), new: Some(prefix + j) });
+            }
+            (Some(i), None) => removed.push(prefix + i),
+            (None, Some(j)) => added.push(prefix + j),
+            (None, None) => {}
+        }
+    }
+    pair_up(&mut rows, &mut removed, &mut added);
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    rows.extend((0..suffix).map(|i| DiffRow { old: Some(old_end + i), new: Some(new_end + i) }));
+    rows
+}
+
+/// Longest common subsequence of the lines, as pairs of indexes with `None`
+/// for lines only one side has. Too large inputs are not aligned at all.
+fn align_lines(old: &[&str], new: &[&str]) -> Vec<(Option<usize>, Option<usize>)> {
+    let (n, m) = (old.len(), new.len());
+    if n * m > MAX_ALIGN_CELLS {
+        return (0..n).map(|i| (Some(i), None)).chain((0..m).map(|j| (None, Some(j)))).collect();
+    }
+
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    let mut common = vec![0u32; (n + 1) * (m + 1)];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            common[at(i, j)] = if old[i] == new[j] {
+                common[at(i + 1, j + 1)] + 1
+            } else {
+                common[at(i + 1, j)].max(common[at(i, j + 1)])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            pairs.push((Some(i), Some(j)));
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && common[at(i + 1, j)] >= common[at(i, j + 1)]) {
+            pairs.push((Some(i), None));
+            i += 1;
+        } else {
+            pairs.push((None, Some(j)));
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// Moves a run of removed and added lines to `rows`, side by side.
+fn pair_up(rows: &mut Vec<DiffRow>, removed: &mut Vec<usize>, added: &mut Vec<usize>) {
+    for k in 0..removed.len().max(added.len()) {
+        rows.push(DiffRow {
+            old: removed.get(k).copied(),
+            new: added.get(k).copied(),
+        });
+    }
+    removed.clear();
+    added.clear();
+}
+
+/// Renders a self-contained HTML page listing the new IDs and, per file, a
+/// side-by-side diff of the changes.
+pub fn render_html(files: &[FileReport]) -> String {
+    let new_ids: Vec<(usize, &ChangeRecord)> = files
+        .iter()
+        .enumerate()
+        .flat_map(|(index, file)| file.changes.iter().map(move |change| (index, change)))
+        .filter(|(_, change)| change.action != ChangeAction::Skipped)
+        .collect();
+    let changed_files = files.iter().filter(|file| file.before != file.after).count();
+
+    let mut tag_counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for (_, change) in &new_ids {
+        *tag_counts.entry(&change.tag).or_default() += 1;
+    }
+    let mut tag_counts: Vec<_> = tag_counts.into_iter().collect();
+    tag_counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let mut html = String::new();
+    html.push_str(HEADER);
+    let _ = writeln!(
+        html,
+        "<p class=\"summary\">{} file(s), {} with changes, {} new ID(s). Nothing has been written yet.</p>",
+        files.len(),
+        changed_files,
+        new_ids.len()
+    );
+
+    html.push_str("<h2>Tags</h2>\n<table class=\"list\"><tr><th>Tag</th><th>New IDs</th></tr>\n");
+    for (tag, count) in &tag_counts {
+        let _ = writeln!(html, "<tr><td><code>{}</code></td><td>{}</td></tr>", escape(tag), count);
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>New IDs</h2>\n<table class=\"list\"><tr><th>ID</th><th>Tag</th><th>Where</th><th></th></tr>\n");
+    for (index, change) in &new_ids {
+        let action = match change.action {
+            ChangeAction::Overwritten => "replaces an ID",
+            _ => "",
+        };
+        let _ = writeln!(
+            html,
+            "<tr><td><code>{}</code></td><td><code>{}</code></td><td><a href=\"#f{}-{}\">{}:{}</a></td><td>{}</td></tr>",
+            escape(&change.id),
+            escape(&change.tag),
+            index,
+            change.line,
+            escape(&files[*index].file),
+            change.line,
+            action
+        );
+    }
+    html.push_str("</table>\n");
+
+    for (index, file) in files.iter().enumerate() {
+        render_file(&mut html, index, file);
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn render_file(html: &mut String, index: usize, file: &FileReport) {
+    let added = file.changes.iter().filter(|change| change.action != ChangeAction::Skipped).count();
+    let _ = writeln!(
+        html,
+        "<h2 id=\"f{}\">{}</h2>\n<p>{} new ID(s)</p>",
+        index,
+        escape(&file.file),
+        added
+    );
+    if file.before == file.after {
+        html.push_str("<p>No changes.</p>\n");
+        return;
+    }
+
+    let old: Vec<&str> = file.before.lines().collect();
+    let new: Vec<&str> = file.after.lines().collect();
+    let rows = diff_rows(&file.before, &file.after);
+    let changed = |row: &DiffRow| match (row.old, row.new) {
+        (Some(i), Some(j)) => old[i] != new[j],
+        _ => true,
+    };
+    let shown: Vec<bool> = (0..rows.len())
+        .map(|k| {
+            let from = k.saturating_sub(CONTEXT_LINES);
+            let to = (k + CONTEXT_LINES + 1).min(rows.len());
+            rows[from..to].iter().any(changed)
+        })
+        .collect();
+
+    html.push_str("<table class=\"diff\">\n");
+    let mut gap = false;
+    for (row, shown) in rows.iter().zip(shown) {
+        if !shown {
+            gap = true;
+            continue;
+        }
+        if gap {
+            html.push_str("<tr class=\"gap\"><td colspan=\"4\">&#8942;</td></tr>\n");
+            gap = false;
+        }
+
+        let old_line = row.old.map(|i| old[i]);
+        let new_line = row.new.map(|j| new[j]);
+        let (old_html, new_html) = match (old_line, new_line) {
+            (Some(a), Some(b)) if a != b => highlight(a, b),
+            _ => (old_line.map(escape).unwrap_or_default(), new_line.map(escape).unwrap_or_default()),
+        };
+        let class = if changed(row) { " class=\"changed\"" } else { "" };
+        let old_number = row.old.map(|i| (i + 1).to_string()).unwrap_or_default();
+        let new_number = row.new.map(|j| (j + 1).to_string()).unwrap_or_default();
+        let anchor = row.old.map(|i| format!(" id=\"f{}-{}\"", index, i + 1)).unwrap_or_default();
+        let _ = writeln!(
+            html,
+            "<tr{}><td class=\"num\"{}>{}</td><td class=\"old\">{}</td><td class=\"num\">{}</td><td class=\"new\">{}</td></tr>",
+            class, anchor, old_number, old_html, new_number, new_html
+        );
+    }
+    if gap {
+        html.push_str("<tr class=\"gap\"><td colspan=\"4\">&#8942;</td></tr>\n");
+    }
+    html.push_str("</table>\n");
+}
+
+/// Escapes both lines, marking the part where they differ.
+fn highlight(old: &str, new: &str) -> (String, String) {
+    let prefix: usize = old
+        .chars()
+        .zip(new.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+    let suffix: usize = old[prefix..]
+        .chars()
+        .rev()
+        .zip(new[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+    let mark = |line: &str| {
+        let end = line.len() - suffix;
+        if end == prefix {
+            return escape(line);
+        }
+        format!(
+            "{}<mark>{}</mark>{}",
+            escape(&line[..prefix]),
+            escape(&line[prefix..end]),
+            escape(&line[end..])
+        )
+    };
+    (mark(old), mark(new))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const HEADER: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>ast-append-ids report</title>
+<style>
+body { font-family: system-ui, sans-serif; margin: 2em; color: #222; }
+code, .diff { font-family: ui-monospace, monospace; font-size: 13px; }
+table { border-collapse: collapse; }
+.list td, .list th { border: 1px solid #ddd; padding: 2px 8px; text-align: left; }
+.diff { width: 100%; table-layout: fixed; margin-bottom: 2em; }
+.diff td { padding: 0 6px; white-space: pre-wrap; word-break: break-all; vertical-align: top; }
+.diff td.num { width: 4em; color: #888; text-align: right; user-select: none; }
+.changed .old { background: #fee; }
+.changed .new { background: #efe; }
+.changed .old mark { background: #fbb; }
+.changed .new mark { background: #9e9; }
+.gap td { color: #888; text-align: center; background: #f6f6f6; }
+</style>
+</head>
+<body>
+<h1>ast-append-ids report</h1>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_rows() {
+        let rows = diff_rows("a\nb\nc\nd", "a\nb2\nx\nc\nd");
+        let pairs: Vec<_> = rows.iter().map(|row| (row.old, row.new)).collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (Some(0), Some(0)),
+                (Some(1), Some(1)),
+                (None, Some(2)),
+                (Some(2), Some(3)),
+                (Some(3), Some(4)),
+            ]
+        );
+        assert_eq!(diff_rows("", "a").len(), 1);
+    }
+
+    #[test]
+    fn test_render_html() {
+        let before = "<main>\n<p>Tom & Jerry</p>\n</main>".to_string();
+        let after = "<main>\n<p data-ast-id=\"el-1\">Tom & Jerry</p>\n</main>".to_string();
+        let change = ChangeRecord {
+            file: "a.html".to_string(),
+            line: 2,
+            col: 1,
+            tag: "p".to_string(),
+            action: ChangeAction::Added,
+            id: "el-1".to_string(),
+        };
+        let files = vec![FileReport {
+            file: "a.html".to_string(),
+            before,
+            after,
+            changes: vec![change],
+        }];
+
+        let html = render_html(&files);
+        assert!(html.contains("1 file(s), 1 with changes, 1 new ID(s)"));
+        assert!(html.contains("<tr><td><code>p</code></td><td>1</td></tr>"));
+        assert!(html.contains("<a href=\"#f0-2\">a.html:2</a>"));
+        assert!(html.contains("&lt;p<mark> data-ast-id=&quot;el-1&quot;</mark>&gt;Tom &amp; Jerry"));
+        assert!(html.contains("<td class=\"old\">&lt;p&gt;Tom"));
+        assert!(html.contains("<td class=\"num\" id=\"f0-2\">2</td>"));
+    }
+}
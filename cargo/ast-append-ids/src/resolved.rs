@@ -0,0 +1,162 @@
+use crate::ast_common::{self, AstNode, NameFilter, OptionOverrides};
+use crate::detect::Format;
+use crate::extra_attrs::{self, AttrTemplate};
+use crate::id_generator::slugify;
+use crate::selector::Selector;
+use crate::strategy::{IdStrategyImpl, StrategyContext};
+use crate::taxonomy::TaxonomyLevel;
+use crate::{IdOptions, IdStrategy};
+use regex::Regex;
+#[cfg(all(feature = "jsx", feature = "html"))]
+use std::cell::OnceCell;
+use std::cell::RefCell;
+use std::collections::HashMap;
+#[cfg(all(feature = "jsx", feature = "html"))]
+use std::rc::Rc;
+
+/// `IdOptions` compiled for a format's processor: what every element is
+/// checked against and made an ID with, in the form the checks need. Built
+/// once and shared by the documents processed with the same options, e.g.
+/// all files of a CLI run. `IdOptions` stays the configuration as users
+/// write it; an option that needs work up front gets its compiled form here
+/// rather than in each processor. `prefix` and `range` are read from the
+/// options of each document, so they may differ between the documents.
+#[cfg_attr(not(any(feature = "jsx", feature = "xml", feature = "html")), allow(dead_code))]
+pub struct ResolvedOptions {
+    /// `include` and `exclude`, with `name_case` settled for the format
+    pub(crate) filter: NameFilter,
+    pub(crate) text_pattern: Option<Regex>,
+    #[cfg_attr(not(feature = "xml"), allow(dead_code))]
+    pub(crate) comment_anchors: Option<Regex>,
+    pub(crate) extra_attrs: Vec<AttrTemplate>,
+    /// `selector`, for formats without a selector engine of their own (all
+    /// but HTML)
+    #[cfg_attr(not(any(feature = "jsx", feature = "xml")), allow(dead_code))]
+    pub(crate) selector: Option<Selector>,
+    /// `taxonomy_levels`, matching names as `ignore_case` says
+    pub(crate) taxonomy_levels: Vec<TaxonomyLevel>,
+    /// The built-in strategies; directives may switch an element to any of
+    /// them, so all are made up front
+    strategies: RefCell<Vec<(IdStrategy, Box<dyn IdStrategyImpl>)>>,
+    /// What a component scope adds to the prefix, by component name
+    component_prefixes: RefCell<HashMap<String, String>>,
+    /// The options compiled for HTML markup embedded in the format
+    #[cfg(all(feature = "jsx", feature = "html"))]
+    embedded_html: OnceCell<Rc<ResolvedOptions>>,
+}
+
+impl ResolvedOptions {
+    pub fn new(options: &IdOptions, format: Format) -> Result<Self, String> {
+        let ignore_case = options.name_case.resolve(format == Format::Html).ignores_case();
+        let selector = match format {
+            // lol_html takes the selector as it is
+            Format::Html => None,
+            _ => options.selector.as_deref().map(Selector::parse).transpose()?,
+        };
+        let strategies = [IdStrategy::Hash, IdStrategy::Slug, IdStrategy::Path, IdStrategy::Taxonomy]
+            .into_iter()
+            .map(|strategy| {
+                let implementation = strategy.implementation();
+                (strategy, implementation)
+            })
+            .collect();
+        Ok(Self {
+            filter: NameFilter::new(options, ignore_case),
+            text_pattern: ast_common::text_pattern(options)?,
+            comment_anchors: ast_common::comment_anchor_pattern(options)?,
+            extra_attrs: extra_attrs::parse_all(&options.extra_attrs)?,
+            selector: selector.map(|selector| selector.ignoring_case(ignore_case)),
+            taxonomy_levels: TaxonomyLevel::parse_all(&options.taxonomy_levels, ignore_case)?,
+            strategies: RefCell::new(strategies),
+            component_prefixes: RefCell::new(HashMap::new()),
+            #[cfg(all(feature = "jsx", feature = "html"))]
+            embedded_html: OnceCell::new(),
+        })
+    }
+
+    /// `options`, which these were compiled from, compiled for HTML, for
+    /// markup embedded in a document of another format. Compiled when first
+    /// asked for.
+    #[cfg(all(feature = "jsx", feature = "html"))]
+    pub(crate) fn embedded_html(&self, options: &IdOptions) -> Result<Rc<ResolvedOptions>, String> {
+        if let Some(resolved) = self.embedded_html.get() {
+            return Ok(resolved.clone());
+        }
+        let resolved = Rc::new(Self::new(options, Format::Html)?);
+        Ok(self.embedded_html.get_or_init(|| resolved).clone())
+    }
+
+    /// A new ID for `node`, made by the strategy `ctx.options` names
+    pub(crate) fn fresh_id(&self, node: &AstNode, ctx: &mut StrategyContext) -> String {
+        let mut strategies = self.strategies.borrow_mut();
+        let (_, strategy) = strategies
+            .iter_mut()
+            .find(|(strategy, _)| *strategy == ctx.options.strategy)
+            .expect("every built-in strategy is made in `new`");
+        strategy.id_for(node, ctx)
+    }
+
+    /// Overrides for the elements of a component scope: the component's name
+    /// goes after the prefix, e.g. `el-user-card-`
+    #[cfg_attr(not(any(feature = "jsx", feature = "xml", feature = "html")), allow(dead_code))]
+    pub(crate) fn component_overrides(&self, options: &IdOptions, component: &str) -> OptionOverrides {
+        let mut prefixes = self.component_prefixes.borrow_mut();
+        if !prefixes.contains_key(component) {
+            prefixes.insert(component.to_string(), format!("{}-", slugify(component)));
+        }
+        OptionOverrides { prefix: Some(format!("{}{}", options.prefix, prefixes[component])), strategy: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::id_generator::IdGenerator;
+    use crate::NameCase;
+
+    #[test]
+    fn test_resolved_options() {
+        let options = IdOptions {
+            include: vec!["DIV".to_string()],
+            selector: Some("MAIN DIV".to_string()),
+            text_matches: Some("^Total".to_string()),
+            ..IdOptions::default()
+        };
+
+        let html = ResolvedOptions::new(&options, Format::Html).unwrap();
+        assert!(html.filter.allows("div"));
+        assert!(html.text_pattern.is_some_and(|pattern| pattern.is_match("Total: 3")));
+        assert!(html.selector.is_none());
+
+        let xml = ResolvedOptions::new(&options, Format::Xml).unwrap();
+        assert!(!xml.filter.allows("div"));
+        assert!(xml.selector.is_some());
+
+        let sensitive = IdOptions { name_case: NameCase::Sensitive, ..options };
+        assert!(!ResolvedOptions::new(&sensitive, Format::Html).unwrap().filter.allows("div"));
+    }
+
+    #[test]
+    fn test_resolved_strategies_and_prefixes() {
+        let options = IdOptions { prefix: "app-".to_string(), ..IdOptions::default() };
+        let resolved = ResolvedOptions::new(&options, Format::Jsx).unwrap();
+        let node = AstNode {
+            node_type: "button".to_string(),
+            text_content: Some("Save".to_string()),
+            attributes: Vec::new(),
+            path: vec![1],
+            context: Vec::new(),
+        };
+        let slug = IdOptions { strategy: IdStrategy::Slug, ..options.clone() };
+        let mut generator = IdGenerator::new();
+        let mut ctx = StrategyContext { generator: &mut generator, options: &slug };
+        assert_eq!(resolved.fresh_id(&node, &mut ctx), "app-save");
+
+        // The prefix of the options at hand comes first, whatever it was before
+        let overrides = resolved.component_overrides(&options, "UserCard");
+        assert_eq!(overrides.prefix.as_deref(), Some("app-usercard-"));
+        let nested = IdOptions { prefix: "app-page-".to_string(), ..options };
+        let overrides = resolved.component_overrides(&nested, "UserCard");
+        assert_eq!(overrides.prefix.as_deref(), Some("app-page-usercard-"));
+    }
+}
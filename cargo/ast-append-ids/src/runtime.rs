@@ -0,0 +1,81 @@
+use crate::ast_common::IGNORE_ATTR;
+
+/// The module `gen-runtime` writes. `__ATTR__` and `__IGNORE_ATTR__` are
+/// replaced with JavaScript string contents.
+const TEMPLATE: &str = r#"// Generated by ast-append-ids gen-runtime. Do not edit.
+
+const ATTR = '__ATTR__';
+const IGNORE_ATTR = '__IGNORE_ATTR__';
+const TAGGED = `[${CSS.escape(ATTR)}]`;
+/** Elements people interact with, which tests need to find */
+const INTERACTIVE =
+  'a[href], button, input:not([type="hidden"]), select, textarea, summary, ' +
+  '[role="button"], [role="link"], [role="checkbox"], [role="tab"], [role="menuitem"], [contenteditable="true"]';
+
+/** The element with ID `id` under `root`, or null */
+export function byAstId(id, root = document) {
+  return root.querySelector(`[${CSS.escape(ATTR)}="${CSS.escape(id)}"]`);
+}
+
+/** IDs of the tagged elements under `root`, in document order */
+export function allAstIds(root = document) {
+  return Array.from(root.querySelectorAll(TAGGED), (el) => el.getAttribute(ATTR));
+}
+
+/**
+ * Warns about interactive elements without an ID under `root`, now and as
+ * they are added, e.g. by components rendered at run time. Elements inside
+ * an ignored subtree are left out. Returns the observer, to disconnect it.
+ */
+export function warnUntagged(root = document.body, report = console.warn) {
+  const reported = new WeakSet();
+  const check = (node) => {
+    if (node.nodeType !== Node.ELEMENT_NODE) return;
+    const found = node.matches(INTERACTIVE) ? [node] : [];
+    found.push(...node.querySelectorAll(INTERACTIVE));
+    for (const el of found) {
+      if (el.hasAttribute(ATTR) || reported.has(el) || el.closest(`[${CSS.escape(IGNORE_ATTR)}]`)) continue;
+      reported.add(el);
+      report(`ast-append-ids: <${el.localName}> is interactive but has no ${ATTR}`, el);
+    }
+  };
+  check(root);
+  const observer = new MutationObserver((records) => {
+    for (const record of records) record.addedNodes.forEach(check);
+  });
+  observer.observe(root, { childList: true, subtree: true });
+  return observer;
+}
+"#;
+
+/// A small ES module for the browser side of the IDs in `attr`: `byAstId(id)`,
+/// `allAstIds()` and `warnUntagged()`, a MutationObserver reporting
+/// interactive elements added without an ID.
+pub fn runtime_module(attr: &str) -> Result<String, String> {
+    crate::check_attribute_name(attr).map_err(|e| format!("Invalid attribute: {}", e))?;
+    Ok(TEMPLATE.replace("__ATTR__", &js_string(attr)).replace("__IGNORE_ATTR__", &js_string(IGNORE_ATTR)))
+}
+
+/// Contents of a single-quoted JavaScript string holding `text`
+fn js_string(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_module() {
+        let module = runtime_module("data-test").unwrap();
+        assert!(module.contains("const ATTR = 'data-test';\n"));
+        assert!(module.contains("const IGNORE_ATTR = 'data-ast-ignore';\n"));
+        assert!(!module.contains("__"));
+        for name in ["byAstId", "allAstIds", "warnUntagged"] {
+            assert!(module.contains(&format!("export function {}(", name)), "{}", name);
+        }
+
+        assert!(runtime_module("data test").unwrap_err().starts_with("Invalid attribute"));
+        assert_eq!(js_string(r"a\b"), r"a\\b");
+    }
+}
@@ -0,0 +1,77 @@
+use crate::id_generator::scheme_hash;
+use crate::manifest::ManifestFile;
+use crate::IdScheme;
+use std::collections::BTreeMap;
+
+/// Moves the IDs recorded for a file from its scheme to `to`, returning the
+/// changed IDs, old to new. `from_salt` is the salt the IDs were made with.
+///
+/// Only the hash part of an ID changes; prefix, namespace and `-N` counter
+/// are kept. IDs without the hash the recorded tag and path give, such as
+/// slugs or IDs reused from the ID cache after their element moved, stay.
+pub fn migrate(file: &mut ManifestFile, to: IdScheme, from_salt: &str, to_salt: &str) -> BTreeMap<String, String> {
+    let mut mapping = BTreeMap::new();
+    for entry in &mut file.entries {
+        let old_hash = scheme_hash(file.scheme, from_salt, &entry.tag, &entry.path);
+        let new_hash = scheme_hash(to, to_salt, &entry.tag, &entry.path);
+        let Some(start) = entry.id.rfind(&old_hash) else {
+            continue;
+        };
+        if old_hash == new_hash {
+            continue;
+        }
+        let mut id = entry.id.clone();
+        id.replace_range(start..start + old_hash.len(), &new_hash);
+        mapping.insert(std::mem::replace(&mut entry.id, id.clone()), id);
+    }
+    file.scheme = to;
+    mapping
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::id_generator::{Assignment, IdGenerator};
+
+    #[test]
+    fn test_migrate() {
+        let mut generator = IdGenerator::new();
+        let entry = |id: String, path: Vec<usize>| Assignment {
+            fingerprint: String::new(),
+            id,
+            tag: "button".to_string(),
+            path,
+            alias: None,
+        };
+        let first = generator.generate_hash_id("button", &[0], "el-");
+        let second = generator.generate_hash_id("button", &[0], "el-");
+        let mut file = ManifestFile {
+            attr: "data-ast-id".to_string(),
+            entries: vec![
+                entry(first.clone(), vec![0]),
+                entry(format!("app--{}", second), vec![0]),
+                entry("el-save".to_string(), vec![1]),
+            ],
+            ..ManifestFile::default()
+        };
+
+        let mapping = migrate(&mut file, IdScheme::V2, "", "pepper");
+        let new_hash = scheme_hash(IdScheme::V2, "pepper", "button", &[0]);
+        assert_eq!(new_hash.len(), 11);
+        assert_eq!(
+            mapping,
+            BTreeMap::from([
+                (first, format!("el-{}", new_hash)),
+                (format!("app--{}", second), format!("app--el-{}-2", new_hash)),
+            ])
+        );
+        let ids: Vec<&str> = file.entries.iter().map(|entry| entry.id.as_str()).collect();
+        assert_eq!(ids[2], "el-save");
+        assert_eq!(file.scheme, IdScheme::V2);
+
+        // Back again, with the salt the IDs were made with
+        let back = migrate(&mut file, IdScheme::V1, "pepper", "");
+        assert_eq!(back.len(), 2);
+        assert!(migrate(&mut file, IdScheme::V1, "", "").is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use crate::ast_common;
+use crate::codemod::normalize_text;
+use crate::selector::Selector;
+
+/// An element matched by a `select` query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectedElement {
+    pub offset: usize,
+    pub tag: String,
+    /// Tag names of the enclosing elements, outermost first
+    pub ancestors: Vec<String>,
+    /// Value of the ID attribute, if the element has one
+    pub id: Option<String>,
+    /// Text of the element and its descendants, as found in the source
+    pub text: String,
+}
+
+/// A selected element located in its source. `line` and `col` are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selection {
+    pub line: usize,
+    pub col: usize,
+    pub tag: String,
+    pub id: Option<String>,
+    /// Text with whitespace collapsed
+    pub text: String,
+}
+
+/// Whether `query` is XPath rather than a CSS selector: it starts with `/`.
+pub fn is_xpath(query: &str) -> bool {
+    query.trim_start().starts_with('/')
+}
+
+/// Parses `query` as XPath when it starts with `/`, as a CSS selector
+/// otherwise (see `Selector` for the supported subset).
+pub fn parse_query(query: &str) -> Result<Selector, String> {
+    if is_xpath(query) {
+        Selector::parse_xpath(query)
+    } else {
+        Selector::parse(query)
+    }
+}
+
+/// Locates the `elements` selected in `content`, in source order.
+pub fn locate(content: &str, mut elements: Vec<SelectedElement>) -> Vec<Selection> {
+    elements.sort_by_key(|element| element.offset);
+    let positions = ast_common::line_columns(content, elements.iter().map(|element| element.offset));
+
+    elements
+        .into_iter()
+        .zip(positions)
+        .map(|(element, (line, col))| Selection {
+            line,
+            col,
+            tag: element.tag,
+            id: element.id,
+            text: normalize_text(&element.text),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selector::SelectorElement;
+
+    #[test]
+    fn test_select_locate() {
+        let path = [SelectorElement {
+            name: "button".to_string(),
+            attributes: Vec::new(),
+        }];
+        assert!(parse_query("main button, button").unwrap().matches(&path));
+        assert!(parse_query(" //button").unwrap().matches(&path));
+        assert!(parse_query("/a//button").is_ok_and(|selector| !selector.matches(&path)));
+
+        let content = "<main>\n  <p>a</p><button data-ast-id=\"el-1\">\n    Save  changes\n  </button>\n</main>";
+        let elements = vec![
+            SelectedElement {
+                offset: content.find("<button").unwrap(),
+                tag: "button".to_string(),
+                ancestors: vec!["main".to_string()],
+                id: Some("el-1".to_string()),
+                text: "\n    Save  changes\n  ".to_string(),
+            },
+            SelectedElement {
+                offset: content.find("<p>").unwrap(),
+                tag: "p".to_string(),
+                ancestors: vec!["main".to_string()],
+                id: None,
+                text: "a".to_string(),
+            },
+        ];
+        let selected = locate(content, elements);
+        assert_eq!(
+            selected.iter().map(|s| (s.line, s.col, s.text.as_str())).collect::<Vec<_>>(),
+            vec![(2, 3, "a"), (2, 11, "Save changes")]
+        );
+    }
+}
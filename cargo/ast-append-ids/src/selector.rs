@@ -0,0 +1,357 @@
+use crate::ast_common;
+
+/// An element on the path `Selector::matches` is checked against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorElement {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl SelectorElement {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        ast_common::find_attribute(&self.attributes, name)
+    }
+}
+
+/// A CSS selector subset for formats without a selector engine (XML, JSX):
+/// type names (with namespace prefix), `*`, `#id`, `.class`, `[attr]` and
+/// `[attr="value"]`, joined by descendant or `>` combinators. Comma-separated
+/// lists match when any of their selectors does. The same subset can be
+/// written as XPath, see `Selector::parse_xpath`. Type names match exactly
+/// unless the selector is made to ignore case.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    alternatives: Vec<ComplexSelector>,
+    ignore_case: bool,
+}
+
+/// `compounds[i]` and `compounds[i + 1]` are joined by `combinators[i]`.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ComplexSelector {
+    compounds: Vec<Compound>,
+    combinators: Vec<Combinator>,
+    /// The first compound only matches the outermost element (XPath `/a`)
+    rooted: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum AttrTest {
+    Exists(String),
+    Equals(String, String),
+    /// Whitespace-separated word, as in `.class`
+    Includes(String, String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Compound {
+    /// `None` for `*` or a compound without a type
+    name: Option<String>,
+    tests: Vec<AttrTest>,
+}
+
+type SelectorChars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+impl Selector {
+    pub fn parse(selector: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid selector '{}'", selector);
+        let mut chars = selector.trim().chars().peekable();
+        let mut alternatives = Vec::new();
+        let mut compounds = Vec::new();
+        let mut combinators = Vec::new();
+
+        loop {
+            compounds.push(Compound::parse(&mut chars).ok_or_else(invalid)?);
+
+            let mut combinator = None;
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    combinator.get_or_insert(Combinator::Descendant);
+                } else if c == '>' && combinator != Some(Combinator::Child) {
+                    combinator = Some(Combinator::Child);
+                } else {
+                    break;
+                }
+                chars.next();
+            }
+
+            if combinator != Some(Combinator::Child) && chars.next_if_eq(&',').is_some() {
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                alternatives.push(ComplexSelector {
+                    compounds: std::mem::take(&mut compounds),
+                    combinators: std::mem::take(&mut combinators),
+                    rooted: false,
+                });
+                continue;
+            }
+            match combinator {
+                Some(combinator) => combinators.push(combinator),
+                None if chars.peek().is_none() => break,
+                None => return Err(invalid()),
+            }
+        }
+
+        alternatives.push(ComplexSelector { compounds, combinators, rooted: false });
+        Ok(Self { alternatives, ignore_case: false })
+    }
+
+    /// Parses the XPath subset the selector can evaluate: `/` and `//` steps
+    /// with element names or `*`, `[@attr]` and `[@attr='value']` predicates,
+    /// and `|` unions. A path starting with a single `/` is anchored at the
+    /// outermost element.
+    pub fn parse_xpath(xpath: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid XPath '{}'", xpath);
+        let mut chars = xpath.trim().chars().peekable();
+        let mut alternatives = Vec::new();
+        let mut selector = ComplexSelector::default();
+
+        loop {
+            chars.next_if_eq(&'/').ok_or_else(invalid)?;
+            let combinator = if chars.next_if_eq(&'/').is_some() {
+                Combinator::Descendant
+            } else {
+                Combinator::Child
+            };
+            if selector.compounds.is_empty() {
+                selector.rooted = combinator == Combinator::Child;
+            } else {
+                selector.combinators.push(combinator);
+            }
+            selector.compounds.push(Compound::parse_step(&mut chars).ok_or_else(invalid)?);
+
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            match chars.peek() {
+                Some('/') => {}
+                Some('|') => {
+                    chars.next();
+                    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                    alternatives.push(std::mem::take(&mut selector));
+                }
+                None => break,
+                Some(_) => return Err(invalid()),
+            }
+        }
+
+        alternatives.push(selector);
+        Ok(Self { alternatives, ignore_case: false })
+    }
+
+    /// The selector with type names matching regardless of ASCII case, as
+    /// HTML tag names do
+    pub fn ignoring_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    /// Whether the last element of `path` matches, given its ancestors.
+    pub fn matches(&self, path: &[SelectorElement]) -> bool {
+        self.alternatives
+            .iter()
+            .any(|selector| selector.matches_from(selector.compounds.len() - 1, path, self.ignore_case))
+    }
+}
+
+impl ComplexSelector {
+    fn matches_from(&self, index: usize, path: &[SelectorElement], ignore_case: bool) -> bool {
+        let Some((element, ancestors)) = path.split_last() else {
+            return false;
+        };
+        if !self.compounds[index].matches(element, ignore_case) {
+            return false;
+        }
+        if index == 0 {
+            return !self.rooted || ancestors.is_empty();
+        }
+        match self.combinators[index - 1] {
+            Combinator::Child => self.matches_from(index - 1, ancestors, ignore_case),
+            Combinator::Descendant => (1..=ancestors.len())
+                .rev()
+                .any(|len| self.matches_from(index - 1, &ancestors[..len], ignore_case)),
+        }
+    }
+}
+
+impl Compound {
+    fn parse(chars: &mut SelectorChars) -> Option<Self> {
+        let mut compound = Compound::default();
+        let mut empty = true;
+
+        if chars.next_if_eq(&'*').is_some() {
+            empty = false;
+        } else {
+            let name = take_name(chars);
+            if !name.is_empty() {
+                compound.name = Some(name);
+                empty = false;
+            }
+        }
+
+        loop {
+            let test = match chars.peek() {
+                Some('#') => {
+                    chars.next();
+                    AttrTest::Equals("id".to_string(), non_empty(take_name(chars))?)
+                }
+                Some('.') => {
+                    chars.next();
+                    AttrTest::Includes("class".to_string(), non_empty(take_name(chars))?)
+                }
+                Some('[') => {
+                    chars.next();
+                    let name = non_empty(take_name(chars))?;
+                    match chars.next()? {
+                        ']' => AttrTest::Exists(name),
+                        '=' => {
+                            let value = take_value(chars)?;
+                            chars.next_if_eq(&']')?;
+                            AttrTest::Equals(name, value)
+                        }
+                        _ => return None,
+                    }
+                }
+                _ => break,
+            };
+            compound.tests.push(test);
+            empty = false;
+        }
+
+        (!empty).then_some(compound)
+    }
+
+    /// Parses an XPath location step such as `div[@role='main']`.
+    fn parse_step(chars: &mut SelectorChars) -> Option<Self> {
+        let name = match chars.next_if_eq(&'*') {
+            Some(_) => None,
+            None => Some(non_empty(take_name(chars))?),
+        };
+        let mut tests = Vec::new();
+        while chars.next_if_eq(&'[').is_some() {
+            chars.next_if_eq(&'@')?;
+            let attr = non_empty(take_name(chars))?;
+            tests.push(match chars.next()? {
+                ']' => AttrTest::Exists(attr),
+                '=' => {
+                    let value = take_value(chars)?;
+                    chars.next_if_eq(&']')?;
+                    AttrTest::Equals(attr, value)
+                }
+                _ => return None,
+            });
+        }
+        Some(Self { name, tests })
+    }
+
+    fn matches(&self, element: &SelectorElement, ignore_case: bool) -> bool {
+        let name_differs = |name: &String| {
+            if ignore_case {
+                !name.eq_ignore_ascii_case(&element.name)
+            } else {
+                *name != element.name
+            }
+        };
+        if self.name.as_ref().is_some_and(name_differs) {
+            return false;
+        }
+        self.tests.iter().all(|test| match test {
+            AttrTest::Exists(name) => element.attribute(name).is_some(),
+            AttrTest::Equals(name, value) => element.attribute(name) == Some(value.as_str()),
+            AttrTest::Includes(name, word) => element
+                .attribute(name)
+                .is_some_and(|value| value.split_whitespace().any(|w| w == word)),
+        })
+    }
+}
+
+fn take_name(chars: &mut SelectorChars) -> String {
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|&c| c.is_alphanumeric() || matches!(c, '-' | '_' | ':')) {
+        name.push(c);
+    }
+    name
+}
+
+fn take_value(chars: &mut SelectorChars) -> Option<String> {
+    let Some(quote) = chars.next_if(|&c| c == '"' || c == '\'') else {
+        return non_empty(take_name(chars));
+    };
+    let mut value = String::new();
+    for c in chars.by_ref() {
+        if c == quote {
+            return Some(value);
+        }
+        value.push(c);
+    }
+    None
+}
+
+fn non_empty(name: String) -> Option<String> {
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selector_matches() {
+        let element = |name: &str, attributes: &[(&str, &str)]| SelectorElement {
+            name: name.to_string(),
+            attributes: attributes.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        };
+        let path = [
+            element("svg:svg", &[]),
+            element("g", &[("class", "icons dark")]),
+            element("path", &[("id", "p1"), ("d", "M 0")]),
+        ];
+        let matches = |selector: &str| Selector::parse(selector).unwrap().matches(&path);
+
+        assert!(matches("path"));
+        assert!(matches("svg:svg path#p1"));
+        assert!(matches("g.dark > [d='M 0']"));
+        assert!(matches("svg:svg > * > *"));
+        assert!(!matches("svg:svg > path"));
+        assert!(!matches("g.light path"));
+        assert!(matches("button, a,path"));
+        assert!(matches("g.light path , [role], svg:svg path"));
+        assert!(!matches("button, [role]"));
+        assert!(!matches("G > PATH"));
+        assert!(Selector::parse("G > PATH").unwrap().ignoring_case(true).matches(&path));
+        // Attribute values keep their case
+        assert!(!Selector::parse("g.DARK path").unwrap().ignoring_case(true).matches(&path));
+
+        for bad in ["", "a[", "a >", "a > > b", "a,", ",a", "a >, b", "a b c ,", "[x=]"] {
+            assert!(Selector::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_xpath_matches() {
+        let element = |name: &str, attributes: &[(&str, &str)]| SelectorElement {
+            name: name.to_string(),
+            attributes: attributes.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        };
+        let path = [
+            element("svg:svg", &[]),
+            element("g", &[("class", "icons dark")]),
+            element("path", &[("id", "p1"), ("d", "M 0")]),
+        ];
+        let matches = |xpath: &str| Selector::parse_xpath(xpath).unwrap().matches(&path);
+
+        assert!(matches("//path"));
+        assert!(matches("/svg:svg/g/path[@id='p1']"));
+        assert!(matches("//g[@class]/*[@d=\"M 0\"]"));
+        assert!(matches("/*//path"));
+        assert!(!matches("/g//path"));
+        assert!(!matches("/svg:svg/path"));
+        assert!(matches("//button | //path"));
+
+        for bad in ["", "path", "//", "//a[", "//a[id]", "//a/", "//a |", "//a b"] {
+            assert!(Selector::parse_xpath(bad).is_err(), "{}", bad);
+        }
+    }
+}
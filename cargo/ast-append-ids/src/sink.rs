@@ -0,0 +1,131 @@
+use crate::file_io::{self, Preserve};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Where processed documents go. Front-ends hand every result to a sink
+/// instead of deciding themselves; an archive or an HTTP response is one more
+/// implementation.
+pub trait Sink {
+    /// Takes the result for the input at `path`, returning the file it was
+    /// written to if it went to one.
+    fn write(&mut self, path: &Path, content: &str) -> Result<Option<PathBuf>, String>;
+}
+
+/// When `FileSink` writes in place through a new file, see `replace_output`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Replace {
+    /// Write through links
+    #[default]
+    Never,
+    /// Replace symbolic links with the output
+    Symlinks,
+    /// Always, which also detaches hard links
+    Always,
+}
+
+/// Writes each result to a file, in place or into a directory, keeping the
+/// input's permissions and whatever else `preserve` asks for.
+#[derive(Debug, Clone, Default)]
+pub struct FileSink {
+    /// Directory the outputs go to, named after their inputs; `None` to write
+    /// over the inputs
+    pub dir: Option<PathBuf>,
+    /// Only applies in place
+    pub replace: Replace,
+    pub preserve: Preserve,
+}
+
+impl FileSink {
+    /// The file the result for `path` goes to
+    pub fn target(&self, path: &Path) -> PathBuf {
+        match &self.dir {
+            Some(dir) => dir.join(path.file_name().unwrap_or_default()),
+            None => path.to_path_buf(),
+        }
+    }
+}
+
+impl Sink for FileSink {
+    fn write(&mut self, path: &Path, content: &str) -> Result<Option<PathBuf>, String> {
+        // Taken before an in-place write replaces the input
+        let metadata = fs::metadata(path).ok();
+        if let Some(dir) = &self.dir {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create output directory {}: {}", dir.display(), e))?;
+        }
+
+        let target = self.target(path);
+        let replace = self.dir.is_none()
+            && match self.replace {
+                Replace::Never => false,
+                Replace::Symlinks => fs::symlink_metadata(&target).is_ok_and(|m| m.file_type().is_symlink()),
+                Replace::Always => true,
+            };
+        if replace {
+            file_io::replace_output(&target, content)?;
+        } else {
+            file_io::write_output(&target, content)?;
+        }
+        if let Some(metadata) = &metadata {
+            file_io::preserve_metadata(&target, metadata, self.preserve)?;
+        }
+        Ok(Some(target))
+    }
+}
+
+/// Writes every result to stdout, one after the other
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutSink;
+
+impl Sink for StdoutSink {
+    fn write(&mut self, path: &Path, content: &str) -> Result<Option<PathBuf>, String> {
+        let mut out = std::io::stdout().lock();
+        out.write_all(content.as_bytes())
+            .and_then(|_| out.flush())
+            .map_err(|e| format!("Failed to write {} to stdout: {}", path.display(), e))?;
+        Ok(None)
+    }
+}
+
+/// Keeps the results, for embedders and tests
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    pub outputs: Vec<(PathBuf, String)>,
+}
+
+impl Sink for MemorySink {
+    fn write(&mut self, path: &Path, content: &str) -> Result<Option<PathBuf>, String> {
+        self.outputs.push((path.to_path_buf(), content.to_string()));
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sinks() {
+        let dir = std::env::temp_dir().join(format!("ast-append-ids-sink-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("page.html");
+        fs::write(&input, "<p>Hello</p>").unwrap();
+
+        let mut sink = FileSink { dir: Some(dir.join("out")), ..FileSink::default() };
+        let written = sink.write(&input, "<p id=\"a\">Hello</p>").unwrap();
+        assert_eq!(written, Some(dir.join("out").join("page.html")));
+        assert_eq!(fs::read_to_string(dir.join("out/page.html")).unwrap(), "<p id=\"a\">Hello</p>");
+        assert_eq!(fs::read_to_string(&input).unwrap(), "<p>Hello</p>");
+
+        let mut in_place = FileSink { replace: Replace::Always, ..FileSink::default() };
+        assert_eq!(in_place.write(&input, "<p id=\"b\">Hello</p>").unwrap(), Some(input.clone()));
+        assert_eq!(fs::read_to_string(&input).unwrap(), "<p id=\"b\">Hello</p>");
+
+        let mut memory = MemorySink::default();
+        assert_eq!(memory.write(&input, "<p/>").unwrap(), None);
+        assert_eq!(memory.outputs, vec![(input, "<p/>".to_string())]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
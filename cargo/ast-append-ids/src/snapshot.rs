@@ -0,0 +1,149 @@
+use crate::ast_common;
+use crate::detect::Format;
+use crate::id_generator::ChangeAction;
+use crate::warnings::Warning;
+use crate::{IdOptions, Processor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Elements without content or end tag
+const VOID_ELEMENTS: [&str; 14] =
+    ["area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr"];
+
+/// Elements whose text is not markup, so it goes in unescaped
+const RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
+
+/// A node of a DOM as a devtools extension captures it from the live page:
+/// `{ tag, attributes: [[name, value]], children }` for an element, with the
+/// tag as `localName` gives it, or `{ text }` for a text node. Comments and
+/// other nodes are left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SnapshotNode {
+    Element {
+        tag: String,
+        #[serde(default)]
+        attributes: Vec<(String, String)>,
+        #[serde(default)]
+        children: Vec<SnapshotNode>,
+    },
+    Text {
+        text: String,
+    },
+}
+
+/// The ID an element of a snapshot gets
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotId {
+    /// Positions among the element children from the snapshot's root down,
+    /// so `[1, 0]` is `root.children[1].children[0]`; `[]` is the root
+    pub path: Vec<usize>,
+    pub tag: String,
+    /// What the build does to the element: `skipped` elements keep the ID
+    /// they have
+    pub action: ChangeAction,
+    pub id: String,
+}
+
+/// What the build would do to a snapshot, without markup to write back
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotIds {
+    /// In document order
+    pub ids: Vec<SnapshotId>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+/// The IDs processing the page `root` was rendered from as HTML with
+/// `options` would give its elements, for inspectors highlighting them on
+/// the live page. The snapshot is written out as markup and processed like
+/// any HTML file, so filters and strategies apply as they do at build time;
+/// the elements are then found again by where they start in that markup.
+pub fn snapshot_ids(root: &SnapshotNode, options: &IdOptions) -> Result<SnapshotIds, String> {
+    let mut markup = String::new();
+    let mut paths = HashMap::new();
+    write_node(root, &mut Vec::new(), &mut markup, &mut paths);
+
+    let mut processor = Processor::new();
+    processor.generator_mut().enable_change_log();
+    let output = processor.process_with_meta(Format::Html, &markup, options)?;
+    let ids = processor
+        .generator_mut()
+        .take_changes()
+        .into_iter()
+        .filter_map(|change| {
+            Some(SnapshotId { path: paths.get(&change.offset)?.clone(), tag: change.tag, action: change.action, id: change.id })
+        })
+        .collect();
+    Ok(SnapshotIds { ids, warnings: output.warnings })
+}
+
+/// Writes `node` as HTML to `markup`, noting the path of each element by the
+/// offset of its start tag
+fn write_node(node: &SnapshotNode, path: &mut Vec<usize>, markup: &mut String, paths: &mut HashMap<usize, Vec<usize>>) {
+    let (tag, attributes, children) = match node {
+        SnapshotNode::Text { text } => {
+            markup.push_str(&text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;"));
+            return;
+        }
+        SnapshotNode::Element { tag, attributes, children } => (tag, attributes, children),
+    };
+    paths.insert(markup.len(), path.clone());
+    markup.push('<');
+    markup.push_str(tag);
+    for (name, value) in attributes {
+        markup.push_str(&format!(" {}=\"{}\"", name, ast_common::escape_attribute(value)));
+    }
+    markup.push('>');
+    let name = tag.to_ascii_lowercase();
+    if VOID_ELEMENTS.contains(&name.as_str()) {
+        return;
+    }
+
+    let mut index = 0;
+    for child in children {
+        match child {
+            SnapshotNode::Text { text } if RAW_TEXT_ELEMENTS.contains(&name.as_str()) => markup.push_str(text),
+            SnapshotNode::Text { .. } => write_node(child, path, markup, paths),
+            SnapshotNode::Element { .. } => {
+                path.push(index);
+                write_node(child, path, markup, paths);
+                path.pop();
+                index += 1;
+            }
+        }
+    }
+    markup.push_str(&format!("</{}>", tag));
+}
+
+#[cfg(all(test, feature = "html"))]
+mod tests {
+    use super::*;
+    use crate::html::HtmlProcessor;
+    use crate::AstProcessor;
+
+    #[test]
+    fn test_snapshot_ids() {
+        let snapshot: SnapshotNode = serde_json::from_str(
+            r#"{ "tag": "body", "children": [
+                { "tag": "script", "children": [{ "text": "if (a < b) render();" }] },
+                { "tag": "form", "attributes": [["class", "login"]], "children": [
+                    { "tag": "input", "attributes": [["name", "email"], ["data-ast-id", "email"]] },
+                    { "text": "Fish & <chips>" },
+                    { "tag": "button", "children": [{ "text": "Sign in" }] }
+                ] }
+            ] }"#,
+        )
+        .unwrap();
+        let options = IdOptions { selector: Some("form *".to_string()), ..IdOptions::default() };
+        let result = snapshot_ids(&snapshot, &options).unwrap();
+        let found: Vec<_> = result.ids.iter().map(|id| (id.path.clone(), id.tag.as_str(), id.action)).collect();
+        assert_eq!(found, [(vec![1, 0], "input", ChangeAction::Skipped), (vec![1, 1], "button", ChangeAction::Added)]);
+        assert_eq!(result.ids[0].id, "email");
+
+        // The IDs are those the page's markup gets
+        let html = r#"<body><script>if (a < b) render();</script><form class="login"><input name="email" data-ast-id="email">Fish &amp; &lt;chips&gt;<button>Sign in</button></form></body>"#;
+        let processed = HtmlProcessor::new().process(html, &options).unwrap();
+        assert!(processed.contains(&format!(r#"<button data-ast-id="{}">"#, result.ids[1].id)), "{}", processed);
+    }
+}
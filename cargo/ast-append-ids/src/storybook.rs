@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+pub const STORY_INDEX_VERSION: u32 = 1;
+
+/// File name endings of Storybook Component Story Format files
+pub const STORY_SUFFIXES: [&str; 4] = [".stories.tsx", ".stories.jsx", ".stories.ts", ".stories.js"];
+
+pub fn is_story_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| STORY_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)))
+}
+
+/// IDs rendered by each story, keyed by story file and then by the story's
+/// export name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoryIndex {
+    pub version: u32,
+    pub files: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+impl StoryIndex {
+    pub fn new() -> Self {
+        Self {
+            version: STORY_INDEX_VERSION,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the stories of `file`; files without stories are left out.
+    pub fn add(&mut self, file: String, stories: Vec<(String, Vec<String>)>) {
+        if !stories.is_empty() {
+            self.files.insert(file, stories.into_iter().collect());
+        }
+    }
+
+    pub fn story_count(&self) -> usize {
+        self.files.values().map(BTreeMap::len).sum()
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self)
+            .map(|json| json + "\n")
+            .map_err(|e| format!("Failed to serialize story index: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_story_index() {
+        assert!(is_story_file(Path::new("src/Button.stories.tsx")));
+        assert!(!is_story_file(Path::new("src/Button.tsx")));
+        assert!(!is_story_file(Path::new("src/stories.tsx")));
+
+        let mut index = StoryIndex::new();
+        index.add("Button.stories.tsx".to_string(), vec![("Primary".to_string(), vec!["el-1".to_string()])]);
+        index.add("Empty.stories.tsx".to_string(), Vec::new());
+        assert_eq!(index.story_count(), 1);
+
+        let json = index.to_json().unwrap();
+        assert!(json.contains("\"Primary\": [\n        \"el-1\"\n      ]"));
+        assert_eq!(serde_json::from_str::<StoryIndex>(&json).unwrap(), index);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::ast_common::AstNode;
+use crate::id_generator::IdGenerator;
+use crate::{IdOptions, IdStrategy};
+
+/// What a strategy has at hand besides the element
+pub struct StrategyContext<'a> {
+    /// Hands out the IDs; `ensure_unique` makes an ID unique among those
+    /// already used
+    pub generator: &'a mut IdGenerator,
+    /// Options in effect for the element, inline directives applied
+    pub options: &'a IdOptions,
+}
+
+/// Makes the ID of an element that has none to reuse. The built-in ones stand
+/// behind `IdStrategy`; others are set on the generator with
+/// `IdGenerator::with_strategy` and then used by every processor. IDs must
+/// carry `options.prefix` and be unique, which `ensure_unique` sees to.
+pub trait IdStrategyImpl {
+    fn id_for(&mut self, node: &AstNode, ctx: &mut StrategyContext) -> String;
+}
+
+/// Hash of the element's tag and path, computed as `IdOptions::scheme` says
+/// and cut to `IdOptions::hash_length`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HashStrategy;
+
+impl IdStrategyImpl for HashStrategy {
+    fn id_for(&mut self, node: &AstNode, ctx: &mut StrategyContext) -> String {
+        let options = ctx.options;
+        let (node_type, path) = (&node.node_type, &node.path);
+        match options.hash_length {
+            Some(length) => ctx
+                .generator
+                .generate_short_hash_id(node_type, path, &options.prefix, options.scheme, &options.salt, length),
+            None => ctx.generator.generate_scheme_hash_id(node_type, path, &options.prefix, options.scheme, &options.salt),
+        }
+    }
+}
+
+/// Slug of the element's text
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SlugStrategy;
+
+impl IdStrategyImpl for SlugStrategy {
+    fn id_for(&mut self, node: &AstNode, ctx: &mut StrategyContext) -> String {
+        let text = node.text_content.as_deref().unwrap_or("");
+        ctx.generator.generate_slug_id(text, &ctx.options.prefix)
+    }
+}
+
+/// Tag followed by the element's path
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PathStrategy;
+
+impl IdStrategyImpl for PathStrategy {
+    fn id_for(&mut self, node: &AstNode, ctx: &mut StrategyContext) -> String {
+        ctx.generator.generate_path_id(&node.node_type, &node.path, &ctx.options.prefix)
+    }
+}
+
+/// Labels of the enclosing taxonomy levels, the tag and an index
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TaxonomyStrategy;
+
+impl IdStrategyImpl for TaxonomyStrategy {
+    fn id_for(&mut self, node: &AstNode, ctx: &mut StrategyContext) -> String {
+        ctx.generator.generate_taxonomy_id(&node.node_type, &node.context, &ctx.options.prefix)
+    }
+}
+
+impl IdStrategy {
+    /// The built-in implementation of the strategy. The strategies hold no
+    /// state, so boxing them does not allocate.
+    pub fn implementation(&self) -> Box<dyn IdStrategyImpl> {
+        match self {
+            IdStrategy::Hash => Box::new(HashStrategy),
+            IdStrategy::Slug => Box::new(SlugStrategy),
+            IdStrategy::Path => Box::new(PathStrategy),
+            IdStrategy::Taxonomy => Box::new(TaxonomyStrategy),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_strategies() {
+        let node = AstNode {
+            node_type: "button".to_string(),
+            text_content: Some("Save draft".to_string()),
+            attributes: Vec::new(),
+            path: vec![0, 2],
+            context: vec!["main".to_string()],
+        };
+        let options = IdOptions::default();
+        let mut generator = IdGenerator::new();
+        let mut id_for = |strategy: IdStrategy| {
+            let mut ctx = StrategyContext { generator: &mut generator, options: &options };
+            strategy.implementation().id_for(&node, &mut ctx)
+        };
+
+        assert_eq!(id_for(IdStrategy::Slug), "el-save-draft");
+        assert_eq!(id_for(IdStrategy::Path), "el-button-0-2");
+        assert_eq!(id_for(IdStrategy::Taxonomy), "el-main.button-0");
+        assert_eq!(id_for(IdStrategy::Hash), IdGenerator::new().generate_hash_id("button", &[0, 2], "el-"));
+    }
+}
@@ -0,0 +1,111 @@
+use crate::id_generator::slugify;
+use crate::select::SelectedElement;
+use std::collections::HashMap;
+
+/// Elements whose text names what they are, whatever its length
+const LABELLED_TAGS: [&str; 13] =
+    ["a", "button", "label", "legend", "option", "summary", "th", "h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// Elements that name the elements after them
+const LABEL_TAGS: [&str; 8] = ["label", "legend", "h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// Words of text an alias takes at most
+const MAX_WORDS: usize = 4;
+
+/// Readable names for `elements`, given in document order, for reviewers
+/// promoting generated IDs to anchors (see `anchors`). An element is named
+/// after its own text when it is a button, link, heading or the like, or
+/// holds nothing but a few words, and otherwise after the nearest label or
+/// heading before it, followed by its role: `save-draft-button`,
+/// `email-input`. Elements with neither get `None`. Names are unique within
+/// `elements`.
+pub fn suggest_aliases(elements: &[SelectedElement]) -> Vec<Option<String>> {
+    let mut label: Option<String> = None;
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    elements
+        .iter()
+        .enumerate()
+        .map(|(index, element)| {
+            let tag = element.tag.to_lowercase();
+            // In document order, an element's descendants follow it
+            let has_children = elements.get(index + 1).is_some_and(|next| next.ancestors.len() > element.ancestors.len());
+            let own = own_label(&tag, &element.text, has_children);
+            let alias = own.clone().or_else(|| label.clone()).map(|name| with_role(&name, &tag));
+            if LABEL_TAGS.contains(&tag.as_str()) && own.is_some() {
+                label = own;
+            }
+            let alias = alias?;
+            let count = seen.entry(alias.clone()).or_insert(0);
+            *count += 1;
+            Some(if *count == 1 { alias } else { format!("{}-{}", alias, count) })
+        })
+        .collect()
+}
+
+/// The slug of `text` if it names an element with `tag`
+fn own_label(tag: &str, text: &str, has_children: bool) -> Option<String> {
+    let words = text.split_whitespace().count();
+    if words == 0 || ((words > MAX_WORDS || has_children) && !LABELLED_TAGS.contains(&tag)) {
+        return None;
+    }
+    let short: Vec<&str> = text.split_whitespace().take(MAX_WORDS).collect();
+    Some(slugify(&short.join(" "))).filter(|slug| !slug.is_empty())
+}
+
+/// `name` followed by what an element with `tag` is, unless it says so already
+fn with_role(name: &str, tag: &str) -> String {
+    let role = match tag {
+        "a" => "link",
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => "heading",
+        "textarea" | "select" => "input",
+        tag => tag,
+    };
+    let role = slugify(role);
+    if role.is_empty() || name == role || name.ends_with(&format!("-{}", role)) {
+        return name.to_string();
+    }
+    format!("{}-{}", name, role)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(tag: &str, text: &str) -> SelectedElement {
+        let ancestors = if tag == "form" { Vec::new() } else { vec!["form".to_string()] };
+        SelectedElement { offset: 0, tag: tag.to_string(), ancestors, id: None, text: text.to_string() }
+    }
+
+    #[test]
+    fn test_suggest_aliases() {
+        let elements = [
+            element("form", "Payment details"),
+            element("h2", "Payment details"),
+            element("label", "Card number"),
+            element("input", ""),
+            element("button", "Pay now"),
+            element("button", "Save draft"),
+            element("button", "Save draft"),
+            element("a", "Read the terms of service before paying"),
+            element("div", "…"),
+            element("form", "Card"),
+            element("span", "Card"),
+        ];
+        let aliases = suggest_aliases(&elements);
+        let expected = [
+            None,
+            Some("payment-details-heading"),
+            Some("card-number-label"),
+            Some("card-number-input"),
+            Some("pay-now-button"),
+            Some("save-draft-button"),
+            Some("save-draft-button-2"),
+            Some("read-the-terms-of-link"),
+            Some("card-number-div"),
+            // The form holds more than its text
+            Some("card-number-form"),
+            Some("card-span"),
+        ];
+        assert_eq!(aliases, expected.map(|alias| alias.map(str::to_string)));
+    }
+}
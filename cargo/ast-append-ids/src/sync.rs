@@ -0,0 +1,152 @@
+use crate::codemod::normalize_text;
+use crate::select::SelectedElement;
+use std::collections::HashSet;
+
+/// IDs to copy from a reference document to a structurally similar target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncPlan {
+    /// ID to set on each target element, in document order
+    pub ids: Vec<Option<String>>,
+    pub copied: usize,
+    /// Reference IDs left out: no target element matches, the match keeps an
+    /// ID of its own, or another target element already has the ID
+    pub skipped: Vec<String>,
+}
+
+/// Matches every element of `target` to the element of `reference` it most
+/// likely corresponds to, keeping document order. Only elements with the same
+/// tag are matched; among those, the alignment with the most similar enclosing
+/// tags and text wins. Time and memory are quadratic in the element counts.
+pub fn align(reference: &[SelectedElement], target: &[SelectedElement]) -> Vec<Option<usize>> {
+    let (n, m) = (reference.len(), target.len());
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    let score = |i: usize, j: usize| similarity(&reference[i], &target[j]);
+
+    // best[at(i, j)]: best total score aligning reference[i..] with target[j..]
+    let mut best = vec![0f32; (n + 1) * (m + 1)];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            let skip = best[at(i + 1, j)].max(best[at(i, j + 1)]);
+            best[at(i, j)] = match score(i, j) {
+                Some(score) => skip.max(score + best[at(i + 1, j + 1)]),
+                None => skip,
+            };
+        }
+    }
+
+    let mut matches = vec![None; m];
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if score(i, j).is_some_and(|score| best[at(i, j)] == score + best[at(i + 1, j + 1)]) {
+            matches[j] = Some(i);
+            i += 1;
+            j += 1;
+        } else if best[at(i + 1, j)] >= best[at(i, j + 1)] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matches
+}
+
+/// Plans copying the IDs of `reference` elements to the `target` elements
+/// `align` matches them to. Target elements that have an ID keep it unless
+/// `overwrite` is set.
+pub fn sync_ids(reference: &[SelectedElement], target: &[SelectedElement], overwrite: bool) -> SyncPlan {
+    let taken: HashSet<&str> = target.iter().filter_map(|element| element.id.as_deref()).collect();
+    let mut ids = vec![None; target.len()];
+    let mut copied = 0;
+    let mut synced = HashSet::new();
+
+    for (j, i) in align(reference, target).into_iter().enumerate() {
+        let Some(id) = i.and_then(|i| reference[i].id.as_deref()) else {
+            continue;
+        };
+        let current = target[j].id.as_deref();
+        if current == Some(id) {
+            synced.insert(id);
+        } else if (current.is_none() || overwrite) && !taken.contains(id) {
+            ids[j] = Some(id.to_string());
+            synced.insert(id);
+            copied += 1;
+        }
+    }
+
+    let skipped = reference
+        .iter()
+        .filter_map(|element| element.id.as_deref())
+        .filter(|id| !synced.contains(id))
+        .map(str::to_string)
+        .collect();
+    SyncPlan { ids, copied, skipped }
+}
+
+/// 1 for the same tag, plus up to 1 each for the share of enclosing tags and
+/// of words the elements have in common. `None` for different tags.
+fn similarity(a: &SelectedElement, b: &SelectedElement) -> Option<f32> {
+    if a.tag != b.tag {
+        return None;
+    }
+
+    let longest = a.ancestors.len().max(b.ancestors.len());
+    let common = a.ancestors.iter().zip(&b.ancestors).take_while(|(a, b)| a == b).count();
+    let path = if longest == 0 { 1.0 } else { common as f32 / longest as f32 };
+
+    let (a_text, b_text) = (normalize_text(&a.text).to_lowercase(), normalize_text(&b.text).to_lowercase());
+    let text = if a_text == b_text {
+        1.0
+    } else {
+        let a_words: HashSet<&str> = a_text.split(' ').collect();
+        let b_words: HashSet<&str> = b_text.split(' ').collect();
+        a_words.intersection(&b_words).count() as f32 / a_words.union(&b_words).count() as f32
+    };
+
+    Some(1.0 + path + text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(tag: &str, ancestors: &[&str], id: Option<&str>, text: &str) -> SelectedElement {
+        SelectedElement {
+            offset: 0,
+            tag: tag.to_string(),
+            ancestors: ancestors.iter().map(|a| a.to_string()).collect(),
+            id: id.map(str::to_string),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_sync_ids() {
+        let reference = vec![
+            element("main", &[], Some("el-main"), "Buy now Cancel"),
+            element("button", &["main"], Some("el-buy"), "Buy now"),
+            element("button", &["main"], Some("el-cancel"), "Cancel"),
+            element("p", &["main"], Some("el-note"), "Fine print"),
+        ];
+        // A translation with a banner added
+        let target = vec![
+            element("main", &[], None, "Acheter Annuler"),
+            element("div", &["main"], None, "Soldes"),
+            element("button", &["main"], None, "Acheter"),
+            element("button", &["main"], Some("cta"), "Annuler"),
+            element("p", &["main"], None, "Petit texte"),
+            element("span", &["main", "p"], Some("el-note"), ""),
+        ];
+
+        assert_eq!(align(&reference, &target), vec![Some(0), None, Some(1), Some(2), Some(3), None]);
+
+        let plan = sync_ids(&reference, &target, false);
+        let main = Some("el-main".to_string());
+        assert_eq!(plan.ids, vec![main, None, Some("el-buy".to_string()), None, None, None]);
+        assert_eq!(plan.copied, 2);
+        assert_eq!(plan.skipped, vec!["el-cancel", "el-note"]);
+
+        let plan = sync_ids(&reference, &target, true);
+        assert_eq!(plan.ids[3].as_deref(), Some("el-cancel"));
+        assert_eq!(plan.copied, 3);
+    }
+}
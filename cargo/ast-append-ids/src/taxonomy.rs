@@ -0,0 +1,165 @@
+use crate::ast_common;
+use crate::id_generator::slugify;
+use crate::resolved::ResolvedOptions;
+use crate::selector::{Selector, SelectorElement};
+use crate::{IdOptions, IdStrategy};
+
+/// Levels used when none are configured, outermost first
+pub const DEFAULT_LEVELS: [&str; 3] = [
+    "area=header, nav, main, aside, footer, [role=banner], [role=navigation], [role=main], \
+     [role=complementary], [role=contentinfo]",
+    "section=section, article, form, dialog, [role=region], [role=dialog], [role=form]",
+    "component=[data-component], [data-ast-component]",
+];
+
+/// Attributes a level element's label is read from, in order. Elements without
+/// any of them are labelled with their tag name.
+pub const LABEL_ATTRIBUTES: [&str; 5] = ["aria-label", "data-component", "data-ast-component", "id", "name"];
+
+/// A level of the taxonomy: the elements matching `selector` name the part of
+/// the document they enclose
+#[derive(Debug, Clone)]
+pub struct TaxonomyLevel {
+    pub name: String,
+    selector: Selector,
+}
+
+impl TaxonomyLevel {
+    /// Parses `name=selector list`, e.g. `area=header, nav, main`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (name, selector) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid taxonomy level '{}': expected name=selector", spec))?;
+        Ok(Self {
+            name: name.trim().to_string(),
+            selector: Selector::parse(selector)?,
+        })
+    }
+
+    /// `specs` as levels, outermost first; the `DEFAULT_LEVELS` when there
+    /// are none. With `ignore_case`, level selectors match tag names
+    /// regardless of case.
+    pub fn parse_all(specs: &[String], ignore_case: bool) -> Result<Vec<Self>, String> {
+        let levels = if specs.is_empty() {
+            DEFAULT_LEVELS.iter().map(|spec| Self::parse(spec)).collect()
+        } else {
+            specs.iter().map(|spec| Self::parse(spec)).collect::<Result<Vec<_>, _>>()
+        }?;
+        Ok(levels
+            .into_iter()
+            .map(|level| Self { selector: level.selector.ignoring_case(ignore_case), ..level })
+            .collect())
+    }
+}
+
+/// Tracks the enclosing level elements while a processor walks a document, to
+/// build the context of each element for the taxonomy strategy.
+#[derive(Debug)]
+pub struct TaxonomyTracker {
+    levels: Vec<TaxonomyLevel>,
+    path: Vec<SelectorElement>,
+    /// For each element of `path`, its label at every level it matches
+    labels: Vec<Vec<Option<String>>>,
+}
+
+impl TaxonomyTracker {
+    /// `specs` are `name=selector list` levels, outermost first; the
+    /// `DEFAULT_LEVELS` are used when there are none.
+    pub fn new(specs: &[String]) -> Result<Self, String> {
+        Ok(Self::with_levels(TaxonomyLevel::parse_all(specs, false)?))
+    }
+
+    pub fn with_levels(levels: Vec<TaxonomyLevel>) -> Self {
+        Self {
+            levels,
+            path: Vec::new(),
+            labels: Vec::new(),
+        }
+    }
+
+    /// A tracker for processing `content`, if the taxonomy strategy is in use
+    /// there, either through `options` or an inline directive, with the
+    /// levels compiled in `resolved`
+    pub fn for_document(content: &str, options: &IdOptions, resolved: &ResolvedOptions) -> Option<Self> {
+        (options.strategy == IdStrategy::Taxonomy || ast_common::OptionOverrides::any_selects(content, &IdStrategy::Taxonomy))
+            .then(|| Self::with_levels(resolved.taxonomy_levels.clone()))
+    }
+
+    /// Number of open elements, for `truncate` at the element's end.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Called when an element starts. Returns its context: for each level,
+    /// outermost first, the label of the innermost enclosing element of that
+    /// level. Levels without one are left out. Elements with content stay open
+    /// until `truncate` is called with the depth from before this call.
+    pub fn enter(&mut self, element: SelectorElement, has_content: bool) -> Vec<String> {
+        let context = (0..self.levels.len())
+            .filter_map(|level| self.labels.iter().rev().find_map(|labels| labels[level].clone()))
+            .collect();
+
+        if has_content {
+            self.path.push(element);
+            let labels = self
+                .levels
+                .iter()
+                .map(|level| level.selector.matches(&self.path).then(|| label(&self.path[self.path.len() - 1])))
+                .collect();
+            self.labels.push(labels);
+        }
+        context
+    }
+
+    /// Closes the elements entered since the tracker was at `depth`.
+    pub fn truncate(&mut self, depth: usize) {
+        self.path.truncate(depth);
+        self.labels.truncate(depth);
+    }
+}
+
+fn label(element: &SelectorElement) -> String {
+    let label = LABEL_ATTRIBUTES
+        .iter()
+        .filter_map(|name| ast_common::find_attribute(&element.attributes, name))
+        .map(slugify)
+        .find(|label| !label.is_empty());
+    label.unwrap_or_else(|| slugify(&element.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(name: &str, attributes: &[(&str, &str)]) -> SelectorElement {
+        SelectorElement {
+            name: name.to_string(),
+            attributes: attributes.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+
+    #[test]
+    fn test_taxonomy_tracker() {
+        let mut tracker = TaxonomyTracker::new(&[]).unwrap();
+        assert!(tracker.enter(element("body", &[]), true).is_empty());
+        assert!(tracker.enter(element("main", &[]), true).is_empty());
+
+        let depth = tracker.depth();
+        assert_eq!(tracker.enter(element("section", &[("aria-label", "Order Summary")]), true), vec!["main"]);
+        tracker.enter(element("div", &[("data-component", "PriceRow")]), true);
+        assert_eq!(
+            tracker.enter(element("button", &[]), false),
+            vec!["main", "order-summary", "pricerow"]
+        );
+        tracker.truncate(depth);
+        assert_eq!(tracker.enter(element("p", &[]), false), vec!["main"]);
+
+        let levels = vec!["page=[role=document]".to_string(), "panel=.panel".to_string()];
+        let mut tracker = TaxonomyTracker::new(&levels).unwrap();
+        tracker.enter(element("div", &[("class", "panel big"), ("id", "cart")]), true);
+        assert_eq!(tracker.enter(element("a", &[]), false), vec!["cart"]);
+
+        assert!(TaxonomyTracker::new(&["area".to_string()]).is_err());
+        assert!(TaxonomyTracker::new(&["area=main >".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What looks wrong, for callers that act on some warnings and not others
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningKind {
+    /// Two elements of the document carry the same ID
+    DuplicateId,
+    /// An element carries the ID attribute with nothing in it
+    EmptyId,
+    /// An element's tag is in both `include` and `exclude`, e.g. spelled in
+    /// another case where the format ignores case; exclude wins
+    IncludedAndExcluded,
+    /// `selector` matched no element of the document
+    SelectorMatchedNothing,
+}
+
+/// Something suspicious noticed while processing a document: the run went
+/// through, but likely not as meant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub message: String,
+    /// Start of the element in the processor's input, when the warning is
+    /// about one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+}
+
+impl Warning {
+    pub fn duplicate_id(id: &str, offset: usize) -> Self {
+        Self {
+            kind: WarningKind::DuplicateId,
+            message: format!("ID '{}' is already on another element", id),
+            offset: Some(offset),
+        }
+    }
+
+    pub fn empty_id(tag: &str, attr: &str, offset: usize) -> Self {
+        Self {
+            kind: WarningKind::EmptyId,
+            message: format!("<{}> has an empty {}", tag, attr),
+            offset: Some(offset),
+        }
+    }
+
+    pub fn included_and_excluded(tag: &str) -> Self {
+        Self {
+            kind: WarningKind::IncludedAndExcluded,
+            message: format!("<{}> is both included and excluded, so it is not tagged", tag),
+            offset: None,
+        }
+    }
+
+    pub fn selector_matched_nothing(selector: &str) -> Self {
+        Self {
+            kind: WarningKind::SelectorMatchedNothing,
+            message: format!("Selector '{}' matched no elements", selector),
+            offset: None,
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
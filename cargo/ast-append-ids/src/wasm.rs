@@ -1,15 +1,41 @@
 use wasm_bindgen::prelude::*;
 use serde_wasm_bindgen::{from_value, to_value};
-use crate::{AstProcessor, IdOptions};
-use crate::jsx::JsxProcessor;
-use crate::xml::XmlProcessor;
-use crate::html::HtmlProcessor;
+use crate::{IdOptions, Processor};
+use crate::compat;
+use crate::detect::{self, Format};
+use crate::snapshot::{self, SnapshotNode};
+use crate::warnings::Warning;
+use crate::watch::{FileChange, WatchSession};
 
 #[wasm_bindgen]
 pub struct WasmAstProcessor {
-    jsx_processor: Option<JsxProcessor>,
-    xml_processor: Option<XmlProcessor>,
-    html_processor: Option<HtmlProcessor>,
+    processor: Processor,
+    watch: WatchSession,
+    /// Warnings of the last document processed
+    warnings: Vec<Warning>,
+}
+
+/// Reads options passed from JavaScript over the defaults, in the shape of
+/// `IdOptions` or of the JS plugins (camelCase, `tags`, `skip`,
+/// `idPropName`), rejecting unknown keys and invalid values
+fn parse_options(options: JsValue) -> Result<IdOptions, JsValue> {
+    let options: serde_json::Value = if options.is_undefined() || options.is_null() {
+        serde_json::Value::Object(serde_json::Map::new())
+    } else {
+        from_value(options).map_err(|e| JsValue::from_str(&format!("Invalid options: {}", e)))?
+    };
+    compat::options_from_value(options).map_err(|e| JsValue::from_str(&e))
+}
+
+impl WasmAstProcessor {
+    /// Formats left out of this build are an error
+    fn process(&mut self, format: Format, content: &str, options: JsValue) -> Result<String, JsValue> {
+        let options = parse_options(options)?;
+        self.warnings.clear();
+        let output = self.processor.process_with_meta(format, content, &options).map_err(|e| JsValue::from_str(&e))?;
+        self.warnings = output.warnings;
+        Ok(output.content)
+    }
 }
 
 #[wasm_bindgen]
@@ -18,81 +44,84 @@ impl WasmAstProcessor {
     pub fn new() -> Self {
         crate::set_panic_hook();
         Self {
-            jsx_processor: None,
-            xml_processor: None,
-            html_processor: None,
+            processor: Processor::new(),
+            watch: WatchSession::new(),
+            warnings: Vec::new(),
         }
     }
 
     #[wasm_bindgen(js_name = processJsx)]
     pub fn process_jsx(&mut self, content: &str, options: JsValue) -> Result<String, JsValue> {
-        let options: IdOptions = from_value(options)
-            .map_err(|e| JsValue::from_str(&format!("Invalid options: {}", e)))?;
-        
-        if self.jsx_processor.is_none() {
-            self.jsx_processor = Some(JsxProcessor::new());
-        }
-        
-        self.jsx_processor
-            .as_mut()
-            .unwrap()
-            .process(content, &options)
-            .map_err(|e| JsValue::from_str(&e))
+        self.process(Format::Jsx, content, options)
     }
 
     #[wasm_bindgen(js_name = processXml)]
     pub fn process_xml(&mut self, content: &str, options: JsValue) -> Result<String, JsValue> {
-        let options: IdOptions = from_value(options)
-            .map_err(|e| JsValue::from_str(&format!("Invalid options: {}", e)))?;
-        
-        if self.xml_processor.is_none() {
-            self.xml_processor = Some(XmlProcessor::new());
-        }
-        
-        self.xml_processor
-            .as_mut()
-            .unwrap()
-            .process(content, &options)
-            .map_err(|e| JsValue::from_str(&e))
+        self.process(Format::Xml, content, options)
     }
 
     #[wasm_bindgen(js_name = processHtml)]
     pub fn process_html(&mut self, content: &str, options: JsValue) -> Result<String, JsValue> {
-        let options: IdOptions = from_value(options)
-            .map_err(|e| JsValue::from_str(&format!("Invalid options: {}", e)))?;
-        
-        if self.html_processor.is_none() {
-            self.html_processor = Some(HtmlProcessor::new());
-        }
-        
-        self.html_processor
-            .as_mut()
-            .unwrap()
-            .process(content, &options)
-            .map_err(|e| JsValue::from_str(&e))
+        self.process(Format::Html, content, options)
     }
 
+    /// Scripts without JSX and Vue files come back unchanged
     #[wasm_bindgen(js_name = processAuto)]
     pub fn process_auto(&mut self, content: &str, options: JsValue) -> Result<String, JsValue> {
-        let options: IdOptions = from_value(options)
-            .map_err(|e| JsValue::from_str(&format!("Invalid options: {}", e)))?;
-        
-        // Auto-detect content type
-        let trimmed = content.trim();
-        
-        if trimmed.starts_with("<?xml") || trimmed.starts_with("<svg") {
-            self.process_xml(content, to_value(&options).unwrap())
-        } else if trimmed.starts_with("<!DOCTYPE") || trimmed.starts_with("<html") {
-            self.process_html(content, to_value(&options).unwrap())
-        } else if trimmed.contains("jsx") || trimmed.contains("React") || trimmed.contains("=>") {
-            self.process_jsx(content, to_value(&options).unwrap())
-        } else if trimmed.starts_with("<") {
-            // Default to HTML for generic markup
-            self.process_html(content, to_value(&options).unwrap())
-        } else {
-            // Assume JSX for JavaScript-like content
-            self.process_jsx(content, to_value(&options).unwrap())
-        }
+        let options = parse_options(options)?;
+        self.warnings.clear();
+        let (_, output) = self.processor.process_auto(None, content, &options).map_err(|e| JsValue::from_str(&e))?;
+        self.warnings = output.warnings;
+        Ok(output.content)
+    }
+
+    /// Warnings about the document the last `process*` call handled, as
+    /// `[{ kind, message, offset }]`, e.g. a selector that matched nothing
+    #[wasm_bindgen(js_name = takeWarnings)]
+    pub fn take_warnings(&mut self) -> Result<JsValue, JsValue> {
+        use serde::Serialize;
+
+        std::mem::take(&mut self.warnings)
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// The IDs the build would give the elements of a DOM snapshot taken by a
+    /// devtools extension, `{ tag, attributes: [[name, value]], children }`
+    /// with `{ text }` for text nodes, without rewriting any markup: `[{ path,
+    /// tag, action, id }]`, `path` being element child positions from the
+    /// snapshot's root. See `snapshot::snapshot_ids`; warnings go to
+    /// `takeWarnings`.
+    #[wasm_bindgen(js_name = idsForSnapshot)]
+    pub fn ids_for_snapshot(&mut self, snapshot: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+        use serde::Serialize;
+
+        let options = parse_options(options)?;
+        let snapshot: SnapshotNode =
+            from_value(snapshot).map_err(|e| JsValue::from_str(&format!("Invalid DOM snapshot: {}", e)))?;
+        self.warnings.clear();
+        let result = snapshot::snapshot_ids(&snapshot, &options).map_err(|e| JsValue::from_str(&e))?;
+        self.warnings = result.warnings;
+        result
+            .ids
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Processes a batch of watcher events, `[{ event, path, content }]` with
+    /// chokidar's event names, returning `[{ path, status, content, error }]`
+    /// in the order the paths come in. See `watch::WatchSession`.
+    #[wasm_bindgen(js_name = processChanges)]
+    pub fn process_changes(&mut self, changes: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+        use serde::Serialize;
+
+        let options = parse_options(options)?;
+        let changes: Vec<FileChange> =
+            from_value(changes).map_err(|e| JsValue::from_str(&format!("Invalid file events: {}", e)))?;
+        self.watch
+            .apply(changes, &options)
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
     }
 }
 
@@ -102,6 +131,29 @@ pub fn create_default_options() -> JsValue {
     to_value(&options).unwrap()
 }
 
+/// JSON Schema of the options object, to validate options before processing
+#[wasm_bindgen(js_name = optionsSchema)]
+pub fn options_schema() -> Result<JsValue, JsValue> {
+    use serde::Serialize;
+
+    // Plain objects rather than JS Maps, so the schema can go to JSON.stringify
+    IdOptions::json_schema()
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// What `content` is, as `processAuto` works it out, with the extension of
+/// `path` (a file name will do) as one more signal: `{ format, confidence,
+/// signals }`, see `detect::Detection`
+#[wasm_bindgen(js_name = detectFormat)]
+pub fn detect_format(content: &str, path: Option<String>) -> Result<JsValue, JsValue> {
+    use serde::Serialize;
+
+    detect::detect(path.as_deref().map(std::path::Path::new), content)
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
 #[wasm_bindgen]
 pub fn version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
@@ -133,12 +185,70 @@ mod tests {
     use super::*;
     use wasm_bindgen_test::*;
 
+    #[cfg(all(feature = "jsx", feature = "html", feature = "xml"))]
     #[wasm_bindgen_test]
     fn test_wasm_processor_creation() {
-        let processor = WasmAstProcessor::new();
-        assert!(processor.jsx_processor.is_none());
-        assert!(processor.xml_processor.is_none());
-        assert!(processor.html_processor.is_none());
+        let mut processor = WasmAstProcessor::new();
+        for content in ["const a = <b />;", "<p>a</p>", "<?xml version=\"1.0\"?><a/>"] {
+            assert_ne!(processor.process_auto(content, JsValue::UNDEFINED).unwrap(), content);
+        }
+        let script = "export const one = 1;";
+        assert_eq!(processor.process_auto(script, JsValue::UNDEFINED).unwrap(), script);
+    }
+
+    #[cfg(feature = "jsx")]
+    #[wasm_bindgen_test]
+    fn test_heap_reused_across_documents() {
+        // wee_alloc could not reuse freed blocks for swc's allocations, so the
+        // heap grew with every document; whichever allocator is built in must
+        // give the memory of one run to the next
+        let source = format!("const A = () => <ul>{}</ul>;", "<li><a href=\"#\">Item</a></li>".repeat(2000));
+        let run = || {
+            let mut processor = WasmAstProcessor::new();
+            processor.process_jsx(&source, JsValue::UNDEFINED).unwrap();
+        };
+        run();
+        let pages = core::arch::wasm32::memory_size::<0>();
+        for _ in 0..20 {
+            run();
+        }
+        assert!(core::arch::wasm32::memory_size::<0>() <= pages + pages / 4, "grew from {} pages", pages);
+    }
+
+    #[cfg(feature = "html")]
+    #[wasm_bindgen_test]
+    fn test_process_changes() {
+        let mut processor = WasmAstProcessor::new();
+        let changes = to_value(&[FileChange {
+            event: crate::watch::FileEvent::Change,
+            path: "index.html".to_string(),
+            content: Some("<p>Hi</p>".to_string()),
+        }])
+        .unwrap();
+        let results: Vec<crate::watch::FileResult> =
+            from_value(processor.process_changes(changes, JsValue::UNDEFINED).unwrap()).unwrap();
+        assert_eq!(results[0].status, crate::watch::FileStatus::Processed);
+    }
+
+    #[cfg(feature = "html")]
+    #[wasm_bindgen_test]
+    fn test_ids_for_snapshot() {
+        let mut processor = WasmAstProcessor::new();
+        let snapshot = SnapshotNode::Element {
+            tag: "main".to_string(),
+            attributes: Vec::new(),
+            children: vec![SnapshotNode::Element { tag: "p".to_string(), attributes: Vec::new(), children: Vec::new() }],
+        };
+        let ids: Vec<snapshot::SnapshotId> =
+            from_value(processor.ids_for_snapshot(to_value(&snapshot).unwrap(), JsValue::UNDEFINED).unwrap()).unwrap();
+        assert_eq!(ids.iter().map(|id| id.path.clone()).collect::<Vec<_>>(), [vec![], vec![0]]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_detect_format() {
+        let detection = detect_format("const a = <b />;", Some("a.js".to_string())).unwrap();
+        let format = js_sys::Reflect::get(&detection, &"format".into()).unwrap();
+        assert_eq!(format.as_string().as_deref(), Some("jsx"));
     }
 
     #[wasm_bindgen_test]
@@ -152,4 +262,12 @@ mod tests {
         let options = create_default_options();
         assert!(!options.is_undefined());
     }
+
+    #[wasm_bindgen_test]
+    fn test_options_are_validated() {
+        let options = create_default_options();
+        assert!(parse_options(options.clone()).is_ok());
+        js_sys::Reflect::set(&options, &"perfix".into(), &"x-".into()).unwrap();
+        assert!(parse_options(options).is_err());
+    }
 }
\ No newline at end of file
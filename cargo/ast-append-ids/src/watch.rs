@@ -0,0 +1,204 @@
+use crate::detect::Format;
+use crate::pragma::{self, FilePragma};
+use crate::warnings::Warning;
+use crate::{IdOptions, Processor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// What happened to a file, named like the events of chokidar and the
+/// watchers modelled on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileEvent {
+    Add,
+    Change,
+    Unlink,
+    AddDir,
+    UnlinkDir,
+}
+
+/// A file event as a dev server hands it over, with the file's content for
+/// `add` and `change`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChange {
+    pub event: FileEvent,
+    pub path: String,
+    #[serde(default)]
+    pub content: Option<String>,
+}
+
+/// What became of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    /// IDs were added; `content` is the file to write
+    Processed,
+    /// Nothing to add, or the content is what this session last produced for
+    /// the path, i.e. its own write coming back
+    Unchanged,
+    /// Not a file with a processor, or opted out with a file pragma
+    Skipped,
+    /// Unlinked; the session forgot it
+    Removed,
+    Error,
+}
+
+/// Outcome for one path of a batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileResult {
+    pub path: String,
+    pub status: FileStatus,
+    /// The processed file, with `Processed` only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Suspicious things noticed processing the file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+impl FileResult {
+    fn new(path: String, status: FileStatus) -> Self {
+        Self { path, status, content: None, error: None, warnings: Vec::new() }
+    }
+}
+
+/// Processes the files a watcher reports as they change, for dev servers
+/// that tag sources on the fly. Each file is processed on its own, so a file
+/// gets the same IDs whatever else changed before it. The session remembers
+/// what it produced for each path, so writing a result back does not come
+/// round as another change.
+#[derive(Debug, Default)]
+pub struct WatchSession {
+    /// Last content produced or seen unchanged, per path
+    outputs: HashMap<String, String>,
+}
+
+impl WatchSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles a batch of events. Only the last event for a path counts, as
+    /// watchers often report an `add` and a `change` for one write; results
+    /// come in the order the paths first appear in the batch.
+    pub fn apply(&mut self, changes: Vec<FileChange>, options: &IdOptions) -> Vec<FileResult> {
+        let mut order: Vec<String> = Vec::new();
+        let mut latest: HashMap<String, FileChange> = HashMap::new();
+        for change in changes {
+            if !latest.contains_key(&change.path) {
+                order.push(change.path.clone());
+            }
+            latest.insert(change.path.clone(), change);
+        }
+        order
+            .into_iter()
+            .filter_map(|path| latest.remove(&path))
+            .map(|change| self.apply_one(change, options))
+            .collect()
+    }
+
+    fn apply_one(&mut self, change: FileChange, options: &IdOptions) -> FileResult {
+        let path = change.path;
+        let content = match change.event {
+            FileEvent::Add | FileEvent::Change => change.content,
+            FileEvent::Unlink => {
+                self.outputs.remove(&path);
+                return FileResult::new(path, FileStatus::Removed);
+            }
+            FileEvent::UnlinkDir => {
+                let dir = format!("{}/", path.trim_end_matches('/'));
+                self.outputs.retain(|file, _| !file.starts_with(&dir));
+                return FileResult::new(path, FileStatus::Removed);
+            }
+            FileEvent::AddDir => return FileResult::new(path, FileStatus::Skipped),
+        };
+        let Some(content) = content else {
+            let mut result = FileResult::new(path, FileStatus::Error);
+            result.error = Some("No content given for the file".to_string());
+            return result;
+        };
+        if self.outputs.get(&path) == Some(&content) {
+            return FileResult::new(path, FileStatus::Unchanged);
+        }
+        if pragma::detect_file_pragma(&content, pragma::DEFAULT_PRAGMA_LINES) == Some(FilePragma::Off) {
+            return FileResult::new(path, FileStatus::Skipped);
+        }
+
+        match Processor::new().process_auto(Some(Path::new(&path)), &content, options) {
+            Ok((Format::Script | Format::Vue, _)) => FileResult::new(path, FileStatus::Skipped),
+            Ok((_, output)) if output.content == content => {
+                self.outputs.insert(path.clone(), output.content);
+                FileResult { warnings: output.warnings, ..FileResult::new(path, FileStatus::Unchanged) }
+            }
+            Ok((_, output)) => {
+                self.outputs.insert(path.clone(), output.content.clone());
+                FileResult {
+                    content: Some(output.content),
+                    warnings: output.warnings,
+                    ..FileResult::new(path, FileStatus::Processed)
+                }
+            }
+            Err(e) => {
+                self.outputs.remove(&path);
+                FileResult { error: Some(e), ..FileResult::new(path, FileStatus::Error) }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "jsx", feature = "html"))]
+mod tests {
+    use super::*;
+
+    fn change(event: FileEvent, path: &str, content: Option<&str>) -> FileChange {
+        FileChange { event, path: path.to_string(), content: content.map(str::to_string) }
+    }
+
+    #[test]
+    fn test_watch_session() {
+        let options = IdOptions::default();
+        let mut session = WatchSession::new();
+        let results = session.apply(
+            vec![
+                change(FileEvent::Add, "src/App.tsx", Some("export const App = () => <main />;")),
+                change(FileEvent::Add, "public/index.html", Some("<p>Draft</p>")),
+                change(FileEvent::Change, "public/index.html", Some("<p>Hi</p>")),
+                change(FileEvent::Add, "src/util.ts", Some("export const one = 1;")),
+                change(FileEvent::Change, "src/empty.tsx", None),
+            ],
+            &options,
+        );
+        let statuses: Vec<_> = results.iter().map(|result| (result.path.as_str(), result.status)).collect();
+        assert_eq!(
+            statuses,
+            [
+                ("src/App.tsx", FileStatus::Processed),
+                ("public/index.html", FileStatus::Processed),
+                ("src/util.ts", FileStatus::Skipped),
+                ("src/empty.tsx", FileStatus::Error),
+            ]
+        );
+        let html = results[1].content.clone().unwrap();
+        assert!(html.starts_with("<p data-ast-id=") && html.ends_with(">Hi</p>"), "{}", html);
+
+        // Writing the result back is not another change
+        let echo = session.apply(vec![change(FileEvent::Change, "public/index.html", Some(&html))], &options);
+        assert_eq!(echo[0].status, FileStatus::Unchanged);
+
+        // The same content gets the same IDs once the path was forgotten
+        let results = session.apply(
+            vec![
+                change(FileEvent::UnlinkDir, "public", None),
+                change(FileEvent::Unlink, "src/App.tsx", None),
+            ],
+            &options,
+        );
+        assert!(results.iter().all(|result| result.status == FileStatus::Removed));
+        assert!(session.outputs.is_empty());
+        let again = session.apply(vec![change(FileEvent::Add, "public/index.html", Some("<p>Hi</p>"))], &options);
+        assert_eq!(again[0].content.as_deref(), Some(html.as_str()));
+    }
+}
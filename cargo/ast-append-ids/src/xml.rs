@@ -1,10 +1,21 @@
-use crate::ast_common::{self, AstNode};
+use crate::ast_common::{self, AstNode, Candidate, ComponentScopes, IdLocation, IgnoreTracker, OptionOverrides, OverrideScopes, IGNORE_ATTR};
+use crate::coverage::PolicyElement;
+use crate::extra_attrs;
 use crate::id_generator::IdGenerator;
-use crate::{AstProcessor, IdOptions};
-use quick_xml::events::{BytesStart, Event};
-use quick_xml::reader::Reader;
+use crate::resolved::ResolvedOptions;
+use crate::select::SelectedElement;
+use crate::selector::{Selector, SelectorElement};
+use crate::taxonomy::TaxonomyTracker;
+use crate::detect::Format;
+use crate::{AstProcessor, IdOptions, ProcessOutput, RunMetadata};
+use quick_xml::events::attributes::Attribute;
+use quick_xml::events::{BytesStart, BytesText, Event};
+use quick_xml::name::ResolveResult;
+use quick_xml::reader::{NsReader, Reader};
 use quick_xml::writer::Writer;
+use std::collections::HashSet;
 use std::io::Cursor;
+use std::rc::Rc;
 
 pub struct XmlProcessor {
     generator: IdGenerator,
@@ -12,9 +23,17 @@ pub struct XmlProcessor {
 
 impl XmlProcessor {
     pub fn new() -> Self {
-        Self {
-            generator: IdGenerator::new(),
-        }
+        Self::with_generator(IdGenerator::new())
+    }
+
+    /// Creates a processor that draws IDs from an existing generator, e.g. one
+    /// pre-seeded with reserved IDs or shared across several files.
+    pub fn with_generator(generator: IdGenerator) -> Self {
+        Self { generator }
+    }
+
+    pub fn into_generator(self) -> IdGenerator {
+        self.generator
     }
 
     #[allow(dead_code)]
@@ -43,112 +62,907 @@ impl XmlProcessor {
         
         text_content
     }
+}
 
-    fn process_element(
+impl Default for XmlProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AstProcessor for XmlProcessor {
+    fn generator_mut(&mut self) -> &mut IdGenerator {
+        &mut self.generator
+    }
+
+    fn process_with_meta(&mut self, content: &str, options: &IdOptions) -> Result<ProcessOutput, String> {
+        let resolved = Rc::new(ResolvedOptions::new(options, Format::Xml)?);
+        self.process_resolved(content, options, &resolved)
+    }
+
+    fn process_resolved(
         &mut self,
-        element: &mut BytesStart,
+        content: &str,
         options: &IdOptions,
-        path: &[usize],
-    ) -> Option<String> {
-        let element_name = String::from_utf8_lossy(element.name().as_ref()).to_string();
-        
-        // Check existing attributes
-        let mut existing_id = None;
-        for attr in element.attributes() {
-            if let Ok(attr) = attr {
-                if String::from_utf8_lossy(attr.key.as_ref()) == options.attr {
-                    existing_id = Some(String::from_utf8_lossy(&attr.value).to_string());
-                    break;
+        resolved: &Rc<ResolvedOptions>,
+    ) -> Result<ProcessOutput, String> {
+        ast_common::with_meta(self, options, |processor| processor.process_document(content, options, resolved))
+    }
+}
+
+impl XmlProcessor {
+    fn process_document(&mut self, content: &str, options: &IdOptions, resolved: &Rc<ResolvedOptions>) -> Result<String, String> {
+        let _span = tracing::debug_span!("process", format = "xml", bytes = content.len()).entered();
+        let generator = std::mem::take(&mut self.generator);
+        let mut visitor = XmlVisitor::with_resolved(content, options, resolved.clone(), generator)?;
+        // Events borrow from `content`; only elements that get an ID are copied
+        let mut writer = Writer::new(Vec::with_capacity(content.len() + content.len() / 4));
+        let result = visitor.try_for_each(|event| {
+            writer.write_event(event?).map_err(|e| format!("Write error: {}", e))
+        });
+        self.generator = visitor.into_generator();
+        result?;
+
+        String::from_utf8(writer.into_inner())
+            .map(|output| ast_common::match_layout(content, output))
+            .map_err(|e| format!("UTF-8 conversion error: {}", e))
+    }
+}
+
+/// Reads an XML document event by event, adding IDs to start tags on the
+/// way, for pipelines that work on quick-xml events themselves: the events
+/// can go through other steps before a `Writer` gets them. `XmlProcessor` is
+/// this visitor writing every event as it comes. Reading stops after the
+/// first error.
+pub struct XmlVisitor<'a> {
+    reader: NsReader<&'a [u8]>,
+    options: &'a IdOptions,
+    tagger: Tagger,
+    /// Counter value at each open element
+    path_stack: Vec<usize>,
+    /// Position of each element in document order, for the text lookup
+    element_index: usize,
+    element_counter: usize,
+    components: ComponentScopes,
+    tracker: IgnoreTracker,
+    ignored_stack: Vec<bool>,
+    scopes: OverrideScopes,
+    scope_stack: Vec<bool>,
+    taxonomy: Option<TaxonomyTracker>,
+    /// Whether each element's text matches, looked up before its start tag
+    /// is passed on
+    text_matches: Option<Vec<bool>>,
+    /// Enclosing elements, when a selector is in use
+    selector_path: Vec<SelectorElement>,
+    /// Label of the comment just read, when it is to be anchored
+    anchor: Option<String>,
+    /// Event read ahead of the anchor put before it, with its offset
+    queued: Option<(usize, Event<'a>)>,
+    /// Whether `IdOptions::metadata` is in the document yet
+    metadata_written: bool,
+    done: bool,
+}
+
+/// Target of the processing instructions anchoring comments
+pub const ANCHOR_TARGET: &str = "ast-anchor";
+
+/// Target of the processing instruction recording the run, see
+/// `IdOptions::metadata`
+pub const METADATA_TARGET: &str = "ast-append-ids";
+
+/// Root element attribute recording the run, see `IdOptions::metadata`
+pub const METADATA_ATTR: &str = "data-ast-run";
+
+/// What IDs are made with
+struct Tagger {
+    generator: IdGenerator,
+    /// Compiled from the options of the document being read
+    resolved: Rc<ResolvedOptions>,
+}
+
+impl<'a> XmlVisitor<'a> {
+    /// A visitor over `content` drawing IDs from `generator`
+    pub fn new(content: &'a str, options: &'a IdOptions, generator: IdGenerator) -> Result<Self, String> {
+        let resolved = Rc::new(ResolvedOptions::new(options, Format::Xml)?);
+        Self::with_resolved(content, options, resolved, generator)
+    }
+
+    /// `new` with `options` compiled up front, e.g. once for many documents
+    pub fn with_resolved(
+        content: &'a str,
+        options: &'a IdOptions,
+        resolved: Rc<ResolvedOptions>,
+        generator: IdGenerator,
+    ) -> Result<Self, String> {
+        let taxonomy = TaxonomyTracker::for_document(content, options, &resolved);
+        let text_matches = match &resolved.text_pattern {
+            Some(pattern) => Some(direct_texts(content)?.iter().map(|text| pattern.is_match(text)).collect()),
+            None => None,
+        };
+        Ok(Self {
+            // Whitespace between elements is kept so the document's layout survives
+            reader: NsReader::from_str(content),
+            options,
+            tagger: Tagger { generator, resolved },
+            path_stack: Vec::new(),
+            element_index: 0,
+            element_counter: 0,
+            components: ComponentScopes::default(),
+            tracker: IgnoreTracker::default(),
+            ignored_stack: Vec::new(),
+            scopes: OverrideScopes::default(),
+            scope_stack: Vec::new(),
+            taxonomy,
+            text_matches,
+            selector_path: Vec::new(),
+            anchor: None,
+            queued: None,
+            metadata_written: false,
+            done: false,
+        })
+    }
+
+    /// The generator, with the IDs given so far
+    pub fn into_generator(self) -> IdGenerator {
+        self.tagger.generator
+    }
+
+    /// Reads the next event, giving a start tag its ID. `None` at the end
+    /// of the document.
+    fn visit(&mut self) -> Result<Option<Event<'a>>, String> {
+        let options = self.options;
+        let counts_all = options.counter.counts_all();
+        // Start of the next event, for the change log
+        let (offset, read) = match self.queued.take() {
+            Some((offset, event)) => (offset, Ok(event)),
+            None => (self.reader.buffer_position(), self.reader.read_event()),
+        };
+        if let (Some(label), Ok(event)) = (self.anchor.take(), &read) {
+            // An anchor written by an earlier run stays, unless overwriting
+            let existing = match event {
+                Event::PI(pi) => anchor_id(pi, &options.attr),
+                _ => None,
+            };
+            let replaced = existing.is_some();
+            if let Some(pi) = self.anchor_event(&label, existing, offset) {
+                if !replaced {
+                    self.queued = read.ok().map(|event| (offset, event));
+                }
+                return Ok(Some(pi));
+            }
+        }
+        if options.metadata == RunMetadata::Pi && !self.metadata_written {
+            match &read {
+                // A record of an earlier run is replaced where it is
+                Ok(Event::PI(pi)) if is_metadata(pi) => {
+                    self.metadata_written = true;
+                    return Ok(Some(metadata_event(options)));
+                }
+                Ok(Event::Start(_) | Event::Empty(_)) => {
+                    self.metadata_written = true;
+                    self.queued = read.ok().map(|event| (offset, event));
+                    return Ok(Some(metadata_event(options)));
                 }
+                _ => {}
             }
         }
+        let event = match read {
+            Ok(Event::Start(mut elem)) => {
+                self.tagger.generator.count_visit();
+                if let Some(component) = component_root(&elem, options) {
+                    self.components.enter(self.path_stack.len(), std::mem::take(&mut self.element_counter));
+                    self.scopes.add_component(self.tagger.resolved.component_overrides(options, &component));
+                }
+                self.path_stack.push(self.element_counter);
+
+                let name = element_name(&self.reader, &elem);
+                let ignored = self.tracker.enter(has_ignore_attr(&elem), true);
+                self.ignored_stack.push(ignored);
+                self.scope_stack.push(self.scopes.enter(options, true));
+                let options = self.scopes.current(options);
+                let context = match &mut self.taxonomy {
+                    Some(tracker) => tracker.enter(selector_element(&elem, &name)?, true),
+                    None => Vec::new(),
+                };
+                let selector = self.tagger.resolved.selector.as_ref();
+                let selected = enter_selector(selector, &mut self.selector_path, &elem, &name)?;
+
+                if !ignored {
+                    let text_matched = self.text_matched();
+                    match ast_common::pick_element(&mut self.tagger.generator, selected, text_matched) {
+                        Ok(()) => {
+                            let path = &self.path_stack[self.components.start()..];
+                            if let Some(id) = self.tagger.process_element(&mut elem, &name, options, path, context, offset) {
+                                elem.push_attribute((options.attr.as_str(), id.as_str()));
+                                self.element_counter += usize::from(!counts_all);
+                            }
+                        }
+                        Err(reason) => ast_common::record_decision(&mut self.tagger.generator, offset, &name, Some(reason)),
+                    }
+                }
+
+                self.record_run(&mut elem);
+                self.element_index += 1;
+                self.element_counter += usize::from(counts_all);
+                Event::Start(elem)
+            }
+            Ok(Event::End(e)) => {
+                self.path_stack.pop();
+                if let Some(counter) = self.components.leave(self.path_stack.len()) {
+                    self.element_counter = counter;
+                }
+                self.selector_path.truncate(self.path_stack.len());
+                if let Some(tracker) = &mut self.taxonomy {
+                    tracker.truncate(self.path_stack.len());
+                }
+                if self.ignored_stack.pop() == Some(true) {
+                    self.tracker.leave();
+                }
+                if self.scope_stack.pop() == Some(true) {
+                    self.scopes.leave();
+                }
+                Event::End(e)
+            }
+            Ok(Event::Empty(mut elem)) => {
+                self.tagger.generator.count_visit();
+                if let Some(component) = component_root(&elem, options) {
+                    self.components.enter(self.path_stack.len(), std::mem::take(&mut self.element_counter));
+                    self.scopes.add_component(self.tagger.resolved.component_overrides(options, &component));
+                }
+                self.path_stack.push(self.element_counter);
 
-        if !ast_common::should_process_node(&element_name, options, existing_id.as_deref()) {
+                let name = element_name(&self.reader, &elem);
+                let ignored = self.tracker.enter(has_ignore_attr(&elem), false);
+                self.scopes.enter(options, false);
+                let options = self.scopes.current(options);
+                let context = match &mut self.taxonomy {
+                    Some(tracker) => tracker.enter(selector_element(&elem, &name)?, false),
+                    None => Vec::new(),
+                };
+                let selector = self.tagger.resolved.selector.as_ref();
+                let selected = enter_selector(selector, &mut self.selector_path, &elem, &name)?;
+
+                if !ignored {
+                    let text_matched = self.text_matched();
+                    match ast_common::pick_element(&mut self.tagger.generator, selected, text_matched) {
+                        Ok(()) => {
+                            let path = &self.path_stack[self.components.start()..];
+                            if let Some(id) = self.tagger.process_element(&mut elem, &name, options, path, context, offset) {
+                                elem.push_attribute((options.attr.as_str(), id.as_str()));
+                                self.element_counter += usize::from(!counts_all);
+                            }
+                        }
+                        Err(reason) => ast_common::record_decision(&mut self.tagger.generator, offset, &name, Some(reason)),
+                    }
+                }
+
+                self.record_run(&mut elem);
+                self.selector_path.truncate(self.path_stack.len() - 1);
+                self.path_stack.pop();
+                self.element_index += 1;
+                self.element_counter += usize::from(counts_all);
+                if let Some(counter) = self.components.leave(self.path_stack.len()) {
+                    self.element_counter = counter;
+                }
+                Event::Empty(elem)
+            }
+            Ok(Event::Comment(c)) => {
+                let text = String::from_utf8_lossy(&c);
+                if ast_common::is_ignore_comment(&text) {
+                    self.tracker.mark_next();
+                } else if let Some(overrides) = OptionOverrides::parse(&text)? {
+                    self.scopes.set_pending(overrides);
+                }
+                let ignored = self.ignored_stack.last() == Some(&true);
+                if let Some(found) = self.tagger.resolved.comment_anchors.as_ref().and_then(|pattern| pattern.find(&text)) {
+                    if !ignored && ast_common::in_range(options, offset) {
+                        self.anchor = Some(text[found.end()..].trim().to_string());
+                    }
+                }
+                Event::Comment(c)
+            }
+            Ok(Event::Eof) => return Ok(None),
+            Ok(e) => e,
+            Err(e) => return Err(format!("XML parsing error: {}", e)),
+        };
+        Ok(Some(event))
+    }
+
+    /// The processing instruction anchoring the comment labelled `label`, or
+    /// `None` to keep the anchor with the `existing` ID that follows it
+    fn anchor_event(&mut self, label: &str, existing: Option<String>, offset: usize) -> Option<Event<'a>> {
+        let options = self.scopes.current(self.options);
+        if existing.is_some() && !options.overwrite {
+            ast_common::record_change(&mut self.tagger.generator, offset, "comment", existing.as_deref(), None);
+            return None;
+        }
+        // Numbered like the element that would come next
+        let mut path = self.path_stack[self.components.start()..].to_vec();
+        path.push(self.element_counter);
+        let node = AstNode {
+            node_type: "comment".to_string(),
+            text_content: Some(label.to_string()),
+            attributes: Vec::new(),
+            path,
+            context: Vec::new(),
+        };
+        let generator = &mut self.tagger.generator;
+        let id = ast_common::generate_id_for_node(generator, &node, options, &self.tagger.resolved);
+        ast_common::record_change(generator, offset, "comment", existing.as_deref(), Some(&id));
+        let content = format!("{} {}=\"{}\"", ANCHOR_TARGET, options.attr, quick_xml::escape::escape(&id));
+        Some(Event::PI(BytesText::from_escaped(content)))
+    }
+
+    /// Writes `IdOptions::metadata` to the root element, when it goes in an
+    /// attribute, replacing that of an earlier run
+    fn record_run(&mut self, element: &mut BytesStart) {
+        if self.options.metadata != RunMetadata::Attribute || self.metadata_written {
+            return;
+        }
+        self.metadata_written = true;
+        let value = metadata_fields(self.options)
+            .iter()
+            .map(|(name, value)| match *name {
+                "version" => format!("{}@{}", METADATA_TARGET, value),
+                _ => format!("{}={}", name, value),
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        let original = element.clone();
+        element.clear_attributes();
+        for attr in original.attributes().flatten() {
+            if attr.key.as_ref() != METADATA_ATTR.as_bytes() {
+                element.push_attribute(attr);
+            }
+        }
+        element.push_attribute((METADATA_ATTR, value.as_str()));
+    }
+
+    /// Whether the text of the element being entered matches `text_matches`
+    fn text_matched(&self) -> bool {
+        self.text_matches.as_ref().is_none_or(|matches| matches[self.element_index])
+    }
+}
+
+impl<'a> Iterator for XmlVisitor<'a> {
+    type Item = Result<Event<'a>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
             return None;
         }
+        let event = self.visit().transpose();
+        self.done = !matches!(event, Some(Ok(_)));
+        event
+    }
+}
+
+impl Tagger {
+    fn process_element(
+        &mut self,
+        element: &mut BytesStart,
+        element_name: &str,
+        options: &IdOptions,
+        path: &[usize],
+        context: Vec<String>,
+        offset: usize,
+    ) -> Option<String> {
+        let attr_key = options.attr.as_bytes();
+
+        // Decide on the borrowed event first; most elements need no allocation
+        // beyond the attribute that gets appended
+        let existing_id = {
+            let existing = element
+                .attributes()
+                .flatten()
+                .find(|attr| attr.key.as_ref() == attr_key)
+                .map(|attr| attribute_value(&attr));
+            let existing_id = existing.as_deref();
+            let has_origin = options
+                .origin_attr
+                .as_ref()
+                .is_some_and(|origin| element.attributes().flatten().any(|attr| attr.key.as_ref() == origin.as_bytes()));
+            let class = filtered_class(element, options);
+            let candidate = Candidate {
+                name: element_name,
+                classes: &class.split_whitespace().collect::<Vec<_>>(),
+                offset,
+                existing_id,
+                dynamic_id: false,
+                has_origin,
+            };
+            if let Err(reason) = ast_common::check_element(&mut self.generator, &self.resolved.filter, options, &candidate) {
+                ast_common::record_change(&mut self.generator, offset, element_name, existing_id, None);
+                ast_common::record_decision(&mut self.generator, offset, element_name, Some(reason));
+                return None;
+            }
+            existing
+        };
 
         let node = AstNode {
-            node_type: element_name,
+            node_type: element_name.to_string(),
             text_content: None,
-            attributes: Vec::new(),
+            attributes: element
+                .attributes()
+                .flatten()
+                .map(|attr| (String::from_utf8_lossy(attr.key.as_ref()).into_owned(), attribute_value(&attr)))
+                .collect(),
             path: path.to_vec(),
+            context,
         };
 
-        let id = ast_common::generate_id_for_node(&mut self.generator, &node, options);
+        let id = ast_common::generate_id_for_node(&mut self.generator, &node, options, &self.resolved);
+        ast_common::record_change(&mut self.generator, offset, &node.node_type, existing_id.as_deref(), Some(&id));
+        ast_common::record_decision(&mut self.generator, offset, &node.node_type, None);
         
-        // Remove existing attribute if overwriting
-        if options.overwrite && existing_id.is_some() {
+        // Remove the existing attributes if overwriting, keeping all others,
+        // and extra attributes about to be written again. Cloning a borrowed
+        // event only copies the reference.
+        let origin_key = options.origin_attr.as_deref().map(str::as_bytes);
+        let extra = extra_attrs::render_all(&self.resolved.extra_attrs, &node, &id);
+        let is_extra = |key: &[u8]| extra.iter().any(|(name, _)| name.as_bytes() == key);
+        let overwriting = options.overwrite && existing_id.is_some();
+        if overwriting || node.attributes.iter().any(|(key, _)| is_extra(key.as_bytes())) {
+            let original = element.clone();
             element.clear_attributes();
-            // Re-add all attributes except the target one
-            // Note: This is simplified; in production, we'd preserve all other attributes
+            for attr in original.attributes().flatten() {
+                let key = attr.key.as_ref();
+                let replaced = overwriting && (key == attr_key || Some(key) == origin_key);
+                if !replaced && !is_extra(key) {
+                    element.push_attribute(attr);
+                }
+            }
+        }
+        // Written before the ID, which the caller appends
+        if let Some(origin_attr) = &options.origin_attr {
+            element.push_attribute((origin_attr.as_str(), ast_common::origin(options).as_str()));
+        }
+        for (name, value) in &extra {
+            element.push_attribute((name.as_str(), value.as_str()));
         }
         
         Some(id)
     }
 }
 
-impl AstProcessor for XmlProcessor {
-    fn process(&mut self, content: &str, options: &IdOptions) -> Result<String, String> {
-        let mut reader = Reader::from_str(content);
-        reader.trim_text(true);
-        
-        let mut writer = Writer::new(Cursor::new(Vec::new()));
-        let mut buf = Vec::new();
-        let mut path_stack = Vec::new();
-        let mut element_counter = 0;
+/// The ID in `pi` if it is an anchor, see `IdOptions::comment_anchors`
+fn anchor_id(pi: &BytesText, attr: &str) -> Option<String> {
+    let content = std::str::from_utf8(pi).ok()?;
+    let rest = content.strip_prefix(ANCHOR_TARGET)?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let (_, value) = rest.split_once(&format!("{}=\"", attr))?;
+    let (value, _) = value.split_once('"')?;
+    Some(quick_xml::escape::unescape(value).map_or_else(|_| value.to_string(), |value| value.into_owned()))
+}
 
-        loop {
-            match reader.read_event_into(&mut buf) {
-                Ok(Event::Start(ref e)) => {
-                    let mut elem = e.clone();
-                    path_stack.push(element_counter);
-                    
-                    if let Some(id) = self.process_element(&mut elem, options, &path_stack) {
-                        elem.push_attribute((options.attr.as_bytes(), id.as_bytes()));
-                    }
-                    
-                    writer.write_event(Event::Start(elem))
-                        .map_err(|e| format!("Write error: {}", e))?;
-                    
-                    element_counter += 1;
-                }
-                Ok(Event::End(ref e)) => {
-                    path_stack.pop();
-                    writer.write_event(Event::End(e.clone()))
-                        .map_err(|e| format!("Write error: {}", e))?;
-                }
-                Ok(Event::Empty(ref e)) => {
-                    let mut elem = e.clone();
-                    path_stack.push(element_counter);
-                    
-                    if let Some(id) = self.process_element(&mut elem, options, &path_stack) {
-                        elem.push_attribute((options.attr.as_bytes(), id.as_bytes()));
+/// What `IdOptions::metadata` records: the version, the options hash and the
+/// timestamp, if any
+fn metadata_fields(options: &IdOptions) -> Vec<(&'static str, String)> {
+    let mut fields = vec![("version", env!("CARGO_PKG_VERSION").to_string()), ("options", options.options_hash())];
+    if let Some(timestamp) = options.metadata_timestamp {
+        fields.push(("timestamp", timestamp.to_string()));
+    }
+    fields
+}
+
+/// The processing instruction recording the run
+fn metadata_event(options: &IdOptions) -> Event<'static> {
+    let mut content = METADATA_TARGET.to_string();
+    for (name, value) in metadata_fields(options) {
+        content.push_str(&format!(" {}=\"{}\"", name, value));
+    }
+    Event::PI(BytesText::from_escaped(content))
+}
+
+/// Whether `pi` records a run
+fn is_metadata(pi: &BytesText) -> bool {
+    pi.strip_prefix(METADATA_TARGET.as_bytes())
+        .is_some_and(|rest| rest.first().is_none_or(u8::is_ascii_whitespace))
+}
+
+/// Text directly inside each element, leaving out that of its child elements,
+/// in document order.
+fn direct_texts(content: &str) -> Result<Vec<String>, String> {
+    let mut reader = Reader::from_str(content);
+    let mut texts = Vec::new();
+    let mut open = Vec::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(_)) => {
+                open.push(texts.len());
+                texts.push(String::new());
+            }
+            Ok(Event::Empty(_)) => texts.push(String::new()),
+            Ok(Event::End(_)) => {
+                open.pop();
+            }
+            Ok(Event::Text(e)) => {
+                if let Some(&index) = open.last() {
+                    let text = e.unescape().map_err(|e| format!("XML parsing error: {}", e))?;
+                    texts[index].push_str(&text);
+                }
+            }
+            Ok(Event::CData(e)) => {
+                if let Some(&index) = open.last() {
+                    texts[index].push_str(&String::from_utf8_lossy(&e));
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => return Err(format!("XML parsing error: {}", e)),
+        }
+    }
+
+    Ok(texts)
+}
+
+/// Enters an element in the selector path, returning whether `selector`
+/// matches it. Without a selector every element matches.
+fn enter_selector(
+    selector: Option<&Selector>,
+    path: &mut Vec<SelectorElement>,
+    element: &BytesStart,
+    name: &str,
+) -> Result<bool, String> {
+    let Some(selector) = selector else {
+        return Ok(true);
+    };
+    path.push(selector_element(element, name)?);
+    Ok(selector.matches(path))
+}
+
+/// The `class` attribute of `element`, when the `include_class` filter
+/// needs it
+fn filtered_class(element: &BytesStart, options: &IdOptions) -> String {
+    if options.include_class.is_empty() {
+        return String::new();
+    }
+    let class = element.attributes().flatten().find(|attr| attr.key.as_ref() == b"class");
+    class.as_ref().map(attribute_value).unwrap_or_default()
+}
+
+/// Component `element` is the root of, with `component_scope`
+fn component_root(element: &BytesStart, options: &IdOptions) -> Option<String> {
+    let attr = options.component_scope.as_deref()?;
+    let component = element.attributes().flatten().find(|a| a.key.as_ref() == attr.as_bytes())?;
+    Some(attribute_value(&component)).filter(|name| !name.is_empty())
+}
+
+/// Value of `attr` with its character references decoded, or as written when
+/// they do not decode
+fn attribute_value(attr: &Attribute) -> String {
+    match attr.unescape_value() {
+        Ok(value) => value.into_owned(),
+        Err(_) => String::from_utf8_lossy(&attr.value).into_owned(),
+    }
+}
+
+fn has_ignore_attr(element: &BytesStart) -> bool {
+    element
+        .attributes()
+        .flatten()
+        .any(|attr| attr.key.as_ref() == IGNORE_ATTR.as_bytes())
+}
+
+/// Returns the values of `attr` already present in an XML document.
+pub fn existing_ids(content: &str, attr: &str) -> Result<Vec<String>, String> {
+    Ok(locate_ids(content, attr)?.into_iter().map(|location| location.id).collect())
+}
+
+/// Finds every element carrying `attr`, in document order.
+pub fn locate_ids(content: &str, attr: &str) -> Result<Vec<IdLocation>, String> {
+    let mut reader = NsReader::from_str(content);
+    let mut locations = Vec::new();
+
+    loop {
+        let offset = reader.buffer_position();
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                for a in e.attributes().flatten() {
+                    if a.key.as_ref() == attr.as_bytes() {
+                        let value = a
+                            .unescape_value()
+                            .map_err(|e| format!("XML parsing error: {}", e))?;
+                        // Values as the processor fingerprints them
+                        let attributes = e
+                            .attributes()
+                            .flatten()
+                            .map(|a| (String::from_utf8_lossy(a.key.as_ref()).into_owned(), attribute_value(&a)))
+                            .collect();
+                        locations.push(IdLocation::new(
+                            offset,
+                            element_name(&reader, e),
+                            value.to_string(),
+                            attributes,
+                            attr,
+                        ));
                     }
-                    
-                    writer.write_event(Event::Empty(elem))
-                        .map_err(|e| format!("Write error: {}", e))?;
-                    
-                    path_stack.pop();
-                    element_counter += 1;
                 }
-                Ok(Event::Eof) => break,
-                Ok(e) => {
-                    writer.write_event(e)
-                        .map_err(|e| format!("Write error: {}", e))?;
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => return Err(format!("XML parsing error: {}", e)),
+        }
+    }
+
+    Ok(locations)
+}
+
+/// Finds every element matching `policy` (see `Selector`) and whether it
+/// carries `attr`, in document order.
+pub fn policy_elements(content: &str, policy: &str, attr: &str) -> Result<Vec<PolicyElement>, String> {
+    let selector = Selector::parse(policy)?;
+    let mut reader = NsReader::from_str(content);
+    let mut path = Vec::new();
+    let mut elements = Vec::new();
+
+    loop {
+        let offset = reader.buffer_position();
+        let (element, empty) = match reader.read_event() {
+            Ok(Event::Start(e)) => (e, false),
+            Ok(Event::Empty(e)) => (e, true),
+            Ok(Event::End(_)) => {
+                path.pop();
+                continue;
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => continue,
+            Err(e) => return Err(format!("XML parsing error: {}", e)),
+        };
+
+        let element = selector_element(&element, &element_name(&reader, &element))?;
+        let covered = element.attribute(attr).is_some_and(|id| !id.trim().is_empty());
+        let tag = element.name.clone();
+        path.push(element);
+        if selector.matches(&path) {
+            elements.push(PolicyElement { offset, tag, covered });
+        }
+        if empty {
+            path.pop();
+        }
+    }
+
+    Ok(elements)
+}
+
+/// Finds every element matching `selector`, with its ID and the text of its
+/// descendants (including CDATA), in document order.
+pub fn select_elements(content: &str, selector: &Selector, attr: &str) -> Result<Vec<SelectedElement>, String> {
+    let mut reader = NsReader::from_str(content);
+    let mut path = Vec::new();
+    let mut elements = Vec::new();
+    // Selected elements collecting text, with their depth in `path`
+    let mut open: Vec<(usize, usize)> = Vec::new();
+
+    loop {
+        let offset = reader.buffer_position();
+        let (element, empty) = match reader.read_event() {
+            Ok(Event::Start(e)) => (e, false),
+            Ok(Event::Empty(e)) => (e, true),
+            Ok(Event::End(_)) => {
+                path.pop();
+                open.retain(|&(depth, _)| depth < path.len());
+                continue;
+            }
+            Ok(Event::Text(e)) => {
+                let text = e.unescape().map_err(|e| format!("XML parsing error: {}", e))?;
+                append_text(&mut elements, &open, &text);
+                continue;
+            }
+            Ok(Event::CData(e)) => {
+                append_text(&mut elements, &open, &String::from_utf8_lossy(&e));
+                continue;
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => continue,
+            Err(e) => return Err(format!("XML parsing error: {}", e)),
+        };
+
+        let element = selector_element(&element, &element_name(&reader, &element))?;
+        let id = element.attribute(attr).map(String::from);
+        let tag = element.name.clone();
+        path.push(element);
+        if selector.matches(&path) {
+            let depth = path.len() - 1;
+            if !empty {
+                open.push((depth, elements.len()));
+            }
+            let ancestors = path[..depth].iter().map(|element| element.name.clone()).collect();
+            elements.push(SelectedElement { offset, tag, ancestors, id, text: String::new() });
+        }
+        if empty {
+            path.pop();
+        }
+    }
+
+    Ok(elements)
+}
+
+fn append_text(elements: &mut [SelectedElement], open: &[(usize, usize)], text: &str) {
+    for &(_, index) in open {
+        elements[index].text.push_str(text);
+    }
+}
+
+/// Removes `attr` from every element whose value is one of `ids`, leaving
+/// hand-written values alone. With `origin_attr`, only elements carrying it
+/// count as generated, and they lose it too.
+pub fn remove_ids(
+    content: &str,
+    attr: &str,
+    ids: &HashSet<String>,
+    origin_attr: Option<&str>,
+) -> Result<String, String> {
+    let mut reader = Reader::from_str(content);
+    let mut writer = Writer::new(Cursor::new(Vec::new()));
+
+    let strip = |e: &BytesStart| -> BytesStart<'static> {
+        let has_key = |key: &str| e.attributes().flatten().any(|a| a.key.as_ref() == key.as_bytes());
+        let generated = origin_attr.is_none_or(has_key)
+            && e.attributes().flatten().any(|a| {
+                a.key.as_ref() == attr.as_bytes() && a.unescape_value().is_ok_and(|v| ids.contains(v.as_ref()))
+            });
+        let mut elem = e.to_owned();
+        if !generated {
+            return elem;
+        }
+        elem.clear_attributes();
+        for a in e.attributes().flatten() {
+            if a.key.as_ref() != attr.as_bytes() && Some(a.key.as_ref()) != origin_attr.map(str::as_bytes) {
+                elem.push_attribute(a);
+            }
+        }
+        elem
+    };
+
+    loop {
+        let event = match reader.read_event() {
+            Ok(Event::Start(e)) => Event::Start(strip(&e)),
+            Ok(Event::Empty(e)) => Event::Empty(strip(&e)),
+            Ok(Event::Eof) => break,
+            Ok(e) => e,
+            Err(e) => return Err(format!("XML parsing error: {}", e)),
+        };
+        writer.write_event(event)
+            .map_err(|e| format!("Write error: {}", e))?;
+    }
+
+    let output = writer.into_inner().into_inner();
+    String::from_utf8(output).map_err(|e| format!("UTF-8 conversion error: {}", e))
+}
+
+/// Sets `attr` on every element matching `selector`, replacing any value it
+/// has. The first match gets `value`; `generator` makes the others unique.
+/// Returns the output and the IDs written, in document order.
+pub fn set_ids(
+    content: &str,
+    selector: &Selector,
+    attr: &str,
+    value: &str,
+    generator: &mut IdGenerator,
+) -> Result<(String, Vec<String>), String> {
+    let mut matched = Vec::new();
+    let mut all = Vec::new();
+    visit_elements(content, attr, |path| {
+        let id = path.last().and_then(|element| element.attribute(attr)).map(str::to_string);
+        if selector.matches(path) {
+            matched.push(id.clone());
+        }
+        all.extend(id);
+        None
+    })?;
+
+    let ids = ast_common::manual_ids(generator, value, all, &matched);
+    let mut queue = ids.iter();
+    let output = visit_elements(content, attr, |path| {
+        if selector.matches(path) {
+            queue.next().map(String::as_str)
+        } else {
+            None
+        }
+    })?;
+
+    Ok((output, ids))
+}
+
+/// Sets `attr` on the elements whose entry in `ids` is `Some`, replacing any
+/// value they have. `ids` has an entry per element, in document order.
+pub fn apply_ids(content: &str, attr: &str, ids: &[Option<String>]) -> Result<String, String> {
+    let mut ids = ids.iter();
+    visit_elements(content, attr, |_| ids.next().and_then(Option::as_deref))
+}
+
+/// Streams `content`, calling `visit` with the path of open elements at every
+/// start tag. When it returns a value, `attr` is set to it, replacing any
+/// previous value. Returns the rewritten document.
+fn visit_elements<'v>(
+    content: &str,
+    attr: &str,
+    mut visit: impl FnMut(&[SelectorElement]) -> Option<&'v str>,
+) -> Result<String, String> {
+    let mut reader = NsReader::from_str(content);
+    let mut writer = Writer::new(Vec::with_capacity(content.len()));
+    let mut path: Vec<SelectorElement> = Vec::new();
+
+    let mut set = |elem: &mut BytesStart, path: &[SelectorElement]| {
+        if let Some(id) = visit(path) {
+            let original = elem.clone();
+            elem.clear_attributes();
+            for a in original.attributes().flatten() {
+                if a.key.as_ref() != attr.as_bytes() {
+                    elem.push_attribute(a);
                 }
-                Err(e) => return Err(format!("XML parsing error: {}", e)),
             }
-            buf.clear();
+            elem.push_attribute((attr, id));
         }
+    };
 
-        let output = writer.into_inner().into_inner();
-        String::from_utf8(output).map_err(|e| format!("UTF-8 conversion error: {}", e))
+    loop {
+        let event = match reader.read_event() {
+            Ok(Event::Start(mut elem)) => {
+                path.push(selector_element(&elem, &element_name(&reader, &elem))?);
+                set(&mut elem, &path);
+                Event::Start(elem)
+            }
+            Ok(Event::Empty(mut elem)) => {
+                path.push(selector_element(&elem, &element_name(&reader, &elem))?);
+                set(&mut elem, &path);
+                path.pop();
+                Event::Empty(elem)
+            }
+            Ok(Event::End(e)) => {
+                path.pop();
+                Event::End(e)
+            }
+            Ok(Event::Eof) => break,
+            Ok(e) => e,
+            Err(e) => return Err(format!("XML parsing error: {}", e)),
+        };
+        writer.write_event(event)
+            .map_err(|e| format!("Write error: {}", e))?;
     }
+
+    String::from_utf8(writer.into_inner()).map_err(|e| format!("UTF-8 conversion error: {}", e))
+}
+
+const MATHML_NAMESPACE: &[u8] = b"http://www.w3.org/1998/Math/MathML";
+
+/// Name of `element`, just read by `reader`: MathML elements go by their local
+/// name, as they are written with and without a prefix (`m:mfrac` or
+/// `mfrac`); other elements by their name as written.
+fn element_name(reader: &NsReader<&[u8]>, element: &BytesStart) -> String {
+    let name = match reader.resolve_element(element.name()) {
+        (ResolveResult::Bound(namespace), local) if namespace.as_ref() == MATHML_NAMESPACE => local.into_inner(),
+        _ => element.name().into_inner(),
+    };
+    String::from_utf8_lossy(name).into_owned()
+}
+
+/// An open element named `name` as seen by a `Selector`
+fn selector_element(element: &BytesStart, name: &str) -> Result<SelectorElement, String> {
+    let attributes = element
+        .attributes()
+        .flatten()
+        .map(|a| {
+            let value = a
+                .unescape_value()
+                .map_err(|e| format!("XML parsing error: {}", e))?;
+            Ok((String::from_utf8_lossy(a.key.as_ref()).into_owned(), value.into_owned()))
+        })
+        .collect::<Result<_, String>>()?;
+    Ok(SelectorElement {
+        name: name.to_string(),
+        attributes,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::IdStrategy;
 
     #[test]
     fn test_xml_processing() {
@@ -179,6 +993,41 @@ mod tests {
         assert!(result.contains(&format!("{}=", options.attr)));
     }
 
+    #[test]
+    fn test_xml_mathml_namespace() {
+        let mut processor = XmlProcessor::new();
+        let options = IdOptions {
+            strategy: IdStrategy::Path,
+            include: vec!["mi".to_string(), "mfrac".to_string(), "db:para".to_string()],
+            ..IdOptions::default()
+        };
+
+        // MathML elements go by their local name, whatever their prefix;
+        // other namespaces keep theirs
+        let input = concat!(
+            r#"<db:para xmlns:db="http://docbook.org/ns/docbook" xmlns:m="http://www.w3.org/1998/Math/MathML">"#,
+            r#"<m:math><m:mfrac><m:mi>a</m:mi><m:mn>2</m:mn></m:mfrac></m:math>"#,
+            r#"<math xmlns="http://www.w3.org/1998/Math/MathML"><mi>b</mi></math></db:para>"#,
+        );
+        let result = processor.process(input, &options).unwrap();
+        assert_eq!(
+            result,
+            concat!(
+                r#"<db:para xmlns:db="http://docbook.org/ns/docbook" xmlns:m="http://www.w3.org/1998/Math/MathML" data-ast-id="el-db:para-0">"#,
+                r#"<m:math><m:mfrac data-ast-id="el-mfrac-0-1-2"><m:mi data-ast-id="el-mi-0-1-2-3">a</m:mi><m:mn>2</m:mn></m:mfrac></m:math>"#,
+                r#"<math xmlns="http://www.w3.org/1998/Math/MathML"><mi data-ast-id="el-mi-0-5-6">b</mi></math></db:para>"#,
+            )
+        );
+
+        let selector = crate::select::parse_query("math > mfrac").unwrap();
+        let selected = select_elements(input, &selector, "data-ast-id").unwrap();
+        let selected: Vec<_> = selected.iter().map(|element| (element.tag.as_str(), element.text.as_str())).collect();
+        assert_eq!(selected, vec![("mfrac", "a2")]);
+        let locations = locate_ids(&result, "data-ast-id").unwrap();
+        let tags: Vec<String> = locations.into_iter().map(|location| location.tag).collect();
+        assert_eq!(tags, vec!["db:para", "mfrac", "mi", "mi"]);
+    }
+
     #[test]
     fn test_xml_empty_elements() {
         let mut processor = XmlProcessor::new();
@@ -195,4 +1044,211 @@ mod tests {
         assert!(result.contains("<empty"));
         assert!(result.contains("<another-empty"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_xml_overwrite_keeps_other_attributes() {
+        let mut processor = XmlProcessor::new();
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+        options.overwrite = true;
+
+        let input = r#"<root><item id="a" data-ast-id="old" lang="en"/></root>"#;
+        let result = processor.process(input, &options).unwrap();
+        assert!(result.contains(r#"<item id="a" lang="en" data-ast-id="el-item-0-1"/>"#));
+    }
+
+    #[test]
+    fn test_xml_selector_and_class_filters() {
+        let mut processor = XmlProcessor::new();
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+        options.selector = Some("root > item, sub".to_string());
+
+        let input = r#"<root><item><sub/></item><group><item/></group></root>"#;
+        let result = processor.process(input, &options).unwrap();
+        assert_eq!(
+            result,
+            r#"<root><item data-ast-id="el-item-0-1"><sub data-ast-id="el-sub-0-1-2"/></item><group><item/></group></root>"#
+        );
+
+        options.selector = None;
+        options.include_class = vec!["card".to_string()];
+        let input = r#"<root><a class="wide card"/><a class="cards"/><a/></root>"#;
+        let result = processor.process(input, &options).unwrap();
+        assert_eq!(result.matches("data-ast-id").count(), 1);
+        assert!(result.contains(r#"<a class="wide card" data-ast-id="#));
+    }
+
+    #[test]
+    fn test_xml_ignore_markers() {
+        let mut processor = XmlProcessor::new();
+        let options = IdOptions::default();
+
+        let input = r#"<root data-ast-ignore="true"><a/></root>"#;
+        let result = processor.process(input, &options).unwrap();
+        assert!(!result.contains("data-ast-id"));
+
+        let input = r#"<root><!-- ast-append-ids: ignore --><skip><a/></skip><b/></root>"#;
+        let result = processor.process(input, &options).unwrap();
+        assert_eq!(result.matches("data-ast-id").count(), 2);
+        assert!(result.contains("<skip><a/></skip>"));
+    }
+
+    #[test]
+    fn test_xml_option_directives() {
+        let mut processor = XmlProcessor::new();
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+
+        let input = r#"<root><!-- ast-append-ids: prefix=modal- --><dialog><p/></dialog><!-- ast-append-ids: prefix=icon- --><img/><p/></root>"#;
+        let result = processor.process(input, &options).unwrap();
+        assert!(result.contains(r#"<root data-ast-id="el-root-0">"#));
+        assert!(result.contains(r#"<dialog data-ast-id="modal-dialog-0-1">"#));
+        assert!(result.contains(r#"<p data-ast-id="modal-p-0-1-2"/>"#));
+        assert!(result.contains(r#"<img data-ast-id="icon-img-0-3"/>"#));
+        assert!(result.contains(r#"<p data-ast-id="el-p-0-4"/>"#));
+
+        let bad = "<root><!-- ast-append-ids: perfix=x --><a/></root>";
+        assert!(processor.process(bad, &options).is_err());
+    }
+
+    #[test]
+    fn test_xml_comment_anchors() {
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Slug;
+        // Only the comments get IDs here
+        options.include = vec!["index".to_string()];
+        options.comment_anchors = Some(r"^\s*section:".to_string());
+
+        let input = concat!(
+            "<doc><!-- section: Getting started --><title>Intro</title>",
+            "<!-- note: draft --><!-- section: Getting started --><para/>",
+            "<appendix data-ast-ignore=\"\"><!-- section: Hidden --></appendix></doc>",
+        );
+        let result = XmlProcessor::new().process(input, &options).unwrap();
+        assert_eq!(
+            result,
+            concat!(
+                r#"<doc><!-- section: Getting started --><?ast-anchor data-ast-id="el-getting-started"?><title>Intro</title>"#,
+                r#"<!-- note: draft --><!-- section: Getting started --><?ast-anchor data-ast-id="el-getting-started-2"?><para/>"#,
+                r#"<appendix data-ast-ignore=""><!-- section: Hidden --></appendix></doc>"#,
+            )
+        );
+
+        // Anchors from an earlier run are kept, or replaced when overwriting
+        let mut processor = XmlProcessor::new();
+        processor.generator_mut().enable_change_log();
+        assert_eq!(processor.process(&result, &options).unwrap(), result);
+        let skipped = processor.generator_mut().take_changes().into_iter().filter(|change| change.tag == "comment").count();
+        assert_eq!(skipped, 2);
+        options.overwrite = true;
+        options.prefix = "sec-".to_string();
+        let rewritten = XmlProcessor::new().process(&result, &options).unwrap();
+        assert_eq!(rewritten.matches("<?ast-anchor").count(), 2);
+        assert!(rewritten.contains(r#"--><?ast-anchor data-ast-id="sec-getting-started-2"?><para/>"#), "{}", rewritten);
+    }
+
+    #[test]
+    fn test_xml_run_metadata() {
+        let mut options = IdOptions::default();
+        options.strategy = IdStrategy::Path;
+        options.metadata = RunMetadata::Pi;
+        let version = env!("CARGO_PKG_VERSION");
+        let hash = options.options_hash();
+        assert_eq!(hash.len(), 12);
+        assert_eq!(IdOptions { range: Some(0..4), metadata_timestamp: Some(1), ..options.clone() }.options_hash(), hash);
+        assert_ne!(IdOptions { prefix: "x-".to_string(), ..options.clone() }.options_hash(), hash);
+
+        let input = "<?xml version=\"1.0\"?>\n<doc><p/></doc>";
+        let result = XmlProcessor::new().process(input, &options).unwrap();
+        let expected = format!(
+            "<?xml version=\"1.0\"?>\n<?ast-append-ids version=\"{}\" options=\"{}\"?><doc data-ast-id=\"el-doc-0\"><p data-ast-id=\"el-p-0-1\"/></doc>",
+            version, hash
+        );
+        assert_eq!(result, expected);
+        assert_eq!(XmlProcessor::new().process(&result, &options).unwrap(), result);
+
+        // Another configuration replaces the record
+        options.prefix = "x-".to_string();
+        let again = XmlProcessor::new().process(&result, &options).unwrap();
+        assert_eq!(again.matches("<?ast-append-ids").count(), 1);
+        assert!(again.contains(&format!("options=\"{}\"?>", options.options_hash())), "{}", again);
+
+        options.metadata = RunMetadata::Attribute;
+        options.metadata_timestamp = Some(1_700_000_000);
+        let result = XmlProcessor::new().process("<doc data-ast-run=\"old\"><p/></doc>", &options).unwrap();
+        assert_eq!(
+            result,
+            format!(
+                "<doc data-ast-id=\"x-doc-0\" data-ast-run=\"ast-append-ids@{}; options={}; timestamp=1700000000\"><p data-ast-id=\"x-p-0-1\"/></doc>",
+                version,
+                options.options_hash()
+            )
+        );
+    }
+
+    #[test]
+    fn test_xml_taxonomy_ids() {
+        let mut options = IdOptions::default();
+        options.taxonomy_levels = vec!["screen=screen".to_string(), "group=group".to_string()];
+
+        let input = r#"<app><!-- ast-append-ids: strategy=taxonomy --><screen name="Login"><group id="fields"><field/><field/></group><button/></screen></app>"#;
+        let result = XmlProcessor::new().process(input, &options).unwrap();
+        assert!(result.contains(r#"<group id="fields" data-ast-id="el-login.group-0">"#));
+        assert!(result.contains(r#"<field data-ast-id="el-login.fields.field-1"/>"#));
+        assert!(result.contains(r#"<button data-ast-id="el-login.button-0"/>"#));
+    }
+
+    #[test]
+    fn test_xml_existing_ids() {
+        let input = r#"<root data-ast-id="a"><item/><item data-ast-id="b"/></root>"#;
+        let ids = existing_ids(input, "data-ast-id").unwrap();
+        assert_eq!(ids, vec!["a".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn test_xml_set_ids() {
+        let input = r#"<root><item k="1" data-ast-id="old"/><g><item>a</item></g><item data-ast-id="t"/></root>"#;
+        let selector = Selector::parse("root > item[k]").unwrap();
+        let (output, ids) = set_ids(input, &selector, "data-ast-id", "t", &mut IdGenerator::new()).unwrap();
+        assert_eq!(ids, vec!["t-2"]);
+        assert_eq!(
+            output,
+            r#"<root><item k="1" data-ast-id="t-2"/><g><item>a</item></g><item data-ast-id="t"/></root>"#
+        );
+        let xpath = Selector::parse_xpath("/root/item[@k]").unwrap();
+        let (by_xpath, _) = set_ids(input, &xpath, "data-ast-id", "t", &mut IdGenerator::new()).unwrap();
+        assert_eq!(by_xpath, output);
+
+        let selector = Selector::parse("g item").unwrap();
+        let (output, ids) = set_ids(input, &selector, "data-ast-id", "a&b", &mut IdGenerator::new()).unwrap();
+        assert_eq!(ids, vec!["a&b"]);
+        assert!(output.contains(r#"<g><item data-ast-id="a&amp;b">a</item></g>"#));
+    }
+
+    #[test]
+    fn test_xml_remove_ids() {
+        let input = "<root data-ast-id=\"a\">\n  <item x=\"1\" data-ast-id=\"b\"/>\n</root>";
+        let ids = HashSet::from(["b".to_string(), "zzz".to_string()]);
+        let result = remove_ids(input, "data-ast-id", &ids, None).unwrap();
+        assert_eq!(result, "<root data-ast-id=\"a\">\n  <item x=\"1\"/>\n</root>");
+    }
+
+    #[test]
+    fn test_xml_visitor_chained() {
+        let input = "<root><!-- note --><item/><item>a</item></root>";
+        let options = IdOptions { strategy: IdStrategy::Path, ..IdOptions::default() };
+        let mut visitor = XmlVisitor::new(input, &options, IdGenerator::new()).unwrap();
+        let mut writer = Writer::new(Vec::new());
+        // Comments are dropped on the way to the writer
+        for event in (&mut visitor).filter(|event| !matches!(event, Ok(Event::Comment(_)))) {
+            writer.write_event(event.unwrap()).unwrap();
+        }
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+
+        let expected = XmlProcessor::new().process(input, &options).unwrap().replace("<!-- note -->", "");
+        assert_eq!(output, expected);
+        assert_eq!(output.matches("data-ast-id=").count(), 3);
+        assert!(visitor.into_generator().is_used("el-item-0-1"));
+    }
+}
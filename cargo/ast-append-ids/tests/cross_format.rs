@@ -0,0 +1,238 @@
+//! Cross-format parity: the same logical document written as JSX, as the
+//! HTML it renders to and as XML gets the same path and hash IDs, so an ID
+//! found in server-rendered HTML leads back to the element in the JSX source.
+//!
+//! Every document is processed with each strategy, counter mode and filter
+//! below, and the IDs added are compared in document order.
+
+use ast_append_ids::detect::Format;
+use ast_append_ids::id_generator::ChangeAction;
+use ast_append_ids::{processor_for, CounterMode, IdOptions, IdStrategy};
+use pretty_assertions::assert_eq;
+
+/// One logical document in the three formats
+struct Document {
+    name: &'static str,
+    jsx: &'static str,
+    html: &'static str,
+    xml: &'static str,
+    /// Whether the JSX has components or fragments, which only stay out of
+    /// element paths when every element counts
+    wrappers: bool,
+}
+
+const DOCUMENTS: [Document; 5] = [
+    Document {
+        name: "layout",
+        jsx: r#"export const App = () => (
+            <div className="app">
+                <header><nav><ul><li><a href="/">Home</a></li><li><a href="/docs">Docs</a></li></ul></nav></header>
+                <main><section><h1>Title</h1><p>One</p><p>Two</p></section></main>
+                <footer><small>Fine print</small></footer>
+            </div>
+        );"#,
+        html: concat!(
+            r#"<div class="app">"#,
+            r#"<header><nav><ul><li><a href="/">Home</a></li><li><a href="/docs">Docs</a></li></ul></nav></header>"#,
+            r#"<main><section><h1>Title</h1><p>One</p><p>Two</p></section></main>"#,
+            r#"<footer><small>Fine print</small></footer>"#,
+            r#"</div>"#,
+        ),
+        xml: concat!(
+            r#"<div class="app">"#,
+            r#"<header><nav><ul><li><a href="/">Home</a></li><li><a href="/docs">Docs</a></li></ul></nav></header>"#,
+            r#"<main><section><h1>Title</h1><p>One</p><p>Two</p></section></main>"#,
+            r#"<footer><small>Fine print</small></footer>"#,
+            r#"</div>"#,
+        ),
+        wrappers: false,
+    },
+    Document {
+        name: "void elements",
+        jsx: r#"export const Login = () => (
+            <form>
+                <label>Name<input name="user" /></label><br />
+                <img src="logo.png" alt="" />
+                <button type="submit">Sign in</button>
+            </form>
+        );"#,
+        html: r#"<form><label>Name<input name="user"></label><br><img src="logo.png" alt=""><button type="submit">Sign in</button></form>"#,
+        xml: r#"<form><label>Name<input name="user"/></label><br/><img src="logo.png" alt=""/><button type="submit">Sign in</button></form>"#,
+        wrappers: false,
+    },
+    Document {
+        name: "inline svg",
+        jsx: r#"export const Icon = () => (
+            <span>
+                <svg viewBox="0 0 8 8"><defs><linearGradient><stop /></linearGradient></defs><path d="M0 0" /></svg>
+            </span>
+        );"#,
+        html: r#"<span><svg viewBox="0 0 8 8"><defs><linearGradient><stop/></linearGradient></defs><path d="M0 0"/></svg></span>"#,
+        xml: r#"<span><svg viewBox="0 0 8 8"><defs><linearGradient><stop/></linearGradient></defs><path d="M0 0"/></svg></span>"#,
+        wrappers: false,
+    },
+    Document {
+        name: "ignored subtree",
+        jsx: r#"export const Table = () => (
+            <table>
+                <tbody data-ast-ignore><tr><td>1</td></tr></tbody>
+                <tfoot><tr><td>Total</td></tr></tfoot>
+            </table>
+        );"#,
+        html: r#"<table><tbody data-ast-ignore><tr><td>1</td></tr></tbody><tfoot><tr><td>Total</td></tr></tfoot></table>"#,
+        xml: r#"<table><tbody data-ast-ignore=""><tr><td>1</td></tr></tbody><tfoot><tr><td>Total</td></tr></tfoot></table>"#,
+        wrappers: false,
+    },
+    Document {
+        name: "components and fragments",
+        jsx: r#"export const Page = () => (
+            <Layout title="Profile">
+                <main>
+                    <Card><p>Hello</p></Card>
+                    <><h2>More</h2><p>Text</p></>
+                    <Footer.Links />
+                    <button>Save</button>
+                </main>
+            </Layout>
+        );"#,
+        html: r#"<main><p>Hello</p><h2>More</h2><p>Text</p><button>Save</button></main>"#,
+        xml: r#"<main><p>Hello</p><h2>More</h2><p>Text</p><button>Save</button></main>"#,
+        wrappers: true,
+    },
+];
+
+/// Option sets each document is processed with, on top of strategy and counter
+fn filters() -> Vec<(&'static str, IdOptions)> {
+    vec![
+        ("all elements", IdOptions::default()),
+        (
+            "included tags",
+            IdOptions { include: ["a", "p", "button", "td", "stop", "input"].map(String::from).to_vec(), ..IdOptions::default() },
+        ),
+        ("excluded tags", IdOptions { exclude: ["div", "li", "svg", "tr"].map(String::from).to_vec(), ..IdOptions::default() }),
+        ("selector", IdOptions { selector: Some("main p, nav a, form button".to_string()), ..IdOptions::default() }),
+    ]
+}
+
+/// Tags and IDs added to `input`, in document order
+fn added_ids(format: Format, input: &str, options: &IdOptions) -> Vec<(String, String)> {
+    let mut processor = processor_for(format).unwrap();
+    processor.generator_mut().enable_change_log();
+    processor.process(input, options).unwrap_or_else(|e| panic!("{:?}: {}", format, e));
+    processor
+        .generator_mut()
+        .take_changes()
+        .into_iter()
+        .filter(|change| change.action == ChangeAction::Added)
+        .map(|change| (change.tag, change.id))
+        .collect()
+}
+
+#[test]
+fn test_ids_agree_across_formats() {
+    for document in &DOCUMENTS {
+        for strategy in [IdStrategy::Path, IdStrategy::Hash] {
+            for counter in [CounterMode::All, CounterMode::Processed] {
+                if document.wrappers && counter == CounterMode::Processed {
+                    continue;
+                }
+                for (filter, options) in filters() {
+                    let options = IdOptions { strategy: strategy.clone(), counter, ..options };
+                    let case = format!("{} / {:?} / {:?} / {}", document.name, strategy, counter, filter);
+
+                    let jsx = added_ids(Format::Jsx, document.jsx, &options);
+                    assert!(!jsx.is_empty() || filter != "all elements", "{}", case);
+                    assert_eq!(added_ids(Format::Html, document.html, &options), jsx, "HTML: {}", case);
+                    assert_eq!(added_ids(Format::Xml, document.xml, &options), jsx, "XML: {}", case);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_filters_keep_other_ids() {
+    // With every element counting, leaving elements out does not move the others
+    for document in &DOCUMENTS {
+        for format in [Format::Jsx, Format::Html, Format::Xml] {
+            let input = match format {
+                Format::Jsx => document.jsx,
+                Format::Html => document.html,
+                _ => document.xml,
+            };
+            let options = IdOptions { strategy: IdStrategy::Path, ..IdOptions::default() };
+            let all = added_ids(format, input, &options);
+            for (filter, options) in filters() {
+                let filtered = added_ids(format, input, &IdOptions { strategy: IdStrategy::Path, ..options });
+                assert!(
+                    filtered.iter().all(|id| all.contains(id)),
+                    "{} / {:?} / {}: {:?} not among {:?}",
+                    document.name,
+                    format,
+                    filter,
+                    filtered,
+                    all
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_ssr_html_matches_component_jsx() {
+    let jsx = r#"
+        export function UserCard({ user }) {
+            return (
+                <div className="card">
+                    <img src={user.avatar} alt="" />
+                    <h3>{user.name}</h3>
+                </div>
+            );
+        }
+
+        export function Page({ user }) {
+            return (
+                <main>
+                    <h1>Team</h1>
+                    <UserCard user={user} />
+                    <p>Footer</p>
+                </main>
+            );
+        }
+    "#;
+    // What a server renders for <Page />, component markers included
+    let html = concat!(
+        r#"<!DOCTYPE html><html><body><main data-ast-component="Page"><h1>Team</h1>"#,
+        r#"<div class="card" data-ast-component="UserCard"><img src="ann.png" alt=""><h3>Ann</h3></div>"#,
+        r#"<p>Footer</p></main></body></html>"#,
+    );
+
+    for strategy in [IdStrategy::Path, IdStrategy::Hash] {
+        let options = IdOptions {
+            strategy,
+            scheme: ast_append_ids::IdScheme::V2,
+            salt: "shared".to_string(),
+            ..IdOptions::default()
+        }
+        .for_ssr("data-ast-component");
+        let sorted = |mut ids: Vec<(String, String)>| {
+            ids.sort();
+            ids
+        };
+        let built = sorted(added_ids(Format::Jsx, jsx, &options));
+        let rendered = added_ids(Format::Html, html, &options);
+        // The page around the components gets IDs of its own
+        let (page, rendered): (Vec<_>, Vec<_>) = rendered.into_iter().partition(|(tag, _)| tag == "html" || tag == "body");
+        assert_eq!(page.len(), 2);
+        assert_eq!(sorted(rendered), built);
+        let xml = html.split_once("<body>").unwrap().1.split_once("</body>").unwrap().0.replace("alt=\"\">", "alt=\"\"/>");
+        assert_eq!(sorted(added_ids(Format::Xml, &xml, &options)), built);
+        assert!(built.iter().any(|(tag, id)| tag == "img" && id.starts_with("el-usercard-")), "{:?}", built);
+    }
+
+    // Without scopes the card's elements are numbered within the page
+    let options = IdOptions { strategy: IdStrategy::Path, ..IdOptions::default() };
+    let built = added_ids(Format::Jsx, jsx, &options);
+    let rendered = added_ids(Format::Html, html, &options);
+    assert!(built.iter().filter(|(tag, _)| tag == "img").all(|id| !rendered.contains(id)));
+}
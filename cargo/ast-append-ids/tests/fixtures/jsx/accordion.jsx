@@ -0,0 +1,12 @@
+export function Faq({ entries }) {
+  return (
+    <div className="faq">
+      {entries.map(({ question, answer }) => (
+        <details key={question}>
+          <summary>{question}</summary>
+          <p>{answer}</p>
+        </details>
+      ))}
+    </div>
+  );
+}
@@ -0,0 +1,7 @@
+export function Button({ label, onClick }) {
+  return (
+    <button type="button" className="btn" onClick={onClick}>
+      {label}
+    </button>
+  );
+}
@@ -0,0 +1,8 @@
+export const ProductCard = ({ product, onAdd }) => (
+  <article className="card">
+    <img src={product.image} alt={product.name} />
+    <h3>{product.name}</h3>
+    <p className="price">${product.price}</p>
+    <button onClick={() => onAdd(product)}>Add to cart</button>
+  </article>
+);
@@ -0,0 +1,13 @@
+import { Card, Modal } from './ui';
+
+export function Page() {
+  return (
+    <Card title="Profile">
+      <Modal.Header>Edit</Modal.Header>
+      <div className="body">
+        <Avatar size={32} />
+        <p>Some text</p>
+      </div>
+    </Card>
+  );
+}
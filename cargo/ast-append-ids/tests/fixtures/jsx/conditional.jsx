@@ -0,0 +1,9 @@
+export function Status({ loading, error, data }) {
+  if (loading) return <p className="loading">Loading&hellip;</p>;
+  return (
+    <section>
+      {error ? <div role="alert">{error.message}</div> : null}
+      {data && <pre>{JSON.stringify(data, null, 2)}</pre>}
+    </section>
+  );
+}
@@ -0,0 +1,17 @@
+import { createContext, useContext } from "react";
+
+const ThemeContext = createContext("light");
+
+export function ThemeToggle() {
+  const theme = useContext(ThemeContext);
+  return <button aria-pressed={theme === "dark"}>Toggle theme</button>;
+}
+
+export const App = ({ children }) => (
+  <ThemeContext.Provider value="dark">
+    <header>
+      <ThemeToggle />
+    </header>
+    <main>{children}</main>
+  </ThemeContext.Provider>
+);
@@ -0,0 +1,7 @@
+export const Pricing = () => (
+  <section>
+    <h2>Plans &amp; pricing</h2>
+    <p>From &euro;9&nbsp;/&nbsp;month &mdash; cancel anytime.</p>
+    <button title="Start &quot;Pro&quot; trial">Try Pro</button>
+  </section>
+);
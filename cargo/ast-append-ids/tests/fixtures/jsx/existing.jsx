@@ -0,0 +1,7 @@
+export const Nav = () => (
+  <nav data-ast-id="main-nav">
+    <a href="/">Home</a>
+    <a href="/about" data-ast-id="about-link">About</a>
+    <a href="/contact">Contact</a>
+  </nav>
+);
@@ -0,0 +1,17 @@
+import React, { useState } from 'react';
+
+interface Props {
+  onSubmit: (email: string) => void;
+}
+
+export const LoginForm: React.FC<Props> = ({ onSubmit }) => {
+  const [email, setEmail] = useState<string>('');
+
+  return (
+    <form onSubmit={(e) => { e.preventDefault(); onSubmit(email); }}>
+      <label htmlFor="email">Email</label>
+      <input id="email" type="email" value={email} onChange={(e) => setEmail(e.target.value)} />
+      <button type="submit">Sign in</button>
+    </form>
+  );
+};
@@ -0,0 +1,11 @@
+export const Layout = ({ children }) => (
+  <>
+    <header>
+      <h1>Title</h1>
+    </header>
+    <main>{children}</main>
+    <>
+      <footer>&copy; 2024</footer>
+    </>
+  </>
+);
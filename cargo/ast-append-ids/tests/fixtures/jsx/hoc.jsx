@@ -0,0 +1,14 @@
+function withLoading(Component) {
+  return function Wrapped({ loading, ...props }) {
+    if (loading) {
+      return <div className="spinner" role="status">Loading…</div>;
+    }
+    return <Component {...props} />;
+  };
+}
+
+export const UserList = withLoading(({ users }) => (
+  <ul>
+    {users.map((u) => <li key={u.id}>{u.email}</li>)}
+  </ul>
+));
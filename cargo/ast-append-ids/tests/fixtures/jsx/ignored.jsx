@@ -0,0 +1,12 @@
+export const Mixed = () => (
+  <div>
+    {/* ast-append-ids-ignore-next */}
+    <table>
+      <tr><td>skipped</td></tr>
+    </table>
+    <aside data-ast-ignore>
+      <p>also skipped</p>
+    </aside>
+    <p>tagged</p>
+  </div>
+);
@@ -0,0 +1,16 @@
+export default function Layout({ title, sidebar, children }) {
+  return (
+    <>
+      <header className="top">
+        <h1>{title}</h1>
+      </header>
+      <div className="columns">
+        <aside>{sidebar}</aside>
+        <main>{children}</main>
+      </div>
+      <footer>
+        <small>© 2024 Example Inc.</small>
+      </footer>
+    </>
+  );
+}
@@ -0,0 +1,12 @@
+export default function TodoList({ items }) {
+  return (
+    <ul className="todos">
+      {items.map((item) => (
+        <li key={item.id}>
+          <input type="checkbox" checked={item.done} readOnly />
+          <span>{item.title}</span>
+        </li>
+      ))}
+    </ul>
+  );
+}
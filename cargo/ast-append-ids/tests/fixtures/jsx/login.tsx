@@ -0,0 +1,24 @@
+import React, { FormEvent } from "react";
+
+interface Props {
+  onSubmit: (email: string, password: string) => void;
+}
+
+export function LoginForm({ onSubmit }: Props) {
+  const handle = (e: FormEvent<HTMLFormElement>) => {
+    e.preventDefault();
+    const data = new FormData(e.currentTarget);
+    onSubmit(String(data.get("email")), String(data.get("password")));
+  };
+  return (
+    <form onSubmit={handle}>
+      <label>
+        Email <input name="email" type="email" />
+      </label>
+      <label>
+        Password <input name="password" type="password" />
+      </label>
+      <button type="submit">Log in</button>
+    </form>
+  );
+}
@@ -0,0 +1,15 @@
+export function ConfirmModal({ open, onClose, onConfirm }) {
+  if (!open) return null;
+  return (
+    <div className="backdrop" onClick={onClose}>
+      <div role="dialog" aria-modal="true" aria-labelledby="confirm-title">
+        <h2 id="confirm-title">Delete project?</h2>
+        <p>This cannot be undone.</p>
+        <footer>
+          <button onClick={onClose}>Cancel</button>
+          <button className="danger" onClick={onConfirm}>Delete</button>
+        </footer>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,14 @@
+import { Link } from "react-router-dom";
+
+export const Navbar = ({ user }) => (
+  <nav className="navbar">
+    <Link to="/">Home</Link>
+    <Link to="/pricing">Pricing</Link>
+    <Link to="/docs">Docs</Link>
+    {user ? (
+      <Link to="/account">{user.name}</Link>
+    ) : (
+      <a href="/login">Sign in</a>
+    )}
+  </nav>
+);
@@ -0,0 +1,9 @@
+export const Pagination = ({ page, pages, go }) => (
+  <nav aria-label="Pagination">
+    <button disabled={page === 1} onClick={() => go(page - 1)}>Previous</button>
+    <span>
+      Page {page} of {pages}
+    </span>
+    <button disabled={page === pages} onClick={() => go(page + 1)}>Next</button>
+  </nav>
+);
@@ -0,0 +1,11 @@
+import { createPortal } from "react-dom";
+
+export function Toast({ message, onDismiss }) {
+  return createPortal(
+    <div className="toast" role="alert">
+      <span>{message}</span>
+      <button aria-label="Dismiss" onClick={onDismiss}>×</button>
+    </div>,
+    document.body
+  );
+}
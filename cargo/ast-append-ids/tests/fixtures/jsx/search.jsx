@@ -0,0 +1,15 @@
+export default function Search({ query, setQuery, results }) {
+  return (
+    <div role="search">
+      <input type="search" value={query} onChange={(e) => setQuery(e.target.value)} placeholder="Search" />
+      {results.length === 0 && query && <p className="empty">No results for “{query}”</p>}
+      <ol>
+        {results.map((r) => (
+          <li key={r.url}>
+            <a href={r.url}>{r.title}</a>
+          </li>
+        ))}
+      </ol>
+    </div>
+  );
+}
@@ -0,0 +1,16 @@
+export function Settings({ prefs, update }: { prefs: Record<string, boolean>; update: (k: string) => void }) {
+  return (
+    <fieldset>
+      <legend>Notifications</legend>
+      {Object.entries(prefs).map(([key, on]) => (
+        <label key={key}>
+          <input type="checkbox" checked={on} onChange={() => update(key)} />
+          {key}
+        </label>
+      ))}
+      <button type="button" disabled={!Object.values(prefs).some(Boolean)}>
+        Save
+      </button>
+    </fieldset>
+  );
+}
@@ -0,0 +1,8 @@
+export function Faq({ entries }) {
+    return (<div className="faq" data-ast-id="el-63d07ede">
+      {entries.map(({ question, answer })=>(<details key={question} data-ast-id="el-fe0533cb">
+          <summary data-ast-id="el-cd5f9713">{question}</summary>
+          <p data-ast-id="el-879d4f21">{answer}</p>
+        </details>))}
+    </div>);
+}
@@ -0,0 +1,8 @@
+export function Faq({ entries }) {
+    return (<div className="faq" data-ast-id="el-div-0">
+      {entries.map(({ question, answer })=>(<details key={question} data-ast-id="el-details-0-1">
+          <summary data-ast-id="el-summary-0-1-2">{question}</summary>
+          <p data-ast-id="el-p-0-1-3">{answer}</p>
+        </details>))}
+    </div>);
+}
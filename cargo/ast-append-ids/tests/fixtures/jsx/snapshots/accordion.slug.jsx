@@ -0,0 +1,8 @@
+export function Faq({ entries }) {
+    return (<div className="faq" data-ast-id="el-46638cd1">
+      {entries.map(({ question, answer })=>(<details key={question} data-ast-id="el-46638cd1-2">
+          <summary data-ast-id="el-46638cd1-3">{question}</summary>
+          <p data-ast-id="el-46638cd1-4">{answer}</p>
+        </details>))}
+    </div>);
+}
@@ -0,0 +1,5 @@
+export function Button({ label, onClick }) {
+    return (<button type="button" className="btn" onClick={onClick} data-ast-id="el-button-0">
+      {label}
+    </button>);
+}
@@ -0,0 +1,6 @@
+export const ProductCard = ({ product, onAdd })=>(<article className="card" data-ast-id="el-efef4a53">
+    <img src={product.image} alt={product.name} data-ast-id="el-22b4a984"/>
+    <h3 data-ast-id="el-a4d08d4f">{product.name}</h3>
+    <p className="price" data-ast-id="el-bfcc3e66">${product.price}</p>
+    <button onClick={()=>onAdd(product)} data-ast-id="el-f4cac71d">Add to cart</button>
+  </article>);
@@ -0,0 +1,6 @@
+export const ProductCard = ({ product, onAdd })=>(<article className="card" data-ast-id="el-article-0">
+    <img src={product.image} alt={product.name} data-ast-id="el-img-0-1"/>
+    <h3 data-ast-id="el-h3-0-2">{product.name}</h3>
+    <p className="price" data-ast-id="el-p-0-3">${product.price}</p>
+    <button onClick={()=>onAdd(product)} data-ast-id="el-button-0-4">Add to cart</button>
+  </article>);
@@ -0,0 +1,6 @@
+export const ProductCard = ({ product, onAdd })=>(<article className="card" data-ast-id="el-46638cd1">
+    <img src={product.image} alt={product.name} data-ast-id="el-46638cd1-2"/>
+    <h3 data-ast-id="el-46638cd1-3">{product.name}</h3>
+    <p className="price" data-ast-id="el-46638cd1-4">${product.price}</p>
+    <button onClick={()=>onAdd(product)} data-ast-id="el-46638cd1-5">Add to cart</button>
+  </article>);
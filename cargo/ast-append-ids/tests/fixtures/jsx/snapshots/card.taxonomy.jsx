@@ -0,0 +1,6 @@
+export const ProductCard = ({ product, onAdd })=>(<article className="card" data-ast-id="el-article-0">
+    <img src={product.image} alt={product.name} data-ast-id="el-article.img-0"/>
+    <h3 data-ast-id="el-article.h3-0">{product.name}</h3>
+    <p className="price" data-ast-id="el-article.p-0">${product.price}</p>
+    <button onClick={()=>onAdd(product)} data-ast-id="el-article.button-0">Add to cart</button>
+  </article>);
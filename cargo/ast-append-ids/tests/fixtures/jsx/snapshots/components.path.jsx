@@ -0,0 +1,10 @@
+import { Card, Modal } from './ui';
+export function Page() {
+    return (<Card title="Profile">
+      <Modal.Header>Edit</Modal.Header>
+      <div className="body" data-ast-id="el-div-0">
+        <Avatar size={32}/>
+        <p data-ast-id="el-p-0-1">Some text</p>
+      </div>
+    </Card>);
+}
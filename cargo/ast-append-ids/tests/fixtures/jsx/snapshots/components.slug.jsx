@@ -0,0 +1,10 @@
+import { Card, Modal } from './ui';
+export function Page() {
+    return (<Card title="Profile">
+      <Modal.Header>Edit</Modal.Header>
+      <div className="body" data-ast-id="el-46638cd1">
+        <Avatar size={32}/>
+        <p data-ast-id="el-46638cd1-2">Some text</p>
+      </div>
+    </Card>);
+}
@@ -0,0 +1,7 @@
+export function Status({ loading, error, data }) {
+    if (loading) return <p className="loading" data-ast-id="el-f5f6b967">Loading&hellip;</p>;
+    return (<section data-ast-id="el-80c37bc2">
+      {error ? <div role="alert" data-ast-id="el-acc1af3d">{error.message}</div> : null}
+      {data && <pre data-ast-id="el-715d3ecc">{JSON.stringify(data, null, 2)}</pre>}
+    </section>);
+}
@@ -0,0 +1,7 @@
+export function Status({ loading, error, data }) {
+    if (loading) return <p className="loading" data-ast-id="el-p-0">Loading&hellip;</p>;
+    return (<section data-ast-id="el-section-1">
+      {error ? <div role="alert" data-ast-id="el-div-1-2">{error.message}</div> : null}
+      {data && <pre data-ast-id="el-pre-1-3">{JSON.stringify(data, null, 2)}</pre>}
+    </section>);
+}
@@ -0,0 +1,7 @@
+export function Status({ loading, error, data }) {
+    if (loading) return <p className="loading" data-ast-id="el-46638cd1">Loading&hellip;</p>;
+    return (<section data-ast-id="el-46638cd1-2">
+      {error ? <div role="alert" data-ast-id="el-46638cd1-3">{error.message}</div> : null}
+      {data && <pre data-ast-id="el-46638cd1-4">{JSON.stringify(data, null, 2)}</pre>}
+    </section>);
+}
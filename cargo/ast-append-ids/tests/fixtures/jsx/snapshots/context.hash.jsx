@@ -0,0 +1,12 @@
+import { createContext, useContext } from "react";
+const ThemeContext = createContext("light");
+export function ThemeToggle() {
+    const theme = useContext(ThemeContext);
+    return <button aria-pressed={theme === "dark"} data-ast-id="el-fd54b6b9">Toggle theme</button>;
+}
+export const App = ({ children })=>(<ThemeContext.Provider value="dark">
+    <header data-ast-id="el-4f938143">
+      <ThemeToggle/>
+    </header>
+    <main data-ast-id="el-e157ab1b">{children}</main>
+  </ThemeContext.Provider>);
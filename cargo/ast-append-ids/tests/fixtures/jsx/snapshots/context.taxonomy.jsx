@@ -0,0 +1,12 @@
+import { createContext, useContext } from "react";
+const ThemeContext = createContext("light");
+export function ThemeToggle() {
+    const theme = useContext(ThemeContext);
+    return <button aria-pressed={theme === "dark"} data-ast-id="el-button-0">Toggle theme</button>;
+}
+export const App = ({ children })=>(<ThemeContext.Provider value="dark">
+    <header data-ast-id="el-header-0">
+      <ThemeToggle/>
+    </header>
+    <main data-ast-id="el-main-0">{children}</main>
+  </ThemeContext.Provider>);
@@ -0,0 +1,5 @@
+export const Pricing = ()=>(<section data-ast-id="el-57a5fd8c">
+    <h2 data-ast-id="el-f6b2cb29">Plans &amp; pricing</h2>
+    <p data-ast-id="el-390b8ed0">From &euro;9&nbsp;/&nbsp;month &mdash; cancel anytime.</p>
+    <button title="Start &quot;Pro&quot; trial" data-ast-id="el-90f7440a">Try Pro</button>
+  </section>);
@@ -0,0 +1,5 @@
+export const Pricing = ()=>(<section data-ast-id="el-section-0">
+    <h2 data-ast-id="el-h2-0-1">Plans &amp; pricing</h2>
+    <p data-ast-id="el-p-0-2">From &euro;9&nbsp;/&nbsp;month &mdash; cancel anytime.</p>
+    <button title="Start &quot;Pro&quot; trial" data-ast-id="el-button-0-3">Try Pro</button>
+  </section>);
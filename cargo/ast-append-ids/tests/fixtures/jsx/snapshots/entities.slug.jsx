@@ -0,0 +1,5 @@
+export const Pricing = ()=>(<section data-ast-id="el-46638cd1">
+    <h2 data-ast-id="el-46638cd1-2">Plans &amp; pricing</h2>
+    <p data-ast-id="el-46638cd1-3">From &euro;9&nbsp;/&nbsp;month &mdash; cancel anytime.</p>
+    <button title="Start &quot;Pro&quot; trial" data-ast-id="el-46638cd1-4">Try Pro</button>
+  </section>);
@@ -0,0 +1,5 @@
+export const Pricing = ()=>(<section data-ast-id="el-section-0">
+    <h2 data-ast-id="el-section.h2-0">Plans &amp; pricing</h2>
+    <p data-ast-id="el-section.p-0">From &euro;9&nbsp;/&nbsp;month &mdash; cancel anytime.</p>
+    <button title="Start &quot;Pro&quot; trial" data-ast-id="el-section.button-0">Try Pro</button>
+  </section>);
@@ -0,0 +1,5 @@
+export const Nav = ()=>(<nav data-ast-id="main-nav">
+    <a href="/" data-ast-id="el-696d54ae">Home</a>
+    <a href="/about" data-ast-id="about-link">About</a>
+    <a href="/contact" data-ast-id="el-1b79dffd">Contact</a>
+  </nav>);
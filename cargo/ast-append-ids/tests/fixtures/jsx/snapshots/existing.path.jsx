@@ -0,0 +1,5 @@
+export const Nav = ()=>(<nav data-ast-id="main-nav">
+    <a href="/" data-ast-id="el-a-0-1">Home</a>
+    <a href="/about" data-ast-id="about-link">About</a>
+    <a href="/contact" data-ast-id="el-a-0-3">Contact</a>
+  </nav>);
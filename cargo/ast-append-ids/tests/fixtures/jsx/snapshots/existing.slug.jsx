@@ -0,0 +1,5 @@
+export const Nav = ()=>(<nav data-ast-id="main-nav">
+    <a href="/" data-ast-id="el-46638cd1">Home</a>
+    <a href="/about" data-ast-id="about-link">About</a>
+    <a href="/contact" data-ast-id="el-46638cd1-2">Contact</a>
+  </nav>);
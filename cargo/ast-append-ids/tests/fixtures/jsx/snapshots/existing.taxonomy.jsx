@@ -0,0 +1,5 @@
+export const Nav = ()=>(<nav data-ast-id="main-nav">
+    <a href="/" data-ast-id="el-nav.a-0">Home</a>
+    <a href="/about" data-ast-id="about-link">About</a>
+    <a href="/contact" data-ast-id="el-nav.a-1">Contact</a>
+  </nav>);
@@ -0,0 +1,15 @@
+import React, { useState } from 'react';
+interface Props {
+    onSubmit: (email: string) => void;
+}
+export const LoginForm: React.FC<Props> = ({ onSubmit })=>{
+    const [email, setEmail] = useState<string>('');
+    return (<form onSubmit={(e)=>{
+        e.preventDefault();
+        onSubmit(email);
+    }} data-ast-id="el-48f752e3">
+      <label htmlFor="email" data-ast-id="el-f9a44b73">Email</label>
+      <input id="email" type="email" value={email} onChange={(e)=>setEmail(e.target.value)} data-ast-id="el-6a35e63e"/>
+      <button type="submit" data-ast-id="el-90f7440a">Sign in</button>
+    </form>);
+};
@@ -0,0 +1,15 @@
+import React, { useState } from 'react';
+interface Props {
+    onSubmit: (email: string) => void;
+}
+export const LoginForm: React.FC<Props> = ({ onSubmit })=>{
+    const [email, setEmail] = useState<string>('');
+    return (<form onSubmit={(e)=>{
+        e.preventDefault();
+        onSubmit(email);
+    }} data-ast-id="el-form-0">
+      <label htmlFor="email" data-ast-id="el-label-0-1">Email</label>
+      <input id="email" type="email" value={email} onChange={(e)=>setEmail(e.target.value)} data-ast-id="el-input-0-2"/>
+      <button type="submit" data-ast-id="el-button-0-3">Sign in</button>
+    </form>);
+};
@@ -0,0 +1,15 @@
+import React, { useState } from 'react';
+interface Props {
+    onSubmit: (email: string) => void;
+}
+export const LoginForm: React.FC<Props> = ({ onSubmit })=>{
+    const [email, setEmail] = useState<string>('');
+    return (<form onSubmit={(e)=>{
+        e.preventDefault();
+        onSubmit(email);
+    }} data-ast-id="el-46638cd1">
+      <label htmlFor="email" data-ast-id="el-46638cd1-2">Email</label>
+      <input id="email" type="email" value={email} onChange={(e)=>setEmail(e.target.value)} data-ast-id="el-46638cd1-3"/>
+      <button type="submit" data-ast-id="el-46638cd1-4">Sign in</button>
+    </form>);
+};
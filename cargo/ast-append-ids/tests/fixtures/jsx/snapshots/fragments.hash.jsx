@@ -0,0 +1,9 @@
+export const Layout = ({ children })=>(<>
+    <header data-ast-id="el-4f5d09cb">
+      <h1 data-ast-id="el-2bad517d">Title</h1>
+    </header>
+    <main data-ast-id="el-e157ab1b">{children}</main>
+    <>
+      <footer data-ast-id="el-0b806fec">&copy; 2024</footer>
+    </>
+  </>);
@@ -0,0 +1,9 @@
+export const Layout = ({ children })=>(<>
+    <header data-ast-id="el-header-0">
+      <h1 data-ast-id="el-h1-0-1">Title</h1>
+    </header>
+    <main data-ast-id="el-main-2">{children}</main>
+    <>
+      <footer data-ast-id="el-footer-3">&copy; 2024</footer>
+    </>
+  </>);
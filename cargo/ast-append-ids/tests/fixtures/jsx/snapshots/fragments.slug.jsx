@@ -0,0 +1,9 @@
+export const Layout = ({ children })=>(<>
+    <header data-ast-id="el-46638cd1">
+      <h1 data-ast-id="el-46638cd1-2">Title</h1>
+    </header>
+    <main data-ast-id="el-46638cd1-3">{children}</main>
+    <>
+      <footer data-ast-id="el-46638cd1-4">&copy; 2024</footer>
+    </>
+  </>);
@@ -0,0 +1,9 @@
+export const Layout = ({ children })=>(<>
+    <header data-ast-id="el-header-0">
+      <h1 data-ast-id="el-header.h1-0">Title</h1>
+    </header>
+    <main data-ast-id="el-main-0">{children}</main>
+    <>
+      <footer data-ast-id="el-footer-0">&copy; 2024</footer>
+    </>
+  </>);
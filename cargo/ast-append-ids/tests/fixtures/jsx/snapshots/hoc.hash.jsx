@@ -0,0 +1,11 @@
+function withLoading(Component) {
+    return function Wrapped({ loading, ...props }) {
+        if (loading) {
+            return <div className="spinner" role="status" data-ast-id="el-63d07ede">Loading…</div>;
+        }
+        return <Component {...props}/>;
+    };
+}
+export const UserList = withLoading(({ users })=>(<ul data-ast-id="el-ebf8fb77">
+    {users.map((u)=><li key={u.id} data-ast-id="el-d43b4347">{u.email}</li>)}
+  </ul>));
@@ -0,0 +1,11 @@
+function withLoading(Component) {
+    return function Wrapped({ loading, ...props }) {
+        if (loading) {
+            return <div className="spinner" role="status" data-ast-id="el-div-0">Loading…</div>;
+        }
+        return <Component {...props}/>;
+    };
+}
+export const UserList = withLoading(({ users })=>(<ul data-ast-id="el-ul-1">
+    {users.map((u)=><li key={u.id} data-ast-id="el-li-1-2">{u.email}</li>)}
+  </ul>));
@@ -0,0 +1,11 @@
+function withLoading(Component) {
+    return function Wrapped({ loading, ...props }) {
+        if (loading) {
+            return <div className="spinner" role="status" data-ast-id="el-46638cd1">Loading…</div>;
+        }
+        return <Component {...props}/>;
+    };
+}
+export const UserList = withLoading(({ users })=>(<ul data-ast-id="el-46638cd1-2">
+    {users.map((u)=><li key={u.id} data-ast-id="el-46638cd1-3">{u.email}</li>)}
+  </ul>));
@@ -0,0 +1,10 @@
+export const Mixed = ()=>(<div data-ast-id="el-46638cd1">
+    {/* ast-append-ids-ignore-next */}
+    <table>
+      <tr><td>skipped</td></tr>
+    </table>
+    <aside data-ast-ignore>
+      <p>also skipped</p>
+    </aside>
+    <p data-ast-id="el-46638cd1-2">tagged</p>
+  </div>);
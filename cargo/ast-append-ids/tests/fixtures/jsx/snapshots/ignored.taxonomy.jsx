@@ -0,0 +1,10 @@
+export const Mixed = ()=>(<div data-ast-id="el-div-0">
+    {/* ast-append-ids-ignore-next */}
+    <table>
+      <tr><td>skipped</td></tr>
+    </table>
+    <aside data-ast-ignore>
+      <p>also skipped</p>
+    </aside>
+    <p data-ast-id="el-p-0">tagged</p>
+  </div>);
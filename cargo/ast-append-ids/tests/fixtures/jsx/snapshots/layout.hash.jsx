@@ -0,0 +1,14 @@
+export default function Layout({ title, sidebar, children }) {
+    return (<>
+      <header className="top" data-ast-id="el-4f5d09cb">
+        <h1 data-ast-id="el-2bad517d">{title}</h1>
+      </header>
+      <div className="columns" data-ast-id="el-50670bc9">
+        <aside data-ast-id="el-3611c273">{sidebar}</aside>
+        <main data-ast-id="el-fd5164bd">{children}</main>
+      </div>
+      <footer data-ast-id="el-e710794d">
+        <small data-ast-id="el-5f705c3e">© 2024 Example Inc.</small>
+      </footer>
+    </>);
+}
@@ -0,0 +1,14 @@
+export default function Layout({ title, sidebar, children }) {
+    return (<>
+      <header className="top" data-ast-id="el-header-0">
+        <h1 data-ast-id="el-h1-0-1">{title}</h1>
+      </header>
+      <div className="columns" data-ast-id="el-div-2">
+        <aside data-ast-id="el-aside-2-3">{sidebar}</aside>
+        <main data-ast-id="el-main-2-4">{children}</main>
+      </div>
+      <footer data-ast-id="el-footer-5">
+        <small data-ast-id="el-small-5-6">© 2024 Example Inc.</small>
+      </footer>
+    </>);
+}
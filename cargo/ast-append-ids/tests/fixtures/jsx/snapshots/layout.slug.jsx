@@ -0,0 +1,14 @@
+export default function Layout({ title, sidebar, children }) {
+    return (<>
+      <header className="top" data-ast-id="el-46638cd1">
+        <h1 data-ast-id="el-46638cd1-2">{title}</h1>
+      </header>
+      <div className="columns" data-ast-id="el-46638cd1-3">
+        <aside data-ast-id="el-46638cd1-4">{sidebar}</aside>
+        <main data-ast-id="el-46638cd1-5">{children}</main>
+      </div>
+      <footer data-ast-id="el-46638cd1-6">
+        <small data-ast-id="el-46638cd1-7">© 2024 Example Inc.</small>
+      </footer>
+    </>);
+}
@@ -0,0 +1,14 @@
+export default function Layout({ title, sidebar, children }) {
+    return (<>
+      <header className="top" data-ast-id="el-header-0">
+        <h1 data-ast-id="el-header.h1-0">{title}</h1>
+      </header>
+      <div className="columns" data-ast-id="el-div-0">
+        <aside data-ast-id="el-aside-0">{sidebar}</aside>
+        <main data-ast-id="el-main-0">{children}</main>
+      </div>
+      <footer data-ast-id="el-footer-0">
+        <small data-ast-id="el-footer.small-0">© 2024 Example Inc.</small>
+      </footer>
+    </>);
+}
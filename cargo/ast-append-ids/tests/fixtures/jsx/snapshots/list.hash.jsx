@@ -0,0 +1,8 @@
+export default function TodoList({ items }) {
+    return (<ul className="todos" data-ast-id="el-a6923e4a">
+      {items.map((item)=>(<li key={item.id} data-ast-id="el-67b3ae80">
+          <input type="checkbox" checked={item.done} readOnly data-ast-id="el-c0a1f7e6"/>
+          <span data-ast-id="el-91f5588f">{item.title}</span>
+        </li>))}
+    </ul>);
+}
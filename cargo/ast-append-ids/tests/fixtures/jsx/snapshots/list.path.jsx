@@ -0,0 +1,8 @@
+export default function TodoList({ items }) {
+    return (<ul className="todos" data-ast-id="el-ul-0">
+      {items.map((item)=>(<li key={item.id} data-ast-id="el-li-0-1">
+          <input type="checkbox" checked={item.done} readOnly data-ast-id="el-input-0-1-2"/>
+          <span data-ast-id="el-span-0-1-3">{item.title}</span>
+        </li>))}
+    </ul>);
+}
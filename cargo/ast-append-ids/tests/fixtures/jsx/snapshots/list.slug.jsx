@@ -0,0 +1,8 @@
+export default function TodoList({ items }) {
+    return (<ul className="todos" data-ast-id="el-46638cd1">
+      {items.map((item)=>(<li key={item.id} data-ast-id="el-46638cd1-2">
+          <input type="checkbox" checked={item.done} readOnly data-ast-id="el-46638cd1-3"/>
+          <span data-ast-id="el-46638cd1-4">{item.title}</span>
+        </li>))}
+    </ul>);
+}
@@ -0,0 +1,20 @@
+import React, { FormEvent } from "react";
+interface Props {
+    onSubmit: (email: string, password: string) => void;
+}
+export function LoginForm({ onSubmit }: Props) {
+    const handle = (e: FormEvent<HTMLFormElement>)=>{
+        e.preventDefault();
+        const data = new FormData(e.currentTarget);
+        onSubmit(String(data.get("email")), String(data.get("password")));
+    };
+    return (<form onSubmit={handle} data-ast-id="el-48f752e3">
+      <label data-ast-id="el-f9a44b73">
+        Email <input name="email" type="email" data-ast-id="el-c0a1f7e6"/>
+      </label>
+      <label data-ast-id="el-f6d47799">
+        Password <input name="password" type="password" data-ast-id="el-cc4f7c8f"/>
+      </label>
+      <button type="submit" data-ast-id="el-109d2acf">Log in</button>
+    </form>);
+}
@@ -0,0 +1,20 @@
+import React, { FormEvent } from "react";
+interface Props {
+    onSubmit: (email: string, password: string) => void;
+}
+export function LoginForm({ onSubmit }: Props) {
+    const handle = (e: FormEvent<HTMLFormElement>)=>{
+        e.preventDefault();
+        const data = new FormData(e.currentTarget);
+        onSubmit(String(data.get("email")), String(data.get("password")));
+    };
+    return (<form onSubmit={handle} data-ast-id="el-form-0">
+      <label data-ast-id="el-label-0-1">
+        Email <input name="email" type="email" data-ast-id="el-input-0-1-2"/>
+      </label>
+      <label data-ast-id="el-label-0-3">
+        Password <input name="password" type="password" data-ast-id="el-input-0-3-4"/>
+      </label>
+      <button type="submit" data-ast-id="el-button-0-5">Log in</button>
+    </form>);
+}
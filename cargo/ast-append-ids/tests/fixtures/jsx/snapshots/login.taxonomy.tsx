@@ -0,0 +1,20 @@
+import React, { FormEvent } from "react";
+interface Props {
+    onSubmit: (email: string, password: string) => void;
+}
+export function LoginForm({ onSubmit }: Props) {
+    const handle = (e: FormEvent<HTMLFormElement>)=>{
+        e.preventDefault();
+        const data = new FormData(e.currentTarget);
+        onSubmit(String(data.get("email")), String(data.get("password")));
+    };
+    return (<form onSubmit={handle} data-ast-id="el-form-0">
+      <label data-ast-id="el-form.label-0">
+        Email <input name="email" type="email" data-ast-id="el-form.input-0"/>
+      </label>
+      <label data-ast-id="el-form.label-1">
+        Password <input name="password" type="password" data-ast-id="el-form.input-1"/>
+      </label>
+      <button type="submit" data-ast-id="el-form.button-0">Log in</button>
+    </form>);
+}
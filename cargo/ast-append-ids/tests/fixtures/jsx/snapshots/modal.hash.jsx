@@ -0,0 +1,13 @@
+export function ConfirmModal({ open, onClose, onConfirm }) {
+    if (!open) return null;
+    return (<div className="backdrop" onClick={onClose} data-ast-id="el-63d07ede">
+      <div role="dialog" aria-modal="true" aria-labelledby="confirm-title" data-ast-id="el-05f6a0fb">
+        <h2 id="confirm-title" data-ast-id="el-f9f07cdb">Delete project?</h2>
+        <p data-ast-id="el-879d4f21">This cannot be undone.</p>
+        <footer data-ast-id="el-97da1f73">
+          <button onClick={onClose} data-ast-id="el-975732dc">Cancel</button>
+          <button className="danger" onClick={onConfirm} data-ast-id="el-f5b53aff">Delete</button>
+        </footer>
+      </div>
+    </div>);
+}
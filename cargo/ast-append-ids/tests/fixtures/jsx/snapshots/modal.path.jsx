@@ -0,0 +1,13 @@
+export function ConfirmModal({ open, onClose, onConfirm }) {
+    if (!open) return null;
+    return (<div className="backdrop" onClick={onClose} data-ast-id="el-div-0">
+      <div role="dialog" aria-modal="true" aria-labelledby="confirm-title" data-ast-id="el-div-0-1">
+        <h2 id="confirm-title" data-ast-id="el-h2-0-1-2">Delete project?</h2>
+        <p data-ast-id="el-p-0-1-3">This cannot be undone.</p>
+        <footer data-ast-id="el-footer-0-1-4">
+          <button onClick={onClose} data-ast-id="el-button-0-1-4-5">Cancel</button>
+          <button className="danger" onClick={onConfirm} data-ast-id="el-button-0-1-4-6">Delete</button>
+        </footer>
+      </div>
+    </div>);
+}
@@ -0,0 +1,13 @@
+export function ConfirmModal({ open, onClose, onConfirm }) {
+    if (!open) return null;
+    return (<div className="backdrop" onClick={onClose} data-ast-id="el-46638cd1">
+      <div role="dialog" aria-modal="true" aria-labelledby="confirm-title" data-ast-id="el-46638cd1-2">
+        <h2 id="confirm-title" data-ast-id="el-46638cd1-3">Delete project?</h2>
+        <p data-ast-id="el-46638cd1-4">This cannot be undone.</p>
+        <footer data-ast-id="el-46638cd1-5">
+          <button onClick={onClose} data-ast-id="el-46638cd1-6">Cancel</button>
+          <button className="danger" onClick={onConfirm} data-ast-id="el-46638cd1-7">Delete</button>
+        </footer>
+      </div>
+    </div>);
+}
@@ -0,0 +1,13 @@
+export function ConfirmModal({ open, onClose, onConfirm }) {
+    if (!open) return null;
+    return (<div className="backdrop" onClick={onClose} data-ast-id="el-div-0">
+      <div role="dialog" aria-modal="true" aria-labelledby="confirm-title" data-ast-id="el-div-1">
+        <h2 id="confirm-title" data-ast-id="el-div.h2-0">Delete project?</h2>
+        <p data-ast-id="el-div.p-0">This cannot be undone.</p>
+        <footer data-ast-id="el-div.footer-0">
+          <button onClick={onClose} data-ast-id="el-footer.div.button-0">Cancel</button>
+          <button className="danger" onClick={onConfirm} data-ast-id="el-footer.div.button-1">Delete</button>
+        </footer>
+      </div>
+    </div>);
+}
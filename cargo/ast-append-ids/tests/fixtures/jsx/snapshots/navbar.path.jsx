@@ -0,0 +1,7 @@
+import { Link } from "react-router-dom";
+export const Navbar = ({ user })=>(<nav className="navbar" data-ast-id="el-nav-0">
+    <Link to="/">Home</Link>
+    <Link to="/pricing">Pricing</Link>
+    <Link to="/docs">Docs</Link>
+    {user ? (<Link to="/account">{user.name}</Link>) : (<a href="/login" data-ast-id="el-a-0-1">Sign in</a>)}
+  </nav>);
@@ -0,0 +1,7 @@
+export const Pagination = ({ page, pages, go })=>(<nav aria-label="Pagination" data-ast-id="el-0fabb771">
+    <button disabled={page === 1} onClick={()=>go(page - 1)} data-ast-id="el-d51f63d3">Previous</button>
+    <span data-ast-id="el-7f8fe2e1">
+      Page {page} of {pages}
+    </span>
+    <button disabled={page === pages} onClick={()=>go(page + 1)} data-ast-id="el-90f7440a">Next</button>
+  </nav>);
@@ -0,0 +1,7 @@
+export const Pagination = ({ page, pages, go })=>(<nav aria-label="Pagination" data-ast-id="el-nav-0">
+    <button disabled={page === 1} onClick={()=>go(page - 1)} data-ast-id="el-button-0-1">Previous</button>
+    <span data-ast-id="el-span-0-2">
+      Page {page} of {pages}
+    </span>
+    <button disabled={page === pages} onClick={()=>go(page + 1)} data-ast-id="el-button-0-3">Next</button>
+  </nav>);
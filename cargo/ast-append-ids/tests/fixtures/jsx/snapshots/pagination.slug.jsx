@@ -0,0 +1,7 @@
+export const Pagination = ({ page, pages, go })=>(<nav aria-label="Pagination" data-ast-id="el-46638cd1">
+    <button disabled={page === 1} onClick={()=>go(page - 1)} data-ast-id="el-46638cd1-2">Previous</button>
+    <span data-ast-id="el-46638cd1-3">
+      Page {page} of {pages}
+    </span>
+    <button disabled={page === pages} onClick={()=>go(page + 1)} data-ast-id="el-46638cd1-4">Next</button>
+  </nav>);
@@ -0,0 +1,7 @@
+export const Pagination = ({ page, pages, go })=>(<nav aria-label="Pagination" data-ast-id="el-nav-0">
+    <button disabled={page === 1} onClick={()=>go(page - 1)} data-ast-id="el-pagination.button-0">Previous</button>
+    <span data-ast-id="el-pagination.span-0">
+      Page {page} of {pages}
+    </span>
+    <button disabled={page === pages} onClick={()=>go(page + 1)} data-ast-id="el-pagination.button-1">Next</button>
+  </nav>);
@@ -0,0 +1,7 @@
+import { createPortal } from "react-dom";
+export function Toast({ message, onDismiss }) {
+    return createPortal(<div className="toast" role="alert" data-ast-id="el-63d07ede">
+      <span data-ast-id="el-f8cc1947">{message}</span>
+      <button aria-label="Dismiss" onClick={onDismiss} data-ast-id="el-fbe1561c">×</button>
+    </div>, document.body);
+}
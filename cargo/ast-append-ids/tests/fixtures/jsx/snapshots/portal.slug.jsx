@@ -0,0 +1,7 @@
+import { createPortal } from "react-dom";
+export function Toast({ message, onDismiss }) {
+    return createPortal(<div className="toast" role="alert" data-ast-id="el-46638cd1">
+      <span data-ast-id="el-46638cd1-2">{message}</span>
+      <button aria-label="Dismiss" onClick={onDismiss} data-ast-id="el-46638cd1-3">×</button>
+    </div>, document.body);
+}
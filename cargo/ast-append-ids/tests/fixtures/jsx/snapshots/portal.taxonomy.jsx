@@ -0,0 +1,7 @@
+import { createPortal } from "react-dom";
+export function Toast({ message, onDismiss }) {
+    return createPortal(<div className="toast" role="alert" data-ast-id="el-div-0">
+      <span data-ast-id="el-span-0">{message}</span>
+      <button aria-label="Dismiss" onClick={onDismiss} data-ast-id="el-button-0">×</button>
+    </div>, document.body);
+}
@@ -0,0 +1,11 @@
+export default function Search({ query, setQuery, results }) {
+    return (<div role="search" data-ast-id="el-63d07ede">
+      <input type="search" value={query} onChange={(e)=>setQuery(e.target.value)} placeholder="Search" data-ast-id="el-a6813956"/>
+      {results.length === 0 && query && <p className="empty" data-ast-id="el-390b8ed0">No results for “{query}”</p>}
+      <ol data-ast-id="el-fce30aca">
+        {results.map((r)=>(<li key={r.url} data-ast-id="el-17cab045">
+            <a href={r.url} data-ast-id="el-17e236d8">{r.title}</a>
+          </li>))}
+      </ol>
+    </div>);
+}
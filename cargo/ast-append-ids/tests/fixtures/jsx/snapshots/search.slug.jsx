@@ -0,0 +1,11 @@
+export default function Search({ query, setQuery, results }) {
+    return (<div role="search" data-ast-id="el-46638cd1">
+      <input type="search" value={query} onChange={(e)=>setQuery(e.target.value)} placeholder="Search" data-ast-id="el-46638cd1-2"/>
+      {results.length === 0 && query && <p className="empty" data-ast-id="el-46638cd1-3">No results for “{query}”</p>}
+      <ol data-ast-id="el-46638cd1-4">
+        {results.map((r)=>(<li key={r.url} data-ast-id="el-46638cd1-5">
+            <a href={r.url} data-ast-id="el-46638cd1-6">{r.title}</a>
+          </li>))}
+      </ol>
+    </div>);
+}
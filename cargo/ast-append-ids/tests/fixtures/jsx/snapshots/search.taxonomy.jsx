@@ -0,0 +1,11 @@
+export default function Search({ query, setQuery, results }) {
+    return (<div role="search" data-ast-id="el-div-0">
+      <input type="search" value={query} onChange={(e)=>setQuery(e.target.value)} placeholder="Search" data-ast-id="el-input-0"/>
+      {results.length === 0 && query && <p className="empty" data-ast-id="el-p-0">No results for “{query}”</p>}
+      <ol data-ast-id="el-ol-0">
+        {results.map((r)=>(<li key={r.url} data-ast-id="el-li-0">
+            <a href={r.url} data-ast-id="el-a-0">{r.title}</a>
+          </li>))}
+      </ol>
+    </div>);
+}
@@ -0,0 +1,15 @@
+export function Settings({ prefs, update }: {
+    prefs: Record<string, boolean>;
+    update: (k: string) => void;
+}) {
+    return (<fieldset data-ast-id="el-0ea7a185">
+      <legend data-ast-id="el-956d0364">Notifications</legend>
+      {Object.entries(prefs).map(([key, on])=>(<label key={key} data-ast-id="el-cfbf9389">
+          <input type="checkbox" checked={on} onChange={()=>update(key)} data-ast-id="el-e9ddf3e9"/>
+          {key}
+        </label>))}
+      <button type="button" disabled={!Object.values(prefs).some(Boolean)} data-ast-id="el-f4cac71d">
+        Save
+      </button>
+    </fieldset>);
+}
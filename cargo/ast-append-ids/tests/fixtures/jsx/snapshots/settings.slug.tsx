@@ -0,0 +1,15 @@
+export function Settings({ prefs, update }: {
+    prefs: Record<string, boolean>;
+    update: (k: string) => void;
+}) {
+    return (<fieldset data-ast-id="el-46638cd1">
+      <legend data-ast-id="el-46638cd1-2">Notifications</legend>
+      {Object.entries(prefs).map(([key, on])=>(<label key={key} data-ast-id="el-46638cd1-3">
+          <input type="checkbox" checked={on} onChange={()=>update(key)} data-ast-id="el-46638cd1-4"/>
+          {key}
+        </label>))}
+      <button type="button" disabled={!Object.values(prefs).some(Boolean)} data-ast-id="el-46638cd1-5">
+        Save
+      </button>
+    </fieldset>);
+}
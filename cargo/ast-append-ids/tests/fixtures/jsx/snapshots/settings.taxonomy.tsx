@@ -0,0 +1,15 @@
+export function Settings({ prefs, update }: {
+    prefs: Record<string, boolean>;
+    update: (k: string) => void;
+}) {
+    return (<fieldset data-ast-id="el-fieldset-0">
+      <legend data-ast-id="el-legend-0">Notifications</legend>
+      {Object.entries(prefs).map(([key, on])=>(<label key={key} data-ast-id="el-label-0">
+          <input type="checkbox" checked={on} onChange={()=>update(key)} data-ast-id="el-input-0"/>
+          {key}
+        </label>))}
+      <button type="button" disabled={!Object.values(prefs).some(Boolean)} data-ast-id="el-button-0">
+        Save
+      </button>
+    </fieldset>);
+}
@@ -0,0 +1,6 @@
+export const Icon = ()=>(<svg width="24" height="24" viewBox="0 0 24 24" data-ast-id="el-956faa85">
+    <g fill="none" stroke="currentColor" data-ast-id="el-8379fcec">
+      <path d="M4 12h16" data-ast-id="el-c1a07dea"/>
+      <circle cx="12" cy="12" r="10" data-ast-id="el-14d91252"/>
+    </g>
+  </svg>);
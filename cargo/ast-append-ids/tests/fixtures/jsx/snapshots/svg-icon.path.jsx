@@ -0,0 +1,6 @@
+export const Icon = ()=>(<svg width="24" height="24" viewBox="0 0 24 24" data-ast-id="el-svg-0">
+    <g fill="none" stroke="currentColor" data-ast-id="el-g-0-1">
+      <path d="M4 12h16" data-ast-id="el-path-0-1-2"/>
+      <circle cx="12" cy="12" r="10" data-ast-id="el-circle-0-1-3"/>
+    </g>
+  </svg>);
@@ -0,0 +1,6 @@
+export const Icon = ()=>(<svg width="24" height="24" viewBox="0 0 24 24" data-ast-id="el-46638cd1">
+    <g fill="none" stroke="currentColor" data-ast-id="el-46638cd1-2">
+      <path d="M4 12h16" data-ast-id="el-46638cd1-3"/>
+      <circle cx="12" cy="12" r="10" data-ast-id="el-46638cd1-4"/>
+    </g>
+  </svg>);
@@ -0,0 +1,23 @@
+type Row = {
+    id: string;
+    name: string;
+    total: number;
+};
+export function OrdersTable({ rows }: {
+    rows: Row[];
+}) {
+    return (<table data-ast-id="el-2684c24b">
+      <thead data-ast-id="el-8abcd7bc">
+        <tr data-ast-id="el-a09ae553">
+          <th data-ast-id="el-9de91625">Customer</th>
+          <th data-ast-id="el-f9c9cb6c">Total</th>
+        </tr>
+      </thead>
+      <tbody data-ast-id="el-bb2f98df">
+        {rows.map((row)=>(<tr key={row.id} data-ast-id="el-a8449f0b">
+            <td data-ast-id="el-c9ac96f6">{row.name}</td>
+            <td data-ast-id="el-4b9f6591">{row.total.toFixed(2)}</td>
+          </tr>))}
+      </tbody>
+    </table>);
+}
@@ -0,0 +1,23 @@
+type Row = {
+    id: string;
+    name: string;
+    total: number;
+};
+export function OrdersTable({ rows }: {
+    rows: Row[];
+}) {
+    return (<table data-ast-id="el-table-0">
+      <thead data-ast-id="el-thead-0-1">
+        <tr data-ast-id="el-tr-0-1-2">
+          <th data-ast-id="el-th-0-1-2-3">Customer</th>
+          <th data-ast-id="el-th-0-1-2-4">Total</th>
+        </tr>
+      </thead>
+      <tbody data-ast-id="el-tbody-0-5">
+        {rows.map((row)=>(<tr key={row.id} data-ast-id="el-tr-0-5-6">
+            <td data-ast-id="el-td-0-5-6-7">{row.name}</td>
+            <td data-ast-id="el-td-0-5-6-8">{row.total.toFixed(2)}</td>
+          </tr>))}
+      </tbody>
+    </table>);
+}
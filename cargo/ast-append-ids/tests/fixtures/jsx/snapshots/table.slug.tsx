@@ -0,0 +1,23 @@
+type Row = {
+    id: string;
+    name: string;
+    total: number;
+};
+export function OrdersTable({ rows }: {
+    rows: Row[];
+}) {
+    return (<table data-ast-id="el-46638cd1">
+      <thead data-ast-id="el-46638cd1-2">
+        <tr data-ast-id="el-46638cd1-3">
+          <th data-ast-id="el-46638cd1-4">Customer</th>
+          <th data-ast-id="el-46638cd1-5">Total</th>
+        </tr>
+      </thead>
+      <tbody data-ast-id="el-46638cd1-6">
+        {rows.map((row)=>(<tr key={row.id} data-ast-id="el-46638cd1-7">
+            <td data-ast-id="el-46638cd1-8">{row.name}</td>
+            <td data-ast-id="el-46638cd1-9">{row.total.toFixed(2)}</td>
+          </tr>))}
+      </tbody>
+    </table>);
+}
@@ -0,0 +1,23 @@
+type Row = {
+    id: string;
+    name: string;
+    total: number;
+};
+export function OrdersTable({ rows }: {
+    rows: Row[];
+}) {
+    return (<table data-ast-id="el-table-0">
+      <thead data-ast-id="el-thead-0">
+        <tr data-ast-id="el-tr-0">
+          <th data-ast-id="el-th-0">Customer</th>
+          <th data-ast-id="el-th-1">Total</th>
+        </tr>
+      </thead>
+      <tbody data-ast-id="el-tbody-0">
+        {rows.map((row)=>(<tr key={row.id} data-ast-id="el-tr-1">
+            <td data-ast-id="el-td-0">{row.name}</td>
+            <td data-ast-id="el-td-1">{row.total.toFixed(2)}</td>
+          </tr>))}
+      </tbody>
+    </table>);
+}
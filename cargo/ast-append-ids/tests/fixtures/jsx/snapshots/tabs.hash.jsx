@@ -0,0 +1,12 @@
+import { useState } from "react";
+export default function Tabs({ tabs }) {
+    const [active, setActive] = useState(0);
+    return (<div className="tabs" data-ast-id="el-63d07ede">
+      <div role="tablist" data-ast-id="el-05f6a0fb">
+        {tabs.map((tab, i)=>(<button role="tab" aria-selected={i === active} onClick={()=>setActive(i)} data-ast-id="el-0faf67bf">
+            {tab.label}
+          </button>))}
+      </div>
+      <section role="tabpanel" data-ast-id="el-463bab77">{tabs[active].content}</section>
+    </div>);
+}
@@ -0,0 +1,12 @@
+import { useState } from "react";
+export default function Tabs({ tabs }) {
+    const [active, setActive] = useState(0);
+    return (<div className="tabs" data-ast-id="el-div-0">
+      <div role="tablist" data-ast-id="el-div-0-1">
+        {tabs.map((tab, i)=>(<button role="tab" aria-selected={i === active} onClick={()=>setActive(i)} data-ast-id="el-button-0-1-2">
+            {tab.label}
+          </button>))}
+      </div>
+      <section role="tabpanel" data-ast-id="el-section-0-3">{tabs[active].content}</section>
+    </div>);
+}
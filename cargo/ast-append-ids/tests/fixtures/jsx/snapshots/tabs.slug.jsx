@@ -0,0 +1,12 @@
+import { useState } from "react";
+export default function Tabs({ tabs }) {
+    const [active, setActive] = useState(0);
+    return (<div className="tabs" data-ast-id="el-46638cd1">
+      <div role="tablist" data-ast-id="el-46638cd1-2">
+        {tabs.map((tab, i)=>(<button role="tab" aria-selected={i === active} onClick={()=>setActive(i)} data-ast-id="el-46638cd1-3">
+            {tab.label}
+          </button>))}
+      </div>
+      <section role="tabpanel" data-ast-id="el-46638cd1-4">{tabs[active].content}</section>
+    </div>);
+}
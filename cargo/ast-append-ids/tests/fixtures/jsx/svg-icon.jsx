@@ -0,0 +1,8 @@
+export const Icon = () => (
+  <svg width="24" height="24" viewBox="0 0 24 24">
+    <g fill="none" stroke="currentColor">
+      <path d="M4 12h16" />
+      <circle cx="12" cy="12" r="10" />
+    </g>
+  </svg>
+);
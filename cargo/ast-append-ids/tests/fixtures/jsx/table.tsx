@@ -0,0 +1,22 @@
+type Row = { id: string; name: string; total: number };
+
+export function OrdersTable({ rows }: { rows: Row[] }) {
+  return (
+    <table>
+      <thead>
+        <tr>
+          <th>Customer</th>
+          <th>Total</th>
+        </tr>
+      </thead>
+      <tbody>
+        {rows.map((row) => (
+          <tr key={row.id}>
+            <td>{row.name}</td>
+            <td>{row.total.toFixed(2)}</td>
+          </tr>
+        ))}
+      </tbody>
+    </table>
+  );
+}
@@ -0,0 +1,17 @@
+import { useState } from "react";
+
+export default function Tabs({ tabs }) {
+  const [active, setActive] = useState(0);
+  return (
+    <div className="tabs">
+      <div role="tablist">
+        {tabs.map((tab, i) => (
+          <button role="tab" aria-selected={i === active} onClick={() => setActive(i)}>
+            {tab.label}
+          </button>
+        ))}
+      </div>
+      <section role="tabpanel">{tabs[active].content}</section>
+    </div>
+  );
+}
@@ -1,7 +1,15 @@
-use ast_append_ids::{AstProcessor, IdOptions, IdStrategy};
+#![allow(clippy::field_reassign_with_default)]
+
+use ast_append_ids::{processor_for, AstProcessor, IdOptions, IdStrategy, Processor};
+use ast_append_ids::detect::Format;
 use ast_append_ids::jsx::JsxProcessor;
 use ast_append_ids::xml::XmlProcessor;
 use ast_append_ids::html::HtmlProcessor;
+use ast_append_ids::change_log::change_records;
+use ast_append_ids::coverage::Coverage;
+use ast_append_ids::find::find_ids;
+use ast_append_ids::id_generator::{Change, ChangeAction, IdGenerator};
+use std::collections::HashSet;
 
 #[test]
 fn test_jsx_basic_processing() {
@@ -252,4 +260,887 @@ fn test_unique_id_generation() {
     
     let unique_ids: std::collections::HashSet<_> = ids.iter().cloned().collect();
     assert_eq!(ids.len(), unique_ids.len(), "All IDs should be unique");
-}
\ No newline at end of file
+}
+
+/// Runs `process` twice under every strategy, with and without overwrite, and
+/// checks the second run changes nothing.
+fn assert_idempotent(name: &str, input: &str, process: impl Fn(&str, &IdOptions) -> String) {
+    for strategy in [IdStrategy::Hash, IdStrategy::Slug, IdStrategy::Path] {
+        for overwrite in [false, true] {
+            let mut options = IdOptions::default();
+            options.strategy = strategy.clone();
+            options.overwrite = overwrite;
+
+            let once = process(input, &options);
+            let twice = process(&once, &options);
+            assert_eq!(
+                once, twice,
+                "{} is not idempotent with {:?} strategy (overwrite: {})",
+                name, strategy, overwrite
+            );
+            assert!(!twice.contains(&format!("{}-2-2", options.prefix)), "{} churned suffixes", name);
+        }
+    }
+}
+
+#[test]
+fn test_idempotency() {
+    let jsx = |input: &str, options: &IdOptions| JsxProcessor::new().process(input, options).unwrap();
+    let html = |input: &str, options: &IdOptions| HtmlProcessor::new().process(input, options).unwrap();
+    let xml = |input: &str, options: &IdOptions| XmlProcessor::new().process(input, options).unwrap();
+
+    assert_idempotent(
+        "jsx",
+        r#"
+            // ast-append-ids: on
+            const App = () => (
+                <>
+                    <ul>
+                        <li>Same</li>
+                        <li>Same</li>
+                        <li data-ast-id="hand-written">Other</li>
+                    </ul>
+                    {/* ast-append-ids-ignore-next */}
+                    <aside><p>Skipped</p></aside>
+                    {/* ast-append-ids: prefix=card- */}
+                    <Card title="x"><p className="a">Text</p></Card>
+                    {items.map(item => <span key={item.id}>{item.name}</span>)}
+                </>
+            );
+        "#,
+        jsx,
+    );
+
+    assert_idempotent(
+        "html",
+        r#"<!DOCTYPE html>
+<html>
+  <body>
+    <nav><a href="/">Home</a><a href="/">Home</a></nav>
+    <main data-ast-id="main">
+      <p class="lead">Hello <b>world</b></p>
+      <img src="a.png">
+      <template><div>Inert</div></template>
+    </main>
+  </body>
+</html>"#,
+        html,
+    );
+
+    assert_idempotent(
+        "xml",
+        r#"<?xml version="1.0"?>
+<catalog>
+  <book id="b1" lang="en"><title>Rust</title></book>
+  <book id="b2" data-ast-id="kept" lang="fr"><title>Rust</title></book>
+  <empty/>
+</catalog>"#,
+        xml,
+    );
+}
+
+fn logging_generator() -> IdGenerator {
+    let mut generator = IdGenerator::new();
+    generator.enable_change_log();
+    generator
+}
+
+fn positions(input: &str, changes: Vec<Change>) -> Vec<(usize, usize, String, ChangeAction, String)> {
+    change_records("file", input, changes)
+        .into_iter()
+        .map(|r| (r.line, r.col, r.tag, r.action, r.id))
+        .collect()
+}
+
+#[test]
+fn test_change_log_positions() {
+    let mut options = IdOptions::default();
+    options.strategy = IdStrategy::Path;
+
+    let html = "<div data-ast-id=\"keep\">\n  <!-- <p> --><script>'<p>'</script>\n  <p>Hi</p>\n</div>";
+    let mut processor = HtmlProcessor::with_generator(logging_generator());
+    processor.process(html, &options).unwrap();
+    assert_eq!(
+        positions(html, processor.into_generator().take_changes()),
+        vec![
+            (1, 1, "div".to_string(), ChangeAction::Skipped, "keep".to_string()),
+            (2, 15, "script".to_string(), ChangeAction::Added, "el-script-0-1".to_string()),
+            (3, 3, "p".to_string(), ChangeAction::Added, "el-p-0-2".to_string()),
+        ]
+    );
+
+    let xml = "<root>\n  <item data-ast-id=\"old\"/>\n</root>";
+    options.overwrite = true;
+    let mut processor = XmlProcessor::with_generator(logging_generator());
+    processor.process(xml, &options).unwrap();
+    assert_eq!(
+        positions(xml, processor.into_generator().take_changes()),
+        vec![
+            (1, 1, "root".to_string(), ChangeAction::Added, "el-root-0".to_string()),
+            (2, 3, "item".to_string(), ChangeAction::Overwritten, "el-item-0-1".to_string()),
+        ]
+    );
+
+    let jsx = "const a = <main>\n  <b>x</b>\n</main>;\nconst v = html`<ul>\n  <li>${x}</li><li>y</li></ul>`;";
+    options.overwrite = false;
+    options.template_tags = vec!["html".to_string()];
+    let mut generator = logging_generator();
+    generator.enable_filter_log();
+    let mut processor = JsxProcessor::with_generator(generator);
+    processor.process(jsx, &options).unwrap();
+    let mut generator = processor.into_generator();
+    let raw_changes = generator.take_changes();
+    let offsets: Vec<_> = raw_changes.iter().map(|change| change.offset).collect();
+    let changes = positions(jsx, raw_changes);
+    let located: Vec<_> = changes.iter().map(|(line, col, tag, ..)| (*line, *col, tag.as_str())).collect();
+    assert_eq!(located, vec![(1, 11, "main"), (2, 3, "b"), (4, 16, "ul"), (5, 3, "li"), (5, 16, "li")]);
+    // Filter decisions in template markup point into the source as well
+    let decided: Vec<_> = generator.take_decisions().iter().map(|decision| decision.offset).collect();
+    assert_eq!(decided, offsets);
+}
+
+#[test]
+fn test_find_ids_locations() {
+    let find = |content: &str, locations| {
+        find_ids(content, locations, "el-", true)
+            .into_iter()
+            .map(|found| (found.line, found.col, found.tag, found.id))
+            .collect::<Vec<_>>()
+    };
+
+    let html = "<main>\n  <!-- <p> --><p data-ast-id=\"el-p\">x</p><p data-ast-id=\"hand\"></p>\n</main>";
+    let locations = ast_append_ids::html::locate_ids(html, "data-ast-id").unwrap();
+    assert_eq!(find(html, locations), vec![(2, 15, "p".to_string(), "el-p".to_string())]);
+
+    let xml = "<root>\n  <item data-ast-id=\"el-a\"/>\n  <item data-ast-id=\"el-b\">x</item>\n</root>";
+    let locations = ast_append_ids::xml::locate_ids(xml, "data-ast-id").unwrap();
+    assert_eq!(
+        find(xml, locations),
+        vec![
+            (2, 3, "item".to_string(), "el-a".to_string()),
+            (3, 3, "item".to_string(), "el-b".to_string()),
+        ]
+    );
+
+    let jsx = "const a = (\n  <Card data-ast-id=\"x\">\n    <b data-ast-id=\"el-b\" />\n  </Card>\n);\nh('div', { 'data-ast-id': 'el-h' });";
+    let locations = ast_append_ids::jsx::locate_ids(jsx, "data-ast-id").unwrap();
+    assert_eq!(
+        find(jsx, locations),
+        vec![
+            (3, 5, "b".to_string(), "el-b".to_string()),
+            (6, 1, "div".to_string(), "el-h".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_policy_coverage() {
+    let policy = "button, a, [role]";
+    let summary = |content: &str, elements| {
+        let coverage = Coverage::new(content, elements);
+        let uncovered: Vec<_> = coverage.uncovered.iter().map(|u| (u.line, u.col, u.tag.clone())).collect();
+        (coverage.covered, coverage.total, uncovered)
+    };
+
+    let html = "<nav role=\"menu\" data-ast-id=\"n\">\n  <a href=\"/\">Home</a><button data-ast-id=\"b\">Go</button>\n</nav>";
+    let elements = ast_append_ids::html::policy_elements(html, policy, "data-ast-id").unwrap();
+    assert_eq!(summary(html, elements), (2, 3, vec![(2, 3, "a".to_string())]));
+
+    let xml = "<ui>\n  <button/>\n  <panel role=\"dialog\" data-ast-id=\"p\"><a data-ast-id=\"l\"/></panel>\n</ui>";
+    let elements = ast_append_ids::xml::policy_elements(xml, policy, "data-ast-id").unwrap();
+    assert_eq!(summary(xml, elements), (2, 3, vec![(2, 3, "button".to_string())]));
+
+    let jsx = "const A = () => (\n  <div role=\"main\">\n    <button data-ast-id={aid(\"b\")}>x</button>\n  </div>\n);";
+    let elements = ast_append_ids::jsx::policy_elements(jsx, policy, "data-ast-id").unwrap();
+    assert_eq!(summary(jsx, elements), (1, 2, vec![(2, 3, "div".to_string())]));
+
+    assert!(ast_append_ids::xml::policy_elements(xml, "button,", "data-ast-id").is_err());
+
+    // An empty ID names nothing
+    let html = "<button data-ast-id=\"\">Go</button><a data-ast-id=\" \">Home</a>";
+    let elements = ast_append_ids::html::policy_elements(html, policy, "data-ast-id").unwrap();
+    assert_eq!(summary(html, elements).0, 0);
+    let xml = "<ui><button data-ast-id=\"\"/></ui>";
+    let elements = ast_append_ids::xml::policy_elements(xml, policy, "data-ast-id").unwrap();
+    assert_eq!(summary(xml, elements).0, 0);
+    let jsx = "const A = () => <div><button data-ast-id=\"\" /><a data-ast-id={\"\"} /><a data-ast-id /></div>;";
+    let elements = ast_append_ids::jsx::policy_elements(jsx, policy, "data-ast-id").unwrap();
+    let uncovered = vec![(1, 22, "button".to_string()), (1, 47, "a".to_string()), (1, 69, "a".to_string())];
+    assert_eq!(summary(jsx, elements), (0, 3, uncovered));
+}
+
+#[test]
+fn test_diff_ids_between_versions() {
+    use ast_append_ids::diff::{diff_ids, DiffKind, IdEntry};
+
+    let entries = |content: &str| -> Vec<IdEntry> {
+        ast_append_ids::html::locate_ids(content, "data-ast-id")
+            .unwrap()
+            .into_iter()
+            .map(IdEntry::from)
+            .collect()
+    };
+    let old = entries(r#"<ul><li data-ast-id="a">1</li><li data-ast-id="b" class="x">2</li><li data-ast-id="c">3</li></ul>"#);
+    let new = entries(r#"<ul><li data-ast-id="a">one</li><li data-ast-id="b" class="y">2</li><li data-ast-id="d">3</li></ul>"#);
+
+    let diffs: Vec<_> = diff_ids(old, new).into_iter().map(|diff| (diff.kind, diff.id)).collect();
+    assert_eq!(
+        diffs,
+        vec![
+            (DiffKind::Changed, "b".to_string()),
+            (DiffKind::Added, "d".to_string()),
+            (DiffKind::Removed, "c".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_text_export_import() {
+    use ast_append_ids::localize::{TextEntry, TextFormat};
+    use std::collections::HashMap;
+
+    let html = "<nav>\n  <a data-ast-id=\"home\">Home</a>\n  <a data-ast-id=\"cart\">Cart <span>(2)</span></a>\n</nav>";
+    let entries: Vec<TextEntry> = ast_append_ids::html::element_texts(html, "data-ast-id")
+        .unwrap()
+        .into_iter()
+        .map(|(id, text)| TextEntry { file: "nav.html".to_string(), id, text })
+        .collect();
+    let csv = TextFormat::Csv.write(&entries).unwrap();
+    assert_eq!(csv, "file,id,text\nnav.html,home,Home\n");
+
+    let translated = TextFormat::Csv.read(&csv.replace(",Home", ",\"Accueil, <maison>\"")).unwrap();
+    let texts: HashMap<_, _> = translated.into_iter().map(|entry| (entry.id, entry.text)).collect();
+    let (output, replaced) = ast_append_ids::html::replace_texts(html, "data-ast-id", &texts).unwrap();
+    assert_eq!(replaced, vec!["home".to_string()]);
+    assert!(output.contains("<a data-ast-id=\"home\">Accueil, &lt;maison&gt;</a>"));
+}
+
+#[test]
+fn test_env_prefix_correlates_ids() {
+    let input = "<main><h1>Title</h1><p>Body</p></main>";
+    let process = |env_prefix: &str| {
+        let mut options = IdOptions::default();
+        options.env_prefix = env_prefix.to_string();
+        HtmlProcessor::new().process(input, &options).unwrap()
+    };
+
+    let plain = process("");
+    let staging = process("staging:");
+    assert_eq!(staging.matches("data-ast-id=\"staging:el-").count(), 3);
+    assert_eq!(staging.replace("staging:", ""), plain);
+}
+
+#[test]
+fn test_story_ids_by_export() {
+    let input = r#"
+        export default { title: 'Button' };
+        export const Primary = () => <div><Button primary /></div>;
+        export const Secondary = { render: () => <section><button>Cancel</button></section> };
+        export const WithArgs = { args: { label: 'x' } };
+        export const __namedExportsOrder = ['Primary', 'Secondary', 'WithArgs'];
+    "#;
+    let processed = JsxProcessor::new().process(input, &IdOptions::default()).unwrap();
+
+    let stories = ast_append_ids::jsx::story_ids(&processed, "data-ast-id").unwrap();
+    let counts: Vec<_> = stories.iter().map(|(name, ids)| (name.as_str(), ids.len())).collect();
+    assert_eq!(counts, vec![("Primary", 1), ("Secondary", 2), ("WithArgs", 0)]);
+    assert!(stories[1].1.iter().all(|id| processed.contains(id.as_str())));
+}
+
+#[test]
+fn test_codemod_text_queries() {
+    use ast_append_ids::codemod::{rewrite_text_queries, text_index};
+
+    let component = r#"const Form = () => (
+        <form>
+            <button data-ast-id="el-submit">
+                Submit order
+            </button>
+            <button data-ast-id="el-a">Cancel</button>
+            <a data-ast-id="el-b">Cancel</a>
+            <li data-ast-id={`el-row-${id}`}>Row</li>
+        </form>
+    );"#;
+    let texts = ast_append_ids::jsx::element_texts(component, "data-ast-id").unwrap();
+    assert_eq!(texts.len(), 3);
+    let ids = text_index(texts);
+
+    let spec = "it('submits', () => {\n  click(screen.getByText(\"Submit order\"));\n  queryAllByText('Cancel');\n  getByText('Row');\n});";
+    let queries = ast_append_ids::jsx::text_queries(spec).unwrap();
+    assert_eq!(queries.len(), 3);
+
+    let (output, rewritten) = rewrite_text_queries(spec, queries, &ids);
+    assert_eq!(rewritten, 1);
+    assert_eq!(
+        output,
+        "it('submits', () => {\n  click(screen.getByTestId(\"el-submit\"));\n  queryAllByText('Cancel');\n  getByText('Row');\n});"
+    );
+}
+
+#[test]
+fn test_taxonomy_ids_follow_landmarks() {
+    let mut options = IdOptions::default();
+    options.strategy = IdStrategy::Taxonomy;
+    options.prefix = String::new();
+
+    let input = r#"const Page = () => (
+        <main>
+            <nav aria-label="Breadcrumbs"><a href="/">Home</a></nav>
+            <section id="cart">
+                <div data-component="LineItem"><button>Remove</button></div>
+                <button>Checkout</button>
+            </section>
+        </main>
+    );"#;
+    let result = JsxProcessor::new().process(input, &options).unwrap();
+    assert!(result.contains(r#"<a href="/" data-ast-id="breadcrumbs.a-0">"#));
+    assert!(result.contains(r#"<button data-ast-id="main.cart.lineitem.button-0">"#));
+    assert!(result.contains(r#"<button data-ast-id="main.cart.button-0">"#));
+}
+
+#[test]
+fn test_text_matches_tags_prices() {
+    let mut options = IdOptions::default();
+    options.text_matches = Some(r"\d+\.\d{2}".to_string());
+
+    let input = r#"<invoice><line><item>Tea</item><total>3.50</total></line><sum>Total <![CDATA[12.00]]></sum><note><b>1.00</b></note></invoice>"#;
+    let result = XmlProcessor::new().process(input, &options).unwrap();
+    assert!(result.contains("<line><item>Tea</item><total data-ast-id="));
+    assert!(result.contains("<sum data-ast-id="));
+    assert!(result.contains("<note><b data-ast-id="));
+    assert!(result.starts_with("<invoice><line>"));
+}
+
+#[test]
+fn test_range_leaves_other_bytes_alone() {
+    use ast_append_ids::ast_common::line_range;
+
+    // Only line 3 changes, and only by the attribute
+    let check = |input: &str, output: String| {
+        let (before, after): (Vec<&str>, Vec<&str>) = (input.lines().collect(), output.lines().collect());
+        assert_eq!(before.len(), after.len(), "{}", output);
+        for (line, (old, new)) in before.iter().zip(&after).enumerate() {
+            if line == 2 {
+                assert!(new.contains("data-ast-id=") && !old.contains("data-ast-id="), "{}", output);
+            } else {
+                assert_eq!(old, new);
+            }
+        }
+    };
+    let with_range = |input: &str| {
+        let mut options = IdOptions::default();
+        options.range = Some(line_range(input, 3, 3));
+        options
+    };
+
+    let jsx = "const Card = () => (\n  <section   className='card'>\n    <h2>Title</h2>\n    <p>{ text }</p>\n  </section>\n);";
+    check(jsx, JsxProcessor::new().process(jsx, &with_range(jsx)).unwrap());
+
+    let html = "<main>\n  <section class=card>\n    <h2>Title</h2>\n    <p>Text\n  </section>\n</main>\n";
+    check(html, HtmlProcessor::new().process(html, &with_range(html)).unwrap());
+
+    let xml = "<?xml version='1.0'?>\n<doc>\n  <title>Title</title>\n  <para/>\n</doc>";
+    check(xml, XmlProcessor::new().process(xml, &with_range(xml)).unwrap());
+}
+
+#[test]
+fn test_select_by_structure() {
+    use ast_append_ids::select::{locate, parse_query};
+
+    let input = r#"<catalog><book><title>Dune</title></book><book><title><![CDATA[Emma]]></title></book></catalog>"#;
+    let mut options = IdOptions::default();
+    options.strategy = IdStrategy::Path;
+    let processed = XmlProcessor::new().process(input, &options).unwrap();
+
+    let selector = parse_query("/catalog/book/title").unwrap();
+    let elements = ast_append_ids::xml::select_elements(&processed, &selector, "data-ast-id").unwrap();
+    let selected = locate(&processed, elements);
+    assert_eq!(
+        selected.iter().map(|s| (s.id.as_deref(), s.text.as_str())).collect::<Vec<_>>(),
+        vec![(Some("el-title-0-1-2"), "Dune"), (Some("el-title-0-3-4"), "Emma")]
+    );
+
+    let component = "const C = () => <nav><a data-ast-id=\"el-home\">Home</a><a>About {page}</a></nav>;";
+    let selector = parse_query("nav > a").unwrap();
+    let elements = ast_append_ids::jsx::select_elements(component, &selector, "data-ast-id").unwrap();
+    let selected = locate(component, elements);
+    assert_eq!(
+        selected.iter().map(|s| (s.id.as_deref(), s.text.as_str())).collect::<Vec<_>>(),
+        vec![(Some("el-home"), "Home"), (None, "About")]
+    );
+}
+
+#[test]
+fn test_sync_ids_to_variant() {
+    use ast_append_ids::selector::Selector;
+    use ast_append_ids::sync::sync_ids;
+
+    let reference = r#"const Checkout = () => (
+        <form data-ast-id="el-form">
+            <h2 data-ast-id="el-title">Checkout</h2>
+            <button data-ast-id="el-pay">Pay now</button>
+        </form>
+    );"#;
+    let variant = r#"const CheckoutB = () => (
+        <form>
+            <p>Free shipping today</p>
+            <h2>Checkout</h2>
+            <button className="big">Pay now</button>
+        </form>
+    );"#;
+
+    let all = Selector::parse("*").unwrap();
+    let reference_elements = ast_append_ids::jsx::select_elements(reference, &all, "data-ast-id").unwrap();
+    let variant_elements = ast_append_ids::jsx::select_elements(variant, &all, "data-ast-id").unwrap();
+    let plan = sync_ids(&reference_elements, &variant_elements, false);
+    assert_eq!(plan.copied, 3);
+    assert!(plan.skipped.is_empty());
+
+    let synced = ast_append_ids::jsx::apply_ids(variant, "data-ast-id", &plan.ids).unwrap();
+    assert!(synced.contains(r#"<form data-ast-id="el-form">"#));
+    assert!(synced.contains("<p>Free shipping today</p>"));
+    assert!(synced.contains(r#"<button className="big" data-ast-id="el-pay">"#));
+}
+
+#[test]
+fn test_options_schema_covers_options() {
+    let schema = serde_json::to_value(IdOptions::json_schema()).unwrap();
+    let properties = schema["properties"].as_object().unwrap();
+    let defaults = serde_json::to_value(IdOptions::default()).unwrap();
+    for name in defaults.as_object().unwrap().keys() {
+        assert!(properties.contains_key(name), "{} is missing from the schema", name);
+    }
+
+    let required: Vec<&str> = schema["required"].as_array().unwrap().iter().filter_map(|v| v.as_str()).collect();
+    assert!(required.contains(&"attr"));
+    assert!(!required.contains(&"templates"));
+    assert!(schema.to_string().contains("\"taxonomy\""));
+}
+
+#[test]
+fn test_options_validation() {
+    let err = serde_json::from_str::<IdOptions>(
+        r#"{"attr":"data-id","strategy":"hash","perfix":"x-","overwrite":false,"include":[],"exclude":[]}"#,
+    )
+    .unwrap_err();
+    assert!(err.to_string().starts_with("unknown field `perfix`"), "{}", err);
+
+    assert!(IdOptions::default().validate().is_ok());
+    let invalid = |change: fn(&mut IdOptions)| {
+        let mut options = IdOptions::default();
+        change(&mut options);
+        options.validate().unwrap_err()
+    };
+    assert_eq!(invalid(|o| o.attr.clear()), "Invalid option 'attr': must not be empty");
+    assert!(invalid(|o| o.attr = "data id".to_string()).starts_with("Invalid option 'attr': 'data id'"));
+    assert!(invalid(|o| o.selector = Some("div[".to_string())).starts_with("Invalid option 'selector'"));
+    assert!(invalid(|o| o.text_matches = Some("(".to_string())).starts_with("Invalid option 'text_matches'"));
+    assert!(invalid(|o| o.comment_anchors = Some("[".to_string())).starts_with("Invalid option 'comment_anchors'"));
+    assert!(invalid(|o| o.taxonomy_levels = vec!["main".to_string()]).starts_with("Invalid option 'taxonomy_levels'"));
+    assert_eq!(invalid(|o| o.id_helper_import = Some("@/aid".to_string())), "Invalid option 'id_helper_import': requires id_helper");
+    assert_eq!(invalid(|o| o.include = vec![String::new()]), "Invalid option 'include': must not contain empty entries");
+    assert_eq!(
+        invalid(|o| (o.include, o.exclude) = (vec!["div".to_string()], vec!["div".to_string()])),
+        "Invalid option 'exclude': 'div' is also in include"
+    );
+    assert_eq!(invalid(|o| o.range = Some(std::ops::Range { start: 5, end: 2 })), "Invalid option 'range': start 5 is after end 2");
+    assert_eq!(invalid(|o| o.origin_attr = Some(o.attr.clone())), "Invalid option 'origin_attr': must differ from attr");
+    assert_eq!(invalid(|o| o.manifest_meta = Some(" ".to_string())), "Invalid option 'manifest_meta': must not be empty");
+    assert!(invalid(|o| o.extra_attrs = vec!["data-x={size}".to_string()]).starts_with("Invalid option 'extra_attrs'"));
+    assert_eq!(
+        invalid(|o| o.extra_attrs = vec![format!("{}={{path}}", o.attr)]),
+        "Invalid option 'extra_attrs': 'data-ast-id' is written already"
+    );
+    assert_eq!(invalid(|o| o.salt = "pepper".to_string()), "Invalid option 'salt': requires scheme v2");
+    assert_eq!(invalid(|o| o.hash_length = Some(9)), "Invalid option 'hash_length': must be from 4 to 8 with this scheme");
+    assert!(invalid(|o| o.hash_length = Some(3)).starts_with("Invalid option 'hash_length'"));
+}
+
+#[test]
+fn test_processor_for_dispatch() {
+    let cases = [
+        (Format::Jsx, "const A = () => <div><span>Hi</span></div>;"),
+        (Format::Xml, "<root><item/></root>"),
+        (Format::Html, "<div><p>Hi</p></div>"),
+    ];
+    for (format, input) in cases {
+        let mut processor = processor_for(format).unwrap();
+        let output = processor.process_with_meta(input, &IdOptions::default()).unwrap();
+        assert_eq!((output.ids_added, output.elements_visited), (2, 2), "{:?}", format);
+        assert!(output.warnings.is_empty());
+
+        // Nothing left to tag the second time
+        let again = processor.process_with_meta(&output.content, &IdOptions::default()).unwrap();
+        assert_eq!(again.content, output.content);
+        assert_eq!((again.ids_added, again.elements_visited), (0, 2), "{:?}", format);
+    }
+    assert_eq!(processor_for(Format::Vue).err().unwrap(), "No processor for vue files");
+}
+
+#[test]
+fn test_processor_facade() {
+    let options = IdOptions { strategy: IdStrategy::Path, ..IdOptions::default() };
+    let mut processor = Processor::new();
+    let jsx = processor.process(Format::Jsx, "const A = () => <p>Save</p>;", &options).unwrap();
+    assert!(jsx.contains(r#"<p data-ast-id="el-p-0">"#), "{}", jsx);
+
+    // One generator for every format, so IDs do not repeat across them
+    let (format, html) = processor.process_auto(Some(std::path::Path::new("a.html")), "<p>Save</p>", &options).unwrap();
+    assert_eq!(format, Format::Html);
+    assert_eq!(html.content, r#"<p data-ast-id="el-p-0-2">Save</p>"#);
+    assert_eq!(html.ids_added, 1);
+    assert!(processor.generator_mut().is_used("el-p-0-2"));
+
+    // Nothing to tag in scripts without JSX
+    let script = "export const one = 1;";
+    let (format, output) = processor.process_auto(None, script, &options).unwrap();
+    assert_eq!((format, output.content.as_str(), output.ids_added), (Format::Script, script, 0));
+}
+
+#[test]
+fn test_origin_attr() {
+    let options = IdOptions {
+        overwrite: true,
+        origin_attr: Some("data-ast-id-origin".to_string()),
+        ..IdOptions::default()
+    };
+    let origin = format!("data-ast-id-origin=\"ast-append-ids@{}:hash\"", env!("CARGO_PKG_VERSION"));
+    let cases = [
+        (Format::Jsx, r#"const A = () => <div><span data-ast-id="save">Hi</span></div>;"#),
+        (Format::Xml, r#"<root><item data-ast-id="save"/></root>"#),
+        (Format::Html, r#"<div><p data-ast-id="save">Hi</p></div>"#),
+    ];
+    for (format, input) in cases {
+        let mut processor = processor_for(format).unwrap();
+        let output = processor.process_with_meta(input, &options).unwrap();
+        // The hand-written ID survives `overwrite` and gets no origin
+        assert_eq!(output.ids_added, 1, "{:?}", format);
+        assert_eq!(output.content.matches(&origin).count(), 1, "{:?}: {}", format, output.content);
+        assert!(output.content.contains(r#"data-ast-id="save""#), "{:?}", format);
+
+        // Overwriting again replaces the generated ID and keeps one origin
+        let again = processor.process(&output.content, &options).unwrap();
+        assert_eq!(again.matches(&origin).count(), 1, "{:?}: {}", format, again);
+
+        // Undo removes the generated ID only, even when told about both
+        let ids: HashSet<String> = again
+            .split("data-ast-id=\"")
+            .skip(1)
+            .map(|rest| rest[..rest.find('"').unwrap()].to_string())
+            .collect();
+        assert_eq!(ids.len(), 2, "{:?}", format);
+        let remove = match format {
+            Format::Jsx => ast_append_ids::jsx::remove_ids,
+            Format::Xml => ast_append_ids::xml::remove_ids,
+            _ => ast_append_ids::html::remove_ids,
+        };
+        let removed = remove(&again, "data-ast-id", &ids, options.origin_attr.as_deref()).unwrap();
+        assert!(removed.contains(r#"data-ast-id="save""#), "{:?}: {}", format, removed);
+        assert!(!removed.contains("el-") && !removed.contains("data-ast-id-origin"), "{:?}: {}", format, removed);
+    }
+}
+
+#[test]
+fn test_extra_attrs() {
+    let options = IdOptions {
+        overwrite: true,
+        extra_attrs: vec!["data-ast-depth={depth}".to_string(), "data-qa={tag}:{attr:role}".to_string()],
+        ..IdOptions::default()
+    };
+    let cases = [
+        (Format::Jsx, r#"const A = () => <div><span role="note">Hi</span></div>;"#),
+        (Format::Xml, r#"<div><span role="note">Hi</span></div>"#),
+        (Format::Html, r#"<div><span role="note">Hi</span></div>"#),
+    ];
+    for (format, input) in cases {
+        let mut processor = processor_for(format).unwrap();
+        let output = processor.process(input, &options).unwrap();
+        assert!(output.contains(r#"data-ast-depth="0" data-qa="div:""#), "{:?}: {}", format, output);
+        assert!(output.contains(r#"data-ast-depth="1" data-qa="span:note""#), "{:?}: {}", format, output);
+
+        // Overwriting writes them afresh rather than twice
+        let again = processor.process(&output, &options).unwrap();
+        assert_eq!(again.matches("data-qa=").count(), 2, "{:?}: {}", format, again);
+    }
+
+    // Templates using a slug ID get it once the element's text is in
+    let options = IdOptions {
+        strategy: IdStrategy::Slug,
+        extra_attrs: vec!["data-testid=test-{id}".to_string()],
+        ..IdOptions::default()
+    };
+    let output = processor_for(Format::Html).unwrap().process("<button>Save draft</button>", &options).unwrap();
+    assert!(output.contains(r#"data-testid="test-el-save-draft""#), "{}", output);
+}
+
+#[test]
+fn test_filter_decisions() {
+    use ast_append_ids::id_generator::SkipReason;
+
+    let options = IdOptions {
+        selector: Some("main > *".to_string()),
+        exclude: vec!["a".to_string()],
+        include_class: vec!["cta".to_string()],
+        ..IdOptions::default()
+    };
+    let cases = [
+        (
+            Format::Jsx,
+            r#"const A = () => <main><button className="cta">Buy</button><button>Back</button><a className="cta">Help</a><p className="cta">Hi</p></main>;"#,
+        ),
+        (
+            Format::Xml,
+            r#"<main><button class="cta">Buy</button><button>Back</button><a class="cta">Help</a><p class="cta">Hi</p></main>"#,
+        ),
+        (
+            Format::Html,
+            r#"<main><button class="cta">Buy</button><button>Back</button><a class="cta">Help</a><p class="cta">Hi</p></main>"#,
+        ),
+    ];
+    for (format, input) in cases {
+        let mut processor = processor_for(format).unwrap();
+        processor.generator_mut().enable_filter_log();
+        processor.process(input, &options).unwrap();
+        let decisions: Vec<_> = processor
+            .generator_mut()
+            .take_decisions()
+            .into_iter()
+            .map(|decision| (decision.tag, decision.skipped))
+            .collect();
+        // The selector narrows first, then the tag and class lists
+        assert_eq!(
+            decisions,
+            [
+                ("main".to_string(), Some(SkipReason::NotSelected)),
+                ("button".to_string(), None),
+                ("button".to_string(), Some(SkipReason::ClassMismatch)),
+                ("a".to_string(), Some(SkipReason::Excluded)),
+                ("p".to_string(), None),
+            ],
+            "{:?}",
+            format
+        );
+    }
+}
+
+#[test]
+fn test_warnings() {
+    use ast_append_ids::warnings::WarningKind;
+
+    let cases = [
+        (Format::Jsx, r#"const A = () => <main><p data-ast-id="a" /><p data-ast-id="a" /><p data-ast-id="" /><p data-ast-id={id} /></main>;"#),
+        (Format::Xml, r#"<main><p data-ast-id="a"/><p data-ast-id="a"/><p data-ast-id=""/></main>"#),
+        (Format::Html, r#"<main><p data-ast-id="a"></p><p data-ast-id="a"></p><p data-ast-id=""></p></main>"#),
+    ];
+    for (format, input) in cases {
+        let mut processor = processor_for(format).unwrap();
+        let output = processor.process_with_meta(input, &IdOptions::default()).unwrap();
+        let kinds: Vec<_> = output.warnings.iter().map(|warning| warning.kind).collect();
+        assert_eq!(kinds, [WarningKind::DuplicateId, WarningKind::EmptyId], "{:?}", format);
+        let duplicate = input.match_indices("<p").nth(1).unwrap().0;
+        assert_eq!(output.warnings[0].offset, Some(duplicate), "{:?}", format);
+
+        let options = IdOptions { selector: Some("form".to_string()), ..IdOptions::default() };
+        let output = processor.process_with_meta(input, &options).unwrap();
+        assert_eq!(output.warnings.len(), 1, "{:?}", format);
+        assert_eq!(output.warnings[0].to_string(), "Selector 'form' matched no elements");
+    }
+
+    // HTML ignores case, so these name the same tag
+    let options = IdOptions {
+        include: vec!["P".to_string()],
+        exclude: vec!["p".to_string()],
+        ..IdOptions::default()
+    };
+    let output = processor_for(Format::Html).unwrap().process_with_meta("<p>a</p><p>b</p>", &options).unwrap();
+    let kinds: Vec<_> = output.warnings.iter().map(|warning| warning.kind).collect();
+    assert_eq!(kinds, [WarningKind::IncludedAndExcluded]);
+}
+
+#[test]
+fn test_match_counts() {
+    // What `--require-matches` adds up over the files of a run
+    let cases = [
+        (Format::Jsx, "const A = () => <form><p /><label /><p /></form>;"),
+        (Format::Xml, "<form><p/><label/><p/></form>"),
+        (Format::Html, "<form><p></p><label></label><p></p></form>"),
+    ];
+    for (format, input) in cases {
+        let mut processor = processor_for(format).unwrap();
+        let options = IdOptions {
+            selector: Some("form *".to_string()),
+            include: vec!["p".to_string(), "span".to_string()],
+            ..IdOptions::default()
+        };
+        processor.process(input, &options).unwrap();
+        assert_eq!(processor.generator_mut().selected_count(), 3, "{:?}", format);
+        assert_eq!(processor.generator_mut().included_count(), 2, "{:?}", format);
+
+        let options = IdOptions { include: vec!["span".to_string()], ..IdOptions::default() };
+        processor.process(input, &options).unwrap();
+        assert_eq!(processor.generator_mut().included_count(), 0, "{:?}", format);
+    }
+}
+
+#[test]
+fn test_name_case_policy() {
+    use ast_append_ids::NameCase;
+
+    let cases = [
+        (Format::Jsx, "const A = () => <g><clipPath /><clippath /></g>;"),
+        (Format::Xml, "<g><clipPath/><clippath/></g>"),
+        (Format::Html, "<div><clipPath></clipPath><CLIPPATH></CLIPPATH></div>"),
+    ];
+    let tagged = |format, input: &str, options: &IdOptions| {
+        let output = processor_for(format).unwrap().process_with_meta(input, options).unwrap();
+        output.ids_added
+    };
+    for (format, input) in cases {
+        let options = IdOptions { include: vec!["clipPath".to_string()], ..IdOptions::default() };
+        // HTML tag names ignore case, JSX and XML names do not
+        let html = format == Format::Html;
+        assert_eq!(tagged(format, input, &options), if html { 2 } else { 1 }, "{:?}", format);
+
+        let insensitive = IdOptions { name_case: NameCase::Insensitive, ..options.clone() };
+        assert_eq!(tagged(format, input, &insensitive), 2, "{:?}", format);
+        // Outside SVG, HTML names are lowercase
+        let sensitive = IdOptions { name_case: NameCase::Sensitive, ..options };
+        assert_eq!(tagged(format, input, &sensitive), if html { 0 } else { 1 }, "{:?}", format);
+
+        let excluded = IdOptions {
+            exclude: vec!["CLIPPATH".to_string(), "G".to_string(), "DIV".to_string()],
+            name_case: NameCase::Insensitive,
+            ..IdOptions::default()
+        };
+        assert_eq!(tagged(format, input, &excluded), 0, "{:?}", format);
+    }
+
+    // Selectors follow the same policy
+    let selected = IdOptions { selector: Some("Card > card".to_string()), ..IdOptions::default() };
+    assert_eq!(tagged(Format::Xml, "<Card><card/></Card>", &selected), 1);
+    assert_eq!(tagged(Format::Xml, "<card><card/></card>", &selected), 0);
+    let selected = IdOptions { name_case: NameCase::Insensitive, ..selected };
+    assert_eq!(tagged(Format::Xml, "<card><card/></card>", &selected), 1);
+}
+
+#[test]
+fn test_counter_agrees_across_formats() {
+    use ast_append_ids::CounterMode;
+
+    let cases = [
+        (Format::Jsx, "const A = () => <main><Nav><a /></Nav><>{flag && <b />}</><p data-ast-ignore><i /></p><button /></main>;"),
+        (Format::Xml, "<main><a/><b/><p data-ast-ignore=\"\"><i/></p><button/></main>"),
+        (Format::Html, "<main><a></a><b></b><p data-ast-ignore><i></i></p><button></button></main>"),
+    ];
+    let button_id = |format, input: &str, options: &IdOptions| {
+        let output = processor_for(format).unwrap().process(input, options).unwrap();
+        let start = output.find("<button").unwrap();
+        let id = output[start..].split('"').nth(1).unwrap_or_default();
+        id.to_string()
+    };
+    let options = IdOptions { strategy: IdStrategy::Path, include: vec!["button".to_string()], ..IdOptions::default() };
+    for (format, input) in cases {
+        // Components, fragments and expressions are not elements; ignored ones are
+        assert_eq!(button_id(format, input, &options), "el-button-0-5", "{:?}", format);
+
+        // Only the button gets an ID, so nothing before it counts
+        let processed = IdOptions { counter: CounterMode::Processed, ..options.clone() };
+        assert_eq!(button_id(format, input, &processed), "el-button-0-0", "{:?}", format);
+    }
+
+    let hash = IdOptions { strategy: IdStrategy::Hash, ..options };
+    let ids: HashSet<String> = cases.iter().map(|(format, input)| button_id(*format, input, &hash)).collect();
+    assert_eq!(ids.len(), 1);
+}
+
+#[test]
+fn test_custom_strategy() {
+    use ast_append_ids::ast_common::AstNode;
+    use ast_append_ids::strategy::{IdStrategyImpl, StrategyContext};
+
+    /// Numbers elements in document order
+    struct Sequence(usize);
+
+    impl IdStrategyImpl for Sequence {
+        fn id_for(&mut self, _node: &AstNode, ctx: &mut StrategyContext) -> String {
+            self.0 += 1;
+            ctx.generator.ensure_unique(format!("{}{}", ctx.options.prefix, self.0))
+        }
+    }
+
+    let options = IdOptions::default();
+    let generator = || IdGenerator::new().with_strategy(Sequence(0));
+    let jsx = JsxProcessor::with_generator(generator())
+        .process("const A = () => <ul><li>a</li></ul>;", &options)
+        .unwrap();
+    assert!(jsx.contains(r#"<ul data-ast-id="el-1"><li data-ast-id="el-2">"#), "{}", jsx);
+    let xml = XmlProcessor::with_generator(generator()).process("<ul><li/></ul>", &options).unwrap();
+    assert_eq!(xml, r#"<ul data-ast-id="el-1"><li data-ast-id="el-2"/></ul>"#);
+    // Inline directives change the options the strategy sees, not the strategy
+    let html = HtmlProcessor::with_generator(generator())
+        .process("<ul><!-- ast-append-ids: prefix=x- --><li>a</li></ul>", &options)
+        .unwrap();
+    assert_eq!(html, r#"<ul data-ast-id="el-1"><!-- ast-append-ids: prefix=x- --><li data-ast-id="x-2">a</li></ul>"#);
+}
+
+#[test]
+fn test_attribute_values_escaped() {
+    // IDs can carry markup characters, through the prefix or a custom strategy
+    let options = IdOptions { prefix: r#"a"&<-"#.to_string(), strategy: IdStrategy::Path, ..IdOptions::default() };
+    let documents = [
+        (Format::Jsx, r#"const a = <main><p data-ast-id="x&amp;y" title="&quot;">Tom &amp; Jerry</p></main>;"#),
+        (Format::Html, r#"<main><p data-ast-id="x&amp;y" title="&quot;">Tom &amp; Jerry</p></main>"#),
+        (Format::Xml, r#"<main><p data-ast-id="x&amp;y" title="&quot;">Tom &amp; Jerry</p></main>"#),
+    ];
+
+    for (format, input) in documents {
+        let output = processor_for(format).unwrap().process(input, &options).unwrap();
+        assert!(output.contains(r#"data-ast-id="a&quot;&amp;&lt;-main-0""#), "{:?}: {}", format, output);
+        let ids = match format {
+            Format::Jsx => ast_append_ids::jsx::existing_ids(&output, "data-ast-id"),
+            Format::Html => ast_append_ids::html::existing_ids(&output, "data-ast-id"),
+            _ => ast_append_ids::xml::existing_ids(&output, "data-ast-id"),
+        };
+        assert_eq!(ids.unwrap(), [r#"a"&<-main-0"#, "x&y"], "{:?}", format);
+        // The IDs read back as written, so a second run keeps them
+        assert_eq!(processor_for(format).unwrap().process(&output, &options).unwrap(), output, "{:?}", format);
+    }
+}
+
+#[test]
+fn test_short_hashes() {
+    let input = format!("<ul>{}</ul>", "<li>x</li>".repeat(2000));
+    let ids = |options: &IdOptions| {
+        let output = XmlProcessor::new().process(&input, options).unwrap();
+        ast_append_ids::xml::existing_ids(&output, "data-ast-id").unwrap()
+    };
+    let full = ids(&IdOptions::default());
+    let short = ids(&IdOptions { hash_length: Some(4), ..IdOptions::default() });
+
+    assert_eq!(short.iter().collect::<HashSet<_>>().len(), full.len());
+    // Every short ID begins its full one, and only the elements whose short
+    // hash was taken by then got more than 4 characters
+    assert!(short.iter().zip(&full).all(|(short, full)| full.starts_with(short.as_str())));
+    let lengthened = short.iter().filter(|id| id.len() > "el-".len() + 4).count();
+    assert!(lengthened > 0 && lengthened < 100, "{}", lengthened);
+}
+
+#[test]
+fn test_deep_nesting() {
+    // Only the innermost element gets an ID, which keeps the run short: every
+    // path and hash ID of such a document is as long as it is deep
+    const DEPTH: usize = 50_000;
+    let markup = format!("{}<b>x</b>{}", "<div>".repeat(DEPTH), "</div>".repeat(DEPTH));
+    let options = IdOptions { include: vec!["b".to_string()], strategy: IdStrategy::Path, ..IdOptions::default() };
+
+    let path = (0..=DEPTH).map(|i| i.to_string()).collect::<Vec<_>>().join("-");
+    for format in [Format::Jsx, Format::Html, Format::Xml] {
+        let input = match format {
+            Format::Jsx => format!("const a = {};", markup),
+            _ => markup.clone(),
+        };
+        let output = processor_for(format).unwrap().process(&input, &options).unwrap();
+        assert_eq!(output.matches("data-ast-id=").count(), 1, "{:?}", format);
+        assert!(output.contains(&format!(r#"<b data-ast-id="el-b-{}">"#, path)), "{:?}", format);
+    }
+}
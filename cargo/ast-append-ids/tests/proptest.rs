@@ -0,0 +1,157 @@
+//! Property-based round-trip tests over randomly generated HTML and XML trees.
+
+#![allow(clippy::field_reassign_with_default)]
+
+use ast_append_ids::html::HtmlProcessor;
+use ast_append_ids::jsx::JsxProcessor;
+use ast_append_ids::xml::XmlProcessor;
+use ast_append_ids::{AstProcessor, IdOptions, IdStrategy};
+use proptest::prelude::*;
+use quick_xml::events::Event;
+use quick_xml::reader::Reader;
+use regex::Regex;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Element {
+        name: &'static str,
+        attrs: Vec<(&'static str, String)>,
+        children: Vec<Node>,
+    },
+    Text(String),
+}
+
+impl Node {
+    fn render(&self, out: &mut String) {
+        match self {
+            Node::Element { name, attrs, children } => {
+                out.push('<');
+                out.push_str(name);
+                for (key, value) in attrs {
+                    out.push_str(&format!(" {}=\"{}\"", key, value));
+                }
+                out.push('>');
+                for child in children {
+                    child.render(out);
+                }
+                out.push_str(&format!("</{}>", name));
+            }
+            Node::Text(text) => out.push_str(text),
+        }
+    }
+
+    fn element_count(&self) -> usize {
+        match self {
+            Node::Element { children, .. } => 1 + children.iter().map(Node::element_count).sum::<usize>(),
+            Node::Text(_) => 0,
+        }
+    }
+}
+
+fn text() -> impl Strategy<Value = String> {
+    prop_oneof![
+        4 => "[a-zA-Z0-9 ,.!?\n\t]{1,16}",
+        1 => Just("Tom &amp; Jerry".to_string()),
+        1 => Just("  ".to_string()),
+    ]
+}
+
+fn attrs() -> impl Strategy<Value = Vec<(&'static str, String)>> {
+    proptest::sample::subsequence(vec!["class", "title", "lang"], 0..=3).prop_flat_map(|keys| {
+        let len = keys.len();
+        (Just(keys), proptest::collection::vec("[a-z0-9 -]{0,8}", len))
+            .prop_map(|(keys, values)| keys.into_iter().zip(values).collect())
+    })
+}
+
+fn tree() -> impl Strategy<Value = Node> {
+    let names = prop::sample::select(vec!["div", "span", "section", "em", "ul", "li", "article"]);
+    let leaf = prop_oneof![
+        text().prop_map(Node::Text),
+        (names.clone(), attrs()).prop_map(|(name, attrs)| Node::Element { name, attrs, children: Vec::new() }),
+    ];
+    let inner = leaf.prop_recursive(4, 48, 5, move |inner| {
+        (names.clone(), attrs(), proptest::collection::vec(inner, 0..5))
+            .prop_map(|(name, attrs, children)| Node::Element { name, attrs, children })
+    });
+    // Always start from an element so XML documents have a root
+    (attrs(), proptest::collection::vec(inner, 0..5))
+        .prop_map(|(attrs, children)| Node::Element { name: "main", attrs, children })
+}
+
+fn strategy() -> impl Strategy<Value = IdStrategy> {
+    prop_oneof![Just(IdStrategy::Hash), Just(IdStrategy::Slug), Just(IdStrategy::Path)]
+}
+
+/// Checks the invariants shared by all processors and returns the output
+/// with the generated attributes stripped again.
+fn check_output(output: &str, tree: &Node, options: &IdOptions) -> Result<String, TestCaseError> {
+    let attr_re = Regex::new(&format!(r#" {}="([^"]*)""#, regex::escape(&options.attr))).unwrap();
+    let ids: Vec<&str> = attr_re.captures_iter(output).map(|c| c.get(1).unwrap().as_str()).collect();
+
+    prop_assert_eq!(ids.len(), tree.element_count(), "every element gets exactly one ID");
+    let unique: HashSet<&str> = ids.iter().copied().collect();
+    prop_assert_eq!(unique.len(), ids.len(), "IDs are unique");
+
+    Ok(attr_re.replace_all(output, "").to_string())
+}
+
+proptest! {
+    #[test]
+    fn html_only_adds_the_target_attribute(tree in tree(), strategy in strategy()) {
+        let mut input = String::new();
+        tree.render(&mut input);
+        let mut options = IdOptions::default();
+        options.strategy = strategy;
+
+        let output = HtmlProcessor::new().process(&input, &options).unwrap();
+        let stripped = check_output(&output, &tree, &options)?;
+        prop_assert_eq!(stripped, input);
+    }
+
+    #[test]
+    fn xml_stays_well_formed(tree in tree(), strategy in strategy()) {
+        let mut input = String::new();
+        tree.render(&mut input);
+        let mut options = IdOptions::default();
+        options.strategy = strategy;
+
+        let output = XmlProcessor::new().process(&input, &options).unwrap();
+        let mut reader = Reader::from_str(&output);
+        loop {
+            match reader.read_event() {
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => return Err(TestCaseError::fail(format!("malformed output: {}", e))),
+            }
+        }
+
+        let stripped = check_output(&output, &tree, &options)?;
+        prop_assert_eq!(stripped, input);
+    }
+
+    #[test]
+    fn processing_is_idempotent(tree in tree(), strategy in strategy(), overwrite in any::<bool>()) {
+        let mut input = String::new();
+        tree.render(&mut input);
+        let mut options = IdOptions::default();
+        options.strategy = strategy;
+        options.overwrite = overwrite;
+
+        let html = HtmlProcessor::new().process(&input, &options).unwrap();
+        prop_assert_eq!(HtmlProcessor::new().process(&html, &options).unwrap(), html);
+        let xml = XmlProcessor::new().process(&input, &options).unwrap();
+        prop_assert_eq!(XmlProcessor::new().process(&xml, &options).unwrap(), xml);
+    }
+
+    #[test]
+    fn malformed_input_never_panics(input in r#"[<>/=\"'{}()`$&#;a-z0-9 !?.-]{0,64}"#, strategy in strategy()) {
+        let mut options = IdOptions::default();
+        options.strategy = strategy;
+
+        let _ = JsxProcessor::new().process(&input, &options);
+        let _ = HtmlProcessor::new().process(&input, &options);
+        let _ = XmlProcessor::new().process(&input, &options);
+    }
+}
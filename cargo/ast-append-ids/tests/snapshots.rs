@@ -0,0 +1,95 @@
+//! Golden-file tests: every input under `tests/fixtures/<kind>/` is processed
+//! with each strategy and compared to `tests/fixtures/<kind>/snapshots/`.
+//!
+//! Regenerate the snapshots after an intended output change with
+//! `cargo xtask snapshots` (or `UPDATE_SNAPSHOTS=1 cargo test --test snapshots`).
+
+#![allow(clippy::field_reassign_with_default)]
+
+use ast_append_ids::html::HtmlProcessor;
+use ast_append_ids::jsx::JsxProcessor;
+use ast_append_ids::xml::XmlProcessor;
+use ast_append_ids::{AstProcessor, IdOptions, IdStrategy};
+use pretty_assertions::assert_eq;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const STRATEGIES: [(IdStrategy, &str); 4] = [
+    (IdStrategy::Hash, "hash"),
+    (IdStrategy::Slug, "slug"),
+    (IdStrategy::Path, "path"),
+    (IdStrategy::Taxonomy, "taxonomy"),
+];
+
+fn fixtures_dir(kind: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(kind)
+}
+
+fn process(kind: &str, input: &str, options: &IdOptions) -> Result<String, String> {
+    match kind {
+        "jsx" => JsxProcessor::new().process(input, options),
+        "html" => HtmlProcessor::new().process(input, options),
+        "xml" => XmlProcessor::new().process(input, options),
+        _ => unreachable!(),
+    }
+}
+
+/// Checks (or with `UPDATE_SNAPSHOTS` set, rewrites) the snapshots of one
+/// fixture kind. Returns the number of snapshots compared.
+fn check_snapshots(kind: &str) -> usize {
+    let dir = fixtures_dir(kind);
+    let snapshot_dir = dir.join("snapshots");
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    if update {
+        fs::create_dir_all(&snapshot_dir).unwrap();
+    }
+
+    let mut inputs: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_file())
+        .collect();
+    inputs.sort();
+
+    let mut checked = 0;
+    for input_path in inputs {
+        let input = fs::read_to_string(&input_path).unwrap();
+        let stem = input_path.file_stem().unwrap().to_string_lossy();
+        let ext = input_path.extension().unwrap().to_string_lossy();
+
+        for (strategy, name) in STRATEGIES {
+            let mut options = IdOptions::default();
+            options.strategy = strategy;
+
+            let output = process(kind, &input, &options)
+                .unwrap_or_else(|e| panic!("{} failed: {}", input_path.display(), e));
+            let snapshot_path = snapshot_dir.join(format!("{}.{}.{}", stem, name, ext));
+
+            if update {
+                fs::write(&snapshot_path, &output).unwrap();
+            } else {
+                let expected = fs::read_to_string(&snapshot_path).unwrap_or_else(|_| {
+                    panic!("Missing snapshot {}; run `cargo xtask snapshots`", snapshot_path.display())
+                });
+                assert_eq!(expected, output, "snapshot mismatch: {}", snapshot_path.display());
+            }
+            checked += 1;
+        }
+    }
+    checked
+}
+
+#[test]
+fn test_jsx_snapshots() {
+    assert!(check_snapshots("jsx") > 0);
+}
+
+#[test]
+fn test_html_snapshots() {
+    assert!(check_snapshots("html") > 0);
+}
+
+#[test]
+fn test_xml_snapshots() {
+    assert!(check_snapshots("xml") > 0);
+}
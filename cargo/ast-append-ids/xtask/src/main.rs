@@ -0,0 +1,458 @@
+//! Development tasks, run with `cargo xtask <task>`.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command};
+
+const USAGE: &str = "Usage: cargo xtask <task>
+
+Tasks:
+  snapshots        Regenerate the golden files under tests/fixtures/*/snapshots
+  bench-compare    Diff the last `cargo bench` run against benches/baseline.json
+                   [--save] record the last run as the new baseline
+                   [--threshold <percent>] allowed slowdown (default 10)
+  wasm             Build the wasm packages with the size-optimized `wasm` profile:
+                   pkg-<target> with every format plus pkg-<target>-<format>
+                   (pkg-node for nodejs)
+                   [--target web|nodejs|bundler] (default web)
+                   [--formats all,jsx,html,xml] bundles to build (default all four)
+                   [--features <list>] extra cargo features, e.g. talc
+                   [--no-opt] skip wasm-opt";
+
+/// Formats that get their own wasm package, as cargo features
+const WASM_FORMATS: [&str; 3] = ["jsx", "html", "xml"];
+/// Same flags as `package.metadata.wasm-pack` in the main Cargo.toml
+const WASM_OPT_FLAGS: [&str; 2] = ["-Oz", "--enable-simd"];
+
+/// Mean time per iteration in nanoseconds, keyed by `<group>/<benchmark>`
+type BenchTimes = BTreeMap<String, f64>;
+
+fn main() {
+    let task = env::args().nth(1);
+    let status = match task.as_deref() {
+        Some("snapshots") => regenerate_snapshots(),
+        Some("bench-compare") => bench_compare(env::args().skip(2).collect()),
+        Some("wasm") => build_wasm(env::args().skip(2).collect()),
+        _ => {
+            eprintln!("{}", USAGE);
+            exit(2);
+        }
+    };
+    exit(status);
+}
+
+fn regenerate_snapshots() -> i32 {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = Command::new(cargo)
+        .args(["test", "--package", "ast-append-ids", "--test", "snapshots"])
+        .env("UPDATE_SNAPSHOTS", "1")
+        .status()
+        .expect("failed to run cargo");
+    status.code().unwrap_or(1)
+}
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap().to_path_buf()
+}
+
+fn bench_compare(args: Vec<String>) -> i32 {
+    let mut save = false;
+    let mut threshold = 10.0;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--save" => save = true,
+            "--threshold" => match args.next().and_then(|value| value.parse().ok()) {
+                Some(value) => threshold = value,
+                None => {
+                    eprintln!("--threshold expects a percentage");
+                    return 2;
+                }
+            },
+            _ => {
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+        }
+    }
+
+    let root = workspace_root();
+    let target = env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| root.join("target"));
+    let current = match read_criterion_results(&target.join("criterion")) {
+        Ok(times) if !times.is_empty() => times,
+        Ok(_) => {
+            eprintln!("No benchmark results found, run `cargo bench` first");
+            return 1;
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    let baseline_path = root.join("benches").join("baseline.json");
+    if save {
+        let rounded: BenchTimes = current.iter().map(|(name, ns)| (name.clone(), ns.round())).collect();
+        let json = serde_json::to_string_pretty(&rounded).unwrap() + "\n";
+        if let Err(e) = fs::write(&baseline_path, json) {
+            eprintln!("Failed to write {}: {}", baseline_path.display(), e);
+            return 1;
+        }
+        println!("Saved {} benchmarks to {}", current.len(), baseline_path.display());
+        return 0;
+    }
+
+    let baseline: BenchTimes = match fs::read_to_string(&baseline_path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(baseline) => baseline,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", baseline_path.display(), e);
+            return 1;
+        }
+    };
+
+    let comparison = compare(&baseline, &current, threshold);
+    println!("{:<16} {:>12} {:>12} {:>9}", "benchmark", "baseline", "current", "change");
+    for (name, change) in &comparison {
+        match *change {
+            Change::New { now } => println!("{:<16} {:>12} {:>12} {:>9}", name, "-", format_ns(now), "new"),
+            Change::Missing => println!("{:<16} missing from the last run", name),
+            Change::Timed { before, now, percent, regression } => println!(
+                "{:<16} {:>12} {:>12} {:>+8.1}%{}",
+                name,
+                format_ns(before),
+                format_ns(now),
+                percent,
+                if regression { "  REGRESSION" } else { "" }
+            ),
+        }
+    }
+
+    let regressions = comparison
+        .iter()
+        .filter(|(_, change)| matches!(change, Change::Timed { regression: true, .. }))
+        .count();
+    if regressions > 0 {
+        eprintln!("{} benchmark(s) slower than the baseline by more than {}%", regressions, threshold);
+        return 1;
+    }
+    0
+}
+
+/// How a benchmark's time compares with the baseline
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Change {
+    /// Not in the baseline
+    New { now: f64 },
+    /// In the baseline but not in the last run
+    Missing,
+    /// Slower by more than the threshold, in percent, is a regression
+    Timed { before: f64, now: f64, percent: f64, regression: bool },
+}
+
+/// Every benchmark of the last run, then those only in the baseline
+fn compare(baseline: &BenchTimes, current: &BenchTimes, threshold: f64) -> Vec<(String, Change)> {
+    let mut changes: Vec<(String, Change)> = current
+        .iter()
+        .map(|(name, &now)| {
+            let change = match baseline.get(name) {
+                None => Change::New { now },
+                Some(&before) => {
+                    let percent = (now - before) / before * 100.0;
+                    Change::Timed { before, now, percent, regression: percent > threshold }
+                }
+            };
+            (name.clone(), change)
+        })
+        .collect();
+    changes.extend(
+        baseline
+            .keys()
+            .filter(|name| !current.contains_key(*name))
+            .map(|name| (name.clone(), Change::Missing)),
+    );
+    changes
+}
+
+/// Collects the mean estimates criterion wrote for its last run.
+fn read_criterion_results(dir: &Path) -> Result<BenchTimes, String> {
+    let mut times = BenchTimes::new();
+    let Ok(groups) = fs::read_dir(dir) else {
+        return Ok(times);
+    };
+
+    for group in groups.flatten().filter(|entry| entry.path().is_dir()) {
+        let benches = fs::read_dir(group.path()).map_err(|e| e.to_string())?;
+        for bench in benches.flatten() {
+            let estimates = bench.path().join("new").join("estimates.json");
+            if !estimates.exists() {
+                continue;
+            }
+            let json = fs::read_to_string(&estimates).map_err(|e| e.to_string())?;
+            let value: serde_json::Value = serde_json::from_str(&json)
+                .map_err(|e| format!("Invalid {}: {}", estimates.display(), e))?;
+            let mean = value["mean"]["point_estimate"]
+                .as_f64()
+                .ok_or_else(|| format!("No mean estimate in {}", estimates.display()))?;
+            let name = format!(
+                "{}/{}",
+                group.file_name().to_string_lossy(),
+                bench.file_name().to_string_lossy()
+            );
+            times.insert(name, mean);
+        }
+    }
+
+    Ok(times)
+}
+
+fn format_ns(ns: f64) -> String {
+    if ns >= 1_000_000.0 {
+        format!("{:.2} ms", ns / 1_000_000.0)
+    } else if ns >= 1_000.0 {
+        format!("{:.2} µs", ns / 1_000.0)
+    } else {
+        format!("{:.0} ns", ns)
+    }
+}
+
+/// What `cargo xtask wasm` was asked to build
+#[derive(Debug, PartialEq)]
+struct WasmArgs {
+    target: String,
+    formats: Vec<String>,
+    optimize: bool,
+    extra_features: Vec<String>,
+}
+
+fn parse_wasm_args(args: Vec<String>) -> Result<WasmArgs, String> {
+    let mut parsed = WasmArgs {
+        target: "web".to_string(),
+        formats: std::iter::once("all").chain(WASM_FORMATS).map(String::from).collect(),
+        optimize: true,
+        extra_features: Vec::new(),
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--target" => match args.next() {
+                Some(value) if ["web", "nodejs", "bundler"].contains(&value.as_str()) => parsed.target = value,
+                _ => return Err("--target expects web, nodejs or bundler".to_string()),
+            },
+            "--formats" => {
+                parsed.formats = args
+                    .next()
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|format| format.trim().to_string())
+                    .collect();
+                if let Some(bad) = parsed
+                    .formats
+                    .iter()
+                    .find(|format| *format != "all" && !WASM_FORMATS.contains(&format.as_str()))
+                {
+                    return Err(format!("Unknown format '{}', expected all, jsx, html or xml", bad));
+                }
+            }
+            "--features" => parsed.extra_features.extend(args.next()),
+            "--no-opt" => parsed.optimize = false,
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    Ok(parsed)
+}
+
+/// The package directory for `format` and the cargo arguments selecting its
+/// features: `all` keeps the default features, one format builds only that
+fn wasm_package(args: &WasmArgs, format: &str) -> (String, Vec<String>) {
+    // Matches the directory names index.js and index.mjs load from
+    let dir_prefix = match args.target.as_str() {
+        "nodejs" => "pkg-node".to_string(),
+        other => format!("pkg-{}", other),
+    };
+    let mut features = args.extra_features.clone();
+    let mut cargo_args = Vec::new();
+    let dir = if format == "all" {
+        dir_prefix
+    } else {
+        cargo_args.push("--no-default-features".to_string());
+        features.push(format.to_string());
+        format!("{}-{}", dir_prefix, format)
+    };
+    if !features.is_empty() {
+        cargo_args.push("--features".to_string());
+        cargo_args.push(features.join(","));
+    }
+    (dir, cargo_args)
+}
+
+fn build_wasm(args: Vec<String>) -> i32 {
+    let mut args = match parse_wasm_args(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    if args.optimize && Command::new("wasm-opt").arg("--version").output().is_err() {
+        eprintln!("wasm-opt not found (install binaryen), packages will not be optimized");
+        args.optimize = false;
+    }
+
+    let root = workspace_root();
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let target_dir = env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| root.join("target"));
+    let wasm = target_dir
+        .join("wasm32-unknown-unknown")
+        .join("wasm")
+        .join("ast_append_ids.wasm");
+    let mut sizes = Vec::new();
+
+    for format in &args.formats {
+        let (dir, cargo_args) = wasm_package(&args, format);
+        let out_dir = root.join(dir);
+        let mut build = Command::new(&cargo);
+        build
+            .current_dir(&root)
+            .args(["build", "--package", "ast-append-ids", "--lib"])
+            .args(["--target", "wasm32-unknown-unknown", "--profile", "wasm"])
+            .args(&cargo_args);
+
+        println!("Building {} ({})", out_dir.display(), format);
+        if !run(&mut build) {
+            return 1;
+        }
+
+        let bindgen = Command::new("wasm-bindgen")
+            .args(["--target", &args.target, "--out-name", "ast_append_ids", "--out-dir"])
+            .arg(&out_dir)
+            .arg(&wasm)
+            .status();
+        match bindgen {
+            Ok(status) if status.success() => {}
+            Ok(_) => return 1,
+            Err(e) => {
+                eprintln!("Failed to run wasm-bindgen ({}), install it with `cargo install wasm-bindgen-cli`", e);
+                return 1;
+            }
+        }
+
+        let output = out_dir.join("ast_append_ids_bg.wasm");
+        if args.optimize && !run(Command::new("wasm-opt").args(WASM_OPT_FLAGS).arg(&output).arg("-o").arg(&output)) {
+            return 1;
+        }
+
+        let size = fs::metadata(&output).map(|meta| meta.len()).unwrap_or(0);
+        sizes.push((out_dir, size));
+    }
+
+    println!();
+    for (dir, size) in sizes {
+        println!("{:>10.1} KiB  {}", size as f64 / 1024.0, dir.join("ast_append_ids_bg.wasm").display());
+    }
+    0
+}
+
+fn run(command: &mut Command) -> bool {
+    match command.status() {
+        Ok(status) => status.success(),
+        Err(e) => {
+            eprintln!("Failed to run {:?}: {}", command.get_program(), e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_flags_regressions() {
+        let times = |entries: &[(&str, f64)]| -> BenchTimes {
+            entries.iter().map(|(name, ns)| (name.to_string(), *ns)).collect()
+        };
+        let baseline = times(&[("html/large", 1000.0), ("jsx/large", 2000.0), ("xml/large", 500.0)]);
+        let current = times(&[("html/large", 1050.0), ("jsx/large", 2400.0), ("jsx/small", 80.0)]);
+
+        let changes = compare(&baseline, &current, 10.0);
+        let flags: Vec<_> = changes
+            .iter()
+            .map(|(name, change)| match change {
+                Change::Timed { regression, .. } => (name.as_str(), if *regression { "slower" } else { "ok" }),
+                Change::New { .. } => (name.as_str(), "new"),
+                Change::Missing => (name.as_str(), "missing"),
+            })
+            .collect();
+        assert_eq!(
+            flags,
+            [("html/large", "ok"), ("jsx/large", "slower"), ("jsx/small", "new"), ("xml/large", "missing")]
+        );
+        assert!(matches!(changes[1].1, Change::Timed { percent, .. } if (percent - 20.0).abs() < 1e-9));
+
+        // A looser threshold lets the same run pass
+        assert!(compare(&baseline, &current, 25.0)
+            .iter()
+            .all(|(_, change)| !matches!(change, Change::Timed { regression: true, .. })));
+    }
+
+    #[test]
+    fn test_wasm_packages() {
+        let args = |list: &[&str]| parse_wasm_args(list.iter().map(|arg| arg.to_string()).collect());
+        let packages = |parsed: &WasmArgs| -> Vec<(String, Vec<String>)> {
+            parsed.formats.iter().map(|format| wasm_package(parsed, format)).collect()
+        };
+        let owned = |list: &[&str]| -> Vec<String> { list.iter().map(|arg| arg.to_string()).collect() };
+
+        // By default the full package and one per format
+        let parsed = args(&[]).unwrap();
+        assert!(parsed.optimize);
+        assert_eq!(
+            packages(&parsed),
+            [
+                ("pkg-web".to_string(), owned(&[])),
+                ("pkg-web-jsx".to_string(), owned(&["--no-default-features", "--features", "jsx"])),
+                ("pkg-web-html".to_string(), owned(&["--no-default-features", "--features", "html"])),
+                ("pkg-web-xml".to_string(), owned(&["--no-default-features", "--features", "xml"])),
+            ]
+        );
+
+        let parsed = args(&["--target", "nodejs", "--formats", "all, html", "--features", "talc", "--no-opt"]).unwrap();
+        assert!(!parsed.optimize);
+        assert_eq!(
+            packages(&parsed),
+            [
+                ("pkg-node".to_string(), owned(&["--features", "talc"])),
+                ("pkg-node-html".to_string(), owned(&["--no-default-features", "--features", "talc,html"])),
+            ]
+        );
+
+        assert!(args(&["--formats", "jsx,vue"]).unwrap_err().contains("'vue'"));
+        assert!(args(&["--target", "deno"]).is_err());
+    }
+
+    #[test]
+    fn test_read_criterion_results() {
+        let dir = env::temp_dir().join(format!("xtask-criterion-{}", std::process::id()));
+        let estimates = dir.join("html").join("large").join("new");
+        fs::create_dir_all(&estimates).unwrap();
+        fs::write(estimates.join("estimates.json"), r#"{"mean":{"point_estimate":1234.5}}"#).unwrap();
+        // Benchmarks without a finished run are left out
+        fs::create_dir_all(dir.join("html").join("report")).unwrap();
+
+        let times = read_criterion_results(&dir).unwrap();
+        assert_eq!(times.into_iter().collect::<Vec<_>>(), [("html/large".to_string(), 1234.5)]);
+        assert!(read_criterion_results(&dir.join("missing")).unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}